@@ -25,7 +25,8 @@ use buckyos_api::{
     AiccServerHandler, BuckyOSRuntimeType, DriverMetadataRuntimeApply, DriverMetadataUpdateGetReq,
     DriverMetadataUpdateSetReq, DriverMetadataUpdateSetResponse, DriverMetadataUpdateStatus,
     DriverMetadataUpdateView, QueryRouteTraceRequest, QueryRouteTraceResponse, QueryUsageRequest,
-    QueryUsageResponse, SystemConfigClient, SystemConfigError, AICC_SERVICE_SERVICE_NAME,
+    QueryUsageResponse, SystemConfigClient, SystemConfigError, SystemConfigRpcError,
+    AICC_SERVICE_SERVICE_NAME,
 };
 use buckyos_http_server::Runner;
 use buckyos_http_server::{
@@ -455,6 +456,9 @@ fn system_config_error_to_rpc(err: SystemConfigError) -> RPCErrors {
         SystemConfigError::Timeout(reason) => {
             RPCErrors::ReasonError(format!("timeout: {}", reason))
         }
+        SystemConfigError::SchemaViolation(violation) => {
+            RPCErrors::ReasonError(SystemConfigRpcError::SchemaViolation { violation }.to_payload())
+        }
         SystemConfigError::ReasonError(reason) => {
             let lower = reason.to_ascii_lowercase();
             if lower.contains("revision")
@@ -11,6 +11,12 @@ use std::time::{Duration, Instant};
 use sysinfo::{Disks, Networks, System};
 use tokio::sync::RwLock;
 
+// Error/warning log counts shown next to the resource charts.
+const DASHBOARD_LOG_RANGE_SECS: u64 = 60 * 60;
+const DASHBOARD_LOG_BUCKET_SECS: u64 = 5 * 60;
+const METRICS_LOG_RANGE_SECS: u64 = 60 * 60;
+const METRICS_LOG_BUCKET_SECS: u64 = 60;
+
 impl ControlPanelServer {
     pub(crate) fn start_metrics_sampler(metrics_snapshot: Arc<RwLock<SystemMetricsSnapshot>>) {
         // Background sampler so metrics timelines are produced server-side.
@@ -304,6 +310,9 @@ impl ControlPanelServer {
             })
             .collect();
 
+        let log_timeline =
+            Self::slog_log_timeline(DASHBOARD_LOG_RANGE_SECS, DASHBOARD_LOG_BUCKET_SECS).await;
+
         let dashboard = json!({
             "recentEvents": [
                 { "title": "System backup completed", "subtitle": "2 mins ago", "tone": "success" },
@@ -321,6 +330,7 @@ impl ControlPanelServer {
                 { "name": "WebPortal", "icon": "🌐", "status": "running" }
             ],
             "resourceTimeline": timeline,
+            "logTimeline": log_timeline,
             "storageSlices": storage_slices,
             "storageCapacityGb": storage_capacity_gb,
             "storageUsedGb": storage_used_gb,
@@ -622,6 +632,9 @@ impl ControlPanelServer {
             })
             .collect();
 
+        let log_timeline =
+            Self::slog_log_timeline(METRICS_LOG_RANGE_SECS, METRICS_LOG_BUCKET_SECS).await;
+
         let metrics = json!({
             "cpu": {
                 "usagePercent": snapshot.cpu_usage_percent,
@@ -665,6 +678,7 @@ impl ControlPanelServer {
             "uptimeSeconds": uptime_seconds,
             "resourceTimeline": resource_timeline,
             "networkTimeline": network_timeline,
+            "logTimeline": log_timeline,
         });
 
         Ok(RPCResponse::new(RPCResult::Success(metrics), req.seq))
@@ -6,6 +6,7 @@ use bytes::Bytes;
 use chrono::{DateTime, Datelike, NaiveDateTime, TimeZone, Utc};
 use http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE};
 use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use regex::{Regex, RegexBuilder};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
@@ -19,6 +20,19 @@ pub(crate) const LOG_ROOT_DIR: &str = "/opt/buckyos/logs";
 pub(crate) const LOG_DOWNLOAD_TTL_SECS: u64 = 600;
 const DEFAULT_LOG_LIMIT: usize = 200;
 const MAX_LOG_LIMIT: usize = 1000;
+const MAX_LOG_REGEX_LEN: usize = 512;
+const LOG_REGEX_SIZE_LIMIT: usize = 1 << 20;
+const SLOG_SERVER_QUERY_URL_ENV_KEY: &str = "SLOG_SERVER_QUERY_URL";
+const DEFAULT_SLOG_SERVER_QUERY_URL: &str = "http://127.0.0.1:22001/query";
+const SLOG_SERVER_QUERY_TIMEOUT_SECS: u64 = 10;
+const SLOG_SERVER_TAIL_URL_ENV_KEY: &str = "SLOG_SERVER_TAIL_URL";
+const DEFAULT_SLOG_SERVER_TAIL_URL: &str = "http://127.0.0.1:22001/tail";
+const SLOG_SERVER_AGGREGATE_URL_ENV_KEY: &str = "SLOG_SERVER_AGGREGATE_URL";
+const DEFAULT_SLOG_SERVER_AGGREGATE_URL: &str = "http://127.0.0.1:22001/aggregate";
+const SLOG_SERVER_AGGREGATE_TIMEOUT_SECS: u64 = 3;
+const SLOG_STREAM_SEQ_HEADER: &str = "x-log-stream-seq";
+const DEFAULT_SLOG_TAIL_WAIT_MS: u64 = 2000;
+const MAX_SLOG_TAIL_WAIT_MS: u64 = 25000;
 
 #[derive(Clone, Serialize, Deserialize)]
 struct LogQueryCursor {
@@ -28,6 +42,16 @@ struct LogQueryCursor {
     direction: String,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+struct SlogQueryCursor {
+    offset: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SlogTailCursor {
+    seq: u64,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 struct LogTailCursor {
     file: String,
@@ -207,6 +231,277 @@ impl ControlPanelServer {
         Ok(results)
     }
 
+    fn parse_regex_filter(req: &RPCRequest) -> Result<Option<Regex>, RPCErrors> {
+        let Some(pattern) = Self::param_str(req, "regex").filter(|value| !value.is_empty()) else {
+            return Ok(None);
+        };
+        if pattern.len() > MAX_LOG_REGEX_LEN {
+            return Err(RPCErrors::ParseRequestError(format!(
+                "regex is longer than {} bytes",
+                MAX_LOG_REGEX_LEN
+            )));
+        }
+        RegexBuilder::new(&pattern)
+            .size_limit(LOG_REGEX_SIZE_LIMIT)
+            .dfa_size_limit(LOG_REGEX_SIZE_LIMIT)
+            .build()
+            .map(Some)
+            .map_err(|err| RPCErrors::ParseRequestError(format!("Invalid regex: {}", err)))
+    }
+
+    /// Convert a slog_server `{node, service, log, highlights}` record into a log entry.
+    fn slog_record_entry(record: &Value) -> Value {
+        let log = record.get("log").cloned().unwrap_or(Value::Null);
+        let timestamp = log
+            .get("time")
+            .and_then(|value| value.as_i64())
+            .and_then(|value| Utc.timestamp_millis_opt(value).single())
+            .map(|value| value.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+            .unwrap_or_default();
+        let level = log
+            .get("level")
+            .and_then(|value| value.as_str())
+            .map(Self::normalize_log_level)
+            .unwrap_or_default();
+        let content = log.get("content").cloned().unwrap_or(Value::Null);
+        json!({
+            "timestamp": timestamp,
+            "level": level,
+            "message": content,
+            "raw": content,
+            "node": record.get("node"),
+            "service": record.get("service"),
+            "file": log.get("file"),
+            "line": log.get("line"),
+            "traceId": log.get("trace_id"),
+            "spanId": log.get("span_id"),
+            "fields": log.get("fields").cloned().unwrap_or(json!({})),
+            "highlights": record.get("highlights").cloned().unwrap_or(json!([])),
+        })
+    }
+
+    fn slog_server_url(env_key: &str, default_url: &str) -> String {
+        std::env::var(env_key)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| default_url.to_string())
+    }
+
+    /// Error and warning counts per bucket over the last `range_secs`, from
+    /// slog_server `/aggregate`. Empty when slog_server cannot be reached so the
+    /// dashboard still renders without it.
+    pub(crate) async fn slog_log_timeline(range_secs: u64, bucket_secs: u64) -> Vec<Value> {
+        let bucket_millis = bucket_secs.max(1) * 1000;
+        let end_time = Utc::now().timestamp_millis().max(0) as u64;
+        let start_time = end_time.saturating_sub(range_secs * 1000);
+        let body = json!({
+            "start_time": start_time,
+            "end_time": end_time,
+            "bucket_secs": bucket_secs.max(1),
+            "group_by": "level",
+            "levels": "error,warn",
+        });
+        let url = Self::slog_server_url(
+            SLOG_SERVER_AGGREGATE_URL_ENV_KEY,
+            DEFAULT_SLOG_SERVER_AGGREGATE_URL,
+        );
+        let response = reqwest::Client::new()
+            .post(&url)
+            .timeout(std::time::Duration::from_secs(
+                SLOG_SERVER_AGGREGATE_TIMEOUT_SECS,
+            ))
+            .json(&body)
+            .send()
+            .await;
+        let payload = match response {
+            Ok(response) => response.json::<Value>().await,
+            Err(err) => Err(err),
+        };
+        let payload = match payload {
+            Ok(payload) if payload.get("ret").and_then(|value| value.as_i64()) == Some(0) => {
+                payload
+            }
+            Ok(payload) => {
+                log::warn!(
+                    "slog_server aggregate failed: {}",
+                    payload
+                        .get("message")
+                        .and_then(|value| value.as_str())
+                        .unwrap_or("unknown error")
+                );
+                return Vec::new();
+            }
+            Err(err) => {
+                log::warn!("Failed to aggregate logs from slog_server {}: {}", url, err);
+                return Vec::new();
+            }
+        };
+
+        // Fill empty buckets so the chart keeps a steady time axis.
+        let first_bucket = start_time - start_time % bucket_millis;
+        let mut counts: std::collections::BTreeMap<u64, (u64, u64)> = (first_bucket..=end_time)
+            .step_by(bucket_millis as usize)
+            .map(|time| (time, (0, 0)))
+            .collect();
+        let buckets = payload
+            .pointer("/data/buckets")
+            .and_then(|value| value.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for bucket in buckets {
+            let Some(time) = bucket.get("time").and_then(|value| value.as_u64()) else {
+                continue;
+            };
+            let count = bucket
+                .get("count")
+                .and_then(|value| value.as_u64())
+                .unwrap_or(0);
+            let entry = counts.entry(time).or_default();
+            match bucket.get("level").and_then(|value| value.as_str()) {
+                Some("Error") => entry.0 += count,
+                Some("Warn") => entry.1 += count,
+                _ => {}
+            }
+        }
+
+        counts
+            .into_iter()
+            .map(|(time, (errors, warnings))| {
+                let label = Utc
+                    .timestamp_millis_opt(time as i64)
+                    .single()
+                    .map(|time| time.format("%H:%M").to_string())
+                    .unwrap_or_default();
+                json!({
+                    "time": label,
+                    "errors": errors,
+                    "warnings": warnings,
+                })
+            })
+            .collect()
+    }
+
+    /// Query the aggregated logs kept by slog_server instead of local files.
+    async fn handle_slog_server_logs_query(
+        &self,
+        req: RPCRequest,
+    ) -> Result<RPCResponse, RPCErrors> {
+        let mut services: Vec<String> = req
+            .params
+            .get("services")
+            .and_then(|value| value.as_array())
+            .map(|list| {
+                list.iter()
+                    .filter_map(|item| item.as_str().map(|value| value.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let Some(service) = Self::param_str(&req, "service") {
+            services.push(service);
+        }
+        services.dedup();
+        if services.len() > 1 {
+            return Err(RPCErrors::ParseRequestError(
+                "slog_server source accepts a single service".to_string(),
+            ));
+        }
+
+        let parse_time = |key: &str| -> Result<Option<u64>, RPCErrors> {
+            match Self::param_str(&req, key) {
+                Some(value) => Self::parse_filter_time(&value)
+                    .map(|time| Some(time.timestamp_millis().max(0) as u64))
+                    .ok_or_else(|| RPCErrors::ParseRequestError(format!("Invalid {}", key))),
+                None => Ok(None),
+            }
+        };
+        let start_time = parse_time("since")?;
+        let end_time = parse_time("until")?;
+        // Validate locally so a bad pattern is reported as a request error.
+        Self::parse_regex_filter(&req)?;
+
+        let limit = req
+            .params
+            .get("limit")
+            .and_then(|value| value.as_u64())
+            .unwrap_or(DEFAULT_LOG_LIMIT as u64)
+            .clamp(1, MAX_LOG_LIMIT as u64);
+        let offset = Self::param_str(&req, "cursor")
+            .and_then(|value| Self::decode_cursor::<SlogQueryCursor>(&value))
+            .map(|cursor| cursor.offset)
+            .unwrap_or(0);
+
+        let body = json!({
+            "node": Self::param_str(&req, "node"),
+            "service": services.pop(),
+            "level": Self::param_str(&req, "level"),
+            "start_time": start_time,
+            "end_time": end_time,
+            "offset": offset,
+            "limit": limit,
+            "keyword": Self::param_str(&req, "keyword"),
+            "regex": Self::param_str(&req, "regex"),
+            "trace_id": Self::param_str(&req, "trace_id"),
+            "span_id": Self::param_str(&req, "span_id"),
+            "fields": req.params.get("fields").cloned().unwrap_or(json!({})),
+        });
+        let url =
+            Self::slog_server_url(SLOG_SERVER_QUERY_URL_ENV_KEY, DEFAULT_SLOG_SERVER_QUERY_URL);
+        let response = reqwest::Client::new()
+            .post(&url)
+            .timeout(std::time::Duration::from_secs(
+                SLOG_SERVER_QUERY_TIMEOUT_SECS,
+            ))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|err| {
+                RPCErrors::ReasonError(format!("Failed to query slog_server {}: {}", url, err))
+            })?;
+        let payload: Value = response.json().await.map_err(|err| {
+            RPCErrors::ReasonError(format!("Invalid slog_server response: {}", err))
+        })?;
+        if payload.get("ret").and_then(|value| value.as_i64()) != Some(0) {
+            let message = payload
+                .get("message")
+                .and_then(|value| value.as_str())
+                .unwrap_or("unknown error");
+            return Err(RPCErrors::ReasonError(format!(
+                "slog_server query failed: {}",
+                message
+            )));
+        }
+
+        let data = payload.get("data").cloned().unwrap_or(Value::Null);
+        let entries: Vec<Value> = data
+            .get("records")
+            .and_then(|value| value.as_array())
+            .map(|records| records.iter().map(Self::slog_record_entry).collect())
+            .unwrap_or_default();
+        let page = data.get("page").cloned().unwrap_or(Value::Null);
+        let has_more = page
+            .get("has_more")
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        let next_cursor = page
+            .get("next_offset")
+            .and_then(|value| value.as_u64())
+            .map(|offset| Self::encode_cursor(&SlogQueryCursor { offset }));
+        let truncated = page
+            .get("truncated")
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+
+        Ok(RPCResponse::new(
+            RPCResult::Success(json!({
+                "entries": entries,
+                "hasMore": has_more,
+                "nextCursor": next_cursor,
+                "truncated": truncated,
+            })),
+            req.seq,
+        ))
+    }
+
     pub(crate) fn list_log_service_ids(&self) -> Result<Vec<String>, RPCErrors> {
         let mut services = Vec::new();
         let entries = std::fs::read_dir(LOG_ROOT_DIR)
@@ -323,6 +618,10 @@ impl ControlPanelServer {
         &self,
         req: RPCRequest,
     ) -> Result<RPCResponse, RPCErrors> {
+        if Self::param_str(&req, "source").as_deref() == Some("slog_server") {
+            return self.handle_slog_server_logs_query(req).await;
+        }
+
         let mut services: Vec<String> = req
             .params
             .get("services")
@@ -363,6 +662,7 @@ impl ControlPanelServer {
         let level_filter = Self::param_str(&req, "level").map(|value| value.to_lowercase());
         let keyword_raw = Self::param_str(&req, "keyword");
         let keyword_filter = keyword_raw.as_ref().map(|value| value.to_lowercase());
+        let regex_filter = Self::parse_regex_filter(&req)?;
         let since_filter =
             Self::param_str(&req, "since").and_then(|value| Self::parse_filter_time(&value));
         let until_filter =
@@ -433,6 +733,11 @@ impl ControlPanelServer {
                                         continue;
                                     }
                                 }
+                                if let Some(filter) = regex_filter.as_ref() {
+                                    if !filter.is_match(&raw) {
+                                        continue;
+                                    }
+                                }
                                 if since_key.is_some() || until_key.is_some() {
                                     if ts.is_empty() {
                                         continue;
@@ -491,6 +796,11 @@ impl ControlPanelServer {
                                 continue;
                             }
                         }
+                        if let Some(filter) = regex_filter.as_ref() {
+                            if !filter.is_match(&raw_line) {
+                                continue;
+                            }
+                        }
                         if since_key.is_some() || until_key.is_some() {
                             if ts.is_empty() {
                                 continue;
@@ -593,6 +903,11 @@ impl ControlPanelServer {
                                         continue;
                                     }
                                 }
+                                if let Some(filter) = regex_filter.as_ref() {
+                                    if !filter.is_match(&raw) {
+                                        continue;
+                                    }
+                                }
                                 if since_key.is_some() || until_key.is_some() {
                                     if ts.is_empty() {
                                         continue;
@@ -685,6 +1000,11 @@ impl ControlPanelServer {
                                 continue;
                             }
                         }
+                        if let Some(filter) = regex_filter.as_ref() {
+                            if !filter.is_match(&raw_line) {
+                                continue;
+                            }
+                        }
                         if since_key.is_some() || until_key.is_some() {
                             if ts.is_empty() {
                                 continue;
@@ -748,10 +1068,185 @@ impl ControlPanelServer {
         }
     }
 
+    /// Collect records pushed by the slog_server `/tail` stream for up to `wait_ms`.
+    ///
+    /// The stream is consumed as a long poll: the request returns once `limit`
+    /// records arrived or the wait expired, and the cursor carries the last
+    /// stream sequence so the next call resumes without gaps or duplicates.
+    async fn handle_slog_server_logs_tail(
+        &self,
+        req: RPCRequest,
+    ) -> Result<RPCResponse, RPCErrors> {
+        let str_list = |key: &str| -> Vec<String> {
+            req.params
+                .get(key)
+                .and_then(|value| value.as_array())
+                .map(|list| {
+                    list.iter()
+                        .filter_map(|item| item.as_str().map(|value| value.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let mut services = str_list("services");
+        services.extend(Self::param_str(&req, "service"));
+        let mut nodes = str_list("nodes");
+        nodes.extend(Self::param_str(&req, "node"));
+        let mut levels = str_list("levels");
+        levels.extend(Self::param_str(&req, "level"));
+        Self::parse_regex_filter(&req)?;
+
+        let limit = req
+            .params
+            .get("limit")
+            .and_then(|value| value.as_u64())
+            .unwrap_or(DEFAULT_LOG_LIMIT as u64)
+            .clamp(1, MAX_LOG_LIMIT as u64) as usize;
+        let wait_ms = req
+            .params
+            .get("wait_ms")
+            .and_then(|value| value.as_u64())
+            .unwrap_or(DEFAULT_SLOG_TAIL_WAIT_MS)
+            .min(MAX_SLOG_TAIL_WAIT_MS);
+        let since_seq = Self::param_str(&req, "cursor")
+            .and_then(|value| Self::decode_cursor::<SlogTailCursor>(&value))
+            .map(|cursor| cursor.seq);
+
+        let mut query: Vec<(&str, String)> = vec![
+            ("services", services.join(",")),
+            ("nodes", nodes.join(",")),
+            ("levels", levels.join(",")),
+        ];
+        for key in ["keyword", "regex", "trace_id", "span_id"] {
+            if let Some(value) = Self::param_str(&req, key) {
+                query.push((key, value));
+            }
+        }
+        if let Some(fields) = req.params.get("fields").and_then(|value| value.as_object()) {
+            let fields = fields
+                .iter()
+                .filter_map(|(key, value)| value.as_str().map(|value| format!("{}:{}", key, value)))
+                .collect::<Vec<String>>()
+                .join(",");
+            query.push(("fields", fields));
+        }
+        if let Some(seq) = since_seq {
+            query.push(("since_seq", seq.to_string()));
+        }
+        query.retain(|(_, value)| !value.is_empty());
+
+        let url = Self::slog_server_url(SLOG_SERVER_TAIL_URL_ENV_KEY, DEFAULT_SLOG_SERVER_TAIL_URL);
+        let mut response = reqwest::Client::new()
+            .get(&url)
+            .query(&query)
+            .header(http::header::ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .map_err(|err| {
+                RPCErrors::ReasonError(format!("Failed to open slog_server tail {}: {}", url, err))
+            })?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let payload: Value = response.json().await.unwrap_or(Value::Null);
+            let message = payload
+                .get("message")
+                .and_then(|value| value.as_str())
+                .unwrap_or("unknown error");
+            return Err(RPCErrors::ReasonError(format!(
+                "slog_server tail failed ({}): {}",
+                status, message
+            )));
+        }
+
+        // Position of the stream when it was opened, used as the cursor when
+        // nothing matching arrives before the wait expires.
+        let start_seq = response
+            .headers()
+            .get(SLOG_STREAM_SEQ_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        let mut entries: Vec<Value> = Vec::new();
+        let mut last_seq: Option<u64> = None;
+        let mut lagged = 0u64;
+        let mut pending: Vec<u8> = Vec::new();
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_millis(wait_ms);
+        while entries.len() < limit {
+            let chunk = match tokio::time::timeout_at(deadline, response.chunk()).await {
+                Ok(Ok(Some(chunk))) => chunk,
+                Ok(Ok(None)) | Err(_) => break,
+                Ok(Err(err)) => {
+                    return Err(RPCErrors::ReasonError(format!(
+                        "Failed to read slog_server tail: {}",
+                        err
+                    )));
+                }
+            };
+            pending.extend_from_slice(&chunk);
+
+            // SSE events are separated by a blank line; keep any partial event for the next chunk.
+            while let Some(end) = pending.windows(2).position(|window| window == b"\n\n") {
+                let event_bytes: Vec<u8> = pending.drain(..end + 2).collect();
+                let event = String::from_utf8_lossy(&event_bytes);
+                let mut event_type = "message";
+                let mut data = String::new();
+                for line in event.lines() {
+                    if let Some(value) = line.strip_prefix("event:") {
+                        event_type = value.trim();
+                    } else if let Some(value) = line.strip_prefix("data:") {
+                        data.push_str(value.trim_start());
+                    }
+                }
+                let Ok(payload) = serde_json::from_str::<Value>(&data) else {
+                    continue;
+                };
+                match event_type {
+                    "log" => {
+                        last_seq = payload
+                            .get("seq")
+                            .and_then(|value| value.as_u64())
+                            .max(last_seq);
+                        entries.push(Self::slog_record_entry(&payload));
+                    }
+                    "lagged" => {
+                        lagged += payload
+                            .get("missed")
+                            .and_then(|value| value.as_u64())
+                            .unwrap_or(0);
+                    }
+                    _ => {}
+                }
+                if entries.len() >= limit {
+                    break;
+                }
+            }
+        }
+
+        // A full page may stop inside the replay, so only skip ahead to the
+        // stream start when the wait ran out with fewer records.
+        let next_seq = if entries.len() >= limit {
+            last_seq
+        } else {
+            last_seq.max(start_seq).or(since_seq)
+        };
+
+        Ok(RPCResponse::new(
+            RPCResult::Success(json!({
+                "entries": entries,
+                "lagged": lagged,
+                "nextCursor": next_seq.map(|seq| Self::encode_cursor(&SlogTailCursor { seq })),
+            })),
+            req.seq,
+        ))
+    }
+
     pub(crate) async fn handle_system_logs_tail(
         &self,
         req: RPCRequest,
     ) -> Result<RPCResponse, RPCErrors> {
+        if Self::param_str(&req, "source").as_deref() == Some("slog_server") {
+            return self.handle_slog_server_logs_tail(req).await;
+        }
+
         let mut services: Vec<String> = req
             .params
             .get("services")
@@ -677,11 +677,17 @@ type LogQueryParams = {
   file?: string
   level?: SystemLogLevel
   keyword?: string
+  regex?: string
   since?: string
   until?: string
   limit?: number
   cursor?: string
   direction?: 'forward' | 'backward'
+  source?: 'local' | 'slog_server'
+  node?: string
+  trace_id?: string
+  span_id?: string
+  fields?: Record<string, string>
 }
 
 type LogTailParams = {
@@ -693,6 +699,15 @@ type LogTailParams = {
   limit?: number
   cursor?: string
   from?: 'start' | 'end'
+  source?: 'local' | 'slog_server'
+  nodes?: string[]
+  node?: string
+  levels?: SystemLogLevel[]
+  regex?: string
+  trace_id?: string
+  span_id?: string
+  fields?: Record<string, string>
+  wait_ms?: number
 }
 
 type LogDownloadParams = {
@@ -491,6 +491,7 @@ mod tests {
             ingress_node: None,
             timestamp: 0,
             data: Value::Null,
+            seq: None,
         }
     }
 
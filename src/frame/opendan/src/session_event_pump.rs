@@ -331,6 +331,7 @@ mod tests {
             ingress_node: None,
             timestamp: 0,
             data: Value::Null,
+            seq: None,
         };
         pump.route_event(event).await;
 
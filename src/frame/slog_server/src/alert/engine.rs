@@ -0,0 +1,543 @@
+use super::AlertPublisher;
+use super::{AlertCondition, AlertRule, CompiledAlertRule};
+use super::{AlertEvent, AlertHistory, AlertHistoryQuery, AlertState};
+use crate::storage::LogRecords;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Global kevent id prefix, the rule id is appended as last segment.
+pub const ALERT_EVENT_ROOT: &str = "/slog_server/alerts";
+const MAX_ALERT_SAMPLE_CHARS: usize = 512;
+
+pub fn alert_eventid(rule_id: &str) -> String {
+    format!("{}/{}", ALERT_EVENT_ROOT, rule_id)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+struct RuleState {
+    compiled: CompiledAlertRule,
+    // Ingest times of the newest matching records, at most `threshold` of them.
+    hits: VecDeque<u64>,
+    // Last record in scope, starts at rule load so silent sources fire too.
+    last_seen: u64,
+    sample: Option<String>,
+    active: Option<AlertEvent>,
+}
+
+impl RuleState {
+    fn new(compiled: CompiledAlertRule, now: u64) -> Self {
+        Self {
+            compiled,
+            hits: VecDeque::new(),
+            last_seen: now,
+            sample: None,
+            active: None,
+        }
+    }
+
+    fn rule(&self) -> &AlertRule {
+        &self.compiled.rule
+    }
+
+    fn observe(&mut self, records: &LogRecords, now: u64) -> Option<AlertEvent> {
+        if !self.compiled.in_scope(&records.node, &records.service) {
+            return None;
+        }
+
+        match self.rule().condition {
+            AlertCondition::Records { threshold, .. } => {
+                for log in records.logs.iter() {
+                    if !self.compiled.matches(log) {
+                        continue;
+                    }
+                    self.hits.push_back(now);
+                    if self.hits.len() > threshold {
+                        self.hits.pop_front();
+                    }
+                    self.sample = Some(log.content.chars().take(MAX_ALERT_SAMPLE_CHARS).collect());
+                }
+                self.prune(now);
+                if self.active.is_none() && self.hits.len() >= threshold {
+                    return Some(self.fire(now));
+                }
+                None
+            }
+            AlertCondition::Silence { .. } => {
+                if records.logs.is_empty() {
+                    return None;
+                }
+                self.last_seen = now;
+                self.resolve(now, "records received again")
+            }
+        }
+    }
+
+    fn evaluate(&mut self, now: u64) -> Option<AlertEvent> {
+        match self.rule().condition {
+            AlertCondition::Records { threshold, .. } => {
+                self.prune(now);
+                if self.hits.len() < threshold {
+                    return self.resolve(now, "below threshold");
+                }
+                None
+            }
+            AlertCondition::Silence { silence_secs } => {
+                if self.active.is_none()
+                    && now.saturating_sub(self.last_seen) >= silence_secs * 1000
+                {
+                    return Some(self.fire(now));
+                }
+                None
+            }
+        }
+    }
+
+    fn prune(&mut self, now: u64) {
+        if let AlertCondition::Records { window_secs, .. } = self.rule().condition {
+            let since = now.saturating_sub(window_secs * 1000);
+            while self.hits.front().is_some_and(|time| *time < since) {
+                self.hits.pop_front();
+            }
+        }
+    }
+
+    fn summary(&self) -> String {
+        let rule = self.rule();
+        let scope = match (rule.node.as_deref(), rule.service.as_deref()) {
+            (Some(node), Some(service)) => format!("{}/{}", node, service),
+            (Some(node), None) => node.to_string(),
+            (None, Some(service)) => service.to_string(),
+            (None, None) => "any source".to_string(),
+        };
+        let text = match &rule.condition {
+            AlertCondition::Records {
+                level,
+                pattern,
+                threshold,
+                window_secs,
+            } => {
+                let mut what = String::from("records");
+                if let Some(level) = level {
+                    what = format!("{} records", level);
+                }
+                if let Some(pattern) = pattern {
+                    what.push_str(&format!(" matching '{}'", pattern));
+                }
+                format!(
+                    ">= {} {} from {} within {}s",
+                    threshold, what, scope, window_secs
+                )
+            }
+            AlertCondition::Silence { silence_secs } => {
+                format!("no records from {} for {}s", scope, silence_secs)
+            }
+        };
+        match rule.description.as_deref() {
+            Some(description) => format!("{}: {}", description, text),
+            None => text,
+        }
+    }
+
+    fn fire(&mut self, now: u64) -> AlertEvent {
+        let rule = self.rule();
+        let event = AlertEvent {
+            alert_id: format!("{}-{}", rule.id, now),
+            rule_id: rule.id.clone(),
+            state: AlertState::Firing,
+            time: now,
+            fired_at: now,
+            node: rule.node.clone(),
+            service: rule.service.clone(),
+            summary: self.summary(),
+            count: self.hits.len(),
+            sample: self.sample.clone(),
+        };
+        self.active = Some(event.clone());
+        event
+    }
+
+    fn resolve(&mut self, now: u64, reason: &str) -> Option<AlertEvent> {
+        let active = self.active.take()?;
+        Some(AlertEvent {
+            state: AlertState::Resolved,
+            time: now,
+            summary: format!("{} ({})", active.summary, reason),
+            count: self.hits.len(),
+            ..active
+        })
+    }
+}
+
+/// Evaluates alert rules over ingested records and publishes transitions.
+///
+/// Windows are measured on ingest time, so a backlog uploaded after an
+/// outage counts as arriving now. Alerts firing when the server stops are
+/// not resolved by the next instance.
+pub struct AlertEngine {
+    rules: Mutex<Vec<RuleState>>,
+    history: AlertHistory,
+    publisher: Option<Arc<dyn AlertPublisher>>,
+}
+
+impl AlertEngine {
+    pub fn new(history: AlertHistory) -> Self {
+        Self {
+            rules: Mutex::new(Vec::new()),
+            history,
+            publisher: None,
+        }
+    }
+
+    pub fn with_publisher(mut self, publisher: Arc<dyn AlertPublisher>) -> Self {
+        self.publisher = Some(publisher);
+        self
+    }
+
+    pub fn rules(&self) -> Vec<AlertRule> {
+        let rules = self.rules.lock().unwrap();
+        rules.iter().map(|state| state.rule().clone()).collect()
+    }
+
+    /// Replace the rule set. Unchanged rules keep their windows and alerts;
+    /// alerts of removed or changed rules are resolved.
+    pub fn set_rules(&self, rules: Vec<AlertRule>) -> Result<(), String> {
+        let compiled = rules
+            .iter()
+            .map(|rule| rule.compile())
+            .collect::<Result<Vec<_>, _>>()?;
+        let events = self.replace_rules(compiled, now_millis());
+        self.emit(events);
+        Ok(())
+    }
+
+    fn replace_rules(&self, compiled: Vec<CompiledAlertRule>, now: u64) -> Vec<AlertEvent> {
+        let mut rules = self.rules.lock().unwrap();
+        let mut previous: Vec<RuleState> = std::mem::take(&mut *rules);
+        for compiled in compiled {
+            let kept = previous
+                .iter()
+                .position(|state| state.compiled.rule == compiled.rule);
+            match kept {
+                Some(index) => rules.push(previous.swap_remove(index)),
+                None => rules.push(RuleState::new(compiled, now)),
+            }
+        }
+        previous
+            .iter_mut()
+            .filter_map(|state| state.resolve(now, "rule removed or changed"))
+            .collect()
+    }
+
+    pub fn ingest(&self, records: &LogRecords) {
+        let events = self.observe(records, now_millis());
+        self.emit(events);
+    }
+
+    /// Run time based checks: window expiry and silence.
+    pub fn evaluate(&self) {
+        let events = self.evaluate_at(now_millis());
+        self.emit(events);
+    }
+
+    fn observe(&self, records: &LogRecords, now: u64) -> Vec<AlertEvent> {
+        let mut rules = self.rules.lock().unwrap();
+        rules
+            .iter_mut()
+            .filter_map(|state| state.observe(records, now))
+            .collect()
+    }
+
+    fn evaluate_at(&self, now: u64) -> Vec<AlertEvent> {
+        let mut rules = self.rules.lock().unwrap();
+        rules
+            .iter_mut()
+            .filter_map(|state| state.evaluate(now))
+            .collect()
+    }
+
+    pub fn active_alerts(&self) -> Vec<AlertEvent> {
+        let rules = self.rules.lock().unwrap();
+        rules
+            .iter()
+            .filter_map(|state| state.active.clone())
+            .collect()
+    }
+
+    pub fn query_history(&self, query: &AlertHistoryQuery) -> Result<Vec<AlertEvent>, String> {
+        self.history.query(query)
+    }
+
+    fn emit(&self, events: Vec<AlertEvent>) {
+        for event in events {
+            match event.state {
+                AlertState::Firing => warn!("alert {} firing: {}", event.rule_id, event.summary),
+                AlertState::Resolved => {
+                    info!("alert {} resolved: {}", event.rule_id, event.summary)
+                }
+            }
+            let _ = self.history.record(&event);
+
+            let Some(publisher) = self.publisher.clone() else {
+                continue;
+            };
+            // Keep kevent latency off the ingest path.
+            tokio::spawn(async move {
+                let eventid = alert_eventid(&event.rule_id);
+                let data = match serde_json::to_value(&event) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        warn!("failed to encode alert event {}: {}", event.alert_id, e);
+                        return;
+                    }
+                };
+                if let Err(e) = publisher.publish(&eventid, data).await {
+                    warn!("failed to publish alert event {}: {}", eventid, e);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use slog::{LogLevel, SystemLogRecord};
+
+    #[derive(Default)]
+    struct RecordingPublisher {
+        published: Mutex<Vec<(String, serde_json::Value)>>,
+    }
+
+    #[async_trait]
+    impl AlertPublisher for RecordingPublisher {
+        async fn publish(&self, eventid: &str, data: serde_json::Value) -> Result<(), String> {
+            self.published
+                .lock()
+                .unwrap()
+                .push((eventid.to_string(), data));
+            Ok(())
+        }
+    }
+
+    fn batch(node: &str, service: &str, logs: &[(LogLevel, &str)]) -> LogRecords {
+        LogRecords {
+            node: node.to_string(),
+            service: service.to_string(),
+            batch_id: None,
+            record_ids: Vec::new(),
+            logs: logs
+                .iter()
+                .map(|(level, content)| SystemLogRecord {
+                    level: *level,
+                    target: service.to_string(),
+                    time: 1,
+                    file: None,
+                    line: None,
+                    content: content.to_string(),
+                    trace_id: None,
+                    span_id: None,
+                    fields: Default::default(),
+                })
+                .collect(),
+        }
+    }
+
+    fn engine_with(rules: &str, now: u64) -> AlertEngine {
+        let engine = AlertEngine::new(AlertHistory::open_in_memory().unwrap());
+        let compiled = AlertRule::parse_list(rules)
+            .unwrap()
+            .iter()
+            .map(|rule| rule.compile().unwrap())
+            .collect();
+        assert!(engine.replace_rules(compiled, now).is_empty());
+        engine
+    }
+
+    #[test]
+    fn test_records_rule_fires_at_threshold_and_resolves_after_window() {
+        let engine = engine_with(
+            r#"[{"id":"errors","service":"svc","kind":"records","level":"error","threshold":3,"window_secs":10}]"#,
+            0,
+        );
+
+        let events = engine.observe(
+            &batch(
+                "ood1",
+                "svc",
+                &[(LogLevel::Error, "e1"), (LogLevel::Info, "i1")],
+            ),
+            1_000,
+        );
+        assert!(events.is_empty());
+        // Other services are out of scope.
+        assert!(
+            engine
+                .observe(&batch("ood1", "other", &[(LogLevel::Error, "x"); 5]), 1_500)
+                .is_empty()
+        );
+
+        let events = engine.observe(
+            &batch(
+                "ood1",
+                "svc",
+                &[(LogLevel::Error, "e2"), (LogLevel::Error, "e3")],
+            ),
+            2_000,
+        );
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state, AlertState::Firing);
+        assert_eq!(events[0].alert_id, "errors-2000");
+        assert_eq!(events[0].count, 3);
+        assert_eq!(events[0].sample.as_deref(), Some("e3"));
+        assert_eq!(engine.active_alerts().len(), 1);
+
+        // Still firing: no duplicate event while above threshold.
+        assert!(
+            engine
+                .observe(&batch("ood1", "svc", &[(LogLevel::Error, "e4")]), 3_000)
+                .is_empty()
+        );
+        assert!(engine.evaluate_at(5_000).is_empty());
+
+        // Window slides past e1..e3: only e4 is left.
+        let events = engine.evaluate_at(12_500);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state, AlertState::Resolved);
+        assert_eq!(events[0].alert_id, "errors-2000");
+        assert_eq!(events[0].fired_at, 2_000);
+        assert!(engine.active_alerts().is_empty());
+    }
+
+    #[test]
+    fn test_pattern_rule_fires_on_first_match() {
+        let engine = engine_with(
+            r#"[{"id":"auth","kind":"records","pattern":"login failed","window_secs":60}]"#,
+            0,
+        );
+        assert!(
+            engine
+                .observe(
+                    &batch("ood1", "verify_hub", &[(LogLevel::Info, "login ok")]),
+                    1_000
+                )
+                .is_empty()
+        );
+        let events = engine.observe(
+            &batch(
+                "ood1",
+                "verify_hub",
+                &[(LogLevel::Warn, "login failed for alice")],
+            ),
+            2_000,
+        );
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].sample.as_deref(), Some("login failed for alice"));
+    }
+
+    #[test]
+    fn test_silence_rule_fires_without_records_and_resolves_on_next_record() {
+        let engine = engine_with(
+            r#"[{"id":"ood1_silent","node":"ood1","kind":"silence","silence_secs":30}]"#,
+            0,
+        );
+        assert!(engine.evaluate_at(29_999).is_empty());
+        // Records of other nodes do not count.
+        assert!(
+            engine
+                .observe(&batch("node2", "svc", &[(LogLevel::Info, "hi")]), 20_000)
+                .is_empty()
+        );
+
+        let events = engine.evaluate_at(30_000);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state, AlertState::Firing);
+        assert_eq!(events[0].node.as_deref(), Some("ood1"));
+        assert!(engine.evaluate_at(60_000).is_empty());
+
+        let events = engine.observe(&batch("ood1", "svc", &[(LogLevel::Info, "hi")]), 61_000);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state, AlertState::Resolved);
+        assert!(engine.evaluate_at(61_000 + 29_000).is_empty());
+    }
+
+    #[test]
+    fn test_replace_rules_keeps_unchanged_state_and_resolves_removed() {
+        let rules = r#"[{"id":"a","kind":"records","pattern":"boom","window_secs":60},
+                        {"id":"b","kind":"records","pattern":"bang","window_secs":60}]"#;
+        let engine = engine_with(rules, 0);
+        let fired = engine.observe(
+            &batch(
+                "ood1",
+                "svc",
+                &[(LogLevel::Error, "boom"), (LogLevel::Error, "bang")],
+            ),
+            1_000,
+        );
+        assert_eq!(fired.len(), 2);
+
+        let compiled = AlertRule::parse_list(
+            r#"[{"id":"a","kind":"records","pattern":"boom","window_secs":60}]"#,
+        )
+        .unwrap()
+        .iter()
+        .map(|rule| rule.compile().unwrap())
+        .collect();
+        let resolved = engine.replace_rules(compiled, 2_000);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].rule_id, "b");
+        assert_eq!(resolved[0].state, AlertState::Resolved);
+        assert_eq!(
+            engine
+                .active_alerts()
+                .iter()
+                .map(|e| e.rule_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingest_records_history_and_publishes_kevent() {
+        let publisher = Arc::new(RecordingPublisher::default());
+        let engine = AlertEngine::new(AlertHistory::open_in_memory().unwrap())
+            .with_publisher(publisher.clone());
+        engine
+            .set_rules(
+                AlertRule::parse_list(
+                    r#"[{"id":"panic","kind":"records","pattern":"panicked","window_secs":60}]"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        engine.ingest(&batch(
+            "ood1",
+            "svc",
+            &[(LogLevel::Error, "thread main panicked")],
+        ));
+
+        let history = engine.query_history(&AlertHistoryQuery::default()).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].rule_id, "panic");
+
+        for _ in 0..50 {
+            if !publisher.published.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let published = publisher.published.lock().unwrap();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].0, "/slog_server/alerts/panic");
+        assert_eq!(published[0].1["state"], "firing");
+        assert_eq!(published[0].1["alert_id"], history[0].alert_id.as_str());
+    }
+}
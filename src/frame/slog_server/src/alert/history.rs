@@ -0,0 +1,281 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Transitions kept in the history table; older rows are dropped on insert.
+pub const MAX_ALERT_HISTORY_ROWS: i64 = 10_000;
+pub const DEFAULT_ALERT_HISTORY_LIMIT: usize = 100;
+pub const MAX_ALERT_HISTORY_LIMIT: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+impl AlertState {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "firing" => Some(Self::Firing),
+            "resolved" => Some(Self::Resolved),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Firing => "firing",
+            Self::Resolved => "resolved",
+        }
+    }
+}
+
+/// One firing or resolved transition, also the kevent payload.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    /// `{rule_id}-{fired_at}`, shared by the firing and the resolved event.
+    pub alert_id: String,
+    pub rule_id: String,
+    pub state: AlertState,
+    /// Transition time, unix millis.
+    pub time: u64,
+    pub fired_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    pub summary: String,
+    /// Matching records in the window when the transition happened.
+    #[serde(default)]
+    pub count: usize,
+    /// Content of the last matching record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AlertHistoryQuery {
+    pub rule_id: Option<String>,
+    pub state: Option<AlertState>,
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+/// Alert transitions persisted next to the log partitions (`alerts.db`).
+#[derive(Clone)]
+pub struct AlertHistory {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl AlertHistory {
+    pub fn open(db_path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(db_path).map_err(|e| {
+            let msg = format!("Failed to open alert history at {:?}: {}", db_path, e);
+            error!("{}", msg);
+            msg
+        })?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self, String> {
+        let conn = Connection::open_in_memory().map_err(|e| {
+            let msg = format!("Failed to open in-memory alert history: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+        Self::init(conn)
+    }
+
+    fn init(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS alert_events (
+                id        INTEGER PRIMARY KEY AUTOINCREMENT,
+                alert_id  TEXT NOT NULL,
+                rule_id   TEXT NOT NULL,
+                state     TEXT NOT NULL,
+                time      INTEGER NOT NULL,
+                fired_at  INTEGER NOT NULL,
+                node      TEXT,
+                service   TEXT,
+                summary   TEXT NOT NULL,
+                count     INTEGER NOT NULL,
+                sample    TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_alert_events_time ON alert_events(time);
+            CREATE INDEX IF NOT EXISTS idx_alert_events_rule_time ON alert_events(rule_id, time);",
+        )
+        .map_err(|e| {
+            let msg = format!("Failed to create alert_events table: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    pub fn record(&self, event: &AlertEvent) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO alert_events
+                (alert_id, rule_id, state, time, fired_at, node, service, summary, count, sample)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![
+                &event.alert_id,
+                &event.rule_id,
+                event.state.as_str(),
+                event.time as i64,
+                event.fired_at as i64,
+                &event.node,
+                &event.service,
+                &event.summary,
+                event.count as i64,
+                &event.sample,
+            ],
+        )
+        .map_err(|e| {
+            let msg = format!("Failed to insert alert event {}: {}", event.alert_id, e);
+            error!("{}", msg);
+            msg
+        })?;
+        conn.execute(
+            "DELETE FROM alert_events WHERE id <= (SELECT MAX(id) FROM alert_events) - ?1",
+            rusqlite::params![MAX_ALERT_HISTORY_ROWS],
+        )
+        .map_err(|e| {
+            let msg = format!("Failed to trim alert history: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+        Ok(())
+    }
+
+    /// Newest transitions first.
+    pub fn query(&self, query: &AlertHistoryQuery) -> Result<Vec<AlertEvent>, String> {
+        let mut sql = String::from(
+            "SELECT alert_id, rule_id, state, time, fired_at, node, service, summary, count, sample
+             FROM alert_events WHERE 1 = 1",
+        );
+        let mut params: Vec<rusqlite::types::Value> = Vec::new();
+        if let Some(rule_id) = query.rule_id.as_ref() {
+            sql.push_str(" AND rule_id = ?");
+            params.push(rule_id.clone().into());
+        }
+        if let Some(state) = query.state {
+            sql.push_str(" AND state = ?");
+            params.push(state.as_str().to_string().into());
+        }
+        if let Some(start_time) = query.start_time {
+            sql.push_str(" AND time >= ?");
+            params.push((start_time as i64).into());
+        }
+        if let Some(end_time) = query.end_time {
+            sql.push_str(" AND time <= ?");
+            params.push((end_time as i64).into());
+        }
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_ALERT_HISTORY_LIMIT)
+            .clamp(1, MAX_ALERT_HISTORY_LIMIT);
+        sql.push_str(&format!(" ORDER BY time DESC, id DESC LIMIT {}", limit));
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql).map_err(|e| {
+            let msg = format!("Failed to prepare alert history query: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(params), |row| {
+                let state: String = row.get(2)?;
+                Ok(AlertEvent {
+                    alert_id: row.get(0)?,
+                    rule_id: row.get(1)?,
+                    state: AlertState::parse(&state).unwrap_or(AlertState::Resolved),
+                    time: row.get::<_, i64>(3)? as u64,
+                    fired_at: row.get::<_, i64>(4)? as u64,
+                    node: row.get(5)?,
+                    service: row.get(6)?,
+                    summary: row.get(7)?,
+                    count: row.get::<_, i64>(8)? as usize,
+                    sample: row.get(9)?,
+                })
+            })
+            .map_err(|e| {
+                let msg = format!("Failed to execute alert history query: {}", e);
+                error!("{}", msg);
+                msg
+            })?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| {
+            let msg = format!("Failed to read alert history row: {}", e);
+            error!("{}", msg);
+            msg
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(rule_id: &str, state: AlertState, time: u64) -> AlertEvent {
+        AlertEvent {
+            alert_id: format!("{}-{}", rule_id, 100),
+            rule_id: rule_id.to_string(),
+            state,
+            time,
+            fired_at: 100,
+            node: Some("ood1".to_string()),
+            service: None,
+            summary: "test".to_string(),
+            count: 3,
+            sample: Some("boom".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_history_query_filters_and_orders_newest_first() {
+        let history = AlertHistory::open_in_memory().unwrap();
+        history
+            .record(&event("a", AlertState::Firing, 100))
+            .unwrap();
+        history
+            .record(&event("b", AlertState::Firing, 150))
+            .unwrap();
+        history
+            .record(&event("a", AlertState::Resolved, 200))
+            .unwrap();
+
+        let all = history.query(&AlertHistoryQuery::default()).unwrap();
+        assert_eq!(
+            all.iter().map(|e| e.time).collect::<Vec<_>>(),
+            vec![200, 150, 100]
+        );
+        assert_eq!(all[2], event("a", AlertState::Firing, 100));
+
+        let rule_a = history
+            .query(&AlertHistoryQuery {
+                rule_id: Some("a".to_string()),
+                state: Some(AlertState::Resolved),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(rule_a.len(), 1);
+        assert_eq!(rule_a[0].time, 200);
+
+        let window = history
+            .query(&AlertHistoryQuery {
+                start_time: Some(120),
+                end_time: Some(180),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(window.len(), 1);
+        assert_eq!(window[0].rule_id, "b");
+    }
+}
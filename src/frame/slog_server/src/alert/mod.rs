@@ -0,0 +1,58 @@
+//! Log based alerting: rules from system config are evaluated on every stored
+//! batch (and periodically for time based conditions); firing and resolved
+//! transitions are kept in `alerts.db` and published as global kevents under
+//! `/slog_server/alerts/{rule_id}`.
+
+mod engine;
+mod history;
+mod remote;
+mod rule;
+
+pub use engine::*;
+pub use history::*;
+pub use remote::*;
+pub use rule::*;
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often window expiry and silence rules are checked.
+pub const ALERT_EVALUATE_INTERVAL_SECS: u64 = 5;
+
+pub fn open_alert_history(storage_dir: &Path) -> Result<AlertHistory, String> {
+    AlertHistory::open(&storage_dir.join("alerts.db"))
+}
+
+/// Keep the rules in sync with `source` and run time based checks.
+pub fn spawn_alert_task(
+    engine: Arc<AlertEngine>,
+    source: Arc<dyn AlertRuleSource>,
+    refresh_interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut refresh = tokio::time::interval(refresh_interval);
+        refresh.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut evaluate = tokio::time::interval(Duration::from_secs(ALERT_EVALUATE_INTERVAL_SECS));
+        evaluate.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = refresh.tick() => {
+                    // Keep the last good rule set when the source is unavailable or invalid.
+                    match source.load_rules().await {
+                        Ok(rules) => {
+                            if rules != engine.rules() {
+                                info!("loaded {} alert rules", rules.len());
+                            }
+                            if let Err(e) = engine.set_rules(rules) {
+                                warn!("ignore invalid alert rules: {}", e);
+                            }
+                        }
+                        Err(e) => warn!("failed to load alert rules: {}", e),
+                    }
+                }
+                _ = evaluate.tick() => engine.evaluate(),
+            }
+        }
+    })
+}
@@ -0,0 +1,66 @@
+use super::AlertRule;
+use async_trait::async_trait;
+use buckyos_api::{KEventClient, SystemConfigClient, SystemConfigError};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// System config key holding the alert rules, see [`AlertRule`].
+pub const ALERT_RULES_CONFIG_KEY: &str = "services/slog_server/alert_rules";
+
+#[async_trait]
+pub trait AlertRuleSource: Send + Sync {
+    async fn load_rules(&self) -> Result<Vec<AlertRule>, String>;
+}
+
+#[async_trait]
+pub trait AlertPublisher: Send + Sync {
+    async fn publish(&self, eventid: &str, data: Value) -> Result<(), String>;
+}
+
+/// Reads the rules through the runtime's system config client.
+pub struct SystemConfigRuleSource {
+    client: Arc<SystemConfigClient>,
+    key: String,
+}
+
+impl SystemConfigRuleSource {
+    pub fn new(client: Arc<SystemConfigClient>) -> Self {
+        Self {
+            client,
+            key: ALERT_RULES_CONFIG_KEY.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl AlertRuleSource for SystemConfigRuleSource {
+    async fn load_rules(&self) -> Result<Vec<AlertRule>, String> {
+        match self.client.get(&self.key).await {
+            Ok(value) => AlertRule::parse_list(&value.value),
+            // No rules configured.
+            Err(SystemConfigError::KeyNotFound(_)) => Ok(Vec::new()),
+            Err(e) => Err(format!("system config get {} failed: {}", self.key, e)),
+        }
+    }
+}
+
+/// Publishes alert events as global kevents through the runtime's kevent client.
+pub struct KEventAlertPublisher {
+    client: Arc<KEventClient>,
+}
+
+impl KEventAlertPublisher {
+    pub fn new(client: Arc<KEventClient>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl AlertPublisher for KEventAlertPublisher {
+    async fn publish(&self, eventid: &str, data: Value) -> Result<(), String> {
+        self.client
+            .pub_event(eventid, data)
+            .await
+            .map_err(|e| format!("kevent publish {} failed: {}", eventid, e))
+    }
+}
@@ -0,0 +1,247 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use slog::{LogLevel, SystemLogRecord};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+const MAX_RULE_ID_LEN: usize = 64;
+
+fn default_threshold() -> usize {
+    1
+}
+
+/// One alert rule as stored in system config.
+///
+/// ```json
+/// [
+///   { "id": "node_daemon_errors", "service": "node_daemon", "kind": "records",
+///     "level": "error", "threshold": 20, "window_secs": 60 },
+///   { "id": "login_failed", "kind": "records", "pattern": "login failed", "window_secs": 300 },
+///   { "id": "ood1_silent", "node": "ood1", "kind": "silence", "silence_secs": 600 }
+/// ]
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    /// Stable id, also the last segment of the kevent event id.
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Only records of this node are considered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    /// Only records of this service are considered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    #[serde(flatten)]
+    pub condition: AlertCondition,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Fires once `threshold` matching records arrived within `window_secs`,
+    /// resolves when the window no longer holds that many.
+    Records {
+        /// Least severe level that counts, e.g. `warn` counts warn and error.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        level: Option<String>,
+        /// Regex the content must match.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pattern: Option<String>,
+        #[serde(default = "default_threshold")]
+        threshold: usize,
+        window_secs: u64,
+    },
+    /// Fires when no record arrived for `silence_secs`, resolves on the next record.
+    Silence { silence_secs: u64 },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AlertRuleList {
+    Rules(Vec<AlertRule>),
+    Wrapped { rules: Vec<AlertRule> },
+}
+
+impl AlertRule {
+    /// Parse the system config value, either a rule array or `{"rules": [...]}`.
+    pub fn parse_list(raw: &str) -> Result<Vec<AlertRule>, String> {
+        if raw.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let rules = match serde_json::from_str::<AlertRuleList>(raw)
+            .map_err(|e| format!("invalid alert rules: {}", e))?
+        {
+            AlertRuleList::Rules(rules) | AlertRuleList::Wrapped { rules } => rules,
+        };
+
+        let mut ids = HashSet::new();
+        for rule in rules.iter() {
+            rule.compile()?;
+            if !ids.insert(rule.id.as_str()) {
+                return Err(format!("duplicate alert rule id '{}'", rule.id));
+            }
+        }
+        Ok(rules)
+    }
+
+    pub(crate) fn compile(&self) -> Result<CompiledAlertRule, String> {
+        if self.id.is_empty()
+            || self.id.len() > MAX_RULE_ID_LEN
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+        {
+            return Err(format!(
+                "invalid alert rule id '{}': expected 1-{} chars of [A-Za-z0-9_.-]",
+                self.id, MAX_RULE_ID_LEN
+            ));
+        }
+
+        let (level, pattern) = match &self.condition {
+            AlertCondition::Records {
+                level,
+                pattern,
+                threshold,
+                window_secs,
+            } => {
+                if *threshold == 0 || *window_secs == 0 {
+                    return Err(format!(
+                        "alert rule '{}': threshold and window_secs must be positive",
+                        self.id
+                    ));
+                }
+                let level = level
+                    .as_deref()
+                    .map(|v| LogLevel::from_str(&v.trim().to_ascii_lowercase()))
+                    .transpose()
+                    .map_err(|e| format!("alert rule '{}': {}", self.id, e))?;
+                let pattern = pattern
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(|e| format!("alert rule '{}': invalid pattern: {}", self.id, e))?;
+                (level, pattern)
+            }
+            AlertCondition::Silence { silence_secs } => {
+                if *silence_secs == 0 {
+                    return Err(format!(
+                        "alert rule '{}': silence_secs must be positive",
+                        self.id
+                    ));
+                }
+                (None, None)
+            }
+        };
+
+        Ok(CompiledAlertRule {
+            rule: self.clone(),
+            level,
+            pattern,
+        })
+    }
+}
+
+pub(crate) struct CompiledAlertRule {
+    pub rule: AlertRule,
+    level: Option<LogLevel>,
+    pattern: Option<Regex>,
+}
+
+impl CompiledAlertRule {
+    pub fn in_scope(&self, node: &str, service: &str) -> bool {
+        self.rule.node.as_deref().is_none_or(|v| v == node)
+            && self.rule.service.as_deref().is_none_or(|v| v == service)
+    }
+
+    pub fn matches(&self, log: &SystemLogRecord) -> bool {
+        self.level
+            .is_none_or(|level| log.level != LogLevel::Off && log.level <= level)
+            && self
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(&log.content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(level: LogLevel, content: &str) -> SystemLogRecord {
+        SystemLogRecord {
+            level,
+            target: "svc".to_string(),
+            time: 1,
+            file: None,
+            line: None,
+            content: content.to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_accepts_array_and_wrapped_forms() {
+        let rules = AlertRule::parse_list(
+            r#"[{"id":"errors","service":"svc","kind":"records","level":"error","threshold":3,"window_secs":60},
+                {"id":"ood1_silent","node":"ood1","kind":"silence","silence_secs":600}]"#,
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            rules[1].condition,
+            AlertCondition::Silence { silence_secs: 600 }
+        );
+
+        let wrapped = AlertRule::parse_list(
+            r#"{"rules":[{"id":"auth","kind":"records","pattern":"login failed","window_secs":30}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            wrapped[0].condition,
+            AlertCondition::Records {
+                level: None,
+                pattern: Some("login failed".to_string()),
+                threshold: 1,
+                window_secs: 30,
+            }
+        );
+        assert!(AlertRule::parse_list("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_list_rejects_invalid_rules() {
+        for raw in [
+            r#"[{"id":"a b","kind":"silence","silence_secs":1}]"#,
+            r#"[{"id":"a","kind":"silence","silence_secs":0}]"#,
+            r#"[{"id":"a","kind":"records","level":"fatal","window_secs":1}]"#,
+            r#"[{"id":"a","kind":"records","pattern":"(","window_secs":1}]"#,
+            r#"[{"id":"a","kind":"records","threshold":0,"window_secs":1}]"#,
+            r#"[{"id":"a","kind":"silence","silence_secs":1},{"id":"a","kind":"silence","silence_secs":2}]"#,
+            r#"[{"id":"a","kind":"unknown"}]"#,
+        ] {
+            assert!(AlertRule::parse_list(raw).is_err(), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_compiled_rule_matches_scope_level_and_pattern() {
+        let rule = AlertRule::parse_list(
+            r#"[{"id":"a","service":"svc","kind":"records","level":"warn","pattern":"^disk","window_secs":1}]"#,
+        )
+        .unwrap()
+        .remove(0)
+        .compile()
+        .unwrap();
+
+        assert!(rule.in_scope("ood1", "svc"));
+        assert!(!rule.in_scope("ood1", "other"));
+        assert!(rule.matches(&record(LogLevel::Error, "disk full")));
+        assert!(rule.matches(&record(LogLevel::Warn, "disk almost full")));
+        assert!(!rule.matches(&record(LogLevel::Info, "disk ok")));
+        assert!(!rule.matches(&record(LogLevel::Error, "net down")));
+    }
+}
@@ -1,6 +1,7 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-use crate::storage::{LogStorageType, PartitionBucket, SqlitePartitionedConfig};
+use crate::storage::{LogStorageType, PartitionBucket, RetentionPolicy, SqlitePartitionedConfig};
 
 /// Environment key for overriding server bind address.
 pub const SLOG_SERVER_BIND_ENV_KEY: &str = "SLOG_SERVER_BIND";
@@ -14,6 +15,19 @@ pub const SLOG_STORAGE_PARTITION_BUCKET_ENV_KEY: &str = "SLOG_STORAGE_PARTITION_
 pub const SLOG_STORAGE_PARTITION_MAX_ROWS_ENV_KEY: &str = "SLOG_STORAGE_PARTITION_MAX_ROWS";
 /// Environment key for max DB size (MB) in one partition DB file.
 pub const SLOG_STORAGE_PARTITION_MAX_SIZE_MB_ENV_KEY: &str = "SLOG_STORAGE_PARTITION_MAX_SIZE_MB";
+/// Environment key for max age (days) of stored logs, `0` keeps logs by age forever.
+pub const SLOG_RETENTION_MAX_AGE_DAYS_ENV_KEY: &str = "SLOG_RETENTION_MAX_AGE_DAYS";
+/// Environment key for total size cap (MB) of all partitions, `0` disables the cap.
+pub const SLOG_RETENTION_MAX_TOTAL_SIZE_MB_ENV_KEY: &str = "SLOG_RETENTION_MAX_TOTAL_SIZE_MB";
+/// Environment key for per-service max age overrides, e.g. `node_daemon=7,control_panel=90`.
+pub const SLOG_RETENTION_SERVICE_MAX_AGE_DAYS_ENV_KEY: &str = "SLOG_RETENTION_SERVICE_MAX_AGE_DAYS";
+/// Environment key for the interval (seconds) between two retention runs.
+pub const SLOG_RETENTION_CHECK_INTERVAL_SECS_ENV_KEY: &str = "SLOG_RETENTION_CHECK_INTERVAL_SECS";
+/// Environment key for switching `/logs` upload authentication; only `off`
+/// (or `false` / `0`) disables it, for servers outside a zone.
+pub const SLOG_UPLOAD_AUTH_ENV_KEY: &str = "SLOG_UPLOAD_AUTH";
+/// Environment key for the interval (seconds) between two alert rule reloads.
+pub const SLOG_ALERT_RULES_REFRESH_SECS_ENV_KEY: &str = "SLOG_ALERT_RULES_REFRESH_SECS";
 /// Default bind address when no external config is provided.
 pub const DEFAULT_SERVER_BIND: &str = "127.0.0.1:22001";
 /// Default backend type.
@@ -24,6 +38,16 @@ pub const DEFAULT_STORAGE_PARTITION_BUCKET: PartitionBucket = PartitionBucket::D
 pub const DEFAULT_STORAGE_PARTITION_MAX_ROWS: u64 = 5_000_000;
 /// Default size cap per partition DB, in MB.
 pub const DEFAULT_STORAGE_PARTITION_MAX_SIZE_MB: u64 = 2048;
+/// Default max age of stored logs, in days.
+pub const DEFAULT_RETENTION_MAX_AGE_DAYS: u64 = 30;
+/// Default total size cap of all partitions, in MB.
+pub const DEFAULT_RETENTION_MAX_TOTAL_SIZE_MB: u64 = 10 * 1024;
+/// Default interval between two retention runs, in seconds.
+pub const DEFAULT_RETENTION_CHECK_INTERVAL_SECS: u64 = 10 * 60;
+/// Default interval between two alert rule reloads, in seconds.
+pub const DEFAULT_ALERT_RULES_REFRESH_SECS: u64 = 30;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
 
 #[derive(Debug, Clone)]
 pub struct NetworkConfig {
@@ -62,11 +86,65 @@ pub struct StoragePartitionConfig {
     pub max_partition_size_mb: u64,
 }
 
+/// Retention policy, only enforced by the partitioned engine.
+///
+/// Expired data is dropped a whole partition at a time, so logs can outlive
+/// `max_age_days` by up to one partition bucket.
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    pub max_age_days: u64,
+    pub max_total_size_mb: u64,
+    pub service_max_age_days: BTreeMap<String, u64>,
+    pub check_interval_secs: u64,
+}
+
+impl RetentionConfig {
+    /// Parse per-service overrides in `service=days[,service=days...]` form.
+    pub fn parse_service_max_age_days(raw: &str) -> Result<BTreeMap<String, u64>, String> {
+        let mut overrides = BTreeMap::new();
+        for item in raw.split(',') {
+            let item = item.trim();
+            if item.is_empty() {
+                continue;
+            }
+
+            let Some((service, days)) = item.split_once('=') else {
+                return Err(format!("invalid item '{}': expected service=days", item));
+            };
+            let service = service.trim();
+            if service.is_empty() {
+                return Err(format!("invalid item '{}': service is empty", item));
+            }
+            let days = days.trim().parse::<u64>().map_err(|e| {
+                format!(
+                    "invalid item '{}': expected unsigned integer days ({})",
+                    item, e
+                )
+            })?;
+            overrides.insert(service.to_string(), days);
+        }
+        Ok(overrides)
+    }
+
+    pub fn to_policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            max_age_secs: self.max_age_days.saturating_mul(SECS_PER_DAY),
+            max_total_bytes: self.max_total_size_mb.saturating_mul(1024 * 1024),
+            service_max_age_secs: self
+                .service_max_age_days
+                .iter()
+                .map(|(service, days)| (service.clone(), days.saturating_mul(SECS_PER_DAY)))
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct StorageConfig {
     pub storage_dir: PathBuf,
     pub storage_engine: StorageEngine,
     pub partition: StoragePartitionConfig,
+    pub retention: RetentionConfig,
 }
 
 impl StorageConfig {
@@ -82,16 +160,43 @@ impl StorageConfig {
                     bucket: self.partition.bucket.clone(),
                     max_rows_per_partition: self.partition.max_rows_per_partition,
                     max_partition_size_bytes,
+                    retention: self.retention.to_policy(),
                 })
             }
         }
     }
 }
 
+/// Upload authentication: every `/logs` batch must be signed by a device of the zone.
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    pub upload_auth: bool,
+}
+
+impl AuthConfig {
+    /// Parse the `SLOG_UPLOAD_AUTH` switch (`on` / `off`).
+    pub fn parse_switch(raw: &str) -> Option<bool> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "on" | "true" | "1" => Some(true),
+            "off" | "false" | "0" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Alert rules are read from system config and events published to kevent
+/// through the zone runtime.
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    pub rules_refresh_secs: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
     pub network: NetworkConfig,
     pub storage: StorageConfig,
+    pub auth: AuthConfig,
+    pub alert: AlertConfig,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -102,6 +207,12 @@ pub struct ServerEnvOverrides {
     pub partition_bucket: Option<PartitionBucket>,
     pub partition_max_rows: Option<u64>,
     pub partition_max_size_mb: Option<u64>,
+    pub retention_max_age_days: Option<u64>,
+    pub retention_max_total_size_mb: Option<u64>,
+    pub retention_service_max_age_days: Option<BTreeMap<String, u64>>,
+    pub retention_check_interval_secs: Option<u64>,
+    pub upload_auth: Option<bool>,
+    pub alert_rules_refresh_secs: Option<u64>,
 }
 
 impl Default for ServerConfig {
@@ -118,6 +229,16 @@ impl Default for ServerConfig {
                     max_rows_per_partition: DEFAULT_STORAGE_PARTITION_MAX_ROWS,
                     max_partition_size_mb: DEFAULT_STORAGE_PARTITION_MAX_SIZE_MB,
                 },
+                retention: RetentionConfig {
+                    max_age_days: DEFAULT_RETENTION_MAX_AGE_DAYS,
+                    max_total_size_mb: DEFAULT_RETENTION_MAX_TOTAL_SIZE_MB,
+                    service_max_age_days: BTreeMap::new(),
+                    check_interval_secs: DEFAULT_RETENTION_CHECK_INTERVAL_SECS,
+                },
+            },
+            auth: AuthConfig { upload_auth: true },
+            alert: AlertConfig {
+                rules_refresh_secs: DEFAULT_ALERT_RULES_REFRESH_SECS,
             },
         }
     }
@@ -153,19 +274,49 @@ impl ServerConfig {
         if let Some(v) = overrides.partition_max_size_mb {
             self.storage.partition.max_partition_size_mb = v;
         }
+
+        if let Some(v) = overrides.retention_max_age_days {
+            self.storage.retention.max_age_days = v;
+        }
+
+        if let Some(v) = overrides.retention_max_total_size_mb {
+            self.storage.retention.max_total_size_mb = v;
+        }
+
+        if let Some(v) = overrides.retention_service_max_age_days {
+            self.storage.retention.service_max_age_days = v;
+        }
+
+        if let Some(v) = overrides.retention_check_interval_secs {
+            self.storage.retention.check_interval_secs = v;
+        }
+
+        if let Some(v) = overrides.upload_auth {
+            self.auth.upload_auth = v;
+        }
+
+        if let Some(v) = overrides.alert_rules_refresh_secs {
+            self.alert.rules_refresh_secs = v;
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        DEFAULT_SERVER_BIND, DEFAULT_STORAGE_PARTITION_MAX_ROWS,
-        DEFAULT_STORAGE_PARTITION_MAX_SIZE_MB, DEFAULT_STORAGE_TYPE, SLOG_SERVER_BIND_ENV_KEY,
-        SLOG_STORAGE_DIR_ENV_KEY, SLOG_STORAGE_PARTITION_BUCKET_ENV_KEY,
+        AuthConfig, DEFAULT_ALERT_RULES_REFRESH_SECS, DEFAULT_RETENTION_MAX_AGE_DAYS,
+        DEFAULT_RETENTION_MAX_TOTAL_SIZE_MB, DEFAULT_SERVER_BIND,
+        DEFAULT_STORAGE_PARTITION_MAX_ROWS, DEFAULT_STORAGE_PARTITION_MAX_SIZE_MB,
+        DEFAULT_STORAGE_TYPE, RetentionConfig, SLOG_ALERT_RULES_REFRESH_SECS_ENV_KEY,
+        SLOG_RETENTION_CHECK_INTERVAL_SECS_ENV_KEY, SLOG_RETENTION_MAX_AGE_DAYS_ENV_KEY,
+        SLOG_RETENTION_MAX_TOTAL_SIZE_MB_ENV_KEY, SLOG_RETENTION_SERVICE_MAX_AGE_DAYS_ENV_KEY,
+        SLOG_SERVER_BIND_ENV_KEY, SLOG_STORAGE_DIR_ENV_KEY, SLOG_STORAGE_PARTITION_BUCKET_ENV_KEY,
         SLOG_STORAGE_PARTITION_MAX_ROWS_ENV_KEY, SLOG_STORAGE_PARTITION_MAX_SIZE_MB_ENV_KEY,
-        SLOG_STORAGE_TYPE_ENV_KEY, ServerConfig, ServerEnvOverrides, StorageEngine,
+        SLOG_STORAGE_TYPE_ENV_KEY, SLOG_UPLOAD_AUTH_ENV_KEY, ServerConfig, ServerEnvOverrides,
+        StorageEngine,
     };
     use crate::storage::{LogStorageType, PartitionBucket};
+    use std::collections::BTreeMap;
     use std::path::PathBuf;
 
     #[test]
@@ -186,6 +337,20 @@ mod tests {
             cfg.storage.partition.max_partition_size_mb,
             DEFAULT_STORAGE_PARTITION_MAX_SIZE_MB
         );
+        assert_eq!(
+            cfg.storage.retention.max_age_days,
+            DEFAULT_RETENTION_MAX_AGE_DAYS
+        );
+        assert_eq!(
+            cfg.storage.retention.max_total_size_mb,
+            DEFAULT_RETENTION_MAX_TOTAL_SIZE_MB
+        );
+        assert!(cfg.storage.retention.service_max_age_days.is_empty());
+        assert!(cfg.auth.upload_auth);
+        assert_eq!(
+            cfg.alert.rules_refresh_secs,
+            DEFAULT_ALERT_RULES_REFRESH_SECS
+        );
     }
 
     #[test]
@@ -198,6 +363,12 @@ mod tests {
             partition_bucket: Some(PartitionBucket::Day),
             partition_max_rows: Some(1000),
             partition_max_size_mb: Some(128),
+            retention_max_age_days: Some(7),
+            retention_max_total_size_mb: Some(512),
+            retention_service_max_age_days: Some(BTreeMap::from([("svc-a".to_string(), 1)])),
+            retention_check_interval_secs: Some(60),
+            upload_auth: Some(false),
+            alert_rules_refresh_secs: Some(5),
         };
         cfg.apply_env_overrides(overrides);
 
@@ -210,6 +381,15 @@ mod tests {
         assert_eq!(cfg.storage.partition.bucket, PartitionBucket::Day);
         assert_eq!(cfg.storage.partition.max_rows_per_partition, 1000);
         assert_eq!(cfg.storage.partition.max_partition_size_mb, 128);
+        assert_eq!(cfg.storage.retention.max_age_days, 7);
+        assert_eq!(cfg.storage.retention.max_total_size_mb, 512);
+        assert_eq!(
+            cfg.storage.retention.service_max_age_days.get("svc-a"),
+            Some(&1)
+        );
+        assert_eq!(cfg.storage.retention.check_interval_secs, 60);
+        assert!(!cfg.auth.upload_auth);
+        assert_eq!(cfg.alert.rules_refresh_secs, 5);
     }
 
     #[test]
@@ -223,6 +403,12 @@ mod tests {
             partition_bucket: None,
             partition_max_rows: None,
             partition_max_size_mb: None,
+            retention_max_age_days: None,
+            retention_max_total_size_mb: None,
+            retention_service_max_age_days: None,
+            retention_check_interval_secs: None,
+            upload_auth: None,
+            alert_rules_refresh_secs: None,
         };
         cfg.apply_env_overrides(overrides);
 
@@ -247,6 +433,35 @@ mod tests {
             SLOG_STORAGE_PARTITION_MAX_SIZE_MB_ENV_KEY,
             "SLOG_STORAGE_PARTITION_MAX_SIZE_MB"
         );
+        assert_eq!(
+            SLOG_RETENTION_MAX_AGE_DAYS_ENV_KEY,
+            "SLOG_RETENTION_MAX_AGE_DAYS"
+        );
+        assert_eq!(
+            SLOG_RETENTION_MAX_TOTAL_SIZE_MB_ENV_KEY,
+            "SLOG_RETENTION_MAX_TOTAL_SIZE_MB"
+        );
+        assert_eq!(
+            SLOG_RETENTION_SERVICE_MAX_AGE_DAYS_ENV_KEY,
+            "SLOG_RETENTION_SERVICE_MAX_AGE_DAYS"
+        );
+        assert_eq!(
+            SLOG_RETENTION_CHECK_INTERVAL_SECS_ENV_KEY,
+            "SLOG_RETENTION_CHECK_INTERVAL_SECS"
+        );
+        assert_eq!(SLOG_UPLOAD_AUTH_ENV_KEY, "SLOG_UPLOAD_AUTH");
+        assert_eq!(
+            SLOG_ALERT_RULES_REFRESH_SECS_ENV_KEY,
+            "SLOG_ALERT_RULES_REFRESH_SECS"
+        );
+    }
+
+    #[test]
+    fn test_upload_auth_switch_parse() {
+        assert_eq!(AuthConfig::parse_switch("off"), Some(false));
+        assert_eq!(AuthConfig::parse_switch(" FALSE "), Some(false));
+        assert_eq!(AuthConfig::parse_switch("on"), Some(true));
+        assert_eq!(AuthConfig::parse_switch("maybe"), None);
     }
 
     #[test]
@@ -276,4 +491,38 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_retention_config_parse_overrides_and_to_policy() {
+        let overrides =
+            RetentionConfig::parse_service_max_age_days(" node_daemon=7, control_panel = 90 ,")
+                .unwrap();
+        assert_eq!(
+            overrides,
+            BTreeMap::from([
+                ("control_panel".to_string(), 90),
+                ("node_daemon".to_string(), 7),
+            ])
+        );
+        assert!(RetentionConfig::parse_service_max_age_days("node_daemon").is_err());
+        assert!(RetentionConfig::parse_service_max_age_days("=7").is_err());
+        assert!(RetentionConfig::parse_service_max_age_days("svc=-1").is_err());
+
+        let mut cfg = ServerConfig::default();
+        cfg.storage.retention.max_age_days = 2;
+        cfg.storage.retention.max_total_size_mb = 3;
+        cfg.storage.retention.service_max_age_days = overrides;
+        match cfg.storage.to_storage_type() {
+            LogStorageType::Sqlite => panic!("expected partitioned storage type"),
+            LogStorageType::SqlitePartitioned(partitioned) => {
+                let policy = partitioned.retention;
+                assert_eq!(policy.max_age_secs, 2 * 86_400);
+                assert_eq!(policy.max_total_bytes, 3 * 1024 * 1024);
+                assert_eq!(
+                    policy.service_max_age_secs.get("node_daemon"),
+                    Some(&(7 * 86_400))
+                );
+            }
+        }
+    }
 }
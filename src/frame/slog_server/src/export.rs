@@ -0,0 +1,603 @@
+//! Export of log query results for external tooling and archives.
+//!
+//! Both formats are line based so an export can be streamed while it is read
+//! from storage: `ndjson` writes one `{node, service, log}` object per record,
+//! `otlp` writes one OTLP/JSON `ExportLogsServiceRequest` per page (the layout
+//! of the OpenTelemetry collector file exporter), with `host.name` and
+//! `service.name` as resource attributes. Records come out newest first.
+//!
+//! A regex export whose page runs out of `MAX_REGEX_SCAN_ROWS` cannot reach
+//! older matches; the stream then ends with one `{"error": "truncated", ...}`
+//! line instead of a record, so a client can tell it apart from a complete
+//! export.
+
+use crate::storage::{LogQueryRequest, LogStorageRef, MAX_REGEX_SCAN_ROWS};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use slog::{LogLevel, SystemLogRecord};
+use std::collections::BTreeMap;
+
+/// Records read from storage per export page.
+pub const EXPORT_PAGE_SIZE: usize = 1000;
+
+const OTLP_SCOPE_NAME: &str = "buckyos.slog";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogExportFormat {
+    #[default]
+    Ndjson,
+    Otlp,
+}
+
+impl LogExportFormat {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            "otlp" | "otlp_json" | "otlp-json" => Some(Self::Otlp),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ndjson => "ndjson",
+            Self::Otlp => "otlp",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        "application/x-ndjson"
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            Self::Ndjson => "ndjson",
+            Self::Otlp => "otlp.jsonl",
+        }
+    }
+
+    /// Encode one page as newline terminated JSON lines.
+    pub fn encode_page(&self, records: &[ExportLogRecord]) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        match self {
+            Self::Ndjson => {
+                for record in records {
+                    serde_json::to_writer(&mut out, record)
+                        .map_err(|e| format!("Failed to encode export record: {}", e))?;
+                    out.push(b'\n');
+                }
+            }
+            Self::Otlp => {
+                if records.is_empty() {
+                    return Ok(out);
+                }
+                serde_json::to_writer(&mut out, &otlp_logs_request(records))
+                    .map_err(|e| format!("Failed to encode OTLP export page: {}", e))?;
+                out.push(b'\n');
+            }
+        }
+        Ok(out)
+    }
+
+    /// Last line of an export cut short by the regex scan limit.
+    pub fn encode_truncated(&self, oldest_exported: Option<u64>) -> Vec<u8> {
+        let mut out = json!({
+            "error": "truncated",
+            "message": format!(
+                "regex search stopped after scanning {} rows, older matches were not exported",
+                MAX_REGEX_SCAN_ROWS
+            ),
+            "oldest_exported_time": oldest_exported,
+        })
+        .to_string()
+        .into_bytes();
+        out.push(b'\n');
+        out
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportLogRecord {
+    pub node: String,
+    pub service: String,
+    pub log: SystemLogRecord,
+}
+
+/// Walks a query from its end time backwards, one storage page at a time,
+/// so an export is not bounded by the `/query` pagination window.
+pub struct LogExportCursor {
+    storage: LogStorageRef,
+    request: LogQueryRequest,
+    page_size: usize,
+    remaining: Option<usize>,
+    end_time: Option<u64>,
+    oldest_exported: Option<u64>,
+    truncated: bool,
+    done: bool,
+}
+
+impl LogExportCursor {
+    /// `request.limit` caps the whole export, `None` exports every match.
+    pub fn new(storage: LogStorageRef, request: LogQueryRequest) -> Self {
+        Self::with_page_size(storage, request, EXPORT_PAGE_SIZE)
+    }
+
+    pub fn with_page_size(
+        storage: LogStorageRef,
+        mut request: LogQueryRequest,
+        page_size: usize,
+    ) -> Self {
+        let remaining = request.limit.take();
+        let end_time = request.end_time;
+        Self {
+            storage,
+            request,
+            page_size: page_size.max(1),
+            remaining,
+            end_time,
+            oldest_exported: None,
+            truncated: false,
+            done: remaining == Some(0),
+        }
+    }
+
+    /// Whether the regex scan limit stopped the export before every match
+    /// was read; final once `next_page` has returned `None`.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Time of the oldest record returned so far.
+    pub fn oldest_exported(&self) -> Option<u64> {
+        self.oldest_exported
+    }
+
+    /// Next page, newest first; `None` once the export is complete.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<ExportLogRecord>>, String> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let mut query = self.request.clone();
+        query.end_time = self.end_time;
+        query.limit = Some(self.page_size);
+        let (mut page, truncated) = self.fetch(query).await?;
+
+        if truncated {
+            // The scan stopped inside this page, the cursor has no position
+            // to continue from.
+            self.truncated = true;
+            self.done = true;
+        } else if page.len() < self.page_size {
+            self.done = true;
+        } else {
+            let newest = page.first().map(|r| r.log.time).unwrap_or(0);
+            let oldest = page.last().map(|r| r.log.time).unwrap_or(0);
+            if newest == oldest {
+                // One millisecond fills the page, read all of it at once so
+                // the cursor can move past it.
+                let mut query = self.request.clone();
+                query.start_time = Some(oldest);
+                query.end_time = Some(oldest);
+                query.limit = None;
+                let (same_time, truncated) = self.fetch(query).await?;
+                page = same_time;
+                if truncated {
+                    self.truncated = true;
+                    self.done = true;
+                } else {
+                    match oldest.checked_sub(1) {
+                        Some(next) if self.request.start_time.is_none_or(|start| next >= start) => {
+                            self.end_time = Some(next);
+                        }
+                        _ => self.done = true,
+                    }
+                }
+            } else {
+                // Records at `oldest` may continue past the page boundary and
+                // are read again by the next page.
+                page.retain(|r| r.log.time > oldest);
+                self.end_time = Some(oldest);
+            }
+        }
+
+        if let Some(remaining) = self.remaining.as_mut() {
+            page.truncate(*remaining);
+            *remaining -= page.len();
+            if *remaining == 0 {
+                self.done = true;
+            }
+        }
+
+        if let Some(oldest) = page.last() {
+            self.oldest_exported = Some(oldest.log.time);
+        }
+        if page.is_empty() && self.done {
+            return Ok(None);
+        }
+        Ok(Some(page))
+    }
+
+    /// Records newest first, and whether the regex scan limit cut them short.
+    async fn fetch(&self, query: LogQueryRequest) -> Result<(Vec<ExportLogRecord>, bool), String> {
+        let result = self.storage.search_logs(query).await?;
+        let mut records: Vec<ExportLogRecord> = result
+            .records
+            .into_iter()
+            .flat_map(|group| {
+                let node = group.node;
+                let service = group.service;
+                group.logs.into_iter().map(move |log| ExportLogRecord {
+                    node: node.clone(),
+                    service: service.clone(),
+                    log,
+                })
+            })
+            .collect();
+        records.sort_by(|left, right| {
+            right
+                .log
+                .time
+                .cmp(&left.log.time)
+                .then_with(|| left.node.cmp(&right.node))
+                .then_with(|| left.service.cmp(&right.service))
+        });
+        Ok((records, result.truncated))
+    }
+}
+
+/// OTLP `SeverityNumber`; `off` records carry no severity.
+fn otlp_severity(level: LogLevel) -> (u8, &'static str) {
+    match level {
+        LogLevel::Off => (0, ""),
+        LogLevel::Error => (17, "ERROR"),
+        LogLevel::Warn => (13, "WARN"),
+        LogLevel::Info => (9, "INFO"),
+        LogLevel::Debug => (5, "DEBUG"),
+        LogLevel::Trace => (1, "TRACE"),
+    }
+}
+
+fn otlp_string_attr(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// Ids that are not OTLP shaped (16/8 bytes hex) are kept as attributes.
+fn otlp_id(value: Option<&str>, hex_len: usize) -> Option<String> {
+    value
+        .filter(|v| v.len() == hex_len && v.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|v| v.to_ascii_lowercase())
+}
+
+fn otlp_log_record(log: &SystemLogRecord) -> Value {
+    // Stored times are unix millis; OTLP wants nanos as a decimal string.
+    let time_nanos = (log.time as u128 * 1_000_000).to_string();
+    let (severity_number, severity_text) = otlp_severity(log.level);
+
+    let mut attributes = vec![otlp_string_attr("log.target", &log.target)];
+    if let Some(file) = log.file.as_deref() {
+        attributes.push(otlp_string_attr("code.filepath", file));
+    }
+    if let Some(line) = log.line {
+        attributes.push(json!({ "key": "code.lineno", "value": { "intValue": line.to_string() } }));
+    }
+    let trace_id = otlp_id(log.trace_id.as_deref(), 32);
+    if trace_id.is_none()
+        && let Some(value) = log.trace_id.as_deref()
+    {
+        attributes.push(otlp_string_attr("trace_id", value));
+    }
+    let span_id = otlp_id(log.span_id.as_deref(), 16);
+    if span_id.is_none()
+        && let Some(value) = log.span_id.as_deref()
+    {
+        attributes.push(otlp_string_attr("span_id", value));
+    }
+    for (key, value) in log.fields.iter() {
+        attributes.push(otlp_string_attr(key, value));
+    }
+
+    let mut record = json!({
+        "timeUnixNano": time_nanos,
+        "observedTimeUnixNano": time_nanos,
+        "severityNumber": severity_number,
+        "body": { "stringValue": log.content },
+        "attributes": attributes,
+    });
+    if !severity_text.is_empty() {
+        record["severityText"] = json!(severity_text);
+    }
+    if let Some(trace_id) = trace_id {
+        record["traceId"] = json!(trace_id);
+    }
+    if let Some(span_id) = span_id {
+        record["spanId"] = json!(span_id);
+    }
+    record
+}
+
+/// One `ExportLogsServiceRequest`, a resource per node/service pair.
+fn otlp_logs_request(records: &[ExportLogRecord]) -> Value {
+    let mut resources: BTreeMap<(&str, &str), Vec<Value>> = BTreeMap::new();
+    for record in records {
+        resources
+            .entry((record.node.as_str(), record.service.as_str()))
+            .or_default()
+            .push(otlp_log_record(&record.log));
+    }
+
+    let resource_logs: Vec<Value> = resources
+        .into_iter()
+        .map(|((node, service), log_records)| {
+            json!({
+                "resource": {
+                    "attributes": [
+                        otlp_string_attr("host.name", node),
+                        otlp_string_attr("service.name", service),
+                    ]
+                },
+                "scopeLogs": [{
+                    "scope": { "name": OTLP_SCOPE_NAME },
+                    "logRecords": log_records,
+                }]
+            })
+        })
+        .collect();
+    json!({ "resourceLogs": resource_logs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{
+        LogRecords, LogSearchFilter, LogStorage, LogStorageType, create_log_storage_with_dir,
+    };
+    use std::sync::{Arc, Mutex};
+
+    /// Applies the time range and limit like the sqlite backends do.
+    struct VecStorage {
+        records: Vec<LogRecords>,
+        queries: Mutex<Vec<LogQueryRequest>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LogStorage for VecStorage {
+        async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String> {
+            Ok(records)
+        }
+
+        async fn query_logs(&self, request: LogQueryRequest) -> Result<Vec<LogRecords>, String> {
+            self.queries.lock().unwrap().push(request.clone());
+            let mut rows: Vec<(String, String, SystemLogRecord)> = self
+                .records
+                .iter()
+                .flat_map(|group| {
+                    group
+                        .logs
+                        .iter()
+                        .map(|log| (group.node.clone(), group.service.clone(), log.clone()))
+                })
+                .filter(|(_, _, log)| {
+                    request.start_time.is_none_or(|start| log.time >= start)
+                        && request.end_time.is_none_or(|end| log.time <= end)
+                })
+                .collect();
+            rows.sort_by_key(|row| std::cmp::Reverse(row.2.time));
+            if let Some(limit) = request.limit {
+                rows.truncate(limit);
+            }
+            Ok(rows
+                .into_iter()
+                .map(|(node, service, log)| LogRecords {
+                    node,
+                    service,
+                    batch_id: None,
+                    record_ids: vec![],
+                    logs: vec![log],
+                })
+                .collect())
+        }
+    }
+
+    fn log(time: u64, content: &str) -> SystemLogRecord {
+        SystemLogRecord {
+            level: LogLevel::Info,
+            target: "svc".to_string(),
+            time,
+            file: None,
+            line: None,
+            content: content.to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
+        }
+    }
+
+    fn storage_with(times: &[u64]) -> Arc<Box<dyn LogStorage>> {
+        Arc::new(Box::new(VecStorage {
+            records: vec![LogRecords {
+                node: "ood1".to_string(),
+                service: "svc".to_string(),
+                batch_id: None,
+                record_ids: vec![],
+                logs: times
+                    .iter()
+                    .enumerate()
+                    .map(|(i, time)| log(*time, &format!("r{}", i)))
+                    .collect(),
+            }],
+            queries: Mutex::new(Vec::new()),
+        }))
+    }
+
+    fn query(limit: Option<usize>) -> LogQueryRequest {
+        LogQueryRequest {
+            node: None,
+            service: None,
+            level: None,
+            start_time: None,
+            end_time: None,
+            limit,
+            search: None,
+        }
+    }
+
+    async fn drain(mut cursor: LogExportCursor) -> Vec<Vec<u64>> {
+        let mut pages = Vec::new();
+        while let Some(page) = cursor.next_page().await.unwrap() {
+            pages.push(page.iter().map(|r| r.log.time).collect());
+        }
+        pages
+    }
+
+    #[tokio::test]
+    async fn test_export_cursor_pages_through_every_record_once() {
+        // Page boundaries fall inside the 300 and 100 runs.
+        let times = [500, 400, 300, 300, 300, 200, 100, 100, 100, 100];
+        let pages = drain(LogExportCursor::with_page_size(
+            storage_with(&times),
+            query(None),
+            3,
+        ))
+        .await;
+        let exported: Vec<u64> = pages.iter().flatten().copied().collect();
+        assert_eq!(exported, times.to_vec());
+
+        let pages = drain(LogExportCursor::with_page_size(
+            storage_with(&times),
+            query(Some(4)),
+            3,
+        ))
+        .await;
+        assert_eq!(
+            pages.iter().flatten().copied().collect::<Vec<_>>(),
+            vec![500, 400, 300, 300]
+        );
+
+        assert!(
+            drain(LogExportCursor::new(storage_with(&[]), query(None)))
+                .await
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_cursor_reports_regex_scan_truncation() {
+        let storage_dir = std::env::temp_dir().join(format!(
+            "buckyos/slog_server_export_tests/{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&storage_dir);
+        let storage = create_log_storage_with_dir(LogStorageType::Sqlite, &storage_dir).unwrap();
+        // One match at each end of more rows than a regex search may scan.
+        let total = MAX_REGEX_SCAN_ROWS as u64 + 10;
+        let newest = 1_000 + total - 1;
+        storage
+            .append_logs(LogRecords {
+                node: "ood1".to_string(),
+                service: "svc".to_string(),
+                batch_id: None,
+                record_ids: vec![],
+                logs: (0..total)
+                    .map(|i| {
+                        let content = if i == 0 || i == total - 1 {
+                            "needle"
+                        } else {
+                            "hay"
+                        };
+                        log(1_000 + i, content)
+                    })
+                    .collect(),
+            })
+            .await
+            .unwrap();
+
+        let mut request = query(None);
+        request.search = Some(LogSearchFilter {
+            regex: Some("^needle$".to_string()),
+            ..Default::default()
+        });
+        let mut cursor = LogExportCursor::new(storage.clone(), request.clone());
+        let mut exported = Vec::new();
+        while let Some(page) = cursor.next_page().await.unwrap() {
+            exported.extend(page.iter().map(|r| r.log.time));
+        }
+        assert_eq!(exported, vec![newest]);
+        assert!(cursor.truncated());
+        let line: Value = serde_json::from_slice(
+            &LogExportFormat::Ndjson.encode_truncated(cursor.oldest_exported()),
+        )
+        .unwrap();
+        assert_eq!(line["error"], "truncated");
+        assert_eq!(line["oldest_exported_time"], newest);
+
+        // Narrowed to fit one scan, the same export is complete.
+        request.start_time = Some(newest - 100);
+        let mut cursor = LogExportCursor::new(storage, request);
+        while cursor.next_page().await.unwrap().is_some() {}
+        assert!(!cursor.truncated());
+
+        let _ = std::fs::remove_dir_all(&storage_dir);
+    }
+
+    #[test]
+    fn test_encode_page_as_ndjson_and_otlp() {
+        let mut error = log(1_700_000_000_123, "disk full");
+        error.level = LogLevel::Error;
+        error.line = Some(42);
+        error.trace_id = Some("4BF92F3577B34DA6A3CE929D0E0E4736".to_string());
+        error.span_id = Some("span-1".to_string());
+        error
+            .fields
+            .insert("peer".to_string(), "10.0.0.2".to_string());
+        let records = vec![
+            ExportLogRecord {
+                node: "ood1".to_string(),
+                service: "svc".to_string(),
+                log: error.clone(),
+            },
+            ExportLogRecord {
+                node: "ood2".to_string(),
+                service: "svc".to_string(),
+                log: log(1_700_000_000_000, "ok"),
+            },
+        ];
+
+        let ndjson = LogExportFormat::Ndjson.encode_page(&records).unwrap();
+        let lines: Vec<&str> = std::str::from_utf8(&ndjson).unwrap().lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first: ExportLogRecord = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first.node, "ood1");
+        assert_eq!(first.log.content, "disk full");
+        assert_eq!(first.log.fields, error.fields);
+
+        let otlp = LogExportFormat::Otlp.encode_page(&records).unwrap();
+        assert_eq!(otlp.iter().filter(|b| **b == b'\n').count(), 1);
+        let request: Value = serde_json::from_slice(&otlp).unwrap();
+        let resources = request["resourceLogs"].as_array().unwrap();
+        assert_eq!(resources.len(), 2);
+        assert_eq!(
+            resources[0]["resource"]["attributes"][0],
+            otlp_string_attr("host.name", "ood1")
+        );
+        assert_eq!(
+            resources[0]["resource"]["attributes"][1],
+            otlp_string_attr("service.name", "svc")
+        );
+        let record = &resources[0]["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["timeUnixNano"], "1700000000123000000");
+        assert_eq!(record["severityNumber"], 17);
+        assert_eq!(record["severityText"], "ERROR");
+        assert_eq!(record["body"]["stringValue"], "disk full");
+        assert_eq!(record["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert!(record.get("spanId").is_none());
+        let attributes = record["attributes"].as_array().unwrap();
+        assert!(attributes.contains(&otlp_string_attr("span_id", "span-1")));
+        assert!(attributes.contains(&otlp_string_attr("peer", "10.0.0.2")));
+        assert!(
+            attributes.contains(&json!({ "key": "code.lineno", "value": { "intValue": "42" } }))
+        );
+
+        assert!(LogExportFormat::Otlp.encode_page(&[]).unwrap().is_empty());
+    }
+}
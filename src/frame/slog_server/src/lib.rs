@@ -1,6 +1,10 @@
+pub mod alert;
+pub mod auth;
 pub mod config;
+pub mod export;
 pub mod server;
 pub mod storage;
+pub mod stream;
 
 #[macro_use]
 extern crate log;
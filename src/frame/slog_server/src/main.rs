@@ -1,20 +1,38 @@
 #![allow(dead_code)]
 
+mod alert;
+mod auth;
 mod config;
+mod export;
 mod server;
 mod storage;
+mod stream;
 
 #[macro_use]
 extern crate log;
 
+use crate::alert::{
+    AlertEngine, KEventAlertPublisher, SystemConfigRuleSource, open_alert_history, spawn_alert_task,
+};
+use crate::auth::{UploadAuthenticator, ZoneDeviceKeys};
 use crate::server::LogHttpServer;
-use crate::storage::create_log_storage_with_dir;
+use crate::storage::{create_log_storage_with_dir, spawn_retention_task};
+use buckyos_api::{
+    BuckyOSRuntimeType, get_buckyos_api_runtime, init_buckyos_api_runtime, set_buckyos_api_runtime,
+};
 use config::{
+    AuthConfig, RetentionConfig, SLOG_ALERT_RULES_REFRESH_SECS_ENV_KEY,
+    SLOG_RETENTION_CHECK_INTERVAL_SECS_ENV_KEY, SLOG_RETENTION_MAX_AGE_DAYS_ENV_KEY,
+    SLOG_RETENTION_MAX_TOTAL_SIZE_MB_ENV_KEY, SLOG_RETENTION_SERVICE_MAX_AGE_DAYS_ENV_KEY,
     SLOG_SERVER_BIND_ENV_KEY, SLOG_STORAGE_DIR_ENV_KEY, SLOG_STORAGE_PARTITION_BUCKET_ENV_KEY,
     SLOG_STORAGE_PARTITION_MAX_ROWS_ENV_KEY, SLOG_STORAGE_PARTITION_MAX_SIZE_MB_ENV_KEY,
-    SLOG_STORAGE_TYPE_ENV_KEY, ServerConfig, ServerEnvOverrides, StorageEngine,
+    SLOG_STORAGE_TYPE_ENV_KEY, SLOG_UPLOAD_AUTH_ENV_KEY, ServerConfig, ServerEnvOverrides,
+    StorageEngine,
 };
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use storage::PartitionBucket;
 
 pub const SERVICE_NAME: &str = "slog_server";
@@ -72,6 +90,44 @@ fn read_env_partition_bucket(env_key: &str) -> Option<PartitionBucket> {
     }
 }
 
+fn read_env_service_max_age_days(env_key: &str) -> Option<BTreeMap<String, u64>> {
+    let value = read_env_nonempty(env_key)?;
+    match RetentionConfig::parse_service_max_age_days(&value) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            warn!("ignore invalid env {}='{}': {}", env_key, value, e);
+            None
+        }
+    }
+}
+
+fn read_env_switch(env_key: &str) -> Option<bool> {
+    let value = read_env_nonempty(env_key)?;
+    match AuthConfig::parse_switch(&value) {
+        Some(v) => Some(v),
+        None => {
+            warn!(
+                "ignore invalid env {}='{}': expected on or off",
+                env_key, value
+            );
+            None
+        }
+    }
+}
+
+/// Log in to the zone; device keys, alert rules and alert events go through this runtime.
+async fn init_runtime() -> Result<(), String> {
+    let mut runtime =
+        init_buckyos_api_runtime(SERVICE_NAME, None, BuckyOSRuntimeType::KernelService)
+            .await
+            .map_err(|e| format!("init buckyos runtime failed: {}", e))?;
+    runtime
+        .login()
+        .await
+        .map_err(|e| format!("login to system failed: {}", e))?;
+    set_buckyos_api_runtime(runtime).map_err(|e| format!("register runtime failed: {}", e))
+}
+
 #[tokio::main]
 async fn main() {
     // First init logs output
@@ -112,23 +168,53 @@ async fn main() {
         partition_bucket: read_env_partition_bucket(SLOG_STORAGE_PARTITION_BUCKET_ENV_KEY),
         partition_max_rows: read_env_u64(SLOG_STORAGE_PARTITION_MAX_ROWS_ENV_KEY),
         partition_max_size_mb: read_env_u64(SLOG_STORAGE_PARTITION_MAX_SIZE_MB_ENV_KEY),
+        retention_max_age_days: read_env_u64(SLOG_RETENTION_MAX_AGE_DAYS_ENV_KEY),
+        retention_max_total_size_mb: read_env_u64(SLOG_RETENTION_MAX_TOTAL_SIZE_MB_ENV_KEY),
+        retention_service_max_age_days: read_env_service_max_age_days(
+            SLOG_RETENTION_SERVICE_MAX_AGE_DAYS_ENV_KEY,
+        ),
+        retention_check_interval_secs: read_env_u64(SLOG_RETENTION_CHECK_INTERVAL_SECS_ENV_KEY),
+        upload_auth: read_env_switch(SLOG_UPLOAD_AUTH_ENV_KEY),
+        alert_rules_refresh_secs: read_env_u64(SLOG_ALERT_RULES_REFRESH_SECS_ENV_KEY),
     };
     cfg.apply_env_overrides(env_overrides);
 
     let bind_addr = cfg.network.bind_addr;
     let storage_type = cfg.storage.to_storage_type();
     let storage_dir = cfg.storage.storage_dir;
+    let retention = cfg.storage.retention;
+    let upload_auth = cfg.auth.upload_auth;
+    let alert_cfg = cfg.alert;
 
     info!(
-        "slog_server config: bind_addr={}, storage_dir={}, storage_engine={}, partition_bucket={}, partition_max_rows={}, partition_max_size_mb={}",
+        "slog_server config: bind_addr={}, storage_dir={}, storage_engine={}, partition_bucket={}, partition_max_rows={}, partition_max_size_mb={}, retention_max_age_days={}, retention_max_total_size_mb={}, retention_service_max_age_days={:?}, retention_check_interval_secs={}",
         bind_addr,
         storage_dir.display(),
         cfg.storage.storage_engine.as_str(),
         cfg.storage.partition.bucket.as_str(),
         cfg.storage.partition.max_rows_per_partition,
-        cfg.storage.partition.max_partition_size_mb
+        cfg.storage.partition.max_partition_size_mb,
+        retention.max_age_days,
+        retention.max_total_size_mb,
+        retention.service_max_age_days,
+        retention.check_interval_secs
     );
 
+    let in_zone = match init_runtime().await {
+        Ok(()) => true,
+        Err(e) if upload_auth => {
+            error!(
+                "log upload authentication needs the zone runtime: {} (set {}=off to run without it)",
+                e, SLOG_UPLOAD_AUTH_ENV_KEY
+            );
+            return;
+        }
+        Err(e) => {
+            warn!("running outside a zone, alert rules are not loaded: {}", e);
+            false
+        }
+    };
+
     let storage = match create_log_storage_with_dir(storage_type, &storage_dir) {
         Ok(s) => s,
         Err(e) => {
@@ -137,7 +223,58 @@ async fn main() {
         }
     };
 
-    let server = LogHttpServer::new(storage);
+    let check_interval = Duration::from_secs(retention.check_interval_secs.max(1));
+    let _retention_task = spawn_retention_task(storage.clone(), check_interval);
+
+    let alert_history = match open_alert_history(&storage_dir) {
+        Ok(history) => history,
+        Err(e) => {
+            error!("failed to open alert history: {}", e);
+            return;
+        }
+    };
+    let mut alert_engine = AlertEngine::new(alert_history);
+    let mut rule_source = None;
+    if in_zone {
+        let runtime = get_buckyos_api_runtime().unwrap();
+        match runtime.get_kevent_client().await {
+            Ok(client) => {
+                alert_engine = alert_engine
+                    .with_publisher(Arc::new(KEventAlertPublisher::new(Arc::new(client))));
+            }
+            Err(e) => warn!("alert events are not published, kevent unavailable: {}", e),
+        }
+        match runtime.get_system_config_client().await {
+            Ok(client) => rule_source = Some(Arc::new(SystemConfigRuleSource::new(client))),
+            Err(e) => warn!(
+                "alert rules are not loaded, system config unavailable: {}",
+                e
+            ),
+        }
+    }
+    let alert_engine = Arc::new(alert_engine);
+    let _alert_task = rule_source.map(|source| {
+        info!(
+            "alerting: rules from system config every {}s",
+            alert_cfg.rules_refresh_secs
+        );
+        spawn_alert_task(
+            alert_engine.clone(),
+            source,
+            Duration::from_secs(alert_cfg.rules_refresh_secs.max(1)),
+        )
+    });
+
+    let mut server = LogHttpServer::new(storage).with_alert_engine(alert_engine);
+    if upload_auth {
+        info!("log upload authentication enabled: trusting the devices of the zone");
+        server = server.with_upload_auth(UploadAuthenticator::new(Arc::new(ZoneDeviceKeys::new())));
+    } else {
+        warn!(
+            "log upload authentication disabled by {}, any caller can upload logs",
+            SLOG_UPLOAD_AUTH_ENV_KEY
+        );
+    }
     info!("Starting slog server at http://{}", bind_addr);
     if let Err(e) = server.run(&bind_addr).await {
         error!("Log server exited with error: {}", e);
@@ -1,15 +1,48 @@
-use crate::storage::{LogQueryRequest, LogRecords, LogStorageRef};
+use crate::alert::{AlertEngine, AlertEvent, AlertHistoryQuery, AlertState};
+use crate::auth::UploadAuthenticator;
+use crate::export::{LogExportCursor, LogExportFormat};
+use crate::storage::{
+    LogAggregateBucket, LogAggregateDimension, LogAggregateRequest, LogHighlight, LogQueryRequest,
+    LogRecords, LogSearchFilter, LogSearchMatcher, LogStorageRef, RetentionStats,
+};
+use crate::stream::{
+    LogStreamEvent, LogStreamFilter, LogStreamHub, StreamLaggedEventData, StreamLogEventData,
+};
 use axum::{
     Json, Router,
+    body::{Body, Bytes},
     extract::Query,
-    http::StatusCode,
+    http::{
+        HeaderMap, HeaderValue, StatusCode,
+        header::{
+            ACCEPT_ENCODING, AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_TYPE,
+        },
+    },
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
     routing::{get, post},
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
 
 const DEFAULT_QUERY_LIMIT: usize = 200;
 const MAX_QUERY_LIMIT: usize = 2000;
 const MAX_QUERY_SCAN: usize = 20_000;
+const STREAM_KEEP_ALIVE_SECS: u64 = 15;
+const DEFAULT_AGGREGATE_BUCKET_SECS: u64 = 60;
+const DEFAULT_AGGREGATE_RANGE_MILLIS: u64 = 60 * 60 * 1000;
+// Lets polling consumers resume from the subscribe point even if no record arrived.
+const LOG_STREAM_SEQ_HEADER: &str = "x-log-stream-seq";
+/// Request content codings accepted on `/logs`, advertised through
+/// `Accept-Encoding` on every upload response (RFC 7694).
+const SUPPORTED_UPLOAD_ENCODINGS: &str = "zstd, gzip";
+const MAX_DECODED_UPLOAD_BYTES: u64 = 64 * 1024 * 1024;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LogResponseMessage {
@@ -33,6 +66,105 @@ pub struct LogQueryHttpRequest {
     pub offset: Option<usize>,
     #[serde(default)]
     pub limit: Option<usize>,
+    /// Case-insensitive substring match on the log content.
+    #[serde(default)]
+    pub keyword: Option<String>,
+    /// Regex matched against the log content.
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[serde(default)]
+    pub trace_id: Option<String>,
+    #[serde(default)]
+    pub span_id: Option<String>,
+    /// Exact matches on structured record fields. Query strings pass them as
+    /// `fields=key1:value1,key2:value2`.
+    #[serde(default, deserialize_with = "deserialize_field_filters")]
+    pub fields: BTreeMap<String, String>,
+}
+
+fn deserialize_field_filters<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FieldFilters {
+        Map(BTreeMap<String, String>),
+        Text(String),
+    }
+
+    match FieldFilters::deserialize(deserializer)? {
+        FieldFilters::Map(fields) => Ok(fields),
+        FieldFilters::Text(text) => text
+            .split(',')
+            .filter(|item| !item.trim().is_empty())
+            .map(|item| {
+                let (key, value) = item.split_once(':').ok_or_else(|| {
+                    serde::de::Error::custom(format!(
+                        "invalid field filter '{}': expected key:value",
+                        item
+                    ))
+                })?;
+                Ok((key.trim().to_string(), value.trim().to_string()))
+            })
+            .collect(),
+    }
+}
+
+/// Filter of `/export`; the same filters as `/query` but without pagination,
+/// `limit` caps the number of exported records.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LogExportHttpRequest {
+    #[serde(default)]
+    pub node: Option<String>,
+    #[serde(default)]
+    pub service: Option<String>,
+    #[serde(default)]
+    pub level: Option<String>,
+    #[serde(default)]
+    pub start_time: Option<u64>,
+    #[serde(default)]
+    pub end_time: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub keyword: Option<String>,
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[serde(default)]
+    pub trace_id: Option<String>,
+    #[serde(default)]
+    pub span_id: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_field_filters")]
+    pub fields: BTreeMap<String, String>,
+    /// `ndjson` (default) or `otlp`.
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+/// Filter of the live `/tail` stream. List values are comma separated.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LogTailHttpRequest {
+    #[serde(default)]
+    pub nodes: Option<String>,
+    #[serde(default)]
+    pub services: Option<String>,
+    /// Levels to include, e.g. `error,warn`.
+    #[serde(default)]
+    pub levels: Option<String>,
+    #[serde(default)]
+    pub keyword: Option<String>,
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[serde(default)]
+    pub trace_id: Option<String>,
+    #[serde(default)]
+    pub span_id: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_field_filters")]
+    pub fields: BTreeMap<String, String>,
+    /// Resume after this sequence; the `Last-Event-ID` header takes precedence.
+    #[serde(default)]
+    pub since_seq: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -40,6 +172,9 @@ pub struct QueryLogRecord {
     pub node: String,
     pub service: String,
     pub log: slog::SystemLogRecord,
+    /// Ranges of `log.content` matched by `keyword` / `regex`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<LogHighlight>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -50,6 +185,10 @@ pub struct QueryPageInfo {
     pub has_more: bool,
     pub next_offset: Option<usize>,
     pub sort: String,
+    /// A regex search hit its scan limit, so matches older than the
+    /// fetched records may be missing.
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -66,6 +205,90 @@ pub struct LogQueryResponseMessage {
     pub data: Option<LogQueryData>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerStatsData {
+    /// `None` when the storage backend does not enforce retention.
+    pub retention: Option<RetentionStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerStatsResponseMessage {
+    pub ret: i32,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<ServerStatsData>,
+}
+
+/// Filter of `/aggregate`. List values are comma separated.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LogAggregateHttpRequest {
+    /// Unix millis, defaults to one hour before `end_time`.
+    #[serde(default)]
+    pub start_time: Option<u64>,
+    /// Unix millis, defaults to now.
+    #[serde(default)]
+    pub end_time: Option<u64>,
+    #[serde(default)]
+    pub bucket_secs: Option<u64>,
+    /// Any of `level`, `service` and `node`.
+    #[serde(default)]
+    pub group_by: Option<String>,
+    #[serde(default)]
+    pub node: Option<String>,
+    #[serde(default)]
+    pub service: Option<String>,
+    #[serde(default)]
+    pub levels: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogAggregateData {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub bucket_secs: u64,
+    /// Sorted by time, buckets without records are left out.
+    pub buckets: Vec<LogAggregateBucket>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogAggregateResponseMessage {
+    pub ret: i32,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<LogAggregateData>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AlertHistoryHttpRequest {
+    #[serde(default)]
+    pub rule_id: Option<String>,
+    /// `firing` or `resolved`.
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub start_time: Option<u64>,
+    #[serde(default)]
+    pub end_time: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AlertQueryData {
+    /// Alerts firing right now.
+    pub active: Vec<AlertEvent>,
+    /// Matching transitions, newest first.
+    pub history: Vec<AlertEvent>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AlertQueryResponseMessage {
+    pub ret: i32,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<AlertQueryData>,
+}
+
 #[derive(Debug, Clone)]
 struct NormalizedLogQueryRequest {
     node: Option<String>,
@@ -75,15 +298,22 @@ struct NormalizedLogQueryRequest {
     end_time: Option<u64>,
     offset: usize,
     limit: usize,
+    search: Option<LogSearchFilter>,
+    matcher: LogSearchMatcher,
     fetch_limit: usize,
 }
 
 pub struct LogHttpServer {
     storage: LogStorageRef,
+    stream_hub: Arc<LogStreamHub>,
+    upload_auth: Option<Arc<UploadAuthenticator>>,
+    alerts: Option<Arc<AlertEngine>>,
 }
 
 async fn handle_append_logs(
     storage: LogStorageRef,
+    stream_hub: Arc<LogStreamHub>,
+    alerts: Option<Arc<AlertEngine>>,
     records: LogRecords,
 ) -> (StatusCode, Json<LogResponseMessage>) {
     info!(
@@ -93,14 +323,24 @@ async fn handle_append_logs(
         records.logs.len()
     );
 
+    // Only records new to storage are streamed and counted by alert rules, so
+    // a retried batch neither repeats tail events nor inflates alert counts.
     match storage.append_logs(records).await {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(LogResponseMessage {
-                ret: 0,
-                message: "Logs stored successfully".to_string(),
-            }),
-        ),
+        Ok(inserted) => {
+            if !inserted.logs.is_empty() {
+                stream_hub.publish(&inserted);
+                if let Some(alerts) = alerts.as_ref() {
+                    alerts.ingest(&inserted);
+                }
+            }
+            (
+                StatusCode::OK,
+                Json(LogResponseMessage {
+                    ret: 0,
+                    message: "Logs stored successfully".to_string(),
+                }),
+            )
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(LogResponseMessage {
@@ -111,6 +351,227 @@ async fn handle_append_logs(
     }
 }
 
+fn decode_upload_body(
+    content_encoding: Option<&str>,
+    body: &[u8],
+) -> Result<Vec<u8>, (StatusCode, String)> {
+    let encoding = content_encoding
+        .map(|v| v.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let mut decoded = Vec::new();
+    let result = match encoding.as_str() {
+        "" | "identity" => return Ok(body.to_vec()),
+        "gzip" | "x-gzip" => flate2::read::MultiGzDecoder::new(body)
+            .take(MAX_DECODED_UPLOAD_BYTES + 1)
+            .read_to_end(&mut decoded),
+        "zstd" => zstd::stream::read::Decoder::new(body).and_then(|decoder| {
+            decoder
+                .take(MAX_DECODED_UPLOAD_BYTES + 1)
+                .read_to_end(&mut decoded)
+        }),
+        other => {
+            return Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("unsupported content encoding '{}'", other),
+            ));
+        }
+    };
+
+    result.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("failed to decode {} body: {}", encoding, e),
+        )
+    })?;
+    if decoded.len() as u64 > MAX_DECODED_UPLOAD_BYTES {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("decoded body exceeds {} bytes", MAX_DECODED_UPLOAD_BYTES),
+        ));
+    }
+    Ok(decoded)
+}
+
+/// `/logs` entry: authenticate the raw body, decode it, check the batch
+/// belongs to the authenticated device, then store it.
+async fn handle_upload_logs(
+    storage: LogStorageRef,
+    stream_hub: Arc<LogStreamHub>,
+    alerts: Option<Arc<AlertEngine>>,
+    upload_auth: Option<Arc<UploadAuthenticator>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let reject = |status: StatusCode, message: String| {
+        warn!("Rejected log upload: {}", message);
+        (status, Json(LogResponseMessage { ret: 1, message }))
+    };
+
+    // Verify before decoding so unauthenticated callers cannot make us inflate anything.
+    let device = match upload_auth.as_ref() {
+        Some(auth) => {
+            let authorization = headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok());
+            match auth.authenticate(authorization, &body).await {
+                Ok(device) => Some(device),
+                Err(e) => return with_upload_encodings(reject(StatusCode::UNAUTHORIZED, e)),
+            }
+        }
+        None => None,
+    };
+
+    let content_encoding = headers.get(CONTENT_ENCODING).and_then(|v| v.to_str().ok());
+    let decoded = match decode_upload_body(content_encoding, &body) {
+        Ok(decoded) => decoded,
+        Err((status, e)) => return with_upload_encodings(reject(status, e)),
+    };
+    let records: LogRecords = match serde_json::from_slice(&decoded) {
+        Ok(records) => records,
+        Err(e) => {
+            return with_upload_encodings(reject(
+                StatusCode::BAD_REQUEST,
+                format!("invalid log records body: {}", e),
+            ));
+        }
+    };
+
+    if let Some(device) = device
+        && records.node != device
+    {
+        return with_upload_encodings(reject(
+            StatusCode::FORBIDDEN,
+            format!(
+                "device {} is not allowed to upload logs of node {}",
+                device, records.node
+            ),
+        ));
+    }
+
+    with_upload_encodings(handle_append_logs(storage, stream_hub, alerts, records).await)
+}
+
+fn with_upload_encodings(response: impl IntoResponse) -> Response {
+    let mut response = response.into_response();
+    response.headers_mut().insert(
+        ACCEPT_ENCODING,
+        HeaderValue::from_static(SUPPORTED_UPLOAD_ENCODINGS),
+    );
+    response
+}
+
+fn split_list_param(value: Option<String>) -> Vec<String> {
+    value
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn normalize_log_tail_request(request: LogTailHttpRequest) -> Result<LogStreamFilter, String> {
+    let mut levels = Vec::new();
+    for level in split_list_param(request.levels) {
+        if let Some(level) = parse_level_filter(Some(level))? {
+            levels.push(level);
+        }
+    }
+
+    let search = LogSearchFilter {
+        keyword: trim_optional_string(request.keyword),
+        regex: trim_optional_string(request.regex),
+        trace_id: trim_optional_string(request.trace_id),
+        span_id: trim_optional_string(request.span_id),
+        fields: request.fields,
+    };
+
+    Ok(LogStreamFilter {
+        nodes: split_list_param(request.nodes),
+        services: split_list_param(request.services),
+        levels,
+        search: (!search.is_empty()).then_some(search),
+    })
+}
+
+fn stream_event(event: LogStreamEvent) -> Event {
+    match event {
+        LogStreamEvent::Record { record, highlights } => {
+            let data = StreamLogEventData {
+                seq: record.seq,
+                node: record.node.clone(),
+                service: record.service.clone(),
+                log: record.log.clone(),
+                highlights,
+            };
+            Event::default()
+                .event("log")
+                .id(record.seq.to_string())
+                .json_data(data)
+                .unwrap_or_else(|e| Event::default().event("error").data(e.to_string()))
+        }
+        LogStreamEvent::Lagged { missed } => Event::default()
+            .event("lagged")
+            .json_data(StreamLaggedEventData { missed })
+            .unwrap_or_else(|e| Event::default().event("error").data(e.to_string())),
+    }
+}
+
+async fn handle_tail_logs(
+    stream_hub: Arc<LogStreamHub>,
+    request: LogTailHttpRequest,
+    headers: HeaderMap,
+) -> Response {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let since_seq = last_event_id.or(request.since_seq);
+
+    let filter = match normalize_log_tail_request(request) {
+        Ok(filter) => filter,
+        Err(e) => {
+            warn!("Rejected log tail request: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(LogResponseMessage {
+                    ret: 1,
+                    message: format!("Invalid tail request: {}", e),
+                }),
+            )
+                .into_response();
+        }
+    };
+    info!(
+        "Open log tail stream: nodes={:?}, services={:?}, levels={:?}, search={:?}, since_seq={:?}",
+        filter.nodes, filter.services, filter.levels, filter.search, since_seq
+    );
+
+    let subscription = match stream_hub.subscribe(filter, since_seq) {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(LogResponseMessage { ret: 1, message: e }),
+            )
+                .into_response();
+        }
+    };
+
+    let start_seq = subscription.start_seq();
+    let events = futures_util::stream::unfold(subscription, |mut subscription| async move {
+        let event = subscription.next().await?;
+        Some((Ok::<Event, Infallible>(stream_event(event)), subscription))
+    });
+    let mut response = Sse::new(events)
+        .keep_alive(KeepAlive::new().interval(Duration::from_secs(STREAM_KEEP_ALIVE_SECS)))
+        .into_response();
+    response
+        .headers_mut()
+        .insert(LOG_STREAM_SEQ_HEADER, start_seq.into());
+    response
+}
+
 fn trim_optional_string(value: Option<String>) -> Option<String> {
     value.and_then(|v| {
         let trimmed = v.trim();
@@ -186,6 +647,19 @@ fn normalize_log_query_request(
         .checked_add(1)
         .ok_or_else(|| "invalid pagination: fetch limit overflow".to_string())?;
 
+    let search = LogSearchFilter {
+        keyword: trim_optional_string(request.keyword),
+        regex: trim_optional_string(request.regex),
+        trace_id: trim_optional_string(request.trace_id),
+        span_id: trim_optional_string(request.span_id),
+        fields: request.fields,
+    };
+    let search = (!search.is_empty()).then_some(search);
+    let matcher = match search.as_ref() {
+        Some(search) => search.matcher()?,
+        None => LogSearchMatcher::default(),
+    };
+
     Ok(NormalizedLogQueryRequest {
         node,
         service,
@@ -194,6 +668,8 @@ fn normalize_log_query_request(
         end_time: request.end_time,
         offset,
         limit,
+        search,
+        matcher,
         fetch_limit,
     })
 }
@@ -208,6 +684,7 @@ fn flatten_query_result(records: Vec<LogRecords>) -> Vec<QueryLogRecord> {
                 node: node.clone(),
                 service: service.clone(),
                 log,
+                highlights: vec![],
             });
         }
     }
@@ -249,14 +726,15 @@ async fn handle_query_logs(
     };
 
     info!(
-        "Received log query request: node={:?}, service={:?}, level={:?}, start_time={:?}, end_time={:?}, offset={}, limit={}",
+        "Received log query request: node={:?}, service={:?}, level={:?}, start_time={:?}, end_time={:?}, offset={}, limit={}, search={:?}",
         normalized.node,
         normalized.service,
         normalized.level,
         normalized.start_time,
         normalized.end_time,
         normalized.offset,
-        normalized.limit
+        normalized.limit,
+        normalized.search
     );
 
     let query_request = LogQueryRequest {
@@ -266,9 +744,10 @@ async fn handle_query_logs(
         start_time: normalized.start_time,
         end_time: normalized.end_time,
         limit: Some(normalized.fetch_limit),
+        search: normalized.search.clone(),
     };
 
-    let queried = match storage.query_logs(query_request).await {
+    let queried = match storage.search_logs(query_request).await {
         Ok(v) => v,
         Err(e) => {
             return (
@@ -282,7 +761,7 @@ async fn handle_query_logs(
         }
     };
 
-    let mut records = flatten_query_result(queried);
+    let mut records = flatten_query_result(queried.records);
     stable_sort_query_records(&mut records);
 
     let fetched_len = records.len();
@@ -291,11 +770,14 @@ async fn handle_query_logs(
         .offset
         .saturating_add(normalized.limit)
         .min(fetched_len);
-    let page_records = if start < end {
+    let mut page_records = if start < end {
         records[start..end].to_vec()
     } else {
         Vec::new()
     };
+    for record in page_records.iter_mut() {
+        record.highlights = normalized.matcher.highlights(&record.log.content);
+    }
     let has_more = fetched_len > end;
     let next_offset = has_more.then_some(end);
 
@@ -313,62 +795,469 @@ async fn handle_query_logs(
                     has_more,
                     next_offset,
                     sort: "time_desc,node_asc,service_asc,level_asc,target_asc,file_asc,line_asc,content_asc".to_string(),
+                    truncated: queried.truncated,
                 },
             }),
         }),
     )
 }
 
-impl LogHttpServer {
-    pub fn new(storage: LogStorageRef) -> Self {
-        Self { storage }
+fn normalize_log_export_request(
+    request: LogExportHttpRequest,
+) -> Result<(LogQueryRequest, LogExportFormat), String> {
+    let format = match trim_optional_string(request.format) {
+        Some(raw) => LogExportFormat::parse(&raw)
+            .ok_or_else(|| format!("invalid format '{}': expected ndjson or otlp", raw))?,
+        None => LogExportFormat::default(),
+    };
+    let level = parse_level_filter(request.level)?;
+
+    if let (Some(start_time), Some(end_time)) = (request.start_time, request.end_time)
+        && start_time > end_time
+    {
+        return Err(format!(
+            "invalid time range: start_time {} is greater than end_time {}",
+            start_time, end_time
+        ));
+    }
+    if request.limit == Some(0) {
+        return Err("invalid limit: must be greater than 0".to_string());
     }
 
-    pub async fn run(&self, addr: &str) -> Result<(), String> {
-        let append_storage = self.storage.clone();
-        let query_get_storage = self.storage.clone();
-        let query_post_storage = self.storage.clone();
-        let app = Router::new()
-            .route(
-                "/logs",
-                post(move |log_records: Json<LogRecords>| {
-                    let storage = append_storage.clone();
-                    async move { handle_append_logs(storage, log_records.0).await }
+    let search = LogSearchFilter {
+        keyword: trim_optional_string(request.keyword),
+        regex: trim_optional_string(request.regex),
+        trace_id: trim_optional_string(request.trace_id),
+        span_id: trim_optional_string(request.span_id),
+        fields: request.fields,
+    };
+    let search = (!search.is_empty()).then_some(search);
+    if let Some(search) = search.as_ref() {
+        search.matcher()?;
+    }
+
+    Ok((
+        LogQueryRequest {
+            node: trim_optional_string(request.node),
+            service: trim_optional_string(request.service),
+            level,
+            start_time: request.start_time,
+            end_time: request.end_time,
+            limit: request.limit,
+            search,
+        },
+        format,
+    ))
+}
+
+async fn handle_export_logs(storage: LogStorageRef, request: LogExportHttpRequest) -> Response {
+    let (query, format) = match normalize_log_export_request(request) {
+        Ok(v) => v,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(LogResponseMessage {
+                    ret: 1,
+                    message: format!("Invalid export request: {}", e),
                 }),
             )
-            .route(
-                "/query",
-                get(move |request: Query<LogQueryHttpRequest>| {
-                    let storage = query_get_storage.clone();
-                    async move { handle_query_logs(storage, request.0).await }
-                })
-                .post(move |request: Json<LogQueryHttpRequest>| {
-                    let storage = query_post_storage.clone();
-                    async move { handle_query_logs(storage, request.0).await }
+                .into_response();
+        }
+    };
+    info!(
+        "Received log export request: format={}, node={:?}, service={:?}, level={:?}, start_time={:?}, end_time={:?}, limit={:?}, search={:?}",
+        format.as_str(),
+        query.node,
+        query.service,
+        query.level,
+        query.start_time,
+        query.end_time,
+        query.limit,
+        query.search
+    );
+
+    // The first page is read before answering so a failing storage still
+    // maps to a status code; later failures can only cut the stream short.
+    let mut cursor = LogExportCursor::new(storage, query);
+    let first_page = match cursor.next_page().await {
+        Ok(page) => page,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(LogResponseMessage {
+                    ret: 1,
+                    message: format!("Failed to export logs: {}", e),
                 }),
-            );
+            )
+                .into_response();
+        }
+    };
 
-        let listener = tokio::net::TcpListener::bind(addr).await.map_err(|e| {
-            let msg = format!("Failed to bind to address {}: {}", addr, e);
-            error!("{}", msg);
-            msg
-        })?;
+    let chunks = futures_util::stream::unfold(
+        (Some(cursor), first_page),
+        move |(cursor, pending)| async move {
+            let mut cursor = cursor?;
+            let page = match pending {
+                Some(page) => page,
+                None => match cursor.next_page().await {
+                    Ok(Some(page)) => page,
+                    Ok(None) if cursor.truncated() => {
+                        warn!("Log export truncated by the regex scan limit");
+                        let line = format.encode_truncated(cursor.oldest_exported());
+                        return Some((Ok(Bytes::from(line)), (None, None)));
+                    }
+                    Ok(None) => return None,
+                    Err(e) => {
+                        error!("Log export aborted: {}", e);
+                        return Some((Err(std::io::Error::other(e)), (None, None)));
+                    }
+                },
+            };
+            let chunk = format
+                .encode_page(&page)
+                .map(Bytes::from)
+                .map_err(std::io::Error::other);
+            Some((chunk, (Some(cursor), None)))
+        },
+    );
 
-        match axum::serve(listener, app).await {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                let msg = format!("HTTP server error: {}", e);
-                error!("{}", msg);
-                Err(msg)
-            }
+    let now_secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let disposition = format!(
+        "attachment; filename=\"slog-export-{}.{}\"",
+        now_secs,
+        format.file_extension()
+    );
+    (
+        [
+            (CONTENT_TYPE, format.content_type().to_string()),
+            (CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+fn normalize_log_aggregate_request(
+    request: LogAggregateHttpRequest,
+) -> Result<LogAggregateRequest, String> {
+    let end_time = request.end_time.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    });
+    let start_time = request
+        .start_time
+        .unwrap_or_else(|| end_time.saturating_sub(DEFAULT_AGGREGATE_RANGE_MILLIS));
+    let bucket_secs = request.bucket_secs.unwrap_or(DEFAULT_AGGREGATE_BUCKET_SECS);
+
+    let mut group_by = Vec::new();
+    for raw in split_list_param(request.group_by) {
+        let dimension = LogAggregateDimension::parse(&raw).ok_or_else(|| {
+            format!(
+                "invalid group_by '{}': expected level, service or node",
+                raw
+            )
+        })?;
+        if !group_by.contains(&dimension) {
+            group_by.push(dimension);
+        }
+    }
+    let mut levels = Vec::new();
+    for level in split_list_param(request.levels) {
+        if let Some(level) = parse_level_filter(Some(level))? {
+            levels.push(level);
         }
     }
+
+    let request = LogAggregateRequest {
+        start_time,
+        end_time,
+        bucket_millis: bucket_secs.saturating_mul(1000),
+        group_by,
+        node: trim_optional_string(request.node),
+        service: trim_optional_string(request.service),
+        levels,
+    };
+    request.validate()?;
+    Ok(request)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::storage::LogStorage;
+fn aggregate_error(
+    status: StatusCode,
+    message: String,
+) -> (StatusCode, Json<LogAggregateResponseMessage>) {
+    (
+        status,
+        Json(LogAggregateResponseMessage {
+            ret: 1,
+            message,
+            data: None,
+        }),
+    )
+}
+
+async fn handle_aggregate_logs(
+    storage: LogStorageRef,
+    request: LogAggregateHttpRequest,
+) -> (StatusCode, Json<LogAggregateResponseMessage>) {
+    let request = match normalize_log_aggregate_request(request) {
+        Ok(request) => request,
+        Err(e) => {
+            return aggregate_error(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid aggregate request: {}", e),
+            );
+        }
+    };
+    let (start_time, end_time) = (request.start_time, request.end_time);
+    let bucket_secs = request.bucket_millis / 1000;
+
+    match storage.aggregate_logs(request).await {
+        Ok(buckets) => (
+            StatusCode::OK,
+            Json(LogAggregateResponseMessage {
+                ret: 0,
+                message: "Logs aggregated successfully".to_string(),
+                data: Some(LogAggregateData {
+                    start_time,
+                    end_time,
+                    bucket_secs,
+                    buckets,
+                }),
+            }),
+        ),
+        Err(e) => aggregate_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to aggregate logs: {}", e),
+        ),
+    }
+}
+
+async fn handle_stats(storage: LogStorageRef) -> (StatusCode, Json<ServerStatsResponseMessage>) {
+    match storage.retention_stats().await {
+        Ok(retention) => (
+            StatusCode::OK,
+            Json(ServerStatsResponseMessage {
+                ret: 0,
+                message: "Stats queried successfully".to_string(),
+                data: Some(ServerStatsData { retention }),
+            }),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ServerStatsResponseMessage {
+                ret: 1,
+                message: format!("Failed to query stats: {}", e),
+                data: None,
+            }),
+        ),
+    }
+}
+
+fn alert_query_error(
+    status: StatusCode,
+    message: String,
+) -> (StatusCode, Json<AlertQueryResponseMessage>) {
+    (
+        status,
+        Json(AlertQueryResponseMessage {
+            ret: 1,
+            message,
+            data: None,
+        }),
+    )
+}
+
+async fn handle_query_alerts(
+    alerts: Option<Arc<AlertEngine>>,
+    request: AlertHistoryHttpRequest,
+) -> (StatusCode, Json<AlertQueryResponseMessage>) {
+    let Some(alerts) = alerts else {
+        return alert_query_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "alerting is not enabled".to_string(),
+        );
+    };
+
+    let state = match trim_optional_string(request.state) {
+        Some(raw) => match AlertState::parse(&raw) {
+            Some(state) => Some(state),
+            None => {
+                return alert_query_error(
+                    StatusCode::BAD_REQUEST,
+                    format!("invalid state '{}': expected firing or resolved", raw),
+                );
+            }
+        },
+        None => None,
+    };
+    let query = AlertHistoryQuery {
+        rule_id: trim_optional_string(request.rule_id),
+        state,
+        start_time: request.start_time,
+        end_time: request.end_time,
+        limit: request.limit,
+    };
+
+    match alerts.query_history(&query) {
+        Ok(history) => {
+            let active = alerts
+                .active_alerts()
+                .into_iter()
+                .filter(|alert| query.rule_id.as_ref().is_none_or(|id| *id == alert.rule_id))
+                .collect();
+            (
+                StatusCode::OK,
+                Json(AlertQueryResponseMessage {
+                    ret: 0,
+                    message: "Alerts queried successfully".to_string(),
+                    data: Some(AlertQueryData { active, history }),
+                }),
+            )
+        }
+        Err(e) => alert_query_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to query alerts: {}", e),
+        ),
+    }
+}
+
+impl LogHttpServer {
+    pub fn new(storage: LogStorageRef) -> Self {
+        Self {
+            storage,
+            stream_hub: Arc::new(LogStreamHub::default()),
+            upload_auth: None,
+            alerts: None,
+        }
+    }
+
+    /// Evaluate alert rules over every stored batch and serve `/alerts`.
+    pub fn with_alert_engine(mut self, alerts: Arc<AlertEngine>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    /// Require every `/logs` batch to be signed by a trusted device.
+    pub fn with_upload_auth(mut self, upload_auth: UploadAuthenticator) -> Self {
+        self.upload_auth = Some(Arc::new(upload_auth));
+        self
+    }
+
+    pub fn stream_hub(&self) -> Arc<LogStreamHub> {
+        self.stream_hub.clone()
+    }
+
+    pub async fn run(&self, addr: &str) -> Result<(), String> {
+        let append_storage = self.storage.clone();
+        let append_stream_hub = self.stream_hub.clone();
+        let append_upload_auth = self.upload_auth.clone();
+        let append_alerts = self.alerts.clone();
+        let query_alerts = self.alerts.clone();
+        let tail_stream_hub = self.stream_hub.clone();
+        let query_get_storage = self.storage.clone();
+        let query_post_storage = self.storage.clone();
+        let export_get_storage = self.storage.clone();
+        let export_post_storage = self.storage.clone();
+        let aggregate_get_storage = self.storage.clone();
+        let aggregate_post_storage = self.storage.clone();
+        let stats_storage = self.storage.clone();
+        let app = Router::new()
+            .route(
+                "/logs",
+                post(move |headers: HeaderMap, body: Bytes| {
+                    let storage = append_storage.clone();
+                    let stream_hub = append_stream_hub.clone();
+                    let alerts = append_alerts.clone();
+                    let upload_auth = append_upload_auth.clone();
+                    async move {
+                        handle_upload_logs(storage, stream_hub, alerts, upload_auth, headers, body)
+                            .await
+                    }
+                }),
+            )
+            .route(
+                "/tail",
+                get(
+                    move |request: Query<LogTailHttpRequest>, headers: HeaderMap| {
+                        let stream_hub = tail_stream_hub.clone();
+                        async move { handle_tail_logs(stream_hub, request.0, headers).await }
+                    },
+                ),
+            )
+            .route(
+                "/query",
+                get(move |request: Query<LogQueryHttpRequest>| {
+                    let storage = query_get_storage.clone();
+                    async move { handle_query_logs(storage, request.0).await }
+                })
+                .post(move |request: Json<LogQueryHttpRequest>| {
+                    let storage = query_post_storage.clone();
+                    async move { handle_query_logs(storage, request.0).await }
+                }),
+            )
+            .route(
+                "/export",
+                get(move |request: Query<LogExportHttpRequest>| {
+                    let storage = export_get_storage.clone();
+                    async move { handle_export_logs(storage, request.0).await }
+                })
+                .post(move |request: Json<LogExportHttpRequest>| {
+                    let storage = export_post_storage.clone();
+                    async move { handle_export_logs(storage, request.0).await }
+                }),
+            )
+            .route(
+                "/aggregate",
+                get(move |request: Query<LogAggregateHttpRequest>| {
+                    let storage = aggregate_get_storage.clone();
+                    async move { handle_aggregate_logs(storage, request.0).await }
+                })
+                .post(move |request: Json<LogAggregateHttpRequest>| {
+                    let storage = aggregate_post_storage.clone();
+                    async move { handle_aggregate_logs(storage, request.0).await }
+                }),
+            )
+            .route(
+                "/stats",
+                get(move || {
+                    let storage = stats_storage.clone();
+                    async move { handle_stats(storage).await }
+                }),
+            )
+            .route(
+                "/alerts",
+                get(move |request: Query<AlertHistoryHttpRequest>| {
+                    let alerts = query_alerts.clone();
+                    async move { handle_query_alerts(alerts, request.0).await }
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind(addr).await.map_err(|e| {
+            let msg = format!("Failed to bind to address {}: {}", addr, e);
+            error!("{}", msg);
+            msg
+        })?;
+
+        match axum::serve(listener, app).await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let msg = format!("HTTP server error: {}", e);
+                error!("{}", msg);
+                Err(msg)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{LogSearchResult, LogStorage, RetentionPolicy, RetentionRunReport};
     use slog::{LogLevel, SystemLogRecord};
     use std::sync::{Arc, Mutex};
 
@@ -376,18 +1265,23 @@ mod tests {
         append_result: Result<(), String>,
         query_result: Result<Vec<LogRecords>, String>,
         captured_query: Arc<Mutex<Option<LogQueryRequest>>>,
+        retention_result: Result<Option<RetentionStats>, String>,
     }
 
     #[async_trait::async_trait]
     impl LogStorage for MockStorage {
-        async fn append_logs(&self, _records: LogRecords) -> Result<(), String> {
-            self.append_result.clone()
+        async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String> {
+            self.append_result.clone().map(|_| records)
         }
 
         async fn query_logs(&self, request: LogQueryRequest) -> Result<Vec<LogRecords>, String> {
             *self.captured_query.lock().unwrap() = Some(request);
             self.query_result.clone()
         }
+
+        async fn retention_stats(&self) -> Result<Option<RetentionStats>, String> {
+            self.retention_result.clone()
+        }
     }
 
     fn test_log(time: u64, level: LogLevel, content: &str) -> SystemLogRecord {
@@ -398,6 +1292,9 @@ mod tests {
             file: Some("test.rs".to_string()),
             line: Some(1),
             content: content.to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
         }
     }
 
@@ -410,6 +1307,7 @@ mod tests {
             append_result,
             query_result,
             captured_query: captured_query.clone(),
+            retention_result: Ok(None),
         }));
         (storage, captured_query)
     }
@@ -419,6 +1317,8 @@ mod tests {
         let (storage, _) = make_storage(Ok(()), Ok(vec![]));
         let (status, body) = handle_append_logs(
             storage,
+            Arc::new(LogStreamHub::default()),
+            None,
             LogRecords {
                 node: "node-1".to_string(),
                 service: "svc-a".to_string(),
@@ -438,6 +1338,8 @@ mod tests {
         let (storage, _) = make_storage(Err("db write failed".to_string()), Ok(vec![]));
         let (status, body) = handle_append_logs(
             storage,
+            Arc::new(LogStreamHub::default()),
+            None,
             LogRecords {
                 node: "node-1".to_string(),
                 service: "svc-a".to_string(),
@@ -453,6 +1355,239 @@ mod tests {
         assert!(body.message.contains("Failed to store logs"));
     }
 
+    async fn next_sse_frame(body: &mut axum::body::BodyDataStream) -> String {
+        use futures_util::StreamExt;
+        let frame = tokio::time::timeout(Duration::from_secs(2), body.next())
+            .await
+            .expect("sse frame should arrive")
+            .expect("sse stream should stay open")
+            .unwrap();
+        String::from_utf8(frame.to_vec()).unwrap()
+    }
+
+    fn upload_body(node: &str) -> Vec<u8> {
+        serde_json::to_vec(&LogRecords {
+            node: node.to_string(),
+            service: "svc-a".to_string(),
+            batch_id: Some("batch-1".to_string()),
+            record_ids: vec!["r1".to_string()],
+            logs: vec![test_log(1, LogLevel::Info, "uploaded")],
+        })
+        .unwrap()
+    }
+
+    fn upload_headers(encoding: Option<&str>, authorization: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(encoding) = encoding {
+            headers.insert(CONTENT_ENCODING, encoding.parse().unwrap());
+        }
+        if let Some(authorization) = authorization {
+            headers.insert(AUTHORIZATION, authorization.parse().unwrap());
+        }
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_handle_upload_logs_decodes_gzip_and_zstd_bodies() {
+        use std::io::Write;
+
+        let json = upload_body("node-1");
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&json).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let zstd = zstd::stream::encode_all(json.as_slice(), 3).unwrap();
+
+        for (encoding, body) in [
+            (None, json.clone()),
+            (Some("gzip"), gzip),
+            (Some("zstd"), zstd),
+        ] {
+            let (storage, _) = make_storage(Ok(()), Ok(vec![]));
+            let stream_hub = Arc::new(LogStreamHub::default());
+            let response = handle_upload_logs(
+                storage,
+                stream_hub.clone(),
+                None,
+                None,
+                upload_headers(encoding, None),
+                Bytes::from(body),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::OK, "{:?}", encoding);
+            assert_eq!(
+                response.headers()[ACCEPT_ENCODING],
+                SUPPORTED_UPLOAD_ENCODINGS
+            );
+            assert_eq!(stream_hub.latest_seq(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_upload_logs_rejects_unsupported_or_corrupt_encoding() {
+        let (storage, _) = make_storage(Ok(()), Ok(vec![]));
+        let hub = Arc::new(LogStreamHub::default());
+        let response = handle_upload_logs(
+            storage.clone(),
+            hub.clone(),
+            None,
+            None,
+            upload_headers(Some("br"), None),
+            Bytes::from(upload_body("node-1")),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(
+            response.headers()[ACCEPT_ENCODING],
+            SUPPORTED_UPLOAD_ENCODINGS
+        );
+
+        let response = handle_upload_logs(
+            storage,
+            hub.clone(),
+            None,
+            None,
+            upload_headers(Some("zstd"), None),
+            Bytes::from(upload_body("node-1")),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(hub.latest_seq(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_handle_upload_logs_requires_token_of_batch_node() {
+        use crate::auth::tests::{sign_upload, test_authenticator};
+
+        let upload_auth = Some(Arc::new(test_authenticator("ood1")));
+        let hub = Arc::new(LogStreamHub::default());
+        let upload = |encoding: Option<&'static str>, token: Option<String>, body: Vec<u8>| {
+            let (storage, _) = make_storage(Ok(()), Ok(vec![]));
+            handle_upload_logs(
+                storage,
+                hub.clone(),
+                None,
+                upload_auth.clone(),
+                upload_headers(encoding, token.as_deref()),
+                Bytes::from(body),
+            )
+        };
+
+        let body = upload_body("ood1");
+        let response = upload(None, None, body.clone()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // A token signed for other bytes does not authorize this body.
+        let stale = sign_upload("ood1", b"other", 300);
+        let response = upload(None, Some(stale), body.clone()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let forged = upload_body("ood2");
+        let token = sign_upload("ood1", &forged, 300);
+        let response = upload(None, Some(token), forged).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(hub.latest_seq(), 0);
+
+        // The token signs the body as sent, i.e. after compression.
+        let compressed = zstd::stream::encode_all(body.as_slice(), 3).unwrap();
+        let token = sign_upload("ood1", &compressed, 300);
+        let response = upload(Some("zstd"), Some(token), compressed).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(hub.latest_seq(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_handle_tail_logs_streams_only_stored_and_matching_records() {
+        let stream_hub = Arc::new(LogStreamHub::default());
+        let batch = |service: &str, logs: Vec<SystemLogRecord>| LogRecords {
+            node: "node-1".to_string(),
+            service: service.to_string(),
+            batch_id: None,
+            record_ids: vec![],
+            logs,
+        };
+
+        let (failing, _) = make_storage(Err("db write failed".to_string()), Ok(vec![]));
+        let (status, _) = handle_append_logs(
+            failing,
+            stream_hub.clone(),
+            None,
+            batch("svc-a", vec![test_log(1, LogLevel::Error, "lost")]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(stream_hub.latest_seq(), 0);
+
+        let (storage, _) = make_storage(Ok(()), Ok(vec![]));
+        let (status, _) = handle_append_logs(
+            storage.clone(),
+            stream_hub.clone(),
+            None,
+            batch(
+                "svc-a",
+                vec![
+                    test_log(2, LogLevel::Info, "started"),
+                    test_log(3, LogLevel::Error, "disk failed"),
+                ],
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("last-event-id", "0".parse().unwrap());
+        let response = handle_tail_logs(
+            stream_hub.clone(),
+            LogTailHttpRequest {
+                services: Some("svc-a, svc-b".to_string()),
+                levels: Some("error,warn".to_string()),
+                since_seq: Some(100),
+                ..Default::default()
+            },
+            headers,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[LOG_STREAM_SEQ_HEADER], "2");
+        assert_eq!(stream_hub.subscriber_count(), 1);
+        let mut body = response.into_body().into_data_stream();
+
+        let frame = next_sse_frame(&mut body).await;
+        assert!(frame.contains("event: log"), "{}", frame);
+        assert!(frame.contains("id: 2"), "{}", frame);
+        assert!(frame.contains("disk failed"), "{}", frame);
+
+        let (status, _) = handle_append_logs(
+            storage,
+            stream_hub.clone(),
+            None,
+            batch("svc-b", vec![test_log(4, LogLevel::Warn, "slow peer")]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let frame = next_sse_frame(&mut body).await;
+        assert!(frame.contains("id: 3"), "{}", frame);
+        assert!(frame.contains("slow peer"), "{}", frame);
+
+        drop(body);
+        assert_eq!(stream_hub.subscriber_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_handle_tail_logs_rejects_invalid_filter() {
+        let stream_hub = Arc::new(LogStreamHub::default());
+        let response = handle_tail_logs(
+            stream_hub.clone(),
+            LogTailHttpRequest {
+                levels: Some("error,loud".to_string()),
+                ..Default::default()
+            },
+            HeaderMap::new(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(stream_hub.subscriber_count(), 0);
+    }
+
     #[tokio::test]
     async fn test_handle_query_logs_returns_paginated_stable_sorted_records() {
         let (storage, captured_query) = make_storage(
@@ -488,6 +1623,11 @@ mod tests {
                 end_time: Some(2000),
                 offset: Some(1),
                 limit: Some(2),
+                keyword: None,
+                regex: None,
+                trace_id: None,
+                span_id: None,
+                fields: BTreeMap::new(),
             },
         )
         .await;
@@ -506,6 +1646,7 @@ mod tests {
         assert_eq!(data.page.returned, 2);
         assert!(!data.page.has_more);
         assert_eq!(data.page.next_offset, None);
+        assert!(!data.page.truncated);
 
         let forwarded = captured_query
             .lock()
@@ -518,6 +1659,155 @@ mod tests {
         assert_eq!(forwarded.start_time, Some(100));
         assert_eq!(forwarded.end_time, Some(2000));
         assert_eq!(forwarded.limit, Some(4));
+        assert_eq!(forwarded.search, None);
+        assert!(
+            data.records
+                .iter()
+                .all(|record| record.highlights.is_empty())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_query_logs_forwards_search_and_highlights_matches() {
+        let (storage, captured_query) = make_storage(
+            Ok(()),
+            Ok(vec![LogRecords {
+                node: "node-a".to_string(),
+                service: "svc-a".to_string(),
+                batch_id: None,
+                record_ids: vec![],
+                logs: vec![test_log(1000, LogLevel::Error, "dial Timeout after 3s")],
+            }]),
+        );
+
+        let (status, body) = handle_query_logs(
+            storage,
+            LogQueryHttpRequest {
+                keyword: Some(" timeout ".to_string()),
+                regex: Some(r"\d+s".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let data = body.data.as_ref().expect("query data should exist");
+        assert_eq!(
+            data.records[0].highlights,
+            vec![
+                LogHighlight { start: 5, end: 12 },
+                LogHighlight { start: 19, end: 21 },
+            ]
+        );
+
+        let forwarded = captured_query.lock().unwrap().clone().unwrap();
+        assert_eq!(
+            forwarded.search,
+            Some(LogSearchFilter {
+                keyword: Some("timeout".to_string()),
+                regex: Some(r"\d+s".to_string()),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_query_logs_reports_truncated_regex_search() {
+        struct TruncatedStorage;
+
+        #[async_trait::async_trait]
+        impl LogStorage for TruncatedStorage {
+            async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String> {
+                Ok(records)
+            }
+
+            async fn query_logs(
+                &self,
+                _request: LogQueryRequest,
+            ) -> Result<Vec<LogRecords>, String> {
+                unreachable!("the query handler reads through search_logs")
+            }
+
+            async fn search_logs(
+                &self,
+                _request: LogQueryRequest,
+            ) -> Result<LogSearchResult, String> {
+                Ok(LogSearchResult {
+                    records: vec![LogRecords {
+                        node: "node-a".to_string(),
+                        service: "svc-a".to_string(),
+                        batch_id: None,
+                        record_ids: vec![],
+                        logs: vec![test_log(1000, LogLevel::Error, "code=7")],
+                    }],
+                    truncated: true,
+                })
+            }
+        }
+
+        let storage: LogStorageRef = Arc::new(Box::new(TruncatedStorage));
+        let (status, body) = handle_query_logs(
+            storage,
+            LogQueryHttpRequest {
+                regex: Some(r"code=\d+".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let data = body.data.as_ref().expect("query data should exist");
+        assert_eq!(data.records.len(), 1);
+        assert!(!data.page.has_more);
+        assert!(data.page.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_handle_query_logs_forwards_trace_and_field_filters() {
+        let (storage, captured_query) = make_storage(Ok(()), Ok(vec![]));
+        let uri: axum::http::Uri =
+            "/query?trace_id=%20trace-1%20&span_id=&fields=method:sys_config_get,%20peer:node-a"
+                .parse()
+                .unwrap();
+        let request = Query::<LogQueryHttpRequest>::try_from_uri(&uri).unwrap();
+        let (status, _) = handle_query_logs(storage, request.0).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let forwarded = captured_query.lock().unwrap().clone().unwrap();
+        assert_eq!(
+            forwarded.search,
+            Some(LogSearchFilter {
+                trace_id: Some("trace-1".to_string()),
+                fields: BTreeMap::from([
+                    ("method".to_string(), "sys_config_get".to_string()),
+                    ("peer".to_string(), "node-a".to_string()),
+                ]),
+                ..Default::default()
+            })
+        );
+
+        let request: LogQueryHttpRequest =
+            serde_json::from_str(r#"{"fields":{"method":"sys_config_get"}}"#).unwrap();
+        assert_eq!(request.fields.len(), 1);
+        let uri: axum::http::Uri = "/query?fields=method".parse().unwrap();
+        assert!(Query::<LogQueryHttpRequest>::try_from_uri(&uri).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handle_query_logs_rejects_invalid_regex() {
+        let (storage, captured_query) = make_storage(Ok(()), Ok(vec![]));
+        let (status, body) = handle_query_logs(
+            storage,
+            LogQueryHttpRequest {
+                regex: Some("(unclosed".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.message.contains("invalid regex"));
+        assert!(captured_query.lock().unwrap().is_none());
     }
 
     #[tokio::test]
@@ -564,4 +1854,416 @@ mod tests {
         assert_eq!(body.ret, 1);
         assert!(body.message.contains("Failed to query logs"));
     }
+
+    #[tokio::test]
+    async fn test_handle_export_logs_streams_selected_format() {
+        let (storage, captured_query) = make_storage(
+            Ok(()),
+            Ok(vec![LogRecords {
+                node: "node-a".to_string(),
+                service: "svc-a".to_string(),
+                batch_id: None,
+                record_ids: vec![],
+                logs: vec![
+                    test_log(900, LogLevel::Info, "older"),
+                    test_log(1000, LogLevel::Error, "newer"),
+                ],
+            }]),
+        );
+
+        let response = handle_export_logs(
+            storage.clone(),
+            LogExportHttpRequest {
+                service: Some(" svc-a ".to_string()),
+                keyword: Some("er".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "application/x-ndjson"
+        );
+        assert!(
+            response
+                .headers()
+                .get(CONTENT_DISPOSITION)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .ends_with(".ndjson\"")
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let lines: Vec<serde_json::Value> = body
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["log"]["content"], "newer");
+        assert_eq!(lines[1]["node"], "node-a");
+        let captured = captured_query.lock().unwrap().clone().unwrap();
+        assert_eq!(captured.service.as_deref(), Some("svc-a"));
+        assert_eq!(captured.search.unwrap().keyword.as_deref(), Some("er"));
+
+        let response = handle_export_logs(
+            storage.clone(),
+            LogExportHttpRequest {
+                format: Some("otlp".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            request["resourceLogs"][0]["scopeLogs"][0]["logRecords"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        for request in [
+            LogExportHttpRequest {
+                format: Some("csv".to_string()),
+                ..Default::default()
+            },
+            LogExportHttpRequest {
+                limit: Some(0),
+                ..Default::default()
+            },
+            LogExportHttpRequest {
+                regex: Some("(".to_string()),
+                ..Default::default()
+            },
+        ] {
+            let response = handle_export_logs(storage.clone(), request).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let (storage, _) = make_storage(Ok(()), Err("db locked".to_string()));
+        let response = handle_export_logs(storage, LogExportHttpRequest::default()).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_handle_export_logs_ends_truncated_regex_export_with_error_line() {
+        struct TruncatedStorage;
+
+        #[async_trait::async_trait]
+        impl LogStorage for TruncatedStorage {
+            async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String> {
+                Ok(records)
+            }
+
+            async fn query_logs(
+                &self,
+                _request: LogQueryRequest,
+            ) -> Result<Vec<LogRecords>, String> {
+                unreachable!("the export cursor reads through search_logs")
+            }
+
+            async fn search_logs(
+                &self,
+                _request: LogQueryRequest,
+            ) -> Result<LogSearchResult, String> {
+                Ok(LogSearchResult {
+                    records: vec![LogRecords {
+                        node: "node-a".to_string(),
+                        service: "svc-a".to_string(),
+                        batch_id: None,
+                        record_ids: vec![],
+                        logs: vec![test_log(1000, LogLevel::Error, "code=7")],
+                    }],
+                    truncated: true,
+                })
+            }
+        }
+
+        let storage: LogStorageRef = Arc::new(Box::new(TruncatedStorage));
+        let response = handle_export_logs(
+            storage,
+            LogExportHttpRequest {
+                regex: Some(r"code=\d+".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let lines: Vec<serde_json::Value> = body
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["log"]["content"], "code=7");
+        assert_eq!(lines[1]["error"], "truncated");
+        assert_eq!(lines[1]["oldest_exported_time"], 1000);
+    }
+
+    #[tokio::test]
+    async fn test_handle_stats_reports_retention_state() {
+        let (storage, _) = make_storage(Ok(()), Ok(vec![]));
+        let (status, body) = handle_stats(storage).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.ret, 0);
+        assert!(body.data.as_ref().unwrap().retention.is_none());
+
+        let retention = RetentionStats {
+            policy: RetentionPolicy {
+                max_age_secs: 86_400,
+                ..Default::default()
+            },
+            partition_count: 2,
+            total_bytes: 8192,
+            dropped_partitions_total: 1,
+            last_run: Some(RetentionRunReport {
+                finished_at: 1_721_000_000,
+                expired_partitions: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let storage: LogStorageRef = Arc::new(Box::new(MockStorage {
+            append_result: Ok(()),
+            query_result: Ok(vec![]),
+            captured_query: Arc::new(Mutex::new(None)),
+            retention_result: Ok(Some(retention.clone())),
+        }));
+        let (status, body) = handle_stats(storage).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.data.as_ref().unwrap().retention, Some(retention));
+
+        let storage: LogStorageRef = Arc::new(Box::new(MockStorage {
+            append_result: Ok(()),
+            query_result: Ok(vec![]),
+            captured_query: Arc::new(Mutex::new(None)),
+            retention_result: Err("manifest locked".to_string()),
+        }));
+        let (status, body) = handle_stats(storage).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body.ret, 1);
+        assert!(body.message.contains("Failed to query stats"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_query_alerts_reports_alerts_fired_by_appended_logs() {
+        let (status, body) = handle_query_alerts(None, AlertHistoryHttpRequest::default()).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body.ret, 1);
+
+        let engine = Arc::new(AlertEngine::new(
+            crate::alert::AlertHistory::open_in_memory().unwrap(),
+        ));
+        engine
+            .set_rules(
+                crate::alert::AlertRule::parse_list(
+                    r#"[{"id":"svc_errors","service":"svc-a","kind":"records","level":"error","window_secs":60}]"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let (storage, _) = make_storage(Ok(()), Ok(vec![]));
+        let (status, _) = handle_append_logs(
+            storage,
+            Arc::new(LogStreamHub::default()),
+            Some(engine.clone()),
+            LogRecords {
+                node: "node-1".to_string(),
+                service: "svc-a".to_string(),
+                batch_id: None,
+                record_ids: vec![],
+                logs: vec![test_log(1, LogLevel::Error, "disk full")],
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) =
+            handle_query_alerts(Some(engine.clone()), AlertHistoryHttpRequest::default()).await;
+        assert_eq!(status, StatusCode::OK);
+        let data = body.data.clone().unwrap();
+        assert_eq!(data.active.len(), 1);
+        assert_eq!(data.active[0].rule_id, "svc_errors");
+        assert_eq!(data.active[0].sample.as_deref(), Some("disk full"));
+        assert_eq!(data.history.len(), 1);
+        assert_eq!(data.history[0].state, AlertState::Firing);
+
+        let (status, body) = handle_query_alerts(
+            Some(engine),
+            AlertHistoryHttpRequest {
+                state: Some("pending".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.message.contains("invalid state"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_append_logs_feeds_only_new_records_to_alerts_and_stream() {
+        let storage_dir = std::env::temp_dir().join(format!(
+            "buckyos/slog_server_append_dedup_tests/{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&storage_dir);
+        let storage = crate::storage::create_log_storage_with_dir(
+            crate::storage::LogStorageType::SqlitePartitioned(Default::default()),
+            &storage_dir,
+        )
+        .unwrap();
+        let stream_hub = Arc::new(LogStreamHub::default());
+        let engine = Arc::new(AlertEngine::new(
+            crate::alert::AlertHistory::open_in_memory().unwrap(),
+        ));
+        engine
+            .set_rules(
+                crate::alert::AlertRule::parse_list(
+                    r#"[{"id":"svc_errors","service":"svc-a","kind":"records","level":"error","threshold":2,"window_secs":600}]"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let batch = LogRecords {
+            node: "node-1".to_string(),
+            service: "svc-a".to_string(),
+            batch_id: Some("batch-1".to_string()),
+            record_ids: vec!["rid-1".to_string()],
+            logs: vec![test_log(now, LogLevel::Error, "disk full")],
+        };
+        // The daemon retries a batch whose response it did not receive.
+        for _ in 0..2 {
+            let (status, _) = handle_append_logs(
+                storage.clone(),
+                stream_hub.clone(),
+                Some(engine.clone()),
+                batch.clone(),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        assert_eq!(stream_hub.latest_seq(), 1);
+        assert!(engine.active_alerts().is_empty());
+
+        let _ = std::fs::remove_dir_all(&storage_dir);
+    }
+
+    #[tokio::test]
+    async fn test_handle_aggregate_logs_counts_buckets_from_storage() {
+        let storage_dir = std::env::temp_dir().join(format!(
+            "buckyos/slog_server_aggregate_tests/{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&storage_dir);
+        let storage = crate::storage::create_log_storage_with_dir(
+            crate::storage::LogStorageType::SqlitePartitioned(Default::default()),
+            &storage_dir,
+        )
+        .unwrap();
+
+        let base = 1_722_000_000_000;
+        for (service, logs) in [
+            (
+                "svc-a",
+                vec![
+                    test_log(base + 1_000, LogLevel::Error, "a1"),
+                    test_log(base + 2_000, LogLevel::Error, "a2"),
+                    test_log(base + 61_000, LogLevel::Info, "a3"),
+                ],
+            ),
+            (
+                "svc-b",
+                vec![test_log(base + 62_000, LogLevel::Error, "b1")],
+            ),
+        ] {
+            storage
+                .append_logs(LogRecords {
+                    node: "node-1".to_string(),
+                    service: service.to_string(),
+                    batch_id: None,
+                    record_ids: vec![],
+                    logs,
+                })
+                .await
+                .unwrap();
+        }
+
+        let (status, body) = handle_aggregate_logs(
+            storage.clone(),
+            LogAggregateHttpRequest {
+                start_time: Some(base),
+                end_time: Some(base + 119_999),
+                group_by: Some("level, service, level".to_string()),
+                levels: Some("error,warn".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let data = body.data.clone().unwrap();
+        assert_eq!(data.bucket_secs, 60);
+        let buckets = data
+            .buckets
+            .iter()
+            .map(|b| {
+                (
+                    b.time,
+                    b.level,
+                    b.service.as_deref(),
+                    b.node.as_deref(),
+                    b.count,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            buckets,
+            vec![
+                (base, Some(LogLevel::Error), Some("svc-a"), None, 2),
+                (base + 60_000, Some(LogLevel::Error), Some("svc-b"), None, 1),
+            ]
+        );
+
+        let (status, body) = handle_aggregate_logs(
+            storage.clone(),
+            LogAggregateHttpRequest {
+                group_by: Some("host".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.message.contains("invalid group_by"));
+
+        let (status, _) = handle_aggregate_logs(
+            storage,
+            LogAggregateHttpRequest {
+                start_time: Some(0),
+                end_time: Some(base),
+                bucket_secs: Some(1),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let _ = std::fs::remove_dir_all(&storage_dir);
+    }
 }
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+/// Max time buckets one aggregate request may span.
+pub const MAX_AGGREGATE_BUCKETS: u64 = 10_000;
+/// Granularity of the per-partition `log_counts` rollup, matching its triggers.
+pub(crate) const ROLLUP_BUCKET_MILLIS: u64 = 60 * 1000;
+
+/// Dimension a bucket count can be split by, besides time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogAggregateDimension {
+    Level,
+    Service,
+    Node,
+}
+
+impl LogAggregateDimension {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "level" => Some(Self::Level),
+            "service" => Some(Self::Service),
+            "node" => Some(Self::Node),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Level => "level",
+            Self::Service => "service",
+            Self::Node => "node",
+        }
+    }
+}
+
+/// Count records per time bucket over `[start_time, end_time]`, both in unix
+/// millis. Buckets are aligned to multiples of `bucket_millis` since the epoch,
+/// so the first one may start before `start_time`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogAggregateRequest {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub bucket_millis: u64,
+    pub group_by: Vec<LogAggregateDimension>,
+    pub node: Option<String>,
+    pub service: Option<String>,
+    /// Only count these levels, all levels when empty.
+    pub levels: Vec<slog::LogLevel>,
+}
+
+impl LogAggregateRequest {
+    pub fn groups_by(&self, dimension: LogAggregateDimension) -> bool {
+        self.group_by.contains(&dimension)
+    }
+
+    pub fn bucket_time(&self, timestamp: u64) -> u64 {
+        timestamp - timestamp % self.bucket_millis
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.bucket_millis == 0 {
+            return Err("bucket size must be greater than 0".to_string());
+        }
+        if self.start_time > self.end_time {
+            return Err(format!(
+                "start_time {} is after end_time {}",
+                self.start_time, self.end_time
+            ));
+        }
+        let buckets = (self.bucket_time(self.end_time) - self.bucket_time(self.start_time))
+            / self.bucket_millis
+            + 1;
+        if buckets > MAX_AGGREGATE_BUCKETS {
+            return Err(format!(
+                "time range spans {} buckets, max is {}",
+                buckets, MAX_AGGREGATE_BUCKETS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Count of one time bucket; dimensions not grouped by are left out.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogAggregateBucket {
+    /// Bucket start, unix millis.
+    pub time: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<slog::LogLevel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    pub count: u64,
+}
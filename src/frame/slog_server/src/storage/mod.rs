@@ -1,7 +1,13 @@
+mod aggregate;
+mod retention;
+mod search;
 mod sqlite;
 mod sqlite_partitioned;
 mod storage;
 
+pub use aggregate::*;
+pub use retention::*;
+pub use search::*;
 pub use sqlite_partitioned::{PartitionBucket, SqlitePartitionedConfig};
 pub use storage::*;
 // pub use sqlite::*;
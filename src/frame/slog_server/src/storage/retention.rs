@@ -0,0 +1,92 @@
+use super::storage::LogStorageRef;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Retention policy applied to whole partitions.
+///
+/// A value of `0` disables the corresponding limit. Service overrides only
+/// affect the age limit: partitions are shared by every service, so a
+/// partition expires once it is older than the longest age any service in it
+/// asks for.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub max_age_secs: u64,
+    pub max_total_bytes: u64,
+    #[serde(default)]
+    pub service_max_age_secs: BTreeMap<String, u64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_secs > 0
+            || self.max_total_bytes > 0
+            || self.service_max_age_secs.values().any(|v| *v > 0)
+    }
+
+    /// Effective age limit for one service, `None` meaning keep forever.
+    pub fn service_max_age_secs(&self, service: &str) -> Option<u64> {
+        let secs = self
+            .service_max_age_secs
+            .get(service)
+            .copied()
+            .unwrap_or(self.max_age_secs);
+        (secs > 0).then_some(secs)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionRunReport {
+    pub finished_at: u64,
+    pub expired_partitions: u64,
+    pub evicted_partitions: u64,
+    pub removed_batch_mappings: u64,
+    pub freed_bytes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionStats {
+    pub policy: RetentionPolicy,
+    pub partition_count: u64,
+    pub total_bytes: u64,
+    pub oldest_log_time: Option<u64>,
+    pub newest_log_time: Option<u64>,
+    pub dropped_partitions_total: u64,
+    pub freed_bytes_total: u64,
+    pub last_run: Option<RetentionRunReport>,
+}
+
+/// Periodically enforce the storage retention policy until the storage
+/// reports it has nothing to enforce.
+pub fn spawn_retention_task(
+    storage: LogStorageRef,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match storage.enforce_retention().await {
+                Ok(Some(report)) => {
+                    if report.expired_partitions > 0 || report.evicted_partitions > 0 {
+                        info!(
+                            "retention dropped partitions: expired={}, evicted={}, removed_batch_mappings={}, freed_bytes={}",
+                            report.expired_partitions,
+                            report.evicted_partitions,
+                            report.removed_batch_mappings,
+                            report.freed_bytes
+                        );
+                    }
+                }
+                Ok(None) => {
+                    info!("retention is disabled for current storage, stop retention task");
+                    return;
+                }
+                Err(e) => warn!("retention run failed: {}", e),
+            }
+        }
+    })
+}
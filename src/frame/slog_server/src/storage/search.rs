@@ -0,0 +1,238 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Max length of a user supplied search regex.
+pub const MAX_SEARCH_REGEX_LEN: usize = 512;
+/// Max rows one query may scan while evaluating a regex filter.
+pub const MAX_REGEX_SCAN_ROWS: usize = 200_000;
+/// Keywords shorter than this cannot use the trigram index.
+pub(crate) const MIN_FTS_KEYWORD_CHARS: usize = 3;
+const SEARCH_REGEX_SIZE_LIMIT: usize = 1 << 20;
+const MAX_HIGHLIGHTS_PER_RECORD: usize = 32;
+
+/// Content and structured-field search applied on top of the basic filters.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogSearchFilter {
+    /// Case-insensitive substring match on the record content.
+    pub keyword: Option<String>,
+    /// Regex matched against the record content.
+    pub regex: Option<String>,
+    pub trace_id: Option<String>,
+    pub span_id: Option<String>,
+    /// Exact matches on record fields, all of which must hold.
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Byte range `[start, end)` of a match inside the record content.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogHighlight {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Compiled form of a [`LogSearchFilter`].
+#[derive(Clone, Debug, Default)]
+pub struct LogSearchMatcher {
+    keyword: Option<Regex>,
+    regex: Option<Regex>,
+}
+
+fn build_regex(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .size_limit(SEARCH_REGEX_SIZE_LIMIT)
+        .dfa_size_limit(SEARCH_REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| format!("invalid regex '{}': {}", pattern, e))
+}
+
+impl LogSearchFilter {
+    pub fn is_empty(&self) -> bool {
+        self.keyword.is_none()
+            && self.regex.is_none()
+            && self.trace_id.is_none()
+            && self.span_id.is_none()
+            && self.fields.is_empty()
+    }
+
+    pub fn matcher(&self) -> Result<LogSearchMatcher, String> {
+        let keyword = match self.keyword.as_deref() {
+            Some(keyword) => Some(build_regex(&format!("(?i){}", regex::escape(keyword)))?),
+            None => None,
+        };
+        let regex = match self.regex.as_deref() {
+            Some(pattern) if pattern.len() > MAX_SEARCH_REGEX_LEN => {
+                return Err(format!(
+                    "invalid regex: pattern length {} exceeds {}",
+                    pattern.len(),
+                    MAX_SEARCH_REGEX_LEN
+                ));
+            }
+            Some(pattern) => Some(build_regex(pattern)?),
+            None => None,
+        };
+        Ok(LogSearchMatcher { keyword, regex })
+    }
+
+    /// Whether the trace ids and fields of `record` satisfy the filter.
+    pub fn matches_structured(&self, record: &slog::SystemLogRecord) -> bool {
+        self.trace_id
+            .as_ref()
+            .is_none_or(|id| record.trace_id.as_ref() == Some(id))
+            && self
+                .span_id
+                .as_ref()
+                .is_none_or(|id| record.span_id.as_ref() == Some(id))
+            && self
+                .fields
+                .iter()
+                .all(|(key, value)| record.fields.get(key) == Some(value))
+    }
+
+    /// FTS5 query matching the keyword as one literal phrase.
+    pub(crate) fn fts_phrase(keyword: &str) -> String {
+        format!("\"{}\"", keyword.replace('"', "\"\""))
+    }
+
+    /// `LIKE` pattern matching the keyword literally, escaped with `\`.
+    pub(crate) fn like_pattern(keyword: &str) -> String {
+        let mut pattern = String::with_capacity(keyword.len() + 2);
+        pattern.push('%');
+        for ch in keyword.chars() {
+            if matches!(ch, '%' | '_' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(ch);
+        }
+        pattern.push('%');
+        pattern
+    }
+}
+
+impl LogSearchMatcher {
+    pub fn has_regex(&self) -> bool {
+        self.regex.is_some()
+    }
+
+    pub fn matches_regex(&self, content: &str) -> bool {
+        self.regex.as_ref().is_none_or(|re| re.is_match(content))
+    }
+
+    /// Keyword and regex match, for records that never went through SQL.
+    pub fn matches_content(&self, content: &str) -> bool {
+        self.keyword.as_ref().is_none_or(|re| re.is_match(content)) && self.matches_regex(content)
+    }
+
+    /// Sorted, merged match ranges of the keyword and the regex in `content`.
+    pub fn highlights(&self, content: &str) -> Vec<LogHighlight> {
+        let mut ranges: Vec<LogHighlight> = self
+            .keyword
+            .iter()
+            .chain(self.regex.iter())
+            .flat_map(|re| re.find_iter(content))
+            .filter(|m| m.start() < m.end())
+            .map(|m| LogHighlight {
+                start: m.start(),
+                end: m.end(),
+            })
+            .collect();
+        ranges.sort_by_key(|range| (range.start, range.end));
+
+        let mut merged: Vec<LogHighlight> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged.truncate(MAX_HIGHLIGHTS_PER_RECORD);
+        merged
+    }
+}
+
+/// Rows a regex search may still scan, shared by every table one query reads.
+#[derive(Debug)]
+pub(crate) struct RegexScanBudget {
+    remaining: usize,
+    exhausted: bool,
+}
+
+impl RegexScanBudget {
+    pub(crate) fn new() -> Self {
+        Self {
+            remaining: MAX_REGEX_SCAN_ROWS,
+            exhausted: false,
+        }
+    }
+
+    /// Take one row from the budget, or return false once it is used up.
+    pub(crate) fn take(&mut self) -> bool {
+        if self.remaining == 0 {
+            self.exhausted = true;
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+
+    /// Whether a row was left unscanned because the budget ran out.
+    pub(crate) fn exhausted(&self) -> bool {
+        self.exhausted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_matcher_highlights_keyword_and_regex() {
+        let filter = LogSearchFilter {
+            keyword: Some("TIMEOUT".to_string()),
+            regex: Some(r"peer=\d+".to_string()),
+            ..Default::default()
+        };
+        let matcher = filter.matcher().unwrap();
+        let content = "connect timeout, peer=42 timeout again";
+        assert!(matcher.matches_regex(content));
+        assert!(!matcher.matches_regex("connect timeout"));
+        assert_eq!(
+            matcher.highlights(content),
+            vec![
+                LogHighlight { start: 8, end: 15 },
+                LogHighlight { start: 17, end: 24 },
+                LogHighlight { start: 25, end: 32 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_filter_rejects_unbounded_regex() {
+        let invalid = LogSearchFilter {
+            regex: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(invalid.matcher().unwrap_err().contains("invalid regex"));
+
+        let too_long = LogSearchFilter {
+            regex: Some("a".repeat(MAX_SEARCH_REGEX_LEN + 1)),
+            ..Default::default()
+        };
+        assert!(too_long.matcher().is_err());
+
+        let too_large = LogSearchFilter {
+            regex: Some(r"\w{1000}{1000}".to_string()),
+            ..Default::default()
+        };
+        assert!(too_large.matcher().is_err());
+    }
+
+    #[test]
+    fn test_search_filter_escapes_keyword_for_fts_and_like() {
+        assert_eq!(
+            LogSearchFilter::fts_phrase(r#"say "hi""#),
+            r#""say ""hi""""#
+        );
+        assert_eq!(LogSearchFilter::like_pattern("50%_a\\b"), r"%50\%\_a\\b%");
+    }
+}
@@ -1,4 +1,8 @@
-use super::storage::{LogQueryRequest, LogRecords, LogStorage};
+use super::search::{LogSearchFilter, LogSearchMatcher, MAX_REGEX_SCAN_ROWS, RegexScanBudget};
+use super::storage::{
+    LogQueryRequest, LogRecords, LogSearchResult, LogStorage, decode_record_fields,
+    encode_record_fields,
+};
 use rusqlite::Connection;
 use slog::SystemLogRecord;
 use std::path::Path;
@@ -11,6 +15,9 @@ struct SystemLogRecordResult {
     pub file: Option<String>,
     pub line: Option<u32>,
     pub content: String,
+    pub trace_id: Option<String>,
+    pub span_id: Option<String>,
+    pub fields: Option<String>,
 }
 
 impl TryInto<SystemLogRecord> for SystemLogRecordResult {
@@ -24,6 +31,9 @@ impl TryInto<SystemLogRecord> for SystemLogRecordResult {
             file: self.file,
             line: self.line,
             content: self.content,
+            trace_id: self.trace_id,
+            span_id: self.span_id,
+            fields: decode_record_fields(self.fields.as_deref()),
         })
     }
 }
@@ -104,6 +114,9 @@ impl SqliteLogStorage {
         Self::ensure_logs_column(&conn, "batch_id TEXT")?;
         Self::ensure_logs_column(&conn, "record_index INTEGER")?;
         Self::ensure_logs_column(&conn, "record_id TEXT")?;
+        Self::ensure_logs_column(&conn, "trace_id TEXT")?;
+        Self::ensure_logs_column(&conn, "span_id TEXT")?;
+        Self::ensure_logs_column(&conn, "fields TEXT")?;
 
         // Create index on (source_fk, timestamp DESC) for efficient querying by source and time
         // Create index on (timestamp DESC) for efficient time-based queries
@@ -120,7 +133,11 @@ impl SqliteLogStorage {
 
              CREATE UNIQUE INDEX IF NOT EXISTS idx_logs_source_record_id
              ON logs (source_fk, record_id)
-             WHERE record_id IS NOT NULL;",
+             WHERE record_id IS NOT NULL;
+
+             CREATE INDEX IF NOT EXISTS idx_logs_trace
+             ON logs (trace_id, timestamp DESC)
+             WHERE trace_id IS NOT NULL;",
         )
         .map_err(|e| {
             let msg = format!("Failed to create indexes on logs table: {}", e);
@@ -137,7 +154,9 @@ impl SqliteLogStorage {
         Ok(ret)
     }
 
-    fn append(&self, logs: LogRecords) -> Result<(), String> {
+    /// Store the batch and return the records it actually added, without the
+    /// ones already stored by an earlier attempt of the same batch.
+    fn append(&self, logs: LogRecords) -> Result<LogRecords, String> {
         let LogRecords {
             node,
             service,
@@ -145,6 +164,13 @@ impl SqliteLogStorage {
             record_ids,
             logs,
         } = logs;
+        let mut inserted = LogRecords {
+            node: node.clone(),
+            service: service.clone(),
+            batch_id: batch_id.clone(),
+            record_ids: Vec::new(),
+            logs: Vec::new(),
+        };
         let mut conn_lock = self.conn.lock().unwrap();
 
         // Do all operations in a transaction!
@@ -180,8 +206,9 @@ impl SqliteLogStorage {
         let mut log_insert_stmt = tx
             .prepare(
                 "INSERT OR IGNORE INTO logs (
-                    source_fk, timestamp, level, target, file, line, content, batch_id, record_index, record_id
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10);",
+                    source_fk, timestamp, level, target, file, line, content, batch_id, record_index, record_id,
+                    trace_id, span_id, fields
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13);",
             )
             .map_err(|e| {
                 let msg = format!("Failed to prepare log insert statement: {}", e);
@@ -209,7 +236,8 @@ impl SqliteLogStorage {
         // Insert log record list
         for (record_index, record) in logs.into_iter().enumerate() {
             let record_id = record_ids.get(record_index).map(|s| s.as_str());
-            log_insert_stmt
+            let fields = encode_record_fields(&record.fields);
+            let changed = log_insert_stmt
                 .execute(rusqlite::params![
                     source_id,
                     record.time as i64,
@@ -221,12 +249,24 @@ impl SqliteLogStorage {
                     batch_id.as_deref(),
                     record_index as i64,
                     record_id,
+                    record.trace_id,
+                    record.span_id,
+                    fields,
                 ])
                 .map_err(|e| {
                     let msg = format!("Failed to insert log record: {}", e);
                     error!("{}", msg);
                     msg
                 })?;
+            // `INSERT OR IGNORE` leaves duplicates of a retried batch unchanged.
+            if changed > 0 {
+                if let Some(record_id) = record_id
+                    && inserted.record_ids.len() == inserted.logs.len()
+                {
+                    inserted.record_ids.push(record_id.to_string());
+                }
+                inserted.logs.push(record);
+            }
         }
 
         drop(log_insert_stmt);
@@ -240,15 +280,24 @@ impl SqliteLogStorage {
             msg
         })?;
 
-        Ok(())
+        Ok(inserted)
     }
 
     fn query(&self, request: LogQueryRequest) -> Result<Vec<LogRecords>, String> {
+        self.search(request).map(|result| result.records)
+    }
+
+    fn search(&self, request: LogQueryRequest) -> Result<LogSearchResult, String> {
+        let matcher = match request.search.as_ref() {
+            Some(search) => search.matcher()?,
+            None => LogSearchMatcher::default(),
+        };
         let conn_lock = self.conn.lock().unwrap();
 
         // Build the query dynamically based on the request parameters
         let mut query = String::from(
-            "SELECT ls.node_id, ls.service_name, l.timestamp, l.level, l.target, l.file, l.line, l.content
+            "SELECT ls.node_id, ls.service_name, l.timestamp, l.level, l.target, l.file, l.line, l.content,
+                    l.trace_id, l.span_id, l.fields
              FROM logs l
              JOIN log_sources ls ON l.source_fk = ls.source_id
              WHERE 1=1",
@@ -275,10 +324,30 @@ impl SqliteLogStorage {
             query.push_str(" AND l.timestamp <= ? ");
             params.push(Box::new(end_time as i64));
         }
+        if let Some(keyword) = request.search.as_ref().and_then(|s| s.keyword.as_deref()) {
+            query.push_str(" AND l.content LIKE ? ESCAPE '\\' ");
+            params.push(Box::new(LogSearchFilter::like_pattern(keyword)));
+        }
+        if let Some(search) = request.search.as_ref() {
+            if let Some(trace_id) = search.trace_id.as_deref() {
+                query.push_str(" AND l.trace_id = ? ");
+                params.push(Box::new(trace_id.to_string()));
+            }
+            if let Some(span_id) = search.span_id.as_deref() {
+                query.push_str(" AND l.span_id = ? ");
+                params.push(Box::new(span_id.to_string()));
+            }
+            for (key, value) in &search.fields {
+                query.push_str(" AND json_extract(l.fields, ?) = ? ");
+                params.push(Box::new(format!("$.\"{}\"", key.replace('"', "\\\""))));
+                params.push(Box::new(value.clone()));
+            }
+        }
 
         query.push_str(" ORDER BY l.timestamp DESC ");
 
-        if let Some(limit) = request.limit {
+        // With a regex filter the limit counts matching rows, so it is applied while scanning.
+        if let Some(limit) = request.limit.filter(|_| !matcher.has_regex()) {
             query.push_str(" LIMIT ? ");
             params.push(Box::new(limit as i64));
         }
@@ -303,6 +372,9 @@ impl SqliteLogStorage {
                             file: row.get::<_, Option<String>>(5)?,
                             line: row.get::<_, Option<i64>>(6)?.map(|v| v as u32),
                             content: row.get::<_, String>(7)?,
+                            trace_id: row.get::<_, Option<String>>(8)?,
+                            span_id: row.get::<_, Option<String>>(9)?,
+                            fields: row.get::<_, Option<String>>(10)?,
                         },
                     ))
                 },
@@ -314,16 +386,30 @@ impl SqliteLogStorage {
             })?;
         let mut records_map: std::collections::HashMap<(String, String), Vec<SystemLogRecord>> =
             std::collections::HashMap::new();
+        let mut regex_scan_budget = RegexScanBudget::new();
+        let mut matched = 0_usize;
         for log_result in log_iter {
+            if matcher.has_regex() && !regex_scan_budget.take() {
+                warn!(
+                    "regex search stopped after scanning {} rows",
+                    MAX_REGEX_SCAN_ROWS
+                );
+                break;
+            }
+
             let (node, service, record) = log_result.map_err(|e| {
                 let msg = format!("Failed to map log row: {}", e);
                 error!("{}", msg);
                 msg
             })?;
+            if !matcher.matches_regex(&record.content) {
+                continue;
+            }
             let key = (node.clone(), service.clone());
             match record.try_into() {
                 Ok(rec) => {
                     records_map.entry(key).or_insert_with(Vec::new).push(rec);
+                    matched += 1;
                 }
                 Err(e) => {
                     let msg = format!("Failed to convert log record: {}", e);
@@ -331,6 +417,9 @@ impl SqliteLogStorage {
                     continue;
                 }
             }
+            if request.limit.is_some_and(|limit| matched >= limit) {
+                break;
+            }
         }
 
         let mut result = Vec::new();
@@ -344,19 +433,26 @@ impl SqliteLogStorage {
             });
         }
 
-        Ok(result)
+        Ok(LogSearchResult {
+            records: result,
+            truncated: regex_scan_budget.exhausted(),
+        })
     }
 }
 
 #[async_trait::async_trait]
 impl LogStorage for SqliteLogStorage {
-    async fn append_logs(&self, logs: LogRecords) -> Result<(), String> {
+    async fn append_logs(&self, logs: LogRecords) -> Result<LogRecords, String> {
         self.append(logs)
     }
 
     async fn query_logs(&self, request: LogQueryRequest) -> Result<Vec<LogRecords>, String> {
         self.query(request)
     }
+
+    async fn search_logs(&self, request: LogQueryRequest) -> Result<LogSearchResult, String> {
+        self.search(request)
+    }
 }
 
 #[cfg(test)]
@@ -389,6 +485,9 @@ mod tests {
             file: None,
             line: None,
             content: content.to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
         }
     }
 
@@ -438,6 +537,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(all.len(), 2);
@@ -450,6 +550,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: Some(10),
+                search: None,
             })
             .unwrap();
         assert_eq!(only_error.len(), 1);
@@ -462,6 +563,63 @@ mod tests {
         cleanup_db_path(&db_path);
     }
 
+    #[test]
+    fn test_sqlite_storage_query_with_keyword_and_regex_search() {
+        let db_path = temp_db_path("search");
+        let storage = SqliteLogStorage::open(&db_path).unwrap();
+
+        storage
+            .append(LogRecords {
+                node: "node-1".to_string(),
+                service: "svc-a".to_string(),
+                batch_id: None,
+                record_ids: vec![],
+                logs: vec![
+                    sample_record(LogLevel::Error, 1000, "dial TIMEOUT peer=7"),
+                    sample_record(LogLevel::Error, 1010, "dial timeout peer=x"),
+                    sample_record(LogLevel::Info, 1020, "100% done"),
+                    sample_record(LogLevel::Info, 1030, "1000 done"),
+                ],
+            })
+            .unwrap();
+
+        let search = |keyword: Option<&str>, regex: Option<&str>, limit: Option<usize>| {
+            let mut contents = storage
+                .query(LogQueryRequest {
+                    node: None,
+                    service: None,
+                    level: None,
+                    start_time: None,
+                    end_time: None,
+                    limit,
+                    search: Some(LogSearchFilter {
+                        keyword: keyword.map(str::to_string),
+                        regex: regex.map(str::to_string),
+                        ..Default::default()
+                    }),
+                })
+                .unwrap()
+                .into_iter()
+                .flat_map(|records| records.logs.into_iter().map(|log| log.content))
+                .collect::<Vec<_>>();
+            contents.sort();
+            contents
+        };
+
+        assert_eq!(
+            search(Some("timeout"), None, None),
+            vec!["dial TIMEOUT peer=7", "dial timeout peer=x"]
+        );
+        assert_eq!(search(Some("0%"), None, None), vec!["100% done"]);
+        assert_eq!(
+            search(Some("timeout"), Some(r"peer=\d+$"), None),
+            vec!["dial TIMEOUT peer=7"]
+        );
+        assert_eq!(search(None, Some("done$"), Some(1)), vec!["1000 done"]);
+
+        cleanup_db_path(&db_path);
+    }
+
     #[test]
     fn test_sqlite_storage_append_is_idempotent_for_same_batch_id() {
         let db_path = temp_db_path("idempotent_batch");
@@ -478,8 +636,10 @@ mod tests {
             ],
         };
 
-        storage.append(payload.clone()).unwrap();
-        storage.append(payload).unwrap();
+        assert_eq!(storage.append(payload.clone()).unwrap().logs.len(), 2);
+        let retried = storage.append(payload).unwrap();
+        assert!(retried.logs.is_empty());
+        assert!(retried.record_ids.is_empty());
 
         let queried = storage
             .query(LogQueryRequest {
@@ -489,6 +649,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(queried.len(), 1);
@@ -529,6 +690,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(node_a.len(), 1);
@@ -545,6 +707,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(node_b.len(), 1);
@@ -561,6 +724,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(shared_service_all_nodes.len(), 2);
@@ -617,6 +781,7 @@ mod tests {
                 start_time: Some(3010),
                 end_time: Some(3020),
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(in_range.len(), 1);
@@ -637,6 +802,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: Some(2),
+                search: None,
             })
             .unwrap();
         assert_eq!(limited.len(), 1);
@@ -698,6 +864,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(q1.len(), 1);
@@ -711,6 +878,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(q2.len(), 1);
@@ -725,6 +893,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         let q3_count: usize = q3.iter().map(|item| item.logs.len()).sum();
@@ -738,6 +907,7 @@ mod tests {
                 start_time: Some(1010),
                 end_time: Some(1040),
                 limit: None,
+                search: None,
             })
             .unwrap();
         let q4_count: usize = q4.iter().map(|item| item.logs.len()).sum();
@@ -751,6 +921,7 @@ mod tests {
                 start_time: Some(1000),
                 end_time: Some(2000),
                 limit: Some(1),
+                search: None,
             })
             .unwrap();
         assert_eq!(q5.len(), 1);
@@ -1,5 +1,15 @@
-use super::storage::{LogQueryRequest, LogRecords, LogStorage};
-use rusqlite::{Connection, params};
+use super::aggregate::{
+    LogAggregateBucket, LogAggregateDimension, LogAggregateRequest, ROLLUP_BUCKET_MILLIS,
+};
+use super::retention::{RetentionPolicy, RetentionRunReport, RetentionStats};
+use super::search::{
+    LogSearchFilter, LogSearchMatcher, MAX_REGEX_SCAN_ROWS, MIN_FTS_KEYWORD_CHARS, RegexScanBudget,
+};
+use super::storage::{
+    LogQueryRequest, LogRecords, LogSearchResult, LogStorage, decode_record_fields,
+    encode_record_fields,
+};
+use rusqlite::{Connection, ErrorCode, OpenFlags, params};
 use slog::SystemLogRecord;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
@@ -44,6 +54,7 @@ pub struct SqlitePartitionedConfig {
     pub bucket: PartitionBucket,
     pub max_rows_per_partition: u64,
     pub max_partition_size_bytes: u64,
+    pub retention: RetentionPolicy,
 }
 
 impl Default for SqlitePartitionedConfig {
@@ -52,6 +63,7 @@ impl Default for SqlitePartitionedConfig {
             bucket: PartitionBucket::Day,
             max_rows_per_partition: DEFAULT_PARTITION_MAX_ROWS,
             max_partition_size_bytes: DEFAULT_PARTITION_MAX_SIZE_BYTES,
+            retention: RetentionPolicy::default(),
         }
     }
 }
@@ -73,6 +85,25 @@ struct PartitionStats {
     size_bytes: u64,
 }
 
+#[derive(Clone, Debug)]
+struct RetainedPartition {
+    file_name: String,
+    start_time: u64,
+    end_time: u64,
+    row_count: u64,
+    disk_bytes: u64,
+}
+
+#[derive(Debug, Default)]
+struct RetentionState {
+    dropped_partitions_total: u64,
+    freed_bytes_total: u64,
+    last_run: Option<RetentionRunReport>,
+}
+
+/// `(bucket time, level, service, node)`, dimensions not grouped by are `None`.
+type AggregateKey = (u64, Option<u32>, Option<String>, Option<String>);
+
 #[derive(Clone)]
 struct IndexedRecord {
     record_index: usize,
@@ -87,6 +118,9 @@ struct SystemLogRecordResult {
     pub file: Option<String>,
     pub line: Option<u32>,
     pub content: String,
+    pub trace_id: Option<String>,
+    pub span_id: Option<String>,
+    pub fields: Option<String>,
 }
 
 impl TryInto<SystemLogRecord> for SystemLogRecordResult {
@@ -100,6 +134,9 @@ impl TryInto<SystemLogRecord> for SystemLogRecordResult {
             file: self.file,
             line: self.line,
             content: self.content,
+            trace_id: self.trace_id,
+            span_id: self.span_id,
+            fields: decode_record_fields(self.fields.as_deref()),
         })
     }
 }
@@ -108,6 +145,7 @@ pub struct SqlitePartitionedLogStorage {
     partitions_dir: PathBuf,
     manifest_conn: Arc<Mutex<Connection>>,
     config: SqlitePartitionedConfig,
+    retention_state: Mutex<RetentionState>,
 }
 
 impl SqlitePartitionedLogStorage {
@@ -191,21 +229,46 @@ impl SqlitePartitionedLogStorage {
         Self::reconcile_manifest_with_partitions(&manifest_conn, &partitions_dir)?;
 
         info!(
-            "Initialized sqlite partitioned storage at {}, bucket={}, max_rows_per_partition={}, max_partition_size_bytes={}",
+            "Initialized sqlite partitioned storage at {}, bucket={}, max_rows_per_partition={}, max_partition_size_bytes={}, retention={:?}",
             storage_dir.display(),
             config.bucket.as_str(),
             config.max_rows_per_partition,
-            config.max_partition_size_bytes
+            config.max_partition_size_bytes,
+            config.retention
         );
 
         Ok(Self {
             partitions_dir,
             manifest_conn: Arc::new(Mutex::new(manifest_conn)),
             config,
+            retention_state: Mutex::new(RetentionState::default()),
         })
     }
 
     fn ensure_partition_schema(conn: &Connection) -> Result<(), String> {
+        let has_fts_index =
+            conn.query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'logs_fts'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(|e| {
+                let msg = format!("Failed to check partition fts schema: {}", e);
+                error!("{}", msg);
+                msg
+            })? > 0;
+        let has_log_counts =
+            conn.query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'log_counts'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(|e| {
+                let msg = format!("Failed to check partition rollup schema: {}", e);
+                error!("{}", msg);
+                msg
+            })? > 0;
+
         conn.execute_batch(
             "CREATE TABLE IF NOT EXISTS logs (
                 log_id        INTEGER PRIMARY KEY,
@@ -219,7 +282,10 @@ impl SqlitePartitionedLogStorage {
                 content       TEXT NOT NULL,
                 batch_id      TEXT,
                 record_index  INTEGER,
-                record_id     TEXT
+                record_id     TEXT,
+                trace_id      TEXT,
+                span_id       TEXT,
+                fields        TEXT
             );
 
             CREATE INDEX IF NOT EXISTS idx_logs_node_service_time
@@ -234,16 +300,139 @@ impl SqlitePartitionedLogStorage {
 
             CREATE UNIQUE INDEX IF NOT EXISTS idx_logs_record_id
             ON logs (node_id, service_name, record_id)
-            WHERE record_id IS NOT NULL;",
+            WHERE record_id IS NOT NULL;
+
+            CREATE VIRTUAL TABLE IF NOT EXISTS logs_fts USING fts5(
+                content,
+                content = 'logs',
+                content_rowid = 'log_id',
+                tokenize = 'trigram'
+            );
+
+            CREATE TRIGGER IF NOT EXISTS logs_fts_ai AFTER INSERT ON logs BEGIN
+                INSERT INTO logs_fts (rowid, content) VALUES (new.log_id, new.content);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS logs_fts_ad AFTER DELETE ON logs BEGIN
+                INSERT INTO logs_fts (logs_fts, rowid, content)
+                VALUES ('delete', old.log_id, old.content);
+            END;",
         )
         .map_err(|e| {
             let msg = format!("Failed to initialize partition logs schema: {}", e);
             error!("{}", msg);
             msg
         })?;
+
+        // Partitions created before structured fields existed lack these columns.
+        for col_def in ["trace_id TEXT", "span_id TEXT", "fields TEXT"] {
+            Self::ensure_partition_column(conn, col_def)?;
+        }
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_logs_trace
+            ON logs (trace_id, timestamp DESC)
+            WHERE trace_id IS NOT NULL;
+
+            CREATE TABLE IF NOT EXISTS log_fields (
+                log_id  INTEGER NOT NULL,
+                key     TEXT NOT NULL,
+                value   TEXT NOT NULL,
+                PRIMARY KEY (key, value, log_id)
+            ) WITHOUT ROWID;
+
+            CREATE INDEX IF NOT EXISTS idx_log_fields_log
+            ON log_fields (log_id);
+
+            CREATE TRIGGER IF NOT EXISTS log_fields_ai AFTER INSERT ON logs
+            WHEN new.fields IS NOT NULL BEGIN
+                INSERT OR IGNORE INTO log_fields (log_id, key, value)
+                SELECT new.log_id, key, CAST(value AS TEXT) FROM json_each(new.fields);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS log_fields_ad AFTER DELETE ON logs BEGIN
+                DELETE FROM log_fields WHERE log_id = old.log_id;
+            END;",
+        )
+        .map_err(|e| {
+            let msg = format!("Failed to initialize partition fields schema: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+
+        // Partitions written before the fts index existed need a one-time backfill.
+        if !has_fts_index {
+            conn.execute_batch("INSERT INTO logs_fts (logs_fts) VALUES ('rebuild');")
+                .map_err(|e| {
+                    let msg = format!("Failed to build partition fts index: {}", e);
+                    error!("{}", msg);
+                    msg
+                })?;
+        }
+
+        // Per-minute counts keep aggregate queries over long ranges off the logs table.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS log_counts (
+                minute        INTEGER NOT NULL,
+                node_id       TEXT NOT NULL,
+                service_name  TEXT NOT NULL,
+                level         INTEGER NOT NULL,
+                count         INTEGER NOT NULL,
+                PRIMARY KEY (minute, node_id, service_name, level)
+            ) WITHOUT ROWID;
+
+            CREATE TRIGGER IF NOT EXISTS log_counts_ai AFTER INSERT ON logs BEGIN
+                INSERT INTO log_counts (minute, node_id, service_name, level, count)
+                VALUES ((new.timestamp / 60000) * 60000, new.node_id, new.service_name, new.level, 1)
+                ON CONFLICT (minute, node_id, service_name, level)
+                DO UPDATE SET count = count + 1;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS log_counts_ad AFTER DELETE ON logs BEGIN
+                UPDATE log_counts SET count = count - 1
+                WHERE minute = (old.timestamp / 60000) * 60000
+                  AND node_id = old.node_id
+                  AND service_name = old.service_name
+                  AND level = old.level;
+            END;",
+        )
+        .map_err(|e| {
+            let msg = format!("Failed to initialize partition rollup schema: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+
+        if !has_log_counts {
+            conn.execute_batch(
+                "INSERT INTO log_counts (minute, node_id, service_name, level, count)
+                 SELECT (timestamp / 60000) * 60000, node_id, service_name, level, COUNT(*)
+                 FROM logs
+                 GROUP BY 1, 2, 3, 4;",
+            )
+            .map_err(|e| {
+                let msg = format!("Failed to backfill partition rollup: {}", e);
+                error!("{}", msg);
+                msg
+            })?;
+        }
         Ok(())
     }
 
+    fn ensure_partition_column(conn: &Connection, col_def: &str) -> Result<(), String> {
+        let sql = format!("ALTER TABLE logs ADD COLUMN {}", col_def);
+        match conn.execute_batch(&sql) {
+            Ok(_) => Ok(()),
+            Err(e) if e.to_string().contains("duplicate column name") => Ok(()),
+            Err(e) => {
+                let msg = format!(
+                    "Failed to alter partition logs table with '{}': {}",
+                    col_def, e
+                );
+                error!("{}", msg);
+                Err(msg)
+            }
+        }
+    }
+
     fn ensure_partition_database(partition_path: &Path) -> Result<(), String> {
         let conn = Connection::open(partition_path).map_err(|e| {
             let msg = format!(
@@ -268,6 +457,34 @@ impl SqlitePartitionedLogStorage {
         Ok(())
     }
 
+    /// Open a partition that is expected to exist without creating it, so a
+    /// reader racing retention cannot bring a dropped partition back as an
+    /// empty file. Returns `None` when the file is gone.
+    fn open_existing_partition(
+        partition_path: &Path,
+        purpose: &str,
+    ) -> Result<Option<Connection>, String> {
+        let flags = OpenFlags::default().difference(OpenFlags::SQLITE_OPEN_CREATE);
+        match Connection::open_with_flags(partition_path, flags) {
+            Ok(conn) => Ok(Some(conn)),
+            Err(rusqlite::Error::SqliteFailure(err, _))
+                if err.code == ErrorCode::CannotOpen && !partition_path.exists() =>
+            {
+                Ok(None)
+            }
+            Err(e) => {
+                let msg = format!(
+                    "Failed to open partition database {} for {}: {}",
+                    partition_path.display(),
+                    purpose,
+                    e
+                );
+                error!("{}", msg);
+                Err(msg)
+            }
+        }
+    }
+
     fn parse_partition_file_name(file_name: &str) -> Option<(String, i64)> {
         if !file_name.starts_with("logs_") || !file_name.ends_with(".db") {
             return None;
@@ -850,7 +1067,7 @@ impl SqlitePartitionedLogStorage {
         service: &str,
         batch_id: Option<&str>,
         records: &[IndexedRecord],
-    ) -> Result<u64, String> {
+    ) -> Result<Vec<bool>, String> {
         Self::ensure_partition_database(partition_path)?;
         let mut conn = Connection::open(partition_path).map_err(|e| {
             let msg = format!(
@@ -875,8 +1092,9 @@ impl SqlitePartitionedLogStorage {
         let mut stmt = tx
             .prepare(
                 "INSERT OR IGNORE INTO logs (
-                    node_id, service_name, timestamp, level, target, file, line, content, batch_id, record_index, record_id
-                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                    node_id, service_name, timestamp, level, target, file, line, content, batch_id, record_index, record_id,
+                    trace_id, span_id, fields
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
             )
             .map_err(|e| {
                 let msg = format!("Failed to prepare partition log insert statement: {}", e);
@@ -884,7 +1102,7 @@ impl SqlitePartitionedLogStorage {
                 msg
             })?;
 
-        let mut inserted_rows = 0_u64;
+        let mut inserted = Vec::with_capacity(records.len());
         for item in records {
             let changed = stmt
                 .execute(params![
@@ -899,13 +1117,17 @@ impl SqlitePartitionedLogStorage {
                     batch_id,
                     item.record_index as i64,
                     item.record_id.as_deref(),
+                    item.record.trace_id.as_deref(),
+                    item.record.span_id.as_deref(),
+                    encode_record_fields(&item.record.fields),
                 ])
                 .map_err(|e| {
                     let msg = format!("Failed to append log row to partition db: {}", e);
                     error!("{}", msg);
                     msg
                 })?;
-            inserted_rows = inserted_rows.saturating_add(changed as u64);
+            // `INSERT OR IGNORE` leaves duplicates of a retried batch unchanged.
+            inserted.push(changed > 0);
         }
 
         drop(stmt);
@@ -919,7 +1141,7 @@ impl SqlitePartitionedLogStorage {
             msg
         })?;
 
-        Ok(inserted_rows)
+        Ok(inserted)
     }
 
     fn refresh_partition_stats(
@@ -973,7 +1195,13 @@ impl SqlitePartitionedLogStorage {
         Ok(())
     }
 
-    fn append(&self, logs: LogRecords) -> Result<(), String> {
+    /// Store the batch and return the records it actually added, without the
+    /// ones already stored by an earlier attempt of the same batch.
+    fn append(&self, logs: LogRecords) -> Result<LogRecords, String> {
+        if logs.logs.is_empty() {
+            return Ok(logs);
+        }
+
         let LogRecords {
             node,
             service,
@@ -982,10 +1210,6 @@ impl SqlitePartitionedLogStorage {
             logs,
         } = logs;
 
-        if logs.is_empty() {
-            return Ok(());
-        }
-
         let mut grouped: BTreeMap<String, Vec<IndexedRecord>> = BTreeMap::new();
         for (record_index, record) in logs.into_iter().enumerate() {
             let bucket_key = self.config.bucket.bucket_key(record.time);
@@ -1003,6 +1227,7 @@ impl SqlitePartitionedLogStorage {
         })?;
         let manifest = &*manifest_lock;
 
+        let mut inserted_records = Vec::new();
         for (bucket_key, records) in grouped {
             let incoming_rows = records.len() as u64;
             let incoming_bytes = Self::estimate_records_bytes(&records);
@@ -1022,13 +1247,14 @@ impl SqlitePartitionedLogStorage {
             )?;
 
             let partition_path = self.partition_path(&target_partition.file_name);
-            let inserted_rows = Self::append_records_to_partition(
+            let inserted = Self::append_records_to_partition(
                 &partition_path,
                 &node,
                 &service,
                 batch_id.as_deref(),
                 &records,
             )?;
+            let inserted_rows = inserted.iter().filter(|v| **v).count() as u64;
 
             self.refresh_partition_stats(
                 manifest,
@@ -1037,9 +1263,28 @@ impl SqlitePartitionedLogStorage {
                 max_time,
                 inserted_rows,
             )?;
+            inserted_records.extend(
+                records
+                    .into_iter()
+                    .zip(inserted)
+                    .filter_map(|(item, inserted)| inserted.then_some(item)),
+            );
         }
 
-        Ok(())
+        inserted_records.sort_by_key(|item| item.record_index);
+        Ok(LogRecords {
+            node,
+            service,
+            batch_id,
+            record_ids: inserted_records
+                .iter()
+                .map_while(|item| item.record_id.clone())
+                .collect(),
+            logs: inserted_records
+                .into_iter()
+                .map(|item| item.record)
+                .collect(),
+        })
     }
 
     fn list_candidate_partitions(
@@ -1094,62 +1339,79 @@ impl SqlitePartitionedLogStorage {
     fn query_single_partition(
         &self,
         partition_file_name: &str,
-        node: Option<&str>,
-        service: Option<&str>,
-        level: Option<slog::LogLevel>,
-        start_time: Option<u64>,
-        end_time: Option<u64>,
-        limit: Option<usize>,
+        request: &LogQueryRequest,
+        matcher: &LogSearchMatcher,
+        regex_scan_budget: &mut RegexScanBudget,
     ) -> Result<Vec<(String, String, SystemLogRecord)>, String> {
         let partition_path = self.partition_path(partition_file_name);
-        if !partition_path.exists() {
+        let Some(conn) = Self::open_existing_partition(&partition_path, "query")? else {
             warn!(
                 "Skipping missing partition database file during query: {}",
                 partition_path.display()
             );
             return Ok(vec![]);
-        }
-
-        let conn = Connection::open(&partition_path).map_err(|e| {
-            let msg = format!(
-                "Failed to open partition database {} for query: {}",
-                partition_path.display(),
-                e
-            );
-            error!("{}", msg);
-            msg
-        })?;
+        };
 
         let mut query = String::from(
-            "SELECT node_id, service_name, timestamp, level, target, file, line, content
+            "SELECT node_id, service_name, timestamp, level, target, file, line, content,
+                    trace_id, span_id, fields
              FROM logs
              WHERE 1=1",
         );
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
-        if let Some(node) = node {
+        if let Some(node) = request.node.as_deref() {
             query.push_str(" AND node_id = ? ");
             params.push(Box::new(node.to_string()));
         }
-        if let Some(service) = service {
+        if let Some(service) = request.service.as_deref() {
             query.push_str(" AND service_name = ? ");
             params.push(Box::new(service.to_string()));
         }
-        if let Some(level) = level {
+        if let Some(level) = request.level {
             query.push_str(" AND level = ? ");
             params.push(Box::new(level as i32));
         }
-        if let Some(start_time) = start_time {
+        if let Some(start_time) = request.start_time {
             query.push_str(" AND timestamp >= ? ");
             params.push(Box::new(start_time as i64));
         }
-        if let Some(end_time) = end_time {
+        if let Some(end_time) = request.end_time {
             query.push_str(" AND timestamp <= ? ");
             params.push(Box::new(end_time as i64));
         }
+        if let Some(keyword) = request.search.as_ref().and_then(|s| s.keyword.as_deref()) {
+            if keyword.chars().count() >= MIN_FTS_KEYWORD_CHARS {
+                query.push_str(
+                    " AND log_id IN (SELECT rowid FROM logs_fts WHERE logs_fts MATCH ?) ",
+                );
+                params.push(Box::new(LogSearchFilter::fts_phrase(keyword)));
+            } else {
+                query.push_str(" AND content LIKE ? ESCAPE '\\' ");
+                params.push(Box::new(LogSearchFilter::like_pattern(keyword)));
+            }
+        }
+        if let Some(search) = request.search.as_ref() {
+            if let Some(trace_id) = search.trace_id.as_deref() {
+                query.push_str(" AND trace_id = ? ");
+                params.push(Box::new(trace_id.to_string()));
+            }
+            if let Some(span_id) = search.span_id.as_deref() {
+                query.push_str(" AND span_id = ? ");
+                params.push(Box::new(span_id.to_string()));
+            }
+            for (key, value) in &search.fields {
+                query.push_str(
+                    " AND log_id IN (SELECT log_id FROM log_fields WHERE key = ? AND value = ?) ",
+                );
+                params.push(Box::new(key.clone()));
+                params.push(Box::new(value.clone()));
+            }
+        }
 
         query.push_str(" ORDER BY timestamp DESC ");
-        if let Some(limit) = limit {
+        // With a regex filter the limit counts matching rows, so it is applied while scanning.
+        if let Some(limit) = request.limit.filter(|_| !matcher.has_regex()) {
             query.push_str(" LIMIT ? ");
             params.push(Box::new(limit as i64));
         }
@@ -1178,6 +1440,9 @@ impl SqlitePartitionedLogStorage {
                             file: row.get::<_, Option<String>>(5)?,
                             line: row.get::<_, Option<i64>>(6)?.map(|v| v as u32),
                             content: row.get::<_, String>(7)?,
+                            trace_id: row.get::<_, Option<String>>(8)?,
+                            span_id: row.get::<_, Option<String>>(9)?,
+                            fields: row.get::<_, Option<String>>(10)?,
                         },
                     ))
                 },
@@ -1194,29 +1459,46 @@ impl SqlitePartitionedLogStorage {
 
         let mut output = Vec::new();
         for row in rows {
+            if matcher.has_regex() && !regex_scan_budget.take() {
+                warn!(
+                    "regex search stopped at partition {}: scanned {} rows",
+                    partition_file_name, MAX_REGEX_SCAN_ROWS
+                );
+                break;
+            }
+
             let (node, service, record) = row.map_err(|e| {
                 let msg = format!("Failed to map partition query row: {}", e);
                 error!("{}", msg);
                 msg
             })?;
+            if !matcher.matches_regex(&record.content) {
+                continue;
+            }
             match record.try_into() {
                 Ok(record) => output.push((node, service, record)),
                 Err(e) => warn!("Failed to convert queried log row into record: {}", e),
             }
+            if request.limit.is_some_and(|limit| output.len() >= limit) {
+                break;
+            }
         }
 
         Ok(output)
     }
 
     fn query(&self, request: LogQueryRequest) -> Result<Vec<LogRecords>, String> {
-        let LogQueryRequest {
-            node,
-            service,
-            level,
-            start_time,
-            end_time,
-            limit,
-        } = request;
+        self.search(request).map(|result| result.records)
+    }
+
+    fn search(&self, request: LogQueryRequest) -> Result<LogSearchResult, String> {
+        let matcher = match request.search.as_ref() {
+            Some(search) => search.matcher()?,
+            None => LogSearchMatcher::default(),
+        };
+        let start_time = request.start_time;
+        let end_time = request.end_time;
+        let limit = request.limit;
 
         let candidate_partitions = {
             let manifest_lock = self.manifest_conn.lock().map_err(|e| {
@@ -1231,19 +1513,17 @@ impl SqlitePartitionedLogStorage {
         };
 
         if candidate_partitions.is_empty() {
-            return Ok(vec![]);
+            return Ok(LogSearchResult::default());
         }
 
         let mut rows: Vec<(String, String, SystemLogRecord)> = Vec::new();
+        let mut regex_scan_budget = RegexScanBudget::new();
         for partition in candidate_partitions {
             let mut chunk = self.query_single_partition(
                 &partition.file_name,
-                node.as_deref(),
-                service.as_deref(),
-                level,
-                start_time,
-                end_time,
-                limit,
+                &request,
+                &matcher,
+                &mut regex_scan_budget,
             )?;
             rows.append(&mut chunk);
         }
@@ -1269,80 +1549,611 @@ impl SqlitePartitionedLogStorage {
                 logs,
             });
         }
-        Ok(result)
+        Ok(LogSearchResult {
+            records: result,
+            truncated: regex_scan_budget.exhausted(),
+        })
     }
-}
 
-#[async_trait::async_trait]
-impl LogStorage for SqlitePartitionedLogStorage {
-    async fn append_logs(&self, logs: LogRecords) -> Result<(), String> {
-        self.append(logs)
-    }
+    /// Add the counts of `[range_start, range_end)` in one partition to `counts`.
+    /// With `use_rollup` the range must be minute aligned.
+    fn aggregate_partition_range(
+        conn: &Connection,
+        request: &LogAggregateRequest,
+        range_start: u64,
+        range_end: u64,
+        use_rollup: bool,
+        counts: &mut BTreeMap<AggregateKey, u64>,
+    ) -> Result<(), String> {
+        if range_start >= range_end {
+            return Ok(());
+        }
 
-    async fn query_logs(&self, request: LogQueryRequest) -> Result<Vec<LogRecords>, String> {
-        self.query(request)
-    }
-}
+        let (table, time_column, count_expr) = if use_rollup {
+            ("log_counts", "minute", "SUM(count)")
+        } else {
+            ("logs", "timestamp", "COUNT(*)")
+        };
+        let column = |dimension: LogAggregateDimension, name: &'static str| {
+            if request.groups_by(dimension) {
+                name
+            } else {
+                "NULL"
+            }
+        };
+        let mut query = format!(
+            "SELECT ({time} / ?) * ?, {level}, {service}, {node}, {count}
+             FROM {table}
+             WHERE {time} >= ? AND {time} < ?",
+            time = time_column,
+            level = column(LogAggregateDimension::Level, "level"),
+            service = column(LogAggregateDimension::Service, "service_name"),
+            node = column(LogAggregateDimension::Node, "node_id"),
+            count = count_expr,
+            table = table,
+        );
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![
+            Box::new(request.bucket_millis as i64),
+            Box::new(request.bucket_millis as i64),
+            Box::new(range_start as i64),
+            Box::new(range_end as i64),
+        ];
+        if let Some(node) = request.node.as_deref() {
+            query.push_str(" AND node_id = ? ");
+            params.push(Box::new(node.to_string()));
+        }
+        if let Some(service) = request.service.as_deref() {
+            query.push_str(" AND service_name = ? ");
+            params.push(Box::new(service.to_string()));
+        }
+        if !request.levels.is_empty() {
+            let placeholders = vec!["?"; request.levels.len()].join(", ");
+            query.push_str(&format!(" AND level IN ({}) ", placeholders));
+            for level in &request.levels {
+                params.push(Box::new(*level as i32));
+            }
+        }
+        query.push_str(" GROUP BY 1, 2, 3, 4");
 
-fn now_unix_secs() -> i64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0)
-}
+        let mut stmt = conn.prepare(&query).map_err(|e| {
+            let msg = format!("Failed to prepare partition aggregate statement: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+        let rows = stmt
+            .query_map(
+                rusqlite::params_from_iter(params.iter().map(|p| &**p)),
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)? as u64,
+                        row.get::<_, Option<i64>>(1)?.map(|v| v as u32),
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, i64>(4)?,
+                    ))
+                },
+            )
+            .map_err(|e| {
+                let msg = format!("Failed to execute partition aggregate query: {}", e);
+                error!("{}", msg);
+                msg
+            })?;
 
-fn normalize_to_millis(timestamp: u64) -> u64 {
-    if timestamp < SECOND_THRESHOLD {
-        timestamp.saturating_mul(1000)
-    } else {
-        timestamp
+        for row in rows {
+            let (time, level, service, node, count) = row.map_err(|e| {
+                let msg = format!("Failed to map partition aggregate row: {}", e);
+                error!("{}", msg);
+                msg
+            })?;
+            // Rollup rows can drop to zero after their logs were deleted.
+            if count > 0 {
+                *counts.entry((time, level, service, node)).or_default() += count as u64;
+            }
+        }
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use slog::{LogLevel, SystemLogRecord};
-    use std::time::{SystemTime, UNIX_EPOCH};
+    fn aggregate(&self, request: LogAggregateRequest) -> Result<Vec<LogAggregateBucket>, String> {
+        request.validate()?;
 
-    fn temp_storage_dir(prefix: &str) -> PathBuf {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        let dir = std::env::temp_dir().join(format!(
-            "buckyos/slog_server_partitioned_tests/{}_{}_{}",
-            prefix,
-            std::process::id(),
-            nanos
-        ));
-        std::fs::create_dir_all(&dir).unwrap();
-        dir
-    }
+        let candidate_partitions = {
+            let manifest_lock = self.manifest_conn.lock().map_err(|e| {
+                let msg = format!(
+                    "Failed to lock partition manifest db mutex for aggregate: {}",
+                    e
+                );
+                error!("{}", msg);
+                msg
+            })?;
+            Self::list_candidate_partitions(
+                &manifest_lock,
+                Some(request.start_time),
+                Some(request.end_time),
+            )?
+        };
 
-    fn cleanup_storage_dir(path: &Path) {
-        if path.exists() {
-            std::fs::remove_dir_all(path).unwrap();
+        // Whole minutes are read from the rollup when buckets are made of whole
+        // minutes, only the partial minutes at both ends scan raw rows.
+        let range_end = request.end_time.saturating_add(1);
+        let mut rollup_start = range_end;
+        let mut rollup_end = range_end;
+        if request.bucket_millis.is_multiple_of(ROLLUP_BUCKET_MILLIS) {
+            let first_minute =
+                request.start_time.div_ceil(ROLLUP_BUCKET_MILLIS) * ROLLUP_BUCKET_MILLIS;
+            let last_minute = range_end - range_end % ROLLUP_BUCKET_MILLIS;
+            if first_minute < last_minute {
+                rollup_start = first_minute;
+                rollup_end = last_minute;
+            }
         }
-    }
 
-    fn record(time: u64, content: &str) -> SystemLogRecord {
-        SystemLogRecord {
-            level: LogLevel::Info,
-            target: "test-target".to_string(),
-            time,
-            file: None,
-            line: None,
-            content: content.to_string(),
+        let mut counts: BTreeMap<AggregateKey, u64> = BTreeMap::new();
+        for partition in candidate_partitions {
+            let partition_path = self.partition_path(&partition.file_name);
+            let Some(conn) = Self::open_existing_partition(&partition_path, "aggregate")? else {
+                warn!(
+                    "Skipping missing partition database file during aggregate: {}",
+                    partition_path.display()
+                );
+                continue;
+            };
+
+            let ranges = [
+                (request.start_time, rollup_start, false),
+                (rollup_start, rollup_end, true),
+                (rollup_end, range_end, false),
+            ];
+            for (range_start, range_end, use_rollup) in ranges {
+                Self::aggregate_partition_range(
+                    &conn,
+                    &request,
+                    range_start,
+                    range_end,
+                    use_rollup,
+                    &mut counts,
+                )?;
+            }
+        }
+
+        let mut buckets = Vec::with_capacity(counts.len());
+        for ((time, level, service, node), count) in counts {
+            let level = match level.map(slog::LogLevel::try_from).transpose() {
+                Ok(level) => level,
+                Err(e) => {
+                    warn!("Skipping aggregate bucket with invalid level: {}", e);
+                    continue;
+                }
+            };
+            buckets.push(LogAggregateBucket {
+                time,
+                level,
+                service,
+                node,
+                count,
+            });
         }
+        Ok(buckets)
     }
 
-    fn payload(
-        node: &str,
-        service: &str,
-        batch_id: &str,
-        rows: Vec<SystemLogRecord>,
-    ) -> LogRecords {
+    fn partition_file_paths(&self, file_name: &str) -> [PathBuf; 3] {
+        [
+            self.partition_path(file_name),
+            self.partition_path(&format!("{}-wal", file_name)),
+            self.partition_path(&format!("{}-shm", file_name)),
+        ]
+    }
+
+    fn partition_disk_bytes(&self, file_name: &str) -> u64 {
+        self.partition_file_paths(file_name)
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .fold(0_u64, |acc, m| acc.saturating_add(m.len()))
+    }
+
+    fn list_retained_partitions(
+        &self,
+        manifest: &Connection,
+    ) -> Result<Vec<RetainedPartition>, String> {
+        let mut stmt = manifest
+            .prepare(
+                "SELECT file_name, start_time, end_time, row_count
+                 FROM partitions
+                 ORDER BY end_time ASC, part_seq ASC",
+            )
+            .map_err(|e| {
+                let msg = format!("Failed to prepare retention partitions query: {}", e);
+                error!("{}", msg);
+                msg
+            })?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?.max(0) as u64,
+                    row.get::<_, i64>(2)?.max(0) as u64,
+                    row.get::<_, i64>(3)?.max(0) as u64,
+                ))
+            })
+            .map_err(|e| {
+                let msg = format!("Failed to execute retention partitions query: {}", e);
+                error!("{}", msg);
+                msg
+            })?;
+
+        let mut ret = Vec::new();
+        for row in rows {
+            let (file_name, start_time, end_time, row_count) = row.map_err(|e| {
+                let msg = format!("Failed to map retention partition row: {}", e);
+                error!("{}", msg);
+                msg
+            })?;
+            let disk_bytes = self.partition_disk_bytes(&file_name);
+            ret.push(RetainedPartition {
+                file_name,
+                start_time,
+                end_time,
+                row_count,
+                disk_bytes,
+            });
+        }
+        Ok(ret)
+    }
+
+    fn list_partition_services(&self, file_name: &str) -> Result<Vec<String>, String> {
+        let partition_path = self.partition_path(file_name);
+        let Some(conn) = Self::open_existing_partition(&partition_path, "retention")? else {
+            return Ok(vec![]);
+        };
+        let mut stmt = conn
+            .prepare("SELECT DISTINCT service_name FROM logs")
+            .map_err(|e| {
+                let msg = format!(
+                    "Failed to prepare partition services query for {}: {}",
+                    partition_path.display(),
+                    e
+                );
+                error!("{}", msg);
+                msg
+            })?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| {
+                let msg = format!(
+                    "Failed to execute partition services query for {}: {}",
+                    partition_path.display(),
+                    e
+                );
+                error!("{}", msg);
+                msg
+            })?;
+
+        let mut services = Vec::new();
+        for row in rows {
+            services.push(row.map_err(|e| {
+                let msg = format!("Failed to map partition service row: {}", e);
+                error!("{}", msg);
+                msg
+            })?);
+        }
+        Ok(services)
+    }
+
+    /// Age limit of a partition: the longest limit among the services it holds.
+    fn partition_max_age_secs(&self, file_name: &str) -> Result<Option<u64>, String> {
+        let policy = &self.config.retention;
+        if policy.service_max_age_secs.is_empty() {
+            return Ok((policy.max_age_secs > 0).then_some(policy.max_age_secs));
+        }
+
+        let services = self.list_partition_services(file_name)?;
+        if services.is_empty() {
+            return Ok((policy.max_age_secs > 0).then_some(policy.max_age_secs));
+        }
+
+        let mut max_age_secs = 0_u64;
+        for service in services {
+            match policy.service_max_age_secs(&service) {
+                Some(secs) => max_age_secs = max_age_secs.max(secs),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(max_age_secs))
+    }
+
+    fn drop_partition(
+        &self,
+        manifest: &Connection,
+        partition: &RetainedPartition,
+    ) -> Result<u64, String> {
+        for path in self.partition_file_paths(&partition.file_name) {
+            if !path.exists() {
+                continue;
+            }
+            std::fs::remove_file(&path).map_err(|e| {
+                let msg = format!(
+                    "Failed to remove expired partition file {}: {}",
+                    path.display(),
+                    e
+                );
+                error!("{}", msg);
+                msg
+            })?;
+        }
+
+        manifest
+            .execute(
+                "DELETE FROM partitions WHERE file_name = ?1",
+                params![partition.file_name.as_str()],
+            )
+            .map_err(|e| {
+                let msg = format!(
+                    "Failed to delete expired partition manifest entry {}: {}",
+                    partition.file_name, e
+                );
+                error!("{}", msg);
+                msg
+            })?;
+        let removed_mappings = manifest
+            .execute(
+                "DELETE FROM batch_partition_map WHERE file_name = ?1",
+                params![partition.file_name.as_str()],
+            )
+            .map_err(|e| {
+                let msg = format!(
+                    "Failed to delete batch mapping for expired partition {}: {}",
+                    partition.file_name, e
+                );
+                error!("{}", msg);
+                msg
+            })?;
+
+        info!(
+            "dropped partition {} by retention: start_time={}, end_time={}, rows={}, bytes={}",
+            partition.file_name,
+            partition.start_time,
+            partition.end_time,
+            partition.row_count,
+            partition.disk_bytes
+        );
+        Ok(removed_mappings as u64)
+    }
+
+    fn run_retention(
+        &self,
+        now_millis: u64,
+        report: &mut RetentionRunReport,
+    ) -> Result<(), String> {
+        let policy = &self.config.retention;
+        let manifest_lock = self.manifest_conn.lock().map_err(|e| {
+            let msg = format!(
+                "Failed to lock partition manifest db mutex for retention: {}",
+                e
+            );
+            error!("{}", msg);
+            msg
+        })?;
+        let manifest = &*manifest_lock;
+        let mut partitions = self.list_retained_partitions(manifest)?;
+
+        // The shortest limit any service can have; younger partitions are kept
+        // without looking at which services they hold.
+        let min_age_secs = std::iter::once(policy.max_age_secs)
+            .chain(policy.service_max_age_secs.values().copied())
+            .filter(|secs| *secs > 0)
+            .min();
+        if let Some(min_age_secs) = min_age_secs {
+            let mut kept = Vec::with_capacity(partitions.len());
+            for partition in partitions {
+                let age_millis = now_millis.saturating_sub(normalize_to_millis(partition.end_time));
+                let expired = age_millis > min_age_secs.saturating_mul(1000)
+                    && self
+                        .partition_max_age_secs(&partition.file_name)?
+                        .is_some_and(|secs| age_millis > secs.saturating_mul(1000));
+                if !expired {
+                    kept.push(partition);
+                    continue;
+                }
+
+                report.removed_batch_mappings += self.drop_partition(manifest, &partition)?;
+                report.expired_partitions += 1;
+                report.freed_bytes = report.freed_bytes.saturating_add(partition.disk_bytes);
+            }
+            partitions = kept;
+        }
+
+        if policy.max_total_bytes > 0 {
+            let mut total_bytes = partitions
+                .iter()
+                .fold(0_u64, |acc, p| acc.saturating_add(p.disk_bytes));
+            // Never evict the newest partition, it is the one still being written.
+            let evictable = partitions.len().saturating_sub(1);
+            for partition in partitions.iter().take(evictable) {
+                if total_bytes <= policy.max_total_bytes {
+                    break;
+                }
+                report.removed_batch_mappings += self.drop_partition(manifest, partition)?;
+                report.evicted_partitions += 1;
+                report.freed_bytes = report.freed_bytes.saturating_add(partition.disk_bytes);
+                total_bytes = total_bytes.saturating_sub(partition.disk_bytes);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn enforce_retention_at(&self, now_millis: u64) -> Result<Option<RetentionRunReport>, String> {
+        if !self.config.retention.is_enabled() {
+            return Ok(None);
+        }
+
+        let mut report = RetentionRunReport::default();
+        let result = self.run_retention(now_millis, &mut report);
+        report.finished_at = now_unix_secs() as u64;
+        if let Err(e) = &result {
+            report.error = Some(e.clone());
+        }
+
+        let mut state = self.retention_state.lock().map_err(|e| {
+            let msg = format!("Failed to lock retention state mutex: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+        state.dropped_partitions_total = state
+            .dropped_partitions_total
+            .saturating_add(report.expired_partitions + report.evicted_partitions);
+        state.freed_bytes_total = state.freed_bytes_total.saturating_add(report.freed_bytes);
+        state.last_run = Some(report.clone());
+
+        result.map(|_| Some(report))
+    }
+
+    fn collect_retention_stats(&self) -> Result<RetentionStats, String> {
+        let partitions = {
+            let manifest_lock = self.manifest_conn.lock().map_err(|e| {
+                let msg = format!(
+                    "Failed to lock partition manifest db mutex for retention stats: {}",
+                    e
+                );
+                error!("{}", msg);
+                msg
+            })?;
+            self.list_retained_partitions(&manifest_lock)?
+        };
+
+        let mut stats = RetentionStats {
+            policy: self.config.retention.clone(),
+            partition_count: partitions.len() as u64,
+            ..Default::default()
+        };
+        for partition in partitions.iter() {
+            stats.total_bytes = stats.total_bytes.saturating_add(partition.disk_bytes);
+            if partition.row_count == 0 {
+                continue;
+            }
+            let start_time = normalize_to_millis(partition.start_time);
+            let end_time = normalize_to_millis(partition.end_time);
+            stats.oldest_log_time = Some(
+                stats
+                    .oldest_log_time
+                    .map_or(start_time, |v| v.min(start_time)),
+            );
+            stats.newest_log_time =
+                Some(stats.newest_log_time.map_or(end_time, |v| v.max(end_time)));
+        }
+
+        let state = self.retention_state.lock().map_err(|e| {
+            let msg = format!("Failed to lock retention state mutex: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+        stats.dropped_partitions_total = state.dropped_partitions_total;
+        stats.freed_bytes_total = state.freed_bytes_total;
+        stats.last_run = state.last_run.clone();
+        Ok(stats)
+    }
+}
+
+#[async_trait::async_trait]
+impl LogStorage for SqlitePartitionedLogStorage {
+    async fn append_logs(&self, logs: LogRecords) -> Result<LogRecords, String> {
+        self.append(logs)
+    }
+
+    async fn query_logs(&self, request: LogQueryRequest) -> Result<Vec<LogRecords>, String> {
+        self.query(request)
+    }
+
+    async fn search_logs(&self, request: LogQueryRequest) -> Result<LogSearchResult, String> {
+        self.search(request)
+    }
+
+    async fn enforce_retention(&self) -> Result<Option<RetentionRunReport>, String> {
+        self.enforce_retention_at(now_unix_millis())
+    }
+
+    async fn retention_stats(&self) -> Result<Option<RetentionStats>, String> {
+        self.collect_retention_stats().map(Some)
+    }
+
+    async fn aggregate_logs(
+        &self,
+        request: LogAggregateRequest,
+    ) -> Result<Vec<LogAggregateBucket>, String> {
+        self.aggregate(request)
+    }
+}
+
+fn now_unix_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn now_unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn normalize_to_millis(timestamp: u64) -> u64 {
+    if timestamp < SECOND_THRESHOLD {
+        timestamp.saturating_mul(1000)
+    } else {
+        timestamp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MAX_AGGREGATE_BUCKETS;
+    use slog::{LogLevel, SystemLogRecord};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_storage_dir(prefix: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!(
+            "buckyos/slog_server_partitioned_tests/{}_{}_{}",
+            prefix,
+            std::process::id(),
+            nanos
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup_storage_dir(path: &Path) {
+        if path.exists() {
+            std::fs::remove_dir_all(path).unwrap();
+        }
+    }
+
+    fn record(time: u64, content: &str) -> SystemLogRecord {
+        SystemLogRecord {
+            level: LogLevel::Info,
+            target: "test-target".to_string(),
+            time,
+            file: None,
+            line: None,
+            content: content.to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
+        }
+    }
+
+    fn payload(
+        node: &str,
+        service: &str,
+        batch_id: &str,
+        rows: Vec<SystemLogRecord>,
+    ) -> LogRecords {
         let record_ids = rows
             .iter()
             .enumerate()
@@ -1388,6 +2199,7 @@ mod tests {
                 bucket: PartitionBucket::Day,
                 max_rows_per_partition: 3,
                 max_partition_size_bytes: 1024 * 1024 * 1024,
+                retention: RetentionPolicy::default(),
             },
         )
         .unwrap();
@@ -1425,6 +2237,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(queried.len(), 1);
@@ -1442,6 +2255,7 @@ mod tests {
                 bucket: PartitionBucket::Day,
                 max_rows_per_partition: 1,
                 max_partition_size_bytes: 1024 * 1024 * 1024,
+                retention: RetentionPolicy::default(),
             },
         )
         .unwrap();
@@ -1461,8 +2275,10 @@ mod tests {
             "batch-b",
             vec![record(1_721_000_100_010, "dup-me")],
         );
-        storage.append(retry_payload.clone()).unwrap();
-        storage.append(retry_payload).unwrap();
+        assert_eq!(storage.append(retry_payload.clone()).unwrap().logs.len(), 1);
+        let retried = storage.append(retry_payload).unwrap();
+        assert!(retried.logs.is_empty());
+        assert!(retried.record_ids.is_empty());
 
         let queried = storage
             .query(LogQueryRequest {
@@ -1472,6 +2288,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(queried.len(), 1);
@@ -1491,6 +2308,7 @@ mod tests {
                 bucket: PartitionBucket::Day,
                 max_rows_per_partition: 100,
                 max_partition_size_bytes: 1024 * 1024 * 1024,
+                retention: RetentionPolicy::default(),
             },
         )
         .unwrap();
@@ -1530,6 +2348,7 @@ mod tests {
                 start_time: Some(day1),
                 end_time: Some(day2 + 40),
                 limit: Some(3),
+                search: None,
             })
             .unwrap();
         assert_eq!(queried.len(), 1);
@@ -1550,6 +2369,7 @@ mod tests {
                 bucket: PartitionBucket::Day,
                 max_rows_per_partition: 10_000,
                 max_partition_size_bytes: 1,
+                retention: RetentionPolicy::default(),
             },
         )
         .unwrap();
@@ -1585,6 +2405,7 @@ mod tests {
                 bucket: PartitionBucket::Day,
                 max_rows_per_partition: 100,
                 max_partition_size_bytes: 1024 * 1024 * 1024,
+                retention: RetentionPolicy::default(),
             },
         )
         .unwrap();
@@ -1618,6 +2439,7 @@ mod tests {
                 start_time: Some(day1_secs),
                 end_time: Some(day2_secs + 60),
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(queried.len(), 1);
@@ -1635,6 +2457,7 @@ mod tests {
                 bucket: PartitionBucket::Day,
                 max_rows_per_partition: 100,
                 max_partition_size_bytes: 1024 * 1024 * 1024,
+                retention: RetentionPolicy::default(),
             },
         )
         .unwrap();
@@ -1666,6 +2489,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(node_a.len(), 1);
@@ -1685,6 +2509,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(node_b.len(), 1);
@@ -1706,6 +2531,7 @@ mod tests {
                 bucket: PartitionBucket::Day,
                 max_rows_per_partition: 100,
                 max_partition_size_bytes: 1024 * 1024 * 1024,
+                retention: RetentionPolicy::default(),
             },
         )
         .unwrap();
@@ -1751,6 +2577,7 @@ mod tests {
                 start_time: Some(second_time),
                 end_time: Some(second_time),
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(queried.len(), 1);
@@ -1781,6 +2608,7 @@ mod tests {
                 bucket: PartitionBucket::Day,
                 max_rows_per_partition: 100,
                 max_partition_size_bytes: 1024 * 1024 * 1024,
+                retention: RetentionPolicy::default(),
             },
         )
         .unwrap();
@@ -1818,6 +2646,7 @@ mod tests {
                 start_time: None,
                 end_time: None,
                 limit: None,
+                search: None,
             })
             .unwrap();
         assert_eq!(queried.len(), 1);
@@ -1826,4 +2655,721 @@ mod tests {
 
         cleanup_storage_dir(&storage_dir);
     }
+
+    fn retention_storage(
+        storage_dir: &Path,
+        max_rows_per_partition: u64,
+        retention: RetentionPolicy,
+    ) -> SqlitePartitionedLogStorage {
+        SqlitePartitionedLogStorage::open(
+            storage_dir,
+            SqlitePartitionedConfig {
+                bucket: PartitionBucket::Day,
+                max_rows_per_partition,
+                max_partition_size_bytes: 1024 * 1024 * 1024,
+                retention,
+            },
+        )
+        .unwrap()
+    }
+
+    fn query_batch_mapping_count(storage: &SqlitePartitionedLogStorage, file_name: &str) -> i64 {
+        storage
+            .manifest_conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT COUNT(*) FROM batch_partition_map WHERE file_name = ?1",
+                params![file_name],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    fn query_contents(storage: &SqlitePartitionedLogStorage) -> Vec<String> {
+        let mut contents = storage
+            .query(LogQueryRequest {
+                node: None,
+                service: None,
+                level: None,
+                start_time: None,
+                end_time: None,
+                limit: None,
+                search: None,
+            })
+            .unwrap()
+            .into_iter()
+            .flat_map(|records| records.logs.into_iter().map(|log| log.content))
+            .collect::<Vec<_>>();
+        contents.sort();
+        contents
+    }
+
+    #[test]
+    fn test_partitioned_storage_retention_drops_expired_partitions_and_batch_mappings() {
+        let storage_dir = temp_storage_dir("retention_max_age");
+        let storage = retention_storage(
+            &storage_dir,
+            100,
+            RetentionPolicy {
+                max_age_secs: 86_400,
+                ..Default::default()
+            },
+        );
+
+        let day1 = 1_721_600_000_000_u64;
+        let day4 = day1 + 3 * DAY_MILLIS;
+        storage
+            .append(payload(
+                "node-1",
+                "svc-a",
+                "batch-old",
+                vec![record(day1, "old")],
+            ))
+            .unwrap();
+        let expired_file = query_first_partition_file_name(&storage);
+        storage
+            .append(payload(
+                "node-1",
+                "svc-a",
+                "batch-new",
+                vec![record(day4, "new")],
+            ))
+            .unwrap();
+        assert_eq!(query_batch_mapping_count(&storage, &expired_file), 1);
+
+        let report = storage
+            .enforce_retention_at(day4 + 1000)
+            .unwrap()
+            .expect("retention policy is enabled");
+        assert_eq!(report.expired_partitions, 1);
+        assert_eq!(report.evicted_partitions, 0);
+        assert_eq!(report.removed_batch_mappings, 1);
+        assert!(report.freed_bytes > 0);
+
+        assert_eq!(query_partition_count(&storage), 1);
+        assert_eq!(query_batch_mapping_count(&storage, &expired_file), 0);
+        assert!(!storage.partition_path(&expired_file).exists());
+        assert_eq!(query_contents(&storage), vec!["new".to_string()]);
+
+        let stats = storage.collect_retention_stats().unwrap();
+        assert_eq!(stats.partition_count, 1);
+        assert_eq!(stats.oldest_log_time, Some(day4));
+        assert_eq!(stats.newest_log_time, Some(day4));
+        assert_eq!(stats.dropped_partitions_total, 1);
+        assert_eq!(stats.freed_bytes_total, report.freed_bytes);
+        assert_eq!(stats.last_run, Some(report));
+
+        cleanup_storage_dir(&storage_dir);
+    }
+
+    #[test]
+    fn test_partitioned_storage_query_does_not_recreate_dropped_partition() {
+        let storage_dir = temp_storage_dir("query_dropped_partition");
+        let storage = retention_storage(&storage_dir, 100, RetentionPolicy::default());
+
+        let day1 = 1_721_600_000_000_u64;
+        storage
+            .append(payload(
+                "node-1",
+                "svc-a",
+                "batch-old",
+                vec![record(day1, "old")],
+            ))
+            .unwrap();
+        let dropped_file = query_first_partition_file_name(&storage);
+        storage
+            .append(payload(
+                "node-1",
+                "svc-a",
+                "batch-new",
+                vec![record(day1 + 3 * DAY_MILLIS, "new")],
+            ))
+            .unwrap();
+
+        // Retention removed the files after the query listed the partition.
+        for path in storage.partition_file_paths(&dropped_file) {
+            let _ = std::fs::remove_file(path);
+        }
+        assert_eq!(query_contents(&storage), vec!["new".to_string()]);
+        assert!(!storage.partition_path(&dropped_file).exists());
+
+        cleanup_storage_dir(&storage_dir);
+    }
+
+    #[test]
+    fn test_partitioned_storage_retention_service_override_keeps_shared_partitions() {
+        let storage_dir = temp_storage_dir("retention_service_override");
+        let storage = retention_storage(
+            &storage_dir,
+            100,
+            RetentionPolicy {
+                max_age_secs: 0,
+                max_total_bytes: 0,
+                service_max_age_secs: [("svc-short".to_string(), 3600)].into_iter().collect(),
+            },
+        );
+
+        let day1 = 1_721_700_000_000_u64;
+        let day2 = day1 + DAY_MILLIS;
+        storage
+            .append(payload(
+                "node-1",
+                "svc-short",
+                "batch-short-1",
+                vec![record(day1, "short-day1")],
+            ))
+            .unwrap();
+        storage
+            .append(payload(
+                "node-1",
+                "svc-short",
+                "batch-short-2",
+                vec![record(day2, "short-day2")],
+            ))
+            .unwrap();
+        storage
+            .append(payload(
+                "node-1",
+                "svc-long",
+                "batch-long",
+                vec![record(day2 + 10, "long-day2")],
+            ))
+            .unwrap();
+
+        let report = storage
+            .enforce_retention_at(day2 + 2 * DAY_MILLIS)
+            .unwrap()
+            .expect("retention policy is enabled");
+        assert_eq!(report.expired_partitions, 1);
+        assert_eq!(
+            query_contents(&storage),
+            vec!["long-day2".to_string(), "short-day2".to_string()]
+        );
+
+        cleanup_storage_dir(&storage_dir);
+    }
+
+    #[test]
+    fn test_partitioned_storage_retention_evicts_oldest_partitions_over_size_cap() {
+        let storage_dir = temp_storage_dir("retention_size_cap");
+        let mut storage = retention_storage(&storage_dir, 1, RetentionPolicy::default());
+        assert_eq!(
+            storage.enforce_retention_at(now_unix_millis()).unwrap(),
+            None
+        );
+
+        let base = 1_721_800_000_000_u64;
+        for (idx, content) in ["p0", "p1", "p2"].iter().enumerate() {
+            storage
+                .append(payload(
+                    "node-1",
+                    "svc-size",
+                    &format!("batch-{}", content),
+                    vec![record(base + idx as u64, content)],
+                ))
+                .unwrap();
+        }
+        assert_eq!(query_partition_count(&storage), 3);
+
+        let total_bytes = storage.collect_retention_stats().unwrap().total_bytes;
+        storage.config.retention.max_total_bytes = total_bytes - 1;
+        let report = storage.enforce_retention_at(base).unwrap().unwrap();
+        assert_eq!(
+            (report.expired_partitions, report.evicted_partitions),
+            (0, 1)
+        );
+        assert_eq!(
+            query_contents(&storage),
+            vec!["p1".to_string(), "p2".to_string()]
+        );
+
+        // The newest partition survives even when it alone exceeds the cap.
+        storage.config.retention.max_total_bytes = 1;
+        let report = storage.enforce_retention_at(base).unwrap().unwrap();
+        assert_eq!(report.evicted_partitions, 1);
+        assert_eq!(query_contents(&storage), vec!["p2".to_string()]);
+        assert_eq!(
+            storage
+                .collect_retention_stats()
+                .unwrap()
+                .dropped_partitions_total,
+            2
+        );
+
+        cleanup_storage_dir(&storage_dir);
+    }
+
+    fn search_contents(
+        storage: &SqlitePartitionedLogStorage,
+        keyword: Option<&str>,
+        regex: Option<&str>,
+        limit: Option<usize>,
+    ) -> Vec<String> {
+        let mut contents = storage
+            .query(LogQueryRequest {
+                node: None,
+                service: None,
+                level: None,
+                start_time: None,
+                end_time: None,
+                limit,
+                search: Some(LogSearchFilter {
+                    keyword: keyword.map(str::to_string),
+                    regex: regex.map(str::to_string),
+                    ..Default::default()
+                }),
+            })
+            .unwrap()
+            .into_iter()
+            .flat_map(|records| records.logs.into_iter().map(|log| log.content))
+            .collect::<Vec<_>>();
+        contents.sort();
+        contents
+    }
+
+    #[test]
+    fn test_partitioned_storage_search_fans_out_across_partitions() {
+        let storage_dir = temp_storage_dir("search_fan_out");
+        let storage = retention_storage(&storage_dir, 100, RetentionPolicy::default());
+
+        let day1 = 1_721_900_000_000_u64;
+        let day2 = day1 + DAY_MILLIS;
+        storage
+            .append(payload(
+                "node-1",
+                "svc-a",
+                "batch-search-1",
+                vec![
+                    record(day1, "connect Timeout peer=1"),
+                    record(day1 + 10, "handshake ok"),
+                ],
+            ))
+            .unwrap();
+        storage
+            .append(payload(
+                "node-1",
+                "svc-b",
+                "batch-search-2",
+                vec![
+                    record(day2, "read timeout peer=22"),
+                    record(day2 + 10, "ok 50%"),
+                ],
+            ))
+            .unwrap();
+        assert_eq!(query_partition_count(&storage), 2);
+
+        assert_eq!(
+            search_contents(&storage, Some("TIMEOUT"), None, None),
+            vec!["connect Timeout peer=1", "read timeout peer=22"]
+        );
+        assert_eq!(
+            search_contents(&storage, Some("0%"), None, None),
+            vec!["ok 50%"]
+        );
+        assert_eq!(
+            search_contents(&storage, Some("timeout"), Some(r"peer=\d{2}"), None),
+            vec!["read timeout peer=22"]
+        );
+        assert_eq!(
+            search_contents(&storage, None, Some("^(connect|handshake)"), Some(1)),
+            vec!["handshake ok"]
+        );
+        assert!(search_contents(&storage, Some("missing"), None, None).is_empty());
+
+        cleanup_storage_dir(&storage_dir);
+    }
+
+    #[test]
+    fn test_partitioned_storage_backfills_fts_index_for_old_partitions() {
+        let storage_dir = temp_storage_dir("search_fts_backfill");
+        let storage = retention_storage(&storage_dir, 100, RetentionPolicy::default());
+        storage
+            .append(payload(
+                "node-1",
+                "svc-a",
+                "batch-backfill",
+                vec![record(1_722_000_000_000, "legacy disk full")],
+            ))
+            .unwrap();
+        let file_name = query_first_partition_file_name(&storage);
+        let partition_path = storage.partition_path(&file_name);
+        drop(storage);
+
+        Connection::open(&partition_path)
+            .unwrap()
+            .execute_batch(
+                "DROP TRIGGER logs_fts_ai;
+                 DROP TRIGGER logs_fts_ad;
+                 DROP TABLE logs_fts;",
+            )
+            .unwrap();
+
+        let storage = retention_storage(&storage_dir, 100, RetentionPolicy::default());
+        assert_eq!(
+            search_contents(&storage, Some("disk full"), None, None),
+            vec!["legacy disk full"]
+        );
+
+        cleanup_storage_dir(&storage_dir);
+    }
+
+    fn traced_record(
+        time: u64,
+        content: &str,
+        trace_id: &str,
+        fields: &[(&str, &str)],
+    ) -> SystemLogRecord {
+        let mut record = record(time, content).with_trace(Some(trace_id.to_string()), None);
+        for (key, value) in fields {
+            record = record.with_field(*key, *value);
+        }
+        record
+    }
+
+    fn query_by_fields(
+        storage: &SqlitePartitionedLogStorage,
+        trace_id: Option<&str>,
+        fields: &[(&str, &str)],
+    ) -> Vec<(String, SystemLogRecord)> {
+        let mut rows = storage
+            .query(LogQueryRequest {
+                node: None,
+                service: None,
+                level: None,
+                start_time: None,
+                end_time: None,
+                limit: None,
+                search: Some(LogSearchFilter {
+                    trace_id: trace_id.map(str::to_string),
+                    fields: fields
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                    ..Default::default()
+                }),
+            })
+            .unwrap()
+            .into_iter()
+            .flat_map(|records| {
+                let service = records.service;
+                records
+                    .logs
+                    .into_iter()
+                    .map(move |log| (service.clone(), log))
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|(_, log)| log.time);
+        rows
+    }
+
+    #[test]
+    fn test_partitioned_storage_query_by_trace_id_and_fields_across_services() {
+        let storage_dir = temp_storage_dir("trace_fields");
+        let storage = retention_storage(&storage_dir, 100, RetentionPolicy::default());
+        let day1 = 1_722_000_000_000_u64;
+        let day2 = day1 + DAY_MILLIS;
+        storage
+            .append(payload(
+                "node-1",
+                "control-panel",
+                "batch-gateway",
+                vec![
+                    traced_record(
+                        day1,
+                        "call sys_config",
+                        "t-1",
+                        &[("method", "sys_config_get")],
+                    ),
+                    traced_record(day1 + 1, "call other", "t-2", &[("method", "other")]),
+                    record(day1 + 2, "untraced"),
+                ],
+            ))
+            .unwrap();
+        storage
+            .append(payload(
+                "node-2",
+                "system-config",
+                "batch-config",
+                vec![traced_record(
+                    day2,
+                    "serve sys_config",
+                    "t-1",
+                    &[("method", "sys_config_get"), ("key", "boot/config")],
+                )],
+            ))
+            .unwrap();
+        assert_eq!(query_partition_count(&storage), 2);
+
+        let rows = query_by_fields(&storage, Some("t-1"), &[]);
+        assert_eq!(
+            rows.iter()
+                .map(|(service, log)| (service.as_str(), log.content.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("control-panel", "call sys_config"),
+                ("system-config", "serve sys_config"),
+            ]
+        );
+        assert_eq!(rows[1].1.trace_id.as_deref(), Some("t-1"));
+        assert_eq!(
+            rows[1].1.fields.get("key").map(String::as_str),
+            Some("boot/config")
+        );
+
+        let rows = query_by_fields(
+            &storage,
+            None,
+            &[("method", "sys_config_get"), ("key", "boot/config")],
+        );
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].1.content, "serve sys_config");
+        assert!(query_by_fields(&storage, Some("t-2"), &[("method", "sys_config_get")]).is_empty());
+
+        cleanup_storage_dir(&storage_dir);
+    }
+
+    #[test]
+    fn test_partitioned_storage_adds_structured_columns_to_old_partitions() {
+        let storage_dir = temp_storage_dir("trace_fields_migrate");
+        let storage = retention_storage(&storage_dir, 100, RetentionPolicy::default());
+        storage
+            .append(payload(
+                "node-1",
+                "svc-a",
+                "batch-legacy",
+                vec![record(1_722_000_000_000, "legacy row")],
+            ))
+            .unwrap();
+        let file_name = query_first_partition_file_name(&storage);
+        let partition_path = storage.partition_path(&file_name);
+        drop(storage);
+
+        Connection::open(&partition_path)
+            .unwrap()
+            .execute_batch(
+                "DROP TRIGGER log_fields_ai;
+                 DROP TRIGGER log_fields_ad;
+                 DROP TABLE log_fields;
+                 DROP INDEX idx_logs_trace;
+                 ALTER TABLE logs DROP COLUMN trace_id;
+                 ALTER TABLE logs DROP COLUMN span_id;
+                 ALTER TABLE logs DROP COLUMN fields;",
+            )
+            .unwrap();
+
+        let storage = retention_storage(&storage_dir, 100, RetentionPolicy::default());
+        storage
+            .append(payload(
+                "node-1",
+                "svc-a",
+                "batch-traced",
+                vec![traced_record(
+                    1_722_000_000_001,
+                    "traced row",
+                    "t-9",
+                    &[("user", "alice")],
+                )],
+            ))
+            .unwrap();
+
+        let rows = query_by_fields(&storage, Some("t-9"), &[("user", "alice")]);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].1.content, "traced row");
+        assert_eq!(query_by_fields(&storage, None, &[]).len(), 2);
+
+        cleanup_storage_dir(&storage_dir);
+    }
+
+    /// Append ~8 minutes of rows over two nodes, two services and three levels,
+    /// returned as `(node, service, record)` for `expected_aggregate`.
+    fn aggregate_fixture(
+        storage: &SqlitePartitionedLogStorage,
+    ) -> Vec<(String, String, SystemLogRecord)> {
+        let base = 1_722_000_000_000;
+        let levels = [LogLevel::Error, LogLevel::Warn, LogLevel::Info];
+        let mut rows = Vec::new();
+        for i in 0..300_u64 {
+            let node = format!("node-{}", i % 2);
+            let service = if i % 5 == 0 { "svc-a" } else { "svc-b" }.to_string();
+            let mut log = record(base + i * 1_700, "aggregate");
+            log.level = levels[(i % 3) as usize];
+            rows.push((node, service, log));
+        }
+
+        let mut grouped: BTreeMap<(String, String), Vec<SystemLogRecord>> = BTreeMap::new();
+        for (node, service, log) in &rows {
+            grouped
+                .entry((node.clone(), service.clone()))
+                .or_default()
+                .push(log.clone());
+        }
+        for ((node, service), logs) in grouped {
+            for (idx, chunk) in logs.chunks(40).enumerate() {
+                let batch_id = format!("{}-{}-{}", node, service, idx);
+                storage
+                    .append(payload(&node, &service, &batch_id, chunk.to_vec()))
+                    .unwrap();
+            }
+        }
+        rows
+    }
+
+    fn expected_aggregate(
+        rows: &[(String, String, SystemLogRecord)],
+        request: &LogAggregateRequest,
+    ) -> Vec<LogAggregateBucket> {
+        let mut counts: BTreeMap<AggregateKey, u64> = BTreeMap::new();
+        for (node, service, log) in rows {
+            if log.time < request.start_time
+                || log.time > request.end_time
+                || request.node.as_ref().is_some_and(|n| n != node)
+                || request.service.as_ref().is_some_and(|s| s != service)
+                || (!request.levels.is_empty() && !request.levels.contains(&log.level))
+            {
+                continue;
+            }
+            let key = (
+                request.bucket_time(log.time),
+                request
+                    .groups_by(LogAggregateDimension::Level)
+                    .then_some(log.level as u32),
+                request
+                    .groups_by(LogAggregateDimension::Service)
+                    .then(|| service.clone()),
+                request
+                    .groups_by(LogAggregateDimension::Node)
+                    .then(|| node.clone()),
+            );
+            *counts.entry(key).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .map(|((time, level, service, node), count)| LogAggregateBucket {
+                time,
+                level: level.map(|v| LogLevel::try_from(v).unwrap()),
+                service,
+                node,
+                count,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_partitioned_storage_aggregate_matches_raw_counts() {
+        let storage_dir = temp_storage_dir("aggregate_counts");
+        let storage = retention_storage(&storage_dir, 100, RetentionPolicy::default());
+        let rows = aggregate_fixture(&storage);
+        assert!(query_partition_count(&storage) > 1);
+
+        let base = 1_722_000_000_000;
+        let requests = [
+            // Partial minutes at both ends around rollup minutes.
+            LogAggregateRequest {
+                start_time: base + 12_345,
+                end_time: base + 400_000,
+                bucket_millis: 60_000,
+                group_by: vec![LogAggregateDimension::Level],
+                node: None,
+                service: None,
+                levels: vec![],
+            },
+            LogAggregateRequest {
+                start_time: base + 12_345,
+                end_time: base + 400_000,
+                bucket_millis: 120_000,
+                group_by: vec![
+                    LogAggregateDimension::Level,
+                    LogAggregateDimension::Service,
+                    LogAggregateDimension::Node,
+                ],
+                node: None,
+                service: None,
+                levels: vec![],
+            },
+            // Buckets that split minutes only use raw rows.
+            LogAggregateRequest {
+                start_time: base,
+                end_time: base + 509_999,
+                bucket_millis: 7_000,
+                group_by: vec![LogAggregateDimension::Node],
+                node: None,
+                service: None,
+                levels: vec![],
+            },
+            LogAggregateRequest {
+                start_time: base,
+                end_time: base + 600_000,
+                bucket_millis: 300_000,
+                group_by: vec![],
+                node: Some("node-1".to_string()),
+                service: Some("svc-b".to_string()),
+                levels: vec![LogLevel::Error, LogLevel::Warn],
+            },
+        ];
+        for request in requests {
+            let buckets = storage.aggregate(request.clone()).unwrap();
+            assert!(!buckets.is_empty());
+            assert_eq!(
+                buckets,
+                expected_aggregate(&rows, &request),
+                "{:?}",
+                request
+            );
+        }
+
+        let too_many = LogAggregateRequest {
+            start_time: base,
+            end_time: base + MAX_AGGREGATE_BUCKETS * 1_000,
+            bucket_millis: 1_000,
+            group_by: vec![],
+            node: None,
+            service: None,
+            levels: vec![],
+        };
+        assert!(storage.aggregate(too_many).is_err());
+
+        cleanup_storage_dir(&storage_dir);
+    }
+
+    #[test]
+    fn test_partitioned_storage_backfills_rollup_for_old_partitions() {
+        let storage_dir = temp_storage_dir("aggregate_rollup_backfill");
+        let storage = retention_storage(&storage_dir, 1_000, RetentionPolicy::default());
+        let rows = aggregate_fixture(&storage);
+        let file_name = query_first_partition_file_name(&storage);
+        let partition_path = storage.partition_path(&file_name);
+        drop(storage);
+
+        Connection::open(&partition_path)
+            .unwrap()
+            .execute_batch(
+                "DROP TRIGGER log_counts_ai;
+                 DROP TRIGGER log_counts_ad;
+                 DROP TABLE log_counts;",
+            )
+            .unwrap();
+
+        let storage = retention_storage(&storage_dir, 1_000, RetentionPolicy::default());
+        let request = LogAggregateRequest {
+            start_time: 1_722_000_000_000,
+            end_time: 1_722_000_599_999,
+            bucket_millis: 60_000,
+            group_by: vec![LogAggregateDimension::Level],
+            node: None,
+            service: None,
+            levels: vec![],
+        };
+        assert_eq!(
+            storage.aggregate(request.clone()).unwrap(),
+            expected_aggregate(&rows, &request)
+        );
+        let rollup_rows: i64 = Connection::open(&partition_path)
+            .unwrap()
+            .query_row("SELECT SUM(count) FROM log_counts", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rollup_rows, 300);
+
+        cleanup_storage_dir(&storage_dir);
+    }
 }
@@ -1,5 +1,9 @@
+use super::aggregate::{LogAggregateBucket, LogAggregateRequest};
+use super::retention::{RetentionRunReport, RetentionStats};
+use super::search::LogSearchFilter;
 use serde::{Deserialize, Serialize};
 use slog::SystemLogRecord;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -13,10 +17,48 @@ pub struct LogRecords {
     pub logs: Vec<SystemLogRecord>,
 }
 
+/// Query result that also tells whether the regex scan limit cut it short.
+#[derive(Clone, Debug, Default)]
+pub struct LogSearchResult {
+    pub records: Vec<LogRecords>,
+    /// The search stopped after `MAX_REGEX_SCAN_ROWS` rows; older matches
+    /// may exist beyond the returned records.
+    pub truncated: bool,
+}
+
 #[async_trait::async_trait]
 pub trait LogStorage: Sync + Send {
-    async fn append_logs(&self, records: LogRecords) -> Result<(), String>;
+    /// Store a batch and return the records actually added; records already
+    /// stored by an earlier attempt of the same batch are left out.
+    async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String>;
     async fn query_logs(&self, request: LogQueryRequest) -> Result<Vec<LogRecords>, String>;
+
+    /// Same as `query_logs`, also reporting whether the search was truncated.
+    async fn search_logs(&self, request: LogQueryRequest) -> Result<LogSearchResult, String> {
+        let records = self.query_logs(request).await?;
+        Ok(LogSearchResult {
+            records,
+            truncated: false,
+        })
+    }
+
+    /// Drop data that falls outside the retention policy.
+    /// Returns `None` when the storage has no retention policy to enforce.
+    async fn enforce_retention(&self) -> Result<Option<RetentionRunReport>, String> {
+        Ok(None)
+    }
+
+    async fn retention_stats(&self) -> Result<Option<RetentionStats>, String> {
+        Ok(None)
+    }
+
+    /// Record counts per time bucket, sorted by time.
+    async fn aggregate_logs(
+        &self,
+        _request: LogAggregateRequest,
+    ) -> Result<Vec<LogAggregateBucket>, String> {
+        Err("log aggregation is not supported by this storage".to_string())
+    }
 }
 
 pub type LogStorageRef = Arc<Box<dyn LogStorage>>;
@@ -29,4 +71,18 @@ pub struct LogQueryRequest {
     pub start_time: Option<u64>,
     pub end_time: Option<u64>,
     pub limit: Option<usize>,
+    pub search: Option<LogSearchFilter>,
+}
+
+/// Record fields are stored as a JSON object, `NULL` when there are none.
+pub(crate) fn encode_record_fields(fields: &BTreeMap<String, String>) -> Option<String> {
+    if fields.is_empty() {
+        return None;
+    }
+    serde_json::to_string(fields).ok()
+}
+
+pub(crate) fn decode_record_fields(raw: Option<&str>) -> BTreeMap<String, String> {
+    raw.and_then(|raw| serde_json::from_str(raw).ok())
+        .unwrap_or_default()
 }
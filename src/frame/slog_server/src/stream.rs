@@ -0,0 +1,407 @@
+use crate::storage::{LogHighlight, LogRecords, LogSearchFilter, LogSearchMatcher};
+use serde::{Deserialize, Serialize};
+use slog::{LogLevel, SystemLogRecord};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Records kept in memory so a reconnecting subscriber can resume by sequence.
+pub const DEFAULT_STREAM_REPLAY_RECORDS: usize = 4096;
+/// Records a subscriber may fall behind before it starts losing them.
+pub const DEFAULT_STREAM_CHANNEL_CAPACITY: usize = 1024;
+pub const DEFAULT_MAX_STREAM_SUBSCRIBERS: usize = 64;
+
+/// One uploaded record as seen by live subscribers.
+#[derive(Debug, Clone)]
+pub struct StreamLogRecord {
+    pub seq: u64,
+    pub node: String,
+    pub service: String,
+    pub log: SystemLogRecord,
+}
+
+/// Wire form of a `log` event.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StreamLogEventData {
+    pub seq: u64,
+    pub node: String,
+    pub service: String,
+    pub log: SystemLogRecord,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<LogHighlight>,
+}
+
+/// Wire form of a `lagged` event: `missed` records were dropped for this subscriber.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StreamLaggedEventData {
+    pub missed: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum LogStreamEvent {
+    Record {
+        record: Arc<StreamLogRecord>,
+        highlights: Vec<LogHighlight>,
+    },
+    Lagged {
+        missed: u64,
+    },
+}
+
+/// Server-side filter of one subscription. Empty lists match everything.
+#[derive(Debug, Clone, Default)]
+pub struct LogStreamFilter {
+    pub nodes: Vec<String>,
+    pub services: Vec<String>,
+    pub levels: Vec<LogLevel>,
+    pub search: Option<LogSearchFilter>,
+}
+
+struct CompiledStreamFilter {
+    filter: LogStreamFilter,
+    matcher: LogSearchMatcher,
+}
+
+impl CompiledStreamFilter {
+    fn new(filter: LogStreamFilter) -> Result<Self, String> {
+        let matcher = match filter.search.as_ref() {
+            Some(search) => search.matcher()?,
+            None => LogSearchMatcher::default(),
+        };
+        Ok(Self { filter, matcher })
+    }
+
+    fn matches(&self, record: &StreamLogRecord) -> bool {
+        let filter = &self.filter;
+        (filter.nodes.is_empty() || filter.nodes.contains(&record.node))
+            && (filter.services.is_empty() || filter.services.contains(&record.service))
+            && (filter.levels.is_empty() || filter.levels.contains(&record.log.level))
+            && filter
+                .search
+                .as_ref()
+                .is_none_or(|search| search.matches_structured(&record.log))
+            && self.matcher.matches_content(&record.log.content)
+    }
+}
+
+struct ReplayBuffer {
+    next_seq: u64,
+    records: VecDeque<Arc<StreamLogRecord>>,
+}
+
+/// Fans uploaded records out to live subscribers.
+///
+/// Publishing never waits for subscribers: each one reads from a bounded
+/// broadcast channel, and a subscriber that falls behind gets a `Lagged`
+/// event and continues from the newest records.
+pub struct LogStreamHub {
+    buffer: Mutex<ReplayBuffer>,
+    sender: broadcast::Sender<Arc<StreamLogRecord>>,
+    replay_capacity: usize,
+    max_subscribers: usize,
+    subscribers: Arc<AtomicUsize>,
+}
+
+impl Default for LogStreamHub {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_STREAM_REPLAY_RECORDS,
+            DEFAULT_STREAM_CHANNEL_CAPACITY,
+            DEFAULT_MAX_STREAM_SUBSCRIBERS,
+        )
+    }
+}
+
+impl LogStreamHub {
+    pub fn new(replay_capacity: usize, channel_capacity: usize, max_subscribers: usize) -> Self {
+        let (sender, _) = broadcast::channel(channel_capacity.max(1));
+        Self {
+            buffer: Mutex::new(ReplayBuffer {
+                next_seq: 1,
+                records: VecDeque::with_capacity(replay_capacity),
+            }),
+            sender,
+            replay_capacity,
+            max_subscribers,
+            subscribers: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.load(Ordering::SeqCst)
+    }
+
+    /// Sequence of the newest published record, `0` before the first one.
+    pub fn latest_seq(&self) -> u64 {
+        self.buffer.lock().unwrap().next_seq - 1
+    }
+
+    pub fn publish(&self, records: &LogRecords) {
+        if records.logs.is_empty() {
+            return;
+        }
+
+        // Sequence assignment and broadcast happen under the buffer lock, so a
+        // concurrent subscribe sees every record either in replay or live.
+        let mut buffer = self.buffer.lock().unwrap();
+        for log in &records.logs {
+            let record = Arc::new(StreamLogRecord {
+                seq: buffer.next_seq,
+                node: records.node.clone(),
+                service: records.service.clone(),
+                log: log.clone(),
+            });
+            buffer.next_seq += 1;
+
+            if self.replay_capacity > 0 {
+                if buffer.records.len() >= self.replay_capacity {
+                    buffer.records.pop_front();
+                }
+                buffer.records.push_back(record.clone());
+            }
+            // No receivers is not an error for a live tail.
+            let _ = self.sender.send(record);
+        }
+    }
+
+    /// Subscribe to records published from now on, or replay the buffered
+    /// records after `since_seq` first.
+    pub fn subscribe(
+        &self,
+        filter: LogStreamFilter,
+        since_seq: Option<u64>,
+    ) -> Result<LogStreamSubscription, String> {
+        let filter = CompiledStreamFilter::new(filter)?;
+
+        let previous = self.subscribers.fetch_add(1, Ordering::SeqCst);
+        if previous >= self.max_subscribers {
+            self.subscribers.fetch_sub(1, Ordering::SeqCst);
+            let msg = format!(
+                "too many log stream subscribers: limit is {}",
+                self.max_subscribers
+            );
+            warn!("{}", msg);
+            return Err(msg);
+        }
+
+        let buffer = self.buffer.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let latest_seq = buffer.next_seq - 1;
+        let mut pending = VecDeque::new();
+        // A `since_seq` ahead of the hub comes from before a server restart and is ignored.
+        if let Some(since_seq) = since_seq.filter(|seq| *seq < latest_seq) {
+            let oldest_seq = buffer
+                .records
+                .front()
+                .map(|r| r.seq)
+                .unwrap_or(latest_seq + 1);
+            if oldest_seq > since_seq + 1 {
+                pending.push_back(LogStreamEvent::Lagged {
+                    missed: oldest_seq - since_seq - 1,
+                });
+            }
+            for record in buffer.records.iter().filter(|r| r.seq > since_seq) {
+                if filter.matches(record) {
+                    pending.push_back(LogStreamEvent::Record {
+                        record: record.clone(),
+                        highlights: filter.matcher.highlights(&record.log.content),
+                    });
+                }
+            }
+        }
+        drop(buffer);
+
+        Ok(LogStreamSubscription {
+            receiver,
+            filter,
+            pending,
+            start_seq: latest_seq,
+            last_seq: latest_seq,
+            subscribers: self.subscribers.clone(),
+        })
+    }
+}
+
+pub struct LogStreamSubscription {
+    receiver: broadcast::Receiver<Arc<StreamLogRecord>>,
+    filter: CompiledStreamFilter,
+    pending: VecDeque<LogStreamEvent>,
+    start_seq: u64,
+    last_seq: u64,
+    subscribers: Arc<AtomicUsize>,
+}
+
+impl LogStreamSubscription {
+    /// Hub sequence at subscribe time; live records start after it.
+    pub fn start_seq(&self) -> u64 {
+        self.start_seq
+    }
+
+    /// Next matching event, `None` once the hub is gone.
+    pub async fn next(&mut self) -> Option<LogStreamEvent> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(event);
+        }
+
+        loop {
+            match self.receiver.recv().await {
+                Ok(record) => {
+                    // Already delivered from the replay buffer.
+                    if record.seq <= self.last_seq {
+                        continue;
+                    }
+                    self.last_seq = record.seq;
+                    if self.filter.matches(&record) {
+                        let highlights = self.filter.matcher.highlights(&record.log.content);
+                        return Some(LogStreamEvent::Record { record, highlights });
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    return Some(LogStreamEvent::Lagged { missed });
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for LogStreamSubscription {
+    fn drop(&mut self) {
+        self.subscribers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(node: &str, service: &str, rows: &[(LogLevel, &str)]) -> LogRecords {
+        LogRecords {
+            node: node.to_string(),
+            service: service.to_string(),
+            batch_id: None,
+            record_ids: vec![],
+            logs: rows
+                .iter()
+                .map(|(level, content)| SystemLogRecord::easy_log(*level, content.to_string()))
+                .collect(),
+        }
+    }
+
+    async fn next_record(subscription: &mut LogStreamSubscription) -> (u64, String) {
+        match subscription.next().await.unwrap() {
+            LogStreamEvent::Record { record, .. } => (record.seq, record.log.content.clone()),
+            LogStreamEvent::Lagged { missed } => panic!("unexpected lag of {}", missed),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_hub_filters_by_node_service_level_and_keyword() {
+        let hub = LogStreamHub::default();
+        let mut subscription = hub
+            .subscribe(
+                LogStreamFilter {
+                    nodes: vec!["node-a".to_string()],
+                    services: vec!["svc-1".to_string(), "svc-2".to_string()],
+                    levels: vec![LogLevel::Error, LogLevel::Warn],
+                    search: Some(LogSearchFilter {
+                        keyword: Some("disk".to_string()),
+                        ..Default::default()
+                    }),
+                },
+                None,
+            )
+            .unwrap();
+
+        hub.publish(&records(
+            "node-b",
+            "svc-1",
+            &[(LogLevel::Error, "disk full")],
+        ));
+        hub.publish(&records(
+            "node-a",
+            "svc-3",
+            &[(LogLevel::Error, "disk full")],
+        ));
+        hub.publish(&records(
+            "node-a",
+            "svc-2",
+            &[
+                (LogLevel::Info, "disk ok"),
+                (LogLevel::Warn, "net down"),
+                (LogLevel::Warn, "Disk slow"),
+            ],
+        ));
+
+        match subscription.next().await.unwrap() {
+            LogStreamEvent::Record { record, highlights } => {
+                assert_eq!(record.seq, 5);
+                assert_eq!(record.service, "svc-2");
+                assert_eq!(record.log.content, "Disk slow");
+                assert_eq!(highlights, vec![LogHighlight { start: 0, end: 4 }]);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(hub.subscriber_count(), 1);
+        drop(subscription);
+        assert_eq!(hub.subscriber_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_hub_replays_since_seq_without_duplicates() {
+        let hub = LogStreamHub::new(3, 16, 4);
+        hub.publish(&records(
+            "node-a",
+            "svc-1",
+            &[
+                (LogLevel::Info, "r1"),
+                (LogLevel::Info, "r2"),
+                (LogLevel::Info, "r3"),
+                (LogLevel::Info, "r4"),
+            ],
+        ));
+        assert_eq!(hub.latest_seq(), 4);
+
+        // r1 fell out of the replay buffer.
+        let mut subscription = hub.subscribe(LogStreamFilter::default(), Some(0)).unwrap();
+        hub.publish(&records("node-a", "svc-1", &[(LogLevel::Info, "r5")]));
+        match subscription.next().await.unwrap() {
+            LogStreamEvent::Lagged { missed } => assert_eq!(missed, 1),
+            other => panic!("unexpected event {:?}", other),
+        }
+        for expected in ["r2", "r3", "r4", "r5"] {
+            assert_eq!(next_record(&mut subscription).await.1, expected);
+        }
+
+        let mut resumed = hub.subscribe(LogStreamFilter::default(), Some(4)).unwrap();
+        assert_eq!(next_record(&mut resumed).await, (5, "r5".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_stream_hub_reports_lag_for_slow_subscriber_and_caps_subscribers() {
+        let hub = LogStreamHub::new(0, 2, 1);
+        let mut slow = hub.subscribe(LogStreamFilter::default(), None).unwrap();
+        assert!(hub.subscribe(LogStreamFilter::default(), None).is_err());
+
+        hub.publish(&records(
+            "node-a",
+            "svc-1",
+            &[
+                (LogLevel::Info, "r1"),
+                (LogLevel::Info, "r2"),
+                (LogLevel::Info, "r3"),
+                (LogLevel::Info, "r4"),
+            ],
+        ));
+        match slow.next().await.unwrap() {
+            LogStreamEvent::Lagged { missed } => assert_eq!(missed, 2),
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(next_record(&mut slow).await.1, "r3");
+        assert_eq!(next_record(&mut slow).await.1, "r4");
+
+        drop(slow);
+        assert!(hub.subscribe(LogStreamFilter::default(), None).is_ok());
+    }
+}
@@ -197,6 +197,14 @@ pub fn new_device_config_by_jwk_with_did(
     bind_device_config_did(DeviceDocument::new_by_jwk(name, public_key), device_did)
 }
 
+/// Zone owner public key from `etc_dir/node_identity.json`, usable before
+/// system config holds `boot/config`.
+pub fn load_local_owner_public_key(etc_dir: &Path) -> std::result::Result<DecodingKey, String> {
+    let node_identity = load_local_node_identity_config(&etc_dir.join("node_identity.json"))?;
+    DecodingKey::from_jwk(&node_identity.owner_public_key)
+        .map_err(|err| format!("parse owner public key failed: {}", err))
+}
+
 pub fn load_device_doc_jwt(device_did: &DID) -> std::result::Result<String, String> {
     let paths = device_identity_paths(device_did)?;
     fs::read_to_string(paths.device_doc_jwt.as_path()).map_err(|err| {
@@ -330,6 +338,20 @@ pub fn save_zone_document_jwt(
 }
 
 pub fn atomic_write(path: &Path, content: &[u8]) -> std::result::Result<(), String> {
+    atomic_write_with_options(path, content, false)
+}
+
+/// Like `atomic_write`, for files holding secrets: on unix the file is
+/// created readable by its owner only.
+pub fn atomic_write_private(path: &Path, content: &[u8]) -> std::result::Result<(), String> {
+    atomic_write_with_options(path, content, true)
+}
+
+fn atomic_write_with_options(
+    path: &Path,
+    content: &[u8],
+    private: bool,
+) -> std::result::Result<(), String> {
     let parent = path
         .parent()
         .ok_or_else(|| format!("{} has no parent directory", path.display()))?;
@@ -349,9 +371,16 @@ pub fn atomic_write(path: &Path, content: &[u8]) -> std::result::Result<(), Stri
         std::process::id(),
         nonce
     ));
-    let mut temp_file = OpenOptions::new()
-        .create_new(true)
-        .write(true)
+    let mut options = OpenOptions::new();
+    options.create_new(true).write(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    let mut temp_file = options
         .open(temp_path.as_path())
         .map_err(|err| format!("create {} failed: {}", temp_path.display(), err))?;
     temp_file
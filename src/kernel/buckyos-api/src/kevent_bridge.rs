@@ -20,10 +20,16 @@
 //!   cannot mis-pair responses.
 //! * Closing a reader just drops its connection: the daemon reclaims every
 //!   reader registered on that connection, which doubles as crash cleanup.
+//! * With a session token configured, every connection opens with an
+//!   `authenticate` request. The token is read again on each connect, so a
+//!   rotated token takes effect at the next reconnect.
 //!
-//! Recovery is "reconnect, then re-register the full set". Nothing is
-//! replayed — KEvent is an allowed-to-drop notification channel and
-//! consumers keep their own polling backstop.
+//! Recovery is "reconnect, re-register the full set, then pull after the
+//! last seen `seq`". The daemon replays what its bounded journal still holds
+//! for the gap, so delivery across a reconnect is at-least-once and the link
+//! drops the copies it already delivered. Anything older than the journal is
+//! gone — KEvent stays an allowed-to-drop notification channel and consumers
+//! keep their own polling backstop.
 
 use crate::kevent_client::{
     Event, KEventDaemonRequest, KEventDaemonResponse, KEventError, KEventResult,
@@ -103,6 +109,9 @@ pub struct KEventTransportStatus {
     /// Connections established, including the first one of each link.
     pub reconnects: u64,
     pub events_received: u64,
+    /// Redelivered events (`seq` at or below the reader's cursor) dropped
+    /// after a reconnect.
+    pub duplicates_dropped: u64,
     pub publishes_dropped: u64,
     pub last_error: Option<String>,
     pub last_error_at_ms: u64,
@@ -115,6 +124,7 @@ struct BridgeStats {
     total_failures: AtomicU64,
     reconnects: AtomicU64,
     events_received: AtomicU64,
+    duplicates_dropped: AtomicU64,
     publishes_dropped: AtomicU64,
     live_readers: AtomicU64,
     connected_readers: AtomicU64,
@@ -180,6 +190,7 @@ impl BridgeStats {
             total_failures: self.total_failures.load(Ordering::Relaxed),
             reconnects: self.reconnects.load(Ordering::Relaxed),
             events_received: self.events_received.load(Ordering::Relaxed),
+            duplicates_dropped: self.duplicates_dropped.load(Ordering::Relaxed),
             publishes_dropped: self.publishes_dropped.load(Ordering::Relaxed),
             last_error: self.last_error.lock().ok().and_then(|slot| slot.clone()),
             last_error_at_ms: self.last_error_at_ms.load(Ordering::Relaxed),
@@ -188,12 +199,42 @@ impl BridgeStats {
     }
 }
 
+/// Source of the session token a bridge presents to the daemon. Asked on
+/// every new connection rather than once, because tokens are renewed.
+#[async_trait]
+pub trait KEventSessionTokenProvider: Send + Sync {
+    /// `None` (or an empty token) connects unauthenticated.
+    async fn session_token(&self) -> Option<String>;
+}
+
+type SessionTokenSource = Option<Arc<dyn KEventSessionTokenProvider>>;
+
 /// One TCP connection carrying strictly ordered request/response frames.
 struct FramedConnection {
     stream: TcpStream,
 }
 
 impl FramedConnection {
+    /// Connect and, when a token is available, authenticate the connection
+    /// before handing it out. A rejected token surfaces as the daemon's
+    /// error, not as a transport failure.
+    async fn open(endpoint: &str, session_token: &SessionTokenSource) -> KEventResult<Self> {
+        let mut conn = Self::connect(endpoint).await?;
+        let token = match session_token {
+            Some(provider) => provider.session_token().await,
+            None => None,
+        };
+        if let Some(session_token) = token.filter(|token| !token.trim().is_empty()) {
+            conn.request(
+                &KEventDaemonRequest::Authenticate { session_token },
+                REQUEST_TIMEOUT,
+            )
+            .await
+            .and_then(response_to_result)?;
+        }
+        Ok(conn)
+    }
+
     async fn connect(endpoint: &str) -> KEventResult<Self> {
         let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(endpoint))
             .await
@@ -272,6 +313,7 @@ fn response_to_result(resp: KEventDaemonResponse) -> KEventResult<Option<Event>>
             "TIMER_NOT_FOUND" => KEventError::TimerNotFound(message),
             "NOT_SUPPORTED" => KEventError::NotSupported(message),
             "READER_CLOSED" => KEventError::ReaderClosed(message),
+            "PERMISSION_DENIED" => KEventError::PermissionDenied(message),
             _ => KEventError::Internal(message),
         }),
     }
@@ -282,6 +324,7 @@ fn response_to_result(resp: KEventDaemonResponse) -> KEventResult<Option<Event>>
 /// concurrent senders would read each other's responses).
 struct PublisherLink {
     endpoint: String,
+    session_token: SessionTokenSource,
     conn: Mutex<Option<FramedConnection>>,
     /// Single-flight backoff gate: while a connect is failing we fail fast
     /// instead of opening a socket per publish.
@@ -291,9 +334,10 @@ struct PublisherLink {
 }
 
 impl PublisherLink {
-    fn new(endpoint: String, stats: Arc<BridgeStats>) -> Self {
+    fn new(endpoint: String, session_token: SessionTokenSource, stats: Arc<BridgeStats>) -> Self {
         Self {
             endpoint,
+            session_token,
             conn: Mutex::new(None),
             next_connect_at_ms: AtomicU64::new(0),
             attempt: AtomicU32::new(0),
@@ -307,7 +351,8 @@ impl PublisherLink {
         // Reuse the live connection first. Once a request has been written,
         // a missing response leaves delivery ambiguous: the daemon may already
         // have distributed the event. Never replay that event on a fresh
-        // connection because Event has no id that subscribers can deduplicate.
+        // connection: the daemon assigns `seq` on acceptance, so a second copy
+        // would get a new one and subscribers could not tell the two apart.
         if guard.is_some() {
             let conn = guard.as_mut().expect("checked above");
             match conn
@@ -350,8 +395,9 @@ impl PublisherLink {
             )));
         }
 
-        let mut conn = match FramedConnection::connect(&self.endpoint).await {
+        let mut conn = match FramedConnection::open(&self.endpoint, &self.session_token).await {
             Ok(conn) => conn,
+            Err(err) if !is_transport_error(&err) => return Err(err),
             Err(err) => {
                 let attempt = self.attempt.fetch_add(1, Ordering::Relaxed);
                 self.next_connect_at_ms.store(
@@ -425,12 +471,22 @@ pub trait BridgeReaderSink: Send + Sync {
 /// The bridge as seen by `KEventClient`.
 pub struct KEventDaemonBridgeTransport {
     endpoint: String,
+    session_token: SessionTokenSource,
     publisher: PublisherLink,
     stats: Arc<BridgeStats>,
 }
 
 impl KEventDaemonBridgeTransport {
     pub fn new(endpoint: impl Into<String>) -> KEventResult<Self> {
+        Self::new_with_session_token(endpoint, None)
+    }
+
+    /// Bridge whose connections authenticate with `session_token`, for
+    /// daemons that enforce publish / subscribe permissions.
+    pub fn new_with_session_token(
+        endpoint: impl Into<String>,
+        session_token: SessionTokenSource,
+    ) -> KEventResult<Self> {
         let endpoint = endpoint.into().trim().to_string();
         if endpoint.is_empty() {
             return Err(KEventError::DaemonUnavailable(
@@ -439,8 +495,9 @@ impl KEventDaemonBridgeTransport {
         }
         let stats = Arc::new(BridgeStats::default());
         Ok(Self {
-            publisher: PublisherLink::new(endpoint.clone(), stats.clone()),
+            publisher: PublisherLink::new(endpoint.clone(), session_token.clone(), stats.clone()),
             endpoint,
+            session_token,
             stats,
         })
     }
@@ -462,11 +519,13 @@ impl KEventDaemonBridgeTransport {
     }
 
     /// Start the dedicated connection for one reader: register the full
-    /// pattern set, then long-poll until the reader is closed.
+    /// pattern set, then long-poll until the reader is closed. `after_seq`
+    /// resumes a reader whose previous incarnation saw events up to it.
     pub fn spawn_reader(
         self: &Arc<Self>,
         reader_id: String,
         sink: Arc<dyn BridgeReaderSink>,
+        after_seq: Option<u64>,
     ) -> Arc<KEventBridgeReaderLink> {
         let shared = Arc::new(ReaderLinkShared {
             dirty: AtomicBool::new(false),
@@ -479,7 +538,7 @@ impl KEventDaemonBridgeTransport {
         // forever. It only shares the dirty/stopped flags.
         let task_shared = shared.clone();
         let handle = tokio::spawn(async move {
-            run_reader_link(transport, reader_id, sink, task_shared).await;
+            run_reader_link(transport, reader_id, sink, task_shared, after_seq).await;
         });
 
         Arc::new(KEventBridgeReaderLink {
@@ -574,12 +633,16 @@ async fn run_reader_link(
     reader_id: String,
     sink: Arc<dyn BridgeReaderSink>,
     link: Arc<ReaderLinkShared>,
+    after_seq: Option<u64>,
 ) {
     let endpoint = transport.endpoint.clone();
+    let session_token = transport.session_token.clone();
     let stats = transport.stats.clone();
     let link_name = format!("reader {}", reader_id);
     let gauge = ReaderGauge::new(stats.clone());
     let mut attempt: u32 = 0;
+    // Survives reconnects: every pull asks the daemon to resume after it.
+    let mut cursor = after_seq;
 
     loop {
         if link.stopped.load(Ordering::Relaxed) {
@@ -603,7 +666,9 @@ async fn run_reader_link(
             Some(patterns) => patterns,
         };
 
-        let mut conn = match FramedConnection::connect(&endpoint).await {
+        // A rejected token is retried like any other failure: the grant may
+        // show up later, and the backoff keeps a denied reader cheap.
+        let mut conn = match FramedConnection::open(&endpoint, &session_token).await {
             Ok(conn) => conn,
             Err(err) => {
                 stats.record_failure(&link_name, &endpoint, &err);
@@ -639,7 +704,8 @@ async fn run_reader_link(
             }
         }
 
-        let disconnect_reason = pump_reader(&mut conn, &reader_id, &sink, &link, &stats).await;
+        let disconnect_reason =
+            pump_reader(&mut conn, &reader_id, &sink, &link, &stats, &mut cursor).await;
         gauge.set_connected(false);
         drop(conn);
 
@@ -675,6 +741,7 @@ async fn pump_reader(
     sink: &Arc<dyn BridgeReaderSink>,
     link: &Arc<ReaderLinkShared>,
     stats: &Arc<BridgeStats>,
+    cursor: &mut Option<u64>,
 ) -> PumpExit {
     let mut reader_closed_streak = 0_u32;
     loop {
@@ -710,6 +777,7 @@ async fn pump_reader(
                 &KEventDaemonRequest::PullEvent {
                     reader_id: reader_id.to_string(),
                     timeout_ms: Some(BRIDGE_PULL_TIMEOUT_MS),
+                    after_seq: *cursor,
                 },
                 Duration::from_millis(BRIDGE_PULL_TIMEOUT_MS) + PULL_READ_SLACK,
             )
@@ -723,6 +791,16 @@ async fn pump_reader(
                     return PumpExit::Stopped;
                 }
                 stats.events_received.fetch_add(1, Ordering::Relaxed);
+                if let Some(seq) = event.seq {
+                    // At-least-once resume may hand back what we already
+                    // delivered; the daemon's ids are monotonic, so anything
+                    // at or below the cursor is a copy.
+                    if cursor.is_some_and(|seen| seq <= seen) {
+                        stats.duplicates_dropped.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    *cursor = Some(seq);
+                }
                 sink.deliver(event).await;
             }
             Ok(None) => {
@@ -792,7 +870,7 @@ mod tests {
             stream.read_exact(&mut frame).await.unwrap();
         });
 
-        let publisher = PublisherLink::new(endpoint, Arc::new(BridgeStats::default()));
+        let publisher = PublisherLink::new(endpoint, None, Arc::new(BridgeStats::default()));
         let event = Event {
             eventid: "/publisher/backoff".to_string(),
             source_node: "test".to_string(),
@@ -800,6 +878,7 @@ mod tests {
             ingress_node: Some("test".to_string()),
             timestamp: now_millis(),
             data: json!({}),
+            seq: None,
         };
 
         let err = publisher.publish(&event).await.unwrap_err();
@@ -809,6 +888,70 @@ mod tests {
         assert!(publisher.next_connect_at_ms.load(Ordering::Relaxed) > now_millis());
     }
 
+    struct FixedToken(&'static str);
+
+    #[async_trait]
+    impl KEventSessionTokenProvider for FixedToken {
+        async fn session_token(&self) -> Option<String> {
+            Some(self.0.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn publisher_authenticates_first_and_denials_are_not_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut ops = Vec::new();
+            for response in [
+                KEventDaemonResponse::Ok { event: None },
+                KEventDaemonResponse::Err {
+                    code: "PERMISSION_DENIED".to_string(),
+                    message: "no publish right on /system/secret".to_string(),
+                },
+            ] {
+                let frame_len = stream.read_u32().await.unwrap() as usize;
+                let mut frame = vec![0_u8; frame_len];
+                stream.read_exact(&mut frame).await.unwrap();
+                ops.push(serde_json::from_slice::<KEventDaemonRequest>(&frame).unwrap());
+                let payload = serde_json::to_vec(&response).unwrap();
+                stream.write_u32(payload.len() as u32).await.unwrap();
+                stream.write_all(&payload).await.unwrap();
+            }
+            ops
+        });
+
+        let publisher = PublisherLink::new(
+            endpoint,
+            Some(Arc::new(FixedToken("token-a"))),
+            Arc::new(BridgeStats::default()),
+        );
+        let event = Event {
+            eventid: "/system/secret".to_string(),
+            source_node: "test".to_string(),
+            source_pid: std::process::id(),
+            ingress_node: Some("test".to_string()),
+            timestamp: now_millis(),
+            data: json!({}),
+            seq: None,
+        };
+
+        let err = publisher.publish(&event).await.unwrap_err();
+        assert!(matches!(err, KEventError::PermissionDenied(_)));
+        assert!(!is_transport_error(&err));
+        assert_eq!(publisher.attempt.load(Ordering::Relaxed), 0);
+
+        let ops = server.await.unwrap();
+        assert_eq!(
+            ops[0],
+            KEventDaemonRequest::Authenticate {
+                session_token: "token-a".to_string()
+            }
+        );
+        assert!(matches!(ops[1], KEventDaemonRequest::PublishGlobal { .. }));
+    }
+
     struct BlockingPatternSink {
         entered: Notify,
         release: Notify,
@@ -825,6 +968,83 @@ mod tests {
         async fn deliver(&self, _event: Event) {}
     }
 
+    struct CollectingSink {
+        delivered: Mutex<Vec<Event>>,
+    }
+
+    #[async_trait]
+    impl BridgeReaderSink for CollectingSink {
+        async fn global_patterns(&self) -> Option<Vec<String>> {
+            Some(vec!["/dedup/**".to_string()])
+        }
+
+        async fn deliver(&self, event: Event) {
+            self.delivered.lock().await.push(event);
+        }
+    }
+
+    #[tokio::test]
+    async fn resumed_pulls_carry_the_cursor_and_drop_redeliveries() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut cursors = Vec::new();
+            // The second 7 is what an at-least-once replay looks like.
+            for seq in [7_u64, 7, 8] {
+                let frame_len = stream.read_u32().await.unwrap() as usize;
+                let mut frame = vec![0_u8; frame_len];
+                stream.read_exact(&mut frame).await.unwrap();
+                match serde_json::from_slice(&frame).unwrap() {
+                    KEventDaemonRequest::PullEvent { after_seq, .. } => cursors.push(after_seq),
+                    other => panic!("unexpected request: {:?}", other),
+                }
+                let payload = serde_json::to_vec(&KEventDaemonResponse::Ok {
+                    event: Some(Event {
+                        eventid: "/dedup/event".to_string(),
+                        source_node: "test".to_string(),
+                        source_pid: 1,
+                        ingress_node: Some("test".to_string()),
+                        timestamp: now_millis(),
+                        data: json!({}),
+                        seq: Some(seq),
+                    }),
+                })
+                .unwrap();
+                stream.write_u32(payload.len() as u32).await.unwrap();
+                stream.write_all(&payload).await.unwrap();
+            }
+            cursors
+        });
+
+        let mut conn = FramedConnection::connect(&endpoint).await.unwrap();
+        let link = Arc::new(ReaderLinkShared {
+            dirty: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+        });
+        let sink = Arc::new(CollectingSink {
+            delivered: Mutex::new(Vec::new()),
+        });
+        let task_sink: Arc<dyn BridgeReaderSink> = sink.clone();
+        let stats = Arc::new(BridgeStats::default());
+        let mut cursor = Some(6);
+
+        // The mock daemon hangs up after three pulls, which ends the pump.
+        let exit = pump_reader(&mut conn, "r1", &task_sink, &link, &stats, &mut cursor).await;
+        assert!(matches!(exit, PumpExit::Failed(_)));
+        assert_eq!(server.await.unwrap(), vec![Some(6), Some(7), Some(7)]);
+        let delivered: Vec<Option<u64>> = sink
+            .delivered
+            .lock()
+            .await
+            .iter()
+            .map(|event| event.seq)
+            .collect();
+        assert_eq!(delivered, vec![Some(7), Some(8)]);
+        assert_eq!(cursor, Some(8));
+        assert_eq!(stats.duplicates_dropped.load(Ordering::Relaxed), 1);
+    }
+
     #[tokio::test]
     async fn pattern_edit_racing_with_snapshot_remains_dirty() {
         let link = Arc::new(ReaderLinkShared {
@@ -1,6 +1,6 @@
 use crate::kevent_bridge::{
     is_transport_error, BridgeReaderSink, KEventBridgeReaderLink, KEventDaemonBridgeTransport,
-    KEventTransportStatus,
+    KEventSessionTokenProvider, KEventTransportStatus,
 };
 use crate::kevent_ringbuffer::SharedKEventRingBuffer;
 use crate::{AppDoc, AppType, SelectorType};
@@ -75,6 +75,8 @@ pub enum KEventError {
     NotSupported(String),
     #[error("READER_CLOSED: {0}")]
     ReaderClosed(String),
+    #[error("PERMISSION_DENIED: {0}")]
+    PermissionDenied(String),
     #[error("INTERNAL: {0}")]
     Internal(String),
 }
@@ -91,6 +93,7 @@ impl KEventError {
             KEventError::TimerNotFound(_) => "TIMER_NOT_FOUND",
             KEventError::NotSupported(_) => "NOT_SUPPORTED",
             KEventError::ReaderClosed(_) => "READER_CLOSED",
+            KEventError::PermissionDenied(_) => "PERMISSION_DENIED",
             KEventError::Internal(_) => "INTERNAL",
         }
     }
@@ -105,6 +108,12 @@ pub struct Event {
     pub ingress_node: Option<String>,
     pub timestamp: u64,
     pub data: Value,
+    /// Sequence id assigned by the node-daemon that delivered this event.
+    /// Monotonic per daemon, so readers use it as a resume cursor and to drop
+    /// redelivered copies. `None` for local events and for events that never
+    /// went through a daemon.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -150,8 +159,8 @@ pub enum KEventClientMode {
 /// The two real transports are mutually exclusive and are chosen when the
 /// client is built — never by probing at runtime. The daemon mirrors every
 /// global event it accepts into the shared ring, so a process that listened
-/// on both would receive each event twice, and `Event` has no id to
-/// deduplicate on.
+/// on both would receive each event twice, and events that other processes
+/// wrote straight into the ring carry no `seq` to deduplicate on.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KEventTransportKind {
     /// No global channel at all (`Local` / `LocalPubOnly`).
@@ -201,6 +210,11 @@ impl KEventTransport {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "op", rename_all = "snake_case")]
 pub enum KEventDaemonRequest {
+    /// Bind a session token to the connection. A daemon that enforces RBAC
+    /// checks every later register / publish on the connection against it.
+    Authenticate {
+        session_token: String,
+    },
     RegisterReader {
         reader_id: String,
         patterns: Vec<String>,
@@ -221,6 +235,11 @@ pub enum KEventDaemonRequest {
     PullEvent {
         reader_id: String,
         timeout_ms: Option<u64>,
+        /// Highest `seq` the client has already seen. The daemon drops queued
+        /// copies up to it and replays newer journaled events the reader
+        /// missed while it was not registered.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        after_seq: Option<u64>,
     },
 }
 
@@ -440,6 +459,9 @@ pub struct EventReader {
     mode: KEventClientMode,
     has_global_patterns: bool,
     closed: AtomicBool,
+    /// `seq` of the newest daemon-sequenced event handed to the caller; 0
+    /// until the first one (daemon sequence ids start far above zero).
+    last_seq: AtomicU64,
 }
 
 impl KEventClient {
@@ -518,6 +540,26 @@ impl KEventClient {
         ))
     }
 
+    /// Daemon-bridge client that authenticates every connection with the
+    /// caller's session token, so an RBAC-enforcing daemon can check what it
+    /// publishes and subscribes to.
+    pub fn new_daemon_bridge_with_session_token(
+        source_node: impl Into<String>,
+        endpoint: impl Into<String>,
+        session_token: Arc<dyn KEventSessionTokenProvider>,
+    ) -> KEventResult<Self> {
+        let transport = Arc::new(KEventDaemonBridgeTransport::new_with_session_token(
+            endpoint,
+            Some(session_token),
+        )?);
+        Ok(Self::build(
+            source_node,
+            KEventClientMode::Full,
+            KEventTransport::DaemonBridge(transport),
+            DEFAULT_READER_CAPACITY,
+        ))
+    }
+
     /// Light client (global publish only) over the native TCP bridge.
     pub fn new_light_daemon_bridge(
         source_node: impl Into<String>,
@@ -603,6 +645,32 @@ impl KEventClient {
     }
 
     pub async fn create_event_reader(&self, patterns: Vec<String>) -> KEventResult<EventReader> {
+        self.create_reader(patterns, None).await
+    }
+
+    /// Like `create_event_reader`, but resumes after `after_seq` — typically
+    /// the `EventReader::last_seq` a previous incarnation of this process
+    /// persisted. The daemon replays what it still has in its journal, so
+    /// delivery is at-least-once; copies the caller already saw are dropped.
+    /// Only the daemon bridge can replay, the shared ring has no history.
+    pub async fn create_event_reader_after(
+        &self,
+        patterns: Vec<String>,
+        after_seq: u64,
+    ) -> KEventResult<EventReader> {
+        if !matches!(self.inner.transport, KEventTransport::DaemonBridge(_)) {
+            return Err(KEventError::NotSupported(
+                "only the daemon bridge can resume a reader".to_string(),
+            ));
+        }
+        self.create_reader(patterns, Some(after_seq)).await
+    }
+
+    async fn create_reader(
+        &self,
+        patterns: Vec<String>,
+        after_seq: Option<u64>,
+    ) -> KEventResult<EventReader> {
         if patterns.is_empty() {
             return Err(KEventError::InvalidPattern(
                 "patterns must not be empty".to_string(),
@@ -654,7 +722,7 @@ impl KEventClient {
                         inner: Arc::downgrade(&self.inner),
                         reader_id: reader_id.clone(),
                     });
-                    bridge_link = Some(bridge.spawn_reader(reader_id.clone(), sink));
+                    bridge_link = Some(bridge.spawn_reader(reader_id.clone(), sink, after_seq));
                 }
                 KEventTransport::None | KEventTransport::PublishOnlyBridge(_) => {}
             }
@@ -667,6 +735,7 @@ impl KEventClient {
             mode: self.mode,
             has_global_patterns,
             closed: AtomicBool::new(false),
+            last_seq: AtomicU64::new(after_seq.unwrap_or(0)),
         })
     }
 
@@ -685,6 +754,7 @@ impl KEventClient {
             },
             timestamp: now_millis(),
             data,
+            seq: None,
         };
 
         let is_global = is_global_eventid(eventid);
@@ -693,8 +763,8 @@ impl KEventClient {
         // drain, so a local dispatch is the only way we see our own global
         // events there. Over the bridge the daemon *does* route the event
         // back to us, so dispatching locally as well would deliver it twice —
-        // and `Event` carries no id to dedupe on. Let the daemon be the single
-        // ordering source instead.
+        // and the local copy has no `seq` yet to dedupe against the daemon's.
+        // Let the daemon be the single ordering source instead.
         let dispatch_locally =
             !is_global || !matches!(self.inner.transport, KEventTransport::DaemonBridge(_));
         if dispatch_locally {
@@ -932,6 +1002,22 @@ impl EventReader {
         &self.reader_id
     }
 
+    /// `seq` of the newest daemon-sequenced event this reader returned (or
+    /// the cursor it was resumed from). Persist it to resume with
+    /// `KEventClient::create_event_reader_after` after a restart.
+    pub fn last_seq(&self) -> Option<u64> {
+        match self.last_seq.load(Ordering::Relaxed) {
+            0 => None,
+            seq => Some(seq),
+        }
+    }
+
+    fn note_pulled(&self, event: &Event) {
+        if let Some(seq) = event.seq {
+            self.last_seq.fetch_max(seq, Ordering::Relaxed);
+        }
+    }
+
     /// Returns the current pattern set for this reader. Useful for tests
     /// and observability; not part of the hot path.
     pub async fn patterns(&self) -> Vec<String> {
@@ -1094,6 +1180,7 @@ impl EventReader {
             .ok_or_else(|| KEventError::ReaderClosed(self.reader_id.clone()))?;
 
             if let Some(event) = state.pop().await {
+                self.note_pulled(&event);
                 return Ok(Some(event));
             }
 
@@ -1128,6 +1215,7 @@ impl EventReader {
                         _ = tokio::time::sleep(remain) => {
                             // Final drain attempt before returning timeout
                             if let Some(event) = state.pop().await {
+                                self.note_pulled(&event);
                                 return Ok(Some(event));
                             }
                             return Ok(None);
@@ -11,6 +11,7 @@ mod msg_center_client;
 pub mod msg_queue;
 mod scheduler_client;
 mod system_config;
+mod system_config_archive;
 mod task_dispatcher;
 mod task_mgr;
 mod taskdata;
@@ -39,6 +40,7 @@ mod permission;
 mod rbac_config;
 mod rdb_mgr;
 mod repo_client;
+mod rpc_log_context;
 mod runtime;
 pub mod test_config;
 
@@ -60,8 +62,10 @@ pub use device_identity::*;
 pub use group_mgr::*;
 pub use msg_center_client::*;
 pub use repo_client::*;
+pub use rpc_log_context::*;
 pub use scheduler_client::*;
 pub use system_config::*;
+pub use system_config_archive::*;
 pub use task_dispatcher::*;
 pub use task_mgr::*;
 pub use taskdata::*;
@@ -6,7 +6,7 @@ use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::net::IpAddr;
 
-use crate::{AppDoc, AppType, SelectorType};
+use crate::{rpc_log_context, AppDoc, AppType, SelectorType};
 
 pub const KMSG_SERVICE_UNIQUE_ID: &str = "kmsg";
 pub const KMSG_SERVICE_NAME: &str = "kmsg";
@@ -71,6 +71,15 @@ pub struct QueueConfig {
     pub retention_seconds: Option<u64>,
     /// 是否需要同步落盘 (Write-Ahead-Log 语义)
     pub sync_write: bool,
+    /// 未 ack 的消息投递后多久 (毫秒) 才会被再次投递；None 表示不隐藏，下次 fetch 立即重投
+    #[serde(default)]
+    pub visibility_timeout_ms: Option<u64>,
+    /// 单条消息对同一订阅的最大投递次数，用尽后转入死信队列；None 表示不限制
+    #[serde(default)]
+    pub max_delivery_attempts: Option<u32>,
+    /// 死信队列；未配置时，投递次数用尽的消息直接丢弃
+    #[serde(default)]
+    pub dead_letter_queue: Option<QueueUrn>,
 
     pub other_app_can_read: bool,
     pub other_app_can_write: bool,
@@ -84,6 +93,9 @@ impl Default for QueueConfig {
             max_messages: None,
             retention_seconds: None,
             sync_write: false,
+            visibility_timeout_ms: None,
+            max_delivery_attempts: None,
+            dead_letter_queue: None,
             other_app_can_read: true,
             other_app_can_write: false,
             other_user_can_read: false,
@@ -92,6 +104,18 @@ impl Default for QueueConfig {
     }
 }
 
+/// 转入死信队列的消息额外携带的 header，原有 header 保留
+pub const DEAD_LETTER_HEADER_SOURCE_QUEUE: &str = "x-dead-letter-source-queue";
+pub const DEAD_LETTER_HEADER_SOURCE_INDEX: &str = "x-dead-letter-source-index";
+pub const DEAD_LETTER_HEADER_SUBSCRIPTION: &str = "x-dead-letter-subscription";
+pub const DEAD_LETTER_HEADER_ATTEMPTS: &str = "x-dead-letter-attempts";
+
+/// 消费组成员未续约时租约的默认长度 (毫秒)
+pub const DEFAULT_CONSUMER_GROUP_LEASE_MS: u64 = 30_000;
+
+/// fetch_messages 的 `wait_ms` 上限 (毫秒)，更长的等待会被截断
+pub const MAX_FETCH_WAIT_MS: u64 = 30_000;
+
 /// 队列状态统计
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct QueueStats {
@@ -99,6 +123,28 @@ pub struct QueueStats {
     pub first_index: u64,
     pub last_index: u64,
     pub size_bytes: u64,
+    #[serde(default)]
+    pub consumer_groups: Vec<ConsumerGroupStats>,
+}
+
+/// 消费组状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConsumerGroupStats {
+    pub name: String,
+    /// 组游标之后尚未被 ack 的消息数 (含 pending)
+    pub lag: u64,
+    /// 已投递给成员、尚未 ack 的消息数
+    pub pending: u64,
+    pub members: Vec<ConsumerGroupMemberStats>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConsumerGroupMemberStats {
+    pub sub_id: SubscriptionId,
+    /// 该成员持有、尚未 ack 的消息数
+    pub pending: u64,
+    /// 租约到期时间 (Unix 毫秒)；到期后成员被移出，其 pending 消息交给其他成员
+    pub lease_expires_at_ms: u64,
 }
 
 /// 订阅起始位置
@@ -250,6 +296,40 @@ impl MsgQueueSubscribeReq {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MsgQueueJoinGroupReq {
+    pub queue_urn: QueueUrn,
+    pub group: String,
+    pub sub_id: Option<String>,
+    pub position: SubPosition,
+    #[serde(default)]
+    pub lease_ms: Option<u64>,
+}
+
+impl MsgQueueJoinGroupReq {
+    pub fn new(
+        queue_urn: QueueUrn,
+        group: String,
+        sub_id: Option<String>,
+        position: SubPosition,
+        lease_ms: Option<u64>,
+    ) -> Self {
+        Self {
+            queue_urn,
+            group,
+            sub_id,
+            position,
+            lease_ms,
+        }
+    }
+
+    pub fn from_json(value: Value) -> std::result::Result<Self, RPCErrors> {
+        serde_json::from_value(value).map_err(|e| {
+            RPCErrors::ParseRequestError(format!("Failed to parse MsgQueueJoinGroupReq: {}", e))
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MsgQueueUnsubscribeReq {
     pub sub_id: SubscriptionId,
@@ -272,14 +352,23 @@ pub struct MsgQueueFetchMessagesReq {
     pub sub_id: SubscriptionId,
     pub length: usize,
     pub auto_commit: bool,
+    /// 没有可投递的消息时最多等待多久 (毫秒)；None 或 0 表示立即返回
+    #[serde(default)]
+    pub wait_ms: Option<u64>,
 }
 
 impl MsgQueueFetchMessagesReq {
-    pub fn new(sub_id: SubscriptionId, length: usize, auto_commit: bool) -> Self {
+    pub fn new(
+        sub_id: SubscriptionId,
+        length: usize,
+        auto_commit: bool,
+        wait_ms: Option<u64>,
+    ) -> Self {
         Self {
             sub_id,
             length,
             auto_commit,
+            wait_ms,
         }
     }
 
@@ -331,6 +420,30 @@ impl MsgQueueCommitAckReq {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MsgQueueNackReq {
+    pub sub_id: SubscriptionId,
+    pub index: MsgIndex,
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+}
+
+impl MsgQueueNackReq {
+    pub fn new(sub_id: SubscriptionId, index: MsgIndex, delay_ms: Option<u64>) -> Self {
+        Self {
+            sub_id,
+            index,
+            delay_ms,
+        }
+    }
+
+    pub fn from_json(value: Value) -> std::result::Result<Self, RPCErrors> {
+        serde_json::from_value(value).map_err(|e| {
+            RPCErrors::ParseRequestError(format!("Failed to parse MsgQueueNackReq: {}", e))
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MsgQueueSeekReq {
     pub sub_id: SubscriptionId,
@@ -569,6 +682,51 @@ impl MsgQueueClient {
         }
     }
 
+    /// 加入消费组，返回该成员的订阅 ID。
+    /// 成员用这个 ID 调用 fetch_messages / commit_ack / nack / unsubscribe；组内每条消息
+    /// 同一时刻只投递给一个成员。成员的每次调用都会续约，`lease_ms` (默认
+    /// `DEFAULT_CONSUMER_GROUP_LEASE_MS`) 内没有调用的成员被移出，其未 ack 的消息交给其他成员。
+    /// `position` 只在组第一次创建时生效。
+    pub async fn join_group(
+        &self,
+        queue_urn: &str,
+        group: &str,
+        sub_id: Option<String>,
+        position: SubPosition,
+        lease_ms: Option<u64>,
+    ) -> std::result::Result<SubscriptionId, RPCErrors> {
+        match self {
+            Self::InProcess(handler) => {
+                let ctx = RPCContext::default();
+                handler
+                    .handle_join_group(queue_urn, group, sub_id, position, lease_ms, ctx)
+                    .await
+            }
+            Self::KRPC(client) => {
+                let req = MsgQueueJoinGroupReq::new(
+                    queue_urn.to_string(),
+                    group.to_string(),
+                    sub_id,
+                    position,
+                    lease_ms,
+                );
+                let req_json = serde_json::to_value(&req).map_err(|e| {
+                    RPCErrors::ReasonError(format!(
+                        "Failed to serialize MsgQueueJoinGroupReq: {}",
+                        e
+                    ))
+                })?;
+                let result = client.call("join_group", req_json).await?;
+                result
+                    .as_str()
+                    .map(|value| value.to_string())
+                    .ok_or_else(|| {
+                        RPCErrors::ParserResponseError("Expected SubscriptionId string".to_string())
+                    })
+            }
+        }
+    }
+
     pub async fn unsubscribe(&self, sub_id: &str) -> std::result::Result<(), RPCErrors> {
         match self {
             Self::InProcess(handler) => {
@@ -595,15 +753,31 @@ impl MsgQueueClient {
         length: usize,
         auto_commit: bool,
     ) -> std::result::Result<Vec<Message>, RPCErrors> {
+        self.fetch_messages_wait(sub_id, length, auto_commit, 0)
+            .await
+    }
+
+    /// 长轮询版本的 fetch_messages：没有可投递的消息时，服务端最多挂起 `wait_ms`
+    /// (上限 `MAX_FETCH_WAIT_MS`)，期间有新消息写入就立即返回。超时返回空列表。
+    /// `wait_ms` 应小于 RPC 客户端的超时时间。
+    pub async fn fetch_messages_wait(
+        &self,
+        sub_id: &str,
+        length: usize,
+        auto_commit: bool,
+        wait_ms: u64,
+    ) -> std::result::Result<Vec<Message>, RPCErrors> {
+        let wait_ms = (wait_ms > 0).then_some(wait_ms);
         match self {
             Self::InProcess(handler) => {
                 let ctx = RPCContext::default();
                 handler
-                    .handle_fetch_messages(sub_id, length, auto_commit, ctx)
+                    .handle_fetch_messages(sub_id, length, auto_commit, wait_ms, ctx)
                     .await
             }
             Self::KRPC(client) => {
-                let req = MsgQueueFetchMessagesReq::new(sub_id.to_string(), length, auto_commit);
+                let req =
+                    MsgQueueFetchMessagesReq::new(sub_id.to_string(), length, auto_commit, wait_ms);
                 let req_json = serde_json::to_value(&req).map_err(|e| {
                     RPCErrors::ReasonError(format!(
                         "Failed to serialize MsgQueueFetchMessagesReq: {}",
@@ -677,6 +851,30 @@ impl MsgQueueClient {
         }
     }
 
+    /// 放弃处理一条已投递的消息：`delay_ms` 之后 (默认立即) 重新投递给同一订阅。
+    /// 投递次数已用尽时直接转入死信队列。
+    pub async fn nack(
+        &self,
+        sub_id: &str,
+        index: MsgIndex,
+        delay_ms: Option<u64>,
+    ) -> std::result::Result<(), RPCErrors> {
+        match self {
+            Self::InProcess(handler) => {
+                let ctx = RPCContext::default();
+                handler.handle_nack(sub_id, index, delay_ms, ctx).await
+            }
+            Self::KRPC(client) => {
+                let req = MsgQueueNackReq::new(sub_id.to_string(), index, delay_ms);
+                let req_json = serde_json::to_value(&req).map_err(|e| {
+                    RPCErrors::ReasonError(format!("Failed to serialize MsgQueueNackReq: {}", e))
+                })?;
+                client.call("nack", req_json).await?;
+                Ok(())
+            }
+        }
+    }
+
     pub async fn seek(
         &self,
         sub_id: &str,
@@ -774,6 +972,16 @@ pub trait MsgQueueHandler: Send + Sync {
         ctx: RPCContext,
     ) -> std::result::Result<SubscriptionId, RPCErrors>;
 
+    async fn handle_join_group(
+        &self,
+        queue_urn: &str,
+        group: &str,
+        sub_id: Option<String>,
+        position: SubPosition,
+        lease_ms: Option<u64>,
+        ctx: RPCContext,
+    ) -> std::result::Result<SubscriptionId, RPCErrors>;
+
     async fn handle_unsubscribe(
         &self,
         sub_id: &str,
@@ -785,6 +993,7 @@ pub trait MsgQueueHandler: Send + Sync {
         sub_id: &str,
         length: usize,
         auto_commit: bool,
+        wait_ms: Option<u64>,
         ctx: RPCContext,
     ) -> std::result::Result<Vec<Message>, RPCErrors>;
 
@@ -803,6 +1012,14 @@ pub trait MsgQueueHandler: Send + Sync {
         ctx: RPCContext,
     ) -> std::result::Result<(), RPCErrors>;
 
+    async fn handle_nack(
+        &self,
+        sub_id: &str,
+        index: MsgIndex,
+        delay_ms: Option<u64>,
+        ctx: RPCContext,
+    ) -> std::result::Result<(), RPCErrors>;
+
     async fn handle_seek(
         &self,
         sub_id: &str,
@@ -826,9 +1043,8 @@ impl<T: MsgQueueHandler> MsgQueueServerHandler<T> {
     }
 }
 
-#[async_trait]
-impl<T: MsgQueueHandler> RPCHandler for MsgQueueServerHandler<T> {
-    async fn handle_rpc_call(
+impl<T: MsgQueueHandler> MsgQueueServerHandler<T> {
+    async fn dispatch_rpc_call(
         &self,
         req: RPCRequest,
         ip_from: IpAddr,
@@ -899,6 +1115,21 @@ impl<T: MsgQueueHandler> RPCHandler for MsgQueueServerHandler<T> {
                     .await?;
                 RPCResult::Success(json!(result))
             }
+            "join_group" => {
+                let join_req = MsgQueueJoinGroupReq::from_json(req.params)?;
+                let result = self
+                    .0
+                    .handle_join_group(
+                        &join_req.queue_urn,
+                        &join_req.group,
+                        join_req.sub_id,
+                        join_req.position,
+                        join_req.lease_ms,
+                        ctx,
+                    )
+                    .await?;
+                RPCResult::Success(json!(result))
+            }
             "unsubscribe" => {
                 let unsubscribe_req = MsgQueueUnsubscribeReq::from_json(req.params)?;
                 let result = self
@@ -915,6 +1146,7 @@ impl<T: MsgQueueHandler> RPCHandler for MsgQueueServerHandler<T> {
                         &fetch_req.sub_id,
                         fetch_req.length,
                         fetch_req.auto_commit,
+                        fetch_req.wait_ms,
                         ctx,
                     )
                     .await?;
@@ -936,6 +1168,14 @@ impl<T: MsgQueueHandler> RPCHandler for MsgQueueServerHandler<T> {
                     .await?;
                 RPCResult::Success(json!(result))
             }
+            "nack" => {
+                let nack_req = MsgQueueNackReq::from_json(req.params)?;
+                let result = self
+                    .0
+                    .handle_nack(&nack_req.sub_id, nack_req.index, nack_req.delay_ms, ctx)
+                    .await?;
+                RPCResult::Success(json!(result))
+            }
             "seek" => {
                 let seek_req = MsgQueueSeekReq::from_json(req.params)?;
                 let result = self
@@ -963,6 +1203,19 @@ impl<T: MsgQueueHandler> RPCHandler for MsgQueueServerHandler<T> {
     }
 }
 
+#[async_trait]
+impl<T: MsgQueueHandler> RPCHandler for MsgQueueServerHandler<T> {
+    async fn handle_rpc_call(
+        &self,
+        req: RPCRequest,
+        ip_from: IpAddr,
+    ) -> std::result::Result<RPCResponse, RPCErrors> {
+        rpc_log_context(&req)
+            .scope(self.dispatch_rpc_call(req, ip_from))
+            .await
+    }
+}
+
 /// 计算确定性的 Queue URN (Deterministic Naming)
 /// 这是一个纯函数，不涉及 IO。
 /// 规则:
@@ -987,6 +1240,39 @@ pub fn parse_queue_urn(urn: &str) -> Option<(&str, &str, &str)> {
     }
 }
 
+/// kmsg 在 post_message 之后发布 "有新消息" 通知的 kevent 路径。
+/// URN 按 `/` 和 `:` 切分成路径段，kevent 不允许的字符替换为 `_`，超出深度的部分并入最后一段。
+/// 不同队列可能映射到同一路径，通知只是唤醒提示，收到后照常 fetch 即可。
+pub fn kmsg_queue_event_path(queue_urn: &str) -> String {
+    const MAX_SEGMENTS: usize = 7;
+    const MAX_PATH_LEN: usize = 256;
+    let mut segments: Vec<String> = queue_urn
+        .split(['/', ':'])
+        .filter(|seg| !seg.is_empty())
+        .map(|seg| {
+            seg.chars()
+                .map(|ch| {
+                    if ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '.') {
+                        ch
+                    } else {
+                        '_'
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    if segments.len() > MAX_SEGMENTS {
+        let tail = segments.split_off(MAX_SEGMENTS - 1).join("_");
+        segments.push(tail);
+    }
+    if segments.is_empty() {
+        segments.push("_".to_string());
+    }
+    let mut path = format!("/{}/{}", KMSG_SERVICE_NAME, segments.join("/"));
+    path.truncate(MAX_PATH_LEN);
+    path.trim_end_matches('/').to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1003,6 +1289,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn queue_event_path_is_a_valid_kevent_path() {
+        assert_eq!(
+            kmsg_queue_event_path("app::owner::inbox"),
+            "/kmsg/app/owner/inbox"
+        );
+        assert_eq!(
+            kmsg_queue_event_path("/jarvis.test.buckyos.io/sessions/tg:lzc_jarvis:5397330802/msg"),
+            "/kmsg/jarvis.test.buckyos.io/sessions/tg/lzc_jarvis/5397330802/msg"
+        );
+        assert_eq!(
+            kmsg_queue_event_path("/a/b/c/d/e/f/g/h/i"),
+            "/kmsg/a/b/c/d/e/f/g_h_i"
+        );
+        assert_eq!(
+            kmsg_queue_event_path("app::owner::we ird"),
+            "/kmsg/app/owner/we_ird"
+        );
+    }
+
     #[test]
     fn calc_queue_urn_keeps_legacy_urn() {
         let legacy = "opendan::jarvis.test.buckyos.io::legacy_queue";
@@ -1128,6 +1434,7 @@ mod tests {
                 first_index,
                 last_index,
                 size_bytes,
+                consumer_groups: Vec::new(),
             })
         }
 
@@ -1202,6 +1509,20 @@ mod tests {
             Ok(sub_id)
         }
 
+        // The mock has no shared delivery state; a member is a plain subscription.
+        async fn handle_join_group(
+            &self,
+            queue_urn: &str,
+            _group: &str,
+            sub_id: Option<String>,
+            position: SubPosition,
+            _lease_ms: Option<u64>,
+            ctx: RPCContext,
+        ) -> std::result::Result<SubscriptionId, RPCErrors> {
+            self.handle_subscribe(queue_urn, "", "", sub_id, position, ctx)
+                .await
+        }
+
         async fn handle_unsubscribe(
             &self,
             sub_id: &str,
@@ -1222,6 +1543,7 @@ mod tests {
             sub_id: &str,
             length: usize,
             auto_commit: bool,
+            _wait_ms: Option<u64>,
             _ctx: RPCContext,
         ) -> std::result::Result<Vec<Message>, RPCErrors> {
             let mut subs = self.subscriptions.lock().await;
@@ -1285,6 +1607,23 @@ mod tests {
             Ok(())
         }
 
+        async fn handle_nack(
+            &self,
+            sub_id: &str,
+            _index: MsgIndex,
+            _delay_ms: Option<u64>,
+            _ctx: RPCContext,
+        ) -> std::result::Result<(), RPCErrors> {
+            let subs = self.subscriptions.lock().await;
+            if !subs.contains_key(sub_id) {
+                return Err(RPCErrors::ReasonError(format!(
+                    "Subscription not found: {}",
+                    sub_id
+                )));
+            }
+            Ok(())
+        }
+
         async fn handle_seek(
             &self,
             sub_id: &str,
@@ -84,6 +84,15 @@ m = (g(r.sub, p.sub) || r.sub == p.sub) && ((r.sub == keyGet3(r.obj, p.obj, p.su
   Task。注意 enforce 是 app 侧和 user 侧的合取：app 侧决定“哪个 App 可以充当控制面”，
   user 侧的 {user} 占位符把可见范围绑定到请求者本人（root/su_admin 的全局通配规则除外）。
 
+### kevent 相关
+- obj://kevent/{eventid} 全局事件命名空间，action 为 publish / subscribe。node-daemon 对 bridge 与
+  HTTP 调用方做检查：publish 检查 eventid 本身，subscribe 检查 pattern 的字面形式（`*`、`**` 原样
+  保留），因此订阅 apps 下的 `**` 需要的是整个 apps 子树的权限，`{app}` 占位符也不会被 `*` 冒充。
+- 命名空间隔离在 app 侧完成：app 只能收发 `/apps/{app}/...`，agent 只能收发 `/agents/{agent}/...`，
+  frame 服务额外可以订阅 task_mgr、收发 msg_center 与对象事件，并可发布自己的 `/{frame}/...`
+  （如 slog_server 的告警事件 `/slog_server/alerts/{rule_id}`）。user 侧对 admin/users 整体放开。
+- 与 node-daemon 同机、直接走共享内存的 kernel 进程不经过这层检查。
+
  */
 pub const DEFAULT_RBAC_POLICY: &str = r#"
 p, kernel, obj://*, all,allow
@@ -101,12 +110,17 @@ p, frame, obj://config/agents/{agent}/{key},read,allow
 p, frame, obj://config/services/{frame}/*,all,allow
 p, frame, obj://config/services/{service}/info,read,allow
 p, frame, obj://config/users*,read,allow
+p, frame, obj://kevent/task_mgr/*,subscribe,allow
+p, frame, obj://kevent/msg_center/*,publish|subscribe,allow
+p, frame, obj://kevent/obj/*,publish|subscribe,allow
+p, frame, obj://kevent/{frame}/*,publish,allow
 
 p, app, obj://config/boot/*, read,allow
 p, app, obj://config/users/{user}/apps/{app}/settings,read|write,allow
 p, app, obj://config/users/{user}/apps/{app}/spec,read,allow
 p, app, obj://config/users/{user}/apps/{app}/info,read|write,allow
 p, app, obj://config/services/{service}/info,read,allow
+p, app, obj://kevent/apps/{app}/*,publish|subscribe,allow
 
 p, agent, obj://config/boot/*, read,allow
 p, agent, obj://config/agents/{agent}/*,read,allow
@@ -115,6 +129,7 @@ p, agent, obj://config/users/{user}/agents/{agent}/spec,read,allow
 p, agent, obj://config/users/{user}/agents/{agent}/info,read|write,allow
 p, agent, obj://config/services/{service}/info,read,allow
 p, agent, obj://config/services/{agent}/instances/{node},write,allow
+p, agent, obj://kevent/agents/{agent}/*,publish|subscribe,allow
 
 p, admin,obj://config/boot/*, read,allow
 p, admin,obj://config/system/*,read,allow
@@ -129,6 +144,7 @@ p, admin,obj://config/services/msg-center/settings,read|write,allow
 p, admin,obj://config/services/{service}/instances/{node},write,allow
 p, admin,obj://config/services/*,read,allow
 p, admin,obj://task/{admin},read,allow
+p, admin,obj://kevent/*,publish|subscribe,allow
 
 p, users,obj://config/boot/*, read,allow
 p, users,obj://config/agents/{agent}/doc,read,allow
@@ -140,6 +156,7 @@ p, users,obj://config/users/{users}/agents/{agent}/{key},read|write,allow
 p, users,obj://config/services/{service}/info,read,allow
 p, users,obj://config/services/{service}/instances/{node},write,allow
 p, users,obj://task/{users},read,allow
+p, users,obj://kevent/*,publish|subscribe,allow
 
 g, node-daemon, kernel
 g, scheduler, kernel
@@ -697,6 +714,132 @@ g, buckyos_filebrowser, app
         );
     }
 
+    /// kevent resources are `obj://kevent` + eventid for publish and
+    /// `obj://kevent` + the literal pattern for subscribe.
+    #[tokio::test]
+    async fn kevent_namespaces_are_isolated_per_app() {
+        let _guard = TEST_LOCK.lock().await;
+
+        let policy_tail = r#"
+g, devtest, admin
+g, buckyos_filebrowser, app
+g, buckyos_jarvis, agent
+"#;
+        let config = build_current_rbac_config(Some(policy_tail));
+        rbac::create_enforcer(&config.model, &config.policy)
+            .await
+            .unwrap();
+
+        // An app owns its own subtree, for both directions.
+        for (resource, action) in [
+            ("obj://kevent/apps/buckyos_filebrowser/changed", "publish"),
+            ("obj://kevent/apps/buckyos_filebrowser/**", "subscribe"),
+        ] {
+            assert!(
+                rbac::enforce("devtest", "buckyos_filebrowser", resource, action, None).await,
+                "{} {}",
+                action,
+                resource
+            );
+        }
+
+        // It cannot sniff system or task events, another app's subtree, or
+        // widen its pattern past its own app id.
+        for resource in [
+            "obj://kevent/system/**",
+            "obj://kevent/task_mgr/**",
+            "obj://kevent/apps/other_app/**",
+            "obj://kevent/apps/*/changed",
+            "obj://kevent/apps/**",
+            "obj://kevent/**",
+        ] {
+            assert!(
+                !rbac::enforce(
+                    "devtest",
+                    "buckyos_filebrowser",
+                    resource,
+                    "subscribe",
+                    None
+                )
+                .await,
+                "subscribe {}",
+                resource
+            );
+        }
+        assert!(
+            !rbac::enforce(
+                "devtest",
+                "buckyos_filebrowser",
+                "obj://kevent/system/node/online",
+                "publish",
+                None
+            )
+            .await
+        );
+
+        // Agents get the same isolation under /agents; frame services can
+        // follow tasks and mailboxes but not publish task events.
+        assert!(
+            rbac::enforce(
+                "devtest",
+                "buckyos_jarvis",
+                "obj://kevent/agents/buckyos_jarvis/**",
+                "subscribe",
+                None
+            )
+            .await
+        );
+        assert!(
+            !rbac::enforce(
+                "devtest",
+                "buckyos_jarvis",
+                "obj://kevent/msg_center/**",
+                "subscribe",
+                None
+            )
+            .await
+        );
+        assert!(
+            rbac::enforce(
+                "devtest",
+                "opendan",
+                "obj://kevent/task_mgr/**",
+                "subscribe",
+                None
+            )
+            .await
+        );
+        assert!(
+            !rbac::enforce(
+                "devtest",
+                "opendan",
+                "obj://kevent/task_mgr/t-1",
+                "publish",
+                None
+            )
+            .await
+        );
+
+        // A frame service publishes under its own id only.
+        assert!(
+            rbac::enforce(
+                "devtest",
+                "slog_server",
+                "obj://kevent/slog_server/alerts/node_errors",
+                "publish",
+                None
+            )
+            .await
+        );
+        for resource in ["obj://kevent/aicc/alerts/x", "obj://kevent/task_mgr/t-1"] {
+            assert!(
+                !rbac::enforce("devtest", "slog_server", resource, "publish", None).await,
+                "publish {}",
+                resource
+            );
+        }
+    }
+
     #[tokio::test]
     async fn sudo_users_role_can_write_own_sensitive_user_data() {
         let _guard = TEST_LOCK.lock().await;
@@ -0,0 +1,49 @@
+use kRPC::{RPCContext, RPCRequest};
+use slog::LogContext;
+
+/// Log context for serving one kRPC call: the trace and span ids from the
+/// request's `trace_id` slot (`trace[/span]`), or a new trace when the caller
+/// sent none. Run the handler under it with `LogContext::scope`.
+pub fn rpc_log_context(req: &RPCRequest) -> LogContext {
+    LogContext::for_rpc_call(req.trace_id.as_deref())
+}
+
+/// Fill `ctx.trace_id` for an outgoing call made while serving another, so
+/// the callee logs under the same trace with a span of its own.
+pub fn with_outgoing_rpc_trace(mut ctx: RPCContext) -> RPCContext {
+    if ctx.trace_id.is_none() {
+        ctx.trace_id = LogContext::current().child_rpc_trace();
+    }
+    ctx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_handler_logs_and_calls_out_under_the_request_trace() {
+        let req = RPCRequest {
+            method: "get".to_string(),
+            params: json!({}),
+            seq: 1,
+            token: None,
+            trace_id: Some("trace-1/span-caller".to_string()),
+        };
+
+        let (served, outgoing) = rpc_log_context(&req)
+            .scope(async {
+                let outgoing = with_outgoing_rpc_trace(RPCContext::default());
+                (LogContext::current(), outgoing.trace_id)
+            })
+            .await;
+        assert_eq!(served.trace_id.as_deref(), Some("trace-1"));
+        assert_eq!(served.span_id.as_deref(), Some("span-caller"));
+
+        let outgoing = LogContext::from_rpc_trace(&outgoing.unwrap());
+        assert_eq!(outgoing.trace_id.as_deref(), Some("trace-1"));
+        assert_ne!(outgoing.span_id.as_deref(), Some("span-caller"));
+        assert!(LogContext::current().is_empty());
+    }
+}
@@ -27,6 +27,7 @@ use named_store::NamedDataMgr;
 use crate::aicc_client::*;
 use crate::app_mgr::*;
 use crate::control_panel::*;
+use crate::kevent_bridge::KEventSessionTokenProvider;
 use crate::kevent_client::{
     KEventClient, BUCKYOS_KEVENT_DAEMON_ADDR_ENV, KEVENT_SERVICE_NATIVE_PORT,
 };
@@ -119,6 +120,19 @@ pub struct RuntimeHealthSnapshot {
     pub tasks: Vec<RuntimeBackgroundTaskStatus>,
 }
 
+/// Hands the daemon bridge whatever session token the runtime currently
+/// holds, so renewals reach the next kevent reconnect.
+#[derive(Clone)]
+struct RuntimeKEventSessionToken(Arc<RwLock<String>>);
+
+#[async_trait::async_trait]
+impl KEventSessionTokenProvider for RuntimeKEventSessionToken {
+    async fn session_token(&self) -> Option<String> {
+        let token = self.0.read().await;
+        (!token.is_empty()).then(|| token.clone())
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct RetryPolicy {
     max_attempts: u32,
@@ -1505,6 +1519,15 @@ impl BuckyOSRuntime {
         Ok(())
     }
 
+    /// 按 kid 查找信任公钥，未命中时先刷新一次 trust keys 再查
+    pub async fn get_trust_key(&self, kid: &str) -> Result<Option<DecodingKey>> {
+        if let Some(key) = self.trust_keys.read().await.get(kid).cloned() {
+            return Ok(Some(key));
+        }
+        self.refresh_trust_keys().await?;
+        Ok(self.trust_keys.read().await.get(kid).cloned())
+    }
+
     pub async fn verify_trusted_session_token(&self, token_str: &str) -> Result<RPCSessionToken> {
         let mut rpc_token = RPCSessionToken::from_string(token_str).map_err(|error| {
             RPCErrors::InvalidToken(format!("Invalid session token: {}", error))
@@ -1544,18 +1567,7 @@ impl BuckyOSRuntime {
             })
             .unwrap_or_else(|| "root".to_string());
 
-        let mut decoding_key = {
-            let key_map = self.trust_keys.read().await;
-            key_map.get(&kid).cloned()
-        };
-
-        if decoding_key.is_none() {
-            self.refresh_trust_keys().await?;
-            decoding_key = {
-                let key_map = self.trust_keys.read().await;
-                key_map.get(&kid).cloned()
-            };
-        }
+        let mut decoding_key = self.get_trust_key(&kid).await?;
 
         if decoding_key.is_none() && kid != "root" {
             kid = "root".to_string();
@@ -1640,6 +1652,27 @@ impl BuckyOSRuntime {
             .or_else(|| decoded_json.get("aud").and_then(|aud| aud.as_str()))
             .unwrap_or("kernel");
 
+        let sudo = decoded_json
+            .get("sudo")
+            .and_then(|sudo| sudo.as_bool())
+            .unwrap_or(false);
+
+        self.enforce_subject(userid, appid, sudo, resource_path, action)
+            .await?;
+        Ok((userid.to_string(), appid.to_string()))
+    }
+
+    /// RBAC check for an identity the caller already verified (for example a
+    /// session token checked once per connection). Reloads the zone policy
+    /// first if it changed, like `enforce`.
+    pub async fn enforce_subject(
+        &self,
+        userid: &str,
+        appid: &str,
+        sudo: bool,
+        resource_path: &str,
+        action: &str,
+    ) -> Result<()> {
         let system_config_client = self.get_system_config_client().await?;
         let rbac_config = crate::load_current_rbac_config(system_config_client.as_ref()).await?;
         if rbac_config.is_changed {
@@ -1650,12 +1683,8 @@ impl BuckyOSRuntime {
                 })?;
         }
 
-        let sudo = decoded_json
-            .get("sudo")
-            .and_then(|sudo| sudo.as_bool())
-            .unwrap_or(false)
+        let sudo = sudo
             .then(|| rbac::SudoMode::Sudo(RPCSessionToken::get_default_sudo_userid(userid)));
-
         let result = rbac::enforce(userid, appid, resource_path, action, sudo).await;
         if !result {
             return Err(RPCErrors::NoPermission(format!(
@@ -1663,7 +1692,7 @@ impl BuckyOSRuntime {
                 userid, appid, resource_path, action
             )));
         }
-        Ok((userid.to_string(), appid.to_string()))
+        Ok(())
     }
 
     //     pub async fn enable_zone_provider (_is_gateway: bool) -> Result<()> {
@@ -2181,8 +2210,14 @@ impl BuckyOSRuntime {
                     "kevent client for {} uses daemon bridge at {}",
                     source_node, endpoint
                 );
-                KEventClient::new_daemon_bridge(source_node, endpoint)
-                    .map_err(|err| RPCErrors::ReasonError(err.to_string()))
+                // The daemon checks publish / subscribe rights against the
+                // identity of this token.
+                KEventClient::new_daemon_bridge_with_session_token(
+                    source_node,
+                    endpoint,
+                    Arc::new(RuntimeKEventSessionToken(self.session_token.clone())),
+                )
+                .map_err(|err| RPCErrors::ReasonError(err.to_string()))
             }
             _ => KEventClient::new_shared_memory(source_node).map_err(|err| {
                 RPCErrors::ReasonError(format!(
@@ -1,7 +1,4 @@
-//TODO:
-//  add WATCH,and load cached value automatically when the value is changed.
-
-use ::kRPC::{kRPC, RPCContext};
+use ::kRPC::{kRPC, RPCContext, RPCErrors};
 use buckyos_kit::buckyos_get_unix_timestamp;
 use log::*;
 use name_lib::{
@@ -13,11 +10,17 @@ use serde_json::{json, Map, Value};
 use thiserror::Error;
 
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{OnceCell, RwLock};
 
-use crate::KVAction;
+use crate::{KVAction, SystemConfigArchive, SystemConfigRestoreDiff};
 
 const CONFIG_CACHE_TIME: u64 = 10; //10s
+/// Long-poll budget of one `sys_config_watch` call made by `SystemConfigWatcher`.
+const WATCH_WAIT_MS: u64 = 15_000;
+const WATCH_RETRY_MIN_MS: u64 = 200;
+const WATCH_RETRY_MAX_MS: u64 = 5_000;
 type ConfigCache = HashMap<String, (String, u64, u64)>;
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
@@ -91,6 +94,8 @@ pub enum SystemConfigError {
     NoPermission(String),
     #[error("Timeout: {0}")]
     Timeout(String),
+    #[error("SchemaViolation: {0}")]
+    SchemaViolation(SystemConfigSchemaViolation),
 }
 
 pub type SytemConfigResult<T> = std::result::Result<T, SystemConfigError>;
@@ -124,6 +129,133 @@ impl SystemConfigValue {
     }
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemConfigEventKind {
+    Put,
+    Delete,
+    Tx,
+}
+
+/// New state of one key after a change.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SystemConfigChange {
+    pub key: String,
+    /// `None` when the key was deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Revision of the key after the change, the `version` returned by `get`.
+    pub version: u64,
+}
+
+/// One write to the store. A `tx` event carries every key of the transaction.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SystemConfigChangeEvent {
+    /// Store-wide revision, increased by one per write.
+    pub revision: u64,
+    pub kind: SystemConfigEventKind,
+    pub changes: Vec<SystemConfigChange>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct SystemConfigWatchResult {
+    /// Latest store revision when the watch returned.
+    pub revision: u64,
+    /// `start_revision` of the next watch, resuming right after this result.
+    pub next_revision: u64,
+    /// The requested revision is no longer in the change log (or is ahead of
+    /// the store after a restore); re-read the watched keys before resuming.
+    #[serde(default)]
+    pub compacted: bool,
+    pub events: Vec<SystemConfigChangeEvent>,
+}
+
+/// One recorded value of a key, as returned by `sys_config_history`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SystemConfigHistoryEntry {
+    /// Revision of the key this value was written at.
+    pub revision: u64,
+    /// `None` when the write deleted the key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Unix seconds.
+    pub timestamp: u64,
+    /// `appid@userid` of the writing session, `None` for internal writes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub writer: Option<String>,
+}
+
+/// Why a write was rejected by the schema registered for its key.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SystemConfigSchemaViolation {
+    pub key: String,
+    /// Key pattern the failing schema is registered for, e.g. `services/*/spec`.
+    pub schema: String,
+    /// JSON pointer of the failing value, `""` for the whole document.
+    pub pointer: String,
+    pub message: String,
+}
+
+/// Structured payload a failed system config rpc call carries as its error
+/// text, so clients decode it instead of searching the reason string.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum SystemConfigRpcError {
+    SchemaViolation {
+        violation: SystemConfigSchemaViolation,
+    },
+}
+
+impl SystemConfigRpcError {
+    pub fn to_payload(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Decodes the error text of a failed call, which may arrive as a quoted
+    /// JSON string.
+    pub fn from_payload(payload: &str) -> Option<Self> {
+        let payload = payload.trim();
+        if let Ok(Value::String(inner)) = serde_json::from_str::<Value>(payload) {
+            return serde_json::from_str(inner.trim()).ok();
+        }
+        serde_json::from_str(payload).ok()
+    }
+}
+
+impl std::fmt::Display for SystemConfigSchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} violates schema {} at '{}': {}",
+            self.key, self.schema, self.pointer, self.message
+        )
+    }
+}
+
+/// Schema checked on writes to keys matching `pattern`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SystemConfigSchema {
+    /// `*` matches one key segment, a trailing `/` matches every key below.
+    pub pattern: String,
+    pub schema: Value,
+    /// Shipped with the service and not overridden by a registered schema.
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+fn write_error(error: RPCErrors) -> SystemConfigError {
+    let payload = match &error {
+        RPCErrors::ReasonError(reason) => SystemConfigRpcError::from_payload(reason),
+        _ => None,
+    };
+    match payload {
+        Some(SystemConfigRpcError::SchemaViolation { violation }) => {
+            SystemConfigError::SchemaViolation(violation)
+        }
+        None => SystemConfigError::ReasonError(error.to_string()),
+    }
+}
+
 impl SystemConfigClient {
     fn need_cache(&self, key: &str) -> bool {
         let cache_key_control = self.cache_key_control.get();
@@ -298,7 +430,7 @@ impl SystemConfigClient {
         let _result = client
             .call("sys_config_set", json!({"key": key, "value": value}))
             .await
-            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+            .map_err(write_error)?;
 
         self.remove_config_cache(key).await;
         Ok(0)
@@ -317,7 +449,7 @@ impl SystemConfigClient {
                 json!({"key": key, "json_path": json_path, "value": value}),
             )
             .await
-            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+            .map_err(write_error)?;
 
         self.remove_config_cache(key).await;
 
@@ -329,7 +461,7 @@ impl SystemConfigClient {
         let _result = client
             .call("sys_config_create", json!({"key": key, "value": value}))
             .await
-            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+            .map_err(write_error)?;
 
         self.remove_config_cache(key).await;
         Ok(0)
@@ -353,7 +485,7 @@ impl SystemConfigClient {
                 json!({"key": key, "append_value": value}),
             )
             .await
-            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+            .map_err(write_error)?;
 
         self.remove_config_cache(key).await;
 
@@ -452,7 +584,7 @@ impl SystemConfigClient {
         client
             .call("sys_config_exec_tx", Value::Object(req_params))
             .await
-            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+            .map_err(write_error)?;
 
         for (key, _action) in tx_actions.iter() {
             self.remove_config_cache(key).await;
@@ -460,6 +592,181 @@ impl SystemConfigClient {
         Ok(0)
     }
 
+    /// Long-poll changes under `key_prefix` starting at `start_revision`
+    /// (inclusive); `None` only reports changes made after the call. Returns
+    /// once there are events or `wait_ms` has passed.
+    pub async fn watch(
+        &self,
+        key_prefix: &str,
+        start_revision: Option<u64>,
+        wait_ms: u64,
+    ) -> SytemConfigResult<SystemConfigWatchResult> {
+        let client = self.get_krpc_client()?;
+        let result = client
+            .call(
+                "sys_config_watch",
+                json!({
+                    "key": key_prefix,
+                    "start_revision": start_revision,
+                    "wait_ms": wait_ms,
+                }),
+            )
+            .await
+            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+        let result: SystemConfigWatchResult = serde_json::from_value(result).map_err(|error| {
+            SystemConfigError::ReasonError(format!("invalid sys_config_watch result: {}", error))
+        })?;
+
+        for event in result.events.iter() {
+            for change in event.changes.iter() {
+                self.remove_config_cache(&change.key).await;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Recorded values of `key`, newest first.
+    pub async fn history(
+        &self,
+        key: &str,
+        limit: Option<usize>,
+    ) -> SytemConfigResult<Vec<SystemConfigHistoryEntry>> {
+        let client = self.get_krpc_client()?;
+        let result = client
+            .call("sys_config_history", json!({"key": key, "limit": limit}))
+            .await
+            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+        serde_json::from_value(result).map_err(|error| {
+            SystemConfigError::ReasonError(format!("invalid sys_config_history result: {}", error))
+        })
+    }
+
+    /// Write the value `key` had at `revision` back as a new revision, which
+    /// is returned. Rolling back to a delete removes the key.
+    pub async fn rollback(&self, key: &str, revision: u64) -> SytemConfigResult<u64> {
+        let client = self.get_krpc_client()?;
+        let result = client
+            .call(
+                "sys_config_rollback",
+                json!({"key": key, "revision": revision}),
+            )
+            .await
+            .map_err(write_error)?;
+        self.remove_config_cache(key).await;
+        result
+            .get("revision")
+            .and_then(|revision| revision.as_u64())
+            .ok_or_else(|| {
+                SystemConfigError::ReasonError(
+                    "sys_config_rollback missing numeric revision".to_string(),
+                )
+            })
+    }
+
+    /// Admin only. Keep the newest `keep` history entries of keys under
+    /// `key_prefix`, and optionally change how many entries are kept per key
+    /// from now on. Returns the number of entries removed.
+    pub async fn prune_history(
+        &self,
+        key_prefix: &str,
+        keep: Option<usize>,
+        history_limit: Option<usize>,
+    ) -> SytemConfigResult<u64> {
+        let client = self.get_krpc_client()?;
+        let result = client
+            .call(
+                "sys_config_prune_history",
+                json!({
+                    "key": key_prefix,
+                    "keep": keep,
+                    "history_limit": history_limit,
+                }),
+            )
+            .await
+            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+        Ok(result
+            .get("removed")
+            .and_then(|removed| removed.as_u64())
+            .unwrap_or(0))
+    }
+
+    /// Admin only. Unsigned snapshot of every key, sign it with
+    /// `SystemConfigArchive::to_jwt` before storing it.
+    pub async fn backup(&self) -> SytemConfigResult<SystemConfigArchive> {
+        let client = self.get_krpc_client()?;
+        let result = client
+            .call("sys_config_backup", json!({}))
+            .await
+            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+        serde_json::from_value(result).map_err(|error| {
+            SystemConfigError::ReasonError(format!("invalid sys_config_backup result: {}", error))
+        })
+    }
+
+    /// Admin only. Restore an owner-signed archive in one transaction.
+    /// A store without `boot/config` accepts any caller and checks the
+    /// signature against the owner key in the node's `node_identity.json`.
+    /// A sealed archive needs `seal_key` from `SystemConfigArchive::unseal_key`;
+    /// the service unseals it itself. `replace` also removes keys missing from
+    /// the archive.
+    pub async fn restore(
+        &self,
+        archive_jwt: &str,
+        seal_key: Option<&str>,
+        replace: bool,
+        dry_run: bool,
+    ) -> SytemConfigResult<SystemConfigRestoreDiff> {
+        let client = self.get_krpc_client()?;
+        let result = client
+            .call(
+                "sys_config_restore",
+                json!({
+                    "archive": archive_jwt,
+                    "seal_key": seal_key,
+                    "replace": replace,
+                    "dry_run": dry_run,
+                }),
+            )
+            .await
+            .map_err(write_error)?;
+        let diff: SystemConfigRestoreDiff = serde_json::from_value(result).map_err(|error| {
+            SystemConfigError::ReasonError(format!("invalid sys_config_restore result: {}", error))
+        })?;
+        if diff.applied {
+            self.config_cache.write().await.clear();
+        }
+        Ok(diff)
+    }
+
+    /// Built-in and registered schemas, by key pattern.
+    pub async fn list_schemas(&self) -> SytemConfigResult<Vec<SystemConfigSchema>> {
+        let client = self.get_krpc_client()?;
+        let result = client
+            .call("sys_config_list_schemas", json!({}))
+            .await
+            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+        serde_json::from_value(result).map_err(|error| {
+            SystemConfigError::ReasonError(format!(
+                "invalid sys_config_list_schemas result: {}",
+                error
+            ))
+        })
+    }
+
+    /// Admin only. Register the schema checked on writes to keys matching
+    /// `pattern`; `None` removes it, falling back to the built-in schema.
+    pub async fn set_schema(&self, pattern: &str, schema: Option<&Value>) -> SytemConfigResult<()> {
+        let client = self.get_krpc_client()?;
+        client
+            .call(
+                "sys_config_set_schema",
+                json!({"pattern": pattern, "schema": schema}),
+            )
+            .await
+            .map_err(|error| SystemConfigError::ReasonError(error.to_string()))?;
+        Ok(())
+    }
+
     pub async fn dump_configs_for_scheduler(&self) -> SytemConfigResult<Value> {
         let client = self.get_krpc_client()?;
         let result = client
@@ -479,6 +786,71 @@ impl SystemConfigClient {
     }
 }
 
+/// Follows changes under a key prefix across reconnects: failed calls are
+/// retried with backoff and the watch resumes from the last revision it
+/// delivered, so no change in the change log is skipped.
+pub struct SystemConfigWatcher {
+    client: Arc<SystemConfigClient>,
+    key_prefix: String,
+    next_revision: Option<u64>,
+}
+
+impl SystemConfigWatcher {
+    /// `start_revision` is inclusive, `None` starts from the first watch call.
+    pub fn new(
+        client: Arc<SystemConfigClient>,
+        key_prefix: &str,
+        start_revision: Option<u64>,
+    ) -> Self {
+        Self {
+            client,
+            key_prefix: key_prefix.to_string(),
+            next_revision: start_revision,
+        }
+    }
+
+    /// Revision the next call resumes from; persist it to resume after a restart.
+    pub fn next_revision(&self) -> Option<u64> {
+        self.next_revision
+    }
+
+    /// Wait for the next batch of events, or a `compacted` result telling the
+    /// caller to re-read the prefix. Never gives up on its own.
+    pub async fn next(&mut self) -> SystemConfigWatchResult {
+        let mut failures = 0_u32;
+        loop {
+            match self
+                .client
+                .watch(&self.key_prefix, self.next_revision, WATCH_WAIT_MS)
+                .await
+            {
+                Ok(result) => {
+                    failures = 0;
+                    self.next_revision = Some(result.next_revision);
+                    if result.compacted || !result.events.is_empty() {
+                        return result;
+                    }
+                }
+                Err(error) => {
+                    let delay = watch_retry_delay(failures);
+                    failures = failures.saturating_add(1);
+                    warn!(
+                        "sys_config watch on {} failed, resuming from revision {:?} in {:?}: {}",
+                        self.key_prefix, self.next_revision, delay, error
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+/// 200ms doubling up to 5s.
+fn watch_retry_delay(failures: u32) -> Duration {
+    let delay = WATCH_RETRY_MIN_MS.saturating_mul(1 << failures.min(5));
+    Duration::from_millis(delay.min(WATCH_RETRY_MAX_MS))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -503,6 +875,55 @@ mod tests {
         assert_eq!(client_a.get_config_cache("services/demo").await, None);
     }
 
+    #[test]
+    fn watch_result_round_trips_and_retry_delay_is_bounded() {
+        let raw = json!({
+            "revision": 7,
+            "next_revision": 8,
+            "events": [{
+                "revision": 7,
+                "kind": "tx",
+                "changes": [
+                    {"key": "services/demo/config", "value": "v2", "version": 3},
+                    {"key": "services/demo/old", "version": 2}
+                ]
+            }]
+        });
+        let result: SystemConfigWatchResult = serde_json::from_value(raw).unwrap();
+        assert!(!result.compacted);
+        assert_eq!(result.events[0].kind, SystemConfigEventKind::Tx);
+        assert_eq!(result.events[0].changes[1].value, None);
+
+        assert_eq!(watch_retry_delay(0), Duration::from_millis(200));
+        assert_eq!(watch_retry_delay(2), Duration::from_millis(800));
+        assert_eq!(watch_retry_delay(30), Duration::from_millis(5_000));
+    }
+
+    #[test]
+    fn schema_violation_is_recovered_from_rpc_error() {
+        let violation = SystemConfigSchemaViolation {
+            key: "users/alice/settings".to_string(),
+            schema: "users/*/settings".to_string(),
+            pointer: "/state".to_string(),
+            message: "value does not match pattern".to_string(),
+        };
+        let payload = SystemConfigRpcError::SchemaViolation {
+            violation: violation.clone(),
+        }
+        .to_payload();
+        let quoted = serde_json::to_string(&payload).unwrap();
+        for reason in [payload, quoted] {
+            match write_error(RPCErrors::ReasonError(reason)) {
+                SystemConfigError::SchemaViolation(parsed) => assert_eq!(parsed, violation),
+                other => panic!("unexpected error: {}", other),
+            }
+        }
+        assert!(matches!(
+            write_error(RPCErrors::ReasonError("key exist".to_string())),
+            SystemConfigError::ReasonError(_)
+        ));
+    }
+
     #[tokio::test]
     async fn sync_session_token_updates_underlying_krpc_client() {
         let client = SystemConfigClient::new(None, Some("token-a"));
@@ -1,4 +1,4 @@
-use crate::KEventService;
+use crate::{map_response_unit, KEventAccess, KEventPrincipal, KEventService, KEventSessionId};
 use async_trait::async_trait;
 use buckyos_api::{Event, KEventDaemonRequest, KEventDaemonResponse, KEventError};
 use buckyos_http_server::{
@@ -6,7 +6,7 @@ use buckyos_http_server::{
 };
 use bytes::Bytes;
 use futures::{stream, TryStreamExt};
-use http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use http::header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE};
 use http::{Method, StatusCode, Version};
 use http_body_util::{combinators::BoxBody, BodyExt, Full, StreamBody};
 use hyper::body::Frame;
@@ -23,6 +23,10 @@ pub struct KEventHttpStreamRequest {
     pub patterns: Vec<String>,
     #[serde(default)]
     pub keepalive_ms: Option<u64>,
+    /// `seq` of the last event a previous stream delivered; the new stream
+    /// starts with what the journal still holds after it.
+    #[serde(default)]
+    pub after_seq: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -73,19 +77,41 @@ impl KEventHttpServer {
         path: &str,
         body: &[u8],
     ) -> ServerResult<http::Response<BoxBody<Bytes, ServerError>>> {
-        match normalize_http_path(path) {
-            Some(KEventHttpRoute::Native) => self.handle_native_http(body).await,
-            Some(KEventHttpRoute::Stream) => self.handle_stream_http(body).await,
-            Some(KEventHttpRoute::Publish) => self.handle_publish_http(body).await,
-            None => Self::build_http_json_response(
+        self.handle_http_request_with_token(path, None, body).await
+    }
+
+    /// Every HTTP request stands alone, so it carries its own session token
+    /// (`Authorization: Bearer` or `X-Auth`) and is checked against it.
+    pub async fn handle_http_request_with_token(
+        &self,
+        path: &str,
+        session_token: Option<&str>,
+        body: &[u8],
+    ) -> ServerResult<http::Response<BoxBody<Bytes, ServerError>>> {
+        let Some(route) = normalize_http_path(path) else {
+            return Self::build_http_json_response(
                 StatusCode::BAD_REQUEST,
                 json!({ "error": format!("Unsupported kevent path: {}", path) }),
-            ),
+            );
+        };
+        let principal = match session_token {
+            Some(token) => self.service.authenticate(token).await,
+            None => Ok(None),
+        };
+        let principal = match principal {
+            Ok(principal) => principal,
+            Err(err) => return Self::build_error_response(err),
+        };
+        match route {
+            KEventHttpRoute::Native => self.handle_native_http(principal.as_ref(), body).await,
+            KEventHttpRoute::Stream => self.handle_stream_http(principal.as_ref(), body).await,
+            KEventHttpRoute::Publish => self.handle_publish_http(principal.as_ref(), body).await,
         }
     }
 
     async fn handle_native_http(
         &self,
+        principal: Option<&KEventPrincipal>,
         body: &[u8],
     ) -> ServerResult<http::Response<BoxBody<Bytes, ServerError>>> {
         let req = serde_json::from_slice::<KEventDaemonRequest>(body).map_err(|error| {
@@ -95,12 +121,16 @@ impl KEventHttpServer {
                 error
             )
         })?;
-        let resp = self.service.handle_protocol_request(req).await;
+        let resp = self
+            .service
+            .handle_protocol_request_as(KEventSessionId::SHARED, principal, req)
+            .await;
         Self::build_daemon_response(StatusCode::OK, resp)
     }
 
     async fn handle_publish_http(
         &self,
+        principal: Option<&KEventPrincipal>,
         body: &[u8],
     ) -> ServerResult<http::Response<BoxBody<Bytes, ServerError>>> {
         let req = serde_json::from_slice::<KEventHttpPublishRequest>(body).map_err(|error| {
@@ -111,27 +141,34 @@ impl KEventHttpServer {
             )
         })?;
 
-        match self
+        let result = match self
             .service
-            .publish_http_global(req.eventid.as_str(), req.data)
+            .check_access(
+                principal,
+                KEventAccess::Publish,
+                std::slice::from_ref(&req.eventid),
+            )
             .await
         {
+            Ok(_) => {
+                self.service
+                    .publish_http_global(req.eventid.as_str(), req.data)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        match result {
             Ok(_) => Self::build_daemon_response(
                 StatusCode::OK,
                 KEventDaemonResponse::Ok { event: None },
             ),
-            Err(err) => Self::build_daemon_response(
-                status_from_kevent_error(&err),
-                KEventDaemonResponse::Err {
-                    code: err.code().to_string(),
-                    message: err.to_string(),
-                },
-            ),
+            Err(err) => Self::build_error_response(err),
         }
     }
 
     async fn handle_stream_http(
         &self,
+        principal: Option<&KEventPrincipal>,
         body: &[u8],
     ) -> ServerResult<http::Response<BoxBody<Bytes, ServerError>>> {
         let req = serde_json::from_slice::<KEventHttpStreamRequest>(body).map_err(|error| {
@@ -148,11 +185,20 @@ impl KEventHttpServer {
             self.stream_seq.fetch_add(1, Ordering::Relaxed) + 1
         );
 
-        if let Err(err) = self
+        // Through the protocol surface so the patterns are permission
+        // checked and the reader is bound to this principal.
+        let registered = self
             .service
-            .register_reader(reader_id.as_str(), req.patterns)
-            .await
-        {
+            .handle_protocol_request_as(
+                KEventSessionId::SHARED,
+                principal,
+                KEventDaemonRequest::RegisterReader {
+                    reader_id: reader_id.clone(),
+                    patterns: req.patterns,
+                },
+            )
+            .await;
+        if let Err(err) = map_response_unit(registered) {
             return Self::build_http_json_response(
                 status_from_kevent_error(&err),
                 json!({ "error": err.to_string() }),
@@ -177,13 +223,22 @@ impl KEventHttpServer {
         }
 
         let service = self.service.clone();
+        let mut cursor = req.after_seq;
         tokio::spawn(async move {
             loop {
                 match service
-                    .pull_event(reader_id.as_str(), Some(keepalive_ms))
+                    .pull_event_in(
+                        KEventSessionId::SHARED,
+                        reader_id.as_str(),
+                        Some(keepalive_ms),
+                        cursor,
+                    )
                     .await
                 {
                     Ok(Some(event)) => {
+                        if event.seq.is_some() {
+                            cursor = event.seq;
+                        }
                         if !Self::send_stream_frame(&sender, &KEventStreamFrame::Event { event })
                             .await
                         {
@@ -221,6 +276,18 @@ impl KEventHttpServer {
         Self::build_stream_response(receiver)
     }
 
+    fn build_error_response(
+        err: KEventError,
+    ) -> ServerResult<http::Response<BoxBody<Bytes, ServerError>>> {
+        Self::build_daemon_response(
+            status_from_kevent_error(&err),
+            KEventDaemonResponse::Err {
+                code: err.code().to_string(),
+                message: err.to_string(),
+            },
+        )
+    }
+
     fn boxed_http_body(bytes: Vec<u8>) -> BoxBody<Bytes, ServerError> {
         Full::new(Bytes::from(bytes))
             .map_err(|never: std::convert::Infallible| match never {})
@@ -337,6 +404,7 @@ impl HttpServer for KEventHttpServer {
         }
 
         let path = req.uri().path().to_string();
+        let session_token = extract_session_token(req.headers());
         let collected = req.into_body().collect().await.map_err(|error| {
             server_err!(
                 ServerErrorCode::BadRequest,
@@ -345,7 +413,8 @@ impl HttpServer for KEventHttpServer {
             )
         })?;
         let body = collected.to_bytes();
-        self.handle_http_request(path.as_str(), body.as_ref()).await
+        self.handle_http_request_with_token(path.as_str(), session_token.as_deref(), body.as_ref())
+            .await
     }
 
     fn id(&self) -> String {
@@ -381,6 +450,19 @@ fn normalize_http_path(path: &str) -> Option<KEventHttpRoute> {
     }
 }
 
+fn extract_session_token(headers: &http::HeaderMap) -> Option<String> {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let x_auth = headers.get("X-Auth").and_then(|value| value.to_str().ok());
+    bearer
+        .or(x_auth)
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+}
+
 fn normalize_keepalive_ms(keepalive_ms: Option<u64>) -> u64 {
     keepalive_ms
         .unwrap_or(DEFAULT_HTTP_STREAM_KEEPALIVE_MS)
@@ -403,6 +485,7 @@ fn status_from_kevent_error(error: &KEventError) -> StatusCode {
         | KEventError::NotSupported(_) => StatusCode::BAD_REQUEST,
         KEventError::DaemonUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
         KEventError::ReaderClosed(_) => StatusCode::GONE,
+        KEventError::PermissionDenied(_) => StatusCode::FORBIDDEN,
         KEventError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
     }
 }
@@ -410,6 +493,7 @@ fn status_from_kevent_error(error: &KEventError) -> StatusCode {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use buckyos_api::KEventResult;
     use serde_json::json;
     use tokio::time::{timeout, Duration};
 
@@ -461,6 +545,7 @@ mod tests {
                 serde_json::to_vec(&KEventDaemonRequest::PullEvent {
                     reader_id: "r1".to_string(),
                     timeout_ms: Some(50),
+                    after_seq: None,
                 })
                 .unwrap()
                 .as_slice(),
@@ -532,4 +617,77 @@ mod tests {
         assert_eq!(event["type"], "event");
         assert_eq!(event["event"]["eventid"], "/system/node/online");
     }
+
+    struct PrefixAuthorizer;
+
+    #[async_trait]
+    impl crate::KEventAuthorizer for PrefixAuthorizer {
+        async fn authenticate(&self, session_token: &str) -> KEventResult<KEventPrincipal> {
+            if session_token != "token-notes" {
+                return Err(KEventError::PermissionDenied("bad token".to_string()));
+            }
+            Ok(KEventPrincipal {
+                user_id: "alice".to_string(),
+                app_id: "notes".to_string(),
+                sudo: false,
+            })
+        }
+
+        async fn authorize(
+            &self,
+            _principal: &KEventPrincipal,
+            _access: KEventAccess,
+            target: &str,
+        ) -> bool {
+            target.starts_with("/apps/notes/")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_routes_enforce_the_request_token() {
+        let service = Arc::new(KEventService::new("node_a"));
+        service.set_authorizer(Arc::new(PrefixAuthorizer)).await;
+        let server = KEventHttpServer::new(service.clone());
+        let publish = |eventid: &str| serde_json::to_vec(&json!({ "eventid": eventid })).unwrap();
+
+        for (token, eventid, status) in [
+            (None, "/apps/notes/saved", StatusCode::FORBIDDEN),
+            (Some("forged"), "/apps/notes/saved", StatusCode::FORBIDDEN),
+            (
+                Some("token-notes"),
+                "/system/node/online",
+                StatusCode::FORBIDDEN,
+            ),
+            (Some("token-notes"), "/apps/notes/saved", StatusCode::OK),
+        ] {
+            let response = server
+                .handle_http_request_with_token("/kapi/kevent/publish", token, &publish(eventid))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{:?} {}", token, eventid);
+        }
+
+        let response = server
+            .handle_http_request_with_token(
+                "/kapi/kevent/stream",
+                Some("token-notes"),
+                serde_json::to_vec(&json!({ "patterns": ["/system/**"] }))
+                    .unwrap()
+                    .as_slice(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(service.reader_count().await, 0);
+    }
+
+    #[test]
+    fn session_token_comes_from_bearer_or_x_auth() {
+        let mut headers = http::HeaderMap::new();
+        assert_eq!(extract_session_token(&headers), None);
+        headers.insert("X-Auth", "token-b".parse().unwrap());
+        assert_eq!(extract_session_token(&headers).as_deref(), Some("token-b"));
+        headers.insert(AUTHORIZATION, "Bearer token-a".parse().unwrap());
+        assert_eq!(extract_session_token(&headers).as_deref(), Some("token-a"));
+    }
 }
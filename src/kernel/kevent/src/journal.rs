@@ -0,0 +1,388 @@
+//! Bounded journal of the global events a daemon delivered.
+//!
+//! The journal is what makes reader resume possible: it hands out the
+//! per-node `seq` ids and keeps the newest `max_events` events so a reader
+//! that reconnects with "I saw up to N" can be replayed the gap. It is a
+//! replay window, not a log of record — events older than the window are
+//! gone, exactly like events dropped by a full reader queue.
+//!
+//! Only global events are journaled: local events never leave the process
+//! that published them, so there is nothing for a reader to resume.
+//!
+//! On disk it is a JSON-lines file of `Event`s. The file is written by a
+//! dedicated writer thread so publishing never waits on the disk; appends
+//! are flushed but not fsynced, so a daemon crash keeps what the OS already
+//! has and a host crash may lose the tail. The file is compacted back to the
+//! window once it has grown to twice its size.
+
+use buckyos_api::{is_global_eventid, Event, KEventError, KEventResult};
+use log::{error, warn};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_JOURNAL_MAX_EVENTS: usize = 4096;
+
+pub struct KEventJournal {
+    inner: Mutex<JournalInner>,
+    writer: Option<JoinHandle<()>>,
+}
+
+struct JournalInner {
+    max_events: usize,
+    events: VecDeque<Event>,
+    next_seq: u64,
+    /// Feeds the writer thread; `None` for an in-memory journal.
+    file_tx: Option<mpsc::Sender<Event>>,
+}
+
+struct JournalFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    lines: usize,
+}
+
+impl KEventJournal {
+    /// Replay window without persistence: resume works across reconnects but
+    /// not across a daemon restart.
+    pub fn in_memory(max_events: usize) -> Self {
+        Self {
+            inner: Mutex::new(JournalInner {
+                max_events: max_events.max(1),
+                events: VecDeque::new(),
+                next_seq: seq_floor(),
+                file_tx: None,
+            }),
+            writer: None,
+        }
+    }
+
+    /// Open (or create) the journal at `path` and reload its window. A torn
+    /// last line from a crash is skipped rather than failing the daemon.
+    pub fn open(path: impl Into<PathBuf>, max_events: usize) -> KEventResult<Self> {
+        let path = path.into();
+        let max_events = max_events.max(1);
+        let mut events = VecDeque::new();
+        match File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line.map_err(|err| journal_err("read", &path, err))?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<Event>(&line) {
+                        Ok(event) if event.seq.is_some() && is_global_eventid(&event.eventid) => {
+                            if events.len() >= max_events {
+                                events.pop_front();
+                            }
+                            events.push_back(event);
+                        }
+                        Ok(_) => {}
+                        Err(err) => warn!(
+                            "kevent journal {} skipped unreadable line: {}",
+                            path.display(),
+                            err
+                        ),
+                    }
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(journal_err("open", &path, err)),
+        }
+
+        let last_seq = events.back().and_then(|event| event.seq).unwrap_or(0);
+        let file = JournalFile::rewrite(&path, &events)?;
+        let (file_tx, file_rx) = mpsc::channel();
+        let window = events.clone();
+        let writer = std::thread::Builder::new()
+            .name("kevent-journal-writer".to_string())
+            .spawn(move || run_writer(file, window, max_events, file_rx))
+            .map_err(|err| journal_err("start writer for", &path, err))?;
+        Ok(Self {
+            inner: Mutex::new(JournalInner {
+                max_events,
+                events,
+                next_seq: (last_seq + 1).max(seq_floor()),
+                file_tx: Some(file_tx),
+            }),
+            writer: Some(writer),
+        })
+    }
+
+    /// Take the next sequence id. Callers serialize reserve + append so ids
+    /// reach the journal (and every reader queue) in order; an id whose
+    /// event is then rejected simply leaves a gap.
+    pub fn reserve_seq(&self) -> u64 {
+        let mut inner = self.lock();
+        let seq = inner.next_seq;
+        inner.next_seq += 1;
+        seq
+    }
+
+    /// Id the next event will get; everything below it is already sequenced.
+    pub fn next_seq(&self) -> u64 {
+        self.lock().next_seq
+    }
+
+    /// Add `event` to the window and queue it for the file. Local events are
+    /// ignored.
+    pub fn append(&self, event: &Event) {
+        if !is_global_eventid(&event.eventid) {
+            return;
+        }
+        let mut inner = self.lock();
+        if inner.events.len() >= inner.max_events {
+            inner.events.pop_front();
+        }
+        inner.events.push_back(event.clone());
+        // Sent under the lock so the file sees events in window order.
+        if let Some(file_tx) = inner.file_tx.as_ref() {
+            if file_tx.send(event.clone()).is_err() {
+                inner.file_tx = None;
+            }
+        }
+    }
+
+    /// Journaled events with `after < seq < before`, oldest first.
+    pub fn events_between(&self, after: u64, before: u64) -> Vec<Event> {
+        let inner = self.lock();
+        inner
+            .events
+            .iter()
+            .filter(|event| event.seq.is_some_and(|seq| seq > after && seq < before))
+            .cloned()
+            .collect()
+    }
+
+    /// Number of events in the replay window.
+    pub fn len(&self) -> usize {
+        self.lock().events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, JournalInner> {
+        self.inner.lock().expect("kevent journal lock poisoned")
+    }
+}
+
+impl Drop for KEventJournal {
+    /// Let the writer drain what was already appended, so a journal reopened
+    /// right after sees every event.
+    fn drop(&mut self) {
+        self.lock().file_tx = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Writer thread: keeps its own copy of the window for compaction and stops
+/// persisting on the first error. Delivery must not depend on the disk.
+fn run_writer(
+    file: JournalFile,
+    mut window: VecDeque<Event>,
+    max_events: usize,
+    file_rx: mpsc::Receiver<Event>,
+) {
+    let mut file = Some(file);
+    while let Ok(event) = file_rx.recv() {
+        if window.len() >= max_events {
+            window.pop_front();
+        }
+        window.push_back(event);
+
+        let Some(current) = file.as_mut() else {
+            continue;
+        };
+        let result = if current.lines >= max_events * 2 {
+            JournalFile::rewrite(&current.path, &window).map(|fresh| *current = fresh)
+        } else {
+            current.append(window.back().expect("event just pushed"))
+        };
+        if let Err(err) = result {
+            error!(
+                "kevent journal {} disabled, resume will not survive a restart: {}",
+                current.path.display(),
+                err
+            );
+            file = None;
+        }
+    }
+}
+
+impl JournalFile {
+    /// Replace the file with exactly `events`, via a temp file so a crash
+    /// mid-compaction leaves the old journal intact.
+    fn rewrite(path: &Path, events: &VecDeque<Event>) -> KEventResult<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| journal_err("create dir", path, err))?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(
+                File::create(&tmp_path).map_err(|err| journal_err("create", &tmp_path, err))?,
+            );
+            for event in events {
+                write_event(&mut writer, event)
+                    .map_err(|err| journal_err("write", &tmp_path, err))?;
+            }
+            writer
+                .flush()
+                .map_err(|err| journal_err("flush", &tmp_path, err))?;
+        }
+        std::fs::rename(&tmp_path, path).map_err(|err| journal_err("rename", path, err))?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|err| journal_err("open", path, err))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            lines: events.len(),
+        })
+    }
+
+    fn append(&mut self, event: &Event) -> KEventResult<()> {
+        write_event(&mut self.writer, event)
+            .and_then(|_| self.writer.flush())
+            .map_err(|err| journal_err("append", &self.path, err))?;
+        self.lines += 1;
+        Ok(())
+    }
+}
+
+fn write_event(writer: &mut impl Write, event: &Event) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *writer, event)?;
+    writer.write_all(b"\n")
+}
+
+fn journal_err(action: &str, path: &Path, err: impl std::fmt::Display) -> KEventError {
+    KEventError::Internal(format!(
+        "kevent journal {} {} failed: {}",
+        action,
+        path.display(),
+        err
+    ))
+}
+
+/// Sequence ids never start below the wall clock in microseconds, so a
+/// daemon that restarted without its journal still hands out ids above any
+/// cursor a client may hold, and client-side dedup stays correct.
+fn seq_floor() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+        .max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(eventid: &str, seq: u64) -> Event {
+        Event {
+            eventid: eventid.to_string(),
+            source_node: "node_a".to_string(),
+            source_pid: 1,
+            ingress_node: Some("node_a".to_string()),
+            timestamp: 1,
+            data: json!({}),
+            seq: Some(seq),
+        }
+    }
+
+    fn temp_journal_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "kevent_journal_{}_{}_{}.jsonl",
+            name,
+            std::process::id(),
+            seq_floor()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn window_is_bounded_and_replays_by_range() {
+        let journal = KEventJournal::in_memory(3);
+        for _ in 0..5 {
+            let seq = journal.reserve_seq();
+            journal.append(&event("/j/e", seq));
+        }
+        assert_eq!(journal.len(), 3);
+
+        let next = journal.next_seq();
+        let replay: Vec<u64> = journal
+            .events_between(next - 4, next)
+            .iter()
+            .filter_map(|event| event.seq)
+            .collect();
+        // The oldest of the asked-for range has already left the window.
+        assert_eq!(replay, vec![next - 3, next - 2, next - 1]);
+    }
+
+    #[test]
+    fn local_events_are_not_journaled() {
+        let path = temp_journal_path("local");
+        {
+            let journal = KEventJournal::open(&path, 4).unwrap();
+            let seq = journal.reserve_seq();
+            journal.append(&event("local_only", seq));
+            let seq = journal.reserve_seq();
+            journal.append(&event("/j/global", seq));
+            assert_eq!(journal.len(), 1);
+        }
+
+        let journal = KEventJournal::open(&path, 4).unwrap();
+        let replay: Vec<String> = journal
+            .events_between(0, u64::MAX)
+            .into_iter()
+            .map(|event| event.eventid)
+            .collect();
+        assert_eq!(replay, vec!["/j/global".to_string()]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn reopen_keeps_window_and_continues_sequence() {
+        let path = temp_journal_path("reopen");
+        let last = {
+            let journal = KEventJournal::open(&path, 2).unwrap();
+            let mut last = 0;
+            // Enough appends to trigger a compaction on the way.
+            for _ in 0..7 {
+                last = journal.reserve_seq();
+                journal.append(&event("/j/e", last));
+            }
+            last
+        };
+        // A torn line from a crash must not make the journal unreadable.
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"eventid\":")
+            .unwrap();
+
+        let journal = KEventJournal::open(&path, 2).unwrap();
+        assert!(journal.next_seq() > last);
+        let replay: Vec<u64> = journal
+            .events_between(0, u64::MAX)
+            .iter()
+            .filter_map(|event| event.seq)
+            .collect();
+        assert_eq!(replay, vec![last - 1, last]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
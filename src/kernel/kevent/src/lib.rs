@@ -1,9 +1,11 @@
 mod http;
+mod journal;
 mod native;
 mod native_server;
 mod service;
 
 pub use http::*;
+pub use journal::*;
 pub use native::*;
 pub use native_server::*;
 pub use service::*;
@@ -24,6 +24,15 @@ impl KEventDaemonClient {
         self.transport.call(req).await
     }
 
+    pub async fn authenticate(&self, session_token: &str) -> KEventResult<()> {
+        map_response_unit(
+            self.call(KEventDaemonRequest::Authenticate {
+                session_token: session_token.to_string(),
+            })
+            .await?,
+        )
+    }
+
     pub async fn register_reader(&self, reader_id: &str, patterns: &[String]) -> KEventResult<()> {
         map_response_unit(
             self.call(KEventDaemonRequest::RegisterReader {
@@ -72,11 +81,22 @@ impl KEventDaemonClient {
         &self,
         reader_id: &str,
         timeout_ms: Option<u64>,
+    ) -> KEventResult<Option<Event>> {
+        self.pull_event_after(reader_id, timeout_ms, None).await
+    }
+
+    /// Pull with a resume cursor; see `KEventService::pull_event_in`.
+    pub async fn pull_event_after(
+        &self,
+        reader_id: &str,
+        timeout_ms: Option<u64>,
+        after_seq: Option<u64>,
     ) -> KEventResult<Option<Event>> {
         map_response_event(
             self.call(KEventDaemonRequest::PullEvent {
                 reader_id: reader_id.to_string(),
                 timeout_ms,
+                after_seq,
             })
             .await?,
         )
@@ -183,6 +203,7 @@ pub fn map_response_error(code: &str, message: &str) -> KEventError {
         "TIMER_NOT_FOUND" => KEventError::TimerNotFound(message.to_string()),
         "NOT_SUPPORTED" => KEventError::NotSupported(message.to_string()),
         "READER_CLOSED" => KEventError::ReaderClosed(message.to_string()),
+        "PERMISSION_DENIED" => KEventError::PermissionDenied(message.to_string()),
         _ => KEventError::Internal(message.to_string()),
     }
 }
@@ -242,6 +263,7 @@ mod tests {
         let req = KEventDaemonRequest::PullEvent {
             reader_id: "r1".to_string(),
             timeout_ms: Some(1000),
+            after_seq: Some(42),
         };
         let req_bytes = encode_daemon_request(&req).unwrap();
         assert_eq!(decode_daemon_request(&req_bytes).unwrap(), req);
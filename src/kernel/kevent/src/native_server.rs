@@ -183,6 +183,7 @@ mod tests {
         let pull_req = KEventDaemonRequest::PullEvent {
             reader_id: "r1".to_string(),
             timeout_ms: Some(0),
+            after_seq: None,
         };
         write_client_frame(&mut client, pull_req).await;
         let pull_resp = read_client_frame(&mut client).await;
@@ -257,6 +258,7 @@ mod tests {
             KEventDaemonRequest::PullEvent {
                 reader_id: "r1".to_string(),
                 timeout_ms: Some(0),
+                after_seq: None,
             },
         )
         .await;
@@ -274,6 +276,7 @@ mod tests {
             KEventDaemonRequest::PullEvent {
                 reader_id: "r1".to_string(),
                 timeout_ms: Some(0),
+                after_seq: None,
             },
         )
         .await;
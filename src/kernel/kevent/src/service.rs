@@ -1,3 +1,4 @@
+use crate::{KEventJournal, DEFAULT_JOURNAL_MAX_EVENTS};
 use async_trait::async_trait;
 use buckyos_api::{
     is_global_eventid, is_global_pattern, match_event_patterns, normalize_patterns,
@@ -19,6 +20,49 @@ pub trait KEventPeerPublisher: Send + Sync {
     async fn broadcast(&self, event: &Event) -> KEventResult<()>;
 }
 
+/// Identity a caller proved with its session token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KEventPrincipal {
+    pub user_id: String,
+    pub app_id: String,
+    pub sudo: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KEventAccess {
+    /// Publish one concrete eventid.
+    Publish,
+    /// Register a reader on one pattern.
+    Subscribe,
+}
+
+impl KEventAccess {
+    pub fn action(&self) -> &'static str {
+        match self {
+            KEventAccess::Publish => "publish",
+            KEventAccess::Subscribe => "subscribe",
+        }
+    }
+}
+
+/// Permission policy for callers outside the daemon process (bridge
+/// connections, the HTTP facade). node-daemon installs one backed by the
+/// zone RBAC policy; without one every caller may publish and subscribe to
+/// any global eventid, which is what in-process users and tests rely on.
+#[async_trait]
+pub trait KEventAuthorizer: Send + Sync {
+    async fn authenticate(&self, session_token: &str) -> KEventResult<KEventPrincipal>;
+
+    /// `target` is the eventid for `Publish` and the pattern, wildcards
+    /// included, for `Subscribe`.
+    async fn authorize(
+        &self,
+        principal: &KEventPrincipal,
+        access: KEventAccess,
+        target: &str,
+    ) -> bool;
+}
+
 /// Namespace a reader belongs to.
 ///
 /// Readers registered over a native TCP connection live in that connection's
@@ -72,22 +116,40 @@ pub struct KEventService {
     peers: Arc<RwLock<Vec<Arc<dyn KEventPeerPublisher>>>>,
     shared_ring: Arc<RwLock<Option<Arc<SharedKEventRingBuffer>>>>,
     session_seq: Arc<AtomicU64>,
+    journal: Arc<KEventJournal>,
+    authorizer: Arc<RwLock<Option<Arc<dyn KEventAuthorizer>>>>,
+    /// Principal each authenticated connection proved with `authenticate`.
+    principals: Arc<RwLock<HashMap<KEventSessionId, KEventPrincipal>>>,
+    /// Held from `seq` assignment until the event sits in every matching
+    /// queue, so each queue is ordered by `seq` (client dedup relies on it)
+    /// and a reader registering concurrently is either live for an event or
+    /// replays it, never both.
+    publish_lock: Arc<Mutex<()>>,
 }
 
 struct ServiceReaderState {
     patterns: StdRwLock<Vec<String>>,
+    /// Who registered the reader when an authorizer is installed. Readers of
+    /// the shared namespace are only served to the same principal, so a
+    /// guessed reader id does not leak another caller's events.
+    owner: StdRwLock<Option<KEventPrincipal>>,
     queue: Mutex<VecDeque<Event>>,
     notify: Notify,
     capacity: usize,
+    /// Events with `seq` at or above this were (or will be) pushed live; a
+    /// resume cursor below it is served from the journal.
+    replay_from: AtomicU64,
 }
 
 impl ServiceReaderState {
-    fn new(patterns: Vec<String>, capacity: usize) -> Self {
+    fn new(patterns: Vec<String>, capacity: usize, replay_from: u64) -> Self {
         Self {
             patterns: StdRwLock::new(patterns),
+            owner: StdRwLock::new(None),
             queue: Mutex::new(VecDeque::new()),
             notify: Notify::new(),
             capacity,
+            replay_from: AtomicU64::new(replay_from),
         }
     }
 
@@ -118,6 +180,20 @@ impl KEventService {
     }
 
     pub fn new_with_capacity(source_node: impl Into<String>, reader_capacity: usize) -> Self {
+        Self::new_with_journal(
+            source_node,
+            reader_capacity,
+            KEventJournal::in_memory(DEFAULT_JOURNAL_MAX_EVENTS),
+        )
+    }
+
+    /// Service whose replay window is `journal`; node-daemon passes an
+    /// on-disk one so readers can resume across a daemon restart.
+    pub fn new_with_journal(
+        source_node: impl Into<String>,
+        reader_capacity: usize,
+        journal: KEventJournal,
+    ) -> Self {
         Self {
             source_node: source_node.into(),
             reader_capacity: reader_capacity.max(1),
@@ -125,6 +201,10 @@ impl KEventService {
             peers: Arc::new(RwLock::new(Vec::new())),
             shared_ring: Arc::new(RwLock::new(None)),
             session_seq: Arc::new(AtomicU64::new(0)),
+            journal: Arc::new(journal),
+            authorizer: Arc::new(RwLock::new(None)),
+            principals: Arc::new(RwLock::new(HashMap::new())),
+            publish_lock: Arc::new(Mutex::new(())),
         }
     }
 
@@ -145,6 +225,7 @@ impl KEventService {
         if session == KEventSessionId::SHARED {
             return 0;
         }
+        self.principals.write().await.remove(&session);
         let mut readers = self.readers.write().await;
         let before = readers.len();
         readers.retain(|key, _| key.session != session);
@@ -164,6 +245,86 @@ impl KEventService {
         *self.shared_ring.write().await = Some(shared_ring);
     }
 
+    pub fn journal(&self) -> &KEventJournal {
+        &self.journal
+    }
+
+    /// Enforce publish / subscribe permissions on protocol requests from now
+    /// on. Direct in-process calls (`publish_local_global`, the shared-ring
+    /// importer, peers) stay trusted.
+    pub async fn set_authorizer(&self, authorizer: Arc<dyn KEventAuthorizer>) {
+        *self.authorizer.write().await = Some(authorizer);
+    }
+
+    /// Verify `session_token` and return who it belongs to. Without an
+    /// authorizer there is nothing to verify against and `None` is returned.
+    pub async fn authenticate(&self, session_token: &str) -> KEventResult<Option<KEventPrincipal>> {
+        let authorizer = self.authorizer.read().await.clone();
+        let Some(authorizer) = authorizer else {
+            return Ok(None);
+        };
+        if session_token.trim().is_empty() {
+            return Err(KEventError::PermissionDenied(
+                "session token is empty".to_string(),
+            ));
+        }
+        authorizer.authenticate(session_token).await.map(Some)
+    }
+
+    /// Bind the principal of `session_token` to a connection's session; it
+    /// applies to every later request on that connection.
+    pub async fn authenticate_session(
+        &self,
+        session: KEventSessionId,
+        session_token: &str,
+    ) -> KEventResult<()> {
+        if session == KEventSessionId::SHARED {
+            return Err(KEventError::NotSupported(
+                "connection-less callers pass their session token with each request".to_string(),
+            ));
+        }
+        match self.authenticate(session_token).await? {
+            Some(principal) => {
+                self.principals.write().await.insert(session, principal);
+            }
+            None => {
+                self.principals.write().await.remove(&session);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail with `PermissionDenied` unless `principal` may `access` every
+    /// target. A missing principal only passes when no authorizer is set.
+    pub async fn check_access(
+        &self,
+        principal: Option<&KEventPrincipal>,
+        access: KEventAccess,
+        targets: &[String],
+    ) -> KEventResult<()> {
+        let authorizer = self.authorizer.read().await.clone();
+        let Some(authorizer) = authorizer else {
+            return Ok(());
+        };
+        let Some(principal) = principal else {
+            return Err(KEventError::PermissionDenied(
+                "kevent daemon requires an authenticated session".to_string(),
+            ));
+        };
+        for target in targets {
+            if !authorizer.authorize(principal, access, target).await {
+                return Err(KEventError::PermissionDenied(format!(
+                    "{}/{} may not {} {}",
+                    principal.user_id,
+                    principal.app_id,
+                    access.action(),
+                    target
+                )));
+            }
+        }
+        Ok(())
+    }
+
     pub async fn register_reader(
         &self,
         reader_id: &str,
@@ -203,6 +364,7 @@ impl KEventService {
 
         let normalized = normalize_patterns(patterns);
         let key = ReaderKey::new(session, reader_id);
+        let _publishing = self.publish_lock.lock().await;
         let mut readers = self.readers.write().await;
         if let Some(existing) = readers.get(&key) {
             // Preserve queue / notify across re-register; just swap patterns.
@@ -210,7 +372,11 @@ impl KEventService {
         } else {
             readers.insert(
                 key,
-                Arc::new(ServiceReaderState::new(normalized, self.reader_capacity)),
+                Arc::new(ServiceReaderState::new(
+                    normalized,
+                    self.reader_capacity,
+                    self.journal.next_seq(),
+                )),
             );
         }
         Ok(())
@@ -304,12 +470,12 @@ impl KEventService {
             ingress_node: Some(self.source_node.clone()),
             timestamp: now_millis(),
             data,
+            seq: None,
         };
         // Mirror to shared ring so other local processes (full-mode SDK
         // readers that mmap the region) observe daemon-originated events
         // via the same fast path as peer/http-originated events.
-        self.mirror_to_shared_ring(&event).await?;
-        self.distribute(&event).await;
+        let event = self.sequence_and_distribute(event, true).await?;
         if should_broadcast_to_peers(&event, &self.source_node) {
             self.broadcast_to_peers(&event).await
         } else {
@@ -325,6 +491,7 @@ impl KEventService {
             ingress_node: Some(self.source_node.clone()),
             timestamp: now_millis(),
             data,
+            seq: None,
         };
         self.accept_external_global(event).await
     }
@@ -340,7 +507,7 @@ impl KEventService {
         if event.ingress_node.is_none() {
             event.ingress_node = Some(self.source_node.clone());
         }
-        self.distribute(&event).await;
+        let event = self.sequence_and_distribute(event, false).await?;
         if should_broadcast_to_peers(&event, &self.source_node) {
             self.broadcast_to_peers(&event).await
         } else {
@@ -357,8 +524,7 @@ impl KEventService {
         validate_eventid(&event.eventid)?;
         validate_event_data_size(&event.data)?;
         event.ingress_node = Some(self.source_node.clone());
-        self.mirror_to_shared_ring(&event).await?;
-        self.distribute(&event).await;
+        let event = self.sequence_and_distribute(event, true).await?;
         if should_broadcast_to_peers(&event, &self.source_node) {
             self.broadcast_to_peers(&event).await
         } else {
@@ -377,8 +543,7 @@ impl KEventService {
         if event.ingress_node.is_none() {
             event.ingress_node = Some(event.source_node.clone());
         }
-        self.mirror_to_shared_ring(&event).await?;
-        self.distribute(&event).await;
+        self.sequence_and_distribute(event, true).await?;
         Ok(())
     }
 
@@ -387,7 +552,7 @@ impl KEventService {
         reader_id: &str,
         timeout_ms: Option<u64>,
     ) -> KEventResult<Option<Event>> {
-        self.pull_event_in(KEventSessionId::SHARED, reader_id, timeout_ms)
+        self.pull_event_in(KEventSessionId::SHARED, reader_id, timeout_ms, None)
             .await
     }
 
@@ -395,14 +560,26 @@ impl KEventService {
     /// does not know about is reported as `ReaderClosed` so a client can tell
     /// "lost my registration" apart from "nothing happened" and re-register
     /// instead of silently pulling into the void.
+    ///
+    /// `after_seq` is the client's cursor: queued events at or below it are
+    /// dropped, and journaled events above it that predate the reader's
+    /// registration are replayed first. Replay is bounded by the journal
+    /// window and the reader capacity.
     pub async fn pull_event_in(
         &self,
         session: KEventSessionId,
         reader_id: &str,
         timeout_ms: Option<u64>,
+        after_seq: Option<u64>,
     ) -> KEventResult<Option<Event>> {
         let deadline = timeout_ms.map(|ms| Instant::now() + Duration::from_millis(ms));
         let key = ReaderKey::new(session, reader_id);
+        if let Some(after_seq) = after_seq {
+            let reader = self.readers.read().await.get(&key).cloned();
+            if let Some(reader) = reader {
+                self.resume_reader(&reader, after_seq).await;
+            }
+        }
         loop {
             let reader = {
                 let readers = self.readers.read().await;
@@ -446,13 +623,44 @@ impl KEventService {
 
     /// Serve one protocol request inside `session`'s reader namespace.
     /// Connection-oriented transports pass their own session so reader ids
-    /// cannot collide across clients and die with the connection.
+    /// cannot collide across clients and die with the connection. The
+    /// session's permissions are those of its last `authenticate`.
     pub async fn handle_protocol_request_in(
         &self,
         session: KEventSessionId,
         req: KEventDaemonRequest,
     ) -> KEventDaemonResponse {
+        if let KEventDaemonRequest::Authenticate { session_token } = &req {
+            return match self.authenticate_session(session, session_token).await {
+                Ok(_) => KEventDaemonResponse::Ok { event: None },
+                Err(err) => err_to_response(err),
+            };
+        }
+        let principal = self.principals.read().await.get(&session).cloned();
+        self.handle_protocol_request_as(session, principal.as_ref(), req)
+            .await
+    }
+
+    /// Serve one protocol request on behalf of an already authenticated
+    /// `principal` (the HTTP facade authenticates each request itself).
+    pub async fn handle_protocol_request_as(
+        &self,
+        session: KEventSessionId,
+        principal: Option<&KEventPrincipal>,
+        req: KEventDaemonRequest,
+    ) -> KEventDaemonResponse {
+        if let Err(err) = self.check_request(session, principal, &req).await {
+            return err_to_response(err);
+        }
         match req {
+            KEventDaemonRequest::Authenticate { session_token } => {
+                // Nothing to bind the identity to; just report whether the
+                // token is good.
+                match self.authenticate(&session_token).await {
+                    Ok(_) => KEventDaemonResponse::Ok { event: None },
+                    Err(err) => err_to_response(err),
+                }
+            }
             KEventDaemonRequest::RegisterReader {
                 reader_id,
                 patterns,
@@ -460,7 +668,10 @@ impl KEventService {
                 .register_reader_in(session, &reader_id, patterns)
                 .await
             {
-                Ok(_) => KEventDaemonResponse::Ok { event: None },
+                Ok(_) => {
+                    self.set_reader_owner(session, &reader_id, principal).await;
+                    KEventDaemonResponse::Ok { event: None }
+                }
                 Err(err) => err_to_response(err),
             },
             KEventDaemonRequest::UnregisterReader { reader_id } => {
@@ -484,13 +695,132 @@ impl KEventService {
             KEventDaemonRequest::PullEvent {
                 reader_id,
                 timeout_ms,
-            } => match self.pull_event_in(session, &reader_id, timeout_ms).await {
+                after_seq,
+            } => match self
+                .pull_event_in(session, &reader_id, timeout_ms, after_seq)
+                .await
+            {
                 Ok(event) => KEventDaemonResponse::Ok { event },
                 Err(err) => err_to_response(err),
             },
         }
     }
 
+    /// Permission gate of the protocol surface: registering and publishing
+    /// need the matching right on every pattern / the eventid, and a shared
+    /// namespace reader can only be used by whoever registered it.
+    async fn check_request(
+        &self,
+        session: KEventSessionId,
+        principal: Option<&KEventPrincipal>,
+        req: &KEventDaemonRequest,
+    ) -> KEventResult<()> {
+        if self.authorizer.read().await.is_none() {
+            return Ok(());
+        }
+        let reader_id = match req {
+            KEventDaemonRequest::Authenticate { .. } => return Ok(()),
+            KEventDaemonRequest::RegisterReader {
+                reader_id,
+                patterns,
+            } => {
+                self.check_access(principal, KEventAccess::Subscribe, patterns)
+                    .await?;
+                reader_id
+            }
+            KEventDaemonRequest::UpdateReader { reader_id, add, .. } => {
+                self.check_access(principal, KEventAccess::Subscribe, add)
+                    .await?;
+                reader_id
+            }
+            KEventDaemonRequest::PublishGlobal { event } => {
+                return self
+                    .check_access(
+                        principal,
+                        KEventAccess::Publish,
+                        std::slice::from_ref(&event.eventid),
+                    )
+                    .await;
+            }
+            KEventDaemonRequest::UnregisterReader { reader_id }
+            | KEventDaemonRequest::PullEvent { reader_id, .. } => reader_id,
+        };
+
+        let reader = self
+            .readers
+            .read()
+            .await
+            .get(&ReaderKey::new(session, reader_id))
+            .cloned();
+        if let Some(reader) = reader {
+            let owner = reader.owner.read().expect("owner lock poisoned").clone();
+            if owner.is_some() && owner.as_ref() != principal {
+                return Err(KEventError::PermissionDenied(format!(
+                    "reader {} belongs to another principal",
+                    reader_id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_reader_owner(
+        &self,
+        session: KEventSessionId,
+        reader_id: &str,
+        principal: Option<&KEventPrincipal>,
+    ) {
+        let reader = self
+            .readers
+            .read()
+            .await
+            .get(&ReaderKey::new(session, reader_id))
+            .cloned();
+        if let Some(reader) = reader {
+            *reader.owner.write().expect("owner lock poisoned") = principal.cloned();
+        }
+    }
+
+    /// Assign the next `seq`, journal the event and hand it to every matching
+    /// reader (and the shared ring when `mirror`), all under the publish lock.
+    async fn sequence_and_distribute(&self, mut event: Event, mirror: bool) -> KEventResult<Event> {
+        let _publishing = self.publish_lock.lock().await;
+        // Whatever seq the event carried belongs to the node that sent it.
+        event.seq = Some(self.journal.reserve_seq());
+        if mirror {
+            self.mirror_to_shared_ring(&event).await?;
+        }
+        self.journal.append(&event);
+        self.distribute(&event).await;
+        Ok(event)
+    }
+
+    async fn resume_reader(&self, reader: &ServiceReaderState, after_seq: u64) {
+        let mut queue = reader.queue.lock().await;
+        queue.retain(|event| event.seq.is_none_or(|seq| seq > after_seq));
+
+        let replay_from = reader.replay_from.load(Ordering::Acquire);
+        if after_seq.saturating_add(1) >= replay_from {
+            return;
+        }
+        let missed: Vec<Event> = self
+            .journal
+            .events_between(after_seq, replay_from)
+            .into_iter()
+            .filter(|event| reader.matches(&event.eventid))
+            .collect();
+        for event in missed.into_iter().rev() {
+            queue.push_front(event);
+        }
+        while queue.len() > reader.capacity {
+            queue.pop_front();
+        }
+        // Only the first resume of a registration needs the journal.
+        reader
+            .replay_from
+            .store(after_seq.saturating_add(1), Ordering::Release);
+    }
+
     async fn distribute(&self, event: &Event) {
         let snapshot: Vec<Arc<ServiceReaderState>> =
             self.readers.read().await.values().cloned().collect();
@@ -674,6 +1004,7 @@ mod tests {
                 ingress_node: Some("light_client".to_string()),
                 timestamp: 1,
                 data: json!({ "ok": true }),
+                seq: None,
             })
             .await
             .unwrap();
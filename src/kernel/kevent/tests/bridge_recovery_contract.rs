@@ -70,13 +70,18 @@ impl TestDaemon {
     /// the previous incarnation is gone, as it would be after a real restart.
     async fn restart(&mut self) {
         self.stop();
+        self.service = Arc::new(KEventService::new("test_node"));
+        self.listen_again().await;
+    }
+
+    /// Serve again with the *same* service after `stop`: every client lost
+    /// its connection, but the daemon (and its journal) kept running.
+    async fn listen_again(&mut self) {
         // Give the aborted task a moment to release the port.
         for _ in 0..50 {
             match TcpListener::bind(&self.addr).await {
                 Ok(listener) => {
-                    let service = Arc::new(KEventService::new("test_node"));
-                    self.service = service.clone();
-                    self.server = Some(spawn_server(service, listener));
+                    self.server = Some(spawn_server(self.service.clone(), listener));
                     return;
                 }
                 Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
@@ -552,3 +557,61 @@ async fn publish_is_best_effort_and_close_releases_the_reader() {
         Err(KEventError::InvalidEventId(_))
     ));
 }
+
+/// Events published while a reader's connection was down are no longer lost:
+/// the link pulls after its last seen `seq`, the daemon replays the gap from
+/// its journal, and nothing the reader already had is delivered twice.
+#[tokio::test]
+async fn reconnected_reader_receives_events_missed_while_disconnected() {
+    let mut daemon = TestDaemon::start().await;
+    let client = KEventClient::new_daemon_bridge("opendan", &daemon.addr).unwrap();
+    let reader = client
+        .create_event_reader(vec!["/resume/**".to_string()])
+        .await
+        .unwrap();
+    wait_until("reader registered", Duration::from_secs(10), || {
+        let service = daemon.service.clone();
+        async move { service.reader_count().await == 1 }
+    })
+    .await;
+
+    daemon
+        .service
+        .publish_local_global("/resume/before", json!({}))
+        .await
+        .unwrap();
+    let before = reader.pull_event(Some(3_000)).await.unwrap().unwrap();
+    let before_seq = before.seq.expect("daemon assigns a seq");
+    assert_eq!(reader.last_seq(), Some(before_seq));
+
+    daemon.stop();
+    for eventid in ["/resume/missed_1", "/resume/missed_2"] {
+        daemon
+            .service
+            .publish_local_global(eventid, json!({}))
+            .await
+            .unwrap();
+    }
+    daemon.listen_again().await;
+
+    let mut got = Vec::new();
+    while got.len() < 2 {
+        let event = reader
+            .pull_event(Some(15_000))
+            .await
+            .unwrap()
+            .expect("missed events are replayed after reconnect");
+        got.push(event.eventid);
+    }
+    assert_eq!(got, vec!["/resume/missed_1", "/resume/missed_2"]);
+    assert!(reader.pull_event(Some(300)).await.unwrap().is_none());
+
+    // A new process can pick up from a persisted cursor the same way.
+    let restarted = KEventClient::new_daemon_bridge("opendan", &daemon.addr).unwrap();
+    let resumed = restarted
+        .create_event_reader_after(vec!["/resume/**".to_string()], before_seq)
+        .await
+        .unwrap();
+    let event = resumed.pull_event(Some(10_000)).await.unwrap().unwrap();
+    assert_eq!(event.eventid, "/resume/missed_1");
+}
@@ -72,6 +72,7 @@ async fn native_endpoint_roundtrips_protocol_and_rejects_bad_json() {
             serde_json::to_vec(&KEventDaemonRequest::PullEvent {
                 reader_id: "r1".to_string(),
                 timeout_ms: Some(50),
+                after_seq: None,
             })
             .unwrap()
             .as_slice(),
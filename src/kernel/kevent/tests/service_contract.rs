@@ -1,9 +1,13 @@
 use async_trait::async_trait;
-use buckyos_api::{Event, KEventError, KEventResult};
-use kevent::{InProcessPeerPublisher, KEventPeerPublisher, KEventService};
+use buckyos_api::{Event, KEventDaemonRequest, KEventDaemonResponse, KEventError, KEventResult};
+use kevent::{
+    InProcessPeerPublisher, KEventAccess, KEventAuthorizer, KEventJournal, KEventPeerPublisher,
+    KEventPrincipal, KEventService, KEventSessionId,
+};
 use serde_json::json;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 struct CountingPeer {
     inner: InProcessPeerPublisher,
@@ -31,6 +35,40 @@ impl KEventPeerPublisher for CountingPeer {
     }
 }
 
+/// Token `token-<app>` authenticates as app `<app>`, which owns
+/// `/apps/<app>/...` and nothing else.
+struct AppNamespaceAuthorizer;
+
+#[async_trait]
+impl KEventAuthorizer for AppNamespaceAuthorizer {
+    async fn authenticate(&self, session_token: &str) -> KEventResult<KEventPrincipal> {
+        let app_id = session_token
+            .strip_prefix("token-")
+            .ok_or_else(|| KEventError::PermissionDenied("bad token".to_string()))?;
+        Ok(KEventPrincipal {
+            user_id: "alice".to_string(),
+            app_id: app_id.to_string(),
+            sudo: false,
+        })
+    }
+
+    async fn authorize(
+        &self,
+        principal: &KEventPrincipal,
+        _access: KEventAccess,
+        target: &str,
+    ) -> bool {
+        target.starts_with(&format!("/apps/{}/", principal.app_id))
+    }
+}
+
+fn response_code(resp: KEventDaemonResponse) -> Option<String> {
+    match resp {
+        KEventDaemonResponse::Ok { .. } => None,
+        KEventDaemonResponse::Err { code, .. } => Some(code),
+    }
+}
+
 #[tokio::test]
 async fn service_register_publish_pull_and_invalid_inputs() {
     let service = KEventService::new("node_a");
@@ -157,3 +195,290 @@ async fn peer_publish_delivers_once_and_does_not_rebroadcast_peer_events() {
     assert_eq!(a_to_b.count(), 1);
     assert_eq!(b_to_a.count(), 0);
 }
+
+async fn drain_eventids(
+    service: &KEventService,
+    session: KEventSessionId,
+    after_seq: Option<u64>,
+) -> Vec<String> {
+    let mut got = Vec::new();
+    let mut cursor = after_seq;
+    while let Some(event) = service
+        .pull_event_in(session, "r1", Some(0), cursor)
+        .await
+        .unwrap()
+    {
+        cursor = event.seq;
+        got.push(event.eventid);
+    }
+    got
+}
+
+/// A reader that comes back on a new connection with its last seen `seq`
+/// gets what was published while it was gone, in order and without the
+/// events it had already pulled.
+#[tokio::test]
+async fn resumed_reader_replays_the_gap_from_the_journal() {
+    let service = KEventService::new("node_a");
+    let first = service.open_session();
+    service
+        .register_reader_in(first, "r1", vec!["/resume/**".to_string()])
+        .await
+        .unwrap();
+    service
+        .publish_local_global("/resume/seen", json!({}))
+        .await
+        .unwrap();
+    let seen = service
+        .pull_event_in(first, "r1", Some(0), None)
+        .await
+        .unwrap()
+        .unwrap();
+    let seen_seq = seen.seq.expect("daemon assigns a seq");
+    service.close_session(first).await;
+
+    for eventid in ["/resume/missed_1", "/other/ignored", "/resume/missed_2"] {
+        service
+            .publish_local_global(eventid, json!({}))
+            .await
+            .unwrap();
+    }
+
+    let second = service.open_session();
+    service
+        .register_reader_in(second, "r1", vec!["/resume/**".to_string()])
+        .await
+        .unwrap();
+    service
+        .publish_local_global("/resume/live", json!({}))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        drain_eventids(&service, second, Some(seen_seq)).await,
+        vec!["/resume/missed_1", "/resume/missed_2", "/resume/live"]
+    );
+    // The gap is replayed once per registration, not on every pull.
+    assert!(drain_eventids(&service, second, Some(seen_seq))
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn on_disk_journal_survives_a_daemon_restart() {
+    let path = std::env::temp_dir().join(format!(
+        "kevent_service_journal_{}_{}.jsonl",
+        std::process::id(),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    let open =
+        || KEventService::new_with_journal("node_a", 16, KEventJournal::open(&path, 16).unwrap());
+
+    let seen_seq = {
+        let service = open();
+        let mut last = 0;
+        for eventid in ["/restart/seen", "/restart/missed"] {
+            service
+                .publish_local_global(eventid, json!({}))
+                .await
+                .unwrap();
+            if last == 0 {
+                last = service.journal().next_seq() - 1;
+            }
+        }
+        last
+    };
+
+    let service = open();
+    assert!(service.journal().next_seq() > seen_seq + 1);
+    let session = service.open_session();
+    service
+        .register_reader_in(session, "r1", vec!["/restart/**".to_string()])
+        .await
+        .unwrap();
+    assert_eq!(
+        drain_eventids(&service, session, Some(seen_seq)).await,
+        vec!["/restart/missed"]
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn authorizer_gates_protocol_register_and_publish_per_session() {
+    let service = KEventService::new("node_a");
+    service
+        .set_authorizer(Arc::new(AppNamespaceAuthorizer))
+        .await;
+    let session = service.open_session();
+    let register = |patterns: &[&str]| KEventDaemonRequest::RegisterReader {
+        reader_id: "r1".to_string(),
+        patterns: patterns.iter().map(|p| p.to_string()).collect(),
+    };
+
+    // Nothing is allowed before the connection authenticates.
+    assert_eq!(
+        response_code(
+            service
+                .handle_protocol_request_in(session, register(&["/apps/notes/**"]))
+                .await
+        )
+        .as_deref(),
+        Some("PERMISSION_DENIED")
+    );
+    assert_eq!(
+        response_code(
+            service
+                .handle_protocol_request_in(
+                    session,
+                    KEventDaemonRequest::Authenticate {
+                        session_token: "forged".to_string(),
+                    },
+                )
+                .await
+        )
+        .as_deref(),
+        Some("PERMISSION_DENIED")
+    );
+
+    assert_eq!(
+        response_code(
+            service
+                .handle_protocol_request_in(
+                    session,
+                    KEventDaemonRequest::Authenticate {
+                        session_token: "token-notes".to_string(),
+                    },
+                )
+                .await
+        ),
+        None
+    );
+    // One pattern outside the app's namespace rejects the whole set.
+    assert_eq!(
+        response_code(
+            service
+                .handle_protocol_request_in(session, register(&["/apps/notes/**", "/system/**"]))
+                .await
+        )
+        .as_deref(),
+        Some("PERMISSION_DENIED")
+    );
+    assert_eq!(service.reader_count().await, 0);
+    assert_eq!(
+        response_code(
+            service
+                .handle_protocol_request_in(session, register(&["/apps/notes/**"]))
+                .await
+        ),
+        None
+    );
+
+    let mut event = Event {
+        eventid: "/system/node/online".to_string(),
+        source_node: "node_a".to_string(),
+        source_pid: 1,
+        ingress_node: None,
+        timestamp: 1,
+        data: json!({}),
+        seq: None,
+    };
+    assert_eq!(
+        response_code(
+            service
+                .handle_protocol_request_in(
+                    session,
+                    KEventDaemonRequest::PublishGlobal {
+                        event: event.clone(),
+                    },
+                )
+                .await
+        )
+        .as_deref(),
+        Some("PERMISSION_DENIED")
+    );
+    event.eventid = "/apps/notes/saved".to_string();
+    assert_eq!(
+        response_code(
+            service
+                .handle_protocol_request_in(session, KEventDaemonRequest::PublishGlobal { event })
+                .await
+        ),
+        None
+    );
+
+    // Daemon-internal publishers stay trusted, and the reader only sees its
+    // own namespace.
+    service
+        .publish_local_global("/system/node/online", json!({}))
+        .await
+        .unwrap();
+    let pulled = service
+        .handle_protocol_request_in(
+            session,
+            KEventDaemonRequest::PullEvent {
+                reader_id: "r1".to_string(),
+                timeout_ms: Some(0),
+                after_seq: None,
+            },
+        )
+        .await;
+    match pulled {
+        KEventDaemonResponse::Ok { event: Some(event) } => {
+            assert_eq!(event.eventid, "/apps/notes/saved")
+        }
+        other => panic!("unexpected pull response: {:?}", other),
+    }
+
+    // The identity dies with the connection.
+    service.close_session(session).await;
+    assert_eq!(
+        response_code(
+            service
+                .handle_protocol_request_in(session, register(&["/apps/notes/**"]))
+                .await
+        )
+        .as_deref(),
+        Some("PERMISSION_DENIED")
+    );
+}
+
+#[tokio::test]
+async fn shared_namespace_readers_are_bound_to_their_principal() {
+    let service = KEventService::new("node_a");
+    service
+        .set_authorizer(Arc::new(AppNamespaceAuthorizer))
+        .await;
+    let notes = service.authenticate("token-notes").await.unwrap().unwrap();
+    let other = service.authenticate("token-other").await.unwrap().unwrap();
+
+    let resp = service
+        .handle_protocol_request_as(
+            KEventSessionId::SHARED,
+            Some(&notes),
+            KEventDaemonRequest::RegisterReader {
+                reader_id: "http_stream_1".to_string(),
+                patterns: vec!["/apps/notes/**".to_string()],
+            },
+        )
+        .await;
+    assert_eq!(response_code(resp), None);
+
+    // Guessing another caller's reader id gets nothing.
+    for (principal, expected) in [(&other, Some("PERMISSION_DENIED")), (&notes, None)] {
+        let resp = service
+            .handle_protocol_request_as(
+                KEventSessionId::SHARED,
+                Some(principal),
+                KEventDaemonRequest::PullEvent {
+                    reader_id: "http_stream_1".to_string(),
+                    timeout_ms: Some(0),
+                    after_seq: None,
+                },
+            )
+            .await;
+        assert_eq!(response_code(resp).as_deref(), expected);
+    }
+}
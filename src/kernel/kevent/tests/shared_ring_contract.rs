@@ -30,6 +30,7 @@ fn event(seq: u64) -> Event {
         ingress_node: Some("node_a".to_string()),
         timestamp: seq,
         data: json!({ "seq": seq }),
+        seq: None,
     }
 }
 
@@ -41,6 +42,7 @@ fn large_event() -> Event {
         ingress_node: Some("node_a".to_string()),
         timestamp: 1,
         data: json!({ "blob": "x".repeat(4096) }),
+        seq: None,
     }
 }
 
@@ -9,6 +9,10 @@ pub enum KLogErrorCode {
     NotLeader,
     LeaderUnavailable,
     VersionConflict,
+    /// The referenced object (e.g. a lease) does not exist or already expired.
+    NotFound,
+    /// A watch cursor is older than the history the server still keeps.
+    Compacted,
     ConfigChangeInProgress,
     PayloadTooLarge,
     Timeout,
@@ -144,6 +148,7 @@ pub fn map_http_status_to_error_code(http_status: u16) -> KLogErrorCode {
         400 => KLogErrorCode::InvalidArgument,
         401 => KLogErrorCode::AuthRequired,
         403 => KLogErrorCode::Forbidden,
+        404 => KLogErrorCode::NotFound,
         408 | 504 => KLogErrorCode::Timeout,
         409 => KLogErrorCode::NotLeader,
         412 => KLogErrorCode::VersionConflict,
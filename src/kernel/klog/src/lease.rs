@@ -0,0 +1,454 @@
+use crate::state_store::KLogStateStoreManagerRef;
+use crate::txn::KLogTxnWritePlan;
+use crate::{KLogError, KLogMetaEntry, KLogRequest, KLogResponse, KNodeId, KRaftRef, KResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Meta key prefix of the JSON encoded `KLogLease` records.
+/// Leases live in replicated meta, so they follow snapshots and leader changes like any key.
+pub const KLOG_LEASE_META_PREFIX: &str = "__klog/lease/";
+pub const LEASE_MIN_TTL_MS: u64 = 1_000;
+pub const LEASE_MAX_TTL_MS: u64 = 24 * 60 * 60 * 1000;
+pub const LEASE_DEFAULT_CHECK_INTERVAL_MS: u64 = 500;
+const LEASE_SCAN_PAGE_SIZE: usize = 256;
+
+/// A time-to-live handle that meta keys can be attached to.
+///
+/// Expiry is never decided locally: the leader proposes `ExpireLease` once `expires_at`
+/// has passed on its clock, and every replica deletes the lease and its keys on apply.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KLogLease {
+    /// Raft log index of the grant, unique for the lifetime of the cluster.
+    pub id: u64,
+    pub ttl_ms: u64,
+    pub granted_at: u64,
+    pub granted_by: KNodeId,
+    /// Leader wall clock (ms) after which the lease expires unless kept alive.
+    pub expires_at: u64,
+    /// Meta keys attached to this lease, deleted together with it.
+    #[serde(default)]
+    pub keys: BTreeSet<String>,
+}
+
+impl KLogLease {
+    pub fn meta_key(lease_id: u64) -> String {
+        // Zero padded so that listing the prefix returns leases in id order.
+        format!("{}{:020}", KLOG_LEASE_META_PREFIX, lease_id)
+    }
+
+    pub fn to_meta_value(&self) -> KResult<String> {
+        serde_json::to_string(self).map_err(|e| {
+            let msg = format!("Failed to encode lease {}: {}", self.id, e);
+            error!("{}", msg);
+            KLogError::InvalidFormat(msg)
+        })
+    }
+
+    pub fn from_meta_value(value: &str) -> KResult<Self> {
+        serde_json::from_str(value).map_err(|e| {
+            let msg = format!("Failed to decode lease: {}", e);
+            error!("{}", msg);
+            KLogError::InvalidFormat(msg)
+        })
+    }
+
+    pub fn remaining_ms(&self, now_ms: u64) -> u64 {
+        self.expires_at.saturating_sub(now_ms)
+    }
+}
+
+pub fn validate_lease_ttl(ttl_ms: u64) -> Result<(), String> {
+    if !(LEASE_MIN_TTL_MS..=LEASE_MAX_TTL_MS).contains(&ttl_ms) {
+        return Err(format!(
+            "invalid lease ttl_ms={}, allowed={}..={}",
+            ttl_ms, LEASE_MIN_TTL_MS, LEASE_MAX_TTL_MS
+        ));
+    }
+    Ok(())
+}
+
+pub async fn load_lease(
+    state_store_manager: &KLogStateStoreManagerRef,
+    lease_id: u64,
+) -> KResult<Option<KLogLease>> {
+    let Some(item) = state_store_manager
+        .get_meta_entry(&KLogLease::meta_key(lease_id))
+        .await?
+    else {
+        return Ok(None);
+    };
+    KLogLease::from_meta_value(&item.value).map(Some)
+}
+
+/// List stored leases in id order, starting after `after_id`.
+pub async fn list_leases(
+    state_store_manager: &KLogStateStoreManagerRef,
+    after_id: Option<u64>,
+    limit: usize,
+) -> KResult<Vec<KLogLease>> {
+    let after_key = after_id.map(KLogLease::meta_key);
+    let page = state_store_manager
+        .export_meta_page(
+            Some(after_key.as_deref().unwrap_or(KLOG_LEASE_META_PREFIX)),
+            limit,
+        )
+        .await?;
+    page.iter()
+        .take_while(|item| item.key.starts_with(KLOG_LEASE_META_PREFIX))
+        .map(|item| KLogLease::from_meta_value(&item.value))
+        .collect()
+}
+
+async fn store_lease(
+    state_store_manager: &KLogStateStoreManagerRef,
+    lease: &KLogLease,
+    updated_at: u64,
+    updated_by: KNodeId,
+) -> KResult<()> {
+    state_store_manager
+        .put_meta_entry(KLogMetaEntry {
+            key: KLogLease::meta_key(lease.id),
+            value: lease.to_meta_value()?,
+            updated_at,
+            updated_by,
+            revision: 0,
+            lease_id: None,
+        })
+        .await?;
+    Ok(())
+}
+
+/// State machine side of `GrantLease`.
+pub async fn apply_grant_lease(
+    state_store_manager: &KLogStateStoreManagerRef,
+    lease_id: u64,
+    ttl_ms: u64,
+    granted_at: u64,
+    granted_by: KNodeId,
+) -> KResult<KLogLease> {
+    let lease = KLogLease {
+        id: lease_id,
+        ttl_ms,
+        granted_at,
+        granted_by,
+        expires_at: granted_at.saturating_add(ttl_ms),
+        keys: BTreeSet::new(),
+    };
+    store_lease(state_store_manager, &lease, granted_at, granted_by).await?;
+    Ok(lease)
+}
+
+/// State machine side of `KeepAliveLease`; `None` when the lease is gone.
+pub async fn apply_keepalive_lease(
+    state_store_manager: &KLogStateStoreManagerRef,
+    lease_id: u64,
+    now_ms: u64,
+) -> KResult<Option<KLogLease>> {
+    let Some(mut lease) = load_lease(state_store_manager, lease_id).await? else {
+        return Ok(None);
+    };
+    // Never move the expiry backwards, e.g. when a lagging node proposes with an older clock.
+    lease.expires_at = lease.expires_at.max(now_ms.saturating_add(lease.ttl_ms));
+    store_lease(state_store_manager, &lease, now_ms, lease.granted_by).await?;
+    Ok(Some(lease))
+}
+
+/// Stage the lease key-set update for `key` moving from lease `prev_lease_id` to `lease_id`
+/// (either may be `None`) into `plan`.
+pub async fn plan_meta_lease_change(
+    plan: &mut KLogTxnWritePlan<'_>,
+    key: &str,
+    prev_lease_id: Option<u64>,
+    lease_id: Option<u64>,
+    updated_at: u64,
+    updated_by: KNodeId,
+) -> KResult<()> {
+    if prev_lease_id == lease_id {
+        return Ok(());
+    }
+    if let Some(prev_lease_id) = prev_lease_id
+        && let Some(mut prev) = plan_load_lease(plan, prev_lease_id).await?
+        && prev.keys.remove(key)
+    {
+        plan_store_lease(plan, &prev, updated_at, updated_by).await?;
+    }
+    if let Some(lease_id) = lease_id
+        && let Some(mut lease) = plan_load_lease(plan, lease_id).await?
+        && lease.keys.insert(key.to_string())
+    {
+        plan_store_lease(plan, &lease, updated_at, updated_by).await?;
+    }
+    Ok(())
+}
+
+async fn plan_load_lease(plan: &KLogTxnWritePlan<'_>, lease_id: u64) -> KResult<Option<KLogLease>> {
+    let Some(item) = plan.get(&KLogLease::meta_key(lease_id)).await? else {
+        return Ok(None);
+    };
+    KLogLease::from_meta_value(&item.value).map(Some)
+}
+
+async fn plan_store_lease(
+    plan: &mut KLogTxnWritePlan<'_>,
+    lease: &KLogLease,
+    updated_at: u64,
+    updated_by: KNodeId,
+) -> KResult<()> {
+    plan.put(KLogMetaEntry {
+        key: KLogLease::meta_key(lease.id),
+        value: lease.to_meta_value()?,
+        updated_at,
+        updated_by,
+        revision: 0,
+        lease_id: None,
+    })
+    .await?;
+    Ok(())
+}
+
+/// State machine side of `RevokeLease`/`ExpireLease`.
+///
+/// With `expected_expires_at` set the lease is only removed if it still has that expiry,
+/// returns `Ok(None)` when there is nothing to remove.
+pub async fn apply_revoke_lease(
+    state_store_manager: &KLogStateStoreManagerRef,
+    lease_id: u64,
+    expected_expires_at: Option<u64>,
+) -> KResult<Option<(KLogLease, Vec<KLogMetaEntry>)>> {
+    let Some(lease) = load_lease(state_store_manager, lease_id).await? else {
+        return Ok(None);
+    };
+    if let Some(expected) = expected_expires_at
+        && expected != lease.expires_at
+    {
+        debug!(
+            "lease expire skipped, renewed meanwhile: lease_id={}, expected_expires_at={}, expires_at={}",
+            lease_id, expected, lease.expires_at
+        );
+        return Ok(None);
+    }
+
+    let mut deleted = Vec::with_capacity(lease.keys.len());
+    for key in &lease.keys {
+        // A key that was re-put under another lease (or none) is no longer ours.
+        let Some(current) = state_store_manager.get_meta_entry(key).await? else {
+            continue;
+        };
+        if current.lease_id != Some(lease_id) {
+            continue;
+        }
+        if let Some(prev) = state_store_manager.delete_meta_key(key).await? {
+            deleted.push(prev);
+        }
+    }
+    state_store_manager
+        .delete_meta_key(&KLogLease::meta_key(lease_id))
+        .await?;
+    Ok(Some((lease, deleted)))
+}
+
+#[derive(Debug)]
+pub enum KLogLeaseError {
+    NotLeader { leader_id: Option<KNodeId> },
+    Store(KLogError),
+    Raft(String),
+}
+
+impl std::fmt::Display for KLogLeaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotLeader { leader_id } => {
+                write!(
+                    f,
+                    "lease expiry must run on leader, current_leader={:?}",
+                    leader_id
+                )
+            }
+            Self::Store(err) => write!(f, "lease store error: {}", err),
+            Self::Raft(err) => write!(f, "lease raft error: {}", err),
+        }
+    }
+}
+
+impl From<KLogError> for KLogLeaseError {
+    fn from(err: KLogError) -> Self {
+        Self::Store(err)
+    }
+}
+
+/// Leases that are due on the leader clock.
+///
+/// `leader_for_ms` is how long this node has been leader: a lease is only expired once
+/// the leader has been in charge for a full TTL, so holders always get a chance to reach
+/// a new leader after failover regardless of clock skew between nodes.
+pub async fn plan_lease_expiry(
+    state_store_manager: &KLogStateStoreManagerRef,
+    now_ms: u64,
+    leader_for_ms: u64,
+) -> KResult<Vec<KLogLease>> {
+    let mut due = Vec::new();
+    let mut after_id = None;
+    loop {
+        let page = list_leases(state_store_manager, after_id, LEASE_SCAN_PAGE_SIZE).await?;
+        let Some(last) = page.last() else {
+            return Ok(due);
+        };
+        after_id = Some(last.id);
+        let full_page = page.len() >= LEASE_SCAN_PAGE_SIZE;
+        due.extend(
+            page.into_iter()
+                .filter(|lease| lease.expires_at < now_ms && leader_for_ms >= lease.ttl_ms),
+        );
+        if !full_page {
+            return Ok(due);
+        }
+    }
+}
+
+/// Run one expiry pass on the leader, replicating `ExpireLease` for every due lease.
+/// Returns how many leases were removed.
+pub async fn run_lease_expiry_once(
+    raft: &KRaftRef,
+    state_store_manager: &KLogStateStoreManagerRef,
+    leader_for_ms: u64,
+) -> Result<u64, KLogLeaseError> {
+    let (local_node_id, current_leader) = {
+        let metrics = raft.metrics();
+        let metrics = metrics.borrow();
+        (metrics.id, metrics.current_leader)
+    };
+    if current_leader != Some(local_node_id) {
+        return Err(KLogLeaseError::NotLeader {
+            leader_id: current_leader,
+        });
+    }
+
+    let due = plan_lease_expiry(state_store_manager, now_millis(), leader_for_ms).await?;
+    let mut expired = 0u64;
+    for lease in due {
+        let resp = raft
+            .client_write(KLogRequest::ExpireLease {
+                lease_id: lease.id,
+                expires_at: lease.expires_at,
+            })
+            .await
+            .map_err(|e| {
+                if let Some(forward) = e.forward_to_leader::<crate::KNode>() {
+                    return KLogLeaseError::NotLeader {
+                        leader_id: forward.leader_id,
+                    };
+                }
+                KLogLeaseError::Raft(e.to_string())
+            })?;
+        match resp.data {
+            KLogResponse::LeaseRevoked {
+                lease,
+                deleted_keys,
+            } => {
+                info!(
+                    "lease expired: node_id={}, lease_id={}, ttl_ms={}, expires_at={}, deleted_keys={:?}",
+                    local_node_id, lease.id, lease.ttl_ms, lease.expires_at, deleted_keys
+                );
+                expired += 1;
+            }
+            // Kept alive or revoked after the plan was made.
+            KLogResponse::LeaseNotFound { .. } => {}
+            KLogResponse::Err(err) => return Err(KLogLeaseError::Raft(err)),
+            other => {
+                return Err(KLogLeaseError::Raft(format!(
+                    "unexpected lease expire response: {:?}",
+                    other
+                )));
+            }
+        }
+    }
+    Ok(expired)
+}
+
+/// Background task that expires leases. Every node may run it; only the current leader
+/// submits expirations.
+pub struct KLogLeaseWorker {
+    raft: KRaftRef,
+    state_store_manager: KLogStateStoreManagerRef,
+    check_interval: Duration,
+}
+
+impl KLogLeaseWorker {
+    pub fn new(
+        raft: KRaftRef,
+        state_store_manager: KLogStateStoreManagerRef,
+        check_interval_ms: u64,
+    ) -> Self {
+        Self {
+            raft,
+            state_store_manager,
+            check_interval: Duration::from_millis(check_interval_ms.max(1)),
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            info!(
+                "KLogLeaseWorker started: check_interval_ms={}",
+                self.check_interval.as_millis()
+            );
+            let mut ticker = tokio::time::interval(self.check_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            // (term, local instant) at which this node was first seen as leader.
+            let mut leader_since: Option<(u64, tokio::time::Instant)> = None;
+            loop {
+                ticker.tick().await;
+                let (is_leader, term) = {
+                    let metrics = self.raft.metrics();
+                    let metrics = metrics.borrow();
+                    (
+                        metrics.current_leader == Some(metrics.id),
+                        metrics.current_term,
+                    )
+                };
+                if !is_leader {
+                    leader_since = None;
+                    continue;
+                }
+                let since = match leader_since {
+                    Some((since_term, since)) if since_term == term => since,
+                    _ => {
+                        let now = tokio::time::Instant::now();
+                        info!("KLogLeaseWorker became leader: term={}", term);
+                        leader_since = Some((term, now));
+                        now
+                    }
+                };
+                let leader_for_ms = since.elapsed().as_millis() as u64;
+                match run_lease_expiry_once(&self.raft, &self.state_store_manager, leader_for_ms)
+                    .await
+                {
+                    Ok(expired) => {
+                        if expired > 0 {
+                            debug!("KLogLeaseWorker pass finished: expired={}", expired);
+                        }
+                    }
+                    Err(KLogLeaseError::NotLeader { leader_id }) => {
+                        debug!(
+                            "KLogLeaseWorker skip pass on non-leader: current_leader={:?}",
+                            leader_id
+                        );
+                        leader_since = None;
+                    }
+                    Err(err) => {
+                        warn!("KLogLeaseWorker pass failed: {}", err);
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -10,14 +10,17 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 
 pub mod error;
+pub mod lease;
 pub mod logs;
 pub mod network;
+pub mod retention;
 pub mod rpc;
 pub(crate) mod service;
 pub mod state_machine;
 pub mod state_store;
 #[cfg(test)]
 mod test;
+pub mod txn;
 pub(crate) mod util;
 
 #[derive(Serialize, Deserialize, Debug, Clone, thiserror::Error)]
@@ -64,6 +67,9 @@ pub struct KLogMetaEntry {
     pub updated_by: KNodeId,
     #[serde(default)]
     pub revision: u64,
+    /// Lease the key is attached to; the key is deleted when the lease expires or is revoked.
+    #[serde(default)]
+    pub lease_id: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +77,11 @@ pub enum KLogRequest {
     AppendLog {
         item: KLogEntry,
     },
+    /// Entries with leader-assigned ids written in one state machine step. An entry whose
+    /// `request_id` was already appended is skipped and reports the earlier id.
+    AppendLogBatch {
+        items: Vec<KLogEntry>,
+    },
     PutMeta {
         item: KLogMetaEntry,
         expected_revision: Option<u64>,
@@ -78,6 +89,40 @@ pub enum KLogRequest {
     DeleteMeta {
         key: String,
     },
+    /// Drop entries with `id < before_id`, optionally only those of one `source`.
+    /// The cut point is decided by the leader so every replica removes the same rows.
+    TruncateBefore {
+        before_id: u64,
+        source: Option<String>,
+    },
+    /// Create a lease; its id is the raft log index of this request.
+    GrantLease {
+        ttl_ms: u64,
+        granted_at: u64,
+        granted_by: KNodeId,
+    },
+    /// Push the lease expiry to `now_ms + ttl_ms`.
+    KeepAliveLease {
+        lease_id: u64,
+        now_ms: u64,
+    },
+    /// Delete the lease and every key still attached to it.
+    RevokeLease {
+        lease_id: u64,
+    },
+    /// Leader decided expiry; only applied if the lease was not kept alive in between,
+    /// i.e. it still has `expires_at`.
+    ExpireLease {
+        lease_id: u64,
+        expires_at: u64,
+    },
+    /// Check every `compare`, then apply `then_ops` if all hold and `else_ops` otherwise,
+    /// all in one state machine step.
+    Txn {
+        compare: Vec<txn::KLogTxnCompare>,
+        then_ops: Vec<txn::KLogTxnOp>,
+        else_ops: Vec<txn::KLogTxnOp>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +132,10 @@ pub enum KLogResponse {
     AppendOk {
         id: u64,
     },
+    /// One id per batch entry, in request order.
+    AppendBatchOk {
+        ids: Vec<u64>,
+    },
     MetaPutOk {
         key: String,
         revision: u64,
@@ -101,6 +150,25 @@ pub enum KLogResponse {
         existed: bool,
         prev_meta: Option<KLogMetaEntry>,
     },
+    TruncateOk {
+        before_id: u64,
+        source: Option<String>,
+        removed: u64,
+    },
+    LeaseOk {
+        lease: lease::KLogLease,
+    },
+    LeaseRevoked {
+        lease: lease::KLogLease,
+        deleted_keys: Vec<String>,
+    },
+    LeaseNotFound {
+        lease_id: u64,
+    },
+    TxnOk {
+        succeeded: bool,
+        results: Vec<txn::KLogTxnOpResult>,
+    },
     Err(String),
 }
 
@@ -447,6 +447,11 @@ impl RaftLogStorage<KTypeConfig> for RocksDbLogStorage {
             .write_opt(batch, &self.write_options())
             .map_err(Self::db_write_err)?;
 
+        // Reclaim the tombstoned prefix right away instead of waiting for background compaction.
+        let end_key = Self::entry_key(log_id.index.saturating_add(1));
+        self.db
+            .compact_range_cf(&logs_cf, None::<&[u8]>, Some(end_key.as_slice()));
+
         Ok(())
     }
 }
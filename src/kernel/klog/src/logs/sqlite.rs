@@ -17,6 +17,7 @@ const META_VOTE: &str = "vote";
 const META_LAST_PURGED: &str = "last_purged";
 const META_COMMITTED: &str = "committed";
 const SQLITE_LOG_INDEX_MAX_U64: u64 = i64::MAX as u64;
+const SQLITE_AUTO_VACUUM_INCREMENTAL: i64 = 2;
 
 #[derive(Debug, Clone)]
 pub struct SqliteLogStorage {
@@ -43,6 +44,8 @@ impl SqliteLogStorage {
             )
         })?;
 
+        Self::ensure_incremental_auto_vacuum(&conn)?;
+
         conn.execute_batch(
             r#"
             PRAGMA journal_mode = WAL;
@@ -67,6 +70,25 @@ impl SqliteLogStorage {
         })
     }
 
+    /// Purged pages are only returned to the filesystem when auto_vacuum is incremental.
+    /// Databases created before this was enabled are converted once with a full VACUUM.
+    fn ensure_incremental_auto_vacuum(conn: &Connection) -> Result<(), String> {
+        let mode: i64 = conn
+            .query_row("PRAGMA auto_vacuum", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read sqlite auto_vacuum mode: {}", e))?;
+        if mode == SQLITE_AUTO_VACUUM_INCREMENTAL {
+            return Ok(());
+        }
+
+        conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")
+            .map_err(|e| format!("Failed to enable sqlite incremental auto_vacuum: {}", e))?;
+        info!(
+            "sqlite raft log auto_vacuum switched to incremental: previous_mode={}",
+            mode
+        );
+        Ok(())
+    }
+
     fn ser<T: serde::Serialize>(payload_type: PersistPayloadType, v: &T) -> StorageResult<Vec<u8>> {
         encode_with_header(payload_type, v).map_err(|e| {
             let io_err = std::io::Error::other(format!("Failed to serialize value: {}", e));
@@ -433,6 +455,9 @@ impl RaftLogStorage<KTypeConfig> for SqliteLogStorage {
         let conn = self.conn.lock().await;
         conn.execute("DELETE FROM raft_logs WHERE log_index <= ?1", params![idx])
             .map_err(Self::sql_write_err)?;
+        // Return freed pages to the filesystem and fold the WAL back so the db file shrinks.
+        conn.execute_batch("PRAGMA incremental_vacuum; PRAGMA wal_checkpoint(TRUNCATE);")
+            .map_err(Self::sql_write_err)?;
         drop(conn);
 
         let encoded = Self::ser(PersistPayloadType::SqliteLastPurgedLogId, &log_id)?;
@@ -1,22 +1,32 @@
 use super::request::{
-    KLOG_FORWARD_HOPS_HEADER, KLOG_FORWARDED_BY_HEADER, KLOG_TRACE_ID_HEADER, KLogAppendRequest,
-    KLogAppendResponse, KLogDataRequestType, KLogMetaDeleteRequest, KLogMetaDeleteResponse,
-    KLogMetaPutRequest, KLogMetaPutResponse, KLogMetaQueryRequest, KLogMetaQueryResponse,
-    KLogQueryRequest, KLogQueryResponse, RaftRequest, RaftResponse,
+    KLOG_FORWARD_HOPS_HEADER, KLOG_FORWARDED_BY_HEADER, KLOG_TRACE_ID_HEADER,
+    KLogAppendBatchRequest, KLogAppendBatchResponse, KLogAppendRequest, KLogAppendResponse,
+    KLogDataRequestType, KLogLeaseRequest, KLogLeaseResponse, KLogMetaDeleteRequest,
+    KLogMetaDeleteResponse, KLogMetaPutRequest, KLogMetaPutResponse, KLogMetaQueryRequest,
+    KLogMetaQueryResponse, KLogQueryRequest, KLogQueryResponse, KLogTxnRequest, KLogTxnResponse,
+    KSnapshotResumeRequest, RaftRequest, RaftResponse,
 };
 use crate::error::{KLogErrorCode, KLogServiceError, parse_error_envelope_json};
 use crate::{KNode, KNodeId, KTypeConfig};
 use openraft::error::{
-    InstallSnapshotError, NetworkError, RPCError, RaftError, RemoteError, Timeout, Unreachable,
+    Fatal, InstallSnapshotError, NetworkError, RPCError, RaftError, RemoteError, ReplicationClosed,
+    StreamingError, Timeout, Unreachable,
 };
 use openraft::network::{RPCOption, RaftNetwork, RaftNetworkFactory};
 use openraft::raft::{
     AppendEntriesRequest, AppendEntriesResponse, InstallSnapshotRequest, InstallSnapshotResponse,
-    VoteRequest, VoteResponse,
+    SnapshotResponse, VoteRequest, VoteResponse,
 };
+use openraft::{OptionalSend, Snapshot, StorageError, StorageIOError, Vote};
+use std::future::Future;
+use std::io::SeekFrom;
 use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
 const DEFAULT_DATA_RPC_TIMEOUT: Duration = Duration::from_secs(3);
+const SNAPSHOT_CHUNK_MAX_RETRIES: u32 = 5;
+const SNAPSHOT_CHUNK_RETRY_BASE: Duration = Duration::from_millis(50);
+const SNAPSHOT_CHUNK_RETRY_MAX: Duration = Duration::from_secs(2);
 
 pub struct KNetworkFactory {
     local: KNodeId,
@@ -140,6 +150,81 @@ impl KDataClient {
         })
     }
 
+    pub async fn append_batch_to_node(
+        &self,
+        target: &KNode,
+        req: &KLogAppendBatchRequest,
+        forward_hops: u32,
+        forwarded_by: KNodeId,
+        trace_id: &str,
+    ) -> Result<KLogAppendBatchResponse, KLogServiceError> {
+        let path = KLogDataRequestType::AppendBatch.klog_path();
+        let endpoint_port = Self::inter_node_port(target);
+        let url = format!("http://{}:{}{}", target.addr, endpoint_port, path);
+        let response = self
+            .client
+            .post(&url)
+            .timeout(self.timeout)
+            .header(KLOG_FORWARD_HOPS_HEADER, forward_hops.to_string())
+            .header(KLOG_FORWARDED_BY_HEADER, forwarded_by.to_string())
+            .header(KLOG_TRACE_ID_HEADER, trace_id)
+            .json(req)
+            .send()
+            .await
+            .map_err(|e| {
+                let msg = format!(
+                    "forward data append batch send failed: target={}({}:{}), url={}, err={}",
+                    target.id, target.addr, endpoint_port, url, e
+                );
+                KLogServiceError::new(
+                    reqwest::StatusCode::BAD_GATEWAY.as_u16(),
+                    KLogErrorCode::Unavailable,
+                    msg,
+                    trace_id.to_string(),
+                )
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|e| format!(r#"{{"message":"<failed to read body: {}>"}}"#, e));
+            let fallback_msg = format!(
+                "forward data append batch failed: target={}({}:{}), url={}, status={}, body={}",
+                target.id, target.addr, endpoint_port, url, status, body
+            );
+            return Err(parse_error_envelope_json(&body)
+                .map(|e| KLogServiceError {
+                    http_status: status.as_u16(),
+                    error: e,
+                })
+                .unwrap_or_else(|| {
+                    KLogServiceError::from_http_status(
+                        status.as_u16(),
+                        fallback_msg,
+                        trace_id.to_string(),
+                    )
+                }));
+        }
+
+        response
+            .json::<KLogAppendBatchResponse>()
+            .await
+            .map_err(|e| {
+                let msg = format!(
+                    "forward data append batch decode failed: target={}({}:{}), url={}, err={}",
+                    target.id, target.addr, endpoint_port, url, e
+                );
+                KLogServiceError::new(
+                    reqwest::StatusCode::BAD_GATEWAY.as_u16(),
+                    KLogErrorCode::Unavailable,
+                    msg,
+                    trace_id.to_string(),
+                )
+            })
+    }
+
     pub async fn query_to_node(
         &self,
         target: &KNode,
@@ -284,6 +369,150 @@ impl KDataClient {
         })
     }
 
+    pub async fn lease_to_node(
+        &self,
+        target: &KNode,
+        req: &KLogLeaseRequest,
+        forward_hops: u32,
+        forwarded_by: KNodeId,
+        trace_id: &str,
+    ) -> Result<KLogLeaseResponse, KLogServiceError> {
+        let path = KLogDataRequestType::Lease.klog_path();
+        let endpoint_port = Self::inter_node_port(target);
+        let url = format!("http://{}:{}{}", target.addr, endpoint_port, path);
+        let response = self
+            .client
+            .post(&url)
+            .timeout(self.timeout)
+            .header(KLOG_FORWARD_HOPS_HEADER, forward_hops.to_string())
+            .header(KLOG_FORWARDED_BY_HEADER, forwarded_by.to_string())
+            .header(KLOG_TRACE_ID_HEADER, trace_id)
+            .json(req)
+            .send()
+            .await
+            .map_err(|e| {
+                let msg = format!(
+                    "forward lease send failed: target={}({}:{}), url={}, err={}",
+                    target.id, target.addr, endpoint_port, url, e
+                );
+                KLogServiceError::new(
+                    reqwest::StatusCode::BAD_GATEWAY.as_u16(),
+                    KLogErrorCode::Unavailable,
+                    msg,
+                    trace_id.to_string(),
+                )
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|e| format!(r#"{{"message":"<failed to read body: {}>"}}"#, e));
+            let fallback_msg = format!(
+                "forward lease failed: target={}({}:{}), url={}, status={}, body={}",
+                target.id, target.addr, endpoint_port, url, status, body
+            );
+            return Err(parse_error_envelope_json(&body)
+                .map(|e| KLogServiceError {
+                    http_status: status.as_u16(),
+                    error: e,
+                })
+                .unwrap_or_else(|| {
+                    KLogServiceError::from_http_status(
+                        status.as_u16(),
+                        fallback_msg,
+                        trace_id.to_string(),
+                    )
+                }));
+        }
+
+        response.json::<KLogLeaseResponse>().await.map_err(|e| {
+            let msg = format!(
+                "forward lease decode failed: target={}({}:{}), url={}, err={}",
+                target.id, target.addr, endpoint_port, url, e
+            );
+            KLogServiceError::new(
+                reqwest::StatusCode::BAD_GATEWAY.as_u16(),
+                KLogErrorCode::Unavailable,
+                msg,
+                trace_id.to_string(),
+            )
+        })
+    }
+
+    pub async fn txn_to_node(
+        &self,
+        target: &KNode,
+        req: &KLogTxnRequest,
+        forward_hops: u32,
+        forwarded_by: KNodeId,
+        trace_id: &str,
+    ) -> Result<KLogTxnResponse, KLogServiceError> {
+        let path = KLogDataRequestType::Txn.klog_path();
+        let endpoint_port = Self::inter_node_port(target);
+        let url = format!("http://{}:{}{}", target.addr, endpoint_port, path);
+        let response = self
+            .client
+            .post(&url)
+            .timeout(self.timeout)
+            .header(KLOG_FORWARD_HOPS_HEADER, forward_hops.to_string())
+            .header(KLOG_FORWARDED_BY_HEADER, forwarded_by.to_string())
+            .header(KLOG_TRACE_ID_HEADER, trace_id)
+            .json(req)
+            .send()
+            .await
+            .map_err(|e| {
+                let msg = format!(
+                    "forward txn send failed: target={}({}:{}), url={}, err={}",
+                    target.id, target.addr, endpoint_port, url, e
+                );
+                KLogServiceError::new(
+                    reqwest::StatusCode::BAD_GATEWAY.as_u16(),
+                    KLogErrorCode::Unavailable,
+                    msg,
+                    trace_id.to_string(),
+                )
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|e| format!(r#"{{"message":"<failed to read body: {}>"}}"#, e));
+            let fallback_msg = format!(
+                "forward txn failed: target={}({}:{}), url={}, status={}, body={}",
+                target.id, target.addr, endpoint_port, url, status, body
+            );
+            return Err(parse_error_envelope_json(&body)
+                .map(|e| KLogServiceError {
+                    http_status: status.as_u16(),
+                    error: e,
+                })
+                .unwrap_or_else(|| {
+                    KLogServiceError::from_http_status(
+                        status.as_u16(),
+                        fallback_msg,
+                        trace_id.to_string(),
+                    )
+                }));
+        }
+
+        response.json::<KLogTxnResponse>().await.map_err(|e| {
+            let msg = format!(
+                "forward txn decode failed: target={}({}:{}), url={}, err={}",
+                target.id, target.addr, endpoint_port, url, e
+            );
+            KLogServiceError::new(
+                reqwest::StatusCode::BAD_GATEWAY.as_u16(),
+                KLogErrorCode::Unavailable,
+                msg,
+                trace_id.to_string(),
+            )
+        })
+    }
+
     pub async fn delete_meta_to_node(
         &self,
         target: &KNode,
@@ -561,6 +790,36 @@ impl KNetworkClient {
         RPCError::Network(NetworkError::new(&io_err))
     }
 
+    /// Ask the target how many bytes of `snapshot_id` it already holds. Any failure, e.g. a
+    /// peer without resumable receive support, falls back to sending from offset 0.
+    async fn query_snapshot_resume_offset(&self, snapshot_id: &str, option: RPCOption) -> u64 {
+        let req = RaftRequest::SnapshotResume(KSnapshotResumeRequest {
+            snapshot_id: snapshot_id.to_string(),
+        });
+        match self
+            .request::<RaftError<KNodeId, InstallSnapshotError>>(req, option)
+            .await
+        {
+            Ok(RaftResponse::SnapshotResume(resp)) if resp.snapshot_id == snapshot_id => {
+                resp.offset
+            }
+            Ok(other) => {
+                warn!(
+                    "Unexpected snapshot-resume response from node {}: {:?}",
+                    self.target, other
+                );
+                0
+            }
+            Err(e) => {
+                warn!(
+                    "Snapshot-resume probe to node {} failed, sending from offset 0: {}",
+                    self.target, e
+                );
+                0
+            }
+        }
+    }
+
     fn get_request_url(&self, req: &RaftRequest) -> String {
         format!(
             "http://{}:{}/klog/{}",
@@ -617,6 +876,148 @@ impl RaftNetwork<KTypeConfig> for KNetworkClient {
         }
     }
 
+    /// Send a full snapshot in chunks, starting from the offset the target already persisted
+    /// and jumping to the offset it reports on a mismatch instead of restarting from zero.
+    async fn full_snapshot(
+        &mut self,
+        vote: Vote<KNodeId>,
+        mut snapshot: Snapshot<KTypeConfig>,
+        cancel: impl Future<Output = ReplicationClosed> + OptionalSend + 'static,
+        option: RPCOption,
+    ) -> Result<SnapshotResponse<KNodeId>, StreamingError<KTypeConfig, Fatal<KNodeId>>> {
+        let signature = snapshot.meta.signature();
+        let target = self.target;
+        let read_err = |e: std::io::Error| {
+            error!(
+                "Failed to read snapshot {} for node {}: {}",
+                signature.snapshot_id, target, e
+            );
+            StorageError::IO {
+                source: StorageIOError::read_snapshot(Some(signature.clone()), &e),
+            }
+        };
+
+        let end = snapshot
+            .snapshot
+            .seek(SeekFrom::End(0))
+            .await
+            .map_err(read_err)?;
+        let chunk_size = option.snapshot_chunk_size().unwrap_or(1024 * 1024).max(1);
+        let mut offset = self
+            .query_snapshot_resume_offset(&snapshot.meta.snapshot_id, option.clone())
+            .await
+            .min(end);
+        info!(
+            "Send snapshot to node {}: snapshot_id={}, bytes={}, resume_offset={}, chunk_size={}",
+            self.target, snapshot.meta.snapshot_id, end, offset, chunk_size
+        );
+
+        let mut cancel = std::pin::pin!(cancel);
+        let mut consecutive_failures = 0u32;
+        loop {
+            // Check for cancellation between chunks without pacing the transfer.
+            tokio::select! {
+                biased;
+                closed = &mut cancel => return Err(closed.into()),
+                _ = std::future::ready(()) => {}
+            }
+
+            snapshot
+                .snapshot
+                .seek(SeekFrom::Start(offset))
+                .await
+                .map_err(read_err)?;
+            let mut buf = Vec::with_capacity(chunk_size);
+            while buf.len() < chunk_size {
+                let n = snapshot
+                    .snapshot
+                    .read_buf(&mut buf)
+                    .await
+                    .map_err(read_err)?;
+                if n == 0 {
+                    break;
+                }
+            }
+            let n_read = buf.len() as u64;
+            let done = offset + n_read == end;
+            let req = InstallSnapshotRequest {
+                vote,
+                meta: snapshot.meta.clone(),
+                offset,
+                data: buf,
+                done,
+            };
+
+            let resp = match self.install_snapshot(req, option.clone()).await {
+                Ok(resp) => {
+                    consecutive_failures = 0;
+                    resp
+                }
+                Err(RPCError::RemoteError(RemoteError {
+                    source: RaftError::APIError(InstallSnapshotError::SnapshotMismatch(mismatch)),
+                    ..
+                })) => {
+                    warn!(
+                        "Snapshot chunk mismatch from node {}, resume at offset {}: {}",
+                        self.target, mismatch.expect.offset, mismatch
+                    );
+                    offset = mismatch.expect.offset.min(end);
+                    consecutive_failures = 0;
+                    continue;
+                }
+                Err(RPCError::RemoteError(RemoteError {
+                    target,
+                    target_node,
+                    source: RaftError::Fatal(fatal),
+                })) => {
+                    return Err(RemoteError {
+                        target,
+                        target_node,
+                        source: fatal,
+                    }
+                    .into());
+                }
+                Err(RPCError::PayloadTooLarge(payload)) => {
+                    let msg = format!("snapshot chunk rejected as too large: {}", payload);
+                    error!("{}", msg);
+                    return Err(NetworkError::new(&std::io::Error::other(msg)).into());
+                }
+                Err(e) => {
+                    consecutive_failures += 1;
+                    if consecutive_failures >= SNAPSHOT_CHUNK_MAX_RETRIES {
+                        error!(
+                            "Send snapshot chunk to node {} failed {} times, give up at offset {}: {}",
+                            self.target, consecutive_failures, offset, e
+                        );
+                        return Err(match e {
+                            RPCError::Timeout(e) => e.into(),
+                            RPCError::Unreachable(e) => e.into(),
+                            RPCError::Network(e) => e.into(),
+                            other => NetworkError::new(&other).into(),
+                        });
+                    }
+
+                    let delay = SNAPSHOT_CHUNK_RETRY_BASE
+                        .saturating_mul(1 << (consecutive_failures - 1))
+                        .min(SNAPSHOT_CHUNK_RETRY_MAX);
+                    warn!(
+                        "Send snapshot chunk to node {} failed, retry in {:?}: offset={}, err={}",
+                        self.target, delay, offset, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+            };
+
+            if resp.vote > vote || done {
+                // A higher vote means the follower moved on; the caller turns it into HigherVote.
+                return Ok(SnapshotResponse::new(resp.vote));
+            }
+
+            offset += n_read;
+        }
+    }
+
     /// Send a RequestVote RPC to the target.
     async fn vote(
         &mut self,
@@ -1,3 +1,7 @@
+use crate::lease::KLogLease;
+use crate::retention::{KLogRetentionPolicy, KLogRetentionReport, KLogRetentionStats};
+use crate::state_store::{KLogAttrFilter, KLogMetaWatchEvent};
+use crate::txn::{KLogTxnCompare, KLogTxnOpResult};
 use crate::{KLogEntry, KLogError, KLogLevel, KLogMetaEntry, KNode, KNodeId, KResult, KTypeConfig};
 use openraft::error::PayloadTooLarge;
 use openraft::error::{InstallSnapshotError, RaftError};
@@ -23,6 +27,7 @@ pub enum RaftRequestType {
     AppendEntries,
     InstallSnapshot,
     Vote,
+    SnapshotResume,
 }
 
 impl RaftRequestType {
@@ -31,6 +36,7 @@ impl RaftRequestType {
             RaftRequestType::AppendEntries => 1,
             RaftRequestType::InstallSnapshot => 2,
             RaftRequestType::Vote => 3,
+            RaftRequestType::SnapshotResume => 4,
         }
     }
 
@@ -39,6 +45,7 @@ impl RaftRequestType {
             1 => Some(RaftRequestType::AppendEntries),
             2 => Some(RaftRequestType::InstallSnapshot),
             3 => Some(RaftRequestType::Vote),
+            4 => Some(RaftRequestType::SnapshotResume),
             _ => None,
         }
     }
@@ -48,6 +55,7 @@ impl RaftRequestType {
             RaftRequestType::AppendEntries => "append-entries",
             RaftRequestType::InstallSnapshot => "install-snapshot",
             RaftRequestType::Vote => "vote",
+            RaftRequestType::SnapshotResume => "snapshot-resume",
         }
     }
 
@@ -62,6 +70,8 @@ pub enum KLogAdminRequestType {
     RemoveLearner,
     ChangeMembership,
     ClusterState,
+    Retention,
+    RetentionRun,
 }
 
 impl KLogAdminRequestType {
@@ -71,6 +81,8 @@ impl KLogAdminRequestType {
             KLogAdminRequestType::RemoveLearner => "remove-learner",
             KLogAdminRequestType::ChangeMembership => "change-membership",
             KLogAdminRequestType::ClusterState => "cluster-state",
+            KLogAdminRequestType::Retention => "retention",
+            KLogAdminRequestType::RetentionRun => "retention-run",
         }
     }
 
@@ -82,20 +94,26 @@ impl KLogAdminRequestType {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum KLogDataRequestType {
     Append,
+    AppendBatch,
     Query,
     MetaPut,
     MetaDelete,
     MetaQuery,
+    Lease,
+    Txn,
 }
 
 impl KLogDataRequestType {
     pub fn as_str(&self) -> &str {
         match self {
             KLogDataRequestType::Append => "append",
+            KLogDataRequestType::AppendBatch => "append-batch",
             KLogDataRequestType::Query => "query",
             KLogDataRequestType::MetaPut => "meta-put",
             KLogDataRequestType::MetaDelete => "meta-delete",
             KLogDataRequestType::MetaQuery => "meta-query",
+            KLogDataRequestType::Lease => "lease",
+            KLogDataRequestType::Txn => "txn",
         }
     }
 
@@ -123,6 +141,18 @@ pub struct KLogAppendResponse {
     pub id: u64,
 }
 
+/// Several appends committed by one raft proposal.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct KLogAppendBatchRequest {
+    pub entries: Vec<KLogAppendRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KLogAppendBatchResponse {
+    /// One id per entry, in request order; deduplicated entries report the earlier id.
+    pub ids: Vec<u64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct KLogQueryRequest {
     pub start_id: Option<u64>,
@@ -137,10 +167,56 @@ pub struct KLogQueryRequest {
     pub attr_key: Option<String>,
     #[serde(default)]
     pub attr_value: Option<String>,
+    /// Inclusive timestamp range in ms.
+    #[serde(default)]
+    pub start_time: Option<u64>,
+    #[serde(default)]
+    pub end_time: Option<u64>,
+    /// Extra attr predicates ANDed together: `key=value` or bare `key` separated by commas,
+    /// see `format_attr_filters`. Kept as a string so the request stays query-string friendly.
+    #[serde(default)]
+    pub attrs: Option<String>,
+    /// Case-sensitive substring of the message.
+    #[serde(default)]
+    pub message_contains: Option<String>,
+    /// Words that must all appear in the message, case-insensitive.
+    #[serde(default)]
+    pub message_terms: Option<String>,
     /// When true, require linearizable read on leader before serving query.
     pub strong_read: Option<bool>,
 }
 
+/// Encode attr predicates for `KLogQueryRequest::attrs`.
+pub fn format_attr_filters(filters: &[KLogAttrFilter]) -> String {
+    filters
+        .iter()
+        .map(|f| match f.value.as_deref() {
+            Some(value) => format!("{}={}", f.key, value),
+            None => f.key.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Decode `KLogQueryRequest::attrs`; keys and values are trimmed, `key=` means presence only.
+pub fn parse_attr_filters(raw: &str) -> Result<Vec<KLogAttrFilter>, String> {
+    let mut filters = Vec::new();
+    for item in raw.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+        let (key, value) = match item.split_once('=') {
+            Some((key, value)) => (key.trim(), Some(value.trim()).filter(|v| !v.is_empty())),
+            None => (item, None),
+        };
+        if key.is_empty() {
+            return Err(format!("attr filter has empty key: {}", item));
+        }
+        filters.push(KLogAttrFilter {
+            key: key.to_string(),
+            value: value.map(|v| v.to_string()),
+        });
+    }
+    Ok(filters)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct KLogQueryResponse {
     pub items: Vec<KLogEntry>,
@@ -152,6 +228,10 @@ pub struct KLogMetaPutRequest {
     pub value: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expected_revision: Option<u64>,
+    /// Attach the key to this lease; it is deleted when the lease expires or is revoked.
+    /// Putting a key without a lease detaches it from its previous lease.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease_id: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -172,6 +252,91 @@ pub struct KLogMetaDeleteResponse {
     pub prev_meta: Option<KLogMetaEntry>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KLogTxnRequestOp {
+    Put {
+        key: String,
+        value: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lease_id: Option<u64>,
+    },
+    Delete {
+        key: String,
+    },
+    Get {
+        key: String,
+    },
+}
+
+impl KLogTxnRequestOp {
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Put { key, .. } | Self::Delete { key } | Self::Get { key } => key.as_str(),
+        }
+    }
+}
+
+/// Multi-key meta transaction: if every `compare` holds, `then` is applied, otherwise `else`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct KLogTxnRequest {
+    #[serde(default)]
+    pub compare: Vec<KLogTxnCompare>,
+    #[serde(default, rename = "then")]
+    pub then_ops: Vec<KLogTxnRequestOp>,
+    #[serde(default, rename = "else")]
+    pub else_ops: Vec<KLogTxnRequestOp>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KLogTxnResponse {
+    /// Whether all compares held, i.e. which branch ran.
+    pub succeeded: bool,
+    /// One result per operation of the branch that ran.
+    pub results: Vec<KLogTxnOpResult>,
+}
+
+/// Lease write operations, all committed through raft on the leader.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum KLogLeaseRequest {
+    Grant {
+        ttl_ms: u64,
+    },
+    #[serde(rename = "keepalive")]
+    KeepAlive {
+        lease_id: u64,
+    },
+    Revoke {
+        lease_id: u64,
+    },
+}
+
+impl KLogLeaseRequest {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Grant { .. } => "grant",
+            Self::KeepAlive { .. } => "keepalive",
+            Self::Revoke { .. } => "revoke",
+        }
+    }
+
+    pub fn lease_id(&self) -> Option<u64> {
+        match self {
+            Self::Grant { .. } => None,
+            Self::KeepAlive { lease_id } | Self::Revoke { lease_id } => Some(*lease_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KLogLeaseResponse {
+    pub lease: KLogLease,
+    /// Keys removed together with a revoked lease.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted_keys: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct KLogMetaQueryRequest {
     pub key: Option<String>,
@@ -186,6 +351,55 @@ pub struct KLogMetaQueryResponse {
     pub items: Vec<KLogMetaEntry>,
 }
 
+/// Long-poll for meta changes under `prefix` that were applied after `after_revision`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct KLogMetaWatchRequest {
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Resume cursor from a previous response; `None` only waits for changes from now on.
+    #[serde(default)]
+    pub after_revision: Option<u64>,
+    pub limit: Option<usize>,
+    /// How long to wait for a matching change before returning an empty batch.
+    pub wait_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KLogMetaWatchResponse {
+    pub events: Vec<KLogMetaWatchEvent>,
+    /// Cursor to pass as `after_revision` on the next call.
+    pub revision: u64,
+    /// `after_revision` fell out of the retained history (or the node restarted); re-read
+    /// the prefix with a meta query and keep watching from `revision`.
+    #[serde(default)]
+    pub compacted: bool,
+}
+
+/// Long-poll for new log entries with `id > after_id` that match the filter.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct KLogLogWatchRequest {
+    /// Resume cursor from a previous response; `None` only waits for entries from now on.
+    #[serde(default)]
+    pub after_id: Option<u64>,
+    pub limit: Option<usize>,
+    pub wait_ms: Option<u64>,
+    #[serde(default)]
+    pub level: Option<KLogLevel>,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub attr_key: Option<String>,
+    #[serde(default)]
+    pub attr_value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KLogLogWatchResponse {
+    pub items: Vec<KLogEntry>,
+    /// Cursor to pass as `after_id` on the next call, also advanced past filtered entries.
+    pub last_id: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct KLogClusterStateResponse {
     pub node_id: KNodeId,
@@ -198,6 +412,46 @@ pub struct KLogClusterStateResponse {
     pub nodes: BTreeMap<KNodeId, KNode>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KLogRetentionStateResponse {
+    pub node_id: KNodeId,
+    pub policy: Option<KLogRetentionPolicy>,
+    pub policy_revision: Option<u64>,
+    pub stats: KLogRetentionStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct KLogRetentionUpdateRequest {
+    /// New policy; `None` clears the stored policy and disables retention.
+    #[serde(default)]
+    pub policy: Option<KLogRetentionPolicy>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KLogRetentionUpdateResponse {
+    pub policy: Option<KLogRetentionPolicy>,
+    pub policy_revision: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KLogRetentionRunResponse {
+    pub node_id: KNodeId,
+    pub report: KLogRetentionReport,
+}
+
+/// Ask a follower how many bytes of a snapshot it already persisted, so an interrupted
+/// transfer can continue from there instead of restarting at offset 0.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KSnapshotResumeRequest {
+    pub snapshot_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KSnapshotResumeResponse {
+    pub snapshot_id: String,
+    pub offset: u64,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 enum NetworkFrameKind {
@@ -364,6 +618,7 @@ pub enum RaftRequest {
     AppendEntries(AppendEntriesRequest<KTypeConfig>),
     InstallSnapshot(InstallSnapshotRequest<KTypeConfig>),
     Vote(VoteRequest<KNodeId>),
+    SnapshotResume(KSnapshotResumeRequest),
 }
 
 impl RaftRequest {
@@ -372,6 +627,7 @@ impl RaftRequest {
             RaftRequest::AppendEntries(_) => RaftRequestType::AppendEntries,
             RaftRequest::InstallSnapshot(_) => RaftRequestType::InstallSnapshot,
             RaftRequest::Vote(_) => RaftRequestType::Vote,
+            RaftRequest::SnapshotResume(_) => RaftRequestType::SnapshotResume,
         }
     }
 
@@ -384,6 +640,7 @@ impl RaftRequest {
             RaftRequest::AppendEntries(_) => RPCTypes::AppendEntries,
             RaftRequest::InstallSnapshot(_) => RPCTypes::InstallSnapshot,
             RaftRequest::Vote(_) => RPCTypes::Vote,
+            RaftRequest::SnapshotResume(_) => RPCTypes::InstallSnapshot,
         }
     }
 
@@ -402,6 +659,10 @@ impl RaftRequest {
                 error!("VoteRequest is too large to send");
                 None
             }
+            RaftRequest::SnapshotResume(_) => {
+                error!("SnapshotResumeRequest is too large to send");
+                None
+            }
         }
     }
 
@@ -434,6 +695,7 @@ pub enum RaftResponse {
     InstallSnapshotError(RaftError<KNodeId, InstallSnapshotError>),
     Vote(VoteResponse<KNodeId>),
     VoteError(RaftError<KNodeId>),
+    SnapshotResume(KSnapshotResumeResponse),
 }
 
 impl RaftResponse {
@@ -446,6 +708,7 @@ impl RaftResponse {
                 RaftRequestType::InstallSnapshot
             }
             RaftResponse::Vote(_) | RaftResponse::VoteError(_) => RaftRequestType::Vote,
+            RaftResponse::SnapshotResume(_) => RaftRequestType::SnapshotResume,
         }
     }
 
@@ -536,6 +799,33 @@ mod tests {
         assert!(msg.contains("rpc type mismatch"));
     }
 
+    #[test]
+    fn test_snapshot_resume_roundtrip_with_header() {
+        let req = RaftRequest::SnapshotResume(KSnapshotResumeRequest {
+            snapshot_id: "1-2-3".to_string(),
+        });
+        let bytes = req.serialize().expect("serialize request");
+        assert_eq!(bytes[11], RaftRequestType::SnapshotResume.to_code());
+        assert_eq!(
+            RaftRequestType::SnapshotResume.klog_path(),
+            "/klog/snapshot-resume"
+        );
+        match RaftRequest::deserialize(&bytes).expect("deserialize request") {
+            RaftRequest::SnapshotResume(r) => assert_eq!(r.snapshot_id, "1-2-3"),
+            other => panic!("unexpected request type: {:?}", other),
+        }
+
+        let resp = RaftResponse::SnapshotResume(KSnapshotResumeResponse {
+            snapshot_id: "1-2-3".to_string(),
+            offset: 4096,
+        });
+        let bytes = resp.serialize().expect("serialize response");
+        match RaftResponse::deserialize(&bytes).expect("deserialize response") {
+            RaftResponse::SnapshotResume(r) => assert_eq!(r.offset, 4096),
+            other => panic!("unexpected response type: {:?}", other),
+        }
+    }
+
     #[test]
     fn test_admin_request_paths() {
         assert_eq!(
@@ -554,6 +844,14 @@ mod tests {
             KLogAdminRequestType::ClusterState.klog_path(),
             "/klog/admin/cluster-state"
         );
+        assert_eq!(
+            KLogAdminRequestType::Retention.klog_path(),
+            "/klog/admin/retention"
+        );
+        assert_eq!(
+            KLogAdminRequestType::RetentionRun.klog_path(),
+            "/klog/admin/retention-run"
+        );
     }
 
     #[test]
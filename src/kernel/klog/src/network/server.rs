@@ -1,12 +1,19 @@
 use super::request::{
-    KLogAdminRequestType, KLogAppendRequest, KLogClusterStateResponse, KLogDataRequestType,
-    KLogMetaDeleteRequest, KLogMetaPutRequest, KLogMetaQueryRequest, KLogQueryRequest, RaftRequest,
-    RaftRequestType, RaftResponse,
+    KLogAdminRequestType, KLogAppendBatchRequest, KLogAppendRequest, KLogClusterStateResponse,
+    KLogDataRequestType, KLogLeaseRequest, KLogMetaDeleteRequest, KLogMetaPutRequest,
+    KLogMetaQueryRequest, KLogQueryRequest, KLogRetentionRunResponse, KLogRetentionStateResponse,
+    KLogRetentionUpdateRequest, KLogRetentionUpdateResponse, KLogTxnRequest,
+    KSnapshotResumeResponse, RaftRequest, RaftRequestType, RaftResponse,
 };
 use crate::error::{KLogErrorEnvelope, KLogServiceError, generate_trace_id};
+use crate::retention::{
+    KLOG_RETENTION_POLICY_META_KEY, KLogRetentionError, collect_retention_stats,
+    load_retention_policy, run_retention_once,
+};
 use crate::service::{KLogQueryService, KLogWriteService};
+use crate::state_machine::{KSnapshotChunkAck, SnapshotManagerRef};
 use crate::state_store::KLogStateStoreManagerRef;
-use crate::{KNode, KNodeId, KRaftRef};
+use crate::{KLogMetaEntry, KLogRequest, KLogResponse, KNode, KNodeId, KRaftRef, KTypeConfig};
 use axum::Json;
 use axum::Router;
 use axum::body::Bytes;
@@ -15,8 +22,9 @@ use axum::extract::{ConnectInfo, Query, State};
 use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
-use openraft::ChangeMembers;
-use openraft::error::{ClientWriteError, RaftError};
+use openraft::error::{ClientWriteError, Fatal, InstallSnapshotError, RaftError, SnapshotMismatch};
+use openraft::raft::{InstallSnapshotRequest, InstallSnapshotResponse};
+use openraft::{ChangeMembers, Snapshot, SnapshotSegmentId};
 use serde::Deserialize;
 use std::collections::{BTreeMap, BTreeSet};
 use std::future::Future;
@@ -66,6 +74,8 @@ struct KNetworkServerState {
     raft: KRaftRef,
     write_service: Option<KLogWriteService>,
     query_service: Option<KLogQueryService>,
+    state_store_manager: Option<KLogStateStoreManagerRef>,
+    snapshot_manager: Option<SnapshotManagerRef>,
     admin_local_only: bool,
     cluster_name: String,
     cluster_id: String,
@@ -77,6 +87,7 @@ pub struct KNetworkServer {
     admin_addr: String,
     raft: KRaftRef,
     state_store_manager: Option<KLogStateStoreManagerRef>,
+    snapshot_manager: Option<SnapshotManagerRef>,
     admin_local_only: bool,
     cluster_name: String,
     cluster_id: String,
@@ -90,6 +101,7 @@ impl KNetworkServer {
             admin_addr: addr,
             raft,
             state_store_manager: None,
+            snapshot_manager: None,
             admin_local_only: false,
             cluster_name: "klog".to_string(),
             cluster_id: "klog".to_string(),
@@ -104,6 +116,13 @@ impl KNetworkServer {
         self
     }
 
+    /// Persist incoming snapshot chunks through the snapshot manager so a transfer that is
+    /// interrupted can resume from the bytes already on disk.
+    pub fn with_snapshot_manager(mut self, snapshot_manager: SnapshotManagerRef) -> Self {
+        self.snapshot_manager = Some(snapshot_manager);
+        self
+    }
+
     pub fn with_inter_node_addr(mut self, inter_node_addr: String) -> Self {
         self.inter_node_addr = inter_node_addr;
         self
@@ -158,6 +177,8 @@ impl KNetworkServer {
             query_service: self.state_store_manager.clone().map(|state_store_manager| {
                 KLogQueryService::new("KNetworkServer", self.raft.clone(), state_store_manager)
             }),
+            state_store_manager: self.state_store_manager.clone(),
+            snapshot_manager: self.snapshot_manager.clone(),
             admin_local_only: self.admin_local_only,
             cluster_name: self.cluster_name.clone(),
             cluster_id: self.cluster_id.clone(),
@@ -191,14 +212,19 @@ impl KNetworkServer {
         let append_entries_path = RaftRequestType::AppendEntries.klog_path();
         let vote_path = RaftRequestType::Vote.klog_path();
         let data_append_path = KLogDataRequestType::Append.klog_path();
+        let data_append_batch_path = KLogDataRequestType::AppendBatch.klog_path();
         let data_query_path = KLogDataRequestType::Query.klog_path();
         let data_meta_put_path = KLogDataRequestType::MetaPut.klog_path();
         let data_meta_delete_path = KLogDataRequestType::MetaDelete.klog_path();
         let data_meta_query_path = KLogDataRequestType::MetaQuery.klog_path();
+        let data_lease_path = KLogDataRequestType::Lease.klog_path();
+        let data_txn_path = KLogDataRequestType::Txn.klog_path();
         let admin_add_learner_path = KLogAdminRequestType::AddLearner.klog_path();
         let admin_remove_learner_path = KLogAdminRequestType::RemoveLearner.klog_path();
         let admin_change_membership_path = KLogAdminRequestType::ChangeMembership.klog_path();
         let admin_cluster_state_path = KLogAdminRequestType::ClusterState.klog_path();
+        let admin_retention_path = KLogAdminRequestType::Retention.klog_path();
+        let admin_retention_run_path = KLogAdminRequestType::RetentionRun.klog_path();
 
         let raft_control_routes = Router::new()
             .route(
@@ -224,9 +250,22 @@ impl KNetworkServer {
                 &admin_cluster_state_path,
                 get(Self::handle_cluster_state_request),
             )
+            .route(
+                &admin_retention_path,
+                get(Self::handle_retention_state_request)
+                    .post(Self::handle_retention_update_request),
+            )
+            .route(
+                &admin_retention_run_path,
+                post(Self::handle_retention_run_request),
+            )
             .route_layer(admin_rpc_middleware);
         let inter_node_data_routes = Router::new()
             .route(&data_append_path, post(Self::handle_data_append_request))
+            .route(
+                &data_append_batch_path,
+                post(Self::handle_data_append_batch_request),
+            )
             .route(&data_query_path, get(Self::handle_data_query_request))
             .route(&data_meta_put_path, post(Self::handle_meta_put_request))
             .route(
@@ -234,6 +273,8 @@ impl KNetworkServer {
                 post(Self::handle_meta_delete_request),
             )
             .route(&data_meta_query_path, get(Self::handle_meta_query_request))
+            .route(&data_lease_path, post(Self::handle_lease_request))
+            .route(&data_txn_path, post(Self::handle_txn_request))
             .route_layer(inter_node_rpc_middleware);
 
         let snapshot_rpc_middleware = ServiceBuilder::new()
@@ -246,11 +287,16 @@ impl KNetworkServer {
             .layer(RequestBodyLimitLayer::new(SNAPSHOT_RPC_BODY_LIMIT_BYTES));
 
         let install_snapshot_path = RaftRequestType::InstallSnapshot.klog_path();
+        let snapshot_resume_path = RaftRequestType::SnapshotResume.klog_path();
         let snapshot_routes = Router::new()
             .route(
                 &install_snapshot_path,
                 post(Self::handle_install_snapshot_request),
             )
+            .route(
+                &snapshot_resume_path,
+                post(Self::handle_snapshot_resume_request),
+            )
             .route_layer(snapshot_rpc_middleware);
         let raft_app = Router::new()
             .merge(raft_control_routes)
@@ -267,7 +313,7 @@ impl KNetworkServer {
             .with_state(state);
 
         info!(
-            "KNetworkServer start: raft_addr={}, inter_node_addr={}, admin_addr={}, cluster_name={}, cluster_id={}, control_limit_bytes={}, snapshot_limit_bytes={}, admin_limit_bytes={}, control_concurrency={}, snapshot_concurrency={}, admin_concurrency={}, control_timeout_ms={}, snapshot_timeout_ms={}, admin_timeout_ms={}, admin_local_only={}, data_append_path={}, data_query_path={}, data_meta_put_path={}, data_meta_delete_path={}, data_meta_query_path={}, admin_add_learner_path={}, admin_remove_learner_path={}, admin_change_membership_path={}, admin_cluster_state_path={}",
+            "KNetworkServer start: raft_addr={}, inter_node_addr={}, admin_addr={}, cluster_name={}, cluster_id={}, control_limit_bytes={}, snapshot_limit_bytes={}, admin_limit_bytes={}, control_concurrency={}, snapshot_concurrency={}, admin_concurrency={}, control_timeout_ms={}, snapshot_timeout_ms={}, admin_timeout_ms={}, admin_local_only={}, resumable_snapshot={}, data_append_path={}, data_append_batch_path={}, data_query_path={}, data_meta_put_path={}, data_meta_delete_path={}, data_meta_query_path={}, data_lease_path={}, data_txn_path={}, admin_add_learner_path={}, admin_remove_learner_path={}, admin_change_membership_path={}, admin_cluster_state_path={}, admin_retention_path={}, admin_retention_run_path={}",
             self.raft_addr,
             self.inter_node_addr,
             self.admin_addr,
@@ -283,15 +329,21 @@ impl KNetworkServer {
             SNAPSHOT_RPC_TIMEOUT_MS,
             ADMIN_RPC_TIMEOUT_MS,
             self.admin_local_only,
+            self.snapshot_manager.is_some(),
             data_append_path,
+            data_append_batch_path,
             data_query_path,
             data_meta_put_path,
             data_meta_delete_path,
             data_meta_query_path,
+            data_lease_path,
+            data_txn_path,
             admin_add_learner_path,
             admin_remove_learner_path,
             admin_change_membership_path,
-            admin_cluster_state_path
+            admin_cluster_state_path,
+            admin_retention_path,
+            admin_retention_run_path
         );
 
         let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
@@ -508,6 +560,23 @@ impl KNetworkServer {
             body.len()
         );
 
+        if let Some(snapshot_manager) = state.snapshot_manager.as_ref() {
+            let ret = Self::receive_snapshot_chunk(&state.raft, snapshot_manager, req).await;
+            return match ret {
+                Ok(resp) => Self::encode_response(
+                    RaftRequestType::InstallSnapshot,
+                    RaftResponse::InstallSnapshot(resp),
+                ),
+                Err(e) => {
+                    error!("KNetworkServer install-snapshot receive failed: {}", e);
+                    Self::encode_response(
+                        RaftRequestType::InstallSnapshot,
+                        RaftResponse::InstallSnapshotError(e),
+                    )
+                }
+            };
+        }
+
         match state.raft.install_snapshot(req).await {
             Ok(resp) => Self::encode_response(
                 RaftRequestType::InstallSnapshot,
@@ -523,6 +592,137 @@ impl KNetworkServer {
         }
     }
 
+    // Chunks are appended to a per-snapshot receive file instead of openraft's in-memory
+    // streaming state, so a follower restart or dropped connection keeps what was received.
+    async fn receive_snapshot_chunk(
+        raft: &KRaftRef,
+        snapshot_manager: &SnapshotManagerRef,
+        req: InstallSnapshotRequest<KTypeConfig>,
+    ) -> Result<InstallSnapshotResponse<KNodeId>, RaftError<KNodeId, InstallSnapshotError>> {
+        let my_vote = raft.with_raft_state(|st| *st.vote_ref()).await?;
+        if req.vote < my_vote {
+            info!(
+                "KNetworkServer install-snapshot rejected by local vote: snapshot_id={}, req_vote={}, my_vote={}",
+                req.meta.snapshot_id, req.vote, my_vote
+            );
+            return Ok(InstallSnapshotResponse { vote: my_vote });
+        }
+
+        let mismatch = |expected_offset: u64| {
+            RaftError::APIError(InstallSnapshotError::SnapshotMismatch(SnapshotMismatch {
+                expect: SnapshotSegmentId {
+                    id: req.meta.snapshot_id.clone(),
+                    offset: expected_offset,
+                },
+                got: SnapshotSegmentId {
+                    id: req.meta.snapshot_id.clone(),
+                    offset: req.offset,
+                },
+            }))
+        };
+
+        let ack = snapshot_manager
+            .receive_snapshot_chunk(&req.meta, req.offset, &req.data)
+            .await
+            .map_err(|e| RaftError::Fatal(Fatal::StorageError(e)))?;
+        match ack {
+            KSnapshotChunkAck::Mismatch { expected_offset } => {
+                return Err(mismatch(expected_offset));
+            }
+            KSnapshotChunkAck::Accepted { received } => {
+                if !req.done {
+                    return Ok(InstallSnapshotResponse { vote: my_vote });
+                }
+                info!(
+                    "KNetworkServer install-snapshot fully received: snapshot_id={}, bytes={}",
+                    req.meta.snapshot_id, received
+                );
+            }
+        }
+
+        let file = match snapshot_manager.open_received_snapshot(&req.meta).await {
+            Ok(file) => file,
+            Err(e) => {
+                // The receive file was discarded, ask the leader to send it again from scratch.
+                warn!(
+                    "KNetworkServer install-snapshot received file rejected: snapshot_id={}, err={}",
+                    req.meta.snapshot_id, e
+                );
+                return Err(mismatch(0));
+            }
+        };
+
+        // Run the install detached so a request timeout cannot abort it half way through
+        // restoring the state store.
+        let raft = raft.clone();
+        let snapshot_manager = snapshot_manager.clone();
+        let vote = req.vote;
+        let meta = req.meta;
+        let task = tokio::spawn(async move {
+            let snapshot_id = meta.snapshot_id.clone();
+            let snapshot = Snapshot {
+                meta,
+                snapshot: Box::new(file),
+            };
+            let ret = raft.install_full_snapshot(vote, snapshot).await;
+            if ret.is_ok() {
+                snapshot_manager
+                    .finish_received_snapshot(&snapshot_id)
+                    .await;
+            }
+            ret
+        });
+        let resp = task.await.map_err(|e| {
+            let msg = format!("install snapshot task join failed: {}", e);
+            error!("{}", msg);
+            RaftError::Fatal(Fatal::Panicked)
+        })??;
+
+        Ok(resp.into())
+    }
+
+    async fn handle_snapshot_resume_request(
+        State(state): State<KNetworkServerState>,
+        body: Bytes,
+    ) -> Response {
+        let req = match Self::decode_request(RaftRequestType::SnapshotResume, &body) {
+            Ok(req) => req,
+            Err(resp) => return resp,
+        };
+        let req = match req {
+            RaftRequest::SnapshotResume(req) => req,
+            other => {
+                let msg = format!(
+                    "KNetworkServer snapshot-resume type check failed: got={}",
+                    other.request_type().as_str()
+                );
+                error!("{}", msg);
+                return Self::error_response(StatusCode::BAD_REQUEST, msg);
+            }
+        };
+
+        let offset = match state.snapshot_manager.as_ref() {
+            Some(snapshot_manager) => {
+                snapshot_manager
+                    .received_snapshot_len(&req.snapshot_id)
+                    .await
+            }
+            None => 0,
+        };
+        info!(
+            "KNetworkServer snapshot-resume request: snapshot_id={}, offset={}",
+            req.snapshot_id, offset
+        );
+
+        Self::encode_response(
+            RaftRequestType::SnapshotResume,
+            RaftResponse::SnapshotResume(KSnapshotResumeResponse {
+                snapshot_id: req.snapshot_id,
+                offset,
+            }),
+        )
+    }
+
     async fn handle_vote_request(
         State(state): State<KNetworkServerState>,
         body: Bytes,
@@ -577,6 +777,25 @@ impl KNetworkServer {
         }
     }
 
+    async fn handle_data_append_batch_request(
+        State(state): State<KNetworkServerState>,
+        headers: HeaderMap,
+        Json(req): Json<KLogAppendBatchRequest>,
+    ) -> Response {
+        let Some(write_service) = state.write_service.as_ref() else {
+            let msg =
+                "KNetworkServer data append batch rejected: state store manager is not configured"
+                    .to_string();
+            error!("{}", msg);
+            return Self::error_response(StatusCode::INTERNAL_SERVER_ERROR, msg);
+        };
+
+        match write_service.append_batch(&headers, req).await {
+            Ok(resp) => (StatusCode::OK, Json(resp)).into_response(),
+            Err(err) => Self::service_error_response(err),
+        }
+    }
+
     async fn handle_data_query_request(
         State(state): State<KNetworkServerState>,
         headers: HeaderMap,
@@ -631,6 +850,42 @@ impl KNetworkServer {
         }
     }
 
+    async fn handle_lease_request(
+        State(state): State<KNetworkServerState>,
+        headers: HeaderMap,
+        Json(req): Json<KLogLeaseRequest>,
+    ) -> Response {
+        let Some(write_service) = state.write_service.as_ref() else {
+            let msg =
+                "KNetworkServer lease rejected: state store manager is not configured".to_string();
+            error!("{}", msg);
+            return Self::error_response(StatusCode::INTERNAL_SERVER_ERROR, msg);
+        };
+
+        match write_service.lease(&headers, req).await {
+            Ok(resp) => (StatusCode::OK, Json(resp)).into_response(),
+            Err(err) => Self::service_error_response(err),
+        }
+    }
+
+    async fn handle_txn_request(
+        State(state): State<KNetworkServerState>,
+        headers: HeaderMap,
+        Json(req): Json<KLogTxnRequest>,
+    ) -> Response {
+        let Some(write_service) = state.write_service.as_ref() else {
+            let msg =
+                "KNetworkServer txn rejected: state store manager is not configured".to_string();
+            error!("{}", msg);
+            return Self::error_response(StatusCode::INTERNAL_SERVER_ERROR, msg);
+        };
+
+        match write_service.txn(&headers, req).await {
+            Ok(resp) => (StatusCode::OK, Json(resp)).into_response(),
+            Err(err) => Self::service_error_response(err),
+        }
+    }
+
     async fn handle_meta_query_request(
         State(state): State<KNetworkServerState>,
         headers: HeaderMap,
@@ -825,6 +1080,193 @@ impl KNetworkServer {
         (StatusCode::OK, axum::Json(body)).into_response()
     }
 
+    async fn handle_retention_state_request(
+        State(state): State<KNetworkServerState>,
+        ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    ) -> Response {
+        if let Some(resp) =
+            Self::reject_non_loopback_admin_access(state.admin_local_only, peer, "retention")
+        {
+            return resp;
+        }
+        let state_store_manager = match Self::require_state_store_manager(&state, "retention") {
+            Ok(v) => v,
+            Err(resp) => return resp,
+        };
+
+        let node_id = state.raft.metrics().borrow().id;
+        let (policy, policy_revision) = match load_retention_policy(&state_store_manager).await {
+            Ok(Some((policy, revision))) => (Some(policy), Some(revision)),
+            Ok(None) => (None, None),
+            Err(err) => {
+                let msg = format!("KNetworkServer admin retention load policy failed: {}", err);
+                error!("{}", msg);
+                return Self::error_response(StatusCode::INTERNAL_SERVER_ERROR, msg);
+            }
+        };
+        let stats = match collect_retention_stats(&state_store_manager).await {
+            Ok(stats) => stats,
+            Err(err) => {
+                let msg = format!("KNetworkServer admin retention stats failed: {}", err);
+                error!("{}", msg);
+                return Self::error_response(StatusCode::INTERNAL_SERVER_ERROR, msg);
+            }
+        };
+
+        info!(
+            "KNetworkServer admin retention request: node_id={}, policy_revision={:?}, entries={}, estimated_bytes={}, first_id={:?}, last_id={:?}",
+            node_id,
+            policy_revision,
+            stats.entries,
+            stats.estimated_bytes,
+            stats.first_id,
+            stats.last_id
+        );
+
+        let body = KLogRetentionStateResponse {
+            node_id,
+            policy,
+            policy_revision,
+            stats,
+        };
+        (StatusCode::OK, axum::Json(body)).into_response()
+    }
+
+    async fn handle_retention_update_request(
+        State(state): State<KNetworkServerState>,
+        ConnectInfo(peer): ConnectInfo<SocketAddr>,
+        Json(req): Json<KLogRetentionUpdateRequest>,
+    ) -> Response {
+        if let Some(resp) =
+            Self::reject_non_loopback_admin_access(state.admin_local_only, peer, "retention")
+        {
+            return resp;
+        }
+
+        let node_id = state.raft.metrics().borrow().id;
+        let raft_req = match &req.policy {
+            Some(policy) => {
+                if let Err(err) = policy.validate() {
+                    let msg = format!("KNetworkServer admin retention invalid policy: {}", err);
+                    error!("{}", msg);
+                    return Self::error_response(StatusCode::BAD_REQUEST, msg);
+                }
+                let value = match policy.to_meta_value() {
+                    Ok(v) => v,
+                    Err(err) => {
+                        let msg = format!("KNetworkServer admin retention encode failed: {}", err);
+                        error!("{}", msg);
+                        return Self::error_response(StatusCode::INTERNAL_SERVER_ERROR, msg);
+                    }
+                };
+                KLogRequest::PutMeta {
+                    item: KLogMetaEntry {
+                        key: KLOG_RETENTION_POLICY_META_KEY.to_string(),
+                        value,
+                        updated_at: now_millis(),
+                        updated_by: node_id,
+                        revision: 0,
+                        lease_id: None,
+                    },
+                    expected_revision: None,
+                }
+            }
+            None => KLogRequest::DeleteMeta {
+                key: KLOG_RETENTION_POLICY_META_KEY.to_string(),
+            },
+        };
+
+        info!(
+            "KNetworkServer admin retention update request: node_id={}, policy={:?}",
+            node_id, req.policy
+        );
+
+        match state.raft.client_write(raft_req).await {
+            Ok(resp) => {
+                let policy_revision = match resp.data {
+                    KLogResponse::MetaPutOk { revision, .. } => Some(revision),
+                    KLogResponse::MetaDeleteOk { .. } => None,
+                    other => {
+                        let msg = format!(
+                            "KNetworkServer admin retention update unexpected response: {:?}",
+                            other
+                        );
+                        error!("{}", msg);
+                        return Self::error_response(StatusCode::INTERNAL_SERVER_ERROR, msg);
+                    }
+                };
+                info!(
+                    "KNetworkServer admin retention update succeeded: node_id={}, log_id={}, policy_revision={:?}",
+                    node_id, resp.log_id, policy_revision
+                );
+                let body = KLogRetentionUpdateResponse {
+                    policy: req.policy,
+                    policy_revision,
+                };
+                (StatusCode::OK, axum::Json(body)).into_response()
+            }
+            Err(err) => Self::raft_client_write_error_response("retention", err),
+        }
+    }
+
+    async fn handle_retention_run_request(
+        State(state): State<KNetworkServerState>,
+        ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    ) -> Response {
+        if let Some(resp) =
+            Self::reject_non_loopback_admin_access(state.admin_local_only, peer, "retention-run")
+        {
+            return resp;
+        }
+        let state_store_manager = match Self::require_state_store_manager(&state, "retention-run") {
+            Ok(v) => v,
+            Err(resp) => return resp,
+        };
+
+        let node_id = state.raft.metrics().borrow().id;
+        info!(
+            "KNetworkServer admin retention-run request: node_id={}",
+            node_id
+        );
+        match run_retention_once(&state.raft, &state_store_manager).await {
+            Ok(report) => {
+                info!(
+                    "KNetworkServer admin retention-run succeeded: node_id={}, plans={:?}, removed={}",
+                    node_id, report.plans, report.removed
+                );
+                let body = KLogRetentionRunResponse { node_id, report };
+                (StatusCode::OK, axum::Json(body)).into_response()
+            }
+            Err(KLogRetentionError::NotLeader { leader_id }) => {
+                let msg = format!(
+                    "KNetworkServer admin retention-run rejected on non-leader: leader_id={:?}",
+                    leader_id
+                );
+                warn!("{}", msg);
+                Self::error_response(StatusCode::CONFLICT, msg)
+            }
+            Err(err) => {
+                let msg = format!("KNetworkServer admin retention-run failed: {}", err);
+                error!("{}", msg);
+                Self::error_response(StatusCode::INTERNAL_SERVER_ERROR, msg)
+            }
+        }
+    }
+
+    fn require_state_store_manager(
+        state: &KNetworkServerState,
+        action: &str,
+    ) -> Result<KLogStateStoreManagerRef, Response> {
+        state.state_store_manager.clone().ok_or_else(|| {
+            let msg = format!(
+                "KNetworkServer admin {} unavailable: state store manager not configured",
+                action
+            );
+            error!("{}", msg);
+            Self::error_response(StatusCode::SERVICE_UNAVAILABLE, msg)
+        })
+    }
+
     fn decode_request(expected: RaftRequestType, body: &[u8]) -> Result<RaftRequest, Response> {
         info!(
             "KNetworkServer decode request: rpc={}, body_bytes={}",
@@ -932,6 +1374,13 @@ impl KNetworkServer {
     }
 }
 
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 fn parse_voter_ids_csv(raw: &str) -> Result<Vec<KNodeId>, String> {
     let mut ids = BTreeSet::new();
     for token in raw.split(',') {
@@ -0,0 +1,453 @@
+use crate::state_store::{KLogQuery, KLogQueryOrder, KLogStateStoreManagerRef};
+use crate::{KLogEntry, KLogError, KLogRequest, KLogResponse, KNodeId, KRaftRef, KResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Meta keys under this prefix are owned by klog itself and rejected on the data APIs.
+pub const KLOG_RESERVED_META_PREFIX: &str = "__klog/";
+/// Meta key holding the JSON encoded `KLogRetentionPolicy`.
+/// Keeping it in replicated meta means the policy follows snapshots and leader changes.
+pub const KLOG_RETENTION_POLICY_META_KEY: &str = "__klog/retention/policy";
+pub const RETENTION_DEFAULT_CHECK_INTERVAL_MS: u64 = 60_000;
+pub const RETENTION_MAX_SOURCE_RULES: usize = 256;
+const RETENTION_SCAN_PAGE_SIZE: usize = 1_024;
+// Rough per-entry overhead on top of variable fields (id/timestamp/node_id/level/encoding).
+const RETENTION_ENTRY_FIXED_BYTES: u64 = 48;
+
+pub fn is_reserved_meta_key(key: &str) -> bool {
+    key.trim().starts_with(KLOG_RESERVED_META_PREFIX)
+}
+
+/// One retention bound set; every unset limit is unbounded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KLogRetentionRule {
+    /// Drop entries whose timestamp is older than `now - max_age_ms`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_ms: Option<u64>,
+
+    /// Keep at most this many newest entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_entries: Option<u64>,
+
+    /// Keep at most this many bytes of newest entries (estimated payload size).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+}
+
+impl KLogRetentionRule {
+    pub fn is_unbounded(&self) -> bool {
+        self.max_age_ms.is_none() && self.max_entries.is_none() && self.max_bytes.is_none()
+    }
+
+    fn validate(&self, scope: &str) -> Result<(), String> {
+        for (name, value) in [
+            ("max_age_ms", self.max_age_ms),
+            ("max_entries", self.max_entries),
+            ("max_bytes", self.max_bytes),
+        ] {
+            if value == Some(0) {
+                return Err(format!(
+                    "invalid retention {}.{}=0: must be greater than 0 or unset",
+                    scope, name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Cluster-wide retention policy.
+/// `global` applies to all entries; `sources` add tighter bounds for single sources,
+/// both are enforced so a source rule can only shorten what `global` keeps.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KLogRetentionPolicy {
+    #[serde(default)]
+    pub global: KLogRetentionRule,
+
+    #[serde(default)]
+    pub sources: BTreeMap<String, KLogRetentionRule>,
+}
+
+impl KLogRetentionPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        self.global.validate("global")?;
+        if self.sources.len() > RETENTION_MAX_SOURCE_RULES {
+            return Err(format!(
+                "too many retention source rules: count={}, max={}",
+                self.sources.len(),
+                RETENTION_MAX_SOURCE_RULES
+            ));
+        }
+        for (source, rule) in &self.sources {
+            if source.trim().is_empty() || source.trim() != source {
+                return Err(format!(
+                    "invalid retention source '{}': must be non-empty without surrounding spaces",
+                    source
+                ));
+            }
+            rule.validate(&format!("sources.{}", source))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.global.is_unbounded() && self.sources.values().all(|r| r.is_unbounded())
+    }
+
+    pub fn to_meta_value(&self) -> KResult<String> {
+        serde_json::to_string(self).map_err(|e| {
+            let msg = format!("Failed to encode retention policy: {}", e);
+            error!("{}", msg);
+            KLogError::InvalidFormat(msg)
+        })
+    }
+
+    pub fn from_meta_value(value: &str) -> KResult<Self> {
+        serde_json::from_str(value).map_err(|e| {
+            let msg = format!("Failed to decode retention policy: {}", e);
+            error!("{}", msg);
+            KLogError::InvalidFormat(msg)
+        })
+    }
+}
+
+/// A single replicated truncation decided by the leader.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KLogTruncatePlan {
+    pub before_id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KLogRetentionStats {
+    pub entries: u64,
+    pub estimated_bytes: u64,
+    pub first_id: Option<u64>,
+    pub last_id: Option<u64>,
+    pub oldest_timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KLogRetentionReport {
+    pub plans: Vec<KLogTruncatePlan>,
+    pub removed: u64,
+}
+
+#[derive(Debug)]
+pub enum KLogRetentionError {
+    NotLeader { leader_id: Option<KNodeId> },
+    Store(KLogError),
+    Raft(String),
+}
+
+impl std::fmt::Display for KLogRetentionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotLeader { leader_id } => {
+                write!(
+                    f,
+                    "retention must run on leader, current_leader={:?}",
+                    leader_id
+                )
+            }
+            Self::Store(err) => write!(f, "retention store error: {}", err),
+            Self::Raft(err) => write!(f, "retention raft error: {}", err),
+        }
+    }
+}
+
+impl From<KLogError> for KLogRetentionError {
+    fn from(err: KLogError) -> Self {
+        Self::Store(err)
+    }
+}
+
+pub fn estimate_entry_bytes(entry: &KLogEntry) -> u64 {
+    let attrs = entry
+        .attrs
+        .iter()
+        .map(|(k, v)| (k.len() + v.len()) as u64)
+        .sum::<u64>();
+    RETENTION_ENTRY_FIXED_BYTES
+        + entry.message.len() as u64
+        + entry.source.as_ref().map(|v| v.len() as u64).unwrap_or(0)
+        + entry
+            .request_id
+            .as_ref()
+            .map(|v| v.len() as u64)
+            .unwrap_or(0)
+        + attrs
+}
+
+pub async fn load_retention_policy(
+    state_store_manager: &KLogStateStoreManagerRef,
+) -> KResult<Option<(KLogRetentionPolicy, u64)>> {
+    let Some(item) = state_store_manager
+        .get_meta_entry(KLOG_RETENTION_POLICY_META_KEY)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let policy = KLogRetentionPolicy::from_meta_value(&item.value)?;
+    Ok(Some((policy, item.revision)))
+}
+
+/// Compute truncations for `policy` against the local state store.
+/// Entries are walked newest-first by id and the cut is placed at the first entry that
+/// breaks any bound, so ids (not timestamps) define what "older" means.
+pub async fn plan_retention(
+    state_store_manager: &KLogStateStoreManagerRef,
+    policy: &KLogRetentionPolicy,
+    now_ms: u64,
+) -> KResult<Vec<KLogTruncatePlan>> {
+    let mut plans = Vec::new();
+    if let Some(before_id) =
+        plan_rule_cut(state_store_manager, &policy.global, None, now_ms).await?
+    {
+        plans.push(KLogTruncatePlan {
+            before_id,
+            source: None,
+        });
+    }
+    for (source, rule) in &policy.sources {
+        if let Some(before_id) =
+            plan_rule_cut(state_store_manager, rule, Some(source.as_str()), now_ms).await?
+        {
+            plans.push(KLogTruncatePlan {
+                before_id,
+                source: Some(source.clone()),
+            });
+        }
+    }
+    Ok(plans)
+}
+
+async fn plan_rule_cut(
+    state_store_manager: &KLogStateStoreManagerRef,
+    rule: &KLogRetentionRule,
+    source: Option<&str>,
+    now_ms: u64,
+) -> KResult<Option<u64>> {
+    if rule.is_unbounded() {
+        return Ok(None);
+    }
+
+    let age_cutoff = rule.max_age_ms.map(|age| now_ms.saturating_sub(age));
+    let mut kept_entries = 0u64;
+    let mut kept_bytes = 0u64;
+    let mut end_id = None;
+    loop {
+        let page = state_store_manager
+            .query_entries(KLogQuery {
+                end_id,
+                limit: RETENTION_SCAN_PAGE_SIZE,
+                order: KLogQueryOrder::Desc,
+                source: source.map(|v| v.to_string()),
+                ..Default::default()
+            })
+            .await?;
+
+        for entry in &page {
+            let entry_bytes = estimate_entry_bytes(entry);
+            let expired = age_cutoff.is_some_and(|cutoff| entry.timestamp < cutoff);
+            let over_entries = rule.max_entries.is_some_and(|max| kept_entries >= max);
+            let over_bytes = rule
+                .max_bytes
+                .is_some_and(|max| kept_bytes.saturating_add(entry_bytes) > max);
+            if expired || over_entries || over_bytes {
+                debug!(
+                    "retention cut found: source={:?}, cut_id={}, kept_entries={}, kept_bytes={}, expired={}, over_entries={}, over_bytes={}",
+                    source, entry.id, kept_entries, kept_bytes, expired, over_entries, over_bytes
+                );
+                return Ok(Some(entry.id.saturating_add(1)));
+            }
+            kept_entries += 1;
+            kept_bytes = kept_bytes.saturating_add(entry_bytes);
+        }
+
+        let Some(last) = page.last() else {
+            return Ok(None);
+        };
+        if page.len() < RETENTION_SCAN_PAGE_SIZE || last.id <= 1 {
+            return Ok(None);
+        }
+        end_id = Some(last.id - 1);
+    }
+}
+
+pub async fn collect_retention_stats(
+    state_store_manager: &KLogStateStoreManagerRef,
+) -> KResult<KLogRetentionStats> {
+    let mut stats = KLogRetentionStats::default();
+    let mut start_id = None;
+    loop {
+        let page = state_store_manager
+            .query_entries(KLogQuery {
+                start_id,
+                limit: RETENTION_SCAN_PAGE_SIZE,
+                order: KLogQueryOrder::Asc,
+                ..Default::default()
+            })
+            .await?;
+
+        for entry in &page {
+            stats.entries += 1;
+            stats.estimated_bytes = stats
+                .estimated_bytes
+                .saturating_add(estimate_entry_bytes(entry));
+            stats.first_id.get_or_insert(entry.id);
+            stats.last_id = Some(entry.id);
+            stats.oldest_timestamp = Some(
+                stats
+                    .oldest_timestamp
+                    .map(|ts| ts.min(entry.timestamp))
+                    .unwrap_or(entry.timestamp),
+            );
+        }
+
+        let Some(last) = page.last() else {
+            return Ok(stats);
+        };
+        if page.len() < RETENTION_SCAN_PAGE_SIZE {
+            return Ok(stats);
+        }
+        start_id = Some(last.id.saturating_add(1));
+    }
+}
+
+/// Run one retention pass on the leader: plan against local state, then replicate each
+/// cut through raft so followers apply exactly the same truncation.
+pub async fn run_retention_once(
+    raft: &KRaftRef,
+    state_store_manager: &KLogStateStoreManagerRef,
+) -> Result<KLogRetentionReport, KLogRetentionError> {
+    let (local_node_id, current_leader) = {
+        let metrics = raft.metrics();
+        let metrics = metrics.borrow();
+        (metrics.id, metrics.current_leader)
+    };
+    if current_leader != Some(local_node_id) {
+        return Err(KLogRetentionError::NotLeader {
+            leader_id: current_leader,
+        });
+    }
+
+    let Some((policy, revision)) = load_retention_policy(state_store_manager).await? else {
+        return Ok(KLogRetentionReport::default());
+    };
+    if policy.is_unbounded() {
+        return Ok(KLogRetentionReport::default());
+    }
+
+    let plans = plan_retention(state_store_manager, &policy, now_millis()).await?;
+    let mut report = KLogRetentionReport {
+        plans: plans.clone(),
+        removed: 0,
+    };
+    for plan in plans {
+        debug!(
+            "retention submit truncate: node_id={}, policy_revision={}, before_id={}, source={:?}",
+            local_node_id, revision, plan.before_id, plan.source
+        );
+        let resp = raft
+            .client_write(KLogRequest::TruncateBefore {
+                before_id: plan.before_id,
+                source: plan.source.clone(),
+            })
+            .await
+            .map_err(|e| {
+                if let Some(forward) = e.forward_to_leader::<crate::KNode>() {
+                    return KLogRetentionError::NotLeader {
+                        leader_id: forward.leader_id,
+                    };
+                }
+                KLogRetentionError::Raft(e.to_string())
+            })?;
+        match resp.data {
+            KLogResponse::TruncateOk { removed, .. } => {
+                report.removed += removed;
+            }
+            KLogResponse::Err(err) => return Err(KLogRetentionError::Raft(err)),
+            other => {
+                return Err(KLogRetentionError::Raft(format!(
+                    "unexpected truncate response: {:?}",
+                    other
+                )));
+            }
+        }
+    }
+
+    if report.removed > 0 {
+        info!(
+            "retention pass done: node_id={}, policy_revision={}, plans={:?}, removed={}",
+            local_node_id, revision, report.plans, report.removed
+        );
+    }
+    Ok(report)
+}
+
+/// Background task that periodically applies the stored retention policy.
+/// Every node may run it; only the current leader submits truncations.
+pub struct KLogRetentionWorker {
+    raft: KRaftRef,
+    state_store_manager: KLogStateStoreManagerRef,
+    check_interval: Duration,
+}
+
+impl KLogRetentionWorker {
+    pub fn new(
+        raft: KRaftRef,
+        state_store_manager: KLogStateStoreManagerRef,
+        check_interval_ms: u64,
+    ) -> Self {
+        Self {
+            raft,
+            state_store_manager,
+            check_interval: Duration::from_millis(check_interval_ms.max(1)),
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            info!(
+                "KLogRetentionWorker started: check_interval_ms={}",
+                self.check_interval.as_millis()
+            );
+            let mut ticker = tokio::time::interval(self.check_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            // The first tick fires immediately; give raft a full interval to settle first.
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match run_retention_once(&self.raft, &self.state_store_manager).await {
+                    Ok(report) => {
+                        debug!(
+                            "KLogRetentionWorker pass finished: plans={}, removed={}",
+                            report.plans.len(),
+                            report.removed
+                        );
+                    }
+                    Err(KLogRetentionError::NotLeader { leader_id }) => {
+                        debug!(
+                            "KLogRetentionWorker skip pass on non-leader: current_leader={:?}",
+                            leader_id
+                        );
+                    }
+                    Err(err) => {
+                        warn!("KLogRetentionWorker pass failed: {}", err);
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
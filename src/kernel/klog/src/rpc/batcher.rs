@@ -0,0 +1,158 @@
+use super::{KLOG_RPC_METHOD_LOG_APPEND_BATCH, KLogClient, KLogClientError};
+use crate::network::KLogAppendRequest;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+const DEFAULT_BATCH_MAX_ENTRIES: usize = 256;
+const DEFAULT_BATCH_MAX_BYTES: usize = 256 * 1024;
+const DEFAULT_BATCH_MAX_DELAY: Duration = Duration::from_millis(10);
+const BATCHER_QUEUE_CAPACITY: usize = 4096;
+
+/// Size and latency budget of one `klog.log.append_batch` call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KLogAppendBatchConfig {
+    /// Send once this many entries are queued.
+    pub max_entries: usize,
+    /// Send before the summed message bytes would exceed this; keep it below the server's
+    /// `DATA_APPEND_BATCH_MAX_BYTES`.
+    pub max_bytes: usize,
+    /// Longest time the first entry of a batch waits for more entries.
+    pub max_delay: Duration,
+}
+
+impl Default for KLogAppendBatchConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: DEFAULT_BATCH_MAX_ENTRIES,
+            max_bytes: DEFAULT_BATCH_MAX_BYTES,
+            max_delay: DEFAULT_BATCH_MAX_DELAY,
+        }
+    }
+}
+
+type PendingAppend = (
+    KLogAppendRequest,
+    oneshot::Sender<Result<u64, KLogClientError>>,
+);
+
+/// Coalesces concurrent appends into batched calls.
+///
+/// `append` resolves with the entry's own id once the batch carrying it is committed; a
+/// failed batch fails every entry in it. One batch is in flight at a time, so entries are
+/// committed in the order they were queued. Dropping the batcher still sends what is queued,
+/// `close` additionally waits for it.
+pub struct KLogAppendBatcher {
+    endpoint: String,
+    tx: mpsc::Sender<PendingAppend>,
+    task: JoinHandle<()>,
+}
+
+impl KLogAppendBatcher {
+    pub fn spawn(client: Arc<KLogClient>, config: KLogAppendBatchConfig) -> Self {
+        let (tx, rx) = mpsc::channel(BATCHER_QUEUE_CAPACITY);
+        let endpoint = client.endpoint().to_string();
+        let task = tokio::spawn(run_append_batcher(client, config, rx));
+        Self { endpoint, tx, task }
+    }
+
+    pub async fn append(&self, req: KLogAppendRequest) -> Result<u64, KLogClientError> {
+        let (done_tx, done_rx) = oneshot::channel();
+        self.tx
+            .send((req, done_tx))
+            .await
+            .map_err(|_| self.closed_error())?;
+        done_rx.await.map_err(|_| self.closed_error())?
+    }
+
+    /// Stop accepting entries and wait until the queued ones are sent.
+    pub async fn close(self) {
+        drop(self.tx);
+        if let Err(err) = self.task.await {
+            warn!(
+                "klog append batcher task stopped abnormally: endpoint={}, err={}",
+                self.endpoint, err
+            );
+        }
+    }
+
+    fn closed_error(&self) -> KLogClientError {
+        KLogClientError::internal(
+            &self.endpoint,
+            KLOG_RPC_METHOD_LOG_APPEND_BATCH,
+            "append batcher is closed",
+        )
+    }
+}
+
+async fn run_append_batcher(
+    client: Arc<KLogClient>,
+    config: KLogAppendBatchConfig,
+    mut rx: mpsc::Receiver<PendingAppend>,
+) {
+    let max_entries = config.max_entries.max(1);
+    let mut carry = None;
+    loop {
+        let first = match carry.take() {
+            Some(item) => item,
+            None => match rx.recv().await {
+                Some(item) => item,
+                None => break,
+            },
+        };
+        let deadline = Instant::now() + config.max_delay;
+        let mut bytes = first.0.message.len();
+        let mut batch: Vec<PendingAppend> = vec![first];
+        while batch.len() < max_entries {
+            let next = match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(item)) => item,
+                Ok(None) | Err(_) => break,
+            };
+            if bytes + next.0.message.len() > config.max_bytes {
+                carry = Some(next);
+                break;
+            }
+            bytes += next.0.message.len();
+            batch.push(next);
+        }
+        flush_append_batch(&client, batch).await;
+    }
+    debug!(
+        "klog append batcher stopped: endpoint={}",
+        client.endpoint()
+    );
+}
+
+async fn flush_append_batch(client: &KLogClient, batch: Vec<PendingAppend>) {
+    let (entries, waiters): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+    let count = entries.len();
+    let err = match client.append_log_batch(entries).await {
+        Ok(resp) if resp.ids.len() == count => {
+            for (waiter, id) in waiters.into_iter().zip(resp.ids) {
+                let _ = waiter.send(Ok(id));
+            }
+            return;
+        }
+        Ok(resp) => KLogClientError::internal(
+            client.endpoint(),
+            KLOG_RPC_METHOD_LOG_APPEND_BATCH,
+            format!(
+                "append batch returned {} ids for {} entries",
+                resp.ids.len(),
+                count
+            ),
+        ),
+        Err(err) => err,
+    };
+    warn!(
+        "klog append batch failed: endpoint={}, entries={}, err={}",
+        client.endpoint(),
+        count,
+        err
+    );
+    for waiter in waiters {
+        let _ = waiter.send(Err(err.clone()));
+    }
+}
@@ -1,25 +1,34 @@
 use super::{
-    KLOG_JSON_RPC_PATH, KLOG_JSON_RPC_VERSION, KLOG_RPC_METHOD_LOG_APPEND,
-    KLOG_RPC_METHOD_LOG_QUERY, KLOG_RPC_METHOD_META_DELETE, KLOG_RPC_METHOD_META_PUT,
-    KLOG_RPC_METHOD_META_QUERY, KLogJsonRpcRequest, KLogJsonRpcResponse,
+    KLOG_JSON_RPC_PATH, KLOG_JSON_RPC_VERSION, KLOG_RPC_METHOD_LEASE_GRANT,
+    KLOG_RPC_METHOD_LEASE_KEEPALIVE, KLOG_RPC_METHOD_LEASE_REVOKE, KLOG_RPC_METHOD_LOG_APPEND,
+    KLOG_RPC_METHOD_LOG_APPEND_BATCH, KLOG_RPC_METHOD_LOG_QUERY, KLOG_RPC_METHOD_LOG_WATCH,
+    KLOG_RPC_METHOD_META_DELETE, KLOG_RPC_METHOD_META_PUT, KLOG_RPC_METHOD_META_QUERY,
+    KLOG_RPC_METHOD_META_TXN, KLOG_RPC_METHOD_META_WATCH, KLogJsonRpcRequest, KLogJsonRpcResponse,
 };
-use crate::KNode;
 use crate::error::{
     KLogErrorCode, KLogErrorEnvelope, generate_trace_id, map_http_status_to_error_code,
     map_json_rpc_error_code_to_klog_error_code, parse_error_envelope_json,
 };
+use crate::lease::KLogLease;
 use crate::network::{
-    KLOG_TRACE_ID_HEADER, KLogAppendRequest, KLogAppendResponse, KLogMetaDeleteRequest,
-    KLogMetaDeleteResponse, KLogMetaPutRequest, KLogMetaPutResponse, KLogMetaQueryRequest,
-    KLogMetaQueryResponse, KLogQueryRequest, KLogQueryResponse,
+    KLOG_TRACE_ID_HEADER, KLogAppendBatchRequest, KLogAppendBatchResponse, KLogAppendRequest,
+    KLogAppendResponse, KLogLeaseRequest, KLogLeaseResponse, KLogLogWatchRequest,
+    KLogLogWatchResponse, KLogMetaDeleteRequest, KLogMetaDeleteResponse, KLogMetaPutRequest,
+    KLogMetaPutResponse, KLogMetaQueryRequest, KLogMetaQueryResponse, KLogMetaWatchRequest,
+    KLogMetaWatchResponse, KLogQueryRequest, KLogQueryResponse, KLogTxnRequest, KLogTxnResponse,
 };
+use crate::state_store::KLogMetaWatchEvent;
+use crate::{KLogEntry, KNode};
 use reqwest::StatusCode;
 use serde::Serialize;
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use uuid::Uuid;
 
 const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(3);
+// Server-side long-poll wait per watch call, leaving room for the round trip.
+const DEFAULT_WATCH_WAIT: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone, thiserror::Error)]
 #[error(
@@ -60,7 +69,7 @@ impl KLogClientError {
         }
     }
 
-    fn internal(endpoint: &str, method: &str, message: impl Into<String>) -> Self {
+    pub(crate) fn internal(endpoint: &str, method: &str, message: impl Into<String>) -> Self {
         let trace_id = generate_trace_id();
         let envelope = KLogErrorEnvelope::new(KLogErrorCode::Internal, message, trace_id);
         Self::from_envelope(endpoint, method, None, envelope)
@@ -110,6 +119,10 @@ impl KLogClient {
         self
     }
 
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
     pub fn generate_request_id(node_id: u64) -> String {
         format!("{}-{}", node_id, Uuid::now_v7())
     }
@@ -130,6 +143,30 @@ impl KLogClient {
         self.call_with_trace(KLOG_RPC_METHOD_LOG_APPEND, &req).await
     }
 
+    /// Append all entries with one raft proposal; ids come back in request order. Every entry
+    /// gets a request_id, so retrying a failed batch does not duplicate what was committed.
+    pub async fn append_log_batch(
+        &self,
+        entries: Vec<KLogAppendRequest>,
+    ) -> Result<KLogAppendBatchResponse, KLogClientError> {
+        let (resp, _) = self.append_log_batch_with_trace(entries).await?;
+        Ok(resp)
+    }
+
+    pub async fn append_log_batch_with_trace(
+        &self,
+        entries: Vec<KLogAppendRequest>,
+    ) -> Result<(KLogAppendBatchResponse, KLogCallTrace), KLogClientError> {
+        let req = KLogAppendBatchRequest {
+            entries: entries
+                .into_iter()
+                .map(|entry| self.fill_append_defaults(entry))
+                .collect(),
+        };
+        self.call_with_trace(KLOG_RPC_METHOD_LOG_APPEND_BATCH, &req)
+            .await
+    }
+
     pub async fn append_log_message(
         &self,
         message: impl Into<String>,
@@ -209,6 +246,135 @@ impl KLogClient {
         self.call_with_trace(KLOG_RPC_METHOD_META_QUERY, &req).await
     }
 
+    /// Apply compares and the chosen branch atomically; `succeeded` tells which branch ran.
+    pub async fn txn(&self, req: KLogTxnRequest) -> Result<KLogTxnResponse, KLogClientError> {
+        let (resp, _) = self.txn_with_trace(req).await?;
+        Ok(resp)
+    }
+
+    pub async fn txn_with_trace(
+        &self,
+        req: KLogTxnRequest,
+    ) -> Result<(KLogTxnResponse, KLogCallTrace), KLogClientError> {
+        self.call_with_trace(KLOG_RPC_METHOD_META_TXN, &req).await
+    }
+
+    /// Grant a lease that expires `ttl_ms` after the last keepalive. Attach keys to it with
+    /// `KLogMetaPutRequest::lease_id`.
+    pub async fn grant_lease(&self, ttl_ms: u64) -> Result<KLogLease, KLogClientError> {
+        let (resp, _): (KLogLeaseResponse, _) = self
+            .call_with_trace(
+                KLOG_RPC_METHOD_LEASE_GRANT,
+                &KLogLeaseRequest::Grant { ttl_ms },
+            )
+            .await?;
+        Ok(resp.lease)
+    }
+
+    /// Fails with `KLogErrorCode::NotFound` once the lease expired, holders must then
+    /// treat everything attached to it as lost.
+    pub async fn keepalive_lease(&self, lease_id: u64) -> Result<KLogLease, KLogClientError> {
+        let (resp, _): (KLogLeaseResponse, _) = self
+            .call_with_trace(
+                KLOG_RPC_METHOD_LEASE_KEEPALIVE,
+                &KLogLeaseRequest::KeepAlive { lease_id },
+            )
+            .await?;
+        Ok(resp.lease)
+    }
+
+    pub async fn revoke_lease(&self, lease_id: u64) -> Result<KLogLeaseResponse, KLogClientError> {
+        let (resp, _) = self
+            .call_with_trace(
+                KLOG_RPC_METHOD_LEASE_REVOKE,
+                &KLogLeaseRequest::Revoke { lease_id },
+            )
+            .await?;
+        Ok(resp)
+    }
+
+    /// Read a lease through a linearizable meta query, `None` if it expired or was revoked.
+    pub async fn get_lease(&self, lease_id: u64) -> Result<Option<KLogLease>, KLogClientError> {
+        let resp = self
+            .query_meta(KLogMetaQueryRequest {
+                key: Some(KLogLease::meta_key(lease_id)),
+                prefix: None,
+                limit: None,
+                strong_read: Some(true),
+            })
+            .await?;
+        let Some(item) = resp.items.into_iter().next() else {
+            return Ok(None);
+        };
+        KLogLease::from_meta_value(&item.value)
+            .map(Some)
+            .map_err(|e| {
+                KLogClientError::internal(
+                    &self.endpoint,
+                    KLOG_RPC_METHOD_META_QUERY,
+                    format!("decode lease {} failed: {}", lease_id, e),
+                )
+            })
+    }
+
+    /// Single long-poll round of `klog.meta.watch`; see [`KLogClient::watch_meta`] for a
+    /// stream that keeps the cursor between calls.
+    pub async fn poll_meta_watch(
+        &self,
+        req: KLogMetaWatchRequest,
+    ) -> Result<KLogMetaWatchResponse, KLogClientError> {
+        let (resp, _) = self.poll_meta_watch_with_trace(req).await?;
+        Ok(resp)
+    }
+
+    pub async fn poll_meta_watch_with_trace(
+        &self,
+        req: KLogMetaWatchRequest,
+    ) -> Result<(KLogMetaWatchResponse, KLogCallTrace), KLogClientError> {
+        let mut req = req;
+        req.wait_ms = Some(req.wait_ms.unwrap_or_else(|| self.default_watch_wait_ms()));
+        self.call_with_trace(KLOG_RPC_METHOD_META_WATCH, &req).await
+    }
+
+    pub async fn poll_log_watch(
+        &self,
+        req: KLogLogWatchRequest,
+    ) -> Result<KLogLogWatchResponse, KLogClientError> {
+        let (resp, _) = self.poll_log_watch_with_trace(req).await?;
+        Ok(resp)
+    }
+
+    pub async fn poll_log_watch_with_trace(
+        &self,
+        req: KLogLogWatchRequest,
+    ) -> Result<(KLogLogWatchResponse, KLogCallTrace), KLogClientError> {
+        let mut req = req;
+        req.wait_ms = Some(req.wait_ms.unwrap_or_else(|| self.default_watch_wait_ms()));
+        self.call_with_trace(KLOG_RPC_METHOD_LOG_WATCH, &req).await
+    }
+
+    /// Stream meta put/delete events under `req.prefix`. Set `req.after_revision` to a
+    /// previously saved [`KLogMetaWatchStream::revision`] to resume after a reconnect.
+    pub fn watch_meta(&self, req: KLogMetaWatchRequest) -> KLogMetaWatchStream<'_> {
+        KLogMetaWatchStream {
+            client: self,
+            delivered_revision: req.after_revision,
+            req,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Stream new log entries matching the filter. Set `req.after_id` to a previously saved
+    /// [`KLogLogWatchStream::last_id`] to resume after a reconnect.
+    pub fn watch_log(&self, req: KLogLogWatchRequest) -> KLogLogWatchStream<'_> {
+        KLogLogWatchStream {
+            client: self,
+            delivered_id: req.after_id,
+            req,
+            pending: VecDeque::new(),
+        }
+    }
+
     async fn call_with_trace<Req, Resp>(
         &self,
         method: &str,
@@ -350,6 +516,10 @@ impl KLogClient {
         Ok((resp, KLogCallTrace { trace_id }))
     }
 
+    fn default_watch_wait_ms(&self) -> u64 {
+        DEFAULT_WATCH_WAIT.min(self.timeout / 2).as_millis() as u64
+    }
+
     fn fill_append_defaults(&self, mut req: KLogAppendRequest) -> KLogAppendRequest {
         let request_id = req
             .request_id
@@ -365,6 +535,92 @@ impl KLogClient {
     }
 }
 
+/// Pull-based stream over `klog.meta.watch` long-polls.
+///
+/// A failed call leaves the cursor untouched, so `next` can simply be retried. When the
+/// server no longer has history for the cursor, `next` returns a `Compacted` error once and
+/// then continues from the server's current revision; re-read the prefix with
+/// [`KLogClient::query_meta`] to catch up on what was missed.
+pub struct KLogMetaWatchStream<'a> {
+    client: &'a KLogClient,
+    req: KLogMetaWatchRequest,
+    pending: VecDeque<KLogMetaWatchEvent>,
+    delivered_revision: Option<u64>,
+}
+
+impl KLogMetaWatchStream<'_> {
+    /// Revision of the last event handed out, or the resume cursor when none is pending.
+    pub fn revision(&self) -> Option<u64> {
+        if self.pending.is_empty() {
+            self.req.after_revision
+        } else {
+            self.delivered_revision
+        }
+    }
+
+    pub async fn next(&mut self) -> Result<KLogMetaWatchEvent, KLogClientError> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                self.delivered_revision = Some(event.revision);
+                return Ok(event);
+            }
+
+            let resp = self.client.poll_meta_watch(self.req.clone()).await?;
+            let prev_revision = self.req.after_revision;
+            self.req.after_revision = Some(resp.revision);
+            if resp.compacted {
+                let msg = format!(
+                    "klog meta watch history compacted: prefix={:?}, after_revision={:?}, resume_revision={}",
+                    self.req.prefix, prev_revision, resp.revision
+                );
+                warn!("{}", msg);
+                let envelope =
+                    KLogErrorEnvelope::new(KLogErrorCode::Compacted, msg, generate_trace_id());
+                return Err(KLogClientError::from_envelope(
+                    self.client.endpoint.as_str(),
+                    KLOG_RPC_METHOD_META_WATCH,
+                    None,
+                    envelope,
+                ));
+            }
+            self.pending.extend(resp.events);
+        }
+    }
+}
+
+/// Pull-based stream over `klog.log.watch` long-polls. A failed call leaves the cursor
+/// untouched, so `next` can simply be retried.
+pub struct KLogLogWatchStream<'a> {
+    client: &'a KLogClient,
+    req: KLogLogWatchRequest,
+    pending: VecDeque<KLogEntry>,
+    delivered_id: Option<u64>,
+}
+
+impl KLogLogWatchStream<'_> {
+    /// Id of the last entry handed out, or the resume cursor when none is pending.
+    pub fn last_id(&self) -> Option<u64> {
+        if self.pending.is_empty() {
+            self.req.after_id
+        } else {
+            self.delivered_id
+        }
+    }
+
+    pub async fn next(&mut self) -> Result<KLogEntry, KLogClientError> {
+        loop {
+            if let Some(entry) = self.pending.pop_front() {
+                self.delivered_id = Some(entry.id);
+                return Ok(entry);
+            }
+
+            let resp = self.client.poll_log_watch(self.req.clone()).await?;
+            self.req.after_id = Some(resp.last_id);
+            self.pending.extend(resp.items);
+        }
+    }
+}
+
 fn response_trace_id_from_headers(headers: &reqwest::header::HeaderMap) -> Option<String> {
     headers
         .get(KLOG_TRACE_ID_HEADER)
@@ -388,23 +644,29 @@ mod tests {
     use crate::KLogEntry;
     use crate::error::KLogErrorCode;
     use crate::network::{
-        KLOG_TRACE_ID_HEADER, KLogAppendRequest, KLogAppendResponse, KLogMetaDeleteRequest,
+        KLOG_TRACE_ID_HEADER, KLogAppendBatchRequest, KLogAppendBatchResponse, KLogAppendRequest,
+        KLogAppendResponse, KLogLogWatchRequest, KLogLogWatchResponse, KLogMetaDeleteRequest,
         KLogMetaDeleteResponse, KLogMetaPutRequest, KLogMetaPutResponse, KLogMetaQueryRequest,
-        KLogMetaQueryResponse, KLogQueryRequest, KLogQueryResponse,
+        KLogMetaQueryResponse, KLogMetaWatchRequest, KLogMetaWatchResponse, KLogQueryRequest,
+        KLogQueryResponse,
     };
     use crate::rpc::{
         KLOG_JSON_RPC_PATH, KLOG_RPC_ERR_METHOD_NOT_FOUND, KLOG_RPC_METHOD_LOG_APPEND,
-        KLOG_RPC_METHOD_LOG_QUERY, KLOG_RPC_METHOD_META_DELETE, KLOG_RPC_METHOD_META_PUT,
-        KLOG_RPC_METHOD_META_QUERY, KLogJsonRpcRequest, KLogJsonRpcResponse,
+        KLOG_RPC_METHOD_LOG_QUERY, KLOG_RPC_METHOD_LOG_WATCH, KLOG_RPC_METHOD_META_DELETE,
+        KLOG_RPC_METHOD_META_PUT, KLOG_RPC_METHOD_META_QUERY, KLOG_RPC_METHOD_META_WATCH,
+        KLogJsonRpcRequest, KLogJsonRpcResponse,
     };
+    use crate::rpc::{KLOG_RPC_METHOD_LOG_APPEND_BATCH, KLogAppendBatchConfig, KLogAppendBatcher};
+    use crate::state_store::{KLogMetaWatchEvent, KLogMetaWatchEventKind};
     use axum::Router;
     use axum::extract::Json;
     use axum::http::{HeaderMap, HeaderValue, StatusCode};
     use axum::routing::post;
     use std::collections::BTreeMap;
     use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
     use std::time::Duration;
-    use tokio::task::JoinHandle;
+    use tokio::task::{JoinHandle, JoinSet};
     use uuid::Uuid;
 
     struct TestJsonRpcServer {
@@ -507,6 +769,77 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_append_batcher_respects_entry_budget_and_keeps_order() -> anyhow::Result<()> {
+        let batches = Arc::new(Mutex::new(Vec::<Vec<String>>::new()));
+        let recorded = batches.clone();
+        let app = Router::new().route(
+            KLOG_JSON_RPC_PATH,
+            post(move |Json(request): Json<KLogJsonRpcRequest>| {
+                let recorded = recorded.clone();
+                async move {
+                    assert_eq!(request.method, KLOG_RPC_METHOD_LOG_APPEND_BATCH);
+                    let params: KLogAppendBatchRequest =
+                        serde_json::from_value(request.params).expect("append batch params");
+                    assert!(params.entries.iter().all(|e| e.request_id.is_some()));
+                    let mut batches = recorded.lock().expect("batches lock");
+                    let first_id = 100 + batches.iter().map(Vec::len).sum::<usize>() as u64;
+                    let ids = (first_id..first_id + params.entries.len() as u64).collect();
+                    batches.push(params.entries.into_iter().map(|e| e.message).collect());
+                    let response =
+                        KLogJsonRpcResponse::success(request.id, KLogAppendBatchResponse { ids });
+                    (StatusCode::OK, Json(response))
+                }
+            }),
+        );
+
+        let Some(server) = TestJsonRpcServer::try_start(app).await? else {
+            return Ok(());
+        };
+        let batcher = Arc::new(KLogAppendBatcher::spawn(
+            Arc::new(server.client()),
+            KLogAppendBatchConfig {
+                max_entries: 3,
+                max_bytes: 1024,
+                max_delay: Duration::from_millis(200),
+            },
+        ));
+        let mut tasks = JoinSet::new();
+        for i in 0..7u64 {
+            let batcher = batcher.clone();
+            tasks.spawn(async move {
+                // Stagger enqueue so the expected order is deterministic.
+                tokio::time::sleep(Duration::from_millis(i * 5)).await;
+                let id = batcher
+                    .append(KLogAppendRequest {
+                        message: format!("m{}", i),
+                        timestamp: None,
+                        node_id: None,
+                        level: None,
+                        source: None,
+                        attrs: None,
+                        request_id: None,
+                    })
+                    .await;
+                (i, id)
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            let (i, id) = joined?;
+            let id = id.map_err(|e| anyhow::anyhow!("batched append {} failed: {}", i, e))?;
+            assert_eq!(id, 100 + i);
+        }
+        let batcher = Arc::into_inner(batcher).expect("no other batcher handle");
+        batcher.close().await;
+
+        let batches = batches.lock().expect("batches lock").clone();
+        assert_eq!(
+            batches,
+            vec![vec!["m0", "m1", "m2"], vec!["m3", "m4", "m5"], vec!["m6"],]
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_json_rpc_client_append_with_trace_roundtrip() -> anyhow::Result<()> {
         let app = Router::new().route(
@@ -642,6 +975,7 @@ mod tests {
                 attr_key: None,
                 attr_value: None,
                 strong_read: None,
+                ..Default::default()
             })
             .await
             .map_err(|e| anyhow::anyhow!("query failed: {}", e))?;
@@ -682,6 +1016,7 @@ mod tests {
                                     updated_at: 1234,
                                     updated_by: 1,
                                     revision: 7,
+                                    lease_id: None,
                                 }],
                             },
                         );
@@ -701,6 +1036,7 @@ mod tests {
                                     updated_at: 1234,
                                     updated_by: 1,
                                     revision: 7,
+                                    lease_id: None,
                                 }),
                             },
                         );
@@ -727,6 +1063,7 @@ mod tests {
                 key: "cluster/config/epoch".to_string(),
                 value: "42".to_string(),
                 expected_revision: None,
+                lease_id: None,
             })
             .await
             .map_err(|e| anyhow::anyhow!("put_meta failed: {}", e))?;
@@ -756,6 +1093,165 @@ mod tests {
         Ok(())
     }
 
+    fn watch_event(revision: u64, kind: KLogMetaWatchEventKind) -> KLogMetaWatchEvent {
+        KLogMetaWatchEvent {
+            revision,
+            kind,
+            key: "cluster/config/epoch".to_string(),
+            meta: crate::KLogMetaEntry {
+                key: "cluster/config/epoch".to_string(),
+                value: revision.to_string(),
+                updated_at: 1234,
+                updated_by: 1,
+                revision: 1,
+                lease_id: None,
+            },
+        }
+    }
+
+    fn log_entry(id: u64) -> KLogEntry {
+        KLogEntry {
+            id,
+            timestamp: id * 10,
+            node_id: 1,
+            request_id: None,
+            level: Default::default(),
+            source: Some("kernel/kmsg".to_string()),
+            attrs: Default::default(),
+            message: format!("watch-{}", id),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_client_watch_meta_stream_resumes_cursor() -> anyhow::Result<()> {
+        let app = Router::new().route(
+            KLOG_JSON_RPC_PATH,
+            post(|Json(request): Json<KLogJsonRpcRequest>| async move {
+                assert_eq!(request.method, KLOG_RPC_METHOD_META_WATCH);
+                let params: KLogMetaWatchRequest =
+                    serde_json::from_value(request.params).expect("meta watch params");
+                assert_eq!(params.prefix.as_deref(), Some("cluster/"));
+                assert_eq!(params.wait_ms, Some(500));
+                let result = match params.after_revision {
+                    None => KLogMetaWatchResponse {
+                        events: vec![
+                            watch_event(5, KLogMetaWatchEventKind::Put),
+                            watch_event(7, KLogMetaWatchEventKind::Delete),
+                        ],
+                        revision: 8,
+                        compacted: false,
+                    },
+                    Some(8) => KLogMetaWatchResponse {
+                        events: vec![],
+                        revision: 20,
+                        compacted: true,
+                    },
+                    Some(20) => KLogMetaWatchResponse {
+                        events: vec![watch_event(21, KLogMetaWatchEventKind::Put)],
+                        revision: 21,
+                        compacted: false,
+                    },
+                    other => panic!("unexpected after_revision: {:?}", other),
+                };
+                (
+                    StatusCode::OK,
+                    Json(KLogJsonRpcResponse::success(request.id, result)),
+                )
+            }),
+        );
+
+        let Some(server) = TestJsonRpcServer::try_start(app).await? else {
+            return Ok(());
+        };
+        let client = server.client();
+        let mut stream = client.watch_meta(KLogMetaWatchRequest {
+            prefix: Some("cluster/".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(stream.revision(), None);
+
+        let first = stream
+            .next()
+            .await
+            .map_err(|e| anyhow::anyhow!("watch next failed: {}", e))?;
+        assert_eq!(first.revision, 5);
+        assert_eq!(stream.revision(), Some(5));
+        let second = stream
+            .next()
+            .await
+            .map_err(|e| anyhow::anyhow!("watch next failed: {}", e))?;
+        assert_eq!(second.kind, KLogMetaWatchEventKind::Delete);
+        assert_eq!(stream.revision(), Some(8));
+
+        let err = stream.next().await.expect_err("compacted error expected");
+        assert_eq!(err.error_code, KLogErrorCode::Compacted);
+        assert!(!err.retryable);
+        assert_eq!(stream.revision(), Some(20));
+
+        let resumed = stream
+            .next()
+            .await
+            .map_err(|e| anyhow::anyhow!("watch next after compaction failed: {}", e))?;
+        assert_eq!(resumed.revision, 21);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_client_watch_log_stream_skips_filtered_ids() -> anyhow::Result<()> {
+        let app = Router::new().route(
+            KLOG_JSON_RPC_PATH,
+            post(|Json(request): Json<KLogJsonRpcRequest>| async move {
+                assert_eq!(request.method, KLOG_RPC_METHOD_LOG_WATCH);
+                let params: KLogLogWatchRequest =
+                    serde_json::from_value(request.params).expect("log watch params");
+                assert_eq!(params.source.as_deref(), Some("kernel/kmsg"));
+                let result = match params.after_id {
+                    Some(2) => KLogLogWatchResponse {
+                        items: vec![log_entry(3)],
+                        last_id: 6,
+                    },
+                    Some(6) => KLogLogWatchResponse {
+                        items: vec![],
+                        last_id: 8,
+                    },
+                    Some(8) => KLogLogWatchResponse {
+                        items: vec![log_entry(9)],
+                        last_id: 9,
+                    },
+                    other => panic!("unexpected after_id: {:?}", other),
+                };
+                (
+                    StatusCode::OK,
+                    Json(KLogJsonRpcResponse::success(request.id, result)),
+                )
+            }),
+        );
+
+        let Some(server) = TestJsonRpcServer::try_start(app).await? else {
+            return Ok(());
+        };
+        let client = server.client();
+        let mut stream = client.watch_log(KLogLogWatchRequest {
+            after_id: Some(2),
+            source: Some("kernel/kmsg".to_string()),
+            ..Default::default()
+        });
+        let first = stream
+            .next()
+            .await
+            .map_err(|e| anyhow::anyhow!("log watch next failed: {}", e))?;
+        assert_eq!(first.id, 3);
+        assert_eq!(stream.last_id(), Some(6));
+
+        let second = stream
+            .next()
+            .await
+            .map_err(|e| anyhow::anyhow!("log watch next failed: {}", e))?;
+        assert_eq!(second.id, 9);
+        assert_eq!(stream.last_id(), Some(9));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_json_rpc_client_error_passthrough() -> anyhow::Result<()> {
         let app = Router::new().route(
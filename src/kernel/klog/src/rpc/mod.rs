@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+mod batcher;
 mod client;
 mod server;
 
+pub use batcher::*;
 pub use client::*;
 pub use server::*;
 
@@ -11,6 +13,7 @@ pub const KLOG_JSON_RPC_VERSION: &str = "2.0";
 pub const KLOG_JSON_RPC_PATH: &str = "/klog/rpc";
 pub const KLOG_RPC_METHOD_LOG_APPEND: &str = "klog.log.append";
 pub const KLOG_RPC_METHOD_LOG_QUERY: &str = "klog.log.query";
+pub const KLOG_RPC_METHOD_LOG_APPEND_BATCH: &str = "klog.log.append_batch";
 pub const KLOG_RPC_METHOD_LOG_APPEND_LEGACY: &str = "klog.append";
 pub const KLOG_RPC_METHOD_LOG_QUERY_LEGACY: &str = "klog.query";
 pub const KLOG_RPC_METHOD_APPEND: &str = KLOG_RPC_METHOD_LOG_APPEND;
@@ -18,6 +21,12 @@ pub const KLOG_RPC_METHOD_QUERY: &str = KLOG_RPC_METHOD_LOG_QUERY;
 pub const KLOG_RPC_METHOD_META_PUT: &str = "klog.meta.put";
 pub const KLOG_RPC_METHOD_META_DELETE: &str = "klog.meta.delete";
 pub const KLOG_RPC_METHOD_META_QUERY: &str = "klog.meta.query";
+pub const KLOG_RPC_METHOD_META_TXN: &str = "klog.meta.txn";
+pub const KLOG_RPC_METHOD_META_WATCH: &str = "klog.meta.watch";
+pub const KLOG_RPC_METHOD_LOG_WATCH: &str = "klog.log.watch";
+pub const KLOG_RPC_METHOD_LEASE_GRANT: &str = "klog.lease.grant";
+pub const KLOG_RPC_METHOD_LEASE_KEEPALIVE: &str = "klog.lease.keepalive";
+pub const KLOG_RPC_METHOD_LEASE_REVOKE: &str = "klog.lease.revoke";
 
 pub const KLOG_RPC_ERR_INVALID_REQUEST: i64 = -32600;
 pub const KLOG_RPC_ERR_METHOD_NOT_FOUND: i64 = -32601;
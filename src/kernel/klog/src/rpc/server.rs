@@ -2,19 +2,23 @@ use crate::error::{
     KLogErrorCode, KLogErrorEnvelope, KLogServiceError, generate_trace_id, normalize_trace_id,
 };
 use crate::network::{
-    KLOG_TRACE_ID_HEADER, KLogAppendRequest, KLogDataRequestType, KLogMetaDeleteRequest,
-    KLogMetaPutRequest, KLogMetaQueryRequest, KLogQueryRequest,
+    KLOG_TRACE_ID_HEADER, KLogAppendBatchRequest, KLogAppendRequest, KLogDataRequestType,
+    KLogLeaseRequest, KLogLogWatchRequest, KLogMetaDeleteRequest, KLogMetaPutRequest,
+    KLogMetaQueryRequest, KLogMetaWatchRequest, KLogQueryRequest, KLogTxnRequest,
 };
-use crate::service::{KLogQueryService, KLogWriteService};
+use crate::service::{KLogQueryService, KLogWriteService, WATCH_DEFAULT_WAIT_MS};
 use crate::state_store::KLogStateStoreManagerRef;
 use crate::{
     KRaftRef,
     rpc::{
         KLOG_JSON_RPC_PATH, KLOG_JSON_RPC_VERSION, KLOG_RPC_ERR_INTERNAL,
         KLOG_RPC_ERR_INVALID_PARAMS, KLOG_RPC_ERR_INVALID_REQUEST, KLOG_RPC_ERR_METHOD_NOT_FOUND,
-        KLOG_RPC_METHOD_LOG_APPEND, KLOG_RPC_METHOD_LOG_APPEND_LEGACY, KLOG_RPC_METHOD_LOG_QUERY,
-        KLOG_RPC_METHOD_LOG_QUERY_LEGACY, KLOG_RPC_METHOD_META_DELETE, KLOG_RPC_METHOD_META_PUT,
-        KLOG_RPC_METHOD_META_QUERY, KLogJsonRpcRequest, KLogJsonRpcResponse,
+        KLOG_RPC_METHOD_LEASE_GRANT, KLOG_RPC_METHOD_LEASE_KEEPALIVE, KLOG_RPC_METHOD_LEASE_REVOKE,
+        KLOG_RPC_METHOD_LOG_APPEND, KLOG_RPC_METHOD_LOG_APPEND_BATCH,
+        KLOG_RPC_METHOD_LOG_APPEND_LEGACY, KLOG_RPC_METHOD_LOG_QUERY,
+        KLOG_RPC_METHOD_LOG_QUERY_LEGACY, KLOG_RPC_METHOD_LOG_WATCH, KLOG_RPC_METHOD_META_DELETE,
+        KLOG_RPC_METHOD_META_PUT, KLOG_RPC_METHOD_META_QUERY, KLOG_RPC_METHOD_META_TXN,
+        KLOG_RPC_METHOD_META_WATCH, KLogJsonRpcRequest, KLogJsonRpcResponse,
     },
 };
 use axum::Json;
@@ -95,6 +99,8 @@ pub struct KRpcServerPolicy {
 struct KRpcServerState {
     write_service: KLogWriteService,
     query_service: KLogQueryService,
+    // Long-poll cap for watch methods, kept well inside the json-rpc route timeout.
+    watch_max_wait_ms: u64,
 }
 
 pub struct KRpcServer {
@@ -142,6 +148,7 @@ impl KRpcServer {
                 self.raft.clone(),
                 self.state_store_manager.clone(),
             ),
+            watch_max_wait_ms: self.policy.jsonrpc.timeout_ms / 2,
         };
 
         self.policy.append.validate("append")?;
@@ -182,19 +189,28 @@ impl KRpcServer {
             ));
 
         let data_append_path = KLogDataRequestType::Append.klog_path();
+        let data_append_batch_path = KLogDataRequestType::AppendBatch.klog_path();
         let data_query_path = KLogDataRequestType::Query.klog_path();
         let data_meta_put_path = KLogDataRequestType::MetaPut.klog_path();
         let data_meta_delete_path = KLogDataRequestType::MetaDelete.klog_path();
         let data_meta_query_path = KLogDataRequestType::MetaQuery.klog_path();
+        let data_lease_path = KLogDataRequestType::Lease.klog_path();
+        let data_txn_path = KLogDataRequestType::Txn.klog_path();
         let app = Router::new()
             .merge(
                 Router::new()
                     .route(&data_append_path, post(Self::handle_data_append_request))
+                    .route(
+                        &data_append_batch_path,
+                        post(Self::handle_data_append_batch_request),
+                    )
                     .route(&data_meta_put_path, post(Self::handle_meta_put_request))
                     .route(
                         &data_meta_delete_path,
                         post(Self::handle_meta_delete_request),
                     )
+                    .route(&data_lease_path, post(Self::handle_lease_request))
+                    .route(&data_txn_path, post(Self::handle_txn_request))
                     .route_layer(append_middleware),
             )
             .merge(
@@ -212,7 +228,7 @@ impl KRpcServer {
             .with_state(state);
 
         info!(
-            "KRpcServer start listening at {}, append(body_limit_bytes={}, concurrency={}, timeout_ms={}), query(body_limit_bytes={}, concurrency={}, timeout_ms={}), jsonrpc(body_limit_bytes={}, concurrency={}, timeout_ms={}), data_append_path={}, data_query_path={}, data_meta_put_path={}, data_meta_delete_path={}, data_meta_query_path={}, json_rpc_path={}",
+            "KRpcServer start listening at {}, append(body_limit_bytes={}, concurrency={}, timeout_ms={}), query(body_limit_bytes={}, concurrency={}, timeout_ms={}), jsonrpc(body_limit_bytes={}, concurrency={}, timeout_ms={}), data_append_path={}, data_append_batch_path={}, data_query_path={}, data_meta_put_path={}, data_meta_delete_path={}, data_meta_query_path={}, data_lease_path={}, data_txn_path={}, json_rpc_path={}",
             self.addr,
             self.policy.append.body_limit_bytes,
             self.policy.append.concurrency,
@@ -224,10 +240,13 @@ impl KRpcServer {
             self.policy.jsonrpc.concurrency,
             self.policy.jsonrpc.timeout_ms,
             data_append_path,
+            data_append_batch_path,
             data_query_path,
             data_meta_put_path,
             data_meta_delete_path,
             data_meta_query_path,
+            data_lease_path,
+            data_txn_path,
             KLOG_JSON_RPC_PATH
         );
 
@@ -288,6 +307,25 @@ impl KRpcServer {
         }
     }
 
+    async fn handle_data_append_batch_request(
+        State(state): State<KRpcServerState>,
+        headers: HeaderMap,
+        Json(req): Json<KLogAppendBatchRequest>,
+    ) -> Response {
+        let trace_id = normalize_trace_id(
+            headers
+                .get(KLOG_TRACE_ID_HEADER)
+                .and_then(|v| v.to_str().ok()),
+        );
+        let headers = Self::inject_trace_id_header(headers, &trace_id);
+        match state.write_service.append_batch(&headers, req).await {
+            Ok(resp) => {
+                Self::with_trace_id((StatusCode::OK, Json(resp)).into_response(), &trace_id)
+            }
+            Err(err) => Self::service_error_response(err),
+        }
+    }
+
     async fn handle_data_query_request(
         State(state): State<KRpcServerState>,
         headers: HeaderMap,
@@ -345,6 +383,44 @@ impl KRpcServer {
         }
     }
 
+    async fn handle_lease_request(
+        State(state): State<KRpcServerState>,
+        headers: HeaderMap,
+        Json(req): Json<KLogLeaseRequest>,
+    ) -> Response {
+        let trace_id = normalize_trace_id(
+            headers
+                .get(KLOG_TRACE_ID_HEADER)
+                .and_then(|v| v.to_str().ok()),
+        );
+        let headers = Self::inject_trace_id_header(headers, &trace_id);
+        match state.write_service.lease(&headers, req).await {
+            Ok(resp) => {
+                Self::with_trace_id((StatusCode::OK, Json(resp)).into_response(), &trace_id)
+            }
+            Err(err) => Self::service_error_response(err),
+        }
+    }
+
+    async fn handle_txn_request(
+        State(state): State<KRpcServerState>,
+        headers: HeaderMap,
+        Json(req): Json<KLogTxnRequest>,
+    ) -> Response {
+        let trace_id = normalize_trace_id(
+            headers
+                .get(KLOG_TRACE_ID_HEADER)
+                .and_then(|v| v.to_str().ok()),
+        );
+        let headers = Self::inject_trace_id_header(headers, &trace_id);
+        match state.write_service.txn(&headers, req).await {
+            Ok(resp) => {
+                Self::with_trace_id((StatusCode::OK, Json(resp)).into_response(), &trace_id)
+            }
+            Err(err) => Self::service_error_response(err),
+        }
+    }
+
     async fn handle_meta_query_request(
         State(state): State<KRpcServerState>,
         headers: HeaderMap,
@@ -452,6 +528,65 @@ impl KRpcServer {
                     }
                 }
             }
+            KLOG_RPC_METHOD_LOG_APPEND_BATCH => {
+                let params: KLogAppendBatchRequest = match serde_json::from_value(request.params) {
+                    Ok(params) => params,
+                    Err(e) => {
+                        let msg = format!(
+                            "Invalid params for {}: {}",
+                            KLOG_RPC_METHOD_LOG_APPEND_BATCH, e
+                        );
+                        let envelope = KLogErrorEnvelope::new(
+                            KLogErrorCode::InvalidArgument,
+                            msg.clone(),
+                            trace_id.clone(),
+                        );
+                        let resp = KLogJsonRpcResponse::error_with_data(
+                            req_id,
+                            KLOG_RPC_ERR_INVALID_PARAMS,
+                            envelope.message.clone(),
+                            Some(
+                                serde_json::to_value(envelope)
+                                    .unwrap_or_else(|_| serde_json::Value::Null),
+                            ),
+                        );
+                        return Self::with_trace_id(
+                            (StatusCode::OK, Json(resp)).into_response(),
+                            &trace_id,
+                        );
+                    }
+                };
+
+                match state.write_service.append_batch(&headers, params).await {
+                    Ok(result) => Self::with_trace_id(
+                        (
+                            StatusCode::OK,
+                            Json(KLogJsonRpcResponse::success(req_id, result)),
+                        )
+                            .into_response(),
+                        &trace_id,
+                    ),
+                    Err(err) => {
+                        let err_trace_id = err.error.trace_id.clone();
+                        Self::with_trace_id(
+                            (
+                                StatusCode::OK,
+                                Json(KLogJsonRpcResponse::error_with_data(
+                                    req_id,
+                                    Self::rpc_error_code_from_error_code(err.error.error_code),
+                                    err.error.message.clone(),
+                                    Some(
+                                        serde_json::to_value(err.error)
+                                            .unwrap_or_else(|_| serde_json::Value::Null),
+                                    ),
+                                )),
+                            )
+                                .into_response(),
+                            &err_trace_id,
+                        )
+                    }
+                }
+            }
             KLOG_RPC_METHOD_LOG_QUERY | KLOG_RPC_METHOD_LOG_QUERY_LEGACY => {
                 let params: KLogQueryRequest = if request.params.is_null() {
                     KLogQueryRequest::default()
@@ -625,6 +760,62 @@ impl KRpcServer {
                     }
                 }
             }
+            KLOG_RPC_METHOD_META_TXN => {
+                let params: KLogTxnRequest = match serde_json::from_value(request.params) {
+                    Ok(params) => params,
+                    Err(e) => {
+                        let msg = format!("Invalid params for {}: {}", KLOG_RPC_METHOD_META_TXN, e);
+                        let envelope = KLogErrorEnvelope::new(
+                            KLogErrorCode::InvalidArgument,
+                            msg.clone(),
+                            trace_id.clone(),
+                        );
+                        let resp = KLogJsonRpcResponse::error_with_data(
+                            req_id,
+                            KLOG_RPC_ERR_INVALID_PARAMS,
+                            envelope.message.clone(),
+                            Some(
+                                serde_json::to_value(envelope)
+                                    .unwrap_or_else(|_| serde_json::Value::Null),
+                            ),
+                        );
+                        return Self::with_trace_id(
+                            (StatusCode::OK, Json(resp)).into_response(),
+                            &trace_id,
+                        );
+                    }
+                };
+
+                match state.write_service.txn(&headers, params).await {
+                    Ok(result) => Self::with_trace_id(
+                        (
+                            StatusCode::OK,
+                            Json(KLogJsonRpcResponse::success(req_id, result)),
+                        )
+                            .into_response(),
+                        &trace_id,
+                    ),
+                    Err(err) => {
+                        let err_trace_id = err.error.trace_id.clone();
+                        Self::with_trace_id(
+                            (
+                                StatusCode::OK,
+                                Json(KLogJsonRpcResponse::error_with_data(
+                                    req_id,
+                                    Self::rpc_error_code_from_error_code(err.error.error_code),
+                                    err.error.message.clone(),
+                                    Some(
+                                        serde_json::to_value(err.error)
+                                            .unwrap_or_else(|_| serde_json::Value::Null),
+                                    ),
+                                )),
+                            )
+                                .into_response(),
+                            &err_trace_id,
+                        )
+                    }
+                }
+            }
             KLOG_RPC_METHOD_META_QUERY => {
                 let params: KLogMetaQueryRequest = if request.params.is_null() {
                     KLogMetaQueryRequest::default()
@@ -686,6 +877,199 @@ impl KRpcServer {
                     }
                 }
             }
+            KLOG_RPC_METHOD_META_WATCH => {
+                let mut params: KLogMetaWatchRequest = if request.params.is_null() {
+                    KLogMetaWatchRequest::default()
+                } else {
+                    match serde_json::from_value(request.params) {
+                        Ok(params) => params,
+                        Err(e) => {
+                            let msg =
+                                format!("Invalid params for {}: {}", KLOG_RPC_METHOD_META_WATCH, e);
+                            let envelope = KLogErrorEnvelope::new(
+                                KLogErrorCode::InvalidArgument,
+                                msg.clone(),
+                                trace_id.clone(),
+                            );
+                            let resp = KLogJsonRpcResponse::error_with_data(
+                                req_id,
+                                KLOG_RPC_ERR_INVALID_PARAMS,
+                                envelope.message.clone(),
+                                Some(
+                                    serde_json::to_value(envelope)
+                                        .unwrap_or_else(|_| serde_json::Value::Null),
+                                ),
+                            );
+                            return Self::with_trace_id(
+                                (StatusCode::OK, Json(resp)).into_response(),
+                                &trace_id,
+                            );
+                        }
+                    }
+                };
+                params.wait_ms = Some(
+                    params
+                        .wait_ms
+                        .unwrap_or(WATCH_DEFAULT_WAIT_MS)
+                        .min(state.watch_max_wait_ms),
+                );
+
+                match state.query_service.watch_meta(&headers, params).await {
+                    Ok(result) => Self::with_trace_id(
+                        (
+                            StatusCode::OK,
+                            Json(KLogJsonRpcResponse::success(req_id, result)),
+                        )
+                            .into_response(),
+                        &trace_id,
+                    ),
+                    Err(err) => {
+                        let err_trace_id = err.error.trace_id.clone();
+                        Self::with_trace_id(
+                            (
+                                StatusCode::OK,
+                                Json(KLogJsonRpcResponse::error_with_data(
+                                    req_id,
+                                    Self::rpc_error_code_from_error_code(err.error.error_code),
+                                    err.error.message.clone(),
+                                    Some(
+                                        serde_json::to_value(err.error)
+                                            .unwrap_or_else(|_| serde_json::Value::Null),
+                                    ),
+                                )),
+                            )
+                                .into_response(),
+                            &err_trace_id,
+                        )
+                    }
+                }
+            }
+            KLOG_RPC_METHOD_LOG_WATCH => {
+                let mut params: KLogLogWatchRequest = if request.params.is_null() {
+                    KLogLogWatchRequest::default()
+                } else {
+                    match serde_json::from_value(request.params) {
+                        Ok(params) => params,
+                        Err(e) => {
+                            let msg =
+                                format!("Invalid params for {}: {}", KLOG_RPC_METHOD_LOG_WATCH, e);
+                            let envelope = KLogErrorEnvelope::new(
+                                KLogErrorCode::InvalidArgument,
+                                msg.clone(),
+                                trace_id.clone(),
+                            );
+                            let resp = KLogJsonRpcResponse::error_with_data(
+                                req_id,
+                                KLOG_RPC_ERR_INVALID_PARAMS,
+                                envelope.message.clone(),
+                                Some(
+                                    serde_json::to_value(envelope)
+                                        .unwrap_or_else(|_| serde_json::Value::Null),
+                                ),
+                            );
+                            return Self::with_trace_id(
+                                (StatusCode::OK, Json(resp)).into_response(),
+                                &trace_id,
+                            );
+                        }
+                    }
+                };
+                params.wait_ms = Some(
+                    params
+                        .wait_ms
+                        .unwrap_or(WATCH_DEFAULT_WAIT_MS)
+                        .min(state.watch_max_wait_ms),
+                );
+
+                match state.query_service.watch_log(&headers, params).await {
+                    Ok(result) => Self::with_trace_id(
+                        (
+                            StatusCode::OK,
+                            Json(KLogJsonRpcResponse::success(req_id, result)),
+                        )
+                            .into_response(),
+                        &trace_id,
+                    ),
+                    Err(err) => {
+                        let err_trace_id = err.error.trace_id.clone();
+                        Self::with_trace_id(
+                            (
+                                StatusCode::OK,
+                                Json(KLogJsonRpcResponse::error_with_data(
+                                    req_id,
+                                    Self::rpc_error_code_from_error_code(err.error.error_code),
+                                    err.error.message.clone(),
+                                    Some(
+                                        serde_json::to_value(err.error)
+                                            .unwrap_or_else(|_| serde_json::Value::Null),
+                                    ),
+                                )),
+                            )
+                                .into_response(),
+                            &err_trace_id,
+                        )
+                    }
+                }
+            }
+            KLOG_RPC_METHOD_LEASE_GRANT
+            | KLOG_RPC_METHOD_LEASE_KEEPALIVE
+            | KLOG_RPC_METHOD_LEASE_REVOKE => {
+                let params =
+                    match Self::lease_request_from_rpc_params(&request.method, request.params) {
+                        Ok(params) => params,
+                        Err(e) => {
+                            let msg = format!("Invalid params for {}: {}", request.method, e);
+                            let envelope = KLogErrorEnvelope::new(
+                                KLogErrorCode::InvalidArgument,
+                                msg.clone(),
+                                trace_id.clone(),
+                            );
+                            let resp = KLogJsonRpcResponse::error_with_data(
+                                req_id,
+                                KLOG_RPC_ERR_INVALID_PARAMS,
+                                envelope.message.clone(),
+                                Some(
+                                    serde_json::to_value(envelope)
+                                        .unwrap_or_else(|_| serde_json::Value::Null),
+                                ),
+                            );
+                            return Self::with_trace_id(
+                                (StatusCode::OK, Json(resp)).into_response(),
+                                &trace_id,
+                            );
+                        }
+                    };
+
+                match state.write_service.lease(&headers, params).await {
+                    Ok(result) => Self::with_trace_id(
+                        (
+                            StatusCode::OK,
+                            Json(KLogJsonRpcResponse::success(req_id, result)),
+                        )
+                            .into_response(),
+                        &trace_id,
+                    ),
+                    Err(err) => {
+                        let err_trace_id = err.error.trace_id.clone();
+                        Self::with_trace_id(
+                            (
+                                StatusCode::OK,
+                                Json(KLogJsonRpcResponse::error_with_data(
+                                    req_id,
+                                    Self::rpc_error_code_from_error_code(err.error.error_code),
+                                    err.error.message.clone(),
+                                    Some(
+                                        serde_json::to_value(err.error)
+                                            .unwrap_or_else(|_| serde_json::Value::Null),
+                                    ),
+                                )),
+                            )
+                                .into_response(),
+                            &err_trace_id,
+                        )
+                    }
+                }
+            }
             _ => {
                 let envelope = KLogErrorEnvelope::new(
                     KLogErrorCode::InvalidArgument,
@@ -710,12 +1094,31 @@ impl KRpcServer {
         }
     }
 
+    /// The lease op is implied by the method name, params only carry `ttl_ms`/`lease_id`.
+    fn lease_request_from_rpc_params(
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<KLogLeaseRequest, serde_json::Error> {
+        let op = match method {
+            KLOG_RPC_METHOD_LEASE_GRANT => "grant",
+            KLOG_RPC_METHOD_LEASE_KEEPALIVE => "keepalive",
+            _ => "revoke",
+        };
+        let mut params = match params {
+            serde_json::Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        params.insert("op".to_string(), serde_json::Value::from(op));
+        serde_json::from_value(serde_json::Value::Object(params))
+    }
+
     fn rpc_error_code_from_error_code(code: KLogErrorCode) -> i64 {
         if matches!(
             code,
             KLogErrorCode::InvalidArgument
                 | KLogErrorCode::PayloadTooLarge
                 | KLogErrorCode::VersionConflict
+                | KLogErrorCode::NotFound
         ) {
             KLOG_RPC_ERR_INVALID_PARAMS
         } else if matches!(
@@ -1,14 +1,25 @@
 use crate::error::{KLogErrorCode, KLogServiceError, normalize_trace_id};
+use crate::lease::validate_lease_ttl;
 use crate::network::{
     KDataClient, KLOG_FORWARD_HOPS_HEADER, KLOG_FORWARDED_BY_HEADER, KLOG_TRACE_ID_HEADER,
-    KLogAppendRequest, KLogAppendResponse, KLogMetaDeleteRequest, KLogMetaDeleteResponse,
-    KLogMetaPutRequest, KLogMetaPutResponse, KLogMetaQueryRequest, KLogMetaQueryResponse,
-    KLogQueryRequest, KLogQueryResponse,
+    KLogAppendBatchRequest, KLogAppendBatchResponse, KLogAppendRequest, KLogAppendResponse,
+    KLogLeaseRequest, KLogLeaseResponse, KLogLogWatchRequest, KLogLogWatchResponse,
+    KLogMetaDeleteRequest, KLogMetaDeleteResponse, KLogMetaPutRequest, KLogMetaPutResponse,
+    KLogMetaQueryRequest, KLogMetaQueryResponse, KLogMetaWatchRequest, KLogMetaWatchResponse,
+    KLogQueryRequest, KLogQueryResponse, KLogTxnRequest, KLogTxnRequestOp, KLogTxnResponse,
+    parse_attr_filters,
+};
+use crate::retention::{KLOG_RESERVED_META_PREFIX, is_reserved_meta_key};
+use crate::state_store::{
+    KLogMetaWatchBatch, KLogQuery, KLogQueryOrder, KLogStateStoreManagerRef, tokenize_message,
+};
+use crate::txn::{KLogTxnCompare, KLogTxnOp};
+use crate::{
+    KLogEntry, KLogLevel, KLogMetaEntry, KLogRequest, KLogResponse, KNode, KNodeId, KRaftRef,
 };
-use crate::state_store::{KLogQuery, KLogQueryOrder, KLogStateStoreManagerRef};
-use crate::{KLogEntry, KLogLevel, KLogMetaEntry, KLogRequest, KLogResponse, KNode, KRaftRef};
 use axum::http::{HeaderMap, StatusCode};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::BTreeSet;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub const DATA_QUERY_DEFAULT_LIMIT: usize = 200;
 pub const DATA_QUERY_MAX_LIMIT: usize = 2_000;
@@ -16,11 +27,19 @@ pub const DATA_QUERY_MAX_FORWARD_HOPS: u32 = 2;
 pub const DATA_APPEND_MAX_MESSAGE_BYTES: usize = 64 * 1024;
 pub const DATA_APPEND_MAX_REQUEST_ID_BYTES: usize = 128;
 pub const DATA_APPEND_MAX_FORWARD_HOPS: u32 = 2;
+pub const DATA_APPEND_BATCH_MAX_ENTRIES: usize = 1_000;
+pub const DATA_APPEND_BATCH_MAX_BYTES: usize = 512 * 1024;
 pub const META_KEY_MAX_BYTES: usize = 256;
 pub const META_VALUE_MAX_BYTES: usize = 256 * 1024;
 pub const META_QUERY_DEFAULT_LIMIT: usize = 200;
 pub const META_QUERY_MAX_LIMIT: usize = 2_000;
 pub const META_RW_MAX_FORWARD_HOPS: u32 = 2;
+pub const TXN_MAX_COMPARES: usize = 64;
+pub const TXN_MAX_OPS: usize = 128;
+pub const WATCH_DEFAULT_LIMIT: usize = 200;
+pub const WATCH_MAX_LIMIT: usize = 2_000;
+pub const WATCH_DEFAULT_WAIT_MS: u64 = 1_000;
+pub const WATCH_MAX_WAIT_MS: u64 = 60_000;
 pub type KServiceResult<T> = Result<T, KLogServiceError>;
 
 #[derive(Clone)]
@@ -51,55 +70,12 @@ impl KLogWriteService {
         req: KLogAppendRequest,
     ) -> KServiceResult<KLogAppendResponse> {
         let trace_id = self.resolve_trace_id(headers);
-        if req.message.trim().is_empty() {
-            let msg = format!("{} data append rejected: empty message", self.service_name);
-            error!("{}", msg);
-            return Err(self.service_error(
-                StatusCode::BAD_REQUEST,
-                KLogErrorCode::InvalidArgument,
-                msg,
-                &trace_id,
-            ));
-        }
-
-        if req.message.len() > DATA_APPEND_MAX_MESSAGE_BYTES {
-            let msg = format!(
-                "{} data append rejected: message too large, bytes={}, max_bytes={}",
-                self.service_name,
-                req.message.len(),
-                DATA_APPEND_MAX_MESSAGE_BYTES
-            );
-            error!("{}", msg);
-            return Err(self.service_error(
-                StatusCode::PAYLOAD_TOO_LARGE,
-                KLogErrorCode::PayloadTooLarge,
-                msg,
-                &trace_id,
-            ));
-        }
-
-        let request_id = req
-            .request_id
-            .as_ref()
-            .map(|v| v.trim().to_string())
-            .filter(|v| !v.is_empty());
-        if let Some(request_id) = request_id.as_ref()
-            && request_id.len() > DATA_APPEND_MAX_REQUEST_ID_BYTES
-        {
-            let msg = format!(
-                "{} data append rejected: request_id too large, bytes={}, max_bytes={}",
-                self.service_name,
-                request_id.len(),
-                DATA_APPEND_MAX_REQUEST_ID_BYTES
-            );
-            error!("{}", msg);
-            return Err(self.service_error(
-                StatusCode::BAD_REQUEST,
-                KLogErrorCode::InvalidArgument,
-                msg,
-                &trace_id,
-            ));
-        }
+        let request_id =
+            self.check_append_request(&req, "data append")
+                .map_err(|(status, code, msg)| {
+                    error!("{}", msg);
+                    self.service_error(status, code, msg, &trace_id)
+                })?;
 
         if let Some(request_id) = request_id.as_ref()
             && let Some(existing_id) = self
@@ -146,34 +122,8 @@ impl KLogWriteService {
 
         let metrics = self.raft.metrics().borrow().clone();
         let local_node_id = metrics.id;
-        let level = req.level.unwrap_or(KLogLevel::Info);
-        let source = req
-            .source
-            .as_deref()
-            .map(str::trim)
-            .filter(|v| !v.is_empty())
-            .map(|v| v.to_string());
-        let attrs = req.attrs.unwrap_or_default();
-        let req = KLogAppendRequest {
-            message: req.message,
-            timestamp: req.timestamp.or_else(|| Some(now_millis())),
-            node_id: req.node_id.or(Some(local_node_id)),
-            level: Some(level),
-            source: source.clone(),
-            attrs: Some(attrs.clone()),
-            request_id: request_id.clone(),
-        };
-
-        let item = self.state_store_manager.prepare_append_entry(KLogEntry {
-            id: 0,
-            timestamp: req.timestamp.unwrap_or(0),
-            node_id: req.node_id.unwrap_or(local_node_id),
-            request_id,
-            level,
-            source,
-            attrs,
-            message: req.message.clone(),
-        });
+        let (req, item) = normalize_append_request(req, request_id, local_node_id);
+        let item = self.state_store_manager.prepare_append_entry(item);
         let requested_id = item.id;
 
         info!(
@@ -342,15 +292,19 @@ impl KLogWriteService {
         }
     }
 
-    pub async fn put_meta(
+    /// Commit several appends with one raft proposal. Ids are allocated as one contiguous
+    /// range on the leader; entries whose request_id is already known keep their earlier id.
+    pub async fn append_batch(
         &self,
         headers: &HeaderMap,
-        req: KLogMetaPutRequest,
-    ) -> KServiceResult<KLogMetaPutResponse> {
+        req: KLogAppendBatchRequest,
+    ) -> KServiceResult<KLogAppendBatchResponse> {
         let trace_id = self.resolve_trace_id(headers);
-        let key = req.key.trim().to_string();
-        if key.is_empty() {
-            let msg = format!("{} meta put rejected: empty key", self.service_name);
+        if req.entries.is_empty() {
+            let msg = format!(
+                "{} data append batch rejected: empty batch",
+                self.service_name
+            );
             error!("{}", msg);
             return Err(self.service_error(
                 StatusCode::BAD_REQUEST,
@@ -359,27 +313,26 @@ impl KLogWriteService {
                 &trace_id,
             ));
         }
-        if key.len() > META_KEY_MAX_BYTES {
+        if req.entries.len() > DATA_APPEND_BATCH_MAX_ENTRIES {
             let msg = format!(
-                "{} meta put rejected: key too large, bytes={}, max_bytes={}",
+                "{} data append batch rejected: too many entries, count={}, max={}",
                 self.service_name,
-                key.len(),
-                META_KEY_MAX_BYTES
+                req.entries.len(),
+                DATA_APPEND_BATCH_MAX_ENTRIES
             );
             error!("{}", msg);
             return Err(self.service_error(
-                StatusCode::BAD_REQUEST,
-                KLogErrorCode::InvalidArgument,
+                StatusCode::PAYLOAD_TOO_LARGE,
+                KLogErrorCode::PayloadTooLarge,
                 msg,
                 &trace_id,
             ));
         }
-        if req.value.len() > META_VALUE_MAX_BYTES {
+        let total_bytes = req.entries.iter().map(|e| e.message.len()).sum::<usize>();
+        if total_bytes > DATA_APPEND_BATCH_MAX_BYTES {
             let msg = format!(
-                "{} meta put rejected: value too large, bytes={}, max_bytes={}",
-                self.service_name,
-                req.value.len(),
-                META_VALUE_MAX_BYTES
+                "{} data append batch rejected: batch too large, bytes={}, max_bytes={}",
+                self.service_name, total_bytes, DATA_APPEND_BATCH_MAX_BYTES
             );
             error!("{}", msg);
             return Err(self.service_error(
@@ -390,8 +343,42 @@ impl KLogWriteService {
             ));
         }
 
+        let mut request_ids = Vec::with_capacity(req.entries.len());
+        for (index, entry) in req.entries.iter().enumerate() {
+            let request_id = self
+                .check_append_request(entry, &format!("data append batch entry={}", index))
+                .map_err(|(status, code, msg)| {
+                    error!("{}", msg);
+                    self.service_error(status, code, msg, &trace_id)
+                })?;
+            request_ids.push(request_id);
+        }
+
+        // Resolve known request_ids up front, only the rest goes through raft.
+        let mut ids = vec![0u64; req.entries.len()];
+        let mut pending = Vec::with_capacity(req.entries.len());
+        for (index, (entry, request_id)) in req.entries.into_iter().zip(request_ids).enumerate() {
+            if let Some(request_id) = request_id.as_ref()
+                && let Some(existing_id) = self
+                    .state_store_manager
+                    .find_recent_request_id(request_id)
+                    .await
+            {
+                info!(
+                    "{} data append batch dedup hit before raft write: index={}, request_id={}, existing_id={}",
+                    self.service_name, index, request_id, existing_id
+                );
+                ids[index] = existing_id;
+                continue;
+            }
+            pending.push((index, entry, request_id));
+        }
+        if pending.is_empty() {
+            return Ok(KLogAppendBatchResponse { ids });
+        }
+
         let forward_hops = self
-            .parse_forward_hops(headers, "meta put")
+            .parse_forward_hops(headers, "data append batch")
             .map_err(|msg| {
                 error!("{}", msg);
                 self.service_error(
@@ -405,10 +392,11 @@ impl KLogWriteService {
             .get(KLOG_FORWARDED_BY_HEADER)
             .and_then(|v| v.to_str().ok())
             .unwrap_or("-");
-        if forward_hops > META_RW_MAX_FORWARD_HOPS {
+
+        if forward_hops > DATA_APPEND_MAX_FORWARD_HOPS {
             let msg = format!(
-                "{} meta put rejected: too many forward hops, hops={}, max_hops={}, forwarded_by={}",
-                self.service_name, forward_hops, META_RW_MAX_FORWARD_HOPS, forwarded_by
+                "{} data append batch rejected: too many forward hops, hops={}, max_hops={}, forwarded_by={}",
+                self.service_name, forward_hops, DATA_APPEND_MAX_FORWARD_HOPS, forwarded_by
             );
             error!("{}", msg);
             return Err(self.service_error(
@@ -421,168 +409,524 @@ impl KLogWriteService {
 
         let metrics = self.raft.metrics().borrow().clone();
         let local_node_id = metrics.id;
-        let expected_revision = req.expected_revision;
-        let item = KLogMetaEntry {
-            key: key.clone(),
-            value: req.value.clone(),
-            // Meta write audit fields are owned by the raft service, not client input.
-            updated_at: now_millis(),
-            updated_by: local_node_id,
-            revision: 0,
-        };
+        let mut indexes = Vec::with_capacity(pending.len());
+        let mut entries = Vec::with_capacity(pending.len());
+        let mut items = Vec::with_capacity(pending.len());
+        for (index, entry, request_id) in pending {
+            let (entry, item) = normalize_append_request(entry, request_id, local_node_id);
+            indexes.push(index);
+            entries.push(entry);
+            items.push(item);
+        }
+        let items = self.state_store_manager.prepare_append_batch(items);
+        let first_id = items.first().map(|item| item.id).unwrap_or(0);
+        let count = items.len();
+
         info!(
-            "{} meta put request: trace_id={}, key={}, value_len={}, updated_at={}, updated_by={}, expected_revision={:?}, local_node_id={}, current_leader={:?}, forward_hops={}, forwarded_by={}",
+            "{} data append batch request: trace_id={}, entries={}, first_id={}, local_node_id={}, current_leader={:?}, forward_hops={}, forwarded_by={}",
             self.service_name,
             trace_id,
-            item.key,
-            item.value.len(),
-            item.updated_at,
-            item.updated_by,
-            expected_revision,
+            count,
+            first_id,
             local_node_id,
             metrics.current_leader,
             forward_hops,
             forwarded_by
         );
 
-        match self
+        let committed = match self
             .raft
-            .client_write(KLogRequest::PutMeta {
-                item,
-                expected_revision,
-            })
+            .client_write(KLogRequest::AppendLogBatch { items })
             .await
         {
             Ok(resp) => match resp.data {
-                KLogResponse::MetaPutOk { key, revision } => {
+                KLogResponse::AppendBatchOk { ids } if ids.len() == count => {
                     info!(
-                        "{} meta put committed: key={}, revision={}",
-                        self.service_name, key, revision
+                        "{} data append batch committed: entries={}, first_id={}",
+                        self.service_name, count, first_id
                     );
-                    Ok(KLogMetaPutResponse { key, revision })
+                    ids
                 }
-                KLogResponse::MetaPutConflict {
-                    key,
-                    expected_revision,
-                    current_revision,
-                } => {
+                KLogResponse::Err(err_msg) => {
                     let msg = format!(
-                        "{} meta put version conflict: key={}, expected_revision={}, current_revision={:?}",
-                        self.service_name, key, expected_revision, current_revision
+                        "{} data append batch failed in state machine: first_id={}, entries={}, err={}",
+                        self.service_name, first_id, count, err_msg
                     );
-                    warn!("{}", msg);
-                    Err(self.service_error(
-                        StatusCode::CONFLICT,
-                        KLogErrorCode::VersionConflict,
+                    error!("{}", msg);
+                    return Err(self.service_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        KLogErrorCode::Internal,
                         msg,
                         &trace_id,
-                    ))
+                    ));
                 }
-                KLogResponse::Err(err_msg) => {
+                other => {
                     let msg = format!(
-                        "{} meta put failed in state machine: key={}, err={}",
-                        self.service_name, key, err_msg
+                        "{} data append batch unexpected response: first_id={}, entries={}, response={:?}",
+                        self.service_name, first_id, count, other
                     );
                     error!("{}", msg);
-                    Err(self.service_error(
+                    return Err(self.service_error(
                         StatusCode::INTERNAL_SERVER_ERROR,
                         KLogErrorCode::Internal,
                         msg,
                         &trace_id,
-                    ))
+                    ));
                 }
-                other => {
+            },
+            Err(err) => {
+                let Some(forward) = err.forward_to_leader::<KNode>() else {
                     let msg = format!(
-                        "{} meta put unexpected response: key={}, response={:?}",
-                        self.service_name, key, other
+                        "{} data append batch raft client_write failed: first_id={}, entries={}, err={}",
+                        self.service_name, first_id, count, err
                     );
                     error!("{}", msg);
-                    Err(self.service_error(
+                    return Err(self.service_error(
                         StatusCode::INTERNAL_SERVER_ERROR,
                         KLogErrorCode::Internal,
                         msg,
                         &trace_id,
-                    ))
+                    ));
+                };
+                if forward_hops >= DATA_APPEND_MAX_FORWARD_HOPS {
+                    let msg = format!(
+                        "{} data append batch forward aborted due to hop limit: local_node_id={}, leader_id={:?}, leader_node={:?}, hops={}, max_hops={}",
+                        self.service_name,
+                        local_node_id,
+                        forward.leader_id,
+                        forward.leader_node,
+                        forward_hops,
+                        DATA_APPEND_MAX_FORWARD_HOPS
+                    );
+                    error!("{}", msg);
+                    return Err(self.service_error(
+                        StatusCode::BAD_GATEWAY,
+                        KLogErrorCode::LeaderUnavailable,
+                        msg,
+                        &trace_id,
+                    ));
                 }
-            },
-            Err(err) => {
-                if let Some(forward) = err.forward_to_leader::<KNode>() {
-                    if forward_hops >= META_RW_MAX_FORWARD_HOPS {
-                        let msg = format!(
-                            "{} meta put forward aborted due to hop limit: local_node_id={}, key={}, leader_id={:?}, leader_node={:?}, hops={}, max_hops={}",
+
+                let leader_node = forward.leader_node.clone().or_else(|| {
+                    forward.leader_id.and_then(|leader_id| {
+                        metrics
+                            .membership_config
+                            .nodes()
+                            .find_map(|(id, node)| (*id == leader_id).then_some(node.clone()))
+                    })
+                });
+                let Some(leader_node) = leader_node else {
+                    let msg = format!(
+                        "{} data append batch can not resolve leader node for forwarding: local_node_id={}, leader_id={:?}",
+                        self.service_name, local_node_id, forward.leader_id
+                    );
+                    warn!("{}", msg);
+                    return Err(self
+                        .service_error(
+                            StatusCode::SERVICE_UNAVAILABLE,
+                            KLogErrorCode::LeaderUnavailable,
+                            msg,
+                            &trace_id,
+                        )
+                        .with_leader_hint(forward.leader_node.clone()));
+                };
+
+                let target_hops = forward_hops + 1;
+                warn!(
+                    "{} data append batch forwarding to leader: local_node_id={}, entries={}, leader_id={}, leader_addr={}:{}, hops={} -> {}",
+                    self.service_name,
+                    local_node_id,
+                    count,
+                    leader_node.id,
+                    leader_node.addr,
+                    leader_node.port,
+                    forward_hops,
+                    target_hops
+                );
+                let forward_req = KLogAppendBatchRequest { entries };
+                match self
+                    .data_client
+                    .append_batch_to_node(
+                        &leader_node,
+                        &forward_req,
+                        target_hops,
+                        local_node_id,
+                        &trace_id,
+                    )
+                    .await
+                {
+                    Ok(resp) if resp.ids.len() == count => {
+                        info!(
+                            "{} data append batch forwarded and committed: trace_id={}, local_node_id={}, entries={}, leader_id={}, hops={}",
                             self.service_name,
+                            trace_id,
                             local_node_id,
-                            key,
-                            forward.leader_id,
-                            forward.leader_node,
-                            forward_hops,
-                            META_RW_MAX_FORWARD_HOPS
+                            count,
+                            leader_node.id,
+                            target_hops
+                        );
+                        resp.ids
+                    }
+                    Ok(resp) => {
+                        let msg = format!(
+                            "{} data append batch forward returned wrong id count: entries={}, ids={}, leader_id={}",
+                            self.service_name,
+                            count,
+                            resp.ids.len(),
+                            leader_node.id
                         );
                         error!("{}", msg);
                         return Err(self.service_error(
                             StatusCode::BAD_GATEWAY,
-                            KLogErrorCode::LeaderUnavailable,
+                            KLogErrorCode::Internal,
                             msg,
                             &trace_id,
                         ));
                     }
-
-                    let leader_node = forward.leader_node.clone().or_else(|| {
-                        forward.leader_id.and_then(|leader_id| {
-                            metrics
-                                .membership_config
-                                .nodes()
-                                .find_map(|(id, node)| (*id == leader_id).then_some(node.clone()))
-                        })
-                    });
-                    let Some(leader_node) = leader_node else {
+                    Err(mut forward_err) => {
                         let msg = format!(
-                            "{} meta put can not resolve leader node for forwarding: local_node_id={}, key={}, leader_id={:?}",
-                            self.service_name, local_node_id, key, forward.leader_id
+                            "{} data append batch forward failed: local_node_id={}, leader_id={}, err={}",
+                            self.service_name, local_node_id, leader_node.id, forward_err
                         );
-                        warn!("{}", msg);
-                        return Err(self
-                            .service_error(
-                                StatusCode::SERVICE_UNAVAILABLE,
-                                KLogErrorCode::LeaderUnavailable,
-                                msg,
-                                &trace_id,
-                            )
-                            .with_leader_hint(forward.leader_node.clone()));
-                    };
+                        error!("{}", msg);
+                        forward_err.http_status = StatusCode::BAD_GATEWAY.as_u16();
+                        forward_err.error.message = msg;
+                        if forward_err.error.leader_hint.is_none() {
+                            forward_err.error.leader_hint = Some(leader_node);
+                        }
+                        return Err(forward_err);
+                    }
+                }
+            }
+        };
 
-                    let target_hops = forward_hops + 1;
-                    warn!(
-                        "{} meta put forwarding to leader: local_node_id={}, key={}, leader_id={}, leader_addr={}:{}, hops={} -> {}",
-                        self.service_name,
-                        local_node_id,
-                        key,
-                        leader_node.id,
-                        leader_node.addr,
-                        leader_node.port,
-                        forward_hops,
-                        target_hops
-                    );
-                    match self
-                        .data_client
-                        .put_meta_to_node(
-                            &leader_node,
-                            &KLogMetaPutRequest {
-                                key: key.clone(),
-                                value: req.value,
-                                expected_revision: req.expected_revision,
-                            },
-                            target_hops,
-                            local_node_id,
-                            &trace_id,
-                        )
-                        .await
-                    {
-                        Ok(resp) => {
-                            info!(
-                                "{} meta put forwarded and committed: trace_id={}, local_node_id={}, key={}, leader_id={}, hops={}",
-                                self.service_name,
-                                trace_id,
+        for (index, id) in indexes.into_iter().zip(committed) {
+            ids[index] = id;
+        }
+        Ok(KLogAppendBatchResponse { ids })
+    }
+
+    /// Validate one append and return its trimmed request_id. `op` prefixes error messages.
+    fn check_append_request(
+        &self,
+        req: &KLogAppendRequest,
+        op: &str,
+    ) -> Result<Option<String>, (StatusCode, KLogErrorCode, String)> {
+        if req.message.trim().is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                format!("{} {} rejected: empty message", self.service_name, op),
+            ));
+        }
+
+        if req.message.len() > DATA_APPEND_MAX_MESSAGE_BYTES {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                KLogErrorCode::PayloadTooLarge,
+                format!(
+                    "{} {} rejected: message too large, bytes={}, max_bytes={}",
+                    self.service_name,
+                    op,
+                    req.message.len(),
+                    DATA_APPEND_MAX_MESSAGE_BYTES
+                ),
+            ));
+        }
+
+        let request_id = req
+            .request_id
+            .as_ref()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        if let Some(request_id) = request_id.as_ref()
+            && request_id.len() > DATA_APPEND_MAX_REQUEST_ID_BYTES
+        {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                format!(
+                    "{} {} rejected: request_id too large, bytes={}, max_bytes={}",
+                    self.service_name,
+                    op,
+                    request_id.len(),
+                    DATA_APPEND_MAX_REQUEST_ID_BYTES
+                ),
+            ));
+        }
+        Ok(request_id)
+    }
+
+    pub async fn put_meta(
+        &self,
+        headers: &HeaderMap,
+        req: KLogMetaPutRequest,
+    ) -> KServiceResult<KLogMetaPutResponse> {
+        let trace_id = self.resolve_trace_id(headers);
+        let key = req.key.trim().to_string();
+        if key.is_empty() {
+            let msg = format!("{} meta put rejected: empty key", self.service_name);
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            ));
+        }
+        if is_reserved_meta_key(&key) {
+            let msg = format!(
+                "{} meta put rejected: key={} uses reserved prefix {}",
+                self.service_name, key, KLOG_RESERVED_META_PREFIX
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            ));
+        }
+        if key.len() > META_KEY_MAX_BYTES {
+            let msg = format!(
+                "{} meta put rejected: key too large, bytes={}, max_bytes={}",
+                self.service_name,
+                key.len(),
+                META_KEY_MAX_BYTES
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            ));
+        }
+        if req.value.len() > META_VALUE_MAX_BYTES {
+            let msg = format!(
+                "{} meta put rejected: value too large, bytes={}, max_bytes={}",
+                self.service_name,
+                req.value.len(),
+                META_VALUE_MAX_BYTES
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                KLogErrorCode::PayloadTooLarge,
+                msg,
+                &trace_id,
+            ));
+        }
+
+        let forward_hops = self
+            .parse_forward_hops(headers, "meta put")
+            .map_err(|msg| {
+                error!("{}", msg);
+                self.service_error(
+                    StatusCode::BAD_REQUEST,
+                    KLogErrorCode::InvalidArgument,
+                    msg,
+                    &trace_id,
+                )
+            })?;
+        let forwarded_by = headers
+            .get(KLOG_FORWARDED_BY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("-");
+        if forward_hops > META_RW_MAX_FORWARD_HOPS {
+            let msg = format!(
+                "{} meta put rejected: too many forward hops, hops={}, max_hops={}, forwarded_by={}",
+                self.service_name, forward_hops, META_RW_MAX_FORWARD_HOPS, forwarded_by
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_GATEWAY,
+                KLogErrorCode::LeaderUnavailable,
+                msg,
+                &trace_id,
+            ));
+        }
+
+        let metrics = self.raft.metrics().borrow().clone();
+        let local_node_id = metrics.id;
+        let expected_revision = req.expected_revision;
+        let item = KLogMetaEntry {
+            key: key.clone(),
+            value: req.value.clone(),
+            // Meta write audit fields are owned by the raft service, not client input.
+            updated_at: now_millis(),
+            updated_by: local_node_id,
+            revision: 0,
+            lease_id: req.lease_id,
+        };
+        info!(
+            "{} meta put request: trace_id={}, key={}, value_len={}, updated_at={}, updated_by={}, expected_revision={:?}, lease_id={:?}, local_node_id={}, current_leader={:?}, forward_hops={}, forwarded_by={}",
+            self.service_name,
+            trace_id,
+            item.key,
+            item.value.len(),
+            item.updated_at,
+            item.updated_by,
+            expected_revision,
+            item.lease_id,
+            local_node_id,
+            metrics.current_leader,
+            forward_hops,
+            forwarded_by
+        );
+
+        match self
+            .raft
+            .client_write(KLogRequest::PutMeta {
+                item,
+                expected_revision,
+            })
+            .await
+        {
+            Ok(resp) => match resp.data {
+                KLogResponse::MetaPutOk { key, revision } => {
+                    info!(
+                        "{} meta put committed: key={}, revision={}",
+                        self.service_name, key, revision
+                    );
+                    Ok(KLogMetaPutResponse { key, revision })
+                }
+                KLogResponse::MetaPutConflict {
+                    key,
+                    expected_revision,
+                    current_revision,
+                } => {
+                    let msg = format!(
+                        "{} meta put version conflict: key={}, expected_revision={}, current_revision={:?}",
+                        self.service_name, key, expected_revision, current_revision
+                    );
+                    warn!("{}", msg);
+                    Err(self.service_error(
+                        StatusCode::CONFLICT,
+                        KLogErrorCode::VersionConflict,
+                        msg,
+                        &trace_id,
+                    ))
+                }
+                KLogResponse::LeaseNotFound { lease_id } => {
+                    let msg = format!(
+                        "{} meta put rejected: key={}, lease_id={} not found or expired",
+                        self.service_name, key, lease_id
+                    );
+                    warn!("{}", msg);
+                    Err(self.service_error(
+                        StatusCode::NOT_FOUND,
+                        KLogErrorCode::NotFound,
+                        msg,
+                        &trace_id,
+                    ))
+                }
+                KLogResponse::Err(err_msg) => {
+                    let msg = format!(
+                        "{} meta put failed in state machine: key={}, err={}",
+                        self.service_name, key, err_msg
+                    );
+                    error!("{}", msg);
+                    Err(self.service_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        KLogErrorCode::Internal,
+                        msg,
+                        &trace_id,
+                    ))
+                }
+                other => {
+                    let msg = format!(
+                        "{} meta put unexpected response: key={}, response={:?}",
+                        self.service_name, key, other
+                    );
+                    error!("{}", msg);
+                    Err(self.service_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        KLogErrorCode::Internal,
+                        msg,
+                        &trace_id,
+                    ))
+                }
+            },
+            Err(err) => {
+                if let Some(forward) = err.forward_to_leader::<KNode>() {
+                    if forward_hops >= META_RW_MAX_FORWARD_HOPS {
+                        let msg = format!(
+                            "{} meta put forward aborted due to hop limit: local_node_id={}, key={}, leader_id={:?}, leader_node={:?}, hops={}, max_hops={}",
+                            self.service_name,
+                            local_node_id,
+                            key,
+                            forward.leader_id,
+                            forward.leader_node,
+                            forward_hops,
+                            META_RW_MAX_FORWARD_HOPS
+                        );
+                        error!("{}", msg);
+                        return Err(self.service_error(
+                            StatusCode::BAD_GATEWAY,
+                            KLogErrorCode::LeaderUnavailable,
+                            msg,
+                            &trace_id,
+                        ));
+                    }
+
+                    let leader_node = forward.leader_node.clone().or_else(|| {
+                        forward.leader_id.and_then(|leader_id| {
+                            metrics
+                                .membership_config
+                                .nodes()
+                                .find_map(|(id, node)| (*id == leader_id).then_some(node.clone()))
+                        })
+                    });
+                    let Some(leader_node) = leader_node else {
+                        let msg = format!(
+                            "{} meta put can not resolve leader node for forwarding: local_node_id={}, key={}, leader_id={:?}",
+                            self.service_name, local_node_id, key, forward.leader_id
+                        );
+                        warn!("{}", msg);
+                        return Err(self
+                            .service_error(
+                                StatusCode::SERVICE_UNAVAILABLE,
+                                KLogErrorCode::LeaderUnavailable,
+                                msg,
+                                &trace_id,
+                            )
+                            .with_leader_hint(forward.leader_node.clone()));
+                    };
+
+                    let target_hops = forward_hops + 1;
+                    warn!(
+                        "{} meta put forwarding to leader: local_node_id={}, key={}, leader_id={}, leader_addr={}:{}, hops={} -> {}",
+                        self.service_name,
+                        local_node_id,
+                        key,
+                        leader_node.id,
+                        leader_node.addr,
+                        leader_node.port,
+                        forward_hops,
+                        target_hops
+                    );
+                    match self
+                        .data_client
+                        .put_meta_to_node(
+                            &leader_node,
+                            &KLogMetaPutRequest {
+                                key: key.clone(),
+                                value: req.value,
+                                expected_revision: req.expected_revision,
+                                lease_id: req.lease_id,
+                            },
+                            target_hops,
+                            local_node_id,
+                            &trace_id,
+                        )
+                        .await
+                    {
+                        Ok(resp) => {
+                            info!(
+                                "{} meta put forwarded and committed: trace_id={}, local_node_id={}, key={}, leader_id={}, hops={}",
+                                self.service_name,
+                                trace_id,
                                 local_node_id,
                                 resp.key,
                                 leader_node.id,
@@ -638,6 +982,19 @@ impl KLogWriteService {
                 &trace_id,
             ));
         }
+        if is_reserved_meta_key(&key) {
+            let msg = format!(
+                "{} meta delete rejected: key={} uses reserved prefix {}",
+                self.service_name, key, KLOG_RESERVED_META_PREFIX
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            ));
+        }
 
         let forward_hops = self
             .parse_forward_hops(headers, "meta delete")
@@ -844,6 +1201,597 @@ impl KLogWriteService {
         }
     }
 
+    /// Apply a multi-key meta transaction on the leader, forwarding like `put_meta`.
+    pub async fn txn(
+        &self,
+        headers: &HeaderMap,
+        req: KLogTxnRequest,
+    ) -> KServiceResult<KLogTxnResponse> {
+        let trace_id = self.resolve_trace_id(headers);
+        let req = self
+            .normalize_txn_request(req)
+            .map_err(|(status, code, msg)| {
+                error!("{}", msg);
+                self.service_error(status, code, msg, &trace_id)
+            })?;
+
+        let forward_hops = self.parse_forward_hops(headers, "txn").map_err(|msg| {
+            error!("{}", msg);
+            self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            )
+        })?;
+        let forwarded_by = headers
+            .get(KLOG_FORWARDED_BY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("-");
+        if forward_hops > META_RW_MAX_FORWARD_HOPS {
+            let msg = format!(
+                "{} txn rejected: too many forward hops, hops={}, max_hops={}, forwarded_by={}",
+                self.service_name, forward_hops, META_RW_MAX_FORWARD_HOPS, forwarded_by
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_GATEWAY,
+                KLogErrorCode::LeaderUnavailable,
+                msg,
+                &trace_id,
+            ));
+        }
+
+        let metrics = self.raft.metrics().borrow().clone();
+        let local_node_id = metrics.id;
+        let updated_at = now_millis();
+        info!(
+            "{} txn request: trace_id={}, compares={}, then_ops={}, else_ops={}, local_node_id={}, current_leader={:?}, forward_hops={}, forwarded_by={}",
+            self.service_name,
+            trace_id,
+            req.compare.len(),
+            req.then_ops.len(),
+            req.else_ops.len(),
+            local_node_id,
+            metrics.current_leader,
+            forward_hops,
+            forwarded_by
+        );
+
+        match self
+            .raft
+            .client_write(KLogRequest::Txn {
+                compare: req.compare.clone(),
+                then_ops: txn_ops(&req.then_ops, updated_at, local_node_id),
+                else_ops: txn_ops(&req.else_ops, updated_at, local_node_id),
+            })
+            .await
+        {
+            Ok(resp) => match resp.data {
+                KLogResponse::TxnOk { succeeded, results } => {
+                    info!(
+                        "{} txn committed: log_id={}, succeeded={}, results={}",
+                        self.service_name,
+                        resp.log_id,
+                        succeeded,
+                        results.len()
+                    );
+                    Ok(KLogTxnResponse { succeeded, results })
+                }
+                KLogResponse::LeaseNotFound { lease_id } => {
+                    let msg = format!(
+                        "{} txn rejected: lease_id={} not found or expired",
+                        self.service_name, lease_id
+                    );
+                    warn!("{}", msg);
+                    Err(self.service_error(
+                        StatusCode::NOT_FOUND,
+                        KLogErrorCode::NotFound,
+                        msg,
+                        &trace_id,
+                    ))
+                }
+                KLogResponse::Err(err_msg) => {
+                    let msg = format!(
+                        "{} txn failed in state machine: err={}",
+                        self.service_name, err_msg
+                    );
+                    error!("{}", msg);
+                    Err(self.service_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        KLogErrorCode::Internal,
+                        msg,
+                        &trace_id,
+                    ))
+                }
+                other => {
+                    let msg = format!(
+                        "{} txn unexpected response: response={:?}",
+                        self.service_name, other
+                    );
+                    error!("{}", msg);
+                    Err(self.service_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        KLogErrorCode::Internal,
+                        msg,
+                        &trace_id,
+                    ))
+                }
+            },
+            Err(err) => {
+                if let Some(forward) = err.forward_to_leader::<KNode>() {
+                    if forward_hops >= META_RW_MAX_FORWARD_HOPS {
+                        let msg = format!(
+                            "{} txn forward aborted due to hop limit: local_node_id={}, leader_id={:?}, leader_node={:?}, hops={}, max_hops={}",
+                            self.service_name,
+                            local_node_id,
+                            forward.leader_id,
+                            forward.leader_node,
+                            forward_hops,
+                            META_RW_MAX_FORWARD_HOPS
+                        );
+                        error!("{}", msg);
+                        return Err(self.service_error(
+                            StatusCode::BAD_GATEWAY,
+                            KLogErrorCode::LeaderUnavailable,
+                            msg,
+                            &trace_id,
+                        ));
+                    }
+
+                    let leader_node = forward.leader_node.clone().or_else(|| {
+                        forward.leader_id.and_then(|leader_id| {
+                            metrics
+                                .membership_config
+                                .nodes()
+                                .find_map(|(id, node)| (*id == leader_id).then_some(node.clone()))
+                        })
+                    });
+                    let Some(leader_node) = leader_node else {
+                        let msg = format!(
+                            "{} txn can not resolve leader node for forwarding: local_node_id={}, leader_id={:?}",
+                            self.service_name, local_node_id, forward.leader_id
+                        );
+                        warn!("{}", msg);
+                        return Err(self
+                            .service_error(
+                                StatusCode::SERVICE_UNAVAILABLE,
+                                KLogErrorCode::LeaderUnavailable,
+                                msg,
+                                &trace_id,
+                            )
+                            .with_leader_hint(forward.leader_node.clone()));
+                    };
+
+                    let target_hops = forward_hops + 1;
+                    warn!(
+                        "{} txn forwarding to leader: local_node_id={}, leader_id={}, leader_addr={}:{}, hops={} -> {}",
+                        self.service_name,
+                        local_node_id,
+                        leader_node.id,
+                        leader_node.addr,
+                        leader_node.port,
+                        forward_hops,
+                        target_hops
+                    );
+                    match self
+                        .data_client
+                        .txn_to_node(&leader_node, &req, target_hops, local_node_id, &trace_id)
+                        .await
+                    {
+                        Ok(resp) => {
+                            info!(
+                                "{} txn forwarded and committed: trace_id={}, local_node_id={}, succeeded={}, leader_id={}, hops={}",
+                                self.service_name,
+                                trace_id,
+                                local_node_id,
+                                resp.succeeded,
+                                leader_node.id,
+                                target_hops
+                            );
+                            Ok(resp)
+                        }
+                        Err(mut forward_err) => {
+                            let msg = format!(
+                                "{} txn forward failed: local_node_id={}, leader_id={}, err={}",
+                                self.service_name, local_node_id, leader_node.id, forward_err
+                            );
+                            error!("{}", msg);
+                            forward_err.http_status = StatusCode::BAD_GATEWAY.as_u16();
+                            forward_err.error.message = msg;
+                            if forward_err.error.leader_hint.is_none() {
+                                forward_err.error.leader_hint = Some(leader_node);
+                            }
+                            Err(forward_err)
+                        }
+                    }
+                } else {
+                    let msg = format!(
+                        "{} txn raft client_write failed: err={}",
+                        self.service_name, err
+                    );
+                    error!("{}", msg);
+                    Err(self.service_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        KLogErrorCode::Internal,
+                        msg,
+                        &trace_id,
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Trim keys and apply the same limits as the single-key meta APIs. Each branch may
+    /// write a key at most once, so per-op results stay unambiguous.
+    fn normalize_txn_request(
+        &self,
+        req: KLogTxnRequest,
+    ) -> Result<KLogTxnRequest, (StatusCode, KLogErrorCode, String)> {
+        let invalid = |msg: String| (StatusCode::BAD_REQUEST, KLogErrorCode::InvalidArgument, msg);
+        let check_key =
+            |key: &str, what: &str| -> Result<String, (StatusCode, KLogErrorCode, String)> {
+                let key = key.trim();
+                if key.is_empty() {
+                    return Err(invalid(format!(
+                        "{} txn rejected: empty key in {}",
+                        self.service_name, what
+                    )));
+                }
+                if key.len() > META_KEY_MAX_BYTES {
+                    return Err(invalid(format!(
+                        "{} txn rejected: key too large in {}, bytes={}, max_bytes={}",
+                        self.service_name,
+                        what,
+                        key.len(),
+                        META_KEY_MAX_BYTES
+                    )));
+                }
+                Ok(key.to_string())
+            };
+
+        if req.compare.len() > TXN_MAX_COMPARES {
+            return Err(invalid(format!(
+                "{} txn rejected: too many compares, count={}, max={}",
+                self.service_name,
+                req.compare.len(),
+                TXN_MAX_COMPARES
+            )));
+        }
+        let mut compare = Vec::with_capacity(req.compare.len());
+        for item in req.compare {
+            let key = check_key(item.key(), "compare")?;
+            compare.push(match item {
+                KLogTxnCompare::RevisionEquals { revision, .. } => {
+                    KLogTxnCompare::RevisionEquals { key, revision }
+                }
+                KLogTxnCompare::Exists { .. } => KLogTxnCompare::Exists { key },
+                KLogTxnCompare::Absent { .. } => KLogTxnCompare::Absent { key },
+            });
+        }
+
+        let mut branches = Vec::with_capacity(2);
+        for (what, ops) in [("then", req.then_ops), ("else", req.else_ops)] {
+            if ops.len() > TXN_MAX_OPS {
+                return Err(invalid(format!(
+                    "{} txn rejected: too many {} ops, count={}, max={}",
+                    self.service_name,
+                    what,
+                    ops.len(),
+                    TXN_MAX_OPS
+                )));
+            }
+            let mut written = BTreeSet::new();
+            let mut normalized = Vec::with_capacity(ops.len());
+            for op in ops {
+                let key = check_key(op.key(), what)?;
+                if !matches!(op, KLogTxnRequestOp::Get { .. }) {
+                    if is_reserved_meta_key(&key) {
+                        return Err(invalid(format!(
+                            "{} txn rejected: key={} uses reserved prefix {}",
+                            self.service_name, key, KLOG_RESERVED_META_PREFIX
+                        )));
+                    }
+                    if !written.insert(key.clone()) {
+                        return Err(invalid(format!(
+                            "{} txn rejected: key={} written more than once in {} ops",
+                            self.service_name, key, what
+                        )));
+                    }
+                }
+                normalized.push(match op {
+                    KLogTxnRequestOp::Put {
+                        value, lease_id, ..
+                    } => {
+                        if value.len() > META_VALUE_MAX_BYTES {
+                            return Err((
+                                StatusCode::PAYLOAD_TOO_LARGE,
+                                KLogErrorCode::PayloadTooLarge,
+                                format!(
+                                    "{} txn rejected: value too large, key={}, bytes={}, max_bytes={}",
+                                    self.service_name,
+                                    key,
+                                    value.len(),
+                                    META_VALUE_MAX_BYTES
+                                ),
+                            ));
+                        }
+                        KLogTxnRequestOp::Put {
+                            key,
+                            value,
+                            lease_id,
+                        }
+                    }
+                    KLogTxnRequestOp::Delete { .. } => KLogTxnRequestOp::Delete { key },
+                    KLogTxnRequestOp::Get { .. } => KLogTxnRequestOp::Get { key },
+                });
+            }
+            branches.push(normalized);
+        }
+        let else_ops = branches.pop().unwrap_or_default();
+        let then_ops = branches.pop().unwrap_or_default();
+        Ok(KLogTxnRequest {
+            compare,
+            then_ops,
+            else_ops,
+        })
+    }
+
+    /// Grant, keep alive or revoke a lease. Lease times come from the leader clock, so
+    /// followers forward to the leader rather than proposing their own timestamps.
+    pub async fn lease(
+        &self,
+        headers: &HeaderMap,
+        req: KLogLeaseRequest,
+    ) -> KServiceResult<KLogLeaseResponse> {
+        let trace_id = self.resolve_trace_id(headers);
+        let op = req.as_str().to_string();
+        if let KLogLeaseRequest::Grant { ttl_ms } = &req
+            && let Err(err) = validate_lease_ttl(*ttl_ms)
+        {
+            let msg = format!("{} lease grant rejected: {}", self.service_name, err);
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            ));
+        }
+
+        let forward_hops = self.parse_forward_hops(headers, "lease").map_err(|msg| {
+            error!("{}", msg);
+            self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            )
+        })?;
+        let forwarded_by = headers
+            .get(KLOG_FORWARDED_BY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("-");
+        if forward_hops > META_RW_MAX_FORWARD_HOPS {
+            let msg = format!(
+                "{} lease {} rejected: too many forward hops, hops={}, max_hops={}, forwarded_by={}",
+                self.service_name, op, forward_hops, META_RW_MAX_FORWARD_HOPS, forwarded_by
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_GATEWAY,
+                KLogErrorCode::LeaderUnavailable,
+                msg,
+                &trace_id,
+            ));
+        }
+
+        let metrics = self.raft.metrics().borrow().clone();
+        let local_node_id = metrics.id;
+        info!(
+            "{} lease request: trace_id={}, op={}, lease_id={:?}, local_node_id={}, current_leader={:?}, forward_hops={}, forwarded_by={}",
+            self.service_name,
+            trace_id,
+            op,
+            req.lease_id(),
+            local_node_id,
+            metrics.current_leader,
+            forward_hops,
+            forwarded_by
+        );
+
+        if metrics.current_leader == Some(local_node_id) {
+            let now_ms = now_millis();
+            let raft_req = match req {
+                KLogLeaseRequest::Grant { ttl_ms } => KLogRequest::GrantLease {
+                    ttl_ms,
+                    granted_at: now_ms,
+                    granted_by: local_node_id,
+                },
+                KLogLeaseRequest::KeepAlive { lease_id } => {
+                    KLogRequest::KeepAliveLease { lease_id, now_ms }
+                }
+                KLogLeaseRequest::Revoke { lease_id } => KLogRequest::RevokeLease { lease_id },
+            };
+            match self.raft.client_write(raft_req).await {
+                Ok(resp) => return self.lease_response(&op, resp.data, &trace_id),
+                Err(err) => {
+                    if err.forward_to_leader::<KNode>().is_none() {
+                        let msg = format!(
+                            "{} lease {} raft client_write failed: err={}",
+                            self.service_name, op, err
+                        );
+                        error!("{}", msg);
+                        return Err(self.service_error(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            KLogErrorCode::Internal,
+                            msg,
+                            &trace_id,
+                        ));
+                    }
+                    // Lost leadership meanwhile, fall through and forward to the new leader.
+                }
+            }
+        }
+
+        if forward_hops >= META_RW_MAX_FORWARD_HOPS {
+            let msg = format!(
+                "{} lease {} forward aborted due to hop limit: local_node_id={}, leader_id={:?}, hops={}, max_hops={}",
+                self.service_name,
+                op,
+                local_node_id,
+                metrics.current_leader,
+                forward_hops,
+                META_RW_MAX_FORWARD_HOPS
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_GATEWAY,
+                KLogErrorCode::LeaderUnavailable,
+                msg,
+                &trace_id,
+            ));
+        }
+
+        let metrics = self.raft.metrics().borrow().clone();
+        let leader_node = metrics.current_leader.and_then(|leader_id| {
+            metrics
+                .membership_config
+                .nodes()
+                .find_map(|(id, node)| (*id == leader_id).then_some(node.clone()))
+        });
+        let Some(leader_node) = leader_node.filter(|node| node.id != local_node_id) else {
+            let msg = format!(
+                "{} lease {} can not resolve leader node for forwarding: local_node_id={}, leader_id={:?}",
+                self.service_name, op, local_node_id, metrics.current_leader
+            );
+            warn!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                KLogErrorCode::LeaderUnavailable,
+                msg,
+                &trace_id,
+            ));
+        };
+
+        let target_hops = forward_hops + 1;
+        warn!(
+            "{} lease {} forwarding to leader: local_node_id={}, leader_id={}, leader_addr={}:{}, hops={} -> {}",
+            self.service_name,
+            op,
+            local_node_id,
+            leader_node.id,
+            leader_node.addr,
+            leader_node.port,
+            forward_hops,
+            target_hops
+        );
+        match self
+            .data_client
+            .lease_to_node(&leader_node, &req, target_hops, local_node_id, &trace_id)
+            .await
+        {
+            Ok(resp) => {
+                info!(
+                    "{} lease {} forwarded and committed: trace_id={}, local_node_id={}, lease_id={}, leader_id={}, hops={}",
+                    self.service_name,
+                    op,
+                    trace_id,
+                    local_node_id,
+                    resp.lease.id,
+                    leader_node.id,
+                    target_hops
+                );
+                Ok(resp)
+            }
+            Err(mut forward_err) => {
+                let msg = format!(
+                    "{} lease {} forward failed: local_node_id={}, leader_id={}, err={}",
+                    self.service_name, op, local_node_id, leader_node.id, forward_err
+                );
+                error!("{}", msg);
+                forward_err.http_status = StatusCode::BAD_GATEWAY.as_u16();
+                forward_err.error.message = msg;
+                if forward_err.error.leader_hint.is_none() {
+                    forward_err.error.leader_hint = Some(leader_node);
+                }
+                Err(forward_err)
+            }
+        }
+    }
+
+    fn lease_response(
+        &self,
+        op: &str,
+        resp: KLogResponse,
+        trace_id: &str,
+    ) -> KServiceResult<KLogLeaseResponse> {
+        match resp {
+            KLogResponse::LeaseOk { lease } => {
+                info!(
+                    "{} lease {} committed: lease_id={}, ttl_ms={}, expires_at={}",
+                    self.service_name, op, lease.id, lease.ttl_ms, lease.expires_at
+                );
+                Ok(KLogLeaseResponse {
+                    lease,
+                    deleted_keys: Vec::new(),
+                })
+            }
+            KLogResponse::LeaseRevoked {
+                lease,
+                deleted_keys,
+            } => {
+                info!(
+                    "{} lease {} committed: lease_id={}, deleted_keys={:?}",
+                    self.service_name, op, lease.id, deleted_keys
+                );
+                Ok(KLogLeaseResponse {
+                    lease,
+                    deleted_keys,
+                })
+            }
+            KLogResponse::LeaseNotFound { lease_id } => {
+                let msg = format!(
+                    "{} lease {} rejected: lease_id={} not found or expired",
+                    self.service_name, op, lease_id
+                );
+                warn!("{}", msg);
+                Err(self.service_error(
+                    StatusCode::NOT_FOUND,
+                    KLogErrorCode::NotFound,
+                    msg,
+                    trace_id,
+                ))
+            }
+            KLogResponse::Err(err_msg) => {
+                let msg = format!(
+                    "{} lease {} failed in state machine: err={}",
+                    self.service_name, op, err_msg
+                );
+                error!("{}", msg);
+                Err(self.service_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    KLogErrorCode::Internal,
+                    msg,
+                    trace_id,
+                ))
+            }
+            other => {
+                let msg = format!(
+                    "{} lease {} unexpected response: response={:?}",
+                    self.service_name, op, other
+                );
+                error!("{}", msg);
+                Err(self.service_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    KLogErrorCode::Internal,
+                    msg,
+                    trace_id,
+                ))
+            }
+        }
+    }
+
     fn parse_forward_hops(&self, headers: &HeaderMap, op: &str) -> Result<u32, String> {
         let Some(raw) = headers.get(KLOG_FORWARD_HOPS_HEADER) else {
             return Ok(0);
@@ -1118,8 +2066,69 @@ impl KLogQueryService {
                 &trace_id,
             ));
         }
+        if let (Some(start_time), Some(end_time)) = (query.start_time, query.end_time)
+            && start_time > end_time
+        {
+            let msg = format!(
+                "{} data query invalid time range: start_time={} > end_time={}",
+                self.service_name, start_time, end_time
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            ));
+        }
+        let attrs = match parse_attr_filters(query.attrs.as_deref().unwrap_or_default()) {
+            Ok(attrs) => attrs,
+            Err(e) => {
+                let msg = format!(
+                    "{} data query invalid attrs filter: {}",
+                    self.service_name, e
+                );
+                error!("{}", msg);
+                return Err(self.service_error(
+                    StatusCode::BAD_REQUEST,
+                    KLogErrorCode::InvalidArgument,
+                    msg,
+                    &trace_id,
+                ));
+            }
+        };
+        let message_contains = query
+            .message_contains
+            .as_deref()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        let message_terms = query
+            .message_terms
+            .as_deref()
+            .map(tokenize_message)
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<Vec<_>>();
+        if message_terms.is_empty()
+            && query
+                .message_terms
+                .as_deref()
+                .is_some_and(|v| !v.trim().is_empty())
+        {
+            let msg = format!(
+                "{} data query invalid message_terms: no words in {:?}",
+                self.service_name, query.message_terms
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            ));
+        }
         info!(
-            "{} data query request: trace_id={}, strong_read={}, start_id={:?}, end_id={:?}, limit={}, order={:?}, level={:?}, source={:?}, attr_key={:?}, attr_value={:?}, forward_hops={}, forwarded_by={}",
+            "{} data query request: trace_id={}, strong_read={}, start_id={:?}, end_id={:?}, limit={}, order={:?}, level={:?}, source={:?}, attr_key={:?}, attr_value={:?}, start_time={:?}, end_time={:?}, attrs={:?}, message_contains={:?}, message_terms={:?}, forward_hops={}, forwarded_by={}",
             self.service_name,
             trace_id,
             strong_read,
@@ -1131,6 +2140,11 @@ impl KLogQueryService {
             source.as_deref(),
             attr_key.as_deref(),
             attr_value.as_deref(),
+            query.start_time,
+            query.end_time,
+            attrs,
+            message_contains.as_deref(),
+            message_terms,
             forward_hops,
             forwarded_by
         );
@@ -1146,6 +2160,11 @@ impl KLogQueryService {
                 source,
                 attr_key,
                 attr_value,
+                start_time: query.start_time,
+                end_time: query.end_time,
+                attrs,
+                message_contains,
+                message_terms,
             })
             .await
             .map_err(|e| {
@@ -1428,6 +2447,275 @@ impl KLogQueryService {
         Ok(KLogMetaQueryResponse { items })
     }
 
+    /// Meta watch is served from the local replica: every node applies the same changes at
+    /// the same revisions, so a watcher can reconnect to any node and resume its cursor.
+    pub async fn watch_meta(
+        &self,
+        headers: &HeaderMap,
+        req: KLogMetaWatchRequest,
+    ) -> KServiceResult<KLogMetaWatchResponse> {
+        let trace_id = self.resolve_trace_id(headers);
+        let prefix = req
+            .prefix
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        if let Some(prefix) = prefix.as_ref()
+            && prefix.len() > META_KEY_MAX_BYTES
+        {
+            let msg = format!(
+                "{} meta watch invalid prefix length: prefix_bytes={}, max_bytes={}",
+                self.service_name,
+                prefix.len(),
+                META_KEY_MAX_BYTES
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            ));
+        }
+        let (limit, wait_ms) =
+            self.resolve_watch_window(req.limit, req.wait_ms, "meta watch", &trace_id)?;
+
+        let hub = self.state_store_manager.watch_hub();
+        // Subscribe before the first scan so a change applied in between still wakes us up.
+        let mut revision_rx = hub.subscribe();
+        let mut after_revision = req.after_revision.unwrap_or_else(|| hub.current_revision());
+        info!(
+            "{} meta watch request: trace_id={}, prefix={:?}, after_revision={}, limit={}, wait_ms={}",
+            self.service_name,
+            trace_id,
+            prefix.as_deref(),
+            after_revision,
+            limit,
+            wait_ms
+        );
+
+        let mut deadline = tokio::time::Instant::now() + Duration::from_millis(wait_ms);
+        loop {
+            match hub.meta_events_after(after_revision, prefix.as_deref(), limit) {
+                KLogMetaWatchBatch::Compacted {
+                    compacted_revision,
+                    revision,
+                } => {
+                    warn!(
+                        "{} meta watch cursor compacted: trace_id={}, after_revision={}, compacted_revision={}, revision={}",
+                        self.service_name, trace_id, after_revision, compacted_revision, revision
+                    );
+                    return Ok(KLogMetaWatchResponse {
+                        events: Vec::new(),
+                        revision,
+                        compacted: true,
+                    });
+                }
+                KLogMetaWatchBatch::Events { events, revision } => {
+                    if !events.is_empty() || tokio::time::Instant::now() >= deadline {
+                        debug!(
+                            "{} meta watch response: trace_id={}, events={}, revision={}",
+                            self.service_name,
+                            trace_id,
+                            events.len(),
+                            revision
+                        );
+                        return Ok(KLogMetaWatchResponse {
+                            events,
+                            revision,
+                            compacted: false,
+                        });
+                    }
+                    after_revision = revision;
+                }
+            }
+
+            if !matches!(
+                tokio::time::timeout_at(deadline, revision_rx.changed()).await,
+                Ok(Ok(()))
+            ) {
+                deadline = tokio::time::Instant::now();
+            }
+        }
+    }
+
+    /// Tail new entries matching the filter. Entries are read back from the store, so the
+    /// `after_id` cursor survives restarts as long as retention has not dropped the range.
+    pub async fn watch_log(
+        &self,
+        headers: &HeaderMap,
+        req: KLogLogWatchRequest,
+    ) -> KServiceResult<KLogLogWatchResponse> {
+        let trace_id = self.resolve_trace_id(headers);
+        let source = req
+            .source
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        let attr_key = req
+            .attr_key
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        let attr_value = req
+            .attr_value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        if attr_key.is_none() && attr_value.is_some() {
+            let msg = format!(
+                "{} log watch invalid attrs filter: attr_value is set but attr_key is empty",
+                self.service_name
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                &trace_id,
+            ));
+        }
+        let (limit, wait_ms) =
+            self.resolve_watch_window(req.limit, req.wait_ms, "log watch", &trace_id)?;
+
+        let mut revision_rx = self.state_store_manager.watch_hub().subscribe();
+        let mut after_id = match req.after_id {
+            Some(after_id) => after_id,
+            None => self.newest_entry_id(&trace_id).await?,
+        };
+        info!(
+            "{} log watch request: trace_id={}, after_id={}, limit={}, wait_ms={}, level={:?}, source={:?}, attr_key={:?}, attr_value={:?}",
+            self.service_name,
+            trace_id,
+            after_id,
+            limit,
+            wait_ms,
+            req.level,
+            source.as_deref(),
+            attr_key.as_deref(),
+            attr_value.as_deref()
+        );
+
+        let mut deadline = tokio::time::Instant::now() + Duration::from_millis(wait_ms);
+        loop {
+            // Everything up to `newest` is visible to the query below, which lets the cursor
+            // move past entries that did not match the filter.
+            let newest = self.newest_entry_id(&trace_id).await?;
+            let items = self
+                .state_store_manager
+                .query_entries(KLogQuery {
+                    start_id: Some(after_id.saturating_add(1)),
+                    end_id: None,
+                    limit,
+                    order: KLogQueryOrder::Asc,
+                    level: req.level,
+                    source: source.clone(),
+                    attr_key: attr_key.clone(),
+                    attr_value: attr_value.clone(),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| {
+                    let msg = format!("{} log watch query failed: {}", self.service_name, e);
+                    error!("{}", msg);
+                    self.service_error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        KLogErrorCode::Internal,
+                        msg,
+                        &trace_id,
+                    )
+                })?;
+
+            let last_item_id = items.last().map(|e| e.id).unwrap_or(after_id);
+            let last_id = if items.len() >= limit {
+                last_item_id
+            } else {
+                last_item_id.max(newest).max(after_id)
+            };
+            if !items.is_empty() || tokio::time::Instant::now() >= deadline {
+                debug!(
+                    "{} log watch response: trace_id={}, items={}, last_id={}",
+                    self.service_name,
+                    trace_id,
+                    items.len(),
+                    last_id
+                );
+                return Ok(KLogLogWatchResponse { items, last_id });
+            }
+            after_id = last_id;
+
+            if !matches!(
+                tokio::time::timeout_at(deadline, revision_rx.changed()).await,
+                Ok(Ok(()))
+            ) {
+                deadline = tokio::time::Instant::now();
+            }
+        }
+    }
+
+    fn resolve_watch_window(
+        &self,
+        limit: Option<usize>,
+        wait_ms: Option<u64>,
+        op: &str,
+        trace_id: &str,
+    ) -> KServiceResult<(usize, u64)> {
+        let limit = limit.unwrap_or(WATCH_DEFAULT_LIMIT);
+        if limit == 0 || limit > WATCH_MAX_LIMIT {
+            let msg = format!(
+                "{} {} invalid limit: limit={}, allowed=1..={}",
+                self.service_name, op, limit, WATCH_MAX_LIMIT
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                trace_id,
+            ));
+        }
+        let wait_ms = wait_ms.unwrap_or(WATCH_DEFAULT_WAIT_MS);
+        if wait_ms > WATCH_MAX_WAIT_MS {
+            let msg = format!(
+                "{} {} invalid wait_ms: wait_ms={}, max_wait_ms={}",
+                self.service_name, op, wait_ms, WATCH_MAX_WAIT_MS
+            );
+            error!("{}", msg);
+            return Err(self.service_error(
+                StatusCode::BAD_REQUEST,
+                KLogErrorCode::InvalidArgument,
+                msg,
+                trace_id,
+            ));
+        }
+        Ok((limit, wait_ms))
+    }
+
+    async fn newest_entry_id(&self, trace_id: &str) -> KServiceResult<u64> {
+        let newest = self
+            .state_store_manager
+            .newest_entry_id()
+            .await
+            .map_err(|e| {
+                let msg = format!(
+                    "{} log watch read newest id failed: {}",
+                    self.service_name, e
+                );
+                error!("{}", msg);
+                self.service_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    KLogErrorCode::Internal,
+                    msg,
+                    trace_id,
+                )
+            })?;
+        Ok(newest.unwrap_or(0))
+    }
+
     fn parse_forward_hops(&self, headers: &HeaderMap, op: &str) -> Result<u32, String> {
         let Some(raw) = headers.get(KLOG_FORWARD_HOPS_HEADER) else {
             return Ok(0);
@@ -1465,6 +2753,68 @@ impl KLogQueryService {
     }
 }
 
+/// Fill defaults of an append request. Returns the request as forwarded to the leader and
+/// the entry to propose, still without id.
+fn normalize_append_request(
+    req: KLogAppendRequest,
+    request_id: Option<String>,
+    local_node_id: KNodeId,
+) -> (KLogAppendRequest, KLogEntry) {
+    let level = req.level.unwrap_or(KLogLevel::Info);
+    let source = req
+        .source
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string());
+    let attrs = req.attrs.unwrap_or_default();
+    let timestamp = req.timestamp.unwrap_or_else(now_millis);
+    let node_id = req.node_id.unwrap_or(local_node_id);
+    let item = KLogEntry {
+        id: 0,
+        timestamp,
+        node_id,
+        request_id: request_id.clone(),
+        level,
+        source: source.clone(),
+        attrs: attrs.clone(),
+        message: req.message.clone(),
+    };
+    let req = KLogAppendRequest {
+        message: req.message,
+        timestamp: Some(timestamp),
+        node_id: Some(node_id),
+        level: Some(level),
+        source,
+        attrs: Some(attrs),
+        request_id,
+    };
+    (req, item)
+}
+
+fn txn_ops(ops: &[KLogTxnRequestOp], updated_at: u64, updated_by: KNodeId) -> Vec<KLogTxnOp> {
+    ops.iter()
+        .map(|op| match op {
+            KLogTxnRequestOp::Put {
+                key,
+                value,
+                lease_id,
+            } => KLogTxnOp::Put {
+                item: KLogMetaEntry {
+                    key: key.clone(),
+                    value: value.clone(),
+                    updated_at,
+                    updated_by,
+                    revision: 0,
+                    lease_id: *lease_id,
+                },
+            },
+            KLogTxnRequestOp::Delete { key } => KLogTxnOp::Delete { key: key.clone() },
+            KLogTxnRequestOp::Get { key } => KLogTxnOp::Get { key: key.clone() },
+        })
+        .collect()
+}
+
 fn now_millis() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -1,8 +1,16 @@
 use super::snapshot::{KSnapshotMeta, SnapshotManager, SnapshotManagerRef};
-use crate::state_machine::snapshot::KSnapshotData;
+use crate::lease::{
+    self, apply_grant_lease, apply_keepalive_lease, apply_revoke_lease, plan_meta_lease_change,
+};
 use crate::state_store::KLogStateStoreManagerRef;
-use crate::state_store::{KLogMetaPutResult, KLogStateMachineMeta, KLogStateSnapshot};
-use crate::{KLogId, KLogRequest, KLogResponse, KNode, KNodeId, KTypeConfig, StorageResult};
+use crate::state_store::{KLogMetaWatchEvent, KLogMetaWatchEventKind, KLogStateMachineMeta};
+use crate::txn::{
+    KLogTxnCompare, KLogTxnOp, KLogTxnOpResult, KLogTxnWritePlan, evaluate_txn_compares,
+};
+use crate::{
+    KLogId, KLogMetaEntry, KLogRequest, KLogResponse, KNode, KNodeId, KResult, KTypeConfig,
+    StorageResult,
+};
 use openraft::{
     Entry, EntryPayload, OptionalSend, RaftSnapshotBuilder, SnapshotMeta, StoredMembership,
     storage::RaftStateMachine,
@@ -57,6 +65,11 @@ impl KLogStateMachine {
             StateMachineData::default()
         };
 
+        // Changes applied before this start are not replayable, watchers resume from here.
+        state_store
+            .watch_hub()
+            .reset(data.last_applied_log_id.map(|id| id.index).unwrap_or(0));
+
         Ok(Self {
             data: Arc::new(AsyncRwLock::new(data)),
             state_store,
@@ -84,7 +97,12 @@ impl KLogStateMachine {
             })
     }
 
-    async fn process_request(&self, req: KLogRequest) -> KLogResponse {
+    async fn process_request(
+        &self,
+        req: KLogRequest,
+        revision: u64,
+        data: &StateMachineData,
+    ) -> KLogResponse {
         match req {
             KLogRequest::AppendLog { item } => {
                 // Id is expected to be assigned on leader before log replication.
@@ -107,6 +125,27 @@ impl KLogStateMachine {
                     }
                 }
             }
+            KLogRequest::AppendLogBatch { items } => {
+                debug!(
+                    "StateMachine process append batch request: entries={}, first_id={:?}, last_id={:?}",
+                    items.len(),
+                    items.first().map(|item| item.id),
+                    items.last().map(|item| item.id)
+                );
+                match self.state_store.append_prepared_batch(items).await {
+                    Ok(ids) => {
+                        debug!(
+                            "StateMachine append batch request committed: entries={}",
+                            ids.len()
+                        );
+                        KLogResponse::AppendBatchOk { ids }
+                    }
+                    Err(err) => {
+                        error!("StateMachine append batch request failed: {}", err);
+                        KLogResponse::Err(err.to_string())
+                    }
+                }
+            }
             KLogRequest::PutMeta {
                 item,
                 expected_revision,
@@ -120,66 +159,406 @@ impl KLogStateMachine {
                     expected_revision
                 );
                 let key = item.key.clone();
+                match self
+                    .apply_meta_put(item, expected_revision, revision, data)
+                    .await
+                {
+                    Ok(resp) => resp,
+                    Err(err) => {
+                        error!("StateMachine put-meta request failed: key={}, {}", key, err);
+                        KLogResponse::Err(err.to_string())
+                    }
+                }
+            }
+            KLogRequest::DeleteMeta { key } => {
+                debug!("StateMachine process delete-meta request: key={}", key);
+                match self.apply_meta_delete(key.clone(), revision, data).await {
+                    Ok(resp) => resp,
+                    Err(err) => {
+                        error!(
+                            "StateMachine delete-meta request failed: key={}, {}",
+                            key, err
+                        );
+                        KLogResponse::Err(err.to_string())
+                    }
+                }
+            }
+            KLogRequest::TruncateBefore { before_id, source } => {
+                debug!(
+                    "StateMachine process truncate request: before_id={}, source={:?}",
+                    before_id, source
+                );
                 match self
                     .state_store
-                    .put_meta_entry_with_expected_revision(item, expected_revision)
+                    .truncate_entries_before(before_id, source.as_deref())
                     .await
                 {
-                    Ok(KLogMetaPutResult::Stored(stored)) => {
+                    Ok(removed) => {
                         debug!(
-                            "StateMachine put-meta request committed: key={}, revision={}",
-                            key, stored.revision
+                            "StateMachine truncate request committed: before_id={}, source={:?}, removed={}",
+                            before_id, source, removed
                         );
-                        KLogResponse::MetaPutOk {
-                            key,
-                            revision: stored.revision,
+                        KLogResponse::TruncateOk {
+                            before_id,
+                            source,
+                            removed,
                         }
                     }
-                    Ok(KLogMetaPutResult::VersionConflict {
-                        expected_revision,
-                        current_revision,
-                    }) => {
-                        warn!(
-                            "StateMachine put-meta request CAS conflict: key={}, expected_revision={}, current_revision={:?}",
-                            key, expected_revision, current_revision
-                        );
-                        KLogResponse::MetaPutConflict {
-                            key,
-                            expected_revision,
-                            current_revision,
-                        }
+                    Err(err) => {
+                        error!("StateMachine truncate request failed: {}", err);
+                        KLogResponse::Err(err.to_string())
                     }
+                }
+            }
+            KLogRequest::GrantLease {
+                ttl_ms,
+                granted_at,
+                granted_by,
+            } => {
+                debug!(
+                    "StateMachine process grant-lease request: lease_id={}, ttl_ms={}, granted_at={}, granted_by={}",
+                    revision, ttl_ms, granted_at, granted_by
+                );
+                match apply_grant_lease(&self.state_store, revision, ttl_ms, granted_at, granted_by)
+                    .await
+                {
+                    Ok(lease) => KLogResponse::LeaseOk { lease },
                     Err(err) => {
-                        error!("StateMachine put-meta request failed: {}", err);
+                        error!("StateMachine grant-lease request failed: {}", err);
                         KLogResponse::Err(err.to_string())
                     }
                 }
             }
-            KLogRequest::DeleteMeta { key } => {
-                debug!("StateMachine process delete-meta request: key={}", key);
-                match self.state_store.delete_meta_key(&key).await {
-                    Ok(prev_meta) => {
-                        let existed = prev_meta.is_some();
-                        debug!(
-                            "StateMachine delete-meta request committed: key={}, existed={}, prev_meta_revision={:?}",
-                            key,
-                            existed,
-                            prev_meta.as_ref().map(|v| v.revision)
-                        );
-                        KLogResponse::MetaDeleteOk {
-                            key,
-                            existed,
-                            prev_meta,
-                        }
+            KLogRequest::KeepAliveLease { lease_id, now_ms } => {
+                debug!(
+                    "StateMachine process keepalive-lease request: lease_id={}, now_ms={}",
+                    lease_id, now_ms
+                );
+                match apply_keepalive_lease(&self.state_store, lease_id, now_ms).await {
+                    Ok(Some(lease)) => KLogResponse::LeaseOk { lease },
+                    Ok(None) => KLogResponse::LeaseNotFound { lease_id },
+                    Err(err) => {
+                        error!("StateMachine keepalive-lease request failed: {}", err);
+                        KLogResponse::Err(err.to_string())
                     }
+                }
+            }
+            KLogRequest::RevokeLease { lease_id } => {
+                debug!(
+                    "StateMachine process revoke-lease request: lease_id={}",
+                    lease_id
+                );
+                self.remove_lease(lease_id, None, revision).await
+            }
+            KLogRequest::ExpireLease {
+                lease_id,
+                expires_at,
+            } => {
+                debug!(
+                    "StateMachine process expire-lease request: lease_id={}, expires_at={}",
+                    lease_id, expires_at
+                );
+                self.remove_lease(lease_id, Some(expires_at), revision)
+                    .await
+            }
+            KLogRequest::Txn {
+                compare,
+                then_ops,
+                else_ops,
+            } => {
+                debug!(
+                    "StateMachine process txn request: compares={}, then_ops={}, else_ops={}",
+                    compare.len(),
+                    then_ops.len(),
+                    else_ops.len()
+                );
+                match self
+                    .apply_txn(compare, then_ops, else_ops, revision, data)
+                    .await
+                {
+                    Ok(resp) => resp,
                     Err(err) => {
-                        error!("StateMachine delete-meta request failed: {}", err);
+                        error!("StateMachine txn request failed: {}", err);
                         KLogResponse::Err(err.to_string())
                     }
                 }
             }
         }
     }
+
+    /// Stage the put and the lease key sets it moves between, then commit them with the
+    /// applied log id in one store write, like a single-op txn.
+    async fn apply_meta_put(
+        &self,
+        item: KLogMetaEntry,
+        expected_revision: Option<u64>,
+        revision: u64,
+        data: &StateMachineData,
+    ) -> KResult<KLogResponse> {
+        let key = item.key.clone();
+        if let Some(lease_id) = item.lease_id
+            && lease::load_lease(&self.state_store, lease_id)
+                .await?
+                .is_none()
+        {
+            warn!(
+                "StateMachine put-meta request rejected, lease not found: key={}, lease_id={}",
+                key, lease_id
+            );
+            return Ok(KLogResponse::LeaseNotFound { lease_id });
+        }
+
+        let mut plan = KLogTxnWritePlan::new(&self.state_store);
+        let prev = plan.get(&key).await?;
+        if let Some(expected_revision) = expected_revision {
+            let current_revision = prev.as_ref().map(|v| v.revision);
+            let matched = if expected_revision == 0 {
+                prev.is_none()
+            } else {
+                current_revision == Some(expected_revision)
+            };
+            if !matched {
+                warn!(
+                    "StateMachine put-meta request CAS conflict: key={}, expected_revision={}, current_revision={:?}",
+                    key, expected_revision, current_revision
+                );
+                return Ok(KLogResponse::MetaPutConflict {
+                    key,
+                    expected_revision,
+                    current_revision,
+                });
+            }
+        }
+
+        let stored = plan.put(item).await?;
+        plan_meta_lease_change(
+            &mut plan,
+            &key,
+            prev.and_then(|v| v.lease_id),
+            stored.lease_id,
+            stored.updated_at,
+            stored.updated_by,
+        )
+        .await?;
+        self.commit_meta_plan(plan, data).await?;
+        debug!(
+            "StateMachine put-meta request committed: key={}, revision={}, lease_id={:?}",
+            key, stored.revision, stored.lease_id
+        );
+        let stored_revision = stored.revision;
+        self.record_meta_events(revision, vec![(KLogMetaWatchEventKind::Put, stored)]);
+        Ok(KLogResponse::MetaPutOk {
+            key,
+            revision: stored_revision,
+        })
+    }
+
+    /// Delete counterpart of `apply_meta_put`.
+    async fn apply_meta_delete(
+        &self,
+        key: String,
+        revision: u64,
+        data: &StateMachineData,
+    ) -> KResult<KLogResponse> {
+        let mut plan = KLogTxnWritePlan::new(&self.state_store);
+        let prev_meta = plan.delete(&key).await?;
+        if let Some(prev) = prev_meta.as_ref() {
+            if prev.lease_id.is_some() {
+                plan_meta_lease_change(
+                    &mut plan,
+                    &prev.key,
+                    prev.lease_id,
+                    None,
+                    prev.updated_at,
+                    prev.updated_by,
+                )
+                .await?;
+            }
+            self.commit_meta_plan(plan, data).await?;
+            self.record_meta_events(
+                revision,
+                vec![(KLogMetaWatchEventKind::Delete, prev.clone())],
+            );
+        }
+        let existed = prev_meta.is_some();
+        debug!(
+            "StateMachine delete-meta request committed: key={}, existed={}, prev_meta_revision={:?}",
+            key,
+            existed,
+            prev_meta.as_ref().map(|v| v.revision)
+        );
+        Ok(KLogResponse::MetaDeleteOk {
+            key,
+            existed,
+            prev_meta,
+        })
+    }
+
+    async fn commit_meta_plan(
+        &self,
+        plan: KLogTxnWritePlan<'_>,
+        data: &StateMachineData,
+    ) -> KResult<()> {
+        self.state_store
+            .commit_meta_writes(
+                plan.into_writes(),
+                KLogStateMachineMeta {
+                    last_applied_log_id: data.last_applied_log_id,
+                    last_membership: data.last_membership.clone(),
+                },
+            )
+            .await
+    }
+
+    /// Only called once the changes are committed, so watchers never see a write that a
+    /// crash could still lose.
+    fn record_meta_events(
+        &self,
+        revision: u64,
+        events: Vec<(KLogMetaWatchEventKind, KLogMetaEntry)>,
+    ) {
+        let watch_hub = self.state_store.watch_hub();
+        for (kind, meta) in events {
+            watch_hub.record_meta_event(KLogMetaWatchEvent {
+                revision,
+                kind,
+                key: meta.key.clone(),
+                meta,
+            });
+        }
+    }
+
+    async fn apply_txn(
+        &self,
+        compare: Vec<KLogTxnCompare>,
+        then_ops: Vec<KLogTxnOp>,
+        else_ops: Vec<KLogTxnOp>,
+        revision: u64,
+        data: &StateMachineData,
+    ) -> KResult<KLogResponse> {
+        let succeeded = evaluate_txn_compares(&self.state_store, &compare).await?;
+        let ops = if succeeded { then_ops } else { else_ops };
+
+        // Reject the whole branch before touching anything, so it applies all or nothing.
+        for op in &ops {
+            if let KLogTxnOp::Put { item } = op
+                && let Some(lease_id) = item.lease_id
+                && lease::load_lease(&self.state_store, lease_id)
+                    .await?
+                    .is_none()
+            {
+                warn!(
+                    "StateMachine txn request rejected, lease not found: key={}, lease_id={}",
+                    item.key, lease_id
+                );
+                return Ok(KLogResponse::LeaseNotFound { lease_id });
+            }
+        }
+
+        // Stage the whole branch, then commit it together with the applied log id so a
+        // crash never leaves part of a txn behind.
+        let mut plan = KLogTxnWritePlan::new(&self.state_store);
+        let mut results = Vec::with_capacity(ops.len());
+        let mut events = Vec::new();
+        for op in ops {
+            let result = match op {
+                KLogTxnOp::Put { item } => {
+                    let prev_lease_id = plan.get(&item.key).await?.and_then(|v| v.lease_id);
+                    let stored = plan.put(item).await?;
+                    plan_meta_lease_change(
+                        &mut plan,
+                        &stored.key,
+                        prev_lease_id,
+                        stored.lease_id,
+                        stored.updated_at,
+                        stored.updated_by,
+                    )
+                    .await?;
+                    let result = KLogTxnOpResult::Put {
+                        key: stored.key.clone(),
+                        revision: stored.revision,
+                    };
+                    events.push((KLogMetaWatchEventKind::Put, stored));
+                    result
+                }
+                KLogTxnOp::Delete { key } => {
+                    let prev_meta = plan.delete(&key).await?;
+                    if let Some(prev) = prev_meta.as_ref() {
+                        if prev.lease_id.is_some() {
+                            plan_meta_lease_change(
+                                &mut plan,
+                                &prev.key,
+                                prev.lease_id,
+                                None,
+                                prev.updated_at,
+                                prev.updated_by,
+                            )
+                            .await?;
+                        }
+                        events.push((KLogMetaWatchEventKind::Delete, prev.clone()));
+                    }
+                    KLogTxnOpResult::Delete {
+                        key,
+                        existed: prev_meta.is_some(),
+                        prev_meta,
+                    }
+                }
+                KLogTxnOp::Get { key } => {
+                    let item = plan.get(&key).await?;
+                    KLogTxnOpResult::Get { key, item }
+                }
+            };
+            results.push(result);
+        }
+
+        self.commit_meta_plan(plan, data).await?;
+        self.record_meta_events(revision, events);
+        debug!(
+            "StateMachine txn request committed: revision={}, succeeded={}, results={}",
+            revision,
+            succeeded,
+            results.len()
+        );
+        Ok(KLogResponse::TxnOk { succeeded, results })
+    }
+
+    async fn remove_lease(
+        &self,
+        lease_id: u64,
+        expected_expires_at: Option<u64>,
+        revision: u64,
+    ) -> KLogResponse {
+        match apply_revoke_lease(&self.state_store, lease_id, expected_expires_at).await {
+            Ok(Some((lease, deleted))) => {
+                let watch_hub = self.state_store.watch_hub();
+                let mut deleted_keys = Vec::with_capacity(deleted.len());
+                for prev in deleted {
+                    deleted_keys.push(prev.key.clone());
+                    watch_hub.record_meta_event(KLogMetaWatchEvent {
+                        revision,
+                        kind: KLogMetaWatchEventKind::Delete,
+                        key: prev.key.clone(),
+                        meta: prev,
+                    });
+                }
+                debug!(
+                    "StateMachine lease removed: lease_id={}, expired={}, deleted_keys={:?}",
+                    lease_id,
+                    expected_expires_at.is_some(),
+                    deleted_keys
+                );
+                KLogResponse::LeaseRevoked {
+                    lease,
+                    deleted_keys,
+                }
+            }
+            Ok(None) => KLogResponse::LeaseNotFound { lease_id },
+            Err(err) => {
+                error!("StateMachine remove-lease request failed: {}", err);
+                KLogResponse::Err(err.to_string())
+            }
+        }
+    }
 }
 
 impl RaftStateMachine<KTypeConfig> for KLogStateMachine {
@@ -210,7 +589,9 @@ impl RaftStateMachine<KTypeConfig> for KLogStateMachine {
             let resp_value = match entry.payload {
                 EntryPayload::Blank => KLogResponse::Empty,
 
-                EntryPayload::Normal(req) => self.process_request(req).await,
+                EntryPayload::Normal(req) => {
+                    self.process_request(req, entry.log_id.index, &data).await
+                }
 
                 EntryPayload::Membership(mem) => {
                     info!("Updating membership to: {:?}", mem);
@@ -228,6 +609,9 @@ impl RaftStateMachine<KTypeConfig> for KLogStateMachine {
         drop(data);
         self.persist_state_machine_meta(persisted_last_applied, persisted_membership)
             .await?;
+        if let Some(last_applied) = persisted_last_applied {
+            self.state_store.watch_hub().advance(last_applied.index);
+        }
 
         Ok(replies)
     }
@@ -249,39 +633,31 @@ impl RaftStateMachine<KTypeConfig> for KLogStateMachine {
             "StateMachine install_snapshot start: snapshot_id={}, last_log_id={:?}, last_membership={:?}",
             meta.snapshot_id, meta.last_log_id, meta.last_membership
         );
-        let data = self
+        let path = self
             .snapshot_manager
             .install_snapshot(meta, snapshot)
             .await?;
         info!(
-            "StateMachine install_snapshot persisted file: snapshot_id={}, klog_data_bytes={}",
-            data.meta.snapshot_id,
-            data.klog_data.len()
+            "StateMachine install_snapshot persisted file: snapshot_id={}, path={}",
+            meta.snapshot_id,
+            path.display()
         );
 
-        // First, restore state store from snapshot payload.
-        self.state_store
-            .install_snapshot(KLogStateSnapshot {
-                data: data.klog_data,
-            })
-            .await
-            .map_err(|e| {
-                let msg = format!("Failed to install state store snapshot: {}", e);
-                error!("{}", msg);
-                StorageError::IO {
-                    source: StorageIOError::write_snapshot(None, &std::io::Error::other(msg)),
-                }
-            })?;
-        self.persist_state_machine_meta(
-            data.meta.last_log_id.clone(),
-            data.meta.last_membership.clone(),
-        )
-        .await?;
+        // First, restore state store from the persisted snapshot file, segment by segment.
+        self.snapshot_manager
+            .restore_snapshot(meta, &path, &self.state_store)
+            .await?;
+        self.persist_state_machine_meta(meta.last_log_id, meta.last_membership.clone())
+            .await?;
 
         // Then, update the in-memory state machine metadata.
         let mut state = self.data.write().await;
-        state.last_applied_log_id = data.meta.last_log_id;
-        state.last_membership = data.meta.last_membership;
+        state.last_applied_log_id = meta.last_log_id;
+        state.last_membership = meta.last_membership.clone();
+        // The store was replaced wholesale, so meta watchers behind this point must re-sync.
+        self.state_store
+            .watch_hub()
+            .reset(meta.last_log_id.map(|id| id.index).unwrap_or(0));
         debug!(
             "StateMachine install_snapshot state updated: last_applied={:?}, membership={:?}",
             state.last_applied_log_id, state.last_membership
@@ -301,7 +677,7 @@ impl RaftStateMachine<KTypeConfig> for KLogStateMachine {
             return Ok(None);
         }
 
-        let (path, snapshot) = ret.unwrap();
+        let (path, meta) = ret.unwrap();
         let file = tokio::fs::File::open(&path).await.map_err(|err| {
             let msg = format!("Failed to open snapshot file: {:?}, {}", path, err);
             error!("{}", msg);
@@ -311,7 +687,7 @@ impl RaftStateMachine<KTypeConfig> for KLogStateMachine {
         })?;
 
         let snapshot = KSnapshot {
-            meta: snapshot.meta,
+            meta,
             snapshot: Box::new(file),
         };
 
@@ -322,7 +698,7 @@ impl RaftStateMachine<KTypeConfig> for KLogStateMachine {
 impl RaftSnapshotBuilder<KTypeConfig> for KLogStateMachine {
     async fn build_snapshot(&mut self) -> StorageResult<KSnapshot> {
         info!("StateMachine build_snapshot start");
-        let meta = {
+        let (meta, last_entry_id, view) = {
             let data = self.data.read().await;
 
             let snapshot_id =
@@ -334,41 +710,49 @@ impl RaftSnapshotBuilder<KTypeConfig> for KLogStateMachine {
                 snapshot_id,
             };
 
-            meta
-        };
-        info!(
-            "StateMachine build_snapshot meta prepared: snapshot_id={}, last_log_id={:?}, last_membership={:?}",
-            meta.snapshot_id, meta.last_log_id, meta.last_membership
-        );
+            // Entries appended after this point belong to later log ids, so the newest id
+            // seen under the lock bounds the entry range exported into this snapshot.
+            let last_entry_id = self.state_store.newest_entry_id().await.map_err(|e| {
+                let msg = format!("Failed to read newest entry id for snapshot: {}", e);
+                error!("{}", msg);
+                StorageError::IO {
+                    source: StorageIOError::write_snapshot(None, &std::io::Error::other(msg)),
+                }
+            })?;
 
-        let klog_data = self.state_store.build_snapshot().await.map_err(|e| {
-            let msg = format!("Failed to build state store snapshot: {}", e);
-            error!("{}", msg);
-            StorageError::IO {
-                source: StorageIOError::write_snapshot(None, &e),
-            }
-        })?;
+            // Apply is blocked while we hold the lock, pin the store now so paging below
+            // sees exactly the state of `last_applied_log_id`.
+            let view = self.state_store.pin_export_view().await.map_err(|e| {
+                let msg = format!("Failed to pin state store for snapshot: {}", e);
+                error!("{}", msg);
+                StorageError::IO {
+                    source: StorageIOError::write_snapshot(None, &std::io::Error::other(msg)),
+                }
+            })?;
+
+            (meta, last_entry_id, view)
+        };
         info!(
-            "StateMachine build_snapshot state_store ready: snapshot_id={}, klog_data_bytes={}",
-            meta.snapshot_id,
-            klog_data.data.len()
+            "StateMachine build_snapshot meta prepared: snapshot_id={}, last_log_id={:?}, last_membership={:?}, last_entry_id={:?}",
+            meta.snapshot_id, meta.last_log_id, meta.last_membership, last_entry_id
         );
 
-        let snapshot = KSnapshotData::new(meta, klog_data.data);
-
-        // First save the snapshot to disk
-        let file = self
+        // Stream the pinned view into a segmented snapshot file instead of materialising it.
+        let (file, summary) = self
             .snapshot_manager
-            .save_snapshot_to_file(&snapshot)
+            .save_segmented_snapshot(&meta, view.as_ref(), last_entry_id)
             .await?;
+        drop(view);
         info!(
-            "StateMachine build_snapshot file saved: snapshot_id={}, path={}",
-            snapshot.meta.snapshot_id,
-            file.display()
+            "StateMachine build_snapshot file saved: snapshot_id={}, path={}, entries={}, meta_entries={}",
+            meta.snapshot_id,
+            file.display(),
+            summary.entries,
+            summary.meta_entries
         );
 
         // Then start a task to clean up old snapshots
-        let snapshot_id = snapshot.meta.snapshot_id.clone();
+        let snapshot_id = meta.snapshot_id.clone();
         let snapshot_manager = self.snapshot_manager.clone();
         tokio::spawn(async move {
             if let Err(e) = snapshot_manager.clean_old_snapshots(&snapshot_id).await {
@@ -385,7 +769,7 @@ impl RaftSnapshotBuilder<KTypeConfig> for KLogStateMachine {
         })?;
 
         let snapshot = Snapshot {
-            meta: snapshot.meta,
+            meta,
             snapshot: Box::new(file),
         };
         info!(
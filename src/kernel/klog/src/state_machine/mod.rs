@@ -1,5 +1,7 @@
 mod machine;
 mod snapshot;
+mod snapshot_segment;
 
 pub use machine::*;
 pub use snapshot::*;
+pub use snapshot_segment::*;
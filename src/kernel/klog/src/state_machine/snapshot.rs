@@ -1,3 +1,9 @@
+use super::snapshot_segment::{
+    DEFAULT_SNAPSHOT_SEGMENT_ENTRIES, KSnapshotSegment, KSnapshotSegmentSummary,
+    SEGMENTED_SNAPSHOT_MAGIC, SegmentedSnapshotReader, SegmentedSnapshotWriter,
+    is_segmented_snapshot,
+};
+use crate::state_store::{KLogStateExportView, KLogStateSnapshot, KLogStateStoreManager};
 use crate::util::persist_format::{PersistPayloadType, decode_with_header, encode_with_header};
 use crate::{KLogId, StorageResult};
 use crate::{KNode, KNodeId};
@@ -6,8 +12,11 @@ use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::io::AsyncSeekExt;
 use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::Mutex as AsyncMutex;
+
+const RECEIVING_SNAPSHOT_PREFIX: &str = "receiving_";
 
 pub type KSnapshotMeta = openraft::SnapshotMeta<KNodeId, KNode>;
 
@@ -43,9 +52,38 @@ impl KSnapshotData {
     }
 }
 
+/// Result of writing one incoming snapshot chunk to the resumable receive file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KSnapshotChunkAck {
+    /// The chunk was persisted; `received` is the number of bytes now on disk.
+    Accepted { received: u64 },
+    /// The chunk starts past the end of the receive file; the sender must resume from
+    /// `expected_offset`.
+    Mismatch { expected_offset: u64 },
+}
+
 #[derive(Debug)]
 pub struct SnapshotManager {
     data_dir: PathBuf,
+    segment_entries: usize,
+    // Serializes writes to the resumable receive file across retried chunk requests.
+    receive_lock: AsyncMutex<()>,
+}
+
+fn snapshot_write_error(meta: Option<&KSnapshotMeta>, msg: String) -> StorageError<KNodeId> {
+    error!("{}", msg);
+    let io_err = std::io::Error::other(msg);
+    StorageError::IO {
+        source: StorageIOError::write_snapshot(meta.map(|m| m.signature()), &io_err),
+    }
+}
+
+fn snapshot_read_error(meta: Option<&KSnapshotMeta>, msg: String) -> StorageError<KNodeId> {
+    error!("{}", msg);
+    let io_err = std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+    StorageError::IO {
+        source: StorageIOError::read_snapshot(meta.map(|m| m.signature()), &io_err),
+    }
 }
 
 impl SnapshotManager {
@@ -57,7 +95,17 @@ impl SnapshotManager {
 
         info!("Snapshot directory set to: {:?}", data_dir);
 
-        Self { data_dir }
+        Self {
+            data_dir,
+            segment_entries: DEFAULT_SNAPSHOT_SEGMENT_ENTRIES,
+            receive_lock: AsyncMutex::new(()),
+        }
+    }
+
+    /// Number of entries packed into one entry segment when building snapshots.
+    pub fn with_segment_entries(mut self, segment_entries: usize) -> Self {
+        self.segment_entries = segment_entries.max(1);
+        self
     }
 
     // Generate a unique snapshot ID based on the current timestamp and last log id
@@ -182,11 +230,13 @@ impl SnapshotManager {
         }
     }
 
+    /// Persist an incoming snapshot stream as `snapshot_<id>` and return its path.
+    /// The payload is applied to the state store separately by `restore_snapshot`.
     pub async fn install_snapshot(
         &self,
         meta: &KSnapshotMeta,
         mut snapshot: Box<tokio::fs::File>,
-    ) -> StorageResult<KSnapshotData> {
+    ) -> StorageResult<PathBuf> {
         // TODO Should we remove the temp snapshot file after installation?
         // let src = self.get_temp_snapshot_path();
 
@@ -265,14 +315,7 @@ impl SnapshotManager {
         })?;
         */
 
-        let snapshot = self.load_snapshot_from_file(Some(meta), &dest).await?;
-
-        // Check that the loaded snapshot matches the meta
-        debug_assert_eq!(meta.snapshot_id, snapshot.meta.snapshot_id);
-        debug_assert_eq!(meta.last_log_id, snapshot.meta.last_log_id);
-        debug_assert_eq!(meta.last_membership, snapshot.meta.last_membership);
-
-        Ok(snapshot)
+        Ok(dest)
     }
 
     pub async fn load_snapshot_from_file(
@@ -364,8 +407,448 @@ impl SnapshotManager {
         Ok(path)
     }
 
+    /// Build a segmented snapshot by paging entries `<= last_entry_id` and the meta table
+    /// out of a pinned store view, writing each page as its own checksummed segment.
+    pub async fn save_segmented_snapshot(
+        &self,
+        meta: &KSnapshotMeta,
+        view: &dyn KLogStateExportView,
+        last_entry_id: Option<u64>,
+    ) -> StorageResult<(PathBuf, KSnapshotSegmentSummary)> {
+        let path = self.data_dir.join(format!("snapshot_{}", meta.snapshot_id));
+        if path.exists() {
+            return Err(snapshot_write_error(
+                Some(meta),
+                format!(
+                    "Snapshot file already exists, refusing overwrite: {:?}",
+                    path
+                ),
+            ));
+        }
+
+        let tmp = self.make_atomic_temp_path(&meta.snapshot_id, "save");
+        info!(
+            "Saving segmented snapshot to file {:?}: last_entry_id={:?}, segment_entries={}",
+            path, last_entry_id, self.segment_entries
+        );
+        let ret = self
+            .write_segmented_snapshot(&tmp, meta, view, last_entry_id)
+            .await;
+        let (summary, bytes) = match ret {
+            Ok(v) => v,
+            Err(e) => {
+                let _ = std::fs::remove_file(&tmp);
+                return Err(e);
+            }
+        };
+
+        self.commit_temp_as_snapshot(&tmp, &path)
+            .await
+            .map_err(|e| {
+                let _ = std::fs::remove_file(&tmp);
+                snapshot_write_error(
+                    Some(meta),
+                    format!(
+                        "Failed to atomically publish snapshot file {:?} from {:?}: {}",
+                        path, tmp, e
+                    ),
+                )
+            })?;
+        info!(
+            "Segmented snapshot saved: snapshot_id={}, entries={}, entry_segments={}, meta_entries={}, bytes={}",
+            meta.snapshot_id, summary.entries, summary.entry_segments, summary.meta_entries, bytes
+        );
+
+        Ok((path, summary))
+    }
+
+    async fn write_segmented_snapshot(
+        &self,
+        tmp: &Path,
+        meta: &KSnapshotMeta,
+        view: &dyn KLogStateExportView,
+        last_entry_id: Option<u64>,
+    ) -> StorageResult<(KSnapshotSegmentSummary, u64)> {
+        let io_err = |e: std::io::Error| {
+            snapshot_write_error(
+                Some(meta),
+                format!("Failed to write segmented snapshot {:?}: {}", tmp, e),
+            )
+        };
+        let store_err = |e: crate::KLogError| {
+            snapshot_write_error(
+                Some(meta),
+                format!("Failed to read state store for snapshot: {}", e),
+            )
+        };
+
+        let file = tokio::fs::File::create_new(tmp).await.map_err(io_err)?;
+        let mut writer = SegmentedSnapshotWriter::begin(tokio::io::BufWriter::new(file), meta)
+            .await
+            .map_err(io_err)?;
+
+        if let Some(last_entry_id) = last_entry_id {
+            let mut after_id = 0;
+            loop {
+                let page = view
+                    .entries_page(after_id, last_entry_id, self.segment_entries)
+                    .await
+                    .map_err(store_err)?;
+                let Some(last) = page.last() else {
+                    break;
+                };
+                after_id = last.id;
+                writer.write_entries(&page).await.map_err(io_err)?;
+                if page.len() < self.segment_entries {
+                    break;
+                }
+            }
+        }
+
+        let mut after_key: Option<String> = None;
+        loop {
+            let page = view
+                .meta_page(after_key.as_deref(), self.segment_entries)
+                .await
+                .map_err(store_err)?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            after_key = Some(last.key.clone());
+            writer.write_meta(&page).await.map_err(io_err)?;
+            if page.len() < self.segment_entries {
+                break;
+            }
+        }
+
+        let (file, summary, bytes) = writer.finish().await.map_err(io_err)?;
+        file.into_inner().sync_all().await.map_err(io_err)?;
+        Ok((summary, bytes))
+    }
+
+    /// Read the snapshot meta of a snapshot file. Segmented snapshots only read the header
+    /// segment; legacy single-blob snapshots have to be decoded in full.
+    pub async fn read_snapshot_meta(&self, path: &Path) -> StorageResult<KSnapshotMeta> {
+        if !self.is_segmented_snapshot_file(None, path).await? {
+            let data = self.load_snapshot_from_file(None, path).await?;
+            return Ok(data.meta);
+        }
+
+        let file = tokio::fs::File::open(path).await.map_err(|e| {
+            snapshot_read_error(
+                None,
+                format!("Failed to open snapshot file {:?}: {}", path, e),
+            )
+        })?;
+        let reader = SegmentedSnapshotReader::open(tokio::io::BufReader::new(file))
+            .await
+            .map_err(|e| {
+                snapshot_read_error(
+                    None,
+                    format!("Failed to read snapshot header {:?}: {}", path, e),
+                )
+            })?;
+        Ok(reader.meta().clone())
+    }
+
+    async fn is_segmented_snapshot_file(
+        &self,
+        meta: Option<&KSnapshotMeta>,
+        path: &Path,
+    ) -> StorageResult<bool> {
+        let mut file = tokio::fs::File::open(path).await.map_err(|e| {
+            snapshot_read_error(
+                meta,
+                format!("Failed to open snapshot file {:?}: {}", path, e),
+            )
+        })?;
+        let mut magic = [0u8; SEGMENTED_SNAPSHOT_MAGIC.len()];
+        match file.read_exact(&mut magic).await {
+            Ok(_) => Ok(is_segmented_snapshot(&magic)),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(snapshot_read_error(
+                meta,
+                format!("Failed to read snapshot file {:?}: {}", path, e),
+            )),
+        }
+    }
+
+    /// Walk every segment of a segmented snapshot and check checksums, ordering and footer
+    /// totals without keeping any segment in memory.
+    pub async fn verify_segmented_snapshot(
+        &self,
+        meta: Option<&KSnapshotMeta>,
+        path: &Path,
+    ) -> StorageResult<KSnapshotSegmentSummary> {
+        let file = tokio::fs::File::open(path).await.map_err(|e| {
+            snapshot_read_error(
+                meta,
+                format!("Failed to open snapshot file {:?}: {}", path, e),
+            )
+        })?;
+        let mut reader = SegmentedSnapshotReader::open(tokio::io::BufReader::new(file))
+            .await
+            .map_err(|e| {
+                snapshot_read_error(meta, format!("Invalid snapshot file {:?}: {}", path, e))
+            })?;
+        if let Some(meta) = meta
+            && reader.meta().snapshot_id != meta.snapshot_id
+        {
+            return Err(snapshot_read_error(
+                Some(meta),
+                format!(
+                    "Snapshot file {:?} carries snapshot_id={}, expected={}",
+                    path,
+                    reader.meta().snapshot_id,
+                    meta.snapshot_id
+                ),
+            ));
+        }
+
+        while reader
+            .next_segment()
+            .await
+            .map_err(|e| {
+                snapshot_read_error(meta, format!("Invalid snapshot file {:?}: {}", path, e))
+            })?
+            .is_some()
+        {}
+
+        Ok(reader.summary().clone())
+    }
+
+    /// Replace the state store content with the snapshot stored at `path`.
+    /// Segmented snapshots are verified end to end first and then applied segment by
+    /// segment; legacy single-blob snapshots go through `KLogStateStore::install_snapshot`.
+    pub async fn restore_snapshot(
+        &self,
+        meta: &KSnapshotMeta,
+        path: &Path,
+        state_store: &KLogStateStoreManager,
+    ) -> StorageResult<()> {
+        let store_err = |e: crate::KLogError| {
+            snapshot_write_error(
+                Some(meta),
+                format!("Failed to install state store snapshot: {}", e),
+            )
+        };
+
+        if !self.is_segmented_snapshot_file(Some(meta), path).await? {
+            let data = self.load_snapshot_from_file(Some(meta), path).await?;
+            if data.meta.snapshot_id != meta.snapshot_id {
+                return Err(snapshot_read_error(
+                    Some(meta),
+                    format!(
+                        "Snapshot file {:?} carries snapshot_id={}, expected={}",
+                        path, data.meta.snapshot_id, meta.snapshot_id
+                    ),
+                ));
+            }
+            info!(
+                "Restoring legacy snapshot: snapshot_id={}, klog_data_bytes={}",
+                meta.snapshot_id,
+                data.klog_data.len()
+            );
+            return state_store
+                .install_snapshot(KLogStateSnapshot {
+                    data: data.klog_data,
+                })
+                .await
+                .map_err(store_err);
+        }
+
+        // Verify before touching the store so a corrupted file never leaves it half-replaced.
+        let expected = self.verify_segmented_snapshot(Some(meta), path).await?;
+
+        let file = tokio::fs::File::open(path).await.map_err(|e| {
+            snapshot_read_error(
+                Some(meta),
+                format!("Failed to open snapshot file {:?}: {}", path, e),
+            )
+        })?;
+        let read_err = |e: std::io::Error| {
+            snapshot_read_error(
+                Some(meta),
+                format!("Failed to read snapshot file {:?}: {}", path, e),
+            )
+        };
+        let mut reader = SegmentedSnapshotReader::open(tokio::io::BufReader::new(file))
+            .await
+            .map_err(read_err)?;
+
+        state_store
+            .begin_segmented_install()
+            .await
+            .map_err(store_err)?;
+        while let Some(segment) = reader.next_segment().await.map_err(read_err)? {
+            match segment {
+                KSnapshotSegment::Entries(segment) => {
+                    debug!(
+                        "Restoring snapshot entry segment: snapshot_id={}, first_id={}, last_id={}, entries={}",
+                        meta.snapshot_id,
+                        segment.first_id,
+                        segment.last_id,
+                        segment.entries.len()
+                    );
+                    state_store
+                        .install_snapshot_entries(segment.entries)
+                        .await
+                        .map_err(store_err)?;
+                }
+                KSnapshotSegment::Meta(items) => {
+                    state_store
+                        .install_snapshot_meta(items)
+                        .await
+                        .map_err(store_err)?;
+                }
+            }
+        }
+        let summary = reader.summary().clone();
+        debug_assert_eq!(summary, expected);
+        state_store
+            .finish_segmented_install(summary.max_entry_id)
+            .await
+            .map_err(store_err)?;
+
+        info!(
+            "Restored segmented snapshot: snapshot_id={}, entries={}, entry_segments={}, meta_entries={}",
+            meta.snapshot_id, summary.entries, summary.entry_segments, summary.meta_entries
+        );
+        Ok(())
+    }
+
+    fn receiving_snapshot_path(&self, snapshot_id: &str) -> PathBuf {
+        self.data_dir
+            .join(format!("{}{}", RECEIVING_SNAPSHOT_PREFIX, snapshot_id))
+    }
+
+    /// Bytes of `snapshot_id` already persisted by an earlier, interrupted transfer.
+    pub async fn received_snapshot_len(&self, snapshot_id: &str) -> u64 {
+        let _guard = self.receive_lock.lock().await;
+        tokio::fs::metadata(self.receiving_snapshot_path(snapshot_id))
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0)
+    }
+
+    /// Write one chunk of an incoming snapshot to its resumable receive file.
+    /// Chunks may rewrite bytes already on disk but must not leave a gap; receive files of
+    /// other snapshot ids are dropped once a new transfer starts.
+    pub async fn receive_snapshot_chunk(
+        &self,
+        meta: &KSnapshotMeta,
+        offset: u64,
+        data: &[u8],
+    ) -> StorageResult<KSnapshotChunkAck> {
+        let _guard = self.receive_lock.lock().await;
+        let path = self.receiving_snapshot_path(&meta.snapshot_id);
+        let received = tokio::fs::metadata(&path)
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0);
+        if offset > received {
+            warn!(
+                "Snapshot chunk leaves a gap, ask sender to resume: snapshot_id={}, offset={}, received={}",
+                meta.snapshot_id, offset, received
+            );
+            return Ok(KSnapshotChunkAck::Mismatch {
+                expected_offset: received,
+            });
+        }
+        if received == 0 {
+            self.remove_stale_receiving_files(&meta.snapshot_id).await;
+        }
+
+        let write_err = |e: std::io::Error| {
+            snapshot_write_error(
+                Some(meta),
+                format!("Failed to write snapshot chunk to {:?}: {}", path, e),
+            )
+        };
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&path)
+            .await
+            .map_err(write_err)?;
+        file.set_len(offset).await.map_err(write_err)?;
+        file.seek(std::io::SeekFrom::Start(offset))
+            .await
+            .map_err(write_err)?;
+        file.write_all(data).await.map_err(write_err)?;
+        file.sync_data().await.map_err(write_err)?;
+
+        let received = offset + data.len() as u64;
+        debug!(
+            "Snapshot chunk persisted: snapshot_id={}, offset={}, chunk_bytes={}, received={}",
+            meta.snapshot_id,
+            offset,
+            data.len(),
+            received
+        );
+        Ok(KSnapshotChunkAck::Accepted { received })
+    }
+
+    /// Open a fully received snapshot for installation. Segmented snapshots are verified
+    /// first; a corrupted receive file is removed so the next transfer starts over.
+    pub async fn open_received_snapshot(
+        &self,
+        meta: &KSnapshotMeta,
+    ) -> StorageResult<tokio::fs::File> {
+        let _guard = self.receive_lock.lock().await;
+        let path = self.receiving_snapshot_path(&meta.snapshot_id);
+        if self.is_segmented_snapshot_file(Some(meta), &path).await?
+            && let Err(e) = self.verify_segmented_snapshot(Some(meta), &path).await
+        {
+            warn!("Discarding corrupted received snapshot {:?}: {}", path, e);
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e);
+        }
+
+        tokio::fs::File::open(&path).await.map_err(|e| {
+            snapshot_read_error(
+                Some(meta),
+                format!("Failed to open received snapshot {:?}: {}", path, e),
+            )
+        })
+    }
+
+    pub async fn finish_received_snapshot(&self, snapshot_id: &str) {
+        let _guard = self.receive_lock.lock().await;
+        let path = self.receiving_snapshot_path(snapshot_id);
+        if let Err(e) = tokio::fs::remove_file(&path).await
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!("Failed to remove received snapshot file {:?}: {}", path, e);
+        }
+    }
+
+    async fn remove_stale_receiving_files(&self, keep_snapshot_id: &str) {
+        let Ok(mut list) = tokio::fs::read_dir(&self.data_dir).await else {
+            return;
+        };
+        let keep = format!("{}{}", RECEIVING_SNAPSHOT_PREFIX, keep_snapshot_id);
+        while let Ok(Some(entry)) = list.next_entry().await {
+            let file_name = entry.file_name();
+            let name = file_name.to_str().unwrap_or_default();
+            if !name.starts_with(RECEIVING_SNAPSHOT_PREFIX) || name == keep {
+                continue;
+            }
+
+            info!("Removing stale received snapshot file {:?}", entry.path());
+            if let Err(e) = tokio::fs::remove_file(entry.path()).await {
+                warn!(
+                    "Failed to remove stale received snapshot file {:?}: {}",
+                    entry.path(),
+                    e
+                );
+            }
+        }
+    }
+
     // Load the most recent snapshot from the snapshots directory
-    pub async fn load_current_snapshot(&self) -> StorageResult<Option<(PathBuf, KSnapshotData)>> {
+    pub async fn load_current_snapshot(&self) -> StorageResult<Option<(PathBuf, KSnapshotMeta)>> {
         if !self.data_dir.exists() {
             warn!("Snapshots directory does not exist: {:?}", self.data_dir);
             return Ok(None);
@@ -454,9 +937,9 @@ impl SnapshotManager {
         let path = self.data_dir.join(latest_file_name.unwrap());
         info!("Loading latest snapshot from file {:?}", path);
 
-        let data = self.load_snapshot_from_file(None, &path).await?;
+        let meta = self.read_snapshot_meta(&path).await?;
 
-        Ok(Some((path, data)))
+        Ok(Some((path, meta)))
     }
 
     /// Clean up old snapshots, keeping only the latest one with id == `last_snapshot_id`
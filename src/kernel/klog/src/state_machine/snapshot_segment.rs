@@ -0,0 +1,360 @@
+use super::snapshot::KSnapshotMeta;
+use crate::{KLogEntry, KLogMetaEntry};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// File layout: magic + version, then a sequence of segments.
+// Segment layout: kind(u8) + payload_len(u32 BE) + sha256(payload) + payload(bincode legacy).
+// The first segment is always the snapshot meta and the last one is a footer carrying the
+// totals, so a truncated file is detected even when every segment checksum is valid.
+pub const SEGMENTED_SNAPSHOT_MAGIC: &[u8; 8] = b"KLOGSEG1";
+const SEGMENTED_SNAPSHOT_VERSION_V1: u16 = 1;
+const SEGMENT_CHECKSUM_LEN: usize = 32;
+const SEGMENT_HEADER_LEN: usize = 1 + 4 + SEGMENT_CHECKSUM_LEN;
+
+/// Upper bound of a single segment payload, guards allocations against corrupted length fields.
+pub const SNAPSHOT_SEGMENT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Default number of entries packed into one entry segment.
+pub const DEFAULT_SNAPSHOT_SEGMENT_ENTRIES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum SegmentKind {
+    Header = 1,
+    Entries = 2,
+    Meta = 3,
+    Footer = 4,
+}
+
+impl SegmentKind {
+    fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(Self::Header),
+            2 => Some(Self::Entries),
+            3 => Some(Self::Meta),
+            4 => Some(Self::Footer),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SegmentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Header => "header",
+            Self::Entries => "entries",
+            Self::Meta => "meta",
+            Self::Footer => "footer",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Serialize)]
+struct EntrySegmentRef<'a> {
+    first_id: u64,
+    last_id: u64,
+    entries: &'a [KLogEntry],
+}
+
+/// One contiguous, id-ordered range of log entries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KSnapshotEntrySegment {
+    pub first_id: u64,
+    pub last_id: u64,
+    pub entries: Vec<KLogEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KSnapshotSegment {
+    Entries(KSnapshotEntrySegment),
+    Meta(Vec<KLogMetaEntry>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KSnapshotSegmentSummary {
+    pub entry_segments: u64,
+    pub meta_segments: u64,
+    pub entries: u64,
+    pub meta_entries: u64,
+    pub max_entry_id: Option<u64>,
+}
+
+pub fn is_segmented_snapshot(prefix: &[u8]) -> bool {
+    prefix.starts_with(SEGMENTED_SNAPSHOT_MAGIC)
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    error!("{}", msg);
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+fn segment_checksum(payload: &[u8]) -> [u8; SEGMENT_CHECKSUM_LEN] {
+    Sha256::digest(payload).into()
+}
+
+/// Writes a segmented snapshot to `W` one segment at a time.
+/// Callers should hand in a buffered writer; nothing is buffered here.
+pub struct SegmentedSnapshotWriter<W> {
+    inner: W,
+    summary: KSnapshotSegmentSummary,
+    bytes_written: u64,
+}
+
+impl<W: AsyncWrite + Unpin> SegmentedSnapshotWriter<W> {
+    pub async fn begin(mut inner: W, meta: &KSnapshotMeta) -> std::io::Result<Self> {
+        inner.write_all(SEGMENTED_SNAPSHOT_MAGIC).await?;
+        inner
+            .write_all(&SEGMENTED_SNAPSHOT_VERSION_V1.to_be_bytes())
+            .await?;
+
+        let mut writer = Self {
+            inner,
+            summary: KSnapshotSegmentSummary::default(),
+            bytes_written: (SEGMENTED_SNAPSHOT_MAGIC.len() + 2) as u64,
+        };
+        writer.write_segment(SegmentKind::Header, meta).await?;
+        Ok(writer)
+    }
+
+    /// Append one entry segment. Entries must be sorted by id and follow the previous segment.
+    pub async fn write_entries(&mut self, entries: &[KLogEntry]) -> std::io::Result<()> {
+        let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+            return Ok(());
+        };
+        if let Some(max_id) = self.summary.max_entry_id
+            && first.id <= max_id
+        {
+            return Err(invalid_data(format!(
+                "Snapshot entry segment out of order: first_id={}, previous_max_id={}",
+                first.id, max_id
+            )));
+        }
+
+        let segment = EntrySegmentRef {
+            first_id: first.id,
+            last_id: last.id,
+            entries,
+        };
+        self.write_segment(SegmentKind::Entries, &segment).await?;
+        self.summary.entry_segments += 1;
+        self.summary.entries += entries.len() as u64;
+        self.summary.max_entry_id = Some(last.id);
+        Ok(())
+    }
+
+    pub async fn write_meta(&mut self, items: &[KLogMetaEntry]) -> std::io::Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        self.write_segment(SegmentKind::Meta, items).await?;
+        self.summary.meta_segments += 1;
+        self.summary.meta_entries += items.len() as u64;
+        Ok(())
+    }
+
+    /// Write the footer and flush. Returns the inner writer, the totals and the file size.
+    pub async fn finish(mut self) -> std::io::Result<(W, KSnapshotSegmentSummary, u64)> {
+        let summary = self.summary.clone();
+        self.write_segment(SegmentKind::Footer, &summary).await?;
+        self.inner.flush().await?;
+        Ok((self.inner, summary, self.bytes_written))
+    }
+
+    async fn write_segment<T: Serialize + ?Sized>(
+        &mut self,
+        kind: SegmentKind,
+        value: &T,
+    ) -> std::io::Result<()> {
+        let payload =
+            bincode::serde::encode_to_vec(value, bincode::config::legacy()).map_err(|e| {
+                invalid_data(format!("Failed to encode snapshot {} segment: {}", kind, e))
+            })?;
+        if payload.len() > SNAPSHOT_SEGMENT_MAX_BYTES {
+            return Err(invalid_data(format!(
+                "Snapshot {} segment too large: bytes={}, max={}",
+                kind,
+                payload.len(),
+                SNAPSHOT_SEGMENT_MAX_BYTES
+            )));
+        }
+
+        self.inner.write_u8(kind as u8).await?;
+        self.inner.write_u32(payload.len() as u32).await?;
+        self.inner.write_all(&segment_checksum(&payload)).await?;
+        self.inner.write_all(&payload).await?;
+        self.bytes_written += (SEGMENT_HEADER_LEN + payload.len()) as u64;
+        Ok(())
+    }
+}
+
+/// Reads a segmented snapshot from `R`, verifying every segment checksum as it goes.
+pub struct SegmentedSnapshotReader<R> {
+    inner: R,
+    meta: KSnapshotMeta,
+    summary: KSnapshotSegmentSummary,
+    finished: bool,
+}
+
+impl<R: AsyncRead + Unpin> SegmentedSnapshotReader<R> {
+    pub async fn open(mut inner: R) -> std::io::Result<Self> {
+        let mut magic = [0u8; 8];
+        inner.read_exact(&mut magic).await?;
+        if !is_segmented_snapshot(&magic) {
+            return Err(invalid_data(format!(
+                "Invalid segmented snapshot magic: got={:?}",
+                magic
+            )));
+        }
+        let version = inner.read_u16().await?;
+        if version != SEGMENTED_SNAPSHOT_VERSION_V1 {
+            return Err(invalid_data(format!(
+                "Unsupported segmented snapshot version: expected={}, got={}",
+                SEGMENTED_SNAPSHOT_VERSION_V1, version
+            )));
+        }
+
+        let Some((kind, payload)) = read_segment(&mut inner).await? else {
+            return Err(invalid_data(
+                "Segmented snapshot ends before header segment".to_string(),
+            ));
+        };
+        if kind != SegmentKind::Header {
+            return Err(invalid_data(format!(
+                "Segmented snapshot must start with header segment, got={}",
+                kind
+            )));
+        }
+        let meta = decode_segment(kind, &payload)?;
+
+        Ok(Self {
+            inner,
+            meta,
+            summary: KSnapshotSegmentSummary::default(),
+            finished: false,
+        })
+    }
+
+    pub fn meta(&self) -> &KSnapshotMeta {
+        &self.meta
+    }
+
+    /// Totals of the segments read so far; complete once `next_segment` returned `None`.
+    pub fn summary(&self) -> &KSnapshotSegmentSummary {
+        &self.summary
+    }
+
+    /// Return the next data segment, or `None` after the footer has been read and matched.
+    pub async fn next_segment(&mut self) -> std::io::Result<Option<KSnapshotSegment>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let Some((kind, payload)) = read_segment(&mut self.inner).await? else {
+            return Err(invalid_data(format!(
+                "Segmented snapshot {} truncated before footer: entry_segments={}, meta_segments={}",
+                self.meta.snapshot_id, self.summary.entry_segments, self.summary.meta_segments
+            )));
+        };
+
+        match kind {
+            SegmentKind::Header => Err(invalid_data(format!(
+                "Duplicate header segment in snapshot {}",
+                self.meta.snapshot_id
+            ))),
+            SegmentKind::Entries => {
+                let segment: KSnapshotEntrySegment = decode_segment(kind, &payload)?;
+                self.check_entry_segment(&segment)?;
+                self.summary.entry_segments += 1;
+                self.summary.entries += segment.entries.len() as u64;
+                self.summary.max_entry_id = Some(segment.last_id);
+                Ok(Some(KSnapshotSegment::Entries(segment)))
+            }
+            SegmentKind::Meta => {
+                let items: Vec<KLogMetaEntry> = decode_segment(kind, &payload)?;
+                self.summary.meta_segments += 1;
+                self.summary.meta_entries += items.len() as u64;
+                Ok(Some(KSnapshotSegment::Meta(items)))
+            }
+            SegmentKind::Footer => {
+                let footer: KSnapshotSegmentSummary = decode_segment(kind, &payload)?;
+                if footer != self.summary {
+                    return Err(invalid_data(format!(
+                        "Segmented snapshot {} footer mismatch: footer={:?}, read={:?}",
+                        self.meta.snapshot_id, footer, self.summary
+                    )));
+                }
+                self.finished = true;
+                Ok(None)
+            }
+        }
+    }
+
+    fn check_entry_segment(&self, segment: &KSnapshotEntrySegment) -> std::io::Result<()> {
+        let (Some(first), Some(last)) = (segment.entries.first(), segment.entries.last()) else {
+            return Err(invalid_data(format!(
+                "Empty entry segment in snapshot {}",
+                self.meta.snapshot_id
+            )));
+        };
+        let ordered = segment.entries.windows(2).all(|w| w[0].id < w[1].id);
+        if first.id != segment.first_id || last.id != segment.last_id || !ordered {
+            return Err(invalid_data(format!(
+                "Malformed entry segment in snapshot {}: first_id={}, last_id={}",
+                self.meta.snapshot_id, segment.first_id, segment.last_id
+            )));
+        }
+        if let Some(max_id) = self.summary.max_entry_id
+            && segment.first_id <= max_id
+        {
+            return Err(invalid_data(format!(
+                "Entry segment out of order in snapshot {}: first_id={}, previous_max_id={}",
+                self.meta.snapshot_id, segment.first_id, max_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+async fn read_segment<R: AsyncRead + Unpin>(
+    inner: &mut R,
+) -> std::io::Result<Option<(SegmentKind, Vec<u8>)>> {
+    let kind = match inner.read_u8().await {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let kind = SegmentKind::from_u8(kind)
+        .ok_or_else(|| invalid_data(format!("Unknown snapshot segment kind: {}", kind)))?;
+
+    let payload_len = inner.read_u32().await? as usize;
+    if payload_len > SNAPSHOT_SEGMENT_MAX_BYTES {
+        return Err(invalid_data(format!(
+            "Snapshot {} segment length out of range: bytes={}, max={}",
+            kind, payload_len, SNAPSHOT_SEGMENT_MAX_BYTES
+        )));
+    }
+
+    let mut checksum = [0u8; SEGMENT_CHECKSUM_LEN];
+    inner.read_exact(&mut checksum).await?;
+    let mut payload = vec![0u8; payload_len];
+    inner.read_exact(&mut payload).await?;
+    if segment_checksum(&payload) != checksum {
+        return Err(invalid_data(format!(
+            "Snapshot {} segment checksum mismatch: bytes={}",
+            kind, payload_len
+        )));
+    }
+
+    Ok(Some((kind, payload)))
+}
+
+fn decode_segment<T: DeserializeOwned>(kind: SegmentKind, payload: &[u8]) -> std::io::Result<T> {
+    let (value, _) = bincode::serde::decode_from_slice(payload, bincode::config::legacy())
+        .map_err(|e| invalid_data(format!("Failed to decode snapshot {} segment: {}", kind, e)))?;
+    Ok(value)
+}
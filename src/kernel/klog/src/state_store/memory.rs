@@ -1,9 +1,9 @@
 use super::store::{
-    KLogQuery, KLogQueryOrder, KLogStateMachineMeta, KLogStateSnapshot, KLogStateSnapshotData,
-    KLogStateStore, REQUEST_DEDUP_MAX_ITEMS, REQUEST_DEDUP_WINDOW_MS,
+    KLogQuery, KLogQueryOrder, KLogStateExportView, KLogStateMachineMeta, KLogStateSnapshot,
+    KLogStateSnapshotData, KLogStateStore, REQUEST_DEDUP_MAX_ITEMS, REQUEST_DEDUP_WINDOW_MS,
 };
 use crate::{KLogEntry, KLogError, KLogMetaEntry, KResult};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -73,6 +73,46 @@ impl RequestDedupIndex {
     }
 }
 
+/// Copy of the memory store taken by `pin_export_view`.
+struct MemoryExportView {
+    entries: Vec<KLogEntry>,
+    metas: BTreeMap<String, KLogMetaEntry>,
+}
+
+#[async_trait::async_trait]
+impl KLogStateExportView for MemoryExportView {
+    async fn entries_page(
+        &self,
+        after_id: u64,
+        last_id: u64,
+        limit: usize,
+    ) -> KResult<Vec<KLogEntry>> {
+        let start = self.entries.partition_point(|e| e.id <= after_id);
+        Ok(self.entries[start..]
+            .iter()
+            .take_while(|e| e.id <= last_id)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn meta_page(
+        &self,
+        after_key: Option<&str>,
+        limit: usize,
+    ) -> KResult<Vec<KLogMetaEntry>> {
+        let iter: Box<dyn Iterator<Item = &KLogMetaEntry>> = match after_key {
+            Some(after) => Box::new(
+                self.metas
+                    .range::<str, _>((std::ops::Bound::Excluded(after), std::ops::Bound::Unbounded))
+                    .map(|(_, v)| v),
+            ),
+            None => Box::new(self.metas.values()),
+        };
+        Ok(iter.take(limit).cloned().collect())
+    }
+}
+
 /// A simple in-memory state store implementation.
 pub struct MemoryStateStore {
     logs: Arc<AsyncMutex<Vec<KLogEntry>>>,
@@ -141,50 +181,9 @@ impl KLogStateStore for MemoryStateStore {
 
     async fn query(&self, query: KLogQuery) -> KResult<Vec<KLogEntry>> {
         let logs = self.logs.lock().await;
-        let source_filter = query
-            .source
-            .as_deref()
-            .map(str::trim)
-            .filter(|v| !v.is_empty());
-        let attr_key_filter = query
-            .attr_key
-            .as_deref()
-            .map(str::trim)
-            .filter(|v| !v.is_empty());
-        let attr_value_filter = query
-            .attr_value
-            .as_deref()
-            .map(str::trim)
-            .filter(|v| !v.is_empty());
-        if attr_key_filter.is_none() && attr_value_filter.is_some() {
-            return Ok(Vec::new());
-        }
         let mut entries = logs
             .iter()
-            .filter(|e| {
-                query.start_id.map(|start| e.id >= start).unwrap_or(true)
-                    && query.end_id.map(|end| e.id <= end).unwrap_or(true)
-                    && query.level.map(|level| e.level == level).unwrap_or(true)
-                    && source_filter
-                        .map(|source| {
-                            e.source
-                                .as_deref()
-                                .map(str::trim)
-                                .filter(|v| !v.is_empty())
-                                .map(|v| v == source)
-                                .unwrap_or(false)
-                        })
-                        .unwrap_or(true)
-                    && attr_key_filter
-                        .map(|key| {
-                            e.attrs.get(key).is_some_and(|value| {
-                                attr_value_filter
-                                    .map(|expect| value == expect)
-                                    .unwrap_or(true)
-                            })
-                        })
-                        .unwrap_or(true)
-            })
+            .filter(|e| query.matches(e))
             .cloned()
             .collect::<Vec<_>>();
         drop(logs);
@@ -253,6 +252,19 @@ impl KLogStateStore for MemoryStateStore {
         Ok(out)
     }
 
+    async fn truncate_before(&self, before_id: u64, source: Option<&str>) -> KResult<u64> {
+        let source = source.map(str::trim).filter(|v| !v.is_empty());
+        let mut logs = self.logs.lock().await;
+        let before = logs.len();
+        logs.retain(|e| {
+            let in_scope = source
+                .map(|source| e.source.as_deref().map(str::trim) == Some(source))
+                .unwrap_or(true);
+            !(in_scope && e.id < before_id)
+        });
+        Ok((before - logs.len()) as u64)
+    }
+
     async fn build_snapshot(&self) -> KResult<KLogStateSnapshot> {
         let logs = self.logs.lock().await;
         let metas = self.metas.lock().await;
@@ -299,6 +311,92 @@ impl KLogStateStore for MemoryStateStore {
         Ok(())
     }
 
+    async fn list_meta_after(
+        &self,
+        after_key: Option<&str>,
+        limit: usize,
+    ) -> KResult<Vec<KLogMetaEntry>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let metas = self.metas.lock().await;
+        let mut out = metas
+            .values()
+            .filter(|item| {
+                after_key
+                    .map(|after| item.key.as_str() > after)
+                    .unwrap_or(true)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        out.sort_by(|a, b| a.key.cmp(&b.key));
+        out.truncate(limit);
+        Ok(out)
+    }
+
+    async fn pin_export_view(&self) -> KResult<Box<dyn KLogStateExportView>> {
+        let mut entries = self.logs.lock().await.clone();
+        entries.sort_by_key(|e| e.id);
+        let metas = self
+            .metas
+            .lock()
+            .await
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        Ok(Box::new(MemoryExportView { entries, metas }))
+    }
+
+    async fn clear_for_snapshot_install(&self) -> KResult<()> {
+        self.logs.lock().await.clear();
+        self.metas.lock().await.clear();
+        self.next_log_id.store(1, Ordering::SeqCst);
+        self.request_dedup.lock().await.clear();
+        Ok(())
+    }
+
+    async fn install_snapshot_entries(&self, entries: Vec<KLogEntry>) -> KResult<()> {
+        let candidate_next = entries
+            .iter()
+            .map(|e| e.id.saturating_add(1))
+            .max()
+            .unwrap_or(0);
+        self.logs.lock().await.extend(entries);
+        self.next_log_id.fetch_max(candidate_next, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn install_snapshot_meta(&self, items: Vec<KLogMetaEntry>) -> KResult<()> {
+        let mut metas = self.metas.lock().await;
+        for item in items {
+            metas.insert(item.key.clone(), item);
+        }
+        Ok(())
+    }
+
+    async fn commit_meta_writes(
+        &self,
+        writes: Vec<(String, Option<KLogMetaEntry>)>,
+        meta: KLogStateMachineMeta,
+    ) -> KResult<()> {
+        // Hold both locks so readers never see the writes without the applied id.
+        let mut metas = self.metas.lock().await;
+        let mut state_machine_meta = self.state_machine_meta.lock().await;
+        for (key, item) in writes {
+            match item {
+                Some(item) => {
+                    metas.insert(key, item);
+                }
+                None => {
+                    metas.remove(&key);
+                }
+            }
+        }
+        *state_machine_meta = Some(meta);
+        Ok(())
+    }
+
     async fn load_next_log_id(&self) -> KResult<Option<u64>> {
         Ok(Some(self.next_log_id.load(Ordering::SeqCst)))
     }
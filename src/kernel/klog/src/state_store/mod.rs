@@ -1,7 +1,9 @@
 mod memory;
 mod rocksdb;
 mod store;
+mod watch;
 
 pub use memory::*;
 pub use rocksdb::*;
 pub use store::*;
+pub use watch::*;
@@ -1,13 +1,13 @@
 use super::store::{
-    KLogQuery, KLogQueryOrder, KLogStateMachineMeta, KLogStateSnapshot, KLogStateSnapshotData,
-    KLogStateStore, REQUEST_DEDUP_WINDOW_MS,
+    KLogQuery, KLogQueryOrder, KLogStateExportView, KLogStateMachineMeta, KLogStateSnapshot,
+    KLogStateSnapshotData, KLogStateStore, REQUEST_DEDUP_WINDOW_MS, tokenize_message,
 };
 use crate::{KLogEntry, KLogError, KLogLevel, KLogMetaEntry, KResult};
 use rocksdb::backup::{BackupEngine, BackupEngineOptions, RestoreOptions};
 use rocksdb::checkpoint::Checkpoint;
 use rocksdb::{
-    ColumnFamilyDescriptor, DB, DEFAULT_COLUMN_FAMILY_NAME, Direction, Env, IteratorMode, Options,
-    WriteBatch, WriteOptions,
+    ColumnFamily, ColumnFamilyDescriptor, DB, DEFAULT_COLUMN_FAMILY_NAME, Direction, Env,
+    IteratorMode, Options, Snapshot, WriteBatch, WriteOptions,
 };
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -15,16 +15,27 @@ use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
 
 const KEY_PREFIX_ENTRY: u8 = b'e';
 const KEY_NEXT_LOG_ID_META: &[u8] = b"m:next_log_id";
 const KEY_STATE_MACHINE_META: &[u8] = b"m:state_machine_meta";
 const KEY_REQUEST_DEDUP_PREFIX: &[u8] = b"m:req:";
 const KEY_DATA_META_PREFIX: &[u8] = b"d:";
+const KEY_LOG_INDEX_VERSION_META: &[u8] = b"m:log_index_version";
 const CF_LOGS: &str = "logs";
 const CF_META: &str = "meta";
 const CF_INDEX_LEVEL: &str = "idx_level";
 const CF_INDEX_SOURCE: &str = "idx_source";
+const CF_INDEX_TIME: &str = "idx_time";
+const CF_INDEX_ATTR: &str = "idx_attr";
+const CF_INDEX_TERM: &str = "idx_term";
+// Bump when the set or layout of secondary indexes changes, existing dbs are rebuilt on open.
+const LOG_INDEX_VERSION: u64 = 2;
+// Longer words are still matched by the query filter, just not indexed.
+const MESSAGE_INDEX_TERM_MAX_LEN: usize = 64;
+// Time range scans collect candidate ids before sorting them, give up above this.
+const TIME_INDEX_MAX_CANDIDATES: usize = 100_000;
 const CHECKPOINT_SNAPSHOT_MAGIC: &str = "klog-rdb-checkpoint-v1";
 const CHECKPOINT_SNAPSHOT_PREFIX: &[u8] = b"KLOG_RDB_CP1";
 const BACKUP_ENGINE_SNAPSHOT_MAGIC: &str = "klog-rdb-backup-v1";
@@ -45,6 +56,15 @@ struct LegacyKLogMetaEntryV0 {
     updated_by: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LegacyKLogMetaEntryV1 {
+    key: String,
+    value: String,
+    updated_at: u64,
+    updated_by: u64,
+    revision: u64,
+}
+
 fn entry_key(id: u64) -> [u8; 9] {
     let mut key = [0u8; 9];
     key[0] = KEY_PREFIX_ENTRY;
@@ -123,6 +143,10 @@ fn normalize_source(source: Option<&str>) -> Option<&str> {
     source.map(|v| v.trim()).filter(|v| !v.is_empty())
 }
 
+fn normalize_attr_filter(value: Option<&str>) -> Option<&str> {
+    value.map(|v| v.trim()).filter(|v| !v.is_empty())
+}
+
 fn level_index_code(level: KLogLevel) -> u8 {
     match level {
         KLogLevel::Trace => 1,
@@ -141,15 +165,6 @@ fn level_index_key(level: KLogLevel, id: u64) -> [u8; 9] {
     key
 }
 
-fn decode_level_index_id(key: &[u8], level: KLogLevel) -> Option<u64> {
-    if key.len() != 9 || key[0] != level_index_code(level) {
-        return None;
-    }
-    let mut id = [0u8; 8];
-    id.copy_from_slice(&key[1..]);
-    Some(u64::from_be_bytes(id))
-}
-
 fn source_index_prefix(source: &str) -> Vec<u8> {
     let source_bytes = source.as_bytes();
     let mut key = Vec::with_capacity(4 + source_bytes.len());
@@ -165,8 +180,52 @@ fn source_index_key(source: &str, id: u64) -> Vec<u8> {
 }
 
 fn decode_source_index_id(key: &[u8], source: &str) -> Option<u64> {
-    let prefix = source_index_prefix(source);
-    if key.len() != prefix.len() + 8 || !key.starts_with(&prefix) {
+    decode_prefixed_index_id(key, &source_index_prefix(source))
+}
+
+fn time_index_key(timestamp: u64, id: u64) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    key[..8].copy_from_slice(&timestamp.to_be_bytes());
+    key[8..].copy_from_slice(&id.to_be_bytes());
+    key
+}
+
+fn decode_time_index_key(key: &[u8]) -> Option<(u64, u64)> {
+    if key.len() != 16 {
+        return None;
+    }
+    let mut timestamp = [0u8; 8];
+    timestamp.copy_from_slice(&key[..8]);
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&key[8..]);
+    Some((u64::from_be_bytes(timestamp), u64::from_be_bytes(id)))
+}
+
+fn attr_index_prefix(key: &str, value: &str) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(8 + key.len() + value.len());
+    prefix.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    prefix.extend_from_slice(key.as_bytes());
+    prefix.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    prefix.extend_from_slice(value.as_bytes());
+    prefix
+}
+
+fn term_index_prefix(term: &str) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(4 + term.len());
+    prefix.extend_from_slice(&(term.len() as u32).to_be_bytes());
+    prefix.extend_from_slice(term.as_bytes());
+    prefix
+}
+
+fn prefixed_index_key(prefix: &[u8], id: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(prefix.len() + 8);
+    key.extend_from_slice(prefix);
+    key.extend_from_slice(&id.to_be_bytes());
+    key
+}
+
+fn decode_prefixed_index_id(key: &[u8], prefix: &[u8]) -> Option<u64> {
+    if key.len() != prefix.len() + 8 || !key.starts_with(prefix) {
         return None;
     }
     let mut id = [0u8; 8];
@@ -174,58 +233,154 @@ fn decode_source_index_id(key: &[u8], source: &str) -> Option<u64> {
     Some(u64::from_be_bytes(id))
 }
 
-fn entry_matches_query(entry: &KLogEntry, query: &KLogQuery) -> bool {
-    if let Some(level) = query.level
-        && entry.level != level
-    {
-        return false;
+fn indexed_message_terms(message: &str) -> impl Iterator<Item = String> {
+    tokenize_message(message)
+        .into_iter()
+        .filter(|term| term.len() <= MESSAGE_INDEX_TERM_MAX_LEN)
+}
+
+/// Words that are guaranteed to be whole words of any message containing `needle`.
+///
+/// The first and last word of the needle may be cut in the middle of a longer word,
+/// so they only count when the needle has a separator before/after them.
+fn complete_terms_of_substring(needle: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut word_start = None;
+    for (pos, c) in needle.char_indices() {
+        if c.is_alphanumeric() {
+            word_start.get_or_insert(pos);
+            continue;
+        }
+        if let Some(start) = word_start.take()
+            && start > 0
+        {
+            terms.push(needle[start..pos].to_lowercase());
+        }
     }
-    if let Some(source) = query
-        .source
-        .as_deref()
-        .map(str::trim)
-        .filter(|v| !v.is_empty())
-    {
-        let entry_source = normalize_source(entry.source.as_deref());
-        if entry_source != Some(source) {
-            return false;
-        }
-    }
-
-    let attr_key = query
-        .attr_key
-        .as_deref()
-        .map(str::trim)
-        .filter(|v| !v.is_empty());
-    let attr_value = query
-        .attr_value
-        .as_deref()
-        .map(str::trim)
-        .filter(|v| !v.is_empty());
-    if attr_key.is_none() && attr_value.is_some() {
-        return false;
-    }
-    if let Some(attr_key) = attr_key {
-        let Some(actual) = entry.attrs.get(attr_key) else {
-            return false;
+    terms
+}
+
+/// Pick the most selective message term that can be served by the term index.
+fn query_index_term(query: &KLogQuery) -> Option<String> {
+    let mut terms = query
+        .message_terms
+        .iter()
+        .flat_map(|term| tokenize_message(term))
+        .collect::<Vec<_>>();
+    if let Some(needle) = query.message_contains.as_deref() {
+        terms.extend(complete_terms_of_substring(needle));
+    }
+    // Longer words tend to be rarer.
+    terms
+        .into_iter()
+        .filter(|term| term.len() <= MESSAGE_INDEX_TERM_MAX_LEN)
+        .max_by_key(|term| term.len())
+}
+
+fn query_index_attr(query: &KLogQuery) -> Option<(String, String)> {
+    let legacy = normalize_attr_filter(query.attr_key.as_deref()).and_then(|key| {
+        normalize_attr_filter(query.attr_value.as_deref()).map(|value| (key, value))
+    });
+    legacy
+        .or_else(|| {
+            query
+                .attrs
+                .iter()
+                .find_map(|f| f.value.as_deref().map(|value| (f.key.as_str(), value)))
+        })
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+}
+
+/// Secondary index column families, written in the same batch as the entry itself.
+struct LogIndexCfs<'a> {
+    level: &'a ColumnFamily,
+    source: &'a ColumnFamily,
+    time: &'a ColumnFamily,
+    attr: &'a ColumnFamily,
+    term: &'a ColumnFamily,
+}
+
+impl<'a> LogIndexCfs<'a> {
+    fn open(db: &'a DB) -> Result<Self, String> {
+        let cf = |name: &str| {
+            db.cf_handle(name).ok_or_else(|| {
+                let msg = format!("Missing column family '{}'", name);
+                error!("{}", msg);
+                msg
+            })
         };
-        if let Some(expected) = attr_value
-            && actual != expected
-        {
-            return false;
+        Ok(Self {
+            level: cf(CF_INDEX_LEVEL)?,
+            source: cf(CF_INDEX_SOURCE)?,
+            time: cf(CF_INDEX_TIME)?,
+            attr: cf(CF_INDEX_ATTR)?,
+            term: cf(CF_INDEX_TERM)?,
+        })
+    }
+
+    fn all(&self) -> [(&'static str, &'a ColumnFamily); 5] {
+        [
+            (CF_INDEX_LEVEL, self.level),
+            (CF_INDEX_SOURCE, self.source),
+            (CF_INDEX_TIME, self.time),
+            (CF_INDEX_ATTR, self.attr),
+            (CF_INDEX_TERM, self.term),
+        ]
+    }
+
+    fn put_entry(&self, batch: &mut WriteBatch, entry: &KLogEntry) {
+        batch.put_cf(self.level, level_index_key(entry.level, entry.id), []);
+        if let Some(source) = normalize_source(entry.source.as_deref()) {
+            batch.put_cf(self.source, source_index_key(source, entry.id), []);
+        }
+        batch.put_cf(self.time, time_index_key(entry.timestamp, entry.id), []);
+        for (key, value) in entry.attrs.iter() {
+            let prefix = attr_index_prefix(key, value);
+            batch.put_cf(self.attr, prefixed_index_key(&prefix, entry.id), []);
+        }
+        for term in indexed_message_terms(entry.message.as_str()) {
+            let prefix = term_index_prefix(term.as_str());
+            batch.put_cf(self.term, prefixed_index_key(&prefix, entry.id), []);
         }
     }
 
-    true
+    fn delete_entry(&self, batch: &mut WriteBatch, entry: &KLogEntry) {
+        batch.delete_cf(self.level, level_index_key(entry.level, entry.id));
+        if let Some(source) = normalize_source(entry.source.as_deref()) {
+            batch.delete_cf(self.source, source_index_key(source, entry.id));
+        }
+        batch.delete_cf(self.time, time_index_key(entry.timestamp, entry.id));
+        for (key, value) in entry.attrs.iter() {
+            let prefix = attr_index_prefix(key, value);
+            batch.delete_cf(self.attr, prefixed_index_key(&prefix, entry.id));
+        }
+        for term in indexed_message_terms(entry.message.as_str()) {
+            let prefix = term_index_prefix(term.as_str());
+            batch.delete_cf(self.term, prefixed_index_key(&prefix, entry.id));
+        }
+    }
 }
 
 fn decode_meta_entry_with_legacy(raw: &[u8]) -> KResult<KLogMetaEntry> {
-    let decoded_v1: Result<(KLogMetaEntry, usize), _> =
+    let decoded_v2: Result<(KLogMetaEntry, usize), _> =
         bincode::serde::decode_from_slice(raw, bincode::config::legacy());
-    if let Ok((item, _)) = decoded_v1 {
+    if let Ok((item, _)) = decoded_v2 {
         return Ok(item);
     }
 
+    let decoded_v1: Result<(LegacyKLogMetaEntryV1, usize), _> =
+        bincode::serde::decode_from_slice(raw, bincode::config::legacy());
+    if let Ok((legacy, _)) = decoded_v1 {
+        return Ok(KLogMetaEntry {
+            key: legacy.key,
+            value: legacy.value,
+            updated_at: legacy.updated_at,
+            updated_by: legacy.updated_by,
+            revision: legacy.revision,
+            lease_id: None,
+        });
+    }
+
     let (legacy, _): (LegacyKLogMetaEntryV0, usize) =
         bincode::serde::decode_from_slice(raw, bincode::config::legacy())
             .map_err(|e| klog_err_with_context("Failed to decode rocksdb data meta entry", e))?;
@@ -235,6 +390,7 @@ fn decode_meta_entry_with_legacy(raw: &[u8]) -> KResult<KLogMetaEntry> {
         updated_at: legacy.updated_at,
         updated_by: legacy.updated_by,
         revision: 1,
+        lease_id: None,
     })
 }
 
@@ -292,7 +448,10 @@ fn open_rocksdb_with_cfs(path: &Path, create_if_missing: bool) -> Result<DB, Str
         ColumnFamilyDescriptor::new(CF_LOGS, logs_cf_opts),
         ColumnFamilyDescriptor::new(CF_META, meta_cf_opts),
         ColumnFamilyDescriptor::new(CF_INDEX_LEVEL, idx_cf_opts.clone()),
-        ColumnFamilyDescriptor::new(CF_INDEX_SOURCE, idx_cf_opts),
+        ColumnFamilyDescriptor::new(CF_INDEX_SOURCE, idx_cf_opts.clone()),
+        ColumnFamilyDescriptor::new(CF_INDEX_TIME, idx_cf_opts.clone()),
+        ColumnFamilyDescriptor::new(CF_INDEX_ATTR, idx_cf_opts.clone()),
+        ColumnFamilyDescriptor::new(CF_INDEX_TERM, idx_cf_opts),
     ];
 
     DB::open_cf_descriptors(&opts, path, cfs).map_err(|e| {
@@ -376,50 +535,61 @@ fn rebuild_log_indexes_if_needed(db: &DB) -> Result<(), String> {
         error!("{}", msg);
         msg
     })?;
-    let idx_level_cf = db.cf_handle(CF_INDEX_LEVEL).ok_or_else(|| {
-        let msg = format!("Missing column family '{}'", CF_INDEX_LEVEL);
-        error!("{}", msg);
-        msg
-    })?;
-    let idx_source_cf = db.cf_handle(CF_INDEX_SOURCE).ok_or_else(|| {
-        let msg = format!("Missing column family '{}'", CF_INDEX_SOURCE);
+    let meta_cf = db.cf_handle(CF_META).ok_or_else(|| {
+        let msg = format!("Missing column family '{}'", CF_META);
         error!("{}", msg);
         msg
     })?;
+    let index_cfs = LogIndexCfs::open(db)?;
 
+    let index_version = match db
+        .get_cf(meta_cf, KEY_LOG_INDEX_VERSION_META)
+        .map_err(|e| format!("Failed to read log index version: {}", e))?
+    {
+        Some(raw) => decode_u64_be(raw.as_ref()).map_err(|e| e.to_string())?,
+        None => 0,
+    };
     let has_logs = db
-        .iterator_cf(&logs_cf, IteratorMode::Start)
+        .iterator_cf(logs_cf, IteratorMode::Start)
         .find_map(|item| match item {
             Ok((k, _)) if decode_entry_key(k.as_ref()).is_some() => Some(true),
             Ok(_) => None,
             Err(_) => Some(false),
         })
         .unwrap_or(false);
-    if !has_logs {
-        return Ok(());
-    }
-
+    // Databases written before the version marker existed always have at least the
+    // level index when they have logs; an empty level index means it was never built.
     let has_level_index = db
-        .iterator_cf(&idx_level_cf, IteratorMode::Start)
+        .iterator_cf(index_cfs.level, IteratorMode::Start)
         .next()
         .is_some();
-    if has_level_index {
+    let mut batch = WriteBatch::default();
+    batch.put_cf(
+        meta_cf,
+        KEY_LOG_INDEX_VERSION_META,
+        LOG_INDEX_VERSION.to_be_bytes(),
+    );
+    if !has_logs || (has_level_index && index_version >= LOG_INDEX_VERSION) {
+        if index_version != LOG_INDEX_VERSION {
+            db.write_opt(batch, &build_write_options(true))
+                .map_err(|e| format!("Failed to persist log index version: {}", e))?;
+        }
         return Ok(());
     }
 
-    info!("RocksDbStateStore rebuilding secondary indexes from logs");
-    let mut batch = WriteBatch::default();
-    for item in db.iterator_cf(&idx_level_cf, IteratorMode::Start) {
-        let (k, _) = item.map_err(|e| format!("Failed to iterate level index cf: {}", e))?;
-        batch.delete_cf(&idx_level_cf, k.as_ref());
-    }
-    for item in db.iterator_cf(&idx_source_cf, IteratorMode::Start) {
-        let (k, _) = item.map_err(|e| format!("Failed to iterate source index cf: {}", e))?;
-        batch.delete_cf(&idx_source_cf, k.as_ref());
+    info!(
+        "RocksDbStateStore rebuilding secondary indexes from logs: index_version={}, target_version={}",
+        index_version, LOG_INDEX_VERSION
+    );
+    for (name, cf) in index_cfs.all() {
+        for item in db.iterator_cf(cf, IteratorMode::Start) {
+            let (k, _) = item.map_err(|e| format!("Failed to iterate index cf {}: {}", name, e))?;
+            batch.delete_cf(cf, k.as_ref());
+        }
     }
 
     let mut rebuilt = 0usize;
-    for item in db.iterator_cf(&logs_cf, IteratorMode::Start) {
+    for item in db.iterator_cf(logs_cf, IteratorMode::Start) {
         let (k, v) =
             item.map_err(|e| format!("Failed to iterate logs cf for index rebuild: {}", e))?;
         if decode_entry_key(k.as_ref()).is_none() {
@@ -428,10 +598,7 @@ fn rebuild_log_indexes_if_needed(db: &DB) -> Result<(), String> {
         let (entry, _): (KLogEntry, usize) =
             bincode::serde::decode_from_slice(v.as_ref(), bincode::config::legacy())
                 .map_err(|e| format!("Failed to decode entry while rebuilding indexes: {}", e))?;
-        batch.put_cf(&idx_level_cf, level_index_key(entry.level, entry.id), []);
-        if let Some(source) = normalize_source(entry.source.as_deref()) {
-            batch.put_cf(&idx_source_cf, source_index_key(source, entry.id), []);
-        }
+        index_cfs.put_entry(&mut batch, &entry);
         rebuilt += 1;
     }
 
@@ -840,143 +1007,420 @@ impl RocksDbStateStore {
         Ok(())
     }
 
-    fn clear_indexes_in_batch(&self, batch: &mut WriteBatch) -> KResult<()> {
-        let level_cf = self.db.cf_handle(CF_INDEX_LEVEL).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_INDEX_LEVEL);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
-        let source_cf = self.db.cf_handle(CF_INDEX_SOURCE).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_INDEX_SOURCE);
+    fn read_entry(&self, logs_cf: &ColumnFamily, id: u64) -> KResult<Option<KLogEntry>> {
+        let Some(raw) = self
+            .db
+            .get_cf(logs_cf, entry_key(id).as_ref())
+            .map_err(|e| klog_err_with_context("Failed to read state entry from rocksdb", e))?
+        else {
+            return Ok(None);
+        };
+        let (entry, _): (KLogEntry, usize) =
+            bincode::serde::decode_from_slice(raw.as_ref(), bincode::config::legacy()).map_err(
+                |e| klog_err_with_context("Failed to deserialize state entry from rocksdb", e),
+            )?;
+        Ok(Some(entry))
+    }
+
+    fn logs_cf(&self) -> KResult<&ColumnFamily> {
+        self.db.cf_handle(CF_LOGS).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_LOGS);
             error!("{}", msg);
             klog_err(msg)
-        })?;
+        })
+    }
 
-        let mut deleted_level = 0usize;
-        for item in self.db.iterator_cf(&level_cf, IteratorMode::Start) {
-            let (k, _) = item.map_err(|e| {
-                klog_err_with_context("Failed to iterate rocksdb while clearing level index", e)
-            })?;
-            batch.delete_cf(&level_cf, k.as_ref());
-            deleted_level += 1;
-        }
+    /// Walk an index whose keys are `prefix + id` in query order and keep matching entries.
+    fn query_by_prefix_index(
+        &self,
+        index_cf: &ColumnFamily,
+        index_name: &str,
+        prefix: &[u8],
+        query: &KLogQuery,
+    ) -> KResult<Vec<KLogEntry>> {
+        let logs_cf = self.logs_cf()?;
+        let (seek_key, direction) = match query.order {
+            KLogQueryOrder::Asc => (
+                prefixed_index_key(prefix, query.start_id.unwrap_or(0)),
+                Direction::Forward,
+            ),
+            KLogQueryOrder::Desc => (
+                prefixed_index_key(prefix, query.end_id.unwrap_or(u64::MAX)),
+                Direction::Reverse,
+            ),
+        };
 
-        let mut deleted_source = 0usize;
-        for item in self.db.iterator_cf(&source_cf, IteratorMode::Start) {
+        let mut out = Vec::with_capacity(query.limit.min(1024));
+        let iter = self
+            .db
+            .iterator_cf(index_cf, IteratorMode::From(&seek_key, direction));
+        for item in iter {
             let (k, _) = item.map_err(|e| {
-                klog_err_with_context("Failed to iterate rocksdb while clearing source index", e)
+                klog_err_with_context(format!("Failed to iterate rocksdb index {}", index_name), e)
             })?;
-            batch.delete_cf(&source_cf, k.as_ref());
-            deleted_source += 1;
+            let Some(id) = decode_prefixed_index_id(k.as_ref(), prefix) else {
+                break;
+            };
+            let past_range = match query.order {
+                KLogQueryOrder::Asc => query.end_id.is_some_and(|end| id > end),
+                KLogQueryOrder::Desc => query.start_id.is_some_and(|start| id < start),
+            };
+            if past_range {
+                break;
+            }
+            let Some(entry) = self.read_entry(logs_cf, id)? else {
+                continue;
+            };
+            if !query.matches(&entry) {
+                continue;
+            }
+            out.push(entry);
+            if out.len() >= query.limit {
+                break;
+            }
         }
-
-        debug!(
-            "RocksDbStateStore clear_indexes_in_batch done: deleted_level={}, deleted_source={}",
-            deleted_level, deleted_source
-        );
-        Ok(())
+        Ok(out)
     }
 
-    fn replace_with_entries(
+    /// Serve a timestamp range from the time index.
+    ///
+    /// The index is ordered by timestamp rather than id, so candidate ids are collected and
+    /// sorted first. Returns `None` when the range is too wide for that to pay off.
+    fn query_by_time_index(
         &self,
-        entries: Vec<KLogEntry>,
-        meta_entries: Vec<KLogMetaEntry>,
-    ) -> KResult<()> {
-        info!(
-            "RocksDbStateStore replace_with_entries start: snapshot_mode={:?}, incoming_entries={}, incoming_meta_entries={}",
-            self.snapshot_mode,
-            summarize_entry_ids(&entries),
-            meta_entries.len()
-        );
-        let mut batch = WriteBatch::default();
-        self.clear_entries_in_batch(&mut batch)?;
-        self.clear_request_dedup_in_batch(&mut batch)?;
-        self.clear_data_meta_in_batch(&mut batch)?;
-        self.clear_indexes_in_batch(&mut batch)?;
-        let logs_cf = self.db.cf_handle(CF_LOGS).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_LOGS);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
-        let idx_level_cf = self.db.cf_handle(CF_INDEX_LEVEL).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_INDEX_LEVEL);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
-        let idx_source_cf = self.db.cf_handle(CF_INDEX_SOURCE).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_INDEX_SOURCE);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
-        let meta_cf = self.db.cf_handle(CF_META).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_META);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
-
-        let mut max_id = 0u64;
-        for entry in entries {
-            if entry.id > max_id {
-                max_id = entry.id;
+        time_cf: &ColumnFamily,
+        query: &KLogQuery,
+    ) -> KResult<Option<Vec<KLogEntry>>> {
+        let logs_cf = self.logs_cf()?;
+        let start_key = time_index_key(query.start_time.unwrap_or(0), 0);
+        let end_time = query.end_time.unwrap_or(u64::MAX);
+
+        let mut ids = Vec::new();
+        let iter = self
+            .db
+            .iterator_cf(time_cf, IteratorMode::From(&start_key, Direction::Forward));
+        for item in iter {
+            let (k, _) =
+                item.map_err(|e| klog_err_with_context("Failed to iterate rocksdb time index", e))?;
+            let Some((timestamp, id)) = decode_time_index_key(k.as_ref()) else {
+                continue;
+            };
+            if timestamp > end_time {
+                break;
             }
-            let key = entry_key(entry.id);
-            let value =
-                bincode::serde::encode_to_vec(&entry, bincode::config::legacy()).map_err(|e| {
-                    klog_err_with_context("Failed to serialize state entry for rocksdb install", e)
-                })?;
-            batch.put_cf(&logs_cf, key, value);
-            batch.put_cf(&idx_level_cf, level_index_key(entry.level, entry.id), []);
-            if let Some(source) = normalize_source(entry.source.as_deref()) {
-                batch.put_cf(&idx_source_cf, source_index_key(source, entry.id), []);
+            if query.start_id.is_some_and(|start| id < start)
+                || query.end_id.is_some_and(|end| id > end)
+            {
+                continue;
+            }
+            ids.push(id);
+            if ids.len() > TIME_INDEX_MAX_CANDIDATES {
+                debug!(
+                    "RocksDbStateStore time index range too wide, fallback to scan: start_time={:?}, end_time={:?}",
+                    query.start_time, query.end_time
+                );
+                return Ok(None);
             }
         }
-        let next_log_id = max_id.saturating_add(1).max(1);
-        batch.put_cf(&meta_cf, KEY_NEXT_LOG_ID_META, next_log_id.to_be_bytes());
-        for item in meta_entries {
-            let encoded = bincode::serde::encode_to_vec(&item, bincode::config::legacy())
-                .map_err(|e| klog_err_with_context("Failed to encode data meta entry", e))?;
-            let key = data_meta_key(item.key.as_str());
-            batch.put_cf(&meta_cf, key, encoded);
+
+        ids.sort_unstable();
+        if query.order == KLogQueryOrder::Desc {
+            ids.reverse();
+        }
+        let mut out = Vec::with_capacity(query.limit.min(ids.len()));
+        for id in ids {
+            let Some(entry) = self.read_entry(logs_cf, id)? else {
+                continue;
+            };
+            if !query.matches(&entry) {
+                continue;
+            }
+            out.push(entry);
+            if out.len() >= query.limit {
+                break;
+            }
         }
+        Ok(Some(out))
+    }
 
-        let write_opts = self.write_options();
-        self.db.write_opt(batch, &write_opts).map_err(|e| {
-            klog_err_with_context("Failed to write entries during rocksdb snapshot install", e)
-        })?;
-        info!(
-            "RocksDbStateStore replace_with_entries completed: snapshot_mode={:?}, next_log_id={}",
-            self.snapshot_mode, next_log_id
-        );
+    fn query_by_scan(&self, query: &KLogQuery) -> KResult<Vec<KLogEntry>> {
+        let logs_cf = self.logs_cf()?;
+        let mut out = Vec::with_capacity(query.limit.min(1024));
+        match query.order {
+            KLogQueryOrder::Asc => {
+                let iter = if let Some(start_id) = query.start_id {
+                    let start_key = entry_key(start_id);
+                    self.db
+                        .iterator_cf(logs_cf, IteratorMode::From(&start_key, Direction::Forward))
+                } else {
+                    self.db.iterator_cf(logs_cf, IteratorMode::Start)
+                };
 
-        Ok(())
-    }
+                for item in iter {
+                    let (k, v) = item
+                        .map_err(|e| klog_err_with_context("Failed to iterate rocksdb entry", e))?;
+                    let Some(id) = decode_entry_key(&k) else {
+                        continue;
+                    };
+                    if query.start_id.map(|start| id < start).unwrap_or(false) {
+                        continue;
+                    }
+                    if query.end_id.map(|end| id > end).unwrap_or(false) {
+                        break;
+                    }
 
-    fn replace_with_db(&self, source_db: &DB) -> KResult<()> {
-        info!(
-            "RocksDbStateStore replace_with_db start: snapshot_mode={:?}",
-            self.snapshot_mode
-        );
-        let mut batch = WriteBatch::default();
-        self.clear_entries_in_batch(&mut batch)?;
-        self.clear_request_dedup_in_batch(&mut batch)?;
-        self.clear_data_meta_in_batch(&mut batch)?;
-        self.clear_indexes_in_batch(&mut batch)?;
-        let logs_cf = self.db.cf_handle(CF_LOGS).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_LOGS);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
-        let idx_level_cf = self.db.cf_handle(CF_INDEX_LEVEL).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_INDEX_LEVEL);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
-        let idx_source_cf = self.db.cf_handle(CF_INDEX_SOURCE).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_INDEX_SOURCE);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
-        let meta_cf = self.db.cf_handle(CF_META).ok_or_else(|| {
+                    let (entry, _): (KLogEntry, usize) =
+                        bincode::serde::decode_from_slice(v.as_ref(), bincode::config::legacy())
+                            .map_err(|e| {
+                                klog_err_with_context(
+                                    "Failed to deserialize state entry from rocksdb",
+                                    e,
+                                )
+                            })?;
+                    if !query.matches(&entry) {
+                        continue;
+                    }
+                    out.push(entry);
+                    if out.len() >= query.limit {
+                        break;
+                    }
+                }
+            }
+            KLogQueryOrder::Desc => {
+                let iter = if let Some(end_id) = query.end_id {
+                    let end_key = entry_key(end_id);
+                    self.db
+                        .iterator_cf(logs_cf, IteratorMode::From(&end_key, Direction::Reverse))
+                } else {
+                    self.db.iterator_cf(logs_cf, IteratorMode::End)
+                };
+
+                for item in iter {
+                    let (k, v) = item
+                        .map_err(|e| klog_err_with_context("Failed to iterate rocksdb entry", e))?;
+                    let Some(id) = decode_entry_key(&k) else {
+                        continue;
+                    };
+                    if query.end_id.map(|end| id > end).unwrap_or(false) {
+                        continue;
+                    }
+                    if query.start_id.map(|start| id < start).unwrap_or(false) {
+                        break;
+                    }
+
+                    let (entry, _): (KLogEntry, usize) =
+                        bincode::serde::decode_from_slice(v.as_ref(), bincode::config::legacy())
+                            .map_err(|e| {
+                                klog_err_with_context(
+                                    "Failed to deserialize state entry from rocksdb",
+                                    e,
+                                )
+                            })?;
+                    if !query.matches(&entry) {
+                        continue;
+                    }
+                    out.push(entry);
+                    if out.len() >= query.limit {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn index_cfs(&self) -> KResult<LogIndexCfs<'_>> {
+        LogIndexCfs::open(&self.db).map_err(klog_err)
+    }
+
+    fn clear_indexes_in_batch(&self, batch: &mut WriteBatch) -> KResult<()> {
+        let index_cfs = self.index_cfs()?;
+        let mut deleted = Vec::new();
+        for (name, cf) in index_cfs.all() {
+            let mut count = 0usize;
+            for item in self.db.iterator_cf(cf, IteratorMode::Start) {
+                let (k, _) = item.map_err(|e| {
+                    klog_err_with_context(
+                        format!("Failed to iterate rocksdb while clearing index {}", name),
+                        e,
+                    )
+                })?;
+                batch.delete_cf(cf, k.as_ref());
+                count += 1;
+            }
+            deleted.push(format!("{}={}", name, count));
+        }
+
+        debug!(
+            "RocksDbStateStore clear_indexes_in_batch done: deleted=[{}]",
+            deleted.join(", ")
+        );
+        Ok(())
+    }
+
+    fn delete_entry_in_batch(
+        &self,
+        batch: &mut WriteBatch,
+        entry: &KLogEntry,
+        logs_cf: &impl rocksdb::AsColumnFamilyRef,
+        index_cfs: &LogIndexCfs<'_>,
+    ) {
+        batch.delete_cf(logs_cf, entry_key(entry.id));
+        index_cfs.delete_entry(batch, entry);
+    }
+
+    fn purge_expired_request_dedup(&self, now_ms: u64) -> KResult<usize> {
+        let meta_cf = self.db.cf_handle(CF_META).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_META);
+            error!("{}", msg);
+            klog_err(msg)
+        })?;
+        let mut batch = WriteBatch::default();
+        let mut purged = 0usize;
+        let iter = self.db.iterator_cf(
+            &meta_cf,
+            IteratorMode::From(KEY_REQUEST_DEDUP_PREFIX, Direction::Forward),
+        );
+        for item in iter {
+            let (k, v) = item.map_err(|e| {
+                klog_err_with_context("Failed to iterate rocksdb while purging request dedup", e)
+            })?;
+            if !k.as_ref().starts_with(KEY_REQUEST_DEDUP_PREFIX) {
+                break;
+            }
+            let expired = match bincode::serde::decode_from_slice::<RequestDedupMeta, _>(
+                v.as_ref(),
+                bincode::config::legacy(),
+            ) {
+                Ok((record, _)) => {
+                    now_ms.saturating_sub(record.seen_at_ms) > REQUEST_DEDUP_WINDOW_MS
+                }
+                Err(_) => true,
+            };
+            if expired {
+                batch.delete_cf(&meta_cf, k.as_ref());
+                purged += 1;
+            }
+        }
+
+        if purged > 0 {
+            let write_opts = self.write_options();
+            self.db.write_opt(batch, &write_opts).map_err(|e| {
+                klog_err_with_context("Failed to purge expired request dedup index", e)
+            })?;
+        }
+        Ok(purged)
+    }
+
+    fn compact_after_truncate(&self, before_id: u64) -> KResult<()> {
+        for cf_name in [
+            CF_LOGS,
+            CF_INDEX_LEVEL,
+            CF_INDEX_SOURCE,
+            CF_INDEX_TIME,
+            CF_INDEX_ATTR,
+            CF_INDEX_TERM,
+            CF_META,
+        ] {
+            let cf = self.db.cf_handle(cf_name).ok_or_else(|| {
+                let msg = format!("Missing column family '{}'", cf_name);
+                error!("{}", msg);
+                klog_err(msg)
+            })?;
+            if cf_name == CF_LOGS {
+                // Entry keys are id-ordered, so only the truncated prefix needs compaction.
+                self.db.compact_range_cf(
+                    &cf,
+                    Some(entry_key(0).as_slice()),
+                    Some(entry_key(before_id).as_slice()),
+                );
+            } else {
+                self.db.compact_range_cf(&cf, None::<&[u8]>, None::<&[u8]>);
+            }
+        }
+        Ok(())
+    }
+
+    fn replace_with_entries(
+        &self,
+        entries: Vec<KLogEntry>,
+        meta_entries: Vec<KLogMetaEntry>,
+    ) -> KResult<()> {
+        info!(
+            "RocksDbStateStore replace_with_entries start: snapshot_mode={:?}, incoming_entries={}, incoming_meta_entries={}",
+            self.snapshot_mode,
+            summarize_entry_ids(&entries),
+            meta_entries.len()
+        );
+        let mut batch = WriteBatch::default();
+        self.clear_entries_in_batch(&mut batch)?;
+        self.clear_request_dedup_in_batch(&mut batch)?;
+        self.clear_data_meta_in_batch(&mut batch)?;
+        self.clear_indexes_in_batch(&mut batch)?;
+        let logs_cf = self.db.cf_handle(CF_LOGS).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_LOGS);
+            error!("{}", msg);
+            klog_err(msg)
+        })?;
+        let index_cfs = self.index_cfs()?;
+        let meta_cf = self.db.cf_handle(CF_META).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_META);
+            error!("{}", msg);
+            klog_err(msg)
+        })?;
+
+        let mut max_id = 0u64;
+        for entry in entries {
+            if entry.id > max_id {
+                max_id = entry.id;
+            }
+            let key = entry_key(entry.id);
+            let value =
+                bincode::serde::encode_to_vec(&entry, bincode::config::legacy()).map_err(|e| {
+                    klog_err_with_context("Failed to serialize state entry for rocksdb install", e)
+                })?;
+            batch.put_cf(&logs_cf, key, value);
+            index_cfs.put_entry(&mut batch, &entry);
+        }
+        let next_log_id = max_id.saturating_add(1).max(1);
+        batch.put_cf(&meta_cf, KEY_NEXT_LOG_ID_META, next_log_id.to_be_bytes());
+        for item in meta_entries {
+            let encoded = bincode::serde::encode_to_vec(&item, bincode::config::legacy())
+                .map_err(|e| klog_err_with_context("Failed to encode data meta entry", e))?;
+            let key = data_meta_key(item.key.as_str());
+            batch.put_cf(&meta_cf, key, encoded);
+        }
+
+        let write_opts = self.write_options();
+        self.db.write_opt(batch, &write_opts).map_err(|e| {
+            klog_err_with_context("Failed to write entries during rocksdb snapshot install", e)
+        })?;
+        info!(
+            "RocksDbStateStore replace_with_entries completed: snapshot_mode={:?}, next_log_id={}",
+            self.snapshot_mode, next_log_id
+        );
+
+        Ok(())
+    }
+
+    fn replace_with_db(&self, source_db: &DB) -> KResult<()> {
+        info!(
+            "RocksDbStateStore replace_with_db start: snapshot_mode={:?}",
+            self.snapshot_mode
+        );
+        let mut batch = WriteBatch::default();
+        self.clear_entries_in_batch(&mut batch)?;
+        self.clear_request_dedup_in_batch(&mut batch)?;
+        self.clear_data_meta_in_batch(&mut batch)?;
+        self.clear_indexes_in_batch(&mut batch)?;
+        let logs_cf = self.db.cf_handle(CF_LOGS).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_LOGS);
+            error!("{}", msg);
+            klog_err(msg)
+        })?;
+        let index_cfs = self.index_cfs()?;
+        let meta_cf = self.db.cf_handle(CF_META).ok_or_else(|| {
             let msg = format!("Missing column family '{}'", CF_META);
             error!("{}", msg);
             klog_err(msg)
@@ -1007,10 +1451,7 @@ impl RocksDbStateStore {
                     |e| klog_err_with_context("Failed to decode source entry for index rebuild", e),
                 )?;
             batch.put_cf(&logs_cf, k.as_ref(), v.as_ref());
-            batch.put_cf(&idx_level_cf, level_index_key(entry.level, entry.id), []);
-            if let Some(source) = normalize_source(entry.source.as_deref()) {
-                batch.put_cf(&idx_source_cf, source_index_key(source, entry.id), []);
-            }
+            index_cfs.put_entry(&mut batch, &entry);
             copied += 1;
             if id > max_id {
                 max_id = id;
@@ -1694,6 +2135,203 @@ impl RocksDbSnapshotStrategy for BackupEngineSnapshotStrategy {
     }
 }
 
+/// Collect data meta entries from an iterator positioned at the first candidate key,
+/// skipping `skip_key` (the exclusive lower bound) when it is present.
+fn collect_data_meta_page(
+    iter: impl Iterator<Item = Result<(Box<[u8]>, Box<[u8]>), rocksdb::Error>>,
+    skip_key: Option<&[u8]>,
+    limit: usize,
+) -> KResult<Vec<KLogMetaEntry>> {
+    let mut out = Vec::with_capacity(limit.min(1024));
+    for item in iter {
+        let (k, v) =
+            item.map_err(|e| klog_err_with_context("Failed to iterate rocksdb data meta", e))?;
+        if !k.as_ref().starts_with(KEY_DATA_META_PREFIX) {
+            break;
+        }
+        if skip_key.is_some_and(|skip| k.as_ref() == skip) {
+            continue;
+        }
+        out.push(decode_meta_entry_with_legacy(v.as_ref())?);
+        if out.len() >= limit {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+enum ExportViewRequest {
+    Entries {
+        after_id: u64,
+        last_id: u64,
+        limit: usize,
+        reply: oneshot::Sender<KResult<Vec<KLogEntry>>>,
+    },
+    Meta {
+        after_key: Option<String>,
+        limit: usize,
+        reply: oneshot::Sender<KResult<Vec<KLogMetaEntry>>>,
+    },
+}
+
+/// Export view backed by a rocksdb snapshot. The snapshot borrows the db, so it is held
+/// by a dedicated thread that serves page reads until the view is dropped.
+struct RocksDbExportView {
+    requests: std::sync::mpsc::Sender<ExportViewRequest>,
+}
+
+impl RocksDbExportView {
+    async fn pin(db: Arc<DB>) -> KResult<Self> {
+        let (requests, rx) = std::sync::mpsc::channel::<ExportViewRequest>();
+        let (pinned_tx, pinned_rx) = oneshot::channel();
+        std::thread::Builder::new()
+            .name("klog-export-view".to_string())
+            .spawn(move || {
+                let snapshot = db.snapshot();
+                let _ = pinned_tx.send(());
+                while let Ok(request) = rx.recv() {
+                    match request {
+                        ExportViewRequest::Entries {
+                            after_id,
+                            last_id,
+                            limit,
+                            reply,
+                        } => {
+                            let page =
+                                read_snapshot_entries(&db, &snapshot, after_id, last_id, limit);
+                            let _ = reply.send(page);
+                        }
+                        ExportViewRequest::Meta {
+                            after_key,
+                            limit,
+                            reply,
+                        } => {
+                            let page =
+                                read_snapshot_meta(&db, &snapshot, after_key.as_deref(), limit);
+                            let _ = reply.send(page);
+                        }
+                    }
+                }
+                debug!("RocksDbExportView released");
+            })
+            .map_err(|e| klog_err_with_context("Failed to spawn rocksdb export view thread", e))?;
+
+        pinned_rx
+            .await
+            .map_err(|_| klog_err("Rocksdb export view thread exited before pinning"))?;
+        Ok(Self { requests })
+    }
+
+    async fn request<T>(
+        &self,
+        make: impl FnOnce(oneshot::Sender<KResult<T>>) -> ExportViewRequest,
+    ) -> KResult<T> {
+        let (reply, rx) = oneshot::channel();
+        self.requests
+            .send(make(reply))
+            .map_err(|_| klog_err("Rocksdb export view thread is gone"))?;
+        rx.await
+            .map_err(|_| klog_err("Rocksdb export view thread dropped the request"))?
+    }
+}
+
+#[async_trait::async_trait]
+impl KLogStateExportView for RocksDbExportView {
+    async fn entries_page(
+        &self,
+        after_id: u64,
+        last_id: u64,
+        limit: usize,
+    ) -> KResult<Vec<KLogEntry>> {
+        self.request(|reply| ExportViewRequest::Entries {
+            after_id,
+            last_id,
+            limit,
+            reply,
+        })
+        .await
+    }
+
+    async fn meta_page(
+        &self,
+        after_key: Option<&str>,
+        limit: usize,
+    ) -> KResult<Vec<KLogMetaEntry>> {
+        let after_key = after_key.map(str::to_string);
+        self.request(|reply| ExportViewRequest::Meta {
+            after_key,
+            limit,
+            reply,
+        })
+        .await
+    }
+}
+
+fn read_snapshot_entries(
+    db: &DB,
+    snapshot: &Snapshot<'_>,
+    after_id: u64,
+    last_id: u64,
+    limit: usize,
+) -> KResult<Vec<KLogEntry>> {
+    if after_id >= last_id || limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let logs_cf = db.cf_handle(CF_LOGS).ok_or_else(|| {
+        let msg = format!("Missing column family '{}'", CF_LOGS);
+        error!("{}", msg);
+        klog_err(msg)
+    })?;
+    let start_key = entry_key(after_id.saturating_add(1));
+    let mut out = Vec::with_capacity(limit.min(1024));
+    for item in snapshot.iterator_cf(&logs_cf, IteratorMode::From(&start_key, Direction::Forward)) {
+        let (k, v) =
+            item.map_err(|e| klog_err_with_context("Failed to iterate rocksdb entry", e))?;
+        let Some(id) = decode_entry_key(&k) else {
+            continue;
+        };
+        if id > last_id {
+            break;
+        }
+
+        let (entry, _): (KLogEntry, usize) =
+            bincode::serde::decode_from_slice(v.as_ref(), bincode::config::legacy()).map_err(
+                |e| klog_err_with_context("Failed to deserialize state entry from rocksdb", e),
+            )?;
+        out.push(entry);
+        if out.len() >= limit {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+fn read_snapshot_meta(
+    db: &DB,
+    snapshot: &Snapshot<'_>,
+    after_key: Option<&str>,
+    limit: usize,
+) -> KResult<Vec<KLogMetaEntry>> {
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let meta_cf = db.cf_handle(CF_META).ok_or_else(|| {
+        let msg = format!("Missing column family '{}'", CF_META);
+        error!("{}", msg);
+        klog_err(msg)
+    })?;
+    let seek_key = after_key
+        .map(data_meta_key)
+        .unwrap_or_else(|| KEY_DATA_META_PREFIX.to_vec());
+    let iter = snapshot.iterator_cf(
+        &meta_cf,
+        IteratorMode::From(seek_key.as_slice(), Direction::Forward),
+    );
+    collect_data_meta_page(iter, after_key.map(|_| seek_key.as_slice()), limit)
+}
+
 #[async_trait::async_trait]
 impl KLogStateStore for RocksDbStateStore {
     async fn append(&self, entries: Vec<KLogEntry>) -> KResult<()> {
@@ -1707,16 +2345,7 @@ impl KLogStateStore for RocksDbStateStore {
             error!("{}", msg);
             klog_err(msg)
         })?;
-        let idx_level_cf = self.db.cf_handle(CF_INDEX_LEVEL).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_INDEX_LEVEL);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
-        let idx_source_cf = self.db.cf_handle(CF_INDEX_SOURCE).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_INDEX_SOURCE);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
+        let index_cfs = self.index_cfs()?;
         let meta_cf = self.db.cf_handle(CF_META).ok_or_else(|| {
             let msg = format!("Missing column family '{}'", CF_META);
             error!("{}", msg);
@@ -1731,10 +2360,7 @@ impl KLogStateStore for RocksDbStateStore {
                     klog_err_with_context("Failed to serialize state entry for rocksdb", e)
                 })?;
             batch.put_cf(&logs_cf, key, value);
-            batch.put_cf(&idx_level_cf, level_index_key(entry.level, entry.id), []);
-            if let Some(source) = normalize_source(entry.source.as_deref()) {
-                batch.put_cf(&idx_source_cf, source_index_key(source, entry.id), []);
-            }
+            index_cfs.put_entry(&mut batch, &entry);
             if let Some(request_id) = normalize_request_id(entry.request_id.as_deref()) {
                 let dedup_key = request_dedup_meta_key(request_id);
                 let dedup_value = bincode::serde::encode_to_vec(
@@ -1761,339 +2387,44 @@ impl KLogStateStore for RocksDbStateStore {
     }
 
     async fn query(&self, query: KLogQuery) -> KResult<Vec<KLogEntry>> {
-        let logs_cf = self.db.cf_handle(CF_LOGS).ok_or_else(|| {
-            let msg = format!("Missing column family '{}'", CF_LOGS);
-            error!("{}", msg);
-            klog_err(msg)
-        })?;
-
         if query.limit == 0 {
             return Ok(Vec::new());
         }
 
-        let mut out = Vec::with_capacity(query.limit.min(1024));
-        let source_filter = query
-            .source
-            .as_deref()
-            .map(str::trim)
-            .filter(|v| !v.is_empty())
-            .map(|v| v.to_string());
-
-        if let Some(source) = source_filter.as_deref() {
-            let idx_source_cf = self.db.cf_handle(CF_INDEX_SOURCE).ok_or_else(|| {
-                let msg = format!("Missing column family '{}'", CF_INDEX_SOURCE);
-                error!("{}", msg);
-                klog_err(msg)
-            })?;
-            let source_prefix = source_index_prefix(source);
-            match query.order {
-                KLogQueryOrder::Asc => {
-                    let start_id = query.start_id.unwrap_or(0);
-                    let start_key = source_index_key(source, start_id);
-                    let iter = self.db.iterator_cf(
-                        &idx_source_cf,
-                        IteratorMode::From(&start_key, Direction::Forward),
-                    );
-                    for item in iter {
-                        let (k, _) = item.map_err(|e| {
-                            klog_err_with_context("Failed to iterate rocksdb source index", e)
-                        })?;
-                        if !k.as_ref().starts_with(&source_prefix) {
-                            break;
-                        }
-                        let Some(id) = decode_source_index_id(k.as_ref(), source) else {
-                            continue;
-                        };
-                        if query.start_id.map(|start| id < start).unwrap_or(false) {
-                            continue;
-                        }
-                        if query.end_id.map(|end| id > end).unwrap_or(false) {
-                            break;
-                        }
-                        let Some(raw) =
-                            self.db
-                                .get_cf(&logs_cf, entry_key(id).as_ref())
-                                .map_err(|e| {
-                                    klog_err_with_context(
-                                        "Failed to read state entry by source index from rocksdb",
-                                        e,
-                                    )
-                                })?
-                        else {
-                            continue;
-                        };
-                        let (entry, _): (KLogEntry, usize) = bincode::serde::decode_from_slice(
-                            raw.as_ref(),
-                            bincode::config::legacy(),
-                        )
-                        .map_err(|e| {
-                            klog_err_with_context(
-                                "Failed to deserialize state entry from rocksdb",
-                                e,
-                            )
-                        })?;
-                        if !entry_matches_query(&entry, &query) {
-                            continue;
-                        }
-                        out.push(entry);
-                        if out.len() >= query.limit {
-                            break;
-                        }
-                    }
-                }
-                KLogQueryOrder::Desc => {
-                    let end_id = query.end_id.unwrap_or(u64::MAX);
-                    let start_key = source_index_key(source, end_id);
-                    let iter = self.db.iterator_cf(
-                        &idx_source_cf,
-                        IteratorMode::From(&start_key, Direction::Reverse),
-                    );
-                    for item in iter {
-                        let (k, _) = item.map_err(|e| {
-                            klog_err_with_context("Failed to iterate rocksdb source index", e)
-                        })?;
-                        if !k.as_ref().starts_with(&source_prefix) {
-                            break;
-                        }
-                        let Some(id) = decode_source_index_id(k.as_ref(), source) else {
-                            continue;
-                        };
-                        if query.end_id.map(|end| id > end).unwrap_or(false) {
-                            continue;
-                        }
-                        if query.start_id.map(|start| id < start).unwrap_or(false) {
-                            break;
-                        }
-                        let Some(raw) =
-                            self.db
-                                .get_cf(&logs_cf, entry_key(id).as_ref())
-                                .map_err(|e| {
-                                    klog_err_with_context(
-                                        "Failed to read state entry by source index from rocksdb",
-                                        e,
-                                    )
-                                })?
-                        else {
-                            continue;
-                        };
-                        let (entry, _): (KLogEntry, usize) = bincode::serde::decode_from_slice(
-                            raw.as_ref(),
-                            bincode::config::legacy(),
-                        )
-                        .map_err(|e| {
-                            klog_err_with_context(
-                                "Failed to deserialize state entry from rocksdb",
-                                e,
-                            )
-                        })?;
-                        if !entry_matches_query(&entry, &query) {
-                            continue;
-                        }
-                        out.push(entry);
-                        if out.len() >= query.limit {
-                            break;
-                        }
-                    }
-                }
-            }
-            return Ok(out);
+        // Pick the most selective index the query can use, every candidate is still
+        // checked against the full query.
+        let index_cfs = self.index_cfs()?;
+        if let Some(term) = query_index_term(&query) {
+            debug!(
+                "RocksDbStateStore query plan: index={}, term={}",
+                CF_INDEX_TERM, term
+            );
+            let prefix = term_index_prefix(term.as_str());
+            return self.query_by_prefix_index(index_cfs.term, CF_INDEX_TERM, &prefix, &query);
         }
-
-        if let Some(level) = query.level {
-            let idx_level_cf = self.db.cf_handle(CF_INDEX_LEVEL).ok_or_else(|| {
-                let msg = format!("Missing column family '{}'", CF_INDEX_LEVEL);
-                error!("{}", msg);
-                klog_err(msg)
-            })?;
-            match query.order {
-                KLogQueryOrder::Asc => {
-                    let start_id = query.start_id.unwrap_or(0);
-                    let start_key = level_index_key(level, start_id);
-                    let iter = self.db.iterator_cf(
-                        &idx_level_cf,
-                        IteratorMode::From(&start_key, Direction::Forward),
-                    );
-                    for item in iter {
-                        let (k, _) = item.map_err(|e| {
-                            klog_err_with_context("Failed to iterate rocksdb level index", e)
-                        })?;
-                        let Some(id) = decode_level_index_id(k.as_ref(), level) else {
-                            break;
-                        };
-                        if query.start_id.map(|start| id < start).unwrap_or(false) {
-                            continue;
-                        }
-                        if query.end_id.map(|end| id > end).unwrap_or(false) {
-                            break;
-                        }
-                        let Some(raw) =
-                            self.db
-                                .get_cf(&logs_cf, entry_key(id).as_ref())
-                                .map_err(|e| {
-                                    klog_err_with_context(
-                                        "Failed to read state entry by level index from rocksdb",
-                                        e,
-                                    )
-                                })?
-                        else {
-                            continue;
-                        };
-                        let (entry, _): (KLogEntry, usize) = bincode::serde::decode_from_slice(
-                            raw.as_ref(),
-                            bincode::config::legacy(),
-                        )
-                        .map_err(|e| {
-                            klog_err_with_context(
-                                "Failed to deserialize state entry from rocksdb",
-                                e,
-                            )
-                        })?;
-                        if !entry_matches_query(&entry, &query) {
-                            continue;
-                        }
-                        out.push(entry);
-                        if out.len() >= query.limit {
-                            break;
-                        }
-                    }
-                }
-                KLogQueryOrder::Desc => {
-                    let end_id = query.end_id.unwrap_or(u64::MAX);
-                    let start_key = level_index_key(level, end_id);
-                    let iter = self.db.iterator_cf(
-                        &idx_level_cf,
-                        IteratorMode::From(&start_key, Direction::Reverse),
-                    );
-                    for item in iter {
-                        let (k, _) = item.map_err(|e| {
-                            klog_err_with_context("Failed to iterate rocksdb level index", e)
-                        })?;
-                        let Some(id) = decode_level_index_id(k.as_ref(), level) else {
-                            break;
-                        };
-                        if query.end_id.map(|end| id > end).unwrap_or(false) {
-                            continue;
-                        }
-                        if query.start_id.map(|start| id < start).unwrap_or(false) {
-                            break;
-                        }
-                        let Some(raw) =
-                            self.db
-                                .get_cf(&logs_cf, entry_key(id).as_ref())
-                                .map_err(|e| {
-                                    klog_err_with_context(
-                                        "Failed to read state entry by level index from rocksdb",
-                                        e,
-                                    )
-                                })?
-                        else {
-                            continue;
-                        };
-                        let (entry, _): (KLogEntry, usize) = bincode::serde::decode_from_slice(
-                            raw.as_ref(),
-                            bincode::config::legacy(),
-                        )
-                        .map_err(|e| {
-                            klog_err_with_context(
-                                "Failed to deserialize state entry from rocksdb",
-                                e,
-                            )
-                        })?;
-                        if !entry_matches_query(&entry, &query) {
-                            continue;
-                        }
-                        out.push(entry);
-                        if out.len() >= query.limit {
-                            break;
-                        }
-                    }
-                }
-            }
+        if let Some((key, value)) = query_index_attr(&query) {
+            debug!(
+                "RocksDbStateStore query plan: index={}, attr_key={}, attr_value={}",
+                CF_INDEX_ATTR, key, value
+            );
+            let prefix = attr_index_prefix(key.as_str(), value.as_str());
+            return self.query_by_prefix_index(index_cfs.attr, CF_INDEX_ATTR, &prefix, &query);
+        }
+        if let Some(source) = normalize_source(query.source.as_deref()) {
+            let prefix = source_index_prefix(source);
+            return self.query_by_prefix_index(index_cfs.source, CF_INDEX_SOURCE, &prefix, &query);
+        }
+        if (query.start_time.is_some() || query.end_time.is_some())
+            && let Some(out) = self.query_by_time_index(index_cfs.time, &query)?
+        {
             return Ok(out);
         }
-
-        match query.order {
-            KLogQueryOrder::Asc => {
-                let iter = if let Some(start_id) = query.start_id {
-                    let start_key = entry_key(start_id);
-                    self.db
-                        .iterator_cf(&logs_cf, IteratorMode::From(&start_key, Direction::Forward))
-                } else {
-                    self.db.iterator_cf(&logs_cf, IteratorMode::Start)
-                };
-
-                for item in iter {
-                    let (k, v) = item
-                        .map_err(|e| klog_err_with_context("Failed to iterate rocksdb entry", e))?;
-                    let Some(id) = decode_entry_key(&k) else {
-                        continue;
-                    };
-                    if query.start_id.map(|start| id < start).unwrap_or(false) {
-                        continue;
-                    }
-                    if query.end_id.map(|end| id > end).unwrap_or(false) {
-                        break;
-                    }
-
-                    let (entry, _): (KLogEntry, usize) =
-                        bincode::serde::decode_from_slice(v.as_ref(), bincode::config::legacy())
-                            .map_err(|e| {
-                                klog_err_with_context(
-                                    "Failed to deserialize state entry from rocksdb",
-                                    e,
-                                )
-                            })?;
-                    if !entry_matches_query(&entry, &query) {
-                        continue;
-                    }
-                    out.push(entry);
-                    if out.len() >= query.limit {
-                        break;
-                    }
-                }
-            }
-            KLogQueryOrder::Desc => {
-                let iter = if let Some(end_id) = query.end_id {
-                    let end_key = entry_key(end_id);
-                    self.db
-                        .iterator_cf(&logs_cf, IteratorMode::From(&end_key, Direction::Reverse))
-                } else {
-                    self.db.iterator_cf(&logs_cf, IteratorMode::End)
-                };
-
-                for item in iter {
-                    let (k, v) = item
-                        .map_err(|e| klog_err_with_context("Failed to iterate rocksdb entry", e))?;
-                    let Some(id) = decode_entry_key(&k) else {
-                        continue;
-                    };
-                    if query.end_id.map(|end| id > end).unwrap_or(false) {
-                        continue;
-                    }
-                    if query.start_id.map(|start| id < start).unwrap_or(false) {
-                        break;
-                    }
-
-                    let (entry, _): (KLogEntry, usize) =
-                        bincode::serde::decode_from_slice(v.as_ref(), bincode::config::legacy())
-                            .map_err(|e| {
-                                klog_err_with_context(
-                                    "Failed to deserialize state entry from rocksdb",
-                                    e,
-                                )
-                            })?;
-                    if !entry_matches_query(&entry, &query) {
-                        continue;
-                    }
-                    out.push(entry);
-                    if out.len() >= query.limit {
-                        break;
-                    }
-                }
-            }
+        if let Some(level) = query.level {
+            let prefix = [level_index_code(level)];
+            return self.query_by_prefix_index(index_cfs.level, CF_INDEX_LEVEL, &prefix, &query);
         }
 
-        Ok(out)
+        self.query_by_scan(&query)
     }
 
     async fn put_meta(&self, item: KLogMetaEntry) -> KResult<KLogMetaEntry> {
@@ -2219,6 +2550,104 @@ impl KLogStateStore for RocksDbStateStore {
         Ok(out)
     }
 
+    async fn truncate_before(&self, before_id: u64, source: Option<&str>) -> KResult<u64> {
+        let source = normalize_source(source);
+        info!(
+            "RocksDbStateStore truncate_before start: before_id={}, source={:?}",
+            before_id, source
+        );
+        let logs_cf = self.db.cf_handle(CF_LOGS).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_LOGS);
+            error!("{}", msg);
+            klog_err(msg)
+        })?;
+        let index_cfs = self.index_cfs()?;
+
+        let mut batch = WriteBatch::default();
+        let mut removed = 0u64;
+        match source {
+            Some(source) => {
+                let start_key = source_index_key(source, 0);
+                let iter = self.db.iterator_cf(
+                    index_cfs.source,
+                    IteratorMode::From(&start_key, Direction::Forward),
+                );
+                for item in iter {
+                    let (k, _) = item.map_err(|e| {
+                        klog_err_with_context("Failed to iterate rocksdb source index", e)
+                    })?;
+                    let Some(id) = decode_source_index_id(k.as_ref(), source) else {
+                        break;
+                    };
+                    if id >= before_id {
+                        break;
+                    }
+                    let Some(raw) = self
+                        .db
+                        .get_cf(&logs_cf, entry_key(id))
+                        .map_err(|e| klog_err_with_context("Failed to read rocksdb entry", e))?
+                    else {
+                        // Dangling index row, drop it together with the range.
+                        batch.delete_cf(index_cfs.source, k.as_ref());
+                        continue;
+                    };
+                    let (entry, _): (KLogEntry, usize) =
+                        bincode::serde::decode_from_slice(raw.as_ref(), bincode::config::legacy())
+                            .map_err(|e| {
+                                klog_err_with_context(
+                                    "Failed to deserialize state entry from rocksdb",
+                                    e,
+                                )
+                            })?;
+                    self.delete_entry_in_batch(&mut batch, &entry, &logs_cf, &index_cfs);
+                    removed += 1;
+                }
+            }
+            None => {
+                let start_key = entry_key(0);
+                let iter = self
+                    .db
+                    .iterator_cf(&logs_cf, IteratorMode::From(&start_key, Direction::Forward));
+                for item in iter {
+                    let (k, v) = item
+                        .map_err(|e| klog_err_with_context("Failed to iterate rocksdb entry", e))?;
+                    let Some(id) = decode_entry_key(k.as_ref()) else {
+                        break;
+                    };
+                    if id >= before_id {
+                        break;
+                    }
+                    let (entry, _): (KLogEntry, usize) =
+                        bincode::serde::decode_from_slice(v.as_ref(), bincode::config::legacy())
+                            .map_err(|e| {
+                                klog_err_with_context(
+                                    "Failed to deserialize state entry from rocksdb",
+                                    e,
+                                )
+                            })?;
+                    self.delete_entry_in_batch(&mut batch, &entry, &logs_cf, &index_cfs);
+                    removed += 1;
+                }
+            }
+        }
+
+        if removed > 0 {
+            let write_opts = self.write_options();
+            self.db
+                .write_opt(batch, &write_opts)
+                .map_err(|e| klog_err_with_context("Failed to truncate rocksdb entries", e))?;
+        }
+        let purged_dedup = self.purge_expired_request_dedup(now_millis())?;
+        if removed > 0 || purged_dedup > 0 {
+            self.compact_after_truncate(before_id)?;
+        }
+        info!(
+            "RocksDbStateStore truncate_before done: before_id={}, source={:?}, removed={}, purged_request_dedup={}",
+            before_id, source, removed, purged_dedup
+        );
+        Ok(removed)
+    }
+
     async fn build_snapshot(&self) -> KResult<KLogStateSnapshot> {
         info!(
             "RocksDbStateStore build_snapshot dispatch: mode={:?}",
@@ -2253,6 +2682,147 @@ impl KLogStateStore for RocksDbStateStore {
         Err(klog_err(msg))
     }
 
+    async fn list_meta_after(
+        &self,
+        after_key: Option<&str>,
+        limit: usize,
+    ) -> KResult<Vec<KLogMetaEntry>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let meta_cf = self.db.cf_handle(CF_META).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_META);
+            error!("{}", msg);
+            klog_err(msg)
+        })?;
+        let seek_key = after_key
+            .map(data_meta_key)
+            .unwrap_or_else(|| KEY_DATA_META_PREFIX.to_vec());
+
+        let iter = self.db.iterator_cf(
+            &meta_cf,
+            IteratorMode::From(seek_key.as_slice(), Direction::Forward),
+        );
+        collect_data_meta_page(iter, after_key.map(|_| seek_key.as_slice()), limit)
+    }
+
+    async fn pin_export_view(&self) -> KResult<Box<dyn KLogStateExportView>> {
+        Ok(Box::new(RocksDbExportView::pin(self.db.clone()).await?))
+    }
+
+    async fn clear_for_snapshot_install(&self) -> KResult<()> {
+        let meta_cf = self.db.cf_handle(CF_META).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_META);
+            error!("{}", msg);
+            klog_err(msg)
+        })?;
+        let mut batch = WriteBatch::default();
+        self.clear_entries_in_batch(&mut batch)?;
+        self.clear_request_dedup_in_batch(&mut batch)?;
+        self.clear_data_meta_in_batch(&mut batch)?;
+        self.clear_indexes_in_batch(&mut batch)?;
+        batch.put_cf(&meta_cf, KEY_NEXT_LOG_ID_META, 1u64.to_be_bytes());
+
+        let write_opts = self.write_options();
+        self.db.write_opt(batch, &write_opts).map_err(|e| {
+            klog_err_with_context(
+                "Failed to clear rocksdb before segmented snapshot install",
+                e,
+            )
+        })?;
+        info!(
+            "RocksDbStateStore cleared for segmented snapshot install: mode={:?}",
+            self.snapshot_mode
+        );
+        Ok(())
+    }
+
+    async fn install_snapshot_entries(&self, entries: Vec<KLogEntry>) -> KResult<()> {
+        let logs_cf = self.db.cf_handle(CF_LOGS).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_LOGS);
+            error!("{}", msg);
+            klog_err(msg)
+        })?;
+        let index_cfs = self.index_cfs()?;
+
+        debug!(
+            "RocksDbStateStore install_snapshot_entries: entries={}",
+            summarize_entry_ids(&entries)
+        );
+        let mut batch = WriteBatch::default();
+        for entry in entries {
+            let value =
+                bincode::serde::encode_to_vec(&entry, bincode::config::legacy()).map_err(|e| {
+                    klog_err_with_context("Failed to serialize state entry for rocksdb install", e)
+                })?;
+            batch.put_cf(&logs_cf, entry_key(entry.id), value);
+            index_cfs.put_entry(&mut batch, &entry);
+        }
+
+        let write_opts = self.write_options();
+        self.db
+            .write_opt(batch, &write_opts)
+            .map_err(|e| klog_err_with_context("Failed to write rocksdb snapshot entry segment", e))
+    }
+
+    async fn install_snapshot_meta(&self, items: Vec<KLogMetaEntry>) -> KResult<()> {
+        let meta_cf = self.db.cf_handle(CF_META).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_META);
+            error!("{}", msg);
+            klog_err(msg)
+        })?;
+
+        let mut batch = WriteBatch::default();
+        for item in items {
+            let encoded = bincode::serde::encode_to_vec(&item, bincode::config::legacy())
+                .map_err(|e| klog_err_with_context("Failed to encode data meta entry", e))?;
+            batch.put_cf(&meta_cf, data_meta_key(item.key.as_str()), encoded);
+        }
+
+        let write_opts = self.write_options();
+        self.db
+            .write_opt(batch, &write_opts)
+            .map_err(|e| klog_err_with_context("Failed to write rocksdb snapshot meta segment", e))
+    }
+
+    async fn commit_meta_writes(
+        &self,
+        writes: Vec<(String, Option<KLogMetaEntry>)>,
+        meta: KLogStateMachineMeta,
+    ) -> KResult<()> {
+        let meta_cf = self.db.cf_handle(CF_META).ok_or_else(|| {
+            let msg = format!("Missing column family '{}'", CF_META);
+            error!("{}", msg);
+            klog_err(msg)
+        })?;
+
+        let mut batch = WriteBatch::default();
+        for (key, item) in writes {
+            let meta_key = data_meta_key(key.as_str());
+            match item {
+                Some(item) => {
+                    let encoded = bincode::serde::encode_to_vec(&item, bincode::config::legacy())
+                        .map_err(|e| {
+                        klog_err_with_context("Failed to encode rocksdb data meta entry", e)
+                    })?;
+                    batch.put_cf(&meta_cf, meta_key, encoded);
+                }
+                None => batch.delete_cf(&meta_cf, meta_key),
+            }
+        }
+        let bytes =
+            bincode::serde::encode_to_vec(&meta, bincode::config::legacy()).map_err(|e| {
+                klog_err_with_context("Failed to encode rocksdb state-machine metadata", e)
+            })?;
+        batch.put_cf(&meta_cf, KEY_STATE_MACHINE_META, bytes);
+
+        let write_opts = self.write_options();
+        self.db
+            .write_opt(batch, &write_opts)
+            .map_err(|e| klog_err_with_context("Failed to commit rocksdb meta writes", e))
+    }
+
     async fn load_next_log_id(&self) -> KResult<Option<u64>> {
         let next_log_id = self.resolve_next_log_id()?;
         Ok(Some(next_log_id))
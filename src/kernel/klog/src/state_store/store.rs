@@ -1,7 +1,8 @@
+use super::watch::KLogWatchHub;
 use crate::{KLogEntry, KLogId, KLogLevel, KLogMetaEntry, KNode, KNodeId, KResult};
 use openraft::StoredMembership;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -36,6 +37,14 @@ pub enum KLogQueryOrder {
     Desc,
 }
 
+/// One attribute predicate; `value: None` only requires the key to be present.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KLogAttrFilter {
+    pub key: String,
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct KLogQuery {
     pub start_id: Option<u64>,
@@ -46,6 +55,21 @@ pub struct KLogQuery {
     pub source: Option<String>,
     pub attr_key: Option<String>,
     pub attr_value: Option<String>,
+    /// Inclusive lower bound on `KLogEntry::timestamp` (ms).
+    #[serde(default)]
+    pub start_time: Option<u64>,
+    /// Inclusive upper bound on `KLogEntry::timestamp` (ms).
+    #[serde(default)]
+    pub end_time: Option<u64>,
+    /// Extra attribute predicates, ANDed with each other and with `attr_key`/`attr_value`.
+    #[serde(default)]
+    pub attrs: Vec<KLogAttrFilter>,
+    /// Case-sensitive substring that must appear in the message.
+    #[serde(default)]
+    pub message_contains: Option<String>,
+    /// Words that must all appear in the message, see `tokenize_message`.
+    #[serde(default)]
+    pub message_terms: Vec<String>,
 }
 
 impl Default for KLogQuery {
@@ -59,10 +83,103 @@ impl Default for KLogQuery {
             source: None,
             attr_key: None,
             attr_value: None,
+            start_time: None,
+            end_time: None,
+            attrs: Vec::new(),
+            message_contains: None,
+            message_terms: Vec::new(),
+        }
+    }
+}
+
+impl KLogQuery {
+    /// Check every filter of the query against `entry`, the id range included.
+    ///
+    /// State stores may use indexes to pick candidates, but the final decision is
+    /// made here so that all stores agree on the result.
+    pub fn matches(&self, entry: &KLogEntry) -> bool {
+        if self.start_id.is_some_and(|start| entry.id < start)
+            || self.end_id.is_some_and(|end| entry.id > end)
+        {
+            return false;
+        }
+        if self.start_time.is_some_and(|start| entry.timestamp < start)
+            || self.end_time.is_some_and(|end| entry.timestamp > end)
+        {
+            return false;
+        }
+        if let Some(level) = self.level
+            && entry.level != level
+        {
+            return false;
+        }
+        if let Some(source) = normalize_filter(self.source.as_deref()) {
+            let entry_source = normalize_filter(entry.source.as_deref());
+            if entry_source != Some(source) {
+                return false;
+            }
         }
+
+        let attr_key = normalize_filter(self.attr_key.as_deref());
+        let attr_value = normalize_filter(self.attr_value.as_deref());
+        if attr_key.is_none() && attr_value.is_some() {
+            return false;
+        }
+        if let Some(attr_key) = attr_key
+            && !attr_matches(entry, attr_key, attr_value)
+        {
+            return false;
+        }
+        if !self
+            .attrs
+            .iter()
+            .all(|f| attr_matches(entry, f.key.as_str(), f.value.as_deref()))
+        {
+            return false;
+        }
+
+        if let Some(needle) = self.message_contains.as_deref()
+            && !entry.message.contains(needle)
+        {
+            return false;
+        }
+        if !self.message_terms.is_empty() {
+            let tokens = tokenize_message(entry.message.as_str());
+            if !self
+                .message_terms
+                .iter()
+                .all(|term| tokens.contains(&term.to_lowercase()))
+            {
+                return false;
+            }
+        }
+
+        true
     }
 }
 
+/// Split a message into lowercase words: maximal runs of alphanumeric characters.
+///
+/// This is the unit of `KLogQuery::message_terms` matching and of the rocksdb term index.
+pub fn tokenize_message(message: &str) -> BTreeSet<String> {
+    message
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+fn normalize_filter(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn attr_matches(entry: &KLogEntry, key: &str, value: Option<&str>) -> bool {
+    entry
+        .attrs
+        .get(key)
+        .is_some_and(|actual| value.is_none_or(|expected| actual == expected))
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct KLogStateMachineMeta {
     pub last_applied_log_id: Option<KLogId>,
@@ -83,10 +200,47 @@ pub trait KLogStateStore: Send + Sync {
 
     async fn list_meta(&self, prefix: Option<&str>, limit: usize) -> KResult<Vec<KLogMetaEntry>>;
 
+    /// Remove entries with `id < before_id`; when `source` is set only entries of that
+    /// source are removed. Backends should reclaim the freed space where they can.
+    /// Returns the number of removed entries.
+    async fn truncate_before(&self, before_id: u64, source: Option<&str>) -> KResult<u64>;
+
     async fn build_snapshot(&self) -> KResult<KLogStateSnapshot>;
 
     async fn install_snapshot(&self, snapshot: KLogStateSnapshot) -> KResult<()>;
 
+    /// List meta entries in key order whose key sorts strictly after `after_key`.
+    /// Used to page through the meta table when exporting a segmented snapshot.
+    async fn list_meta_after(
+        &self,
+        after_key: Option<&str>,
+        limit: usize,
+    ) -> KResult<Vec<KLogMetaEntry>>;
+
+    /// Pin the current entries and meta table for a segmented snapshot export.
+    /// The caller holds the state machine lock, so the view matches the applied state
+    /// even though paging continues after apply resumes.
+    async fn pin_export_view(&self) -> KResult<Box<dyn KLogStateExportView>>;
+
+    /// Drop all entries, indexes, request-dedup records and meta entries before a
+    /// segmented snapshot is installed.
+    async fn clear_for_snapshot_install(&self) -> KResult<()>;
+
+    /// Write one entry segment of a segmented snapshot. Entry ids are kept as-is and
+    /// no request-dedup records are created.
+    async fn install_snapshot_entries(&self, entries: Vec<KLogEntry>) -> KResult<()>;
+
+    /// Write one meta segment of a segmented snapshot. Revisions are kept as-is.
+    async fn install_snapshot_meta(&self, items: Vec<KLogMetaEntry>) -> KResult<()>;
+
+    /// Write meta entries (`None` deletes the key) together with the state-machine
+    /// metadata in one atomic store write. Revisions are kept as-is.
+    async fn commit_meta_writes(
+        &self,
+        writes: Vec<(String, Option<KLogMetaEntry>)>,
+        meta: KLogStateMachineMeta,
+    ) -> KResult<()>;
+
     /// Load persisted next-log-id cursor.
     /// Return `Ok(None)` only when the store has not initialized this metadata yet.
     async fn load_next_log_id(&self) -> KResult<Option<u64>>;
@@ -108,6 +262,23 @@ pub trait KLogStateStore: Send + Sync {
 
 pub type KLogStateStoreRef = Arc<Box<dyn KLogStateStore>>;
 
+/// Point-in-time read view of a state store, see `KLogStateStore::pin_export_view`.
+/// Writes made after the view was pinned are never visible through it.
+#[async_trait::async_trait]
+pub trait KLogStateExportView: Send + Sync {
+    /// Read entries with `after_id < id <= last_id` in ascending order, at most `limit`.
+    async fn entries_page(
+        &self,
+        after_id: u64,
+        last_id: u64,
+        limit: usize,
+    ) -> KResult<Vec<KLogEntry>>;
+
+    /// List meta entries in key order whose key sorts strictly after `after_key`.
+    async fn meta_page(&self, after_key: Option<&str>, limit: usize)
+    -> KResult<Vec<KLogMetaEntry>>;
+}
+
 #[derive(Debug, Clone, Copy)]
 struct RequestDedupRecord {
     log_id: u64,
@@ -181,12 +352,16 @@ pub struct KLogStateStoreManager {
     // The kernel state: next id to assign to the next state entry
     next_log_id: AtomicU64,
     request_dedup: AsyncMutex<RequestDedupCache>,
+
+    // Change notifications for meta/log watchers, fed by state machine apply.
+    watch_hub: KLogWatchHub,
 }
 
 impl std::fmt::Debug for KLogStateStoreManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("KLogStateStoreManager")
             .field("next_log_id", &self.next_log_id.load(Ordering::SeqCst))
+            .field("watch_hub", &self.watch_hub)
             .finish()
     }
 }
@@ -203,9 +378,14 @@ impl KLogStateStoreManager {
             state_store,
             next_log_id: AtomicU64::new(recovered_next),
             request_dedup: AsyncMutex::new(RequestDedupCache::default()),
+            watch_hub: KLogWatchHub::default(),
         })
     }
 
+    pub fn watch_hub(&self) -> &KLogWatchHub {
+        &self.watch_hub
+    }
+
     pub async fn append(&self, entries: Vec<KLogEntry>) -> KResult<()> {
         let request_id_pairs = entries
             .iter()
@@ -231,6 +411,11 @@ impl KLogStateStoreManager {
         self.next_log_id.fetch_add(1, Ordering::SeqCst)
     }
 
+    /// Allocate `count` contiguous ids and return the first one.
+    pub fn alloc_log_ids(&self, count: u64) -> u64 {
+        self.next_log_id.fetch_add(count, Ordering::SeqCst)
+    }
+
     pub fn peek_next_log_id(&self) -> u64 {
         self.next_log_id.load(Ordering::SeqCst)
     }
@@ -244,6 +429,26 @@ impl KLogStateStoreManager {
         item
     }
 
+    /// Batch version of `prepare_append_entry`, entries without id get one contiguous range.
+    pub fn prepare_append_batch(&self, items: Vec<KLogEntry>) -> Vec<KLogEntry> {
+        let missing = items.iter().filter(|item| item.id == 0).count() as u64;
+        let mut next_id = if missing > 0 {
+            self.alloc_log_ids(missing)
+        } else {
+            0
+        };
+        items
+            .into_iter()
+            .map(|mut item| {
+                if item.id == 0 {
+                    item.id = next_id;
+                    next_id += 1;
+                }
+                item
+            })
+            .collect()
+    }
+
     pub async fn find_recent_request_id(&self, request_id: &str) -> Option<u64> {
         let request_id = normalize_request_id(Some(request_id))?;
         let now_ms = now_millis();
@@ -293,6 +498,38 @@ impl KLogStateStoreManager {
         Ok(id)
     }
 
+    /// Append already prepared entries in one store write, returning one id per entry.
+    /// A request_id seen before, or earlier in the same batch, maps to the existing id.
+    pub async fn append_prepared_batch(&self, items: Vec<KLogEntry>) -> KResult<Vec<u64>> {
+        let mut ids = Vec::with_capacity(items.len());
+        let mut batch_request_ids = HashMap::new();
+        let mut pending = Vec::with_capacity(items.len());
+        for item in items {
+            if let Some(request_id) = normalize_request_id(item.request_id.as_deref()) {
+                let existing_id = match batch_request_ids.get(request_id) {
+                    Some(id) => Some(*id),
+                    None => self.find_recent_request_id(request_id).await,
+                };
+                if let Some(existing_id) = existing_id {
+                    info!(
+                        "KLogStateStoreManager dedup hit in append_prepared_batch: request_id={}, existing_id={}, incoming_id={}",
+                        request_id, existing_id, item.id
+                    );
+                    ids.push(existing_id);
+                    continue;
+                }
+                batch_request_ids.insert(request_id.to_string(), item.id);
+            }
+            ids.push(item.id);
+            pending.push(item);
+        }
+
+        if !pending.is_empty() {
+            self.append(pending).await?;
+        }
+        Ok(ids)
+    }
+
     pub async fn build_snapshot(&self) -> KResult<KLogStateSnapshot> {
         self.state_store.build_snapshot().await
     }
@@ -301,6 +538,115 @@ impl KLogStateStoreManager {
         self.state_store.query(query).await
     }
 
+    pub async fn newest_entry_id(&self) -> KResult<Option<u64>> {
+        let newest = self
+            .state_store
+            .query(KLogQuery {
+                limit: 1,
+                order: KLogQueryOrder::Desc,
+                ..Default::default()
+            })
+            .await?;
+        Ok(newest.first().map(|e| e.id))
+    }
+
+    /// Read entries with `after_id < id <= last_id` in ascending order, at most `limit`.
+    pub async fn export_entries_page(
+        &self,
+        after_id: u64,
+        last_id: u64,
+        limit: usize,
+    ) -> KResult<Vec<KLogEntry>> {
+        if after_id >= last_id || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        self.state_store
+            .query(KLogQuery {
+                start_id: Some(after_id.saturating_add(1)),
+                end_id: Some(last_id),
+                limit,
+                ..Default::default()
+            })
+            .await
+    }
+
+    pub async fn export_meta_page(
+        &self,
+        after_key: Option<&str>,
+        limit: usize,
+    ) -> KResult<Vec<KLogMetaEntry>> {
+        self.state_store.list_meta_after(after_key, limit).await
+    }
+
+    pub async fn pin_export_view(&self) -> KResult<Box<dyn KLogStateExportView>> {
+        self.state_store.pin_export_view().await
+    }
+
+    /// Start a segmented snapshot install: the store is emptied and segments are
+    /// written one by one with `install_snapshot_entries`/`install_snapshot_meta`.
+    pub async fn begin_segmented_install(&self) -> KResult<()> {
+        self.state_store.clear_for_snapshot_install().await?;
+        self.request_dedup.lock().await.clear();
+        Ok(())
+    }
+
+    pub async fn install_snapshot_entries(&self, entries: Vec<KLogEntry>) -> KResult<()> {
+        self.state_store.install_snapshot_entries(entries).await
+    }
+
+    pub async fn install_snapshot_meta(&self, items: Vec<KLogMetaEntry>) -> KResult<()> {
+        self.state_store.install_snapshot_meta(items).await
+    }
+
+    /// Finish a segmented snapshot install and reset next_log_id from the newest
+    /// installed entry.
+    pub async fn finish_segmented_install(&self, max_entry_id: Option<u64>) -> KResult<()> {
+        let next_log_id = max_entry_id.map(|id| id.saturating_add(1)).unwrap_or(1);
+        self.state_store.save_next_log_id(next_log_id).await?;
+        self.next_log_id.store(next_log_id, Ordering::SeqCst);
+        self.request_dedup.lock().await.clear();
+        info!(
+            "KLogStateStoreManager segmented install finished: next_log_id={}",
+            next_log_id
+        );
+        Ok(())
+    }
+
+    /// Apply a replicated truncation.
+    /// The newest entry is always kept so next-log-id recovery from entries stays monotonic
+    /// on every replica, including ones rebuilt from a snapshot.
+    pub async fn truncate_entries_before(
+        &self,
+        before_id: u64,
+        source: Option<&str>,
+    ) -> KResult<u64> {
+        let Some(newest_id) = self.newest_entry_id().await? else {
+            return Ok(0);
+        };
+
+        let effective_before_id = before_id.min(newest_id);
+        if effective_before_id != before_id {
+            debug!(
+                "KLogStateStoreManager truncate cut clamped to keep newest entry: requested_before_id={}, effective_before_id={}, source={:?}",
+                before_id, effective_before_id, source
+            );
+        }
+        if effective_before_id <= 1 {
+            return Ok(0);
+        }
+
+        let removed = self
+            .state_store
+            .truncate_before(effective_before_id, source)
+            .await?;
+        info!(
+            "KLogStateStoreManager truncate committed: before_id={}, source={:?}, removed={}",
+            effective_before_id, source, removed
+        );
+        Ok(removed)
+    }
+
     pub async fn put_meta_entry(&self, item: KLogMetaEntry) -> KResult<KLogMetaEntry> {
         self.state_store.put_meta(item).await
     }
@@ -331,6 +677,14 @@ impl KLogStateStoreManager {
         Ok(KLogMetaPutResult::Stored(stored))
     }
 
+    pub async fn commit_meta_writes(
+        &self,
+        writes: Vec<(String, Option<KLogMetaEntry>)>,
+        meta: KLogStateMachineMeta,
+    ) -> KResult<()> {
+        self.state_store.commit_meta_writes(writes, meta).await
+    }
+
     pub async fn delete_meta_key(&self, key: &str) -> KResult<Option<KLogMetaEntry>> {
         self.state_store.delete_meta(key).await
     }
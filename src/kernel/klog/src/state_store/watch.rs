@@ -0,0 +1,197 @@
+use crate::KLogMetaEntry;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+/// How many meta change events are kept in memory for watchers to resume from.
+pub const DEFAULT_WATCH_HISTORY_CAPACITY: usize = 4096;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KLogMetaWatchEventKind {
+    Put,
+    Delete,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KLogMetaWatchEvent {
+    /// Raft log index that applied the change, ordered across all keys.
+    pub revision: u64,
+    pub kind: KLogMetaWatchEventKind,
+    pub key: String,
+    /// Stored entry after a put, or the removed entry for a delete.
+    pub meta: KLogMetaEntry,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KLogMetaWatchBatch {
+    Events {
+        events: Vec<KLogMetaWatchEvent>,
+        /// Cursor to resume from; every change up to it has been scanned.
+        revision: u64,
+    },
+    /// The requested resume point is older than the retained history.
+    Compacted {
+        compacted_revision: u64,
+        revision: u64,
+    },
+}
+
+#[derive(Debug, Default)]
+struct WatchHistory {
+    events: VecDeque<KLogMetaWatchEvent>,
+    // Events at or below this revision may have been dropped.
+    compacted_revision: u64,
+    // Highest revision whose events are all recorded.
+    applied_revision: u64,
+}
+
+/// Fan-out point between state machine apply and long-polling watchers.
+///
+/// Meta changes are kept in a bounded in-memory history keyed by the raft log index that
+/// applied them. Log entries are not buffered here: they are persisted with monotonic ids,
+/// so log watchers only use the revision channel as a wake-up and re-read the store.
+pub struct KLogWatchHub {
+    history: Mutex<WatchHistory>,
+    revision_tx: watch::Sender<u64>,
+    capacity: usize,
+}
+
+impl std::fmt::Debug for KLogWatchHub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let history = self.history.lock().unwrap();
+        f.debug_struct("KLogWatchHub")
+            .field("events", &history.events.len())
+            .field("compacted_revision", &history.compacted_revision)
+            .field("applied_revision", &history.applied_revision)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl Default for KLogWatchHub {
+    fn default() -> Self {
+        Self::new(DEFAULT_WATCH_HISTORY_CAPACITY)
+    }
+}
+
+impl KLogWatchHub {
+    pub fn new(capacity: usize) -> Self {
+        let (revision_tx, _) = watch::channel(0);
+        Self {
+            history: Mutex::new(WatchHistory::default()),
+            revision_tx,
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn current_revision(&self) -> u64 {
+        self.history.lock().unwrap().applied_revision
+    }
+
+    pub fn compacted_revision(&self) -> u64 {
+        self.history.lock().unwrap().compacted_revision
+    }
+
+    /// Receiver that observes every `advance`/`reset`, used by watchers to wait for changes.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.revision_tx.subscribe()
+    }
+
+    /// Drop the history and start over at `revision`, e.g. after restart or snapshot install,
+    /// where the individual changes that led to the current state are unknown.
+    pub fn reset(&self, revision: u64) {
+        {
+            let mut history = self.history.lock().unwrap();
+            info!(
+                "KLogWatchHub reset: revision={}, dropped_events={}, prev_applied_revision={}",
+                revision,
+                history.events.len(),
+                history.applied_revision
+            );
+            history.events.clear();
+            history.compacted_revision = revision;
+            history.applied_revision = revision;
+        }
+        self.revision_tx.send_replace(revision);
+    }
+
+    pub fn record_meta_event(&self, event: KLogMetaWatchEvent) {
+        let mut history = self.history.lock().unwrap();
+        if event.revision <= history.applied_revision {
+            warn!(
+                "KLogWatchHub ignore stale meta event: key={}, revision={}, applied_revision={}",
+                event.key, event.revision, history.applied_revision
+            );
+            return;
+        }
+        history.events.push_back(event);
+        while history.events.len() > self.capacity {
+            if let Some(dropped) = history.events.pop_front() {
+                history.compacted_revision = dropped.revision;
+            }
+        }
+    }
+
+    /// Mark every change up to `revision` as recorded and wake up waiting watchers.
+    pub fn advance(&self, revision: u64) {
+        {
+            let mut history = self.history.lock().unwrap();
+            if revision <= history.applied_revision {
+                return;
+            }
+            history.applied_revision = revision;
+        }
+        self.revision_tx.send_replace(revision);
+    }
+
+    /// Collect meta changes under `prefix` with `revision > after_revision`, about `limit`
+    /// events: the batch may run over to finish the last revision.
+    pub fn meta_events_after(
+        &self,
+        after_revision: u64,
+        prefix: Option<&str>,
+        limit: usize,
+    ) -> KLogMetaWatchBatch {
+        let history = self.history.lock().unwrap();
+        if after_revision < history.compacted_revision {
+            return KLogMetaWatchBatch::Compacted {
+                compacted_revision: history.compacted_revision,
+                revision: history.applied_revision,
+            };
+        }
+
+        let mut events = Vec::new();
+        let mut revision = history.applied_revision.max(after_revision);
+        for event in history.events.iter() {
+            if event.revision <= after_revision {
+                continue;
+            }
+            if event.revision > history.applied_revision {
+                break;
+            }
+            if let Some(prefix) = prefix
+                && !event.key.starts_with(prefix)
+            {
+                continue;
+            }
+            // A txn or lease expiry records several events at one revision; the cursor can
+            // not resume in the middle of it, so never split a revision across batches.
+            if events.len() >= limit
+                && events
+                    .last()
+                    .is_none_or(|e: &KLogMetaWatchEvent| e.revision != event.revision)
+            {
+                revision = events
+                    .last()
+                    .map(|e: &KLogMetaWatchEvent| e.revision)
+                    .unwrap_or(after_revision);
+                break;
+            }
+            events.push(event.clone());
+        }
+
+        KLogMetaWatchBatch::Events { events, revision }
+    }
+}
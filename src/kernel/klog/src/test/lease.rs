@@ -0,0 +1,320 @@
+use super::common::unique_test_path;
+use crate::lease::{KLogLease, load_lease, plan_lease_expiry, validate_lease_ttl};
+use crate::state_machine::{KLogStateMachine, SnapshotManager};
+use crate::state_store::{
+    KLogMetaWatchBatch, KLogMetaWatchEventKind, KLogStateStore, KLogStateStoreManager,
+    KLogStateStoreManagerRef, MemoryStateStore, RocksDbSnapshotMode, RocksDbStateStore,
+};
+use crate::{KLogMetaEntry, KLogRequest, KLogResponse};
+use openraft::entry::EntryPayload;
+use openraft::storage::RaftStateMachine;
+use openraft::{CommittedLeaderId, Entry, LogId};
+use std::sync::Arc;
+
+fn entry(index: u64, req: KLogRequest) -> Entry<crate::KTypeConfig> {
+    Entry {
+        log_id: LogId::new(CommittedLeaderId::new(1, 0), index),
+        payload: EntryPayload::Normal(req),
+    }
+}
+
+fn put_meta(key: &str, lease_id: Option<u64>) -> KLogRequest {
+    KLogRequest::PutMeta {
+        item: KLogMetaEntry {
+            key: key.to_string(),
+            value: "holder-1".to_string(),
+            updated_at: 1000,
+            updated_by: 1,
+            revision: 0,
+            lease_id,
+        },
+        expected_revision: None,
+    }
+}
+
+async fn memory_state_machine() -> anyhow::Result<(KLogStateStoreManagerRef, KLogStateMachine)> {
+    let state_store = Arc::new(Box::new(MemoryStateStore::new()) as Box<dyn KLogStateStore>);
+    let manager = Arc::new(KLogStateStoreManager::new(state_store).await?);
+    let data_dir = unique_test_path("lease_snapshot");
+    std::fs::create_dir_all(&data_dir)?;
+    let snapshot_manager = Arc::new(SnapshotManager::new(data_dir));
+    let sm = KLogStateMachine::new(manager.clone(), snapshot_manager).await?;
+    Ok((manager, sm))
+}
+
+async fn lease(manager: &KLogStateStoreManagerRef, lease_id: u64) -> anyhow::Result<KLogLease> {
+    load_lease(manager, lease_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("lease {} not found", lease_id))
+}
+
+#[test]
+fn test_lease_ttl_bounds_and_meta_key_order() {
+    assert!(validate_lease_ttl(999).is_err());
+    assert!(validate_lease_ttl(1_000).is_ok());
+    assert!(validate_lease_ttl(24 * 60 * 60 * 1000 + 1).is_err());
+    assert!(KLogLease::meta_key(9) < KLogLease::meta_key(10));
+}
+
+#[tokio::test]
+async fn test_state_machine_lease_attach_keepalive_and_expire() -> anyhow::Result<()> {
+    let (manager, mut sm) = memory_state_machine().await?;
+
+    let replies = sm
+        .apply(vec![
+            entry(
+                1,
+                KLogRequest::GrantLease {
+                    ttl_ms: 5_000,
+                    granted_at: 1_000,
+                    granted_by: 1,
+                },
+            ),
+            entry(2, put_meta("lock/a", Some(1))),
+            entry(3, put_meta("lock/b", Some(1))),
+            // Re-putting without a lease detaches the key.
+            entry(4, put_meta("lock/b", None)),
+            entry(5, put_meta("lock/c", Some(99))),
+        ])
+        .await?;
+    assert!(
+        matches!(&replies[0], KLogResponse::LeaseOk { lease } if lease.id == 1 && lease.expires_at == 6_000)
+    );
+    assert!(matches!(
+        replies[4],
+        KLogResponse::LeaseNotFound { lease_id: 99 }
+    ));
+    assert!(manager.get_meta_entry("lock/c").await?.is_none());
+    assert_eq!(
+        lease(&manager, 1)
+            .await?
+            .keys
+            .into_iter()
+            .collect::<Vec<_>>(),
+        vec!["lock/a".to_string()]
+    );
+
+    let replies = sm
+        .apply(vec![
+            entry(
+                6,
+                KLogRequest::KeepAliveLease {
+                    lease_id: 1,
+                    now_ms: 3_000,
+                },
+            ),
+            // Planned before the keepalive was applied, must not remove the renewed lease.
+            entry(
+                7,
+                KLogRequest::ExpireLease {
+                    lease_id: 1,
+                    expires_at: 6_000,
+                },
+            ),
+        ])
+        .await?;
+    assert!(matches!(&replies[0], KLogResponse::LeaseOk { lease } if lease.expires_at == 8_000));
+    assert!(matches!(
+        replies[1],
+        KLogResponse::LeaseNotFound { lease_id: 1 }
+    ));
+    assert!(manager.get_meta_entry("lock/a").await?.is_some());
+
+    // A fresh leader waits a full TTL before expiring anything.
+    assert!(plan_lease_expiry(&manager, 9_000, 100).await?.is_empty());
+    let due = plan_lease_expiry(&manager, 9_000, 5_000).await?;
+    assert_eq!(due.iter().map(|l| l.id).collect::<Vec<_>>(), vec![1]);
+    assert!(plan_lease_expiry(&manager, 7_000, 5_000).await?.is_empty());
+
+    let replies = sm
+        .apply(vec![
+            entry(
+                8,
+                KLogRequest::ExpireLease {
+                    lease_id: 1,
+                    expires_at: due[0].expires_at,
+                },
+            ),
+            entry(9, KLogRequest::RevokeLease { lease_id: 1 }),
+        ])
+        .await?;
+    let KLogResponse::LeaseRevoked { deleted_keys, .. } = &replies[0] else {
+        panic!("unexpected expire reply: {:?}", replies[0]);
+    };
+    assert_eq!(deleted_keys, &vec!["lock/a".to_string()]);
+    assert!(matches!(
+        replies[1],
+        KLogResponse::LeaseNotFound { lease_id: 1 }
+    ));
+    assert!(manager.get_meta_entry("lock/a").await?.is_none());
+    assert!(manager.get_meta_entry("lock/b").await?.is_some());
+    assert!(load_lease(&manager, 1).await?.is_none());
+
+    // Watchers see the expired key as an ordinary delete.
+    let KLogMetaWatchBatch::Events { events, .. } =
+        manager.watch_hub().meta_events_after(7, None, 10)
+    else {
+        panic!("history must not be compacted");
+    };
+    assert_eq!(
+        events
+            .iter()
+            .map(|e| (e.revision, e.kind, e.key.as_str(), e.meta.lease_id))
+            .collect::<Vec<_>>(),
+        vec![(8, KLogMetaWatchEventKind::Delete, "lock/a", Some(1))]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_state_machine_revoke_skips_keys_moved_to_other_lease() -> anyhow::Result<()> {
+    let (manager, mut sm) = memory_state_machine().await?;
+    let grant = |granted_at| KLogRequest::GrantLease {
+        ttl_ms: 5_000,
+        granted_at,
+        granted_by: 2,
+    };
+
+    sm.apply(vec![
+        entry(1, grant(1_000)),
+        entry(2, grant(1_000)),
+        entry(3, put_meta("leader/scheduler", Some(1))),
+        entry(4, put_meta("leader/scheduler", Some(2))),
+        entry(5, put_meta("member/node-1", Some(1))),
+        entry(
+            6,
+            KLogRequest::DeleteMeta {
+                key: "member/node-1".to_string(),
+            },
+        ),
+    ])
+    .await?;
+    assert!(lease(&manager, 1).await?.keys.is_empty());
+    assert_eq!(lease(&manager, 2).await?.keys.len(), 1);
+
+    let replies = sm
+        .apply(vec![entry(7, KLogRequest::RevokeLease { lease_id: 1 })])
+        .await?;
+    assert!(
+        matches!(&replies[0], KLogResponse::LeaseRevoked { deleted_keys, .. } if deleted_keys.is_empty())
+    );
+    let holder = manager
+        .get_meta_entry("leader/scheduler")
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("leader key must survive"))?;
+    assert_eq!(holder.lease_id, Some(2));
+
+    let replies = sm
+        .apply(vec![entry(8, KLogRequest::RevokeLease { lease_id: 2 })])
+        .await?;
+    assert!(
+        matches!(&replies[0], KLogResponse::LeaseRevoked { deleted_keys, .. } if deleted_keys == &vec!["leader/scheduler".to_string()])
+    );
+    assert!(manager.get_meta_entry("leader/scheduler").await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_state_machine_meta_put_commits_lease_keys_with_applied_id() -> anyhow::Result<()> {
+    let path = unique_test_path("lease_put_commit.rocks");
+    let rocks = RocksDbStateStore::open_with_mode(&path, RocksDbSnapshotMode::Enumerate)
+        .map_err(anyhow::Error::msg)?;
+    let manager = Arc::new(
+        KLogStateStoreManager::new(Arc::new(Box::new(rocks) as Box<dyn KLogStateStore>)).await?,
+    );
+    let data_dir = unique_test_path("lease_put_commit_snapshot");
+    std::fs::create_dir_all(&data_dir)?;
+    let mut sm =
+        KLogStateMachine::new(manager.clone(), Arc::new(SnapshotManager::new(data_dir))).await?;
+
+    let replies = sm
+        .apply(vec![
+            entry(
+                1,
+                KLogRequest::GrantLease {
+                    ttl_ms: 5_000,
+                    granted_at: 1_000,
+                    granted_by: 1,
+                },
+            ),
+            entry(2, put_meta("lock/a", Some(1))),
+            entry(3, put_meta("lock/b", Some(1))),
+            entry(
+                4,
+                KLogRequest::DeleteMeta {
+                    key: "lock/b".to_string(),
+                },
+            ),
+            // A CAS conflict leaves the stored entry alone.
+            entry(
+                5,
+                KLogRequest::PutMeta {
+                    item: KLogMetaEntry {
+                        key: "lock/a".to_string(),
+                        value: "holder-2".to_string(),
+                        updated_at: 2000,
+                        updated_by: 2,
+                        revision: 0,
+                        lease_id: Some(1),
+                    },
+                    expected_revision: Some(0),
+                },
+            ),
+        ])
+        .await?;
+    assert!(matches!(
+        replies[1],
+        KLogResponse::MetaPutOk { revision: 1, .. }
+    ));
+    assert!(matches!(
+        replies[4],
+        KLogResponse::MetaPutConflict {
+            current_revision: Some(1),
+            ..
+        }
+    ));
+    let KLogMetaWatchBatch::Events { events, .. } =
+        manager.watch_hub().meta_events_after(1, None, 10)
+    else {
+        panic!("history must not be compacted");
+    };
+    assert_eq!(
+        events
+            .iter()
+            .map(|e| (e.revision, e.kind, e.key.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            (2, KLogMetaWatchEventKind::Put, "lock/a"),
+            (3, KLogMetaWatchEventKind::Put, "lock/b"),
+            (4, KLogMetaWatchEventKind::Delete, "lock/b"),
+        ]
+    );
+    drop(sm);
+    drop(manager);
+
+    let reopened = RocksDbStateStore::open_with_mode(&path, RocksDbSnapshotMode::Enumerate)
+        .map_err(anyhow::Error::msg)?;
+    let manager = Arc::new(
+        KLogStateStoreManager::new(Arc::new(Box::new(reopened) as Box<dyn KLogStateStore>)).await?,
+    );
+    let meta = manager.load_state_machine_meta().await?.unwrap();
+    assert_eq!(meta.last_applied_log_id.map(|id| id.index), Some(5));
+    let holder = manager.get_meta_entry("lock/a").await?.unwrap();
+    assert_eq!(
+        (holder.value.as_str(), holder.revision, holder.lease_id),
+        ("holder-1", 1, Some(1))
+    );
+    assert!(manager.get_meta_entry("lock/b").await?.is_none());
+    assert_eq!(
+        lease(&manager, 1)
+            .await?
+            .keys
+            .into_iter()
+            .collect::<Vec<_>>(),
+        vec!["lock/a".to_string()]
+    );
+
+    Ok(())
+}
@@ -1,6 +1,12 @@
 mod common;
+mod lease;
 mod log_storage;
 mod network;
 mod openraft_suite;
+mod query;
+mod retention;
+mod snapshot;
 mod state_machine;
 mod state_store_rocksdb;
+mod txn;
+mod watch;
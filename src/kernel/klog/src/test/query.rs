@@ -0,0 +1,307 @@
+use super::common::unique_test_path;
+use crate::network::{format_attr_filters, parse_attr_filters};
+use crate::state_store::{
+    KLogAttrFilter, KLogQuery, KLogQueryOrder, KLogStateStore, MemoryStateStore,
+    RocksDbSnapshotMode, RocksDbStateStore, tokenize_message,
+};
+use crate::{KLogEntry, KLogLevel};
+use std::collections::BTreeMap;
+
+fn entry(id: u64, timestamp: u64, attrs: &[(&str, &str)], message: &str) -> KLogEntry {
+    KLogEntry {
+        id,
+        timestamp,
+        node_id: 1,
+        request_id: None,
+        level: if id % 2 == 0 {
+            KLogLevel::Info
+        } else {
+            KLogLevel::Error
+        },
+        source: Some("kernel/net".to_string()),
+        attrs: attrs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+        message: message.to_string(),
+    }
+}
+
+fn query_entries() -> Vec<KLogEntry> {
+    vec![
+        entry(
+            1,
+            1000,
+            &[("svc", "kmsg"), ("pid", "7")],
+            "Connection reset by peer",
+        ),
+        entry(
+            2,
+            1500,
+            &[("svc", "kmsg")],
+            "connection established to node-2",
+        ),
+        // Timestamps are not required to follow ids.
+        entry(
+            3,
+            1200,
+            &[("svc", "net"), ("pid", "7")],
+            "timeout while reading header",
+        ),
+        entry(
+            4,
+            2000,
+            &[("svc", "kmsg"), ("pid", "9")],
+            "Disk full: /var/log",
+        ),
+        entry(5, 2500, &[], "reconnecting after timeout"),
+    ]
+}
+
+async fn ids(store: &dyn KLogStateStore, query: KLogQuery) -> anyhow::Result<Vec<u64>> {
+    Ok(store
+        .query(query)
+        .await?
+        .into_iter()
+        .map(|e| e.id)
+        .collect())
+}
+
+async fn assert_query_filters(store: &dyn KLogStateStore) -> anyhow::Result<()> {
+    let time_range = KLogQuery {
+        start_time: Some(1200),
+        end_time: Some(2000),
+        ..Default::default()
+    };
+    assert_eq!(ids(store, time_range.clone()).await?, vec![2, 3, 4]);
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                order: KLogQueryOrder::Desc,
+                limit: 2,
+                ..time_range.clone()
+            }
+        )
+        .await?,
+        vec![4, 3]
+    );
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                start_id: Some(3),
+                level: Some(KLogLevel::Error),
+                ..time_range
+            }
+        )
+        .await?,
+        vec![3]
+    );
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                start_time: Some(2000),
+                ..Default::default()
+            }
+        )
+        .await?,
+        vec![4, 5]
+    );
+
+    let attrs = KLogQuery {
+        attrs: vec![
+            KLogAttrFilter {
+                key: "svc".to_string(),
+                value: Some("kmsg".to_string()),
+            },
+            KLogAttrFilter {
+                key: "pid".to_string(),
+                value: None,
+            },
+        ],
+        ..Default::default()
+    };
+    assert_eq!(ids(store, attrs.clone()).await?, vec![1, 4]);
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                attr_key: Some("pid".to_string()),
+                attr_value: Some("7".to_string()),
+                ..attrs
+            }
+        )
+        .await?,
+        vec![1]
+    );
+
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                message_contains: Some("onnection ".to_string()),
+                ..Default::default()
+            }
+        )
+        .await?,
+        vec![1, 2]
+    );
+    // Substring matching is case-sensitive, the interior word still narrows via the index.
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                message_contains: Some("Disk full: /var".to_string()),
+                ..Default::default()
+            }
+        )
+        .await?,
+        vec![4]
+    );
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                message_contains: Some("Disk Full: /var".to_string()),
+                ..Default::default()
+            }
+        )
+        .await?,
+        Vec::<u64>::new()
+    );
+
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                message_terms: vec!["TIMEOUT".to_string()],
+                order: KLogQueryOrder::Desc,
+                ..Default::default()
+            }
+        )
+        .await?,
+        vec![5, 3]
+    );
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                message_terms: vec!["connection".to_string(), "peer".to_string()],
+                ..Default::default()
+            }
+        )
+        .await?,
+        vec![1]
+    );
+    // Terms are whole words, not prefixes.
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                message_terms: vec!["connect".to_string()],
+                ..Default::default()
+            }
+        )
+        .await?,
+        Vec::<u64>::new()
+    );
+    assert_eq!(
+        ids(
+            store,
+            KLogQuery {
+                message_terms: vec!["timeout".to_string()],
+                attrs: vec![KLogAttrFilter {
+                    key: "pid".to_string(),
+                    value: Some("7".to_string()),
+                }],
+                start_time: Some(1100),
+                ..Default::default()
+            }
+        )
+        .await?,
+        vec![3]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_tokenize_message_and_attr_filter_params() {
+    assert_eq!(
+        tokenize_message("Disk FULL: /var/log, disk-2 full")
+            .into_iter()
+            .collect::<Vec<_>>(),
+        vec!["2", "disk", "full", "log", "var"]
+    );
+
+    let filters = parse_attr_filters(" svc = kmsg ,pid, ,trace=").unwrap();
+    assert_eq!(
+        filters,
+        vec![
+            KLogAttrFilter {
+                key: "svc".to_string(),
+                value: Some("kmsg".to_string()),
+            },
+            KLogAttrFilter {
+                key: "pid".to_string(),
+                value: None,
+            },
+            KLogAttrFilter {
+                key: "trace".to_string(),
+                value: None,
+            },
+        ]
+    );
+    assert_eq!(format_attr_filters(&filters), "svc=kmsg,pid,trace");
+    assert!(parse_attr_filters("=kmsg").is_err());
+}
+
+#[tokio::test]
+async fn test_memory_query_time_attrs_and_message() -> anyhow::Result<()> {
+    let store = MemoryStateStore::new();
+    store.append(query_entries()).await?;
+    assert_query_filters(&store).await
+}
+
+#[tokio::test]
+async fn test_rocksdb_query_time_attrs_and_message() -> anyhow::Result<()> {
+    let path = unique_test_path("state_store_query_filters.rocks");
+    let store = RocksDbStateStore::open_with_mode(&path, RocksDbSnapshotMode::Enumerate)
+        .map_err(anyhow::Error::msg)?;
+    store.append(query_entries()).await?;
+    assert_query_filters(&store).await?;
+    drop(store);
+
+    // Indexes are persisted, and truncated entries leave none behind.
+    let store = RocksDbStateStore::open_with_mode(&path, RocksDbSnapshotMode::Enumerate)
+        .map_err(anyhow::Error::msg)?;
+    assert_query_filters(&store).await?;
+    store.truncate_before(4, None).await?;
+    assert_eq!(
+        ids(
+            &store,
+            KLogQuery {
+                message_terms: vec!["timeout".to_string()],
+                ..Default::default()
+            }
+        )
+        .await?,
+        vec![5]
+    );
+    assert_eq!(
+        ids(
+            &store,
+            KLogQuery {
+                start_time: Some(0),
+                end_time: Some(2000),
+                ..Default::default()
+            }
+        )
+        .await?,
+        vec![4]
+    );
+
+    Ok(())
+}
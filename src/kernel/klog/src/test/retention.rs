@@ -0,0 +1,274 @@
+use super::common::{TestMemoryContext, unique_test_path};
+use crate::retention::{
+    KLOG_RETENTION_POLICY_META_KEY, KLogRetentionPolicy, KLogRetentionRule, KLogTruncatePlan,
+    collect_retention_stats, estimate_entry_bytes, is_reserved_meta_key, plan_retention,
+};
+use crate::state_store::{
+    KLogQuery, KLogStateStore, KLogStateStoreManager, KLogStateStoreManagerRef, MemoryStateStore,
+    RocksDbSnapshotMode, RocksDbStateStore,
+};
+use crate::{KLogEntry, KLogRequest, KLogResponse};
+use openraft::entry::EntryPayload;
+use openraft::storage::RaftStateMachine;
+use openraft::{CommittedLeaderId, Entry, LogId};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+fn retention_entry(id: u64, timestamp: u64, source: &str) -> KLogEntry {
+    KLogEntry {
+        id,
+        timestamp,
+        node_id: 1,
+        request_id: None,
+        level: Default::default(),
+        source: Some(source.to_string()),
+        attrs: Default::default(),
+        message: format!("retention-msg-{}", id),
+    }
+}
+
+// ids 1..=10, timestamp = id * 100, odd ids from "app", even ids from "sys".
+fn retention_entries() -> Vec<KLogEntry> {
+    (1..=10)
+        .map(|id| retention_entry(id, id * 100, if id % 2 == 1 { "app" } else { "sys" }))
+        .collect()
+}
+
+async fn memory_manager() -> anyhow::Result<KLogStateStoreManagerRef> {
+    let state_store = Arc::new(Box::new(MemoryStateStore::new()) as Box<dyn KLogStateStore>);
+    let manager = KLogStateStoreManager::new(state_store).await?;
+    manager.append(retention_entries()).await?;
+    Ok(Arc::new(manager))
+}
+
+async fn entry_ids(manager: &KLogStateStoreManager) -> anyhow::Result<Vec<u64>> {
+    let entries = manager
+        .query_entries(KLogQuery {
+            limit: 100,
+            ..Default::default()
+        })
+        .await?;
+    Ok(entries.into_iter().map(|e| e.id).collect())
+}
+
+#[test]
+fn test_retention_policy_validate() {
+    assert!(KLogRetentionPolicy::default().validate().is_ok());
+    assert!(KLogRetentionPolicy::default().is_unbounded());
+
+    let zero = KLogRetentionPolicy {
+        global: KLogRetentionRule {
+            max_entries: Some(0),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(zero.validate().unwrap_err().contains("global.max_entries"));
+
+    let mut sources = BTreeMap::new();
+    sources.insert(" app".to_string(), KLogRetentionRule::default());
+    let bad_source = KLogRetentionPolicy {
+        global: Default::default(),
+        sources,
+    };
+    assert!(bad_source.validate().is_err());
+
+    let json = r#"{"global":{"max_age_ms":1000},"sources":{"app":{"max_entries":5}}}"#;
+    let policy = KLogRetentionPolicy::from_meta_value(json).unwrap();
+    assert_eq!(policy.global.max_age_ms, Some(1000));
+    assert_eq!(policy.sources["app"].max_entries, Some(5));
+    assert!(policy.validate().is_ok());
+    let roundtrip = KLogRetentionPolicy::from_meta_value(&policy.to_meta_value().unwrap()).unwrap();
+    assert_eq!(roundtrip, policy);
+
+    assert!(is_reserved_meta_key(KLOG_RETENTION_POLICY_META_KEY));
+    assert!(!is_reserved_meta_key("app/__klog/x"));
+}
+
+#[tokio::test]
+async fn test_plan_retention_by_entries_age_and_bytes() -> anyhow::Result<()> {
+    let manager = memory_manager().await?;
+
+    let by_entries = KLogRetentionPolicy {
+        global: KLogRetentionRule {
+            max_entries: Some(3),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert_eq!(
+        plan_retention(&manager, &by_entries, 10_000).await?,
+        vec![KLogTruncatePlan {
+            before_id: 8,
+            source: None
+        }]
+    );
+
+    // now=1000, max_age=450 keeps timestamps >= 550, i.e. ids 6..=10.
+    let by_age = KLogRetentionPolicy {
+        global: KLogRetentionRule {
+            max_age_ms: Some(450),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert_eq!(
+        plan_retention(&manager, &by_age, 1_000).await?,
+        vec![KLogTruncatePlan {
+            before_id: 6,
+            source: None
+        }]
+    );
+
+    let entry_bytes = estimate_entry_bytes(&retention_entry(10, 1000, "sys"));
+    let by_bytes = KLogRetentionPolicy {
+        global: KLogRetentionRule {
+            max_bytes: Some(entry_bytes * 2),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert_eq!(
+        plan_retention(&manager, &by_bytes, 10_000).await?,
+        vec![KLogTruncatePlan {
+            before_id: 9,
+            source: None
+        }]
+    );
+
+    let within_limits = KLogRetentionPolicy {
+        global: KLogRetentionRule {
+            max_entries: Some(100),
+            max_age_ms: Some(1_000_000),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(
+        plan_retention(&manager, &within_limits, 10_000)
+            .await?
+            .is_empty()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_truncate_per_source_keeps_other_sources() -> anyhow::Result<()> {
+    let manager = memory_manager().await?;
+
+    let mut sources = BTreeMap::new();
+    sources.insert(
+        "app".to_string(),
+        KLogRetentionRule {
+            max_entries: Some(2),
+            ..Default::default()
+        },
+    );
+    let policy = KLogRetentionPolicy {
+        global: Default::default(),
+        sources,
+    };
+    let plans = plan_retention(&manager, &policy, 10_000).await?;
+    assert_eq!(
+        plans,
+        vec![KLogTruncatePlan {
+            before_id: 6,
+            source: Some("app".to_string())
+        }]
+    );
+
+    let removed = manager.truncate_entries_before(6, Some("app")).await?;
+    assert_eq!(removed, 3);
+    assert_eq!(entry_ids(&manager).await?, vec![2, 4, 6, 7, 8, 9, 10]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_truncate_never_removes_newest_entry() -> anyhow::Result<()> {
+    let manager = memory_manager().await?;
+
+    let removed = manager.truncate_entries_before(u64::MAX, None).await?;
+    assert_eq!(removed, 9);
+    assert_eq!(entry_ids(&manager).await?, vec![10]);
+    assert_eq!(manager.peek_next_log_id(), 11);
+
+    let stats = collect_retention_stats(&manager).await?;
+    assert_eq!(stats.entries, 1);
+    assert_eq!(stats.first_id, Some(10));
+    assert_eq!(stats.last_id, Some(10));
+    assert_eq!(stats.oldest_timestamp, Some(1000));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rocksdb_truncate_before_and_reopen() -> anyhow::Result<()> {
+    let path = unique_test_path("state_store_truncate_before.rocks");
+    let rocks = RocksDbStateStore::open_with_mode(&path, RocksDbSnapshotMode::Enumerate)
+        .map_err(anyhow::Error::msg)?;
+    let state_store = Arc::new(Box::new(rocks) as Box<dyn KLogStateStore>);
+    let manager = KLogStateStoreManager::new(state_store).await?;
+    manager.append(retention_entries()).await?;
+
+    assert_eq!(manager.truncate_entries_before(5, Some("sys")).await?, 2);
+    assert_eq!(manager.truncate_entries_before(4, None).await?, 2);
+    assert_eq!(entry_ids(&manager).await?, vec![5, 6, 7, 8, 9, 10]);
+
+    let sys = manager
+        .query_entries(KLogQuery {
+            source: Some("sys".to_string()),
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(sys.iter().map(|e| e.id).collect::<Vec<_>>(), vec![6, 8, 10]);
+    drop(manager);
+
+    let reopened = RocksDbStateStore::open_with_mode(&path, RocksDbSnapshotMode::Enumerate)
+        .map_err(anyhow::Error::msg)?;
+    let reopened = Arc::new(Box::new(reopened) as Box<dyn KLogStateStore>);
+    let manager = KLogStateStoreManager::new(reopened).await?;
+    assert_eq!(entry_ids(&manager).await?, vec![5, 6, 7, 8, 9, 10]);
+    assert_eq!(manager.peek_next_log_id(), 11);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_state_machine_apply_truncate_before() -> anyhow::Result<()> {
+    let context = TestMemoryContext::new().await;
+    let mut sm = context.state_machine;
+
+    let mut entries = retention_entries()
+        .into_iter()
+        .enumerate()
+        .map(|(idx, item)| Entry {
+            log_id: LogId::new(CommittedLeaderId::new(1, 0), idx as u64 + 1),
+            payload: EntryPayload::Normal(KLogRequest::AppendLog { item }),
+        })
+        .collect::<Vec<_>>();
+    entries.push(Entry {
+        log_id: LogId::new(CommittedLeaderId::new(1, 0), 11),
+        payload: EntryPayload::Normal(KLogRequest::TruncateBefore {
+            before_id: 4,
+            source: None,
+        }),
+    });
+
+    let resps = sm.apply(entries).await?;
+    match resps.last() {
+        Some(KLogResponse::TruncateOk {
+            before_id,
+            source,
+            removed,
+        }) => {
+            assert_eq!(*before_id, 4);
+            assert!(source.is_none());
+            assert_eq!(*removed, 3);
+        }
+        other => panic!("unexpected response: {:?}", other),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,416 @@
+use super::common::{sample_membership, unique_test_path};
+use crate::state_machine::{
+    KSnapshotChunkAck, KSnapshotData, KSnapshotMeta, KSnapshotSegment, SegmentedSnapshotReader,
+    SegmentedSnapshotWriter, SnapshotManager,
+};
+use crate::state_store::{
+    KLogQuery, KLogStateStore, KLogStateStoreManager, MemoryStateStore, RocksDbSnapshotMode,
+    RocksDbStateStore,
+};
+use crate::{KLogEntry, KLogMetaEntry};
+use openraft::{CommittedLeaderId, LogId, StoredMembership};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn snapshot_entry(id: u64) -> KLogEntry {
+    KLogEntry {
+        id,
+        timestamp: 1_000 + id,
+        node_id: 1,
+        request_id: None,
+        level: Default::default(),
+        source: Some(if id % 2 == 0 { "sys" } else { "app" }.to_string()),
+        attrs: Default::default(),
+        message: format!("snapshot-msg-{}", id),
+    }
+}
+
+fn snapshot_meta_entry(key: &str, revision: u64) -> KLogMetaEntry {
+    KLogMetaEntry {
+        key: key.to_string(),
+        value: format!("value-of-{}", key),
+        updated_at: 500 + revision,
+        updated_by: 1,
+        revision,
+        lease_id: None,
+    }
+}
+
+fn snapshot_meta(index: u64) -> KSnapshotMeta {
+    let last_log_id = LogId::new(CommittedLeaderId::new(3, 1), index);
+    KSnapshotMeta {
+        last_log_id: Some(last_log_id),
+        last_membership: StoredMembership::new(Some(last_log_id), sample_membership(1)),
+        snapshot_id: SnapshotManager::generate_snapshot_id(Some(&last_log_id)),
+    }
+}
+
+fn snapshot_dir(name: &str) -> anyhow::Result<PathBuf> {
+    let dir = unique_test_path(name);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+async fn memory_manager() -> anyhow::Result<KLogStateStoreManager> {
+    let state_store = Arc::new(Box::new(MemoryStateStore::new()) as Box<dyn KLogStateStore>);
+    Ok(KLogStateStoreManager::new(state_store).await?)
+}
+
+async fn entry_ids(manager: &KLogStateStoreManager) -> anyhow::Result<Vec<u64>> {
+    let entries = manager
+        .query_entries(KLogQuery {
+            limit: 1_000,
+            ..Default::default()
+        })
+        .await?;
+    Ok(entries.into_iter().map(|e| e.id).collect())
+}
+
+async fn write_sample_segments(meta: &KSnapshotMeta) -> anyhow::Result<Vec<u8>> {
+    let entries = (1..=5).map(snapshot_entry).collect::<Vec<_>>();
+    let mut writer = SegmentedSnapshotWriter::begin(Vec::new(), meta).await?;
+    writer.write_entries(&entries[..3]).await?;
+    writer.write_entries(&entries[3..]).await?;
+    writer
+        .write_meta(&[snapshot_meta_entry("a", 2), snapshot_meta_entry("b", 7)])
+        .await?;
+    let (bytes, summary, written) = writer.finish().await?;
+    assert_eq!(written, bytes.len() as u64);
+    assert_eq!(summary.entry_segments, 2);
+    assert_eq!(summary.entries, 5);
+    assert_eq!(summary.meta_entries, 2);
+    assert_eq!(summary.max_entry_id, Some(5));
+    Ok(bytes)
+}
+
+async fn read_all_segments(bytes: &[u8]) -> std::io::Result<Vec<KSnapshotSegment>> {
+    let mut reader = SegmentedSnapshotReader::open(bytes).await?;
+    let mut segments = Vec::new();
+    while let Some(segment) = reader.next_segment().await? {
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+#[tokio::test]
+async fn test_segmented_snapshot_roundtrip() -> anyhow::Result<()> {
+    let meta = snapshot_meta(9);
+    let bytes = write_sample_segments(&meta).await?;
+
+    let mut reader = SegmentedSnapshotReader::open(bytes.as_slice()).await?;
+    assert_eq!(reader.meta(), &meta);
+    let mut ids = Vec::new();
+    let mut meta_keys = Vec::new();
+    while let Some(segment) = reader.next_segment().await? {
+        match segment {
+            KSnapshotSegment::Entries(segment) => {
+                assert_eq!(segment.first_id, segment.entries[0].id);
+                assert_eq!(segment.last_id, segment.entries.last().unwrap().id);
+                ids.extend(segment.entries.iter().map(|e| e.id));
+            }
+            KSnapshotSegment::Meta(items) => {
+                meta_keys.extend(items.into_iter().map(|m| (m.key, m.revision)));
+            }
+        }
+    }
+    assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+    assert_eq!(meta_keys, vec![("a".to_string(), 2), ("b".to_string(), 7)]);
+    assert_eq!(reader.summary().entries, 5);
+
+    // Entry segments must be strictly ascending.
+    let mut writer = SegmentedSnapshotWriter::begin(Vec::new(), &meta).await?;
+    writer.write_entries(&[snapshot_entry(4)]).await?;
+    assert!(writer.write_entries(&[snapshot_entry(2)]).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_segmented_snapshot_detects_corruption_and_truncation() -> anyhow::Result<()> {
+    let meta = snapshot_meta(9);
+    let bytes = write_sample_segments(&meta).await?;
+
+    let mut corrupted = bytes.clone();
+    let idx = corrupted.len() / 2;
+    corrupted[idx] ^= 0xff;
+    let err = read_all_segments(&corrupted).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // Drop the footer: every remaining segment is intact but the stream must still be rejected.
+    let truncated = &bytes[..bytes.len() - 8];
+    assert!(read_all_segments(truncated).await.is_err());
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = b'X';
+    assert!(read_all_segments(&bad_magic).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_segmented_snapshot_build_and_restore() -> anyhow::Result<()> {
+    let source = memory_manager().await?;
+    source
+        .append((1..=23).map(snapshot_entry).collect())
+        .await?;
+    for key in ["k1", "k2", "k3", "k4", "k5"] {
+        source.put_meta_entry(snapshot_meta_entry(key, 0)).await?;
+    }
+    // Bump k3 so the restored revision differs from a freshly inserted key.
+    source.put_meta_entry(snapshot_meta_entry("k3", 0)).await?;
+    let k3_revision = source.get_meta_entry("k3").await?.unwrap().revision;
+    assert!(k3_revision > 1);
+
+    let dir = snapshot_dir("segmented_build_restore")?;
+    let manager = SnapshotManager::new(dir.clone()).with_segment_entries(4);
+    let meta = snapshot_meta(30);
+    let view = source.pin_export_view().await?;
+    // Entries after the bound were appended after the snapshot point and must be left out.
+    let (path, summary) = manager
+        .save_segmented_snapshot(&meta, view.as_ref(), Some(20))
+        .await?;
+    assert_eq!(summary.entries, 20);
+    assert_eq!(summary.entry_segments, 5);
+    assert_eq!(summary.meta_entries, 5);
+    assert_eq!(summary.max_entry_id, Some(20));
+    assert!(
+        manager
+            .save_segmented_snapshot(&meta, view.as_ref(), Some(20))
+            .await
+            .is_err()
+    );
+
+    assert_eq!(manager.read_snapshot_meta(&path).await?, meta);
+    let (current_path, current_meta) = manager.load_current_snapshot().await?.unwrap();
+    assert_eq!(current_path, path);
+    assert_eq!(current_meta, meta);
+
+    let memory_target = memory_manager().await?;
+    memory_target.append(vec![snapshot_entry(99)]).await?;
+    memory_target
+        .put_meta_entry(snapshot_meta_entry("stale", 1))
+        .await?;
+
+    let rocks_path = unique_test_path("segmented_restore.rocks");
+    let rocks = RocksDbStateStore::open_with_mode(&rocks_path, RocksDbSnapshotMode::Enumerate)
+        .map_err(anyhow::Error::msg)?;
+    let rocks_target =
+        KLogStateStoreManager::new(Arc::new(Box::new(rocks) as Box<dyn KLogStateStore>)).await?;
+
+    for target in [&memory_target, &rocks_target] {
+        manager.restore_snapshot(&meta, &path, target).await?;
+        assert_eq!(entry_ids(target).await?, (1..=20).collect::<Vec<_>>());
+        assert_eq!(target.peek_next_log_id(), 21);
+        assert!(target.get_meta_entry("stale").await?.is_none());
+        let k3 = target.get_meta_entry("k3").await?.unwrap();
+        assert_eq!(k3.revision, k3_revision);
+        assert_eq!(k3.value, "value-of-k3");
+
+        let sys = target
+            .query_entries(KLogQuery {
+                source: Some("sys".to_string()),
+                limit: 100,
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(sys.len(), 10);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_segmented_snapshot_ignores_writes_after_pin() -> anyhow::Result<()> {
+    let rocks_path = unique_test_path("segmented_pinned.rocks");
+    let rocks = RocksDbStateStore::open_with_mode(&rocks_path, RocksDbSnapshotMode::Enumerate)
+        .map_err(anyhow::Error::msg)?;
+    let rocks_source =
+        KLogStateStoreManager::new(Arc::new(Box::new(rocks) as Box<dyn KLogStateStore>)).await?;
+    let memory_source = memory_manager().await?;
+
+    for (name, source) in [("memory", &memory_source), ("rocks", &rocks_source)] {
+        source.append((1..=6).map(snapshot_entry).collect()).await?;
+        source.put_meta_entry(snapshot_meta_entry("k1", 0)).await?;
+        let view = source.pin_export_view().await?;
+
+        // Apply keeps running while the snapshot is paged out.
+        source.append((7..=9).map(snapshot_entry).collect()).await?;
+        source.put_meta_entry(snapshot_meta_entry("k1", 0)).await?;
+        source.put_meta_entry(snapshot_meta_entry("k2", 0)).await?;
+        source.truncate_entries_before(4, None).await?;
+
+        let manager = SnapshotManager::new(snapshot_dir(&format!("segmented_pinned_{}", name))?)
+            .with_segment_entries(2);
+        let meta = snapshot_meta(7);
+        let (path, summary) = manager
+            .save_segmented_snapshot(&meta, view.as_ref(), Some(9))
+            .await?;
+        assert_eq!(summary.entries, 6);
+        assert_eq!(summary.meta_entries, 1);
+        drop(view);
+
+        let target = memory_manager().await?;
+        manager.restore_snapshot(&meta, &path, &target).await?;
+        assert_eq!(entry_ids(&target).await?, (1..=6).collect::<Vec<_>>());
+        assert_eq!(target.get_meta_entry("k1").await?.unwrap().revision, 1);
+        assert!(target.get_meta_entry("k2").await?.is_none());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_restore_rejects_corrupted_snapshot_without_touching_store() -> anyhow::Result<()> {
+    let source = memory_manager().await?;
+    source.append((1..=8).map(snapshot_entry).collect()).await?;
+
+    let dir = snapshot_dir("segmented_restore_corrupted")?;
+    let manager = SnapshotManager::new(dir).with_segment_entries(2);
+    let meta = snapshot_meta(8);
+    let view = source.pin_export_view().await?;
+    let (path, _) = manager
+        .save_segmented_snapshot(&meta, view.as_ref(), Some(8))
+        .await?;
+
+    let mut bytes = std::fs::read(&path)?;
+    let idx = bytes.len() - 40;
+    bytes[idx] ^= 0xff;
+    std::fs::write(&path, bytes)?;
+
+    let target = memory_manager().await?;
+    target.append(vec![snapshot_entry(50)]).await?;
+    assert!(
+        manager
+            .restore_snapshot(&meta, &path, &target)
+            .await
+            .is_err()
+    );
+    assert_eq!(entry_ids(&target).await?, vec![50]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_legacy_snapshot_file_still_restores() -> anyhow::Result<()> {
+    let source = memory_manager().await?;
+    source.append((1..=3).map(snapshot_entry).collect()).await?;
+    let klog_data = source.build_snapshot().await?;
+
+    let dir = snapshot_dir("legacy_restore")?;
+    let manager = SnapshotManager::new(dir);
+    let meta = snapshot_meta(3);
+    let path = manager
+        .save_snapshot_to_file(&KSnapshotData::new(meta.clone(), klog_data.data))
+        .await?;
+    assert_eq!(manager.read_snapshot_meta(&path).await?, meta);
+
+    let target = memory_manager().await?;
+    manager.restore_snapshot(&meta, &path, &target).await?;
+    assert_eq!(entry_ids(&target).await?, vec![1, 2, 3]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_receive_snapshot_chunks_resume_after_restart() -> anyhow::Result<()> {
+    let source = memory_manager().await?;
+    source
+        .append((1..=12).map(snapshot_entry).collect())
+        .await?;
+    let k = source.put_meta_entry(snapshot_meta_entry("k", 0)).await?;
+
+    let leader_dir = snapshot_dir("receive_resume_leader")?;
+    let leader = SnapshotManager::new(leader_dir).with_segment_entries(3);
+    let meta = snapshot_meta(12);
+    let view = source.pin_export_view().await?;
+    let (path, _) = leader
+        .save_segmented_snapshot(&meta, view.as_ref(), Some(12))
+        .await?;
+    let bytes = std::fs::read(&path)?;
+    let half = bytes.len() / 2;
+
+    let follower_dir = snapshot_dir("receive_resume_follower")?;
+    let follower = SnapshotManager::new(follower_dir.clone());
+    assert_eq!(follower.received_snapshot_len(&meta.snapshot_id).await, 0);
+    let ack = follower
+        .receive_snapshot_chunk(&meta, 0, &bytes[..half])
+        .await?;
+    assert_eq!(
+        ack,
+        KSnapshotChunkAck::Accepted {
+            received: half as u64
+        }
+    );
+
+    // A chunk past the received bytes leaves a gap and reports where to resume.
+    let ack = follower
+        .receive_snapshot_chunk(&meta, half as u64 + 10, &bytes[half + 10..])
+        .await?;
+    assert_eq!(
+        ack,
+        KSnapshotChunkAck::Mismatch {
+            expected_offset: half as u64
+        }
+    );
+    drop(follower);
+
+    // The partial transfer survives a restart of the follower.
+    let follower = SnapshotManager::new(follower_dir);
+    let offset = follower.received_snapshot_len(&meta.snapshot_id).await;
+    assert_eq!(offset, half as u64);
+    follower
+        .receive_snapshot_chunk(&meta, offset, &bytes[offset as usize..])
+        .await?;
+
+    let installed = follower
+        .install_snapshot(
+            &meta,
+            Box::new(follower.open_received_snapshot(&meta).await?),
+        )
+        .await?;
+    follower.finish_received_snapshot(&meta.snapshot_id).await;
+    assert_eq!(follower.received_snapshot_len(&meta.snapshot_id).await, 0);
+
+    let target = memory_manager().await?;
+    follower
+        .restore_snapshot(&meta, &installed, &target)
+        .await?;
+    assert_eq!(entry_ids(&target).await?, (1..=12).collect::<Vec<_>>());
+    assert_eq!(target.get_meta_entry("k").await?.unwrap(), k);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_receive_snapshot_discards_corrupted_transfer() -> anyhow::Result<()> {
+    let source = memory_manager().await?;
+    source.append((1..=4).map(snapshot_entry).collect()).await?;
+
+    let leader = SnapshotManager::new(snapshot_dir("receive_corrupted_leader")?);
+    let meta = snapshot_meta(4);
+    let view = source.pin_export_view().await?;
+    let (path, _) = leader
+        .save_segmented_snapshot(&meta, view.as_ref(), Some(4))
+        .await?;
+    let mut bytes = std::fs::read(&path)?;
+    let idx = bytes.len() / 2;
+    bytes[idx] ^= 0xff;
+
+    let follower = SnapshotManager::new(snapshot_dir("receive_corrupted_follower")?);
+    follower.receive_snapshot_chunk(&meta, 0, &bytes).await?;
+    assert!(follower.open_received_snapshot(&meta).await.is_err());
+    assert_eq!(follower.received_snapshot_len(&meta.snapshot_id).await, 0);
+
+    // Starting another snapshot drops receive files left behind by older transfers.
+    let other = snapshot_meta(5);
+    follower
+        .receive_snapshot_chunk(&meta, 0, &bytes[..16])
+        .await?;
+    follower
+        .receive_snapshot_chunk(&other, 0, &bytes[..16])
+        .await?;
+    assert_eq!(follower.received_snapshot_len(&meta.snapshot_id).await, 0);
+    assert_eq!(follower.received_snapshot_len(&other.snapshot_id).await, 16);
+
+    Ok(())
+}
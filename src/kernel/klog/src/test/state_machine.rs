@@ -129,6 +129,69 @@ async fn test_state_store_manager_request_id_dedup() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_state_machine_apply_append_batch_dedups_request_ids() -> anyhow::Result<()> {
+    let state_store = Arc::new(Box::new(MemoryStateStore::new()) as Box<dyn KLogStateStore>);
+    let manager = Arc::new(KLogStateStoreManager::new(state_store).await?);
+    let data_dir = unique_test_path("append_batch_snapshot");
+    std::fs::create_dir_all(&data_dir)?;
+    let mut sm =
+        KLogStateMachine::new(manager.clone(), Arc::new(SnapshotManager::new(data_dir))).await?;
+    let entry = |request_id: Option<&str>, message: &str| KLogEntry {
+        id: 0,
+        timestamp: 400,
+        node_id: 1,
+        request_id: request_id.map(|v| v.to_string()),
+        level: Default::default(),
+        source: None,
+        attrs: Default::default(),
+        message: message.to_string(),
+    };
+
+    let first_id = manager
+        .append_prepared_entry(manager.prepare_append_entry(entry(Some("batch-0"), "single")))
+        .await?;
+    let items = manager.prepare_append_batch(vec![
+        entry(Some("batch-1"), "one"),
+        entry(None, "two"),
+        // Retried entries, committed earlier and earlier in this batch.
+        entry(Some("batch-0"), "single-retry"),
+        entry(Some(" batch-1 "), "one-retry"),
+    ]);
+    let allocated = items.iter().map(|item| item.id).collect::<Vec<_>>();
+    assert_eq!(
+        allocated,
+        (first_id + 1..=first_id + 4).collect::<Vec<_>>(),
+        "batch ids must be one contiguous range"
+    );
+
+    let resps = sm
+        .apply(vec![Entry {
+            log_id: LogId::new(CommittedLeaderId::new(2, 0), 1),
+            payload: EntryPayload::Normal(KLogRequest::AppendLogBatch { items }),
+        }])
+        .await?;
+    let KLogResponse::AppendBatchOk { ids } = &resps[0] else {
+        panic!("unexpected response: {:?}", resps[0]);
+    };
+    assert_eq!(
+        ids,
+        &vec![first_id + 1, first_id + 2, first_id, first_id + 1]
+    );
+
+    let entries = manager.query_entries(KLogQuery::default()).await?;
+    assert_eq!(
+        entries
+            .iter()
+            .map(|e| e.message.as_str())
+            .collect::<Vec<_>>(),
+        vec!["single", "one", "two"]
+    );
+    assert_eq!(manager.peek_next_log_id(), first_id + 5);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_state_machine_recovers_persisted_meta_after_restart() -> anyhow::Result<()> {
     let state_store_path = unique_test_path("state_machine_meta_restart.rocks");
@@ -196,6 +259,7 @@ async fn test_state_machine_apply_meta_put_and_delete() -> anyhow::Result<()> {
                 updated_at: 5000,
                 updated_by: 1,
                 revision: 0,
+                lease_id: None,
             },
             expected_revision: None,
         }),
@@ -239,6 +303,7 @@ async fn test_state_machine_apply_meta_put_with_optional_cas() -> anyhow::Result
                 updated_at: 6000,
                 updated_by: 1,
                 revision: 0,
+                lease_id: None,
             },
             expected_revision: Some(0),
         }),
@@ -252,6 +317,7 @@ async fn test_state_machine_apply_meta_put_with_optional_cas() -> anyhow::Result
                 updated_at: 6001,
                 updated_by: 1,
                 revision: 0,
+                lease_id: None,
             },
             expected_revision: Some(1),
         }),
@@ -265,6 +331,7 @@ async fn test_state_machine_apply_meta_put_with_optional_cas() -> anyhow::Result
                 updated_at: 6002,
                 updated_by: 1,
                 revision: 0,
+                lease_id: None,
             },
             expected_revision: Some(1),
         }),
@@ -91,6 +91,7 @@ async fn test_rocksdb_request_id_dedup_persists_after_reopen() -> anyhow::Result
             source: None,
             attr_key: None,
             attr_value: None,
+            ..Default::default()
         })
         .await?;
     assert_eq!(items.len(), 1);
@@ -114,6 +115,7 @@ async fn test_rocksdb_meta_persists_after_reopen() -> anyhow::Result<()> {
             updated_at: 1000,
             updated_by: 1,
             revision: 0,
+            lease_id: None,
         })
         .await?;
     drop(manager);
@@ -137,6 +139,7 @@ async fn test_rocksdb_meta_persists_after_reopen() -> anyhow::Result<()> {
             updated_at: 1001,
             updated_by: 1,
             revision: 0,
+            lease_id: None,
         })
         .await?;
     assert_eq!(second.revision, 2);
@@ -164,6 +167,7 @@ async fn test_rocksdb_meta_snapshot_roundtrip() -> anyhow::Result<()> {
             updated_at: 2000,
             updated_by: 2,
             revision: 0,
+            lease_id: None,
         })
         .await?;
     let src_snapshot = src_mgr.build_snapshot().await?;
@@ -522,6 +526,7 @@ async fn test_rocksdb_query_entries_asc_range_limit() -> anyhow::Result<()> {
             source: None,
             attr_key: None,
             attr_value: None,
+            ..Default::default()
         })
         .await?;
     let ids = items.into_iter().map(|e| e.id).collect::<Vec<_>>();
@@ -592,6 +597,7 @@ async fn test_rocksdb_query_entries_desc_range_limit() -> anyhow::Result<()> {
             source: None,
             attr_key: None,
             attr_value: None,
+            ..Default::default()
         })
         .await?;
     let ids = items.into_iter().map(|e| e.id).collect::<Vec<_>>();
@@ -668,6 +674,7 @@ async fn test_rocksdb_query_entries_with_source_level_and_attrs() -> anyhow::Res
             source: Some("kernel/kmsg".to_string()),
             attr_key: None,
             attr_value: None,
+            ..Default::default()
         })
         .await?;
     assert_eq!(
@@ -685,6 +692,7 @@ async fn test_rocksdb_query_entries_with_source_level_and_attrs() -> anyhow::Res
             source: None,
             attr_key: None,
             attr_value: None,
+            ..Default::default()
         })
         .await?;
     assert_eq!(
@@ -702,6 +710,7 @@ async fn test_rocksdb_query_entries_with_source_level_and_attrs() -> anyhow::Res
             source: Some("kernel/kmsg".to_string()),
             attr_key: Some("pid".to_string()),
             attr_value: Some("43".to_string()),
+            ..Default::default()
         })
         .await?;
     assert_eq!(
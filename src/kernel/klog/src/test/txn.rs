@@ -0,0 +1,338 @@
+use super::common::unique_test_path;
+use crate::lease;
+use crate::state_machine::{KLogStateMachine, SnapshotManager};
+use crate::state_store::{
+    KLogMetaWatchBatch, KLogMetaWatchEventKind, KLogStateStore, KLogStateStoreManager,
+    KLogStateStoreManagerRef, MemoryStateStore, RocksDbSnapshotMode, RocksDbStateStore,
+};
+use crate::txn::{KLogTxnCompare, KLogTxnOp, KLogTxnOpResult};
+use crate::{KLogMetaEntry, KLogRequest, KLogResponse};
+use openraft::entry::EntryPayload;
+use openraft::storage::RaftStateMachine;
+use openraft::{CommittedLeaderId, Entry, LogId};
+use std::sync::Arc;
+
+fn entry(index: u64, req: KLogRequest) -> Entry<crate::KTypeConfig> {
+    Entry {
+        log_id: LogId::new(CommittedLeaderId::new(1, 0), index),
+        payload: EntryPayload::Normal(req),
+    }
+}
+
+fn put_op(key: &str, value: &str, lease_id: Option<u64>) -> KLogTxnOp {
+    KLogTxnOp::Put {
+        item: KLogMetaEntry {
+            key: key.to_string(),
+            value: value.to_string(),
+            updated_at: 1000,
+            updated_by: 1,
+            revision: 0,
+            lease_id,
+        },
+    }
+}
+
+fn txn(
+    compare: Vec<KLogTxnCompare>,
+    then_ops: Vec<KLogTxnOp>,
+    else_ops: Vec<KLogTxnOp>,
+) -> KLogRequest {
+    KLogRequest::Txn {
+        compare,
+        then_ops,
+        else_ops,
+    }
+}
+
+async fn memory_state_machine() -> anyhow::Result<(KLogStateStoreManagerRef, KLogStateMachine)> {
+    let state_store = Arc::new(Box::new(MemoryStateStore::new()) as Box<dyn KLogStateStore>);
+    let manager = Arc::new(KLogStateStoreManager::new(state_store).await?);
+    let data_dir = unique_test_path("txn_snapshot");
+    std::fs::create_dir_all(&data_dir)?;
+    let snapshot_manager = Arc::new(SnapshotManager::new(data_dir));
+    let sm = KLogStateMachine::new(manager.clone(), snapshot_manager).await?;
+    Ok((manager, sm))
+}
+
+#[test]
+fn test_txn_compare_matches_current_meta() {
+    let meta = KLogMetaEntry {
+        key: "k".to_string(),
+        value: "v".to_string(),
+        updated_at: 1,
+        updated_by: 1,
+        revision: 3,
+        lease_id: None,
+    };
+    let key = "k".to_string();
+    let revision_equals = |revision| KLogTxnCompare::RevisionEquals {
+        key: key.clone(),
+        revision,
+    };
+
+    assert!(revision_equals(3).matches(Some(&meta)));
+    assert!(!revision_equals(2).matches(Some(&meta)));
+    assert!(revision_equals(0).matches(None));
+    assert!(!revision_equals(0).matches(Some(&meta)));
+    assert!(KLogTxnCompare::Exists { key: key.clone() }.matches(Some(&meta)));
+    assert!(!KLogTxnCompare::Exists { key: key.clone() }.matches(None));
+    assert!(KLogTxnCompare::Absent { key }.matches(None));
+}
+
+#[tokio::test]
+async fn test_state_machine_txn_then_and_else_branches() -> anyhow::Result<()> {
+    let (manager, mut sm) = memory_state_machine().await?;
+
+    let replies = sm
+        .apply(vec![
+            // Create-if-absent for two keys at once.
+            entry(
+                1,
+                txn(
+                    vec![
+                        KLogTxnCompare::Absent {
+                            key: "cfg/a".to_string(),
+                        },
+                        KLogTxnCompare::Absent {
+                            key: "cfg/b".to_string(),
+                        },
+                    ],
+                    vec![put_op("cfg/a", "a1", None), put_op("cfg/b", "b1", None)],
+                    vec![KLogTxnOp::Get {
+                        key: "cfg/a".to_string(),
+                    }],
+                ),
+            ),
+            // Same txn again takes the else branch and reads the winner.
+            entry(
+                2,
+                txn(
+                    vec![KLogTxnCompare::Absent {
+                        key: "cfg/a".to_string(),
+                    }],
+                    vec![put_op("cfg/a", "a2", None)],
+                    vec![KLogTxnOp::Get {
+                        key: "cfg/a".to_string(),
+                    }],
+                ),
+            ),
+            // Compare-and-swap on one key, delete another.
+            entry(
+                3,
+                txn(
+                    vec![KLogTxnCompare::RevisionEquals {
+                        key: "cfg/a".to_string(),
+                        revision: 1,
+                    }],
+                    vec![
+                        put_op("cfg/a", "a2", None),
+                        KLogTxnOp::Delete {
+                            key: "cfg/b".to_string(),
+                        },
+                        KLogTxnOp::Delete {
+                            key: "cfg/missing".to_string(),
+                        },
+                    ],
+                    vec![],
+                ),
+            ),
+        ])
+        .await?;
+
+    let KLogResponse::TxnOk { succeeded, results } = &replies[0] else {
+        panic!("unexpected txn reply: {:?}", replies[0]);
+    };
+    assert!(succeeded);
+    assert_eq!(
+        results,
+        &vec![
+            KLogTxnOpResult::Put {
+                key: "cfg/a".to_string(),
+                revision: 1
+            },
+            KLogTxnOpResult::Put {
+                key: "cfg/b".to_string(),
+                revision: 1
+            },
+        ]
+    );
+    let KLogResponse::TxnOk { succeeded, results } = &replies[1] else {
+        panic!("unexpected txn reply: {:?}", replies[1]);
+    };
+    assert!(!succeeded);
+    assert!(matches!(
+        results.as_slice(),
+        [KLogTxnOpResult::Get { item: Some(item), .. }] if item.value == "a1"
+    ));
+    let KLogResponse::TxnOk { succeeded, results } = &replies[2] else {
+        panic!("unexpected txn reply: {:?}", replies[2]);
+    };
+    assert!(succeeded);
+    assert!(matches!(
+        results.as_slice(),
+        [
+            KLogTxnOpResult::Put { revision: 2, .. },
+            KLogTxnOpResult::Delete { existed: true, .. },
+            KLogTxnOpResult::Delete {
+                existed: false,
+                prev_meta: None,
+                ..
+            },
+        ]
+    ));
+    assert_eq!(
+        manager.get_meta_entry("cfg/a").await?.map(|v| v.value),
+        Some("a2".to_string())
+    );
+    assert!(manager.get_meta_entry("cfg/b").await?.is_none());
+
+    // All changes of one txn share its log index as watch revision.
+    let KLogMetaWatchBatch::Events { events, .. } =
+        manager.watch_hub().meta_events_after(0, None, 1)
+    else {
+        panic!("history must not be compacted");
+    };
+    assert_eq!(
+        events
+            .iter()
+            .map(|e| (e.revision, e.kind, e.key.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            (1, KLogMetaWatchEventKind::Put, "cfg/a"),
+            (1, KLogMetaWatchEventKind::Put, "cfg/b"),
+        ]
+    );
+    let KLogMetaWatchBatch::Events { events, .. } =
+        manager.watch_hub().meta_events_after(1, None, 10)
+    else {
+        panic!("history must not be compacted");
+    };
+    assert_eq!(
+        events
+            .iter()
+            .map(|e| (e.revision, e.kind, e.key.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            (3, KLogMetaWatchEventKind::Put, "cfg/a"),
+            (3, KLogMetaWatchEventKind::Delete, "cfg/b"),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_state_machine_txn_unknown_lease_rejects_whole_branch() -> anyhow::Result<()> {
+    let (manager, mut sm) = memory_state_machine().await?;
+
+    let replies = sm
+        .apply(vec![
+            entry(
+                1,
+                KLogRequest::GrantLease {
+                    ttl_ms: 5_000,
+                    granted_at: 1_000,
+                    granted_by: 1,
+                },
+            ),
+            entry(
+                2,
+                txn(
+                    vec![],
+                    vec![
+                        put_op("lock/a", "holder", Some(1)),
+                        put_op("lock/b", "holder", Some(7)),
+                    ],
+                    vec![],
+                ),
+            ),
+        ])
+        .await?;
+    assert!(matches!(
+        replies[1],
+        KLogResponse::LeaseNotFound { lease_id: 7 }
+    ));
+    assert!(manager.get_meta_entry("lock/a").await?.is_none());
+    assert!(manager.get_meta_entry("lock/b").await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_state_machine_txn_commits_with_applied_id() -> anyhow::Result<()> {
+    let path = unique_test_path("txn_commit.rocks");
+    let rocks = RocksDbStateStore::open_with_mode(&path, RocksDbSnapshotMode::Enumerate)
+        .map_err(anyhow::Error::msg)?;
+    let manager = Arc::new(
+        KLogStateStoreManager::new(Arc::new(Box::new(rocks) as Box<dyn KLogStateStore>)).await?,
+    );
+    let data_dir = unique_test_path("txn_commit_snapshot");
+    std::fs::create_dir_all(&data_dir)?;
+    let mut sm =
+        KLogStateMachine::new(manager.clone(), Arc::new(SnapshotManager::new(data_dir))).await?;
+
+    let replies = sm
+        .apply(vec![
+            entry(
+                1,
+                KLogRequest::GrantLease {
+                    ttl_ms: 5_000,
+                    granted_at: 1_000,
+                    granted_by: 1,
+                },
+            ),
+            // Later ops of a branch see the earlier ones, lease key sets included.
+            entry(
+                2,
+                txn(
+                    vec![],
+                    vec![
+                        put_op("lock/a", "holder", Some(1)),
+                        put_op("lock/b", "holder", Some(1)),
+                        put_op("lock/a", "holder-2", Some(1)),
+                        KLogTxnOp::Delete {
+                            key: "lock/b".to_string(),
+                        },
+                        KLogTxnOp::Get {
+                            key: "lock/a".to_string(),
+                        },
+                    ],
+                    vec![],
+                ),
+            ),
+        ])
+        .await?;
+    let KLogResponse::TxnOk { succeeded, results } = &replies[1] else {
+        panic!("unexpected txn reply: {:?}", replies[1]);
+    };
+    assert!(succeeded);
+    assert!(matches!(
+        results.as_slice(),
+        [
+            KLogTxnOpResult::Put { revision: 1, .. },
+            KLogTxnOpResult::Put { revision: 1, .. },
+            KLogTxnOpResult::Put { revision: 2, .. },
+            KLogTxnOpResult::Delete { existed: true, .. },
+            KLogTxnOpResult::Get { item: Some(item), .. },
+        ] if item.value == "holder-2" && item.revision == 2
+    ));
+    drop(sm);
+    drop(manager);
+
+    let reopened = RocksDbStateStore::open_with_mode(&path, RocksDbSnapshotMode::Enumerate)
+        .map_err(anyhow::Error::msg)?;
+    let manager = Arc::new(
+        KLogStateStoreManager::new(Arc::new(Box::new(reopened) as Box<dyn KLogStateStore>)).await?,
+    );
+    let meta = manager.load_state_machine_meta().await?.unwrap();
+    assert_eq!(meta.last_applied_log_id.map(|id| id.index), Some(2));
+    assert_eq!(manager.get_meta_entry("lock/a").await?.unwrap().revision, 2);
+    assert!(manager.get_meta_entry("lock/b").await?.is_none());
+    let lease = lease::load_lease(&manager, 1).await?.unwrap();
+    assert_eq!(
+        lease.keys.into_iter().collect::<Vec<_>>(),
+        vec!["lock/a".to_string()]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use super::common::unique_test_path;
+use crate::state_machine::{KLogStateMachine, SnapshotManager};
+use crate::state_store::{
+    KLogMetaWatchBatch, KLogMetaWatchEvent, KLogMetaWatchEventKind, KLogStateStore,
+    KLogStateStoreManager, KLogStateStoreManagerRef, KLogWatchHub, MemoryStateStore,
+};
+use crate::{KLogMetaEntry, KLogRequest};
+use openraft::entry::EntryPayload;
+use openraft::storage::RaftStateMachine;
+use openraft::{CommittedLeaderId, Entry, LogId};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn meta_event(revision: u64, key: &str) -> KLogMetaWatchEvent {
+    KLogMetaWatchEvent {
+        revision,
+        kind: KLogMetaWatchEventKind::Put,
+        key: key.to_string(),
+        meta: KLogMetaEntry {
+            key: key.to_string(),
+            value: format!("v{}", revision),
+            updated_at: revision * 10,
+            updated_by: 1,
+            revision: 1,
+            lease_id: None,
+        },
+    }
+}
+
+fn batch_revisions(batch: &KLogMetaWatchBatch) -> (Vec<u64>, u64) {
+    match batch {
+        KLogMetaWatchBatch::Events { events, revision } => {
+            (events.iter().map(|e| e.revision).collect(), *revision)
+        }
+        other => panic!("unexpected compacted batch: {:?}", other),
+    }
+}
+
+fn meta_entry(index: u64, req: KLogRequest) -> Entry<crate::KTypeConfig> {
+    Entry {
+        log_id: LogId::new(CommittedLeaderId::new(1, 0), index),
+        payload: EntryPayload::Normal(req),
+    }
+}
+
+fn put_meta(key: &str, value: &str) -> KLogRequest {
+    KLogRequest::PutMeta {
+        item: KLogMetaEntry {
+            key: key.to_string(),
+            value: value.to_string(),
+            updated_at: 1000,
+            updated_by: 1,
+            revision: 0,
+            lease_id: None,
+        },
+        expected_revision: None,
+    }
+}
+
+async fn memory_state_machine() -> anyhow::Result<(KLogStateStoreManagerRef, KLogStateMachine)> {
+    let state_store = Arc::new(Box::new(MemoryStateStore::new()) as Box<dyn KLogStateStore>);
+    let manager = Arc::new(KLogStateStoreManager::new(state_store).await?);
+    let data_dir = unique_test_path("watch_snapshot");
+    std::fs::create_dir_all(&data_dir)?;
+    let snapshot_manager = Arc::new(SnapshotManager::new(data_dir));
+    let sm = KLogStateMachine::new(manager.clone(), snapshot_manager).await?;
+    Ok((manager, sm))
+}
+
+#[test]
+fn test_watch_hub_prefix_limit_and_cursor() {
+    let hub = KLogWatchHub::new(16);
+    hub.record_meta_event(meta_event(1, "app/a"));
+    hub.record_meta_event(meta_event(2, "sys/x"));
+    hub.record_meta_event(meta_event(3, "app/b"));
+    hub.record_meta_event(meta_event(4, "app/c"));
+
+    // Nothing is visible until the apply batch is marked done.
+    assert_eq!(
+        batch_revisions(&hub.meta_events_after(0, None, 10)),
+        (vec![], 0)
+    );
+    hub.advance(5);
+    assert_eq!(hub.current_revision(), 5);
+
+    assert_eq!(
+        batch_revisions(&hub.meta_events_after(0, None, 10)),
+        (vec![1, 2, 3, 4], 5)
+    );
+    assert_eq!(
+        batch_revisions(&hub.meta_events_after(0, Some("app/"), 2)),
+        (vec![1, 3], 3)
+    );
+    assert_eq!(
+        batch_revisions(&hub.meta_events_after(3, Some("app/"), 2)),
+        (vec![4], 5)
+    );
+    // Filtered-out changes still advance the cursor.
+    assert_eq!(
+        batch_revisions(&hub.meta_events_after(0, Some("none/"), 10)),
+        (vec![], 5)
+    );
+    // A cursor ahead of this replica waits instead of moving backwards.
+    assert_eq!(
+        batch_revisions(&hub.meta_events_after(9, None, 10)),
+        (vec![], 9)
+    );
+}
+
+#[test]
+fn test_watch_hub_compaction_and_reset() {
+    let hub = KLogWatchHub::new(2);
+    for revision in 1..=4 {
+        hub.record_meta_event(meta_event(revision, "k"));
+    }
+    hub.advance(4);
+    assert_eq!(hub.compacted_revision(), 2);
+
+    assert_eq!(
+        hub.meta_events_after(1, None, 10),
+        KLogMetaWatchBatch::Compacted {
+            compacted_revision: 2,
+            revision: 4
+        }
+    );
+    assert_eq!(
+        batch_revisions(&hub.meta_events_after(2, None, 10)),
+        (vec![3, 4], 4)
+    );
+
+    hub.reset(10);
+    assert_eq!(hub.current_revision(), 10);
+    assert!(matches!(
+        hub.meta_events_after(4, None, 10),
+        KLogMetaWatchBatch::Compacted {
+            compacted_revision: 10,
+            revision: 10
+        }
+    ));
+    assert_eq!(
+        batch_revisions(&hub.meta_events_after(10, None, 10)),
+        (vec![], 10)
+    );
+
+    // Events from before the reset point are ignored.
+    hub.record_meta_event(meta_event(9, "k"));
+    hub.record_meta_event(meta_event(11, "k"));
+    hub.advance(11);
+    assert_eq!(
+        batch_revisions(&hub.meta_events_after(10, None, 10)),
+        (vec![11], 11)
+    );
+}
+
+#[tokio::test]
+async fn test_state_machine_apply_publishes_meta_watch_events() -> anyhow::Result<()> {
+    let (manager, mut sm) = memory_state_machine().await?;
+    let hub = manager.watch_hub();
+    let mut revision_rx = hub.subscribe();
+    assert_eq!(hub.current_revision(), 0);
+
+    sm.apply(vec![
+        meta_entry(1, put_meta("app/a", "1")),
+        meta_entry(2, put_meta("app/a", "2")),
+        meta_entry(
+            3,
+            KLogRequest::DeleteMeta {
+                key: "missing".to_string(),
+            },
+        ),
+        meta_entry(
+            4,
+            KLogRequest::DeleteMeta {
+                key: "app/a".to_string(),
+            },
+        ),
+    ])
+    .await?;
+
+    tokio::time::timeout(Duration::from_secs(1), revision_rx.changed()).await??;
+    assert_eq!(*revision_rx.borrow(), 4);
+
+    let KLogMetaWatchBatch::Events { events, revision } = hub.meta_events_after(0, None, 10) else {
+        panic!("history must not be compacted");
+    };
+    assert_eq!(revision, 4);
+    assert_eq!(
+        events
+            .iter()
+            .map(|e| (e.revision, e.kind, e.meta.revision, e.meta.value.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            (1, KLogMetaWatchEventKind::Put, 1, "1"),
+            (2, KLogMetaWatchEventKind::Put, 2, "2"),
+            (4, KLogMetaWatchEventKind::Delete, 2, "2"),
+        ]
+    );
+
+    // A restarted state machine can not replay earlier changes.
+    let data_dir = unique_test_path("watch_snapshot_restart");
+    std::fs::create_dir_all(&data_dir)?;
+    let _restarted =
+        KLogStateMachine::new(manager.clone(), Arc::new(SnapshotManager::new(data_dir))).await?;
+    assert_eq!(hub.current_revision(), 4);
+    assert!(matches!(
+        hub.meta_events_after(0, None, 10),
+        KLogMetaWatchBatch::Compacted { revision: 4, .. }
+    ));
+
+    Ok(())
+}
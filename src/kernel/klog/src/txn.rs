@@ -0,0 +1,145 @@
+use crate::state_store::KLogStateStoreManagerRef;
+use crate::{KLogMetaEntry, KResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Condition checked against the current meta state before a txn picks its branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KLogTxnCompare {
+    /// The key's current revision equals `revision`; `0` matches an absent key.
+    RevisionEquals {
+        key: String,
+        revision: u64,
+    },
+    Exists {
+        key: String,
+    },
+    Absent {
+        key: String,
+    },
+}
+
+impl KLogTxnCompare {
+    pub fn key(&self) -> &str {
+        match self {
+            Self::RevisionEquals { key, .. } | Self::Exists { key } | Self::Absent { key } => {
+                key.as_str()
+            }
+        }
+    }
+
+    pub fn matches(&self, current: Option<&KLogMetaEntry>) -> bool {
+        match self {
+            Self::RevisionEquals { revision, .. } => {
+                current.map(|v| v.revision).unwrap_or(0) == *revision
+            }
+            Self::Exists { .. } => current.is_some(),
+            Self::Absent { .. } => current.is_none(),
+        }
+    }
+}
+
+/// One operation of a txn branch as replicated through raft.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KLogTxnOp {
+    Put { item: KLogMetaEntry },
+    Delete { key: String },
+    Get { key: String },
+}
+
+impl KLogTxnOp {
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Put { item } => item.key.as_str(),
+            Self::Delete { key } | Self::Get { key } => key.as_str(),
+        }
+    }
+
+    pub fn is_write(&self) -> bool {
+        !matches!(self, Self::Get { .. })
+    }
+}
+
+/// Result of one executed txn operation, in the same order as the branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KLogTxnOpResult {
+    Put {
+        key: String,
+        revision: u64,
+    },
+    Delete {
+        key: String,
+        existed: bool,
+        prev_meta: Option<KLogMetaEntry>,
+    },
+    Get {
+        key: String,
+        item: Option<KLogMetaEntry>,
+    },
+}
+
+/// Evaluate every compare against the current state; an empty list always succeeds.
+pub async fn evaluate_txn_compares(
+    state_store_manager: &KLogStateStoreManagerRef,
+    compares: &[KLogTxnCompare],
+) -> KResult<bool> {
+    for compare in compares {
+        let current = state_store_manager.get_meta_entry(compare.key()).await?;
+        if !compare.matches(current.as_ref()) {
+            debug!(
+                "txn compare failed: compare={:?}, current_revision={:?}",
+                compare,
+                current.as_ref().map(|v| v.revision)
+            );
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Meta writes of one txn branch, staged so the branch commits in a single store write.
+/// Reads through the plan see the branch's own earlier writes.
+pub struct KLogTxnWritePlan<'a> {
+    state_store_manager: &'a KLogStateStoreManagerRef,
+    writes: BTreeMap<String, Option<KLogMetaEntry>>,
+}
+
+impl<'a> KLogTxnWritePlan<'a> {
+    pub fn new(state_store_manager: &'a KLogStateStoreManagerRef) -> Self {
+        Self {
+            state_store_manager,
+            writes: BTreeMap::new(),
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> KResult<Option<KLogMetaEntry>> {
+        if let Some(item) = self.writes.get(key) {
+            return Ok(item.clone());
+        }
+        self.state_store_manager.get_meta_entry(key).await
+    }
+
+    /// Stage a put, assigning the next revision of the key like `KLogStateStore::put_meta`.
+    pub async fn put(&mut self, mut item: KLogMetaEntry) -> KResult<KLogMetaEntry> {
+        let prev = self.get(&item.key).await?;
+        item.revision = prev.map(|v| v.revision.saturating_add(1)).unwrap_or(1);
+        self.writes.insert(item.key.clone(), Some(item.clone()));
+        Ok(item)
+    }
+
+    /// Stage a delete, returning the entry it removes.
+    pub async fn delete(&mut self, key: &str) -> KResult<Option<KLogMetaEntry>> {
+        let prev = self.get(key).await?;
+        if prev.is_some() {
+            self.writes.insert(key.to_string(), None);
+        }
+        Ok(prev)
+    }
+
+    pub fn into_writes(self) -> Vec<(String, Option<KLogMetaEntry>)> {
+        self.writes.into_iter().collect()
+    }
+}
@@ -74,6 +74,7 @@ struct WorkloadMix {
     query_strong_read: bool,
     meta_query_strong_read: bool,
     meta_key_space: u64,
+    append_batch_size: usize,
 }
 
 impl Default for WorkloadMix {
@@ -87,6 +88,7 @@ impl Default for WorkloadMix {
             query_strong_read: false,
             meta_query_strong_read: false,
             meta_key_space: 1024,
+            append_batch_size: 1,
         }
     }
 }
@@ -134,6 +136,9 @@ impl WorkloadMix {
         if self.meta_key_space == 0 {
             return Err("invalid workload: meta_key_space must be > 0".to_string());
         }
+        if self.append_batch_size == 0 {
+            return Err("invalid workload: append_batch_size must be > 0".to_string());
+        }
         Ok(())
     }
 }
@@ -149,6 +154,7 @@ struct WorkloadMixPatch {
     query_strong_read: Option<bool>,
     meta_query_strong_read: Option<bool>,
     meta_key_space: Option<u64>,
+    append_batch_size: Option<usize>,
 }
 
 impl WorkloadMix {
@@ -177,6 +183,9 @@ impl WorkloadMix {
         if let Some(v) = patch.meta_key_space {
             self.meta_key_space = v;
         }
+        if let Some(v) = patch.append_batch_size {
+            self.append_batch_size = v;
+        }
     }
 }
 
@@ -559,6 +568,7 @@ Options:
   --query-strong-read <BOOL> Query strong_read mode (default: false)
   --meta-query-strong-read <BOOL>  Meta query strong_read mode (default: false)
   --meta-key-space <N>       Number of meta keys for random access (default: 1024)
+  --append-batch-size <N>    Entries per append request, >1 uses batch append (default: 1)
   --fault-kill-leader-at-sec <N>  Inject fault by killing current leader at N seconds
   --fault-wait-new-leader-timeout-sec <N>  Timeout waiting new leader after fault (default: 20)
   --request-node-id <ID>     request node id for generated request_id (default: 9001)
@@ -589,7 +599,7 @@ async fn run_main() -> Result<(), String> {
         .unwrap_or_else(|| format!("klog_bench_{}_{}", std::process::id(), now_unix_ms()));
 
     println!(
-        "klog_bench starting: cluster_name={}, nodes={}, concurrency={}, duration_sec={}, warmup_sec={}, payload_bytes={}, write_target={}, daemon_bin={}, workload(append={}, query={}, meta_put={}, meta_query={}, query_limit={}, query_strong_read={}, meta_query_strong_read={}, meta_key_space={}), fault(enabled={}, kill_leader_at_sec={:?}, wait_new_leader_timeout_sec={})",
+        "klog_bench starting: cluster_name={}, nodes={}, concurrency={}, duration_sec={}, warmup_sec={}, payload_bytes={}, write_target={}, daemon_bin={}, workload(append={}, query={}, meta_put={}, meta_query={}, query_limit={}, query_strong_read={}, meta_query_strong_read={}, meta_key_space={}, append_batch_size={}), fault(enabled={}, kill_leader_at_sec={:?}, wait_new_leader_timeout_sec={})",
         cluster_name,
         cfg.nodes,
         cfg.concurrency,
@@ -606,6 +616,7 @@ async fn run_main() -> Result<(), String> {
         cfg.workload.query_strong_read,
         cfg.workload.meta_query_strong_read,
         cfg.workload.meta_key_space,
+        cfg.workload.append_batch_size,
         cfg.fault.enabled,
         cfg.fault.kill_leader_at_sec,
         cfg.fault.wait_new_leader_timeout_sec
@@ -929,6 +940,7 @@ async fn run_workload_phase(
                 attr_key: None,
                 attr_value: None,
                 strong_read: Some(workload.query_strong_read),
+                ..Default::default()
             };
 
             while Instant::now() < deadline {
@@ -948,7 +960,21 @@ async fn run_workload_phase(
                 let operation = workload.choose_operation();
                 req_total.fetch_add(1, Ordering::Relaxed);
                 let begin = Instant::now();
-                let result: Result<Option<u64>, _> = match operation {
+                let result: Result<Vec<u64>, _> = match operation {
+                    BenchOperation::Append if workload.append_batch_size > 1 => {
+                        let entries = (0..workload.append_batch_size)
+                            .map(|_| {
+                                append_seq = append_seq.wrapping_add(1);
+                                let mut req = base_append_req.clone();
+                                req.request_id = Some(format!(
+                                    "bench-{}-{}-{}",
+                                    request_node_id, worker_id, append_seq
+                                ));
+                                req
+                            })
+                            .collect();
+                        client.append_log_batch(entries).await.map(|resp| resp.ids)
+                    }
                     BenchOperation::Append => {
                         append_seq = append_seq.wrapping_add(1);
                         let mut req = base_append_req.clone();
@@ -956,19 +982,21 @@ async fn run_workload_phase(
                             "bench-{}-{}-{}",
                             request_node_id, worker_id, append_seq
                         ));
-                        client.append_log(req).await.map(|resp| Some(resp.id))
-                    }
-                    BenchOperation::Query => {
-                        client.query_log(base_query_req.clone()).await.map(|_| None)
+                        client.append_log(req).await.map(|resp| vec![resp.id])
                     }
+                    BenchOperation::Query => client
+                        .query_log(base_query_req.clone())
+                        .await
+                        .map(|_| Vec::new()),
                     BenchOperation::MetaPut => {
                         let key_idx = rand::random::<u64>() % workload.meta_key_space;
                         let req = KLogMetaPutRequest {
                             key: format!("bench/meta/{}", key_idx),
                             value: payload.clone(),
                             expected_revision: None,
+                            lease_id: None,
                         };
-                        client.put_meta(req).await.map(|_| None)
+                        client.put_meta(req).await.map(|_| Vec::new())
                     }
                     BenchOperation::MetaQuery => {
                         let key_idx = rand::random::<u64>() % workload.meta_key_space;
@@ -978,15 +1006,15 @@ async fn run_workload_phase(
                             limit: Some(1),
                             strong_read: Some(workload.meta_query_strong_read),
                         };
-                        client.query_meta(req).await.map(|_| None)
+                        client.query_meta(req).await.map(|_| Vec::new())
                     }
                 };
 
                 match result {
-                    Ok(maybe_append_id) => {
+                    Ok(append_ids) => {
                         req_success.fetch_add(1, Ordering::Relaxed);
                         stats.record_success(operation, begin.elapsed().as_micros() as u64);
-                        if let Some(id) = maybe_append_id {
+                        for id in append_ids {
                             stats.record_append_id(id);
                         }
                         if let Some(shared) = fault_shared.as_ref() {
@@ -1224,6 +1252,7 @@ async fn collect_node_max_log_ids(
             attr_key: None,
             attr_value: None,
             strong_read: Some(true),
+            ..Default::default()
         };
         if let Ok(resp) = client.query_log(req).await {
             let max_id = resp.items.first().map(|e| e.id).unwrap_or(0);
@@ -1399,7 +1428,7 @@ fn print_report(report: &BenchReport) {
         report.duration_sec, report.warmup_sec, report.concurrency, report.payload_bytes
     );
     println!(
-        "workload: append={}, query={}, meta_put={}, meta_query={}, query_limit={}, query_strong_read={}, meta_query_strong_read={}, meta_key_space={}",
+        "workload: append={}, query={}, meta_put={}, meta_query={}, query_limit={}, query_strong_read={}, meta_query_strong_read={}, meta_key_space={}, append_batch_size={}",
         report.workload.append_weight,
         report.workload.query_weight,
         report.workload.meta_put_weight,
@@ -1407,7 +1436,8 @@ fn print_report(report: &BenchReport) {
         report.workload.query_limit,
         report.workload.query_strong_read,
         report.workload.meta_query_strong_read,
-        report.workload.meta_key_space
+        report.workload.meta_key_space,
+        report.workload.append_batch_size
     );
     println!(
         "requests: total={}, success={}, fail={}, success_rate={:.2}%",
@@ -1565,6 +1595,11 @@ fn parse_args(args: Vec<String>) -> Result<BenchConfig, String> {
                 cfg.workload.meta_key_space = parse_next::<u64>(&args, i, "--meta-key-space")?;
                 i += 2;
             }
+            "--append-batch-size" => {
+                cfg.workload.append_batch_size =
+                    parse_next::<usize>(&args, i, "--append-batch-size")?;
+                i += 2;
+            }
             "--fault-kill-leader-at-sec" => {
                 cfg.fault.kill_leader_at_sec =
                     Some(parse_next::<u64>(&args, i, "--fault-kill-leader-at-sec")?);
@@ -581,6 +581,7 @@ mod tests {
             raft: KLogRaftConfig::default(),
             admin_local_only: true,
             rpc: Default::default(),
+            retention: Default::default(),
         }
     }
 
@@ -11,12 +11,12 @@ use crate::constants::{
     DEFAULT_RAFT_MAX_IN_SNAPSHOT_LOG_TO_KEEP, DEFAULT_RAFT_MAX_PAYLOAD_ENTRIES, DEFAULT_RAFT_PORT,
     DEFAULT_RAFT_PURGE_BATCH_SIZE, DEFAULT_RAFT_REPLICATION_LAG_THRESHOLD,
     DEFAULT_RAFT_SNAPSHOT_MAX_CHUNK_SIZE_BYTES, DEFAULT_RAFT_SNAPSHOT_POLICY,
-    DEFAULT_RPC_BODY_LIMIT_BYTES, DEFAULT_RPC_CONCURRENCY_LIMIT, DEFAULT_RPC_LISTEN_HOST,
-    DEFAULT_RPC_PORT, DEFAULT_RPC_TIMEOUT_MS, DEFAULT_STATE_STORE_SYNC_WRITE,
-    ENV_ADMIN_ADVERTISE_PORT, ENV_ADMIN_LISTEN_ADDR, ENV_ADMIN_LOCAL_ONLY, ENV_ADVERTISE_ADDR,
-    ENV_ADVERTISE_INTER_PORT, ENV_ADVERTISE_PORT, ENV_AUTO_BOOTSTRAP, ENV_CLUSTER_ID,
-    ENV_CLUSTER_NAME, ENV_CONFIG_FILE, ENV_DATA_DIR, ENV_ENABLE_RPC_SERVER,
-    ENV_INTER_NODE_LISTEN_ADDR, ENV_JOIN_BLOCKING,
+    DEFAULT_RETENTION_CHECK_INTERVAL_MS, DEFAULT_RETENTION_ENABLED, DEFAULT_RPC_BODY_LIMIT_BYTES,
+    DEFAULT_RPC_CONCURRENCY_LIMIT, DEFAULT_RPC_LISTEN_HOST, DEFAULT_RPC_PORT,
+    DEFAULT_RPC_TIMEOUT_MS, DEFAULT_STATE_STORE_SYNC_WRITE, ENV_ADMIN_ADVERTISE_PORT,
+    ENV_ADMIN_LISTEN_ADDR, ENV_ADMIN_LOCAL_ONLY, ENV_ADVERTISE_ADDR, ENV_ADVERTISE_INTER_PORT,
+    ENV_ADVERTISE_PORT, ENV_AUTO_BOOTSTRAP, ENV_CLUSTER_ID, ENV_CLUSTER_NAME, ENV_CONFIG_FILE,
+    ENV_DATA_DIR, ENV_ENABLE_RPC_SERVER, ENV_INTER_NODE_LISTEN_ADDR, ENV_JOIN_BLOCKING,
     ENV_JOIN_RETRY_CONFIG_CHANGE_CONFLICT_EXTRA_BACKOFF_MS, ENV_JOIN_RETRY_INITIAL_INTERVAL_MS,
     ENV_JOIN_RETRY_JITTER_RATIO, ENV_JOIN_RETRY_MAX_ATTEMPTS, ENV_JOIN_RETRY_MAX_INTERVAL_MS,
     ENV_JOIN_RETRY_MULTIPLIER, ENV_JOIN_RETRY_REQUEST_TIMEOUT_MS, ENV_JOIN_RETRY_SHUFFLE_TARGETS,
@@ -25,11 +25,12 @@ use crate::constants::{
     ENV_RAFT_HEARTBEAT_INTERVAL_MS, ENV_RAFT_INSTALL_SNAPSHOT_TIMEOUT_MS,
     ENV_RAFT_MAX_IN_SNAPSHOT_LOG_TO_KEEP, ENV_RAFT_MAX_PAYLOAD_ENTRIES, ENV_RAFT_PURGE_BATCH_SIZE,
     ENV_RAFT_REPLICATION_LAG_THRESHOLD, ENV_RAFT_SNAPSHOT_MAX_CHUNK_SIZE_BYTES,
-    ENV_RAFT_SNAPSHOT_POLICY, ENV_RPC_ADVERTISE_PORT, ENV_RPC_APPEND_BODY_LIMIT_BYTES,
-    ENV_RPC_APPEND_CONCURRENCY, ENV_RPC_APPEND_TIMEOUT_MS, ENV_RPC_JSONRPC_BODY_LIMIT_BYTES,
-    ENV_RPC_JSONRPC_CONCURRENCY, ENV_RPC_JSONRPC_TIMEOUT_MS, ENV_RPC_LISTEN_ADDR,
-    ENV_RPC_QUERY_BODY_LIMIT_BYTES, ENV_RPC_QUERY_CONCURRENCY, ENV_RPC_QUERY_TIMEOUT_MS,
-    ENV_STATE_STORE_SYNC_WRITE, KLOG_SERVICE_NAME,
+    ENV_RAFT_SNAPSHOT_POLICY, ENV_RETENTION_CHECK_INTERVAL_MS, ENV_RETENTION_ENABLED,
+    ENV_RPC_ADVERTISE_PORT, ENV_RPC_APPEND_BODY_LIMIT_BYTES, ENV_RPC_APPEND_CONCURRENCY,
+    ENV_RPC_APPEND_TIMEOUT_MS, ENV_RPC_JSONRPC_BODY_LIMIT_BYTES, ENV_RPC_JSONRPC_CONCURRENCY,
+    ENV_RPC_JSONRPC_TIMEOUT_MS, ENV_RPC_LISTEN_ADDR, ENV_RPC_QUERY_BODY_LIMIT_BYTES,
+    ENV_RPC_QUERY_CONCURRENCY, ENV_RPC_QUERY_TIMEOUT_MS, ENV_STATE_STORE_SYNC_WRITE,
+    KLOG_SERVICE_NAME,
 };
 use buckyos_kit::get_buckyos_service_data_dir;
 use klog::KNodeId;
@@ -245,6 +246,24 @@ impl KLogRaftConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KLogRetentionConfig {
+    /// Whether the background retention worker runs on this node.
+    pub enabled: bool,
+
+    /// Interval between retention passes in milliseconds.
+    pub check_interval_ms: u64,
+}
+
+impl Default for KLogRetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: DEFAULT_RETENTION_ENABLED,
+            check_interval_ms: DEFAULT_RETENTION_CHECK_INTERVAL_MS,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct KLogJoinRetryConfig {
     /// Retry strategy: fixed interval or exponential backoff.
@@ -363,6 +382,9 @@ pub struct KLogRuntimeConfig {
 
     /// Route-level RPC policies for append/query/jsonrpc.
     pub rpc: KLogRpcConfig,
+
+    /// Background retention worker settings.
+    pub retention: KLogRetentionConfig,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -536,6 +558,16 @@ pub struct KLogRpcConfigPatch {
     pub jsonrpc: Option<KLogRpcRouteConfigPatch>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KLogRetentionConfigPatch {
+    /// Optional switch for the retention worker.
+    pub enabled: Option<bool>,
+
+    /// Optional retention check interval in milliseconds.
+    pub check_interval_ms: Option<u64>,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct KLogRuntimeConfigPatch {
@@ -560,6 +592,9 @@ pub struct KLogRuntimeConfigPatch {
     /// Optional grouped rpc policy section.
     pub rpc: Option<KLogRpcConfigPatch>,
 
+    /// Optional grouped retention worker section.
+    pub retention: Option<KLogRetentionConfigPatch>,
+
     /// Required node id; can also come from env.
     pub node_id: Option<KNodeId>,
 }
@@ -668,6 +703,10 @@ impl KLogRuntimeConfig {
                     concurrency: parse_env_usize(ENV_RPC_JSONRPC_CONCURRENCY)?,
                 }),
             }),
+            retention: Some(KLogRetentionConfigPatch {
+                enabled: parse_env_bool(ENV_RETENTION_ENABLED)?,
+                check_interval_ms: parse_env_u64(ENV_RETENTION_CHECK_INTERVAL_MS)?,
+            }),
             ..Default::default()
         };
 
@@ -717,6 +756,7 @@ impl KLogRuntimeConfig {
             raft,
             admin,
             rpc,
+            retention,
             node_id,
         } = patch;
 
@@ -727,6 +767,7 @@ impl KLogRuntimeConfig {
         let raft = raft.unwrap_or_default();
         let admin = admin.unwrap_or_default();
         let rpc = rpc.unwrap_or_default();
+        let retention = retention.unwrap_or_default();
 
         let node_id = match node_id {
             Some(v) => v,
@@ -777,6 +818,7 @@ impl KLogRuntimeConfig {
         let rpc_cfg = merge_rpc_config(rpc)?;
         let join_retry_cfg = merge_join_retry_config(join.retry.unwrap_or_default())?;
         let raft_cfg = merge_raft_config(raft)?;
+        let retention_cfg = merge_retention_config(retention)?;
         let listen_addr = network.listen_addr.unwrap_or_else(default_listen_addr);
         let inter_node_listen_addr = network
             .inter_node_listen_addr
@@ -857,6 +899,7 @@ impl KLogRuntimeConfig {
             raft: raft_cfg,
             admin_local_only: admin.local_only.unwrap_or(DEFAULT_ADMIN_LOCAL_ONLY),
             rpc: rpc_cfg,
+            retention: retention_cfg,
         })
     }
 }
@@ -1105,6 +1148,23 @@ fn merge_raft_config(patch: KLogRaftConfigPatch) -> Result<KLogRaftConfig, Strin
     Ok(cfg)
 }
 
+fn merge_retention_config(patch: KLogRetentionConfigPatch) -> Result<KLogRetentionConfig, String> {
+    let cfg = KLogRetentionConfig {
+        enabled: patch.enabled.unwrap_or(DEFAULT_RETENTION_ENABLED),
+        check_interval_ms: patch
+            .check_interval_ms
+            .unwrap_or(DEFAULT_RETENTION_CHECK_INTERVAL_MS),
+    };
+
+    if cfg.check_interval_ms == 0 {
+        let msg = "Invalid retention.check_interval_ms=0: must be greater than 0".to_string();
+        error!("{}", msg);
+        return Err(msg);
+    }
+
+    Ok(cfg)
+}
+
 fn parse_snapshot_policy(input: &str) -> Result<SnapshotPolicy, String> {
     let v = input.trim();
     if v.eq_ignore_ascii_case("never") {
@@ -1360,6 +1420,10 @@ concurrency = 96
 timeout_ms = 3300
 body_limit_bytes = 1048576
 concurrency = 128
+
+[retention]
+enabled = false
+check_interval_ms = 15000
 "#;
         std::fs::write(&file, content).expect("write file");
 
@@ -1415,6 +1479,8 @@ concurrency = 128
         assert_eq!(cfg.rpc.jsonrpc.timeout_ms, 3300);
         assert_eq!(cfg.rpc.jsonrpc.body_limit_bytes, 1048576);
         assert_eq!(cfg.rpc.jsonrpc.concurrency, 128);
+        assert!(!cfg.retention.enabled);
+        assert_eq!(cfg.retention.check_interval_ms, 15000);
 
         let _ = std::fs::remove_file(&file);
     }
@@ -1556,6 +1622,11 @@ id = "cluster_partial_id"
             DEFAULT_RPC_BODY_LIMIT_BYTES
         );
         assert_eq!(cfg.rpc.jsonrpc.concurrency, DEFAULT_RPC_CONCURRENCY_LIMIT);
+        assert_eq!(cfg.retention.enabled, DEFAULT_RETENTION_ENABLED);
+        assert_eq!(
+            cfg.retention.check_interval_ms,
+            DEFAULT_RETENTION_CHECK_INTERVAL_MS
+        );
 
         let _ = std::fs::remove_file(&file);
     }
@@ -1710,6 +1781,10 @@ id = "cluster_admin_conflict_id"
             admin: Some(KLogAdminConfigPatch {
                 local_only: Some(false),
             }),
+            retention: Some(KLogRetentionConfigPatch {
+                enabled: Some(true),
+                check_interval_ms: Some(5000),
+            }),
             node_id: Some(3),
             ..Default::default()
         };
@@ -1759,6 +1834,8 @@ id = "cluster_admin_conflict_id"
         assert_eq!(cfg.rpc.append.timeout_ms, DEFAULT_RPC_TIMEOUT_MS);
         assert_eq!(cfg.rpc.query.timeout_ms, DEFAULT_RPC_TIMEOUT_MS);
         assert_eq!(cfg.rpc.jsonrpc.timeout_ms, DEFAULT_RPC_TIMEOUT_MS);
+        assert!(cfg.retention.enabled);
+        assert_eq!(cfg.retention.check_interval_ms, 5000);
     }
 
     #[test]
@@ -1782,4 +1859,26 @@ concurrency = 0
 
         let _ = std::fs::remove_file(&file);
     }
+
+    #[test]
+    fn test_from_file_retention_invalid_zero_rejected() {
+        let file = unique_test_file("retention_invalid_zero");
+        let content = r#"
+node_id = 7
+
+[cluster]
+name = "cluster_retention_invalid"
+id = "cluster_retention_invalid_id"
+
+[retention]
+check_interval_ms = 0
+"#;
+        std::fs::write(&file, content).expect("write file");
+
+        let err = KLogRuntimeConfig::from_file(&file)
+            .expect_err("retention.check_interval_ms=0 must fail");
+        assert!(err.contains("retention.check_interval_ms=0"));
+
+        let _ = std::fs::remove_file(&file);
+    }
 }
@@ -146,6 +146,12 @@ pub const ENV_RAFT_MAX_IN_SNAPSHOT_LOG_TO_KEEP: &str = "KLOG_RAFT_MAX_IN_SNAPSHO
 /// Environment variable key: raft purge batch size.
 pub const ENV_RAFT_PURGE_BATCH_SIZE: &str = "KLOG_RAFT_PURGE_BATCH_SIZE";
 
+/// Environment variable key: whether the retention worker is enabled.
+pub const ENV_RETENTION_ENABLED: &str = "KLOG_RETENTION_ENABLED";
+
+/// Environment variable key: retention worker check interval in milliseconds.
+pub const ENV_RETENTION_CHECK_INTERVAL_MS: &str = "KLOG_RETENTION_CHECK_INTERVAL_MS";
+
 /// Default host for raft protocol listener.
 pub const DEFAULT_LISTEN_HOST: &str = "0.0.0.0";
 
@@ -248,5 +254,11 @@ pub const DEFAULT_RAFT_MAX_IN_SNAPSHOT_LOG_TO_KEEP: u64 = 1_000;
 /// Default raft purge batch size.
 pub const DEFAULT_RAFT_PURGE_BATCH_SIZE: u64 = 1;
 
+/// Default switch: run retention worker (no-op until a policy is stored).
+pub const DEFAULT_RETENTION_ENABLED: bool = true;
+
+/// Default retention worker check interval in milliseconds.
+pub const DEFAULT_RETENTION_CHECK_INTERVAL_MS: u64 = 60_000;
+
 /// Service name used to derive default data dir.
 pub const KLOG_SERVICE_NAME: &str = "klog";
@@ -8,6 +8,7 @@ pub async fn run_server_lifecycle(
     network_server: KNetworkServer,
     rpc_server: Option<KRpcServer>,
     auto_join_task: Option<JoinHandle<()>>,
+    background_tasks: Vec<(&'static str, JoinHandle<()>)>,
 ) -> Result<(), String> {
     let (raft_shutdown_tx_raw, raft_shutdown_rx) = oneshot::channel::<()>();
     let mut raft_shutdown_tx = Some(raft_shutdown_tx_raw);
@@ -91,6 +92,7 @@ pub async fn run_server_lifecycle(
     };
 
     stop_auto_join_task(auto_join_task).await;
+    stop_background_tasks(background_tasks).await;
     server_result
 }
 
@@ -102,6 +104,14 @@ pub async fn stop_auto_join_task(join_task: Option<JoinHandle<()>>) {
     }
 }
 
+pub async fn stop_background_tasks(tasks: Vec<(&'static str, JoinHandle<()>)>) {
+    for (name, handle) in tasks {
+        handle.abort();
+        let _ = handle.await;
+        info!("{} task stopped because network server exited", name);
+    }
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         if let Err(e) = tokio::signal::ctrl_c().await {
@@ -6,8 +6,10 @@ mod logging;
 
 use cluster::{initialize_cluster_if_needed, spawn_auto_join_task};
 use config::KLogRuntimeConfig;
+use klog::lease::{KLogLeaseWorker, LEASE_DEFAULT_CHECK_INTERVAL_MS};
 use klog::logs::RocksDbLogStorage;
 use klog::network::{KNetworkFactory, KNetworkServer};
+use klog::retention::KLogRetentionWorker;
 use klog::rpc::KRpcServer;
 use klog::state_machine::{KLogStateMachine, SnapshotManager};
 use klog::state_store::{
@@ -47,7 +49,7 @@ async fn run(cfg: KLogRuntimeConfig) -> Result<(), String> {
     })?;
 
     info!(
-        "klog startup config: node_id={}, raft_listen_addr={}, inter_node_listen_addr={}, admin_listen_addr={}, rpc_enabled={}, rpc_listen_addr={}, advertise_addr={}, advertise_port={}, advertise_inter_port={}, advertise_admin_port={}, rpc_advertise_port={}, data_dir={}, cluster_name={}, cluster_id={}, auto_bootstrap={}, state_store_sync_write={}, join_targets={:?}, join_blocking={}, join_target_role={}, join_retry(strategy={}, initial_interval_ms={}, max_interval_ms={}, multiplier={}, jitter_ratio={}, max_attempts={}, request_timeout_ms={}, shuffle_targets_each_round={}, config_change_conflict_extra_backoff_ms={}), raft(election_timeout_min_ms={}, election_timeout_max_ms={}, heartbeat_interval_ms={}, install_snapshot_timeout_ms={}, max_payload_entries={}, replication_lag_threshold={}, snapshot_policy={}, snapshot_max_chunk_size_bytes={}, max_in_snapshot_log_to_keep={}, purge_batch_size={}), admin_local_only={}, rpc_append(timeout_ms={}, body_limit_bytes={}, concurrency={}), rpc_query(timeout_ms={}, body_limit_bytes={}, concurrency={}), rpc_jsonrpc(timeout_ms={}, body_limit_bytes={}, concurrency={})",
+        "klog startup config: node_id={}, raft_listen_addr={}, inter_node_listen_addr={}, admin_listen_addr={}, rpc_enabled={}, rpc_listen_addr={}, advertise_addr={}, advertise_port={}, advertise_inter_port={}, advertise_admin_port={}, rpc_advertise_port={}, data_dir={}, cluster_name={}, cluster_id={}, auto_bootstrap={}, state_store_sync_write={}, join_targets={:?}, join_blocking={}, join_target_role={}, join_retry(strategy={}, initial_interval_ms={}, max_interval_ms={}, multiplier={}, jitter_ratio={}, max_attempts={}, request_timeout_ms={}, shuffle_targets_each_round={}, config_change_conflict_extra_backoff_ms={}), raft(election_timeout_min_ms={}, election_timeout_max_ms={}, heartbeat_interval_ms={}, install_snapshot_timeout_ms={}, max_payload_entries={}, replication_lag_threshold={}, snapshot_policy={}, snapshot_max_chunk_size_bytes={}, max_in_snapshot_log_to_keep={}, purge_batch_size={}), admin_local_only={}, rpc_append(timeout_ms={}, body_limit_bytes={}, concurrency={}), rpc_query(timeout_ms={}, body_limit_bytes={}, concurrency={}), rpc_jsonrpc(timeout_ms={}, body_limit_bytes={}, concurrency={}), retention(enabled={}, check_interval_ms={})",
         cfg.node_id,
         cfg.listen_addr,
         cfg.inter_node_listen_addr,
@@ -95,7 +97,9 @@ async fn run(cfg: KLogRuntimeConfig) -> Result<(), String> {
         cfg.rpc.query.concurrency,
         cfg.rpc.jsonrpc.timeout_ms,
         cfg.rpc.jsonrpc.body_limit_bytes,
-        cfg.rpc.jsonrpc.concurrency
+        cfg.rpc.jsonrpc.concurrency,
+        cfg.retention.enabled,
+        cfg.retention.check_interval_ms
     );
     if cfg.admin_local_only {
         warn!(
@@ -145,9 +149,10 @@ async fn run(cfg: KLogRuntimeConfig) -> Result<(), String> {
     );
 
     let snapshot_manager = Arc::new(SnapshotManager::new(cfg.data_dir.clone()));
-    let state_machine = KLogStateMachine::new(state_store_manager.clone(), snapshot_manager)
-        .await
-        .map_err(|e| format!("Failed to initialize state machine: {}", e))?;
+    let state_machine =
+        KLogStateMachine::new(state_store_manager.clone(), snapshot_manager.clone())
+            .await
+            .map_err(|e| format!("Failed to initialize state machine: {}", e))?;
 
     let raft_config = cfg
         .raft
@@ -175,11 +180,40 @@ async fn run(cfg: KLogRuntimeConfig) -> Result<(), String> {
 
     initialize_cluster_if_needed(&cfg, &raft).await;
     let join_task = spawn_auto_join_task(&cfg);
+    let mut background_tasks = Vec::new();
+    if cfg.retention.enabled {
+        info!(
+            "Starting retention worker: check_interval_ms={}",
+            cfg.retention.check_interval_ms
+        );
+        background_tasks.push((
+            "Retention",
+            KLogRetentionWorker::new(
+                raft.clone(),
+                state_store_manager.clone(),
+                cfg.retention.check_interval_ms,
+            )
+            .spawn(),
+        ));
+    } else {
+        warn!("Retention worker is disabled by config");
+    }
+    // Leases only expire through this worker, so it always runs; it is idle on followers.
+    background_tasks.push((
+        "Lease",
+        KLogLeaseWorker::new(
+            raft.clone(),
+            state_store_manager.clone(),
+            LEASE_DEFAULT_CHECK_INTERVAL_MS,
+        )
+        .spawn(),
+    ));
 
     let network_server = KNetworkServer::new(cfg.listen_addr.clone(), raft.clone())
         .with_inter_node_addr(cfg.inter_node_listen_addr.clone())
         .with_admin_addr(cfg.admin_listen_addr.clone())
         .with_state_store_manager(state_store_manager.clone())
+        .with_snapshot_manager(snapshot_manager.clone())
         .with_admin_local_only(cfg.admin_local_only)
         .with_cluster_identity(cfg.cluster_name.clone(), cfg.cluster_id.clone());
     info!(
@@ -201,5 +235,5 @@ async fn run(cfg: KLogRuntimeConfig) -> Result<(), String> {
         None
     };
 
-    run_server_lifecycle(network_server, rpc_server, join_task).await
+    run_server_lifecycle(network_server, rpc_server, join_task, background_tasks).await
 }
@@ -0,0 +1,98 @@
+mod common;
+
+use common::*;
+use klog::network::{KLogAppendRequest, KLogQueryRequest};
+use klog::rpc::{KLogAppendBatchConfig, KLogAppendBatcher, KLogClient};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn entry(message: &str, request_id: &str) -> KLogAppendRequest {
+    KLogAppendRequest {
+        message: message.to_string(),
+        timestamp: None,
+        node_id: None,
+        level: None,
+        source: Some("batch".to_string()),
+        attrs: None,
+        request_id: Some(request_id.to_string()),
+    }
+}
+
+#[tokio::test]
+async fn test_single_node_append_batch_via_client() -> Result<(), String> {
+    if !can_bind_localhost() {
+        eprintln!("skip single-node append batch test: localhost bind is not available");
+        return Ok(());
+    }
+
+    let port = choose_free_port().map_err(|e| format!("choose free port failed: {}", e))?;
+    let cluster_name = format!("klog_append_batch_{}", port);
+    let mut node = spawn_node(1, port, &cluster_name, true, &[], "voter").await?;
+
+    let result = async {
+        wait_single_node_leader(port, 1, Duration::from_secs(20)).await?;
+        let client =
+            KLogClient::from_daemon_addr(format!("127.0.0.1:{}", node.rpc_port).as_str(), 9001)
+                .with_timeout(Duration::from_secs(3));
+
+        let batch = vec![
+            entry("b1", "batch-1"),
+            entry("b2", "batch-2"),
+            entry("b3", "batch-3"),
+        ];
+        let first = client
+            .append_log_batch(batch.clone())
+            .await
+            .map_err(|e| format!("append_log_batch failed: {}", e))?;
+        if first.ids.len() != 3 || first.ids.windows(2).any(|w| w[1] != w[0] + 1) {
+            return Err(format!("batch ids are not contiguous: {:?}", first.ids));
+        }
+        // A retried batch resolves to the same ids without writing anything.
+        let retried = client
+            .append_log_batch(batch)
+            .await
+            .map_err(|e| format!("retry append_log_batch failed: {}", e))?;
+        if retried.ids != first.ids {
+            return Err(format!(
+                "retried batch got new ids: first={:?}, retried={:?}",
+                first.ids, retried.ids
+            ));
+        }
+
+        let batcher = KLogAppendBatcher::spawn(
+            Arc::new(
+                KLogClient::from_daemon_addr(format!("127.0.0.1:{}", node.rpc_port).as_str(), 9001)
+                    .with_timeout(Duration::from_secs(3)),
+            ),
+            KLogAppendBatchConfig::default(),
+        );
+        let batched_id = batcher
+            .append(entry("b4", "batch-4"))
+            .await
+            .map_err(|e| format!("batcher append failed: {}", e))?;
+        batcher.close().await;
+        if batched_id <= first.ids[2] {
+            return Err(format!("batcher id {} is not after batch", batched_id));
+        }
+
+        let items = client
+            .query_log(KLogQueryRequest {
+                source: Some("batch".to_string()),
+                strong_read: Some(true),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| format!("query_log failed: {}", e))?
+            .items;
+        let messages = items.iter().map(|e| e.message.as_str()).collect::<Vec<_>>();
+        if messages != vec!["b1", "b2", "b3", "b4"] {
+            return Err(format!("unexpected batch entries: {:?}", messages));
+        }
+
+        Ok(())
+    }
+    .await;
+
+    node.stop().await;
+    result
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use common::*;
+use klog::error::KLogErrorCode;
+use klog::network::{KLogMetaPutRequest, KLogMetaQueryRequest};
+use klog::rpc::KLogClient;
+use std::time::Duration;
+
+async fn meta_exists(client: &KLogClient, key: &str) -> Result<bool, String> {
+    let resp = client
+        .query_meta(KLogMetaQueryRequest {
+            key: Some(key.to_string()),
+            prefix: None,
+            limit: None,
+            strong_read: Some(true),
+        })
+        .await
+        .map_err(|e| format!("query_meta {} failed: {}", key, e))?;
+    Ok(!resp.items.is_empty())
+}
+
+#[tokio::test]
+async fn test_single_node_lease_expiry_and_revoke_via_client() -> Result<(), String> {
+    if !can_bind_localhost() {
+        eprintln!("skip single-node lease test: localhost bind is not available");
+        return Ok(());
+    }
+
+    let port = choose_free_port().map_err(|e| format!("choose free port failed: {}", e))?;
+    let cluster_name = format!("klog_lease_{}", port);
+    let mut node = spawn_node(1, port, &cluster_name, true, &[], "voter").await?;
+
+    let result = async {
+        wait_single_node_leader(port, 1, Duration::from_secs(20)).await?;
+        let client =
+            KLogClient::from_daemon_addr(format!("127.0.0.1:{}", node.rpc_port).as_str(), 9001)
+                .with_timeout(Duration::from_secs(3));
+
+        let short = client
+            .grant_lease(1_000)
+            .await
+            .map_err(|e| format!("grant short lease failed: {}", e))?;
+        let long = client
+            .grant_lease(60_000)
+            .await
+            .map_err(|e| format!("grant long lease failed: {}", e))?;
+        for (key, lease_id) in [("lock/short", short.id), ("lock/long", long.id)] {
+            client
+                .put_meta(KLogMetaPutRequest {
+                    key: key.to_string(),
+                    value: "holder".to_string(),
+                    expected_revision: Some(0),
+                    lease_id: Some(lease_id),
+                })
+                .await
+                .map_err(|e| format!("put_meta {} failed: {}", key, e))?;
+        }
+
+        let err = client
+            .put_meta(KLogMetaPutRequest {
+                key: "lock/orphan".to_string(),
+                value: "holder".to_string(),
+                expected_revision: None,
+                lease_id: Some(long.id + 1_000),
+            })
+            .await
+            .err()
+            .ok_or_else(|| "put_meta with unknown lease must fail".to_string())?;
+        if err.error_code != KLogErrorCode::NotFound {
+            return Err(format!("unexpected unknown lease error: {}", err));
+        }
+
+        let renewed = client
+            .keepalive_lease(long.id)
+            .await
+            .map_err(|e| format!("keepalive failed: {}", e))?;
+        if renewed.expires_at < long.expires_at {
+            return Err(format!("keepalive moved expiry backwards: {:?}", renewed));
+        }
+
+        // The short lease is never kept alive and must disappear together with its key.
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+        while meta_exists(&client, "lock/short").await? {
+            if tokio::time::Instant::now() > deadline {
+                return Err("short lease key did not expire".to_string());
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        let gone = client
+            .get_lease(short.id)
+            .await
+            .map_err(|e| format!("get_lease failed: {}", e))?;
+        if gone.is_some() {
+            return Err(format!("expired lease still readable: {:?}", gone));
+        }
+        let err = client
+            .keepalive_lease(short.id)
+            .await
+            .err()
+            .ok_or_else(|| "keepalive of expired lease must fail".to_string())?;
+        if err.error_code != KLogErrorCode::NotFound {
+            return Err(format!("unexpected expired keepalive error: {}", err));
+        }
+
+        if !meta_exists(&client, "lock/long").await? {
+            return Err("long lease key expired too early".to_string());
+        }
+        let revoked = client
+            .revoke_lease(long.id)
+            .await
+            .map_err(|e| format!("revoke failed: {}", e))?;
+        if revoked.deleted_keys != vec!["lock/long".to_string()] {
+            return Err(format!("unexpected revoke result: {:?}", revoked));
+        }
+        if meta_exists(&client, "lock/long").await? {
+            return Err("revoked lease key still present".to_string());
+        }
+
+        Ok(())
+    }
+    .await;
+
+    node.stop().await;
+    result
+}
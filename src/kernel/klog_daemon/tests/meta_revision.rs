@@ -45,6 +45,7 @@ async fn test_three_node_meta_revision_optional_cas_via_client() -> Result<(), S
                 key: key.clone(),
                 value: "v1".to_string(),
                 expected_revision: Some(0),
+                lease_id: None,
             })
             .await
             .map_err(|e| format!("create-if-absent put_meta failed: {}", e))?;
@@ -60,6 +61,7 @@ async fn test_three_node_meta_revision_optional_cas_via_client() -> Result<(), S
                 key: key.clone(),
                 value: "v-create-conflict".to_string(),
                 expected_revision: Some(0),
+                lease_id: None,
             })
             .await
             .expect_err("expected create-if-absent conflict");
@@ -76,6 +78,7 @@ async fn test_three_node_meta_revision_optional_cas_via_client() -> Result<(), S
                 key: key.clone(),
                 value: "v2".to_string(),
                 expected_revision: Some(1),
+                lease_id: None,
             })
             .await
             .map_err(|e| format!("cas put_meta(expected=1) failed: {}", e))?;
@@ -91,6 +94,7 @@ async fn test_three_node_meta_revision_optional_cas_via_client() -> Result<(), S
                 key: key.clone(),
                 value: "v-stale".to_string(),
                 expected_revision: Some(1),
+                lease_id: None,
             })
             .await
             .expect_err("expected stale revision conflict");
@@ -107,6 +111,7 @@ async fn test_three_node_meta_revision_optional_cas_via_client() -> Result<(), S
                 key: key.clone(),
                 value: "v3-non-cas".to_string(),
                 expected_revision: None,
+                lease_id: None,
             })
             .await
             .map_err(|e| format!("non-cas put_meta failed: {}", e))?;
@@ -176,6 +181,7 @@ async fn test_three_node_meta_revision_kept_after_leader_failover() -> Result<()
                 key: key.clone(),
                 value: "before-failover".to_string(),
                 expected_revision: Some(0),
+                lease_id: None,
             })
             .await
             .map_err(|e| format!("put_meta before failover failed: {}", e))?;
@@ -235,6 +241,7 @@ async fn test_three_node_meta_revision_kept_after_leader_failover() -> Result<()
                 key: key.clone(),
                 value: "after-failover".to_string(),
                 expected_revision: Some(1),
+                lease_id: None,
             })
             .await
             .map_err(|e| format!("put_meta after failover failed: {}", e))?;
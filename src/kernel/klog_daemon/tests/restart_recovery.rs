@@ -74,6 +74,7 @@ async fn test_three_voter_full_restart_recovers_membership_leader_and_writes() -
                 key: meta_key.clone(),
                 value: "before-restart".to_string(),
                 expected_revision: Some(0),
+                lease_id: None,
             })
             .await
             .map_err(|e| format!("put_meta before full restart failed: {}", e))?;
@@ -186,6 +187,7 @@ async fn test_three_voter_full_restart_recovers_membership_leader_and_writes() -
                 key: meta_key.clone(),
                 value: "after-restart".to_string(),
                 expected_revision: Some(1),
+                lease_id: None,
             })
             .await
             .map_err(|e| format!("cas put_meta after full restart failed: {}", e))?;
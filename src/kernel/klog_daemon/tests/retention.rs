@@ -0,0 +1,139 @@
+mod common;
+
+use common::*;
+use klog::network::{
+    KLogAdminRequestType, KLogRetentionRunResponse, KLogRetentionStateResponse,
+    KLogRetentionUpdateRequest, KLogRetentionUpdateResponse,
+};
+use klog::retention::{KLogRetentionPolicy, KLogRetentionRule};
+use std::time::Duration;
+
+async fn admin_json<T: serde::de::DeserializeOwned>(
+    req: reqwest::RequestBuilder,
+) -> Result<T, String> {
+    let resp = req
+        .send()
+        .await
+        .map_err(|e| format!("admin request failed: {}", e))?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_else(|_| String::new());
+        return Err(format!("admin request returned {}: {}", status, body));
+    }
+    resp.json::<T>()
+        .await
+        .map_err(|e| format!("decode admin response failed: {}", e))
+}
+
+#[tokio::test]
+async fn test_single_node_retention_policy_truncates_old_entries() -> Result<(), String> {
+    if !can_bind_localhost() {
+        eprintln!("skip single-node retention test: localhost bind is not available");
+        return Ok(());
+    }
+
+    let port = choose_free_port().map_err(|e| format!("choose free port failed: {}", e))?;
+    let cluster_name = format!("klog_retention_{}", port);
+    let mut node = spawn_node(1, port, &cluster_name, true, &[], "voter").await?;
+
+    let result = async {
+        wait_single_node_leader(port, 1, Duration::from_secs(20)).await?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(3))
+            .build()
+            .map_err(|e| format!("failed to build http client: {}", e))?;
+        let admin_port = resolve_admin_port(port);
+        let retention_url = format!(
+            "http://127.0.0.1:{}{}",
+            admin_port,
+            KLogAdminRequestType::Retention.klog_path()
+        );
+        let run_url = format!(
+            "http://127.0.0.1:{}{}",
+            admin_port,
+            KLogAdminRequestType::RetentionRun.klog_path()
+        );
+
+        let mut ids = Vec::new();
+        for i in 0..8u64 {
+            let resp = append_log(
+                &client,
+                node.rpc_port,
+                &format!("retention-{}", i),
+                Some(1_000 + i),
+                Some(1),
+            )
+            .await?;
+            ids.push(resp.id);
+        }
+
+        let initial: KLogRetentionStateResponse = admin_json(client.get(&retention_url)).await?;
+        if initial.policy.is_some() || initial.stats.entries != 8 {
+            return Err(format!("unexpected initial retention state: {:?}", initial));
+        }
+
+        let policy = KLogRetentionPolicy {
+            global: KLogRetentionRule {
+                max_entries: Some(3),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let updated: KLogRetentionUpdateResponse = admin_json(client.post(&retention_url).json(
+            &KLogRetentionUpdateRequest {
+                policy: Some(policy.clone()),
+            },
+        ))
+        .await?;
+        if updated.policy.as_ref() != Some(&policy) || updated.policy_revision.is_none() {
+            return Err(format!(
+                "unexpected retention update response: {:?}",
+                updated
+            ));
+        }
+
+        let run: KLogRetentionRunResponse = admin_json(client.post(&run_url)).await?;
+        if run.report.removed != 5 {
+            return Err(format!("unexpected retention run report: {:?}", run));
+        }
+
+        let remaining = query_logs(&client, node.rpc_port, None, None, Some(100), Some(false))
+            .await?
+            .items
+            .iter()
+            .map(|e| e.id)
+            .collect::<Vec<_>>();
+        if remaining != ids[5..] {
+            return Err(format!(
+                "unexpected remaining ids after retention: {:?}, expected={:?}",
+                remaining,
+                &ids[5..]
+            ));
+        }
+
+        let state: KLogRetentionStateResponse = admin_json(client.get(&retention_url)).await?;
+        if state.policy.as_ref() != Some(&policy) || state.stats.entries != 3 {
+            return Err(format!("unexpected retention state after run: {:?}", state));
+        }
+
+        let bad_policy = serde_json::json!({ "policy": { "global": { "max_entries": 0 } } });
+        let resp = client
+            .post(&retention_url)
+            .json(&bad_policy)
+            .send()
+            .await
+            .map_err(|e| format!("invalid policy request failed: {}", e))?;
+        if resp.status() != reqwest::StatusCode::BAD_REQUEST {
+            return Err(format!(
+                "invalid policy must be rejected with 400, got {}",
+                resp.status()
+            ));
+        }
+
+        Ok(())
+    }
+    .await;
+
+    node.stop().await;
+    result
+}
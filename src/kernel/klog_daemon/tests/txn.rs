@@ -0,0 +1,82 @@
+mod common;
+
+use common::*;
+use klog::error::KLogErrorCode;
+use klog::network::{KLogTxnRequest, KLogTxnRequestOp};
+use klog::rpc::KLogClient;
+use klog::txn::{KLogTxnCompare, KLogTxnOpResult};
+use std::time::Duration;
+
+fn put(key: &str, value: &str) -> KLogTxnRequestOp {
+    KLogTxnRequestOp::Put {
+        key: key.to_string(),
+        value: value.to_string(),
+        lease_id: None,
+    }
+}
+
+#[tokio::test]
+async fn test_single_node_meta_txn_via_client() -> Result<(), String> {
+    if !can_bind_localhost() {
+        eprintln!("skip single-node txn test: localhost bind is not available");
+        return Ok(());
+    }
+
+    let port = choose_free_port().map_err(|e| format!("choose free port failed: {}", e))?;
+    let cluster_name = format!("klog_txn_{}", port);
+    let mut node = spawn_node(1, port, &cluster_name, true, &[], "voter").await?;
+
+    let result = async {
+        wait_single_node_leader(port, 1, Duration::from_secs(20)).await?;
+        let client =
+            KLogClient::from_daemon_addr(format!("127.0.0.1:{}", node.rpc_port).as_str(), 9001)
+                .with_timeout(Duration::from_secs(3));
+
+        let claim = |value: &str| KLogTxnRequest {
+            compare: vec![KLogTxnCompare::Absent {
+                key: "txn/owner".to_string(),
+            }],
+            then_ops: vec![put("txn/owner", value), put("txn/epoch", "1")],
+            else_ops: vec![KLogTxnRequestOp::Get {
+                key: "txn/owner".to_string(),
+            }],
+        };
+        let first = client
+            .txn(claim("node-a"))
+            .await
+            .map_err(|e| format!("first txn failed: {}", e))?;
+        if !first.succeeded || first.results.len() != 2 {
+            return Err(format!("unexpected first txn result: {:?}", first));
+        }
+        let second = client
+            .txn(claim("node-b"))
+            .await
+            .map_err(|e| format!("second txn failed: {}", e))?;
+        match second.results.as_slice() {
+            [
+                KLogTxnOpResult::Get {
+                    item: Some(item), ..
+                },
+            ] if !second.succeeded && item.value == "node-a" => {}
+            _ => return Err(format!("unexpected second txn result: {:?}", second)),
+        }
+
+        let err = client
+            .txn(KLogTxnRequest {
+                then_ops: vec![put("txn/dup", "1"), put(" txn/dup ", "2")],
+                ..Default::default()
+            })
+            .await
+            .err()
+            .ok_or_else(|| "txn writing one key twice must fail".to_string())?;
+        if err.error_code != KLogErrorCode::InvalidArgument {
+            return Err(format!("unexpected duplicate key error: {}", err));
+        }
+
+        Ok(())
+    }
+    .await;
+
+    node.stop().await;
+    result
+}
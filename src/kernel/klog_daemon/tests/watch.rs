@@ -0,0 +1,180 @@
+mod common;
+
+use common::*;
+use klog::network::{
+    KLogAppendRequest, KLogLogWatchRequest, KLogMetaDeleteRequest, KLogMetaPutRequest,
+    KLogMetaWatchRequest,
+};
+use klog::rpc::KLogClient;
+use klog::state_store::KLogMetaWatchEventKind;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_single_node_watch_meta_and_log_via_client() -> Result<(), String> {
+    if !can_bind_localhost() {
+        eprintln!("skip single-node watch test: localhost bind is not available");
+        return Ok(());
+    }
+
+    let port = choose_free_port().map_err(|e| format!("choose free port failed: {}", e))?;
+    let cluster_name = format!("klog_watch_{}", port);
+    let mut node = spawn_node(1, port, &cluster_name, true, &[], "voter").await?;
+
+    let result = async {
+        wait_single_node_leader(port, 1, Duration::from_secs(20)).await?;
+        let client =
+            KLogClient::from_daemon_addr(format!("127.0.0.1:{}", node.rpc_port).as_str(), 9001)
+                .with_timeout(Duration::from_secs(3));
+
+        let put = client
+            .put_meta(KLogMetaPutRequest {
+                key: "watch/a".to_string(),
+                value: "v1".to_string(),
+                expected_revision: None,
+                lease_id: None,
+            })
+            .await
+            .map_err(|e| format!("put_meta failed: {}", e))?;
+
+        // Start from "now", so only changes after the first put are delivered.
+        let mut meta_stream = client.watch_meta(KLogMetaWatchRequest {
+            prefix: Some("watch/".to_string()),
+            ..Default::default()
+        });
+        let first_poll = client
+            .poll_meta_watch(KLogMetaWatchRequest {
+                prefix: Some("watch/".to_string()),
+                wait_ms: Some(0),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| format!("poll_meta_watch failed: {}", e))?;
+        if !first_poll.events.is_empty() || first_poll.compacted {
+            return Err(format!("unexpected initial watch poll: {:?}", first_poll));
+        }
+
+        let watcher = async {
+            let mut events = Vec::new();
+            while events.len() < 2 {
+                let event = meta_stream
+                    .next()
+                    .await
+                    .map_err(|e| format!("meta watch next failed: {}", e))?;
+                events.push(event);
+            }
+            Ok::<_, String>(events)
+        };
+        let writer = async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            client
+                .put_meta(KLogMetaPutRequest {
+                    key: "other/b".to_string(),
+                    value: "ignored".to_string(),
+                    expected_revision: None,
+                    lease_id: None,
+                })
+                .await
+                .map_err(|e| format!("put_meta other failed: {}", e))?;
+            client
+                .put_meta(KLogMetaPutRequest {
+                    key: "watch/a".to_string(),
+                    value: "v2".to_string(),
+                    expected_revision: Some(put.revision),
+                    lease_id: None,
+                })
+                .await
+                .map_err(|e| format!("put_meta update failed: {}", e))?;
+            client
+                .delete_meta(KLogMetaDeleteRequest {
+                    key: "watch/a".to_string(),
+                })
+                .await
+                .map_err(|e| format!("delete_meta failed: {}", e))?;
+            Ok::<_, String>(())
+        };
+        let (events, written) = tokio::time::timeout(Duration::from_secs(10), async {
+            tokio::join!(watcher, writer)
+        })
+        .await
+        .map_err(|_| "meta watch timed out".to_string())?;
+        written?;
+        let events = events?;
+        let summary = events
+            .iter()
+            .map(|e| (e.key.as_str(), e.kind, e.meta.value.as_str()))
+            .collect::<Vec<_>>();
+        if summary
+            != vec![
+                ("watch/a", KLogMetaWatchEventKind::Put, "v2"),
+                ("watch/a", KLogMetaWatchEventKind::Delete, "v2"),
+            ]
+        {
+            return Err(format!("unexpected meta watch events: {:?}", events));
+        }
+        if events[0].revision >= events[1].revision {
+            return Err(format!("meta watch revisions not ordered: {:?}", events));
+        }
+
+        // Resuming from the first event replays only what came after it.
+        let resumed = client
+            .poll_meta_watch(KLogMetaWatchRequest {
+                prefix: Some("watch/".to_string()),
+                after_revision: Some(events[0].revision),
+                wait_ms: Some(0),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| format!("resume poll_meta_watch failed: {}", e))?;
+        if resumed.events != events[1..] {
+            return Err(format!("unexpected resumed meta events: {:?}", resumed));
+        }
+
+        let anchor_id = client
+            .append_log_message("watch-anchor")
+            .await
+            .map_err(|e| format!("append anchor failed: {}", e))?;
+        let mut log_stream = client.watch_log(KLogLogWatchRequest {
+            after_id: Some(anchor_id),
+            source: Some("watch-src".to_string()),
+            ..Default::default()
+        });
+        let writer = async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            client
+                .append_log_message("unmatched")
+                .await
+                .map_err(|e| format!("append unmatched failed: {}", e))?;
+            client
+                .append_log(KLogAppendRequest {
+                    message: "matched".to_string(),
+                    timestamp: None,
+                    node_id: None,
+                    level: None,
+                    source: Some("watch-src".to_string()),
+                    attrs: None,
+                    request_id: None,
+                })
+                .await
+                .map_err(|e| format!("append matched failed: {}", e))
+        };
+        let (entry, appended) = tokio::time::timeout(Duration::from_secs(10), async {
+            tokio::join!(log_stream.next(), writer)
+        })
+        .await
+        .map_err(|_| "log watch timed out".to_string())?;
+        let appended = appended?;
+        let entry = entry.map_err(|e| format!("log watch next failed: {}", e))?;
+        if entry.id != appended.id || entry.message != "matched" {
+            return Err(format!(
+                "unexpected log watch entry: {:?}, appended={:?}",
+                entry, appended
+            ));
+        }
+
+        Ok(())
+    }
+    .await;
+
+    node.stop().await;
+    result
+}
@@ -7,8 +7,8 @@ use buckyos_kit::init_logging;
 use std::sync::Arc;
 
 use buckyos_http_server::*;
-use log::error;
-use sled_msg_queue::SledMsgQueueServer;
+use log::{error, warn};
+use sled_msg_queue::{SledMsgQueue, SledMsgQueueServer};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -30,7 +30,13 @@ async fn main() -> anyhow::Result<()> {
     set_buckyos_api_runtime(runtime)
         .map_err(|err| anyhow::anyhow!("register kmsg runtime failed: {}", err))?;
 
-    let server = SledMsgQueueServer::new();
+    let mut queue = SledMsgQueue::new()
+        .map_err(|err| anyhow::anyhow!("open kmsg sled database failed: {}", err))?;
+    match get_buckyos_api_runtime()?.get_kevent_client().await {
+        Ok(client) => queue = queue.with_kevent_client(Arc::new(client)),
+        Err(err) => warn!("kmsg post events disabled, kevent unavailable: {}", err),
+    }
+    let server = SledMsgQueueServer::new(queue);
 
     let runner = Runner::new(KMSG_SERVICE_MAIN_PORT);
     if let Err(err) = runner.add_http_server("/kapi/kmsg".to_string(), Arc::new(server)) {
@@ -1,5 +1,6 @@
 use ::kRPC::*;
 use async_trait::async_trait;
+use buckyos_api::KEventClient;
 use buckyos_api::msg_queue::*;
 use buckyos_http_server::{
     HttpServer, ServerError, ServerErrorCode, ServerResult, StreamInfo, serve_http_by_rpc_handler,
@@ -9,20 +10,27 @@ use buckyos_kit::get_buckyos_service_local_data_dir;
 use bytes::Bytes;
 use http::{Method, Version};
 use http_body_util::combinators::BoxBody;
+use log::warn;
 use serde::{Deserialize, Serialize};
 use sled::{Db, IVec, Tree, transaction::Transactional};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::Path;
-use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
 use tokio::task;
+use tokio::time::Instant;
+
+/// How often a parked fetch looks again without a post, so messages whose
+/// visibility timeout or nack delay runs out are still picked up.
+const FETCH_WAIT_RECHECK: Duration = Duration::from_secs(1);
 
 pub struct SledMsgQueueServer {
     handler: MsgQueueServerHandler<SledMsgQueue>,
 }
 
 impl SledMsgQueueServer {
-    pub fn new() -> Self {
-        let queue = SledMsgQueue::new().expect("Failed to open kmsg sled database");
+    pub fn new(queue: SledMsgQueue) -> Self {
         Self {
             handler: MsgQueueServerHandler::new(queue),
         }
@@ -91,8 +99,137 @@ struct QueueMeta {
 struct SubscriptionState {
     queue_urn: QueueUrn,
     cursor: MsgIndex,
+    /// Delivered but unacked messages, only kept for queues with a visibility
+    /// timeout or delivery limit and for messages that were nacked.
+    #[serde(default)]
+    inflight: BTreeMap<MsgIndex, InflightMessage>,
+    /// Messages at or above `cursor` that left the subscription out of order
+    /// (dead-lettered); the cursor skips them once everything below is acked.
+    #[serde(default)]
+    settled: BTreeSet<MsgIndex>,
+    /// Set for consumer group members. Their delivery state lives in the
+    /// group and the fields above are unused.
+    #[serde(default)]
+    group: Option<GroupMembership>,
+}
+
+impl SubscriptionState {
+    fn new(queue_urn: &str, cursor: MsgIndex) -> Self {
+        Self {
+            queue_urn: queue_urn.to_string(),
+            cursor,
+            inflight: BTreeMap::new(),
+            settled: BTreeSet::new(),
+            group: None,
+        }
+    }
+
+    /// Move the cursor to `cursor` and forget delivery state below it.
+    fn reset_cursor(&mut self, cursor: MsgIndex) {
+        self.cursor = cursor;
+        self.inflight = self.inflight.split_off(&cursor);
+        self.settled = self.settled.split_off(&cursor);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InflightMessage {
+    /// Times the message was handed to this subscription.
+    attempts: u32,
+    /// Unix millis before which fetch skips the message.
+    visible_at_ms: u64,
+    /// Subscription or group member the message was last handed to; `None`
+    /// once it was nacked or the member lost its lease.
+    #[serde(default)]
+    owner: Option<SubscriptionId>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GroupMembership {
+    name: String,
+    lease_ms: u64,
+}
+
+/// Shared delivery state of a consumer group. Every member fetches from the
+/// same cursor; a message in flight belongs to exactly one member until it is
+/// acked, nacked, its visibility timeout runs out or the member's lease lapses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GroupState {
+    delivery: SubscriptionState,
+    members: BTreeMap<SubscriptionId, GroupMember>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GroupMember {
+    lease_expires_at_ms: u64,
 }
 
+impl GroupState {
+    /// Remove members whose lease lapsed, except `keep`, and hand their
+    /// messages back to the rest of the group right away.
+    fn expire_members(&mut self, now: u64, keep: Option<&str>) {
+        let lapsed: Vec<SubscriptionId> = self
+            .members
+            .iter()
+            .filter(|(sub_id, member)| {
+                Some(sub_id.as_str()) != keep && member.lease_expires_at_ms <= now
+            })
+            .map(|(sub_id, _)| sub_id.clone())
+            .collect();
+        for sub_id in lapsed {
+            self.remove_member(&sub_id);
+        }
+    }
+
+    fn remove_member(&mut self, sub_id: &str) {
+        self.members.remove(sub_id);
+        for entry in self.delivery.inflight.values_mut() {
+            if entry.owner.as_deref() == Some(sub_id) {
+                entry.owner = None;
+                entry.visible_at_ms = 0;
+            }
+        }
+    }
+
+    fn is_held(entry: &InflightMessage, now: u64) -> bool {
+        entry.owner.is_some() && entry.visible_at_ms > now
+    }
+
+    fn stats(&self, name: String, meta: &QueueMeta, now: u64) -> ConsumerGroupStats {
+        let first = self.delivery.cursor.max(meta.first_index);
+        let lag = if meta.message_count == 0 || first > meta.last_index {
+            0
+        } else {
+            let settled = self.delivery.settled.range(first..=meta.last_index).count() as u64;
+            (meta.last_index - first + 1).saturating_sub(settled)
+        };
+        let held = |sub_id: Option<&str>| {
+            self.delivery
+                .inflight
+                .values()
+                .filter(|entry| Self::is_held(entry, now))
+                .filter(|entry| sub_id.is_none() || entry.owner.as_deref() == sub_id)
+                .count() as u64
+        };
+        ConsumerGroupStats {
+            name,
+            lag,
+            pending: held(None),
+            members: self
+                .members
+                .iter()
+                .map(|(sub_id, member)| ConsumerGroupMemberStats {
+                    sub_id: sub_id.clone(),
+                    pending: held(Some(sub_id.as_str())),
+                    lease_expires_at_ms: member.lease_expires_at_ms,
+                })
+                .collect(),
+        }
+    }
+}
+
+type DeliveryLock = Arc<tokio::sync::Mutex<()>>;
+
 #[derive(Clone)]
 pub struct SledMsgQueue {
     db: Arc<Db>,
@@ -100,7 +237,14 @@ pub struct SledMsgQueue {
     queue_meta: Tree,
     messages: Tree,
     subs: Tree,
+    groups: Tree,
     meta: Tree,
+    /// One lock per consumer group and per plain subscription, so fetch, ack
+    /// and nack of the same delivery state never interleave.
+    delivery_locks: Arc<Mutex<HashMap<Vec<u8>, DeliveryLock>>>,
+    /// Last posted index per queue, for fetches parked with `wait_ms`.
+    posted: Arc<Mutex<HashMap<QueueUrn, watch::Sender<MsgIndex>>>>,
+    kevent_client: Option<Arc<KEventClient>>,
 }
 
 impl SledMsgQueue {
@@ -118,11 +262,21 @@ impl SledMsgQueue {
             queue_meta: db.open_tree("queue_meta")?,
             messages: db.open_tree("messages")?,
             subs: db.open_tree("subs")?,
+            groups: db.open_tree("groups")?,
             meta: db.open_tree("meta")?,
+            delivery_locks: Arc::new(Mutex::new(HashMap::new())),
+            posted: Arc::new(Mutex::new(HashMap::new())),
+            kevent_client: None,
             db: Arc::new(db),
         })
     }
 
+    /// Also announce every post on `kmsg_queue_event_path(queue_urn)`.
+    pub fn with_kevent_client(mut self, client: Arc<KEventClient>) -> Self {
+        self.kevent_client = Some(client);
+        self
+    }
+
     fn now_seconds() -> u64 {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -130,6 +284,13 @@ impl SledMsgQueue {
             .as_secs()
     }
 
+    fn now_millis() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+
     fn queue_key(queue_urn: &str) -> Vec<u8> {
         queue_urn.as_bytes().to_vec()
     }
@@ -230,6 +391,460 @@ impl SledMsgQueue {
         Self::decode_queue_config(&config)
     }
 
+    fn validate_queue_config(
+        queue_urn: &str,
+        config: &QueueConfig,
+    ) -> std::result::Result<(), RPCErrors> {
+        if config.max_delivery_attempts == Some(0) {
+            return Err(RPCErrors::ReasonError(
+                "max_delivery_attempts must be at least 1".to_string(),
+            ));
+        }
+        if config.dead_letter_queue.as_deref() == Some(queue_urn) {
+            return Err(RPCErrors::ReasonError(format!(
+                "Queue cannot be its own dead letter queue: {}",
+                queue_urn
+            )));
+        }
+        Ok(())
+    }
+
+    fn group_lock_key(queue_urn: &str, group: &str) -> Vec<u8> {
+        let mut key = vec![b'g'];
+        key.extend_from_slice(&Self::group_key(queue_urn, group));
+        key
+    }
+
+    fn sub_lock_key(sub_id: &str) -> Vec<u8> {
+        let mut key = vec![b's'];
+        key.extend_from_slice(sub_id.as_bytes());
+        key
+    }
+
+    async fn lock_delivery(&self, key: Vec<u8>) -> tokio::sync::OwnedMutexGuard<()> {
+        let lock = self
+            .delivery_locks
+            .lock()
+            .expect("kmsg delivery locks poisoned")
+            .entry(key)
+            .or_default()
+            .clone();
+        lock.lock_owned().await
+    }
+
+    fn forget_delivery_locks(&self, matches: impl Fn(&[u8]) -> bool) {
+        self.delivery_locks
+            .lock()
+            .expect("kmsg delivery locks poisoned")
+            .retain(|key, _| !matches(key));
+    }
+
+    fn load_subscription(&self, sub_id: &str) -> std::result::Result<SubscriptionState, RPCErrors> {
+        let sub_value = self
+            .subs
+            .get(sub_id.as_bytes())
+            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?
+            .ok_or_else(|| RPCErrors::ReasonError(format!("Subscription not found: {}", sub_id)))?;
+        serde_json::from_slice(&sub_value).map_err(|err| RPCErrors::ReasonError(err.to_string()))
+    }
+
+    fn store_subscription(
+        &self,
+        sub_id: &str,
+        sub: &SubscriptionState,
+    ) -> std::result::Result<(), RPCErrors> {
+        let data =
+            serde_json::to_vec(sub).map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+        self.subs
+            .insert(sub_id.as_bytes(), data)
+            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+        Ok(())
+    }
+
+    /// Advance the cursor over settled messages that are now at its head.
+    fn skip_settled(&self, sub: &mut SubscriptionState) -> std::result::Result<(), RPCErrors> {
+        while !sub.settled.is_empty() {
+            let start = Self::message_key(&sub.queue_urn, sub.cursor);
+            let end = Self::message_key(&sub.queue_urn, u64::MAX);
+            let next = match self.messages.range(start..=end).next() {
+                Some(item) => {
+                    let (key, _) = item.map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+                    Self::decode_index_from_key(&key)
+                }
+                None => None,
+            };
+            match next {
+                Some(index) if sub.settled.contains(&index) => sub.reset_cursor(index + 1),
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Take `message` out of the subscription: post it to the dead letter
+    /// queue, or drop it when none is configured. If the dead letter queue
+    /// rejects it, the message stays in flight and the next fetch retries.
+    async fn dead_letter(
+        &self,
+        sub_id: &str,
+        sub: &mut SubscriptionState,
+        config: &QueueConfig,
+        mut message: Message,
+    ) {
+        let index = message.index;
+        let attempts = sub
+            .inflight
+            .get(&index)
+            .map(|entry| entry.attempts)
+            .unwrap_or(0);
+        match config.dead_letter_queue.as_deref() {
+            Some(dead_letter_queue) => {
+                message.index = 0;
+                message.headers.insert(
+                    DEAD_LETTER_HEADER_SOURCE_QUEUE.to_string(),
+                    sub.queue_urn.clone(),
+                );
+                message.headers.insert(
+                    DEAD_LETTER_HEADER_SOURCE_INDEX.to_string(),
+                    index.to_string(),
+                );
+                message.headers.insert(
+                    DEAD_LETTER_HEADER_SUBSCRIPTION.to_string(),
+                    sub_id.to_string(),
+                );
+                message.headers.insert(
+                    DEAD_LETTER_HEADER_ATTEMPTS.to_string(),
+                    attempts.to_string(),
+                );
+                if let Err(err) = self
+                    .handle_post_message(dead_letter_queue, message, RPCContext::default())
+                    .await
+                {
+                    warn!(
+                        "kmsg dead letter failed, keeping message in flight: queue={}, index={}, dlq={}, err={}",
+                        sub.queue_urn, index, dead_letter_queue, err
+                    );
+                    return;
+                }
+            }
+            None => warn!(
+                "kmsg dropped message after {} attempts, no dead letter queue: queue={}, index={}, sub={}",
+                attempts, sub.queue_urn, index, sub_id
+            ),
+        }
+        sub.inflight.remove(&index);
+        sub.settled.insert(index);
+    }
+
+    fn start_cursor(meta: &QueueMeta, position: SubPosition) -> MsgIndex {
+        let first_index = if meta.first_index == 0 {
+            1
+        } else {
+            meta.first_index
+        };
+        match position {
+            SubPosition::Earliest => first_index,
+            SubPosition::Latest => meta.last_index + 1,
+            SubPosition::At(index) => index,
+        }
+    }
+
+    /// Walk the queue from the cursor and pick up to `length` messages. When
+    /// `track` is set, each picked message is recorded in flight for `owner`
+    /// and hidden for `hold_ms`; messages that used up their delivery
+    /// attempts are returned separately for the caller to dead-letter.
+    fn collect_deliveries(
+        &self,
+        sub: &mut SubscriptionState,
+        config: &QueueConfig,
+        length: usize,
+        track: bool,
+        hold_ms: u64,
+        owner: Option<&str>,
+    ) -> std::result::Result<(Vec<Message>, Vec<Message>), RPCErrors> {
+        let now = Self::now_millis();
+        let start = Self::message_key(&sub.queue_urn, sub.cursor);
+        let end = Self::message_key(&sub.queue_urn, u64::MAX);
+        let mut messages = Vec::new();
+        let mut exhausted = Vec::new();
+        for item in self.messages.range(start..=end) {
+            let (_, value) = item.map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+            let msg = Self::decode_message(&value)?;
+            if sub.settled.contains(&msg.index) {
+                continue;
+            }
+            if track {
+                let entry = sub.inflight.entry(msg.index).or_insert(InflightMessage {
+                    attempts: 0,
+                    visible_at_ms: 0,
+                    owner: None,
+                });
+                if entry.visible_at_ms > now {
+                    continue;
+                }
+                if config
+                    .max_delivery_attempts
+                    .is_some_and(|max| entry.attempts >= max)
+                {
+                    exhausted.push(msg);
+                    continue;
+                }
+                entry.attempts += 1;
+                entry.visible_at_ms = now.saturating_add(hold_ms);
+                entry.owner = owner.map(|owner| owner.to_string());
+            }
+            messages.push(msg);
+            if messages.len() >= length {
+                break;
+            }
+        }
+        Ok((messages, exhausted))
+    }
+
+    fn group_key(queue_urn: &str, group: &str) -> Vec<u8> {
+        let mut key = Self::message_prefix(queue_urn);
+        key.extend_from_slice(group.as_bytes());
+        key
+    }
+
+    fn load_group(
+        &self,
+        queue_urn: &str,
+        group: &str,
+    ) -> std::result::Result<Option<GroupState>, RPCErrors> {
+        let value = self
+            .groups
+            .get(Self::group_key(queue_urn, group))
+            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+        value
+            .map(|value| {
+                serde_json::from_slice(&value)
+                    .map_err(|err| RPCErrors::ReasonError(err.to_string()))
+            })
+            .transpose()
+    }
+
+    fn store_group(
+        &self,
+        queue_urn: &str,
+        group: &str,
+        state: &GroupState,
+    ) -> std::result::Result<(), RPCErrors> {
+        let data =
+            serde_json::to_vec(state).map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+        self.groups
+            .insert(Self::group_key(queue_urn, group), data)
+            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+        Ok(())
+    }
+
+    /// Load the group of a member for an operation by that member: lapsed
+    /// peers are removed and the member's own lease is renewed, rejoining it
+    /// if it had lapsed itself. Callers hold the group's delivery lock.
+    fn enter_group(
+        &self,
+        sub_id: &str,
+        queue_urn: &str,
+        membership: &GroupMembership,
+    ) -> std::result::Result<GroupState, RPCErrors> {
+        let mut group = self
+            .load_group(queue_urn, &membership.name)?
+            .ok_or_else(|| {
+                RPCErrors::ReasonError(format!("Consumer group not found: {}", membership.name))
+            })?;
+        let now = Self::now_millis();
+        group.expire_members(now, Some(sub_id));
+        group.members.insert(
+            sub_id.to_string(),
+            GroupMember {
+                lease_expires_at_ms: now.saturating_add(membership.lease_ms),
+            },
+        );
+        Ok(group)
+    }
+
+    async fn fetch_group_messages(
+        &self,
+        sub_id: &str,
+        queue_urn: &str,
+        membership: &GroupMembership,
+        length: usize,
+        auto_commit: bool,
+    ) -> std::result::Result<Vec<Message>, RPCErrors> {
+        let config = self.get_queue_config(queue_urn)?;
+        let _guard = self
+            .lock_delivery(Self::group_lock_key(queue_urn, &membership.name))
+            .await;
+        let mut group = self.enter_group(sub_id, queue_urn, membership)?;
+        // Without a visibility timeout a message stays with its member until
+        // it is acked or nacked, or the member's lease lapses.
+        let hold_ms = config.visibility_timeout_ms.unwrap_or(u64::MAX);
+        let (messages, exhausted) = self.collect_deliveries(
+            &mut group.delivery,
+            &config,
+            length,
+            true,
+            hold_ms,
+            Some(sub_id),
+        )?;
+        for msg in exhausted {
+            self.dead_letter(sub_id, &mut group.delivery, &config, msg)
+                .await;
+        }
+        if auto_commit {
+            for msg in &messages {
+                group.delivery.inflight.remove(&msg.index);
+                group.delivery.settled.insert(msg.index);
+            }
+        }
+        self.skip_settled(&mut group.delivery)?;
+        self.store_group(queue_urn, &membership.name, &group)?;
+        Ok(messages)
+    }
+
+    async fn nack_group_message(
+        &self,
+        sub_id: &str,
+        queue_urn: &str,
+        membership: &GroupMembership,
+        index: MsgIndex,
+        delay_ms: Option<u64>,
+    ) -> std::result::Result<(), RPCErrors> {
+        let config = self.get_queue_config(queue_urn)?;
+        let _guard = self
+            .lock_delivery(Self::group_lock_key(queue_urn, &membership.name))
+            .await;
+        let mut group = self.enter_group(sub_id, queue_urn, membership)?;
+        let now = Self::now_millis();
+        let entry = group
+            .delivery
+            .inflight
+            .get_mut(&index)
+            .filter(|entry| entry.owner.as_deref() == Some(sub_id))
+            .ok_or_else(|| {
+                RPCErrors::ReasonError(format!("Message {} is not held by {}", index, sub_id))
+            })?;
+        // The message goes back to the whole group, not just this member.
+        entry.owner = None;
+        entry.visible_at_ms = now.saturating_add(delay_ms.unwrap_or(0));
+        let attempts = entry.attempts;
+        if config
+            .max_delivery_attempts
+            .is_some_and(|max| attempts >= max)
+        {
+            let value = self
+                .messages
+                .get(Self::message_key(queue_urn, index))
+                .map_err(|err| RPCErrors::ReasonError(err.to_string()))?
+                .ok_or_else(|| {
+                    RPCErrors::ReasonError(format!("Message not found: {} in {}", index, queue_urn))
+                })?;
+            let message = Self::decode_message(&value)?;
+            self.dead_letter(sub_id, &mut group.delivery, &config, message)
+                .await;
+            self.skip_settled(&mut group.delivery)?;
+        }
+        self.store_group(queue_urn, &membership.name, &group)
+    }
+
+    fn group_stats(
+        &self,
+        queue_urn: &str,
+        meta: &QueueMeta,
+    ) -> std::result::Result<Vec<ConsumerGroupStats>, RPCErrors> {
+        let now = Self::now_millis();
+        let prefix = Self::message_prefix(queue_urn);
+        let mut groups = Vec::new();
+        for item in self.groups.scan_prefix(&prefix) {
+            let (key, value) = item.map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+            let mut state: GroupState = serde_json::from_slice(&value)
+                .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+            // Report lapsed members as gone even before the group next runs.
+            state.expire_members(now, None);
+            let name = String::from_utf8_lossy(&key[prefix.len()..]).to_string();
+            groups.push(state.stats(name, meta, now));
+        }
+        Ok(groups)
+    }
+
+    fn watch_posts(&self, queue_urn: &str) -> watch::Receiver<MsgIndex> {
+        let mut posted = self
+            .posted
+            .lock()
+            .expect("kmsg post watchers lock poisoned");
+        posted
+            .entry(queue_urn.to_string())
+            .or_insert_with(|| watch::channel(0).0)
+            .subscribe()
+    }
+
+    /// Wake fetches parked on the queue and, when a kevent client is set,
+    /// publish the post without holding up the producer.
+    fn notify_posted(&self, queue_urn: &str, index: MsgIndex) {
+        if let Some(sender) = self
+            .posted
+            .lock()
+            .expect("kmsg post watchers lock poisoned")
+            .get(queue_urn)
+        {
+            sender.send_replace(index);
+        }
+        let Some(client) = self.kevent_client.clone() else {
+            return;
+        };
+        let eventid = kmsg_queue_event_path(queue_urn);
+        let payload = serde_json::json!({ "queue_urn": queue_urn, "index": index });
+        tokio::spawn(async move {
+            if let Err(err) = client.pub_event(&eventid, payload).await {
+                warn!(
+                    "kmsg publish post event failed: eventid={}, err={}",
+                    eventid, err
+                );
+            }
+        });
+    }
+
+    async fn fetch_ready(
+        &self,
+        sub_id: &str,
+        length: usize,
+        auto_commit: bool,
+    ) -> std::result::Result<Vec<Message>, RPCErrors> {
+        let sub = self.load_subscription(sub_id)?;
+        if let Some(membership) = sub.group.as_ref() {
+            return self
+                .fetch_group_messages(sub_id, &sub.queue_urn, membership, length, auto_commit)
+                .await;
+        }
+        let _guard = self.lock_delivery(Self::sub_lock_key(sub_id)).await;
+        let mut sub = self.load_subscription(sub_id)?;
+        let config = self.get_queue_config(&sub.queue_urn)?;
+        // Without a visibility timeout or delivery limit, and with nothing
+        // nacked, a fetch that does not commit leaves no trace: the same
+        // messages come back until they are acked.
+        let track = !auto_commit
+            && (config.visibility_timeout_ms.is_some()
+                || config.max_delivery_attempts.is_some()
+                || !sub.inflight.is_empty());
+        let hold_ms = config.visibility_timeout_ms.unwrap_or(0);
+        let (messages, exhausted) =
+            self.collect_deliveries(&mut sub, &config, length, track, hold_ms, Some(sub_id))?;
+
+        if track {
+            for msg in exhausted {
+                self.dead_letter(sub_id, &mut sub, &config, msg).await;
+            }
+            self.skip_settled(&mut sub)?;
+            self.store_subscription(sub_id, &sub)?;
+        } else if auto_commit && let Some(last) = messages.last() {
+            sub.reset_cursor(last.index + 1);
+            self.skip_settled(&mut sub)?;
+            self.store_subscription(sub_id, &sub)?;
+        }
+
+        Ok(messages)
+    }
+
     fn store_queue_meta(
         &self,
         queue_urn: &str,
@@ -259,6 +874,7 @@ impl MsgQueueHandler for SledMsgQueue {
             None => format!("queue-{}", self.next_id("queue_id")?),
         };
         let queue_urn = calc_queue_urn(appid, app_owner, &name);
+        Self::validate_queue_config(&queue_urn, &config)?;
         let key = Self::queue_key(&queue_urn);
         let config_data =
             serde_json::to_vec(&config).map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
@@ -336,10 +952,27 @@ impl MsgQueueHandler for SledMsgQueue {
             })
             .collect();
         for key in sub_keys {
+            let lock_key = Self::sub_lock_key(&String::from_utf8_lossy(&key));
+            self.forget_delivery_locks(|held| held == lock_key.as_slice());
             let _ = self.subs.remove(key);
         }
 
+        let group_keys: Vec<Vec<u8>> = self
+            .groups
+            .scan_prefix(Self::message_prefix(queue_urn))
+            .filter_map(|item| item.ok().map(|(key, _)| key.to_vec()))
+            .collect();
+        for key in group_keys {
+            let _ = self.groups.remove(key);
+        }
+        let group_locks = Self::group_lock_key(queue_urn, "");
+        self.forget_delivery_locks(|key| key.starts_with(&group_locks));
+
         let _ = self.queue_meta.remove(Self::queue_key(queue_urn));
+        self.posted
+            .lock()
+            .expect("kmsg post watchers lock poisoned")
+            .remove(queue_urn);
         self.db
             .flush()
             .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
@@ -352,11 +985,13 @@ impl MsgQueueHandler for SledMsgQueue {
         _ctx: RPCContext,
     ) -> std::result::Result<QueueStats, RPCErrors> {
         let meta = self.get_queue_meta(queue_urn)?;
+        let consumer_groups = self.group_stats(queue_urn, &meta)?;
         Ok(QueueStats {
             message_count: meta.message_count,
             first_index: meta.first_index,
             last_index: meta.last_index,
             size_bytes: meta.size_bytes,
+            consumer_groups,
         })
     }
 
@@ -378,6 +1013,7 @@ impl MsgQueueHandler for SledMsgQueue {
                 queue_urn
             )));
         }
+        Self::validate_queue_config(queue_urn, &config)?;
         let config_data =
             serde_json::to_vec(&config).map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
         self.queues
@@ -462,6 +1098,7 @@ impl MsgQueueHandler for SledMsgQueue {
                 .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
         }
 
+        self.notify_posted(queue_urn, result);
         Ok(result)
     }
 
@@ -475,17 +1112,49 @@ impl MsgQueueHandler for SledMsgQueue {
         _ctx: RPCContext,
     ) -> std::result::Result<SubscriptionId, RPCErrors> {
         let meta = self.get_queue_meta(queue_urn)?;
-        let first_index = if meta.first_index == 0 {
-            1
-        } else {
-            meta.first_index
-        };
-        let last_index = meta.last_index;
-        let cursor = match position {
-            SubPosition::Earliest => first_index,
-            SubPosition::Latest => last_index + 1,
-            SubPosition::At(index) => index,
+        let cursor = Self::start_cursor(&meta, position);
+
+        let sub_id = match sub_id {
+            Some(value) => value,
+            None => format!("sub-{}", self.next_id("sub_id")?),
         };
+        if self
+            .subs
+            .get(sub_id.as_bytes())
+            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?
+            .is_some()
+        {
+            return Err(RPCErrors::ReasonError(format!(
+                "Subscription already exists: {}",
+                sub_id
+            )));
+        }
+
+        self.store_subscription(&sub_id, &SubscriptionState::new(queue_urn, cursor))?;
+        Ok(sub_id)
+    }
+
+    async fn handle_join_group(
+        &self,
+        queue_urn: &str,
+        group: &str,
+        sub_id: Option<String>,
+        position: SubPosition,
+        lease_ms: Option<u64>,
+        _ctx: RPCContext,
+    ) -> std::result::Result<SubscriptionId, RPCErrors> {
+        if group.is_empty() {
+            return Err(RPCErrors::ReasonError(
+                "Consumer group name must not be empty".to_string(),
+            ));
+        }
+        let lease_ms = lease_ms.unwrap_or(DEFAULT_CONSUMER_GROUP_LEASE_MS);
+        if lease_ms == 0 {
+            return Err(RPCErrors::ReasonError(
+                "Consumer group lease_ms must be positive".to_string(),
+            ));
+        }
+        let meta = self.get_queue_meta(queue_urn)?;
 
         let sub_id = match sub_id {
             Some(value) => value,
@@ -503,15 +1172,32 @@ impl MsgQueueHandler for SledMsgQueue {
             )));
         }
 
-        let sub = SubscriptionState {
-            queue_urn: queue_urn.to_string(),
-            cursor,
+        let _guard = self
+            .lock_delivery(Self::group_lock_key(queue_urn, group))
+            .await;
+        let mut state = match self.load_group(queue_urn, group)? {
+            Some(state) => state,
+            None => GroupState {
+                delivery: SubscriptionState::new(queue_urn, Self::start_cursor(&meta, position)),
+                members: BTreeMap::new(),
+            },
         };
-        let data =
-            serde_json::to_vec(&sub).map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
-        self.subs
-            .insert(sub_id.as_bytes(), data)
-            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+        let now = Self::now_millis();
+        state.expire_members(now, None);
+        state.members.insert(
+            sub_id.clone(),
+            GroupMember {
+                lease_expires_at_ms: now.saturating_add(lease_ms),
+            },
+        );
+
+        let mut member = SubscriptionState::new(queue_urn, 0);
+        member.group = Some(GroupMembership {
+            name: group.to_string(),
+            lease_ms,
+        });
+        self.store_subscription(&sub_id, &member)?;
+        self.store_group(queue_urn, group, &state)?;
         Ok(sub_id)
     }
 
@@ -520,6 +1206,23 @@ impl MsgQueueHandler for SledMsgQueue {
         sub_id: &str,
         _ctx: RPCContext,
     ) -> std::result::Result<(), RPCErrors> {
+        let sub = self.load_subscription(sub_id)?;
+        let lock_key = match sub.group.as_ref() {
+            Some(membership) => Self::group_lock_key(&sub.queue_urn, &membership.name),
+            None => Self::sub_lock_key(sub_id),
+        };
+        let _guard = self.lock_delivery(lock_key).await;
+        if let Some(membership) = sub.group.as_ref() {
+            // The group itself outlives its members; only this member's
+            // messages go back to the others.
+            if let Some(mut group) = self.load_group(&sub.queue_urn, &membership.name)? {
+                group.remove_member(sub_id);
+                self.store_group(&sub.queue_urn, &membership.name, &group)?;
+            }
+        } else {
+            let lock_key = Self::sub_lock_key(sub_id);
+            self.forget_delivery_locks(|key| key == lock_key.as_slice());
+        }
         if self
             .subs
             .remove(sub_id.as_bytes())
@@ -539,40 +1242,32 @@ impl MsgQueueHandler for SledMsgQueue {
         sub_id: &str,
         length: usize,
         auto_commit: bool,
+        wait_ms: Option<u64>,
         _ctx: RPCContext,
     ) -> std::result::Result<Vec<Message>, RPCErrors> {
-        let sub_value = self
-            .subs
-            .get(sub_id.as_bytes())
-            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?
-            .ok_or_else(|| RPCErrors::ReasonError(format!("Subscription not found: {}", sub_id)))?;
-        let mut sub: SubscriptionState = serde_json::from_slice(&sub_value)
-            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
-
-        let start = Self::message_key(&sub.queue_urn, sub.cursor);
-        let end = Self::message_key(&sub.queue_urn, u64::MAX);
-        let mut messages = Vec::new();
-        for item in self.messages.range(start..=end) {
-            let (_, value) = item.map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
-            let msg = Self::decode_message(&value)?;
-            messages.push(msg);
-            if messages.len() >= length {
-                break;
-            }
+        let wait_ms = wait_ms.unwrap_or(0).min(MAX_FETCH_WAIT_MS);
+        if wait_ms == 0 {
+            return self.fetch_ready(sub_id, length, auto_commit).await;
         }
 
-        if auto_commit {
-            if let Some(last) = messages.last() {
-                sub.cursor = last.index + 1;
-                let data = serde_json::to_vec(&sub)
-                    .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
-                self.subs
-                    .insert(sub_id.as_bytes(), data)
-                    .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+        let queue_urn = self.load_subscription(sub_id)?.queue_urn;
+        let mut posted = self.watch_posts(&queue_urn);
+        let deadline = Instant::now() + Duration::from_millis(wait_ms);
+        loop {
+            // Mark the current post as seen before looking, so one that lands
+            // while we fetch still ends the wait below.
+            posted.borrow_and_update();
+            let messages = self.fetch_ready(sub_id, length, auto_commit).await?;
+            let now = Instant::now();
+            if !messages.is_empty() || now >= deadline {
+                return Ok(messages);
+            }
+            let wake_at = deadline.min(now + FETCH_WAIT_RECHECK);
+            if let Ok(Err(_)) = tokio::time::timeout_at(wake_at, posted.changed()).await {
+                // The queue was deleted; the next fetch reports it.
+                tokio::time::sleep_until(wake_at).await;
             }
         }
-
-        Ok(messages)
     }
 
     async fn handle_read_message(
@@ -604,20 +1299,113 @@ impl MsgQueueHandler for SledMsgQueue {
         index: MsgIndex,
         _ctx: RPCContext,
     ) -> std::result::Result<(), RPCErrors> {
-        let sub_value = self
-            .subs
-            .get(sub_id.as_bytes())
+        let sub = self.load_subscription(sub_id)?;
+        if let Some(membership) = sub.group.as_ref() {
+            let _guard = self
+                .lock_delivery(Self::group_lock_key(&sub.queue_urn, &membership.name))
+                .await;
+            let mut group = self.enter_group(sub_id, &sub.queue_urn, membership)?;
+            // A member acks what it holds up to `index`; messages it lost
+            // with its lease now belong to someone else and are left alone.
+            let acked: Vec<MsgIndex> = group
+                .delivery
+                .inflight
+                .range(..=index)
+                .filter(|(_, entry)| entry.owner.as_deref() == Some(sub_id))
+                .map(|(held, _)| *held)
+                .collect();
+            for held in acked {
+                group.delivery.inflight.remove(&held);
+                group.delivery.settled.insert(held);
+            }
+            self.skip_settled(&mut group.delivery)?;
+            return self.store_group(&sub.queue_urn, &membership.name, &group);
+        }
+        let _guard = self.lock_delivery(Self::sub_lock_key(sub_id)).await;
+        let mut sub = self.load_subscription(sub_id)?;
+        // An ack covers everything delivered up to `index`, but not messages
+        // below it that were nacked and wait for redelivery: the cursor stops
+        // at the lowest of those and the acked ones above it are settled.
+        let pending: BTreeSet<MsgIndex> = sub
+            .inflight
+            .range(..index)
+            .filter(|(_, entry)| entry.owner.is_none())
+            .map(|(pending, _)| *pending)
+            .collect();
+        match pending.first() {
+            Some(&lowest) => {
+                let start = Self::message_key(&sub.queue_urn, lowest + 1);
+                let end = Self::message_key(&sub.queue_urn, index);
+                for item in self.messages.range(start..=end) {
+                    let (key, _) = item.map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+                    let Some(acked) = Self::decode_index_from_key(&key) else {
+                        continue;
+                    };
+                    if !pending.contains(&acked) {
+                        sub.inflight.remove(&acked);
+                        sub.settled.insert(acked);
+                    }
+                }
+                if lowest > sub.cursor {
+                    sub.reset_cursor(lowest);
+                }
+            }
+            None => sub.reset_cursor(index + 1),
+        }
+        self.skip_settled(&mut sub)?;
+        self.store_subscription(sub_id, &sub)
+    }
+
+    async fn handle_nack(
+        &self,
+        sub_id: &str,
+        index: MsgIndex,
+        delay_ms: Option<u64>,
+        _ctx: RPCContext,
+    ) -> std::result::Result<(), RPCErrors> {
+        let sub = self.load_subscription(sub_id)?;
+        if let Some(membership) = sub.group.as_ref() {
+            return self
+                .nack_group_message(sub_id, &sub.queue_urn, membership, index, delay_ms)
+                .await;
+        }
+        let _guard = self.lock_delivery(Self::sub_lock_key(sub_id)).await;
+        let mut sub = self.load_subscription(sub_id)?;
+        if index < sub.cursor || sub.settled.contains(&index) {
+            return Err(RPCErrors::ReasonError(format!(
+                "Message {} is already acknowledged by {}",
+                index, sub_id
+            )));
+        }
+        let value = self
+            .messages
+            .get(Self::message_key(&sub.queue_urn, index))
             .map_err(|err| RPCErrors::ReasonError(err.to_string()))?
-            .ok_or_else(|| RPCErrors::ReasonError(format!("Subscription not found: {}", sub_id)))?;
-        let mut sub: SubscriptionState = serde_json::from_slice(&sub_value)
-            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
-        sub.cursor = index + 1;
-        let data =
-            serde_json::to_vec(&sub).map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
-        self.subs
-            .insert(sub_id.as_bytes(), data)
-            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
-        Ok(())
+            .ok_or_else(|| {
+                RPCErrors::ReasonError(format!("Message not found: {} in {}", index, sub.queue_urn))
+            })?;
+        let config = self.get_queue_config(&sub.queue_urn)?;
+
+        // A nacked message was delivered at least once, even on a queue that
+        // did not track it until now.
+        let now = Self::now_millis();
+        let entry = sub.inflight.entry(index).or_insert(InflightMessage {
+            attempts: 1,
+            visible_at_ms: now,
+            owner: None,
+        });
+        entry.owner = None;
+        entry.visible_at_ms = now.saturating_add(delay_ms.unwrap_or(0));
+        let attempts = entry.attempts;
+        if config
+            .max_delivery_attempts
+            .is_some_and(|max| attempts >= max)
+        {
+            let message = Self::decode_message(&value)?;
+            self.dead_letter(sub_id, &mut sub, &config, message).await;
+            self.skip_settled(&mut sub)?;
+        }
+        self.store_subscription(sub_id, &sub)
     }
 
     async fn handle_seek(
@@ -626,33 +1414,22 @@ impl MsgQueueHandler for SledMsgQueue {
         index: SubPosition,
         _ctx: RPCContext,
     ) -> std::result::Result<(), RPCErrors> {
-        let sub_value = self
-            .subs
-            .get(sub_id.as_bytes())
-            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?
-            .ok_or_else(|| RPCErrors::ReasonError(format!("Subscription not found: {}", sub_id)))?;
-        let mut sub: SubscriptionState = serde_json::from_slice(&sub_value)
-            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
+        let sub = self.load_subscription(sub_id)?;
+        if let Some(membership) = sub.group.as_ref() {
+            return Err(RPCErrors::ReasonError(format!(
+                "Cannot seek member {} of consumer group {}",
+                sub_id, membership.name
+            )));
+        }
+        let _guard = self.lock_delivery(Self::sub_lock_key(sub_id)).await;
+        let mut sub = self.load_subscription(sub_id)?;
 
         let meta = self.get_queue_meta(&sub.queue_urn)?;
-        let first_index = if meta.first_index == 0 {
-            1
-        } else {
-            meta.first_index
-        };
-        let last_index = meta.last_index;
-        sub.cursor = match index {
-            SubPosition::Earliest => first_index,
-            SubPosition::Latest => last_index + 1,
-            SubPosition::At(value) => value,
-        };
-
-        let data =
-            serde_json::to_vec(&sub).map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
-        self.subs
-            .insert(sub_id.as_bytes(), data)
-            .map_err(|err| RPCErrors::ReasonError(err.to_string()))?;
-        Ok(())
+        // A seek replays from scratch, including redelivery counts.
+        sub.cursor = Self::start_cursor(&meta, index);
+        sub.inflight.clear();
+        sub.settled.clear();
+        self.store_subscription(sub_id, &sub)
     }
 
     async fn handle_delete_message_before(
@@ -835,7 +1612,7 @@ mod tests {
             )
             .await?;
         let msgs = queue
-            .handle_fetch_messages(&sub_id, 2, true, RPCContext::default())
+            .handle_fetch_messages(&sub_id, 2, true, None, RPCContext::default())
             .await?;
         assert_eq!(msgs.len(), 2);
         assert_eq!(msgs[0].index, 1);
@@ -843,7 +1620,7 @@ mod tests {
 
         // fetch remaining without auto commit then ack
         let msgs = queue
-            .handle_fetch_messages(&sub_id, 2, false, RPCContext::default())
+            .handle_fetch_messages(&sub_id, 2, false, None, RPCContext::default())
             .await?;
         assert_eq!(msgs.len(), 1);
         assert_eq!(msgs[0].index, 3);
@@ -851,7 +1628,7 @@ mod tests {
             .handle_commit_ack(&sub_id, 3, RPCContext::default())
             .await?;
         let msgs = queue
-            .handle_fetch_messages(&sub_id, 1, false, RPCContext::default())
+            .handle_fetch_messages(&sub_id, 1, false, None, RPCContext::default())
             .await?;
         assert!(msgs.is_empty());
         let history = queue
@@ -862,7 +1639,7 @@ mod tests {
             vec![1, 2]
         );
         let msgs = queue
-            .handle_fetch_messages(&sub_id, 1, false, RPCContext::default())
+            .handle_fetch_messages(&sub_id, 1, false, None, RPCContext::default())
             .await?;
         assert!(msgs.is_empty());
 
@@ -871,7 +1648,7 @@ mod tests {
             .handle_seek(&sub_id, SubPosition::Earliest, RPCContext::default())
             .await?;
         let msgs = queue
-            .handle_fetch_messages(&sub_id, 1, false, RPCContext::default())
+            .handle_fetch_messages(&sub_id, 1, false, None, RPCContext::default())
             .await?;
         assert_eq!(msgs[0].index, 1);
 
@@ -887,7 +1664,7 @@ mod tests {
             )
             .await?;
         let msgs = queue
-            .handle_fetch_messages(&sub_latest, 1, false, RPCContext::default())
+            .handle_fetch_messages(&sub_latest, 1, false, None, RPCContext::default())
             .await?;
         assert!(msgs.is_empty());
 
@@ -912,7 +1689,7 @@ mod tests {
             .await?;
         assert!(
             queue
-                .handle_fetch_messages(&sub_id, 1, false, RPCContext::default())
+                .handle_fetch_messages(&sub_id, 1, false, None, RPCContext::default())
                 .await
                 .is_err()
         );
@@ -982,7 +1759,7 @@ mod tests {
 
         // earliest reads first three and auto commits
         let msgs = queue
-            .handle_fetch_messages(&sub_earliest, 3, true, RPCContext::default())
+            .handle_fetch_messages(&sub_earliest, 3, true, None, RPCContext::default())
             .await?;
         assert_eq!(
             msgs.iter().map(|m| m.index).collect::<Vec<_>>(),
@@ -991,7 +1768,7 @@ mod tests {
 
         // mid reads two without commit, then commit to 6
         let msgs = queue
-            .handle_fetch_messages(&sub_mid, 2, false, RPCContext::default())
+            .handle_fetch_messages(&sub_mid, 2, false, None, RPCContext::default())
             .await?;
         assert_eq!(msgs.iter().map(|m| m.index).collect::<Vec<_>>(), vec![5, 6]);
         queue
@@ -1000,7 +1777,7 @@ mod tests {
 
         // latest should see nothing until new messages arrive
         let msgs = queue
-            .handle_fetch_messages(&sub_latest, 5, true, RPCContext::default())
+            .handle_fetch_messages(&sub_latest, 5, true, None, RPCContext::default())
             .await?;
         assert!(msgs.is_empty());
 
@@ -1014,7 +1791,7 @@ mod tests {
 
         // latest now gets both new messages
         let msgs = queue
-            .handle_fetch_messages(&sub_latest, 10, true, RPCContext::default())
+            .handle_fetch_messages(&sub_latest, 10, true, None, RPCContext::default())
             .await?;
         assert_eq!(
             msgs.iter().map(|m| m.index).collect::<Vec<_>>(),
@@ -1035,7 +1812,7 @@ mod tests {
 
         // earliest cursor was at 4 (after auto commit) and should now see from 6
         let msgs = queue
-            .handle_fetch_messages(&sub_earliest, 3, true, RPCContext::default())
+            .handle_fetch_messages(&sub_earliest, 3, true, None, RPCContext::default())
             .await?;
         assert_eq!(
             msgs.iter().map(|m| m.index).collect::<Vec<_>>(),
@@ -1044,7 +1821,7 @@ mod tests {
 
         // mid cursor at 7 after commit; fetch remaining
         let msgs = queue
-            .handle_fetch_messages(&sub_mid, 10, true, RPCContext::default())
+            .handle_fetch_messages(&sub_mid, 10, true, None, RPCContext::default())
             .await?;
         assert_eq!(
             msgs.iter().map(|m| m.index).collect::<Vec<_>>(),
@@ -1102,7 +1879,7 @@ mod tests {
             .await?;
 
         let messages = queue
-            .handle_fetch_messages(sub_id, 1, true, RPCContext::default())
+            .handle_fetch_messages(sub_id, 1, true, None, RPCContext::default())
             .await?;
         assert_eq!(messages.len(), 1);
         assert_eq!(messages[0].payload, b"hello".to_vec());
@@ -106,6 +106,18 @@ impl MsgQueueHandler for FakeMsgQueue {
         Ok(sub_id.unwrap_or_else(|| "sub-generated".to_string()))
     }
 
+    async fn handle_join_group(
+        &self,
+        _queue_urn: &str,
+        group: &str,
+        sub_id: Option<String>,
+        _position: SubPosition,
+        _lease_ms: Option<u64>,
+        _ctx: RPCContext,
+    ) -> Result<SubscriptionId, RPCErrors> {
+        Ok(sub_id.unwrap_or_else(|| format!("{}-member", group)))
+    }
+
     async fn handle_unsubscribe(&self, _sub_id: &str, _ctx: RPCContext) -> Result<(), RPCErrors> {
         Ok(())
     }
@@ -115,6 +127,7 @@ impl MsgQueueHandler for FakeMsgQueue {
         _sub_id: &str,
         _length: usize,
         _auto_commit: bool,
+        _wait_ms: Option<u64>,
         _ctx: RPCContext,
     ) -> Result<Vec<Message>, RPCErrors> {
         Ok(vec![Message::new(b"fake".to_vec())])
@@ -139,6 +152,16 @@ impl MsgQueueHandler for FakeMsgQueue {
         Ok(())
     }
 
+    async fn handle_nack(
+        &self,
+        _sub_id: &str,
+        _index: MsgIndex,
+        _delay_ms: Option<u64>,
+        _ctx: RPCContext,
+    ) -> Result<(), RPCErrors> {
+        Ok(())
+    }
+
     async fn handle_seek(
         &self,
         _sub_id: &str,
@@ -254,6 +277,43 @@ async fn wrong_param_type_returns_parse_error() {
     assert!(matches!(err, RPCErrors::ParseRequestError(_)));
 }
 
+#[tokio::test]
+async fn nack_delay_is_optional() {
+    let server = MsgQueueServerHandler::new(FakeMsgQueue::new());
+    let response = server
+        .handle_rpc_call(
+            rpc_req("nack", json!({ "sub_id": "sub", "index": 7 })),
+            localhost(),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(response.result, RPCResult::Success(_)));
+}
+
+#[tokio::test]
+async fn join_group_returns_member_sub_id() {
+    let server = MsgQueueServerHandler::new(FakeMsgQueue::new());
+    let response = server
+        .handle_rpc_call(
+            rpc_req(
+                "join_group",
+                json!({
+                    "queue_urn": "q",
+                    "group": "workers",
+                    "sub_id": null,
+                    "position": "Earliest"
+                }),
+            ),
+            localhost(),
+        )
+        .await
+        .unwrap();
+    match response.result {
+        RPCResult::Success(value) => assert_eq!(value, json!("workers-member")),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
 #[tokio::test]
 async fn handler_error_is_propagated_as_rpc_error() {
     let server = MsgQueueServerHandler::new(FakeMsgQueue::failing_get_stats());
@@ -6,7 +6,7 @@ use kRPC::RPCContext;
 use sled_msg_queue::SledMsgQueue;
 use std::collections::HashSet;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 fn make_message(text: &str) -> Message {
     Message::new(text.as_bytes().to_vec())
@@ -76,7 +76,7 @@ async fn persistence_reopen_preserves_queue_messages_and_cursor()
     );
 
     let pending = reopened
-        .handle_fetch_messages(&sub_id, 10, false, RPCContext::default())
+        .handle_fetch_messages(&sub_id, 10, false, None, RPCContext::default())
         .await?;
     assert_eq!(pending.len(), 1);
     assert_eq!(pending[0].index, second);
@@ -115,7 +115,7 @@ async fn sync_write_cursor_survives_reopen() -> Result<(), Box<dyn std::error::E
         )
         .await?;
     let messages = queue
-        .handle_fetch_messages(&sub_id, 1, false, RPCContext::default())
+        .handle_fetch_messages(&sub_id, 1, false, None, RPCContext::default())
         .await?;
     assert_eq!(messages[0].index, first);
     queue
@@ -125,13 +125,68 @@ async fn sync_write_cursor_survives_reopen() -> Result<(), Box<dyn std::error::E
 
     let reopened = SledMsgQueue::new_in_dir(&path)?;
     let pending = reopened
-        .handle_fetch_messages(&sub_id, 1, false, RPCContext::default())
+        .handle_fetch_messages(&sub_id, 1, false, None, RPCContext::default())
         .await?;
     assert!(pending.is_empty());
 
     Ok(())
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn fetch_with_wait_returns_as_soon_as_a_message_is_posted()
+-> Result<(), Box<dyn std::error::Error>> {
+    let temp = tempfile::TempDir::new()?;
+    let queue = Arc::new(SledMsgQueue::new_in_dir(temp.path())?);
+    let queue_urn = queue
+        .handle_create_queue(
+            Some("long-poll"),
+            "app",
+            "owner",
+            QueueConfig::default(),
+            RPCContext::default(),
+        )
+        .await?;
+    let sub_id = queue
+        .handle_subscribe(
+            &queue_urn,
+            "user",
+            "app",
+            None,
+            SubPosition::Earliest,
+            RPCContext::default(),
+        )
+        .await?;
+
+    // Nothing arrives: the wait runs out and the fetch comes back empty.
+    let started = Instant::now();
+    let empty = queue
+        .handle_fetch_messages(&sub_id, 10, true, Some(200), RPCContext::default())
+        .await?;
+    assert!(empty.is_empty());
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    let producer = {
+        let queue = queue.clone();
+        let queue_urn = queue_urn.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            queue
+                .handle_post_message(&queue_urn, make_message("late"), RPCContext::default())
+                .await
+        })
+    };
+    let started = Instant::now();
+    let messages = queue
+        .handle_fetch_messages(&sub_id, 10, true, Some(10_000), RPCContext::default())
+        .await?;
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].payload, b"late".to_vec());
+    producer.await??;
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn max_messages_config_is_currently_not_enforced() -> Result<(), Box<dyn std::error::Error>> {
     let temp = tempfile::TempDir::new()?;
@@ -337,7 +392,7 @@ async fn post_and_fetch_baseline() -> Result<(), Box<dyn std::error::Error>> {
     let mut fetched = 0usize;
     while fetched < 10_000 {
         let batch = queue
-            .handle_fetch_messages(&sub_id, 100, true, RPCContext::default())
+            .handle_fetch_messages(&sub_id, 100, true, None, RPCContext::default())
             .await?;
         if batch.is_empty() {
             break;
@@ -396,7 +451,7 @@ async fn fetch_without_auto_commit_ack_baseline() -> Result<(), Box<dyn std::err
     let mut last_index = 0;
     while fetched < 10_000 {
         let batch = queue
-            .handle_fetch_messages(&sub_id, 100, false, RPCContext::default())
+            .handle_fetch_messages(&sub_id, 100, false, None, RPCContext::default())
             .await?;
         if batch.is_empty() {
             break;
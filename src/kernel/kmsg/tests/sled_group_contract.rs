@@ -0,0 +1,197 @@
+#[path = "../src/sled_msg_queue.rs"]
+mod sled_msg_queue;
+
+use buckyos_api::msg_queue::*;
+use kRPC::RPCContext;
+use sled_msg_queue::SledMsgQueue;
+use std::time::Duration;
+
+async fn create_queue(queue: &SledMsgQueue, name: &str) -> Result<QueueUrn, kRPC::RPCErrors> {
+    let queue_urn = queue
+        .handle_create_queue(
+            Some(name),
+            "app",
+            "owner",
+            QueueConfig::default(),
+            RPCContext::default(),
+        )
+        .await?;
+    for text in ["m1", "m2", "m3", "m4"] {
+        queue
+            .handle_post_message(
+                &queue_urn,
+                Message::new(text.as_bytes().to_vec()),
+                RPCContext::default(),
+            )
+            .await?;
+    }
+    Ok(queue_urn)
+}
+
+async fn join(
+    queue: &SledMsgQueue,
+    queue_urn: &str,
+    sub_id: &str,
+    lease_ms: Option<u64>,
+) -> Result<SubscriptionId, kRPC::RPCErrors> {
+    queue
+        .handle_join_group(
+            queue_urn,
+            "workers",
+            Some(sub_id.to_string()),
+            SubPosition::Earliest,
+            lease_ms,
+            RPCContext::default(),
+        )
+        .await
+}
+
+async fn fetch(
+    queue: &SledMsgQueue,
+    sub_id: &str,
+    length: usize,
+) -> Result<Vec<MsgIndex>, kRPC::RPCErrors> {
+    let messages = queue
+        .handle_fetch_messages(sub_id, length, false, None, RPCContext::default())
+        .await?;
+    Ok(messages.iter().map(|msg| msg.index).collect())
+}
+
+async fn group_stats(
+    queue: &SledMsgQueue,
+    queue_urn: &str,
+) -> Result<ConsumerGroupStats, Box<dyn std::error::Error>> {
+    let stats = queue
+        .handle_get_queue_stats(queue_urn, RPCContext::default())
+        .await?;
+    assert_eq!(stats.consumer_groups.len(), 1);
+    Ok(stats.consumer_groups[0].clone())
+}
+
+fn member_pending(group: &ConsumerGroupStats) -> Vec<(String, u64)> {
+    group
+        .members
+        .iter()
+        .map(|member| (member.sub_id.clone(), member.pending))
+        .collect()
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn members_share_the_group_cursor_without_overlap() -> Result<(), Box<dyn std::error::Error>>
+{
+    let temp = tempfile::TempDir::new()?;
+    let queue = SledMsgQueue::new_in_dir(temp.path())?;
+    let queue_urn = create_queue(&queue, "jobs").await?;
+    let a = join(&queue, &queue_urn, "worker-a", None).await?;
+    let b = join(&queue, &queue_urn, "worker-b", None).await?;
+    assert!(join(&queue, &queue_urn, "worker-b", None).await.is_err());
+
+    assert_eq!(fetch(&queue, &a, 2).await?, vec![1, 2]);
+    assert_eq!(fetch(&queue, &b, 10).await?, vec![3, 4]);
+    assert!(fetch(&queue, &a, 10).await?.is_empty());
+
+    // Each member acks only what it holds, so b's ack leaves 1 and 2 alone.
+    queue
+        .handle_commit_ack(&b, 4, RPCContext::default())
+        .await?;
+    let group = group_stats(&queue, &queue_urn).await?;
+    assert_eq!(group.name, "workers");
+    assert_eq!((group.lag, group.pending), (2, 2));
+    assert_eq!(
+        member_pending(&group),
+        vec![("worker-a".to_string(), 2), ("worker-b".to_string(), 0)]
+    );
+
+    queue
+        .handle_commit_ack(&a, 2, RPCContext::default())
+        .await?;
+    let group = group_stats(&queue, &queue_urn).await?;
+    assert_eq!((group.lag, group.pending), (0, 0));
+
+    assert!(
+        queue
+            .handle_seek(&a, SubPosition::Earliest, RPCContext::default())
+            .await
+            .is_err(),
+        "members cannot move the shared cursor"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn lapsed_member_messages_are_rebalanced() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = tempfile::TempDir::new()?;
+    let queue = SledMsgQueue::new_in_dir(temp.path())?;
+    let queue_urn = create_queue(&queue, "jobs").await?;
+    let a = join(&queue, &queue_urn, "worker-a", Some(200)).await?;
+    let b = join(&queue, &queue_urn, "worker-b", Some(60_000)).await?;
+
+    assert_eq!(fetch(&queue, &a, 2).await?, vec![1, 2]);
+    assert_eq!(fetch(&queue, &b, 1).await?, vec![3]);
+
+    // a stops calling in; once its lease lapses its messages go to b.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let group = group_stats(&queue, &queue_urn).await?;
+    assert_eq!(member_pending(&group), vec![("worker-b".to_string(), 1)]);
+    assert_eq!(fetch(&queue, &b, 10).await?, vec![1, 2, 4]);
+
+    // a comes back as a fresh member; its late ack does not touch b's work.
+    assert!(fetch(&queue, &a, 10).await?.is_empty());
+    queue
+        .handle_commit_ack(&a, 2, RPCContext::default())
+        .await?;
+    let group = group_stats(&queue, &queue_urn).await?;
+    assert_eq!((group.lag, group.pending), (4, 4));
+
+    queue
+        .handle_commit_ack(&b, 4, RPCContext::default())
+        .await?;
+    let group = group_stats(&queue, &queue_urn).await?;
+    assert_eq!((group.lag, group.pending), (0, 0));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn nack_and_leave_hand_messages_to_other_members() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = tempfile::TempDir::new()?;
+    let path = temp.path().to_path_buf();
+    let queue = SledMsgQueue::new_in_dir(&path)?;
+    let queue_urn = create_queue(&queue, "jobs").await?;
+    let a = join(&queue, &queue_urn, "worker-a", None).await?;
+    let b = join(&queue, &queue_urn, "worker-b", None).await?;
+
+    assert_eq!(fetch(&queue, &a, 2).await?, vec![1, 2]);
+    queue
+        .handle_nack(&a, 1, None, RPCContext::default())
+        .await?;
+    assert!(
+        queue
+            .handle_nack(&a, 1, None, RPCContext::default())
+            .await
+            .is_err(),
+        "a nacked message is no longer held by the member"
+    );
+    assert_eq!(fetch(&queue, &b, 1).await?, vec![1]);
+
+    // Group state survives a restart, including who holds what.
+    drop(queue);
+    let queue = SledMsgQueue::new_in_dir(&path)?;
+    queue.handle_unsubscribe(&a, RPCContext::default()).await?;
+    assert_eq!(fetch(&queue, &b, 10).await?, vec![2, 3, 4]);
+    let group = group_stats(&queue, &queue_urn).await?;
+    assert_eq!(member_pending(&group), vec![("worker-b".to_string(), 4)]);
+
+    // Deleting the queue removes its groups with it.
+    queue
+        .handle_delete_queue(&queue_urn, RPCContext::default())
+        .await?;
+    let queue_urn = create_queue(&queue, "jobs").await?;
+    let stats = queue
+        .handle_get_queue_stats(&queue_urn, RPCContext::default())
+        .await?;
+    assert!(stats.consumer_groups.is_empty());
+
+    Ok(())
+}
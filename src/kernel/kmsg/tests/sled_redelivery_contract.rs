@@ -0,0 +1,327 @@
+#[path = "../src/sled_msg_queue.rs"]
+mod sled_msg_queue;
+
+use buckyos_api::msg_queue::*;
+use kRPC::RPCContext;
+use sled_msg_queue::SledMsgQueue;
+use std::time::Duration;
+
+fn make_message(text: &str) -> Message {
+    Message::new(text.as_bytes().to_vec())
+}
+
+fn indexes(messages: &[Message]) -> Vec<MsgIndex> {
+    messages.iter().map(|msg| msg.index).collect()
+}
+
+async fn create_queue_with(
+    queue: &SledMsgQueue,
+    name: &str,
+    config: QueueConfig,
+) -> Result<QueueUrn, kRPC::RPCErrors> {
+    queue
+        .handle_create_queue(Some(name), "app", "owner", config, RPCContext::default())
+        .await
+}
+
+async fn post_all(
+    queue: &SledMsgQueue,
+    queue_urn: &str,
+    texts: &[&str],
+) -> Result<(), kRPC::RPCErrors> {
+    for text in texts {
+        queue
+            .handle_post_message(queue_urn, make_message(text), RPCContext::default())
+            .await?;
+    }
+    Ok(())
+}
+
+async fn subscribe(
+    queue: &SledMsgQueue,
+    queue_urn: &str,
+    sub_id: &str,
+) -> Result<SubscriptionId, kRPC::RPCErrors> {
+    queue
+        .handle_subscribe(
+            queue_urn,
+            "user",
+            "app",
+            Some(sub_id.to_string()),
+            SubPosition::Earliest,
+            RPCContext::default(),
+        )
+        .await
+}
+
+async fn fetch(queue: &SledMsgQueue, sub_id: &str) -> Result<Vec<MsgIndex>, kRPC::RPCErrors> {
+    let messages = queue
+        .handle_fetch_messages(sub_id, 10, false, None, RPCContext::default())
+        .await?;
+    Ok(indexes(&messages))
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn visibility_timeout_hides_unacked_messages_until_it_expires()
+-> Result<(), Box<dyn std::error::Error>> {
+    let temp = tempfile::TempDir::new()?;
+    let path = temp.path().to_path_buf();
+    let queue = SledMsgQueue::new_in_dir(&path)?;
+    let config = QueueConfig {
+        visibility_timeout_ms: Some(500),
+        ..QueueConfig::default()
+    };
+    let queue_urn = create_queue_with(&queue, "visibility", config).await?;
+    post_all(&queue, &queue_urn, &["m1", "m2"]).await?;
+    let sub_id = subscribe(&queue, &queue_urn, "visibility-sub").await?;
+
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![1, 2]);
+    assert!(fetch(&queue, &sub_id).await?.is_empty());
+
+    // In-flight state is part of the subscription and survives a restart.
+    drop(queue);
+    let queue = SledMsgQueue::new_in_dir(&path)?;
+    assert!(fetch(&queue, &sub_id).await?.is_empty());
+
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![1, 2]);
+    queue
+        .handle_commit_ack(&sub_id, 2, RPCContext::default())
+        .await?;
+
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert!(fetch(&queue, &sub_id).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn nack_redelivers_after_its_delay_on_a_plain_queue() -> Result<(), Box<dyn std::error::Error>>
+{
+    let temp = tempfile::TempDir::new()?;
+    let queue = SledMsgQueue::new_in_dir(temp.path())?;
+    let queue_urn = create_queue_with(&queue, "nack", QueueConfig::default()).await?;
+    post_all(&queue, &queue_urn, &["m1", "m2", "m3"]).await?;
+    let sub_id = subscribe(&queue, &queue_urn, "nack-sub").await?;
+
+    // Without a visibility timeout unacked messages keep coming back.
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![1, 2, 3]);
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![1, 2, 3]);
+
+    queue
+        .handle_nack(&sub_id, 2, Some(300), RPCContext::default())
+        .await?;
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![1, 3]);
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![1, 2, 3]);
+
+    queue
+        .handle_commit_ack(&sub_id, 1, RPCContext::default())
+        .await?;
+    assert!(
+        queue
+            .handle_nack(&sub_id, 1, None, RPCContext::default())
+            .await
+            .is_err(),
+        "an acked message cannot be nacked"
+    );
+    assert!(
+        queue
+            .handle_nack(&sub_id, 99, None, RPCContext::default())
+            .await
+            .is_err(),
+        "a message that does not exist cannot be nacked"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn ack_above_a_nacked_message_does_not_drop_it() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = tempfile::TempDir::new()?;
+    let path = temp.path().to_path_buf();
+    let queue = SledMsgQueue::new_in_dir(&path)?;
+    let queue_urn = create_queue_with(&queue, "ack-above-nack", QueueConfig::default()).await?;
+    post_all(&queue, &queue_urn, &["m1", "m2", "m3", "m4"]).await?;
+    let sub_id = subscribe(&queue, &queue_urn, "ack-above-nack-sub").await?;
+
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![1, 2, 3, 4]);
+    queue
+        .handle_nack(&sub_id, 2, Some(300), RPCContext::default())
+        .await?;
+    // Acking 3 covers 1 and 3, but 2 still waits for its redelivery.
+    queue
+        .handle_commit_ack(&sub_id, 3, RPCContext::default())
+        .await?;
+    assert!(
+        queue
+            .handle_nack(&sub_id, 3, None, RPCContext::default())
+            .await
+            .is_err(),
+        "an acked message above the cursor cannot be nacked"
+    );
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![4]);
+
+    drop(queue);
+    let queue = SledMsgQueue::new_in_dir(&path)?;
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![2, 4]);
+
+    // Once 2 is acked the cursor moves past the settled 3 as well.
+    queue
+        .handle_commit_ack(&sub_id, 2, RPCContext::default())
+        .await?;
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![4]);
+    queue
+        .handle_commit_ack(&sub_id, 4, RPCContext::default())
+        .await?;
+    assert!(fetch(&queue, &sub_id).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn nack_past_the_delivery_limit_moves_message_to_dead_letter_queue()
+-> Result<(), Box<dyn std::error::Error>> {
+    let temp = tempfile::TempDir::new()?;
+    let queue = SledMsgQueue::new_in_dir(temp.path())?;
+    let dlq_urn = create_queue_with(&queue, "orders-dlq", QueueConfig::default()).await?;
+    let config = QueueConfig {
+        visibility_timeout_ms: Some(60_000),
+        max_delivery_attempts: Some(2),
+        dead_letter_queue: Some(dlq_urn.clone()),
+        ..QueueConfig::default()
+    };
+    let queue_urn = create_queue_with(&queue, "orders", config).await?;
+    post_all(&queue, &queue_urn, &["m1", "m2", "m3"]).await?;
+    let sub_id = subscribe(&queue, &queue_urn, "orders-sub").await?;
+
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![1, 2, 3]);
+    queue
+        .handle_nack(&sub_id, 2, None, RPCContext::default())
+        .await?;
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![2]);
+    queue
+        .handle_nack(&sub_id, 2, None, RPCContext::default())
+        .await?;
+    assert!(fetch(&queue, &sub_id).await?.is_empty());
+
+    let dead = queue
+        .handle_read_message(&dlq_urn, 1, 10, RPCContext::default())
+        .await?;
+    assert_eq!(dead.len(), 1);
+    assert_eq!(dead[0].payload, b"m2".to_vec());
+    let header = |name: &str| dead[0].headers.get(name).map(String::as_str);
+    assert_eq!(
+        header(DEAD_LETTER_HEADER_SOURCE_QUEUE),
+        Some(queue_urn.as_str())
+    );
+    assert_eq!(header(DEAD_LETTER_HEADER_SOURCE_INDEX), Some("2"));
+    assert_eq!(header(DEAD_LETTER_HEADER_SUBSCRIPTION), Some("orders-sub"));
+    assert_eq!(header(DEAD_LETTER_HEADER_ATTEMPTS), Some("2"));
+
+    // The dead-lettered message no longer holds the cursor back.
+    queue
+        .handle_commit_ack(&sub_id, 1, RPCContext::default())
+        .await?;
+    assert!(
+        queue
+            .handle_nack(&sub_id, 2, None, RPCContext::default())
+            .await
+            .is_err()
+    );
+    queue
+        .handle_nack(&sub_id, 3, None, RPCContext::default())
+        .await?;
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![3]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn expired_visibility_past_the_delivery_limit_is_dead_lettered()
+-> Result<(), Box<dyn std::error::Error>> {
+    let temp = tempfile::TempDir::new()?;
+    let queue = SledMsgQueue::new_in_dir(temp.path())?;
+    let dlq_urn = create_queue_with(&queue, "jobs-dlq", QueueConfig::default()).await?;
+    let config = QueueConfig {
+        visibility_timeout_ms: Some(200),
+        max_delivery_attempts: Some(1),
+        dead_letter_queue: Some(dlq_urn.clone()),
+        ..QueueConfig::default()
+    };
+    let queue_urn = create_queue_with(&queue, "jobs", config).await?;
+    post_all(&queue, &queue_urn, &["crashy"]).await?;
+    let sub_id = subscribe(&queue, &queue_urn, "jobs-sub").await?;
+
+    // The consumer takes the message and never answers.
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![1]);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(fetch(&queue, &sub_id).await?.is_empty());
+
+    let stats = queue
+        .handle_get_queue_stats(&dlq_urn, RPCContext::default())
+        .await?;
+    assert_eq!(stats.message_count, 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn delivery_limit_without_dead_letter_queue_drops_the_message()
+-> Result<(), Box<dyn std::error::Error>> {
+    let temp = tempfile::TempDir::new()?;
+    let queue = SledMsgQueue::new_in_dir(temp.path())?;
+    let config = QueueConfig {
+        max_delivery_attempts: Some(1),
+        ..QueueConfig::default()
+    };
+    let queue_urn = create_queue_with(&queue, "drop", config).await?;
+    post_all(&queue, &queue_urn, &["m1", "m2"]).await?;
+    let sub_id = subscribe(&queue, &queue_urn, "drop-sub").await?;
+
+    assert_eq!(fetch(&queue, &sub_id).await?, vec![1, 2]);
+    queue
+        .handle_nack(&sub_id, 1, None, RPCContext::default())
+        .await?;
+    // Message 2 was delivered once and not acked either, so this fetch
+    // exhausts it as well.
+    assert!(fetch(&queue, &sub_id).await?.is_empty());
+
+    // The queue itself is untouched; only the subscription moved on.
+    let stats = queue
+        .handle_get_queue_stats(&queue_urn, RPCContext::default())
+        .await?;
+    assert_eq!(stats.message_count, 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn invalid_redelivery_config_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    let temp = tempfile::TempDir::new()?;
+    let queue = SledMsgQueue::new_in_dir(temp.path())?;
+    let zero_attempts = QueueConfig {
+        max_delivery_attempts: Some(0),
+        ..QueueConfig::default()
+    };
+    assert!(
+        create_queue_with(&queue, "zero", zero_attempts)
+            .await
+            .is_err()
+    );
+
+    let queue_urn = create_queue_with(&queue, "self", QueueConfig::default()).await?;
+    let self_dlq = QueueConfig {
+        dead_letter_queue: Some(queue_urn.clone()),
+        ..QueueConfig::default()
+    };
+    assert!(
+        queue
+            .handle_update_queue_config(&queue_urn, self_dlq, RPCContext::default())
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
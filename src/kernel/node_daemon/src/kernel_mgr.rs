@@ -16,6 +16,10 @@ use std::path::PathBuf;
 
 use crate::run_item::*;
 
+const SLOG_DAEMON_SERVICE_NAME: &str = "slog_daemon";
+const SLOG_DEVICE_KEY_FILE_ENV_KEY: &str = "SLOG_DEVICE_KEY_FILE";
+const SLOG_NODE_ID_ENV_KEY: &str = "SLOG_NODE_ID";
+
 pub struct KernelServiceRunItem {
     pub service_name: String,
     pub pkg_desc: SubPkgDesc,
@@ -225,6 +229,24 @@ impl RunItemControl for KernelServiceRunItem {
             std::env::set_var(env_key.as_str(), device_session_token_jwt);
         }
 
+        //slog_daemon 用本机设备私钥签名日志上传,slog_server 按 zone 里的设备文档验签
+        if self.service_name == SLOG_DAEMON_SERVICE_NAME {
+            let identity_paths = device_identity_paths(&device_doc.id).map_err(|err| {
+                error!(
+                    "locate device key for {} failed! {}",
+                    self.service_name, err
+                );
+                ControlRuntItemErrors::ExecuteError("start".to_string(), err)
+            })?;
+            unsafe {
+                std::env::set_var(
+                    SLOG_DEVICE_KEY_FILE_ENV_KEY,
+                    identity_paths.authentication_private_key,
+                );
+                std::env::set_var(SLOG_NODE_ID_ENV_KEY, device_doc.name.as_str());
+            }
+        }
+
         let result = self.service_pkg.start(params).await.map_err(|err| {
             return ControlRuntItemErrors::ExecuteError("start".to_string(), err.to_string());
         })?;
@@ -1,20 +1,95 @@
+use async_trait::async_trait;
 use buckyos_api::{
-    Event, SharedKEventRingBuffer, KEVENT_SERVICE_MAIN_PORT, KEVENT_SERVICE_NATIVE_PORT,
+    get_buckyos_api_runtime, Event, KEventError, KEventResult, SharedKEventRingBuffer,
+    KEVENT_SERVICE_MAIN_PORT, KEVENT_SERVICE_NATIVE_PORT,
 };
 use buckyos_http_server::Runner;
-use kevent::{run_native_tcp_server, KEventHttpServer, KEventService};
-use log::{error, info};
+use buckyos_kit::get_buckyos_service_local_data_dir;
+use kevent::{
+    run_native_tcp_server, KEventAccess, KEventAuthorizer, KEventHttpServer, KEventJournal,
+    KEventPrincipal, KEventService, DEFAULT_DAEMON_READER_CAPACITY, DEFAULT_JOURNAL_MAX_EVENTS,
+};
+use log::{error, info, warn};
 use std::io;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::TcpListener;
 
 const SHARED_RING_DRAIN_BATCH: usize = 128;
+const KEVENT_JOURNAL_FILE: &str = "kevent_journal.jsonl";
 #[cfg(target_os = "linux")]
 const SHARED_RING_WAIT_TIMEOUT_MS: u64 = 500;
 #[cfg(not(target_os = "linux"))]
 const SHARED_RING_WAIT_TIMEOUT_MS: u64 = 1;
 
+/// The daemon keeps its replay window on disk so bridge readers can resume
+/// across a node-daemon restart. A journal that cannot be opened costs only
+/// that: events still flow, resume just stops at the restart.
+pub fn new_node_kevent_service(source_node: &str) -> KEventService {
+    let path = get_buckyos_service_local_data_dir("node_daemon").join(KEVENT_JOURNAL_FILE);
+    let journal = match KEventJournal::open(&path, DEFAULT_JOURNAL_MAX_EVENTS) {
+        Ok(journal) => journal,
+        Err(err) => {
+            error!(
+                "kevent journal disabled, keeping replay in memory only: {}",
+                err
+            );
+            KEventJournal::in_memory(DEFAULT_JOURNAL_MAX_EVENTS)
+        }
+    };
+    KEventService::new_with_journal(source_node, DEFAULT_DAEMON_READER_CAPACITY, journal)
+}
+
+/// Bridge and HTTP callers are checked against the zone RBAC policy with
+/// eventids mapped to `obj://kevent/<eventid>`. Kernel processes publishing
+/// through the shared ring are not: they already run as the node.
+struct RuntimeKEventAuthorizer;
+
+#[async_trait]
+impl KEventAuthorizer for RuntimeKEventAuthorizer {
+    async fn authenticate(&self, session_token: &str) -> KEventResult<KEventPrincipal> {
+        let runtime = get_buckyos_api_runtime()
+            .map_err(|err| KEventError::PermissionDenied(err.to_string()))?;
+        let token = runtime
+            .verify_trusted_session_token(session_token)
+            .await
+            .map_err(|err| KEventError::PermissionDenied(err.to_string()))?;
+        let (user_id, app_id) = token
+            .get_subs()
+            .map_err(|err| KEventError::PermissionDenied(err.to_string()))?;
+        Ok(KEventPrincipal {
+            user_id,
+            app_id,
+            sudo: token.sudo,
+        })
+    }
+
+    async fn authorize(
+        &self,
+        principal: &KEventPrincipal,
+        access: KEventAccess,
+        target: &str,
+    ) -> bool {
+        let runtime = match get_buckyos_api_runtime() {
+            Ok(runtime) => runtime,
+            Err(err) => {
+                warn!("kevent rbac check without runtime, denying: {}", err);
+                return false;
+            }
+        };
+        runtime
+            .enforce_subject(
+                &principal.user_id,
+                &principal.app_id,
+                principal.sudo,
+                &format!("obj://kevent{}", target),
+                access.action(),
+            )
+            .await
+            .is_ok()
+    }
+}
+
 pub async fn start_node_kevent_service(service: Arc<KEventService>) {
     info!(
         "start kevent service on http port {} and native tcp port {} for source_node={}",
@@ -33,6 +108,9 @@ pub async fn start_node_kevent_service(service: Arc<KEventService>) {
             error!("kevent shared ring disabled: {}", err);
         }
     }
+    service
+        .set_authorizer(Arc::new(RuntimeKEventAuthorizer))
+        .await;
 
     let http_server = Arc::new(KEventHttpServer::new(service.clone()));
     let runner = Runner::new(KEVENT_SERVICE_MAIN_PORT);
@@ -721,7 +721,7 @@ static KEVENT_SERVICE: OnceLock<Arc<kevent::KEventService>> = OnceLock::new();
 
 fn get_kevent_service(source_node: &str) -> Arc<kevent::KEventService> {
     KEVENT_SERVICE
-        .get_or_init(|| Arc::new(kevent::KEventService::new(source_node.to_string())))
+        .get_or_init(|| Arc::new(new_node_kevent_service(source_node)))
         .clone()
 }
 
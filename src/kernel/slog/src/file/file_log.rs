@@ -716,6 +716,9 @@ mod tests {
             file: None,
             line: None,
             content: content.to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
         }
     }
 
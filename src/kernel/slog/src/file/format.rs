@@ -1,31 +1,89 @@
 use crate::system_log::{LogLevel, LogTimeHelper, SystemLogRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
+/// Marker of the optional structured section placed right before the content:
+/// `time [level] [target] <file:line> @{"trace_id":..,"fields":{..}} content`
+const STRUCTURED_SECTION_MARKER: char = '@';
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct StructuredSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trace_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span_id: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    fields: BTreeMap<String, String>,
+}
+
 pub struct SystemLogRecordLineFormatter;
 
 impl SystemLogRecordLineFormatter {
     pub fn format_record(record: &SystemLogRecord) -> String {
+        let structured = Self::format_structured_section(record);
         if record.has_file_pos() {
             format!(
-                "{} [{}] [{}] <{}:{}> {}\n",
+                "{} [{}] [{}] <{}:{}> {}{}\n",
                 record.time_string(),
                 record.level(),
                 record.target(),
                 record.file(),
                 record.line(),
+                structured,
                 record.content(),
             )
         } else {
             format!(
-                "{} [{}] [{}] {}\n",
+                "{} [{}] [{}] {}{}\n",
                 record.time_string(),
                 record.level(),
                 record.target(),
+                structured,
                 record.content(),
             )
         }
     }
 
+    // Empty for records without structured data, so their lines keep the plain format.
+    fn format_structured_section(record: &SystemLogRecord) -> String {
+        if !record.has_structured_fields() {
+            return String::new();
+        }
+
+        let section = StructuredSection {
+            trace_id: record.trace_id.clone(),
+            span_id: record.span_id.clone(),
+            fields: record.fields.clone(),
+        };
+        match serde_json::to_string(&section) {
+            Ok(json) => format!("{}{} ", STRUCTURED_SECTION_MARKER, json),
+            Err(_) => String::new(),
+        }
+    }
+
+    // Split a leading structured section off the content. Content that merely
+    // looks like one is left untouched.
+    fn parse_structured_section(content: &str) -> (StructuredSection, &str) {
+        let Some(json_part) = content.strip_prefix(STRUCTURED_SECTION_MARKER) else {
+            return (StructuredSection::default(), content);
+        };
+        if !json_part.starts_with('{') {
+            return (StructuredSection::default(), content);
+        }
+
+        let mut stream =
+            serde_json::Deserializer::from_str(json_part).into_iter::<StructuredSection>();
+        match stream.next() {
+            Some(Ok(section)) => {
+                let rest = &json_part[stream.byte_offset()..];
+                (section, rest.strip_prefix(' ').unwrap_or(rest))
+            }
+            _ => (StructuredSection::default(), content),
+        }
+    }
+
     pub fn parse_record(line: &str) -> Result<SystemLogRecord, String> {
         // println!("Parsing log line: {}", line);
         let parts: Vec<&str> = line.splitn(4, ' ').collect();
@@ -44,7 +102,7 @@ impl SystemLogRecordLineFormatter {
 
         // Check if there is file and line info
         let content_part = parts[3].trim();
-        let (file, line, content) = if content_part.starts_with('<') {
+        let (file, line, content_part) = if content_part.starts_with('<') {
             // Has file and line info
             let end_pos = content_part.find('>').ok_or_else(|| {
                 let msg = format!("invalid log line format, missing '>': {}", line);
@@ -54,15 +112,13 @@ impl SystemLogRecordLineFormatter {
             let (file, line_num) = Self::parse_file_line(file_line_str)?;
             let file = Some(file);
             let line = Some(line_num);
-            let content = content_part[end_pos + 1..]
-                .trim()
-                .trim_end_matches('\n')
-                .to_string();
+            let content = content_part[end_pos + 1..].trim().trim_end_matches('\n');
             (file, line, content)
         } else {
             // No file and line info
-            (None, None, content_part.trim_end_matches('\n').to_string())
+            (None, None, content_part.trim_end_matches('\n'))
         };
+        let (structured, content) = Self::parse_structured_section(content_part);
 
         let record = SystemLogRecord {
             level,
@@ -70,7 +126,10 @@ impl SystemLogRecordLineFormatter {
             time,
             file,
             line,
-            content,
+            content: content.to_string(),
+            trace_id: structured.trace_id,
+            span_id: structured.span_id,
+            fields: structured.fields,
         };
 
         Ok(record)
@@ -111,6 +170,9 @@ mod tests {
             file: Some("test_file.rs".to_string()),
             line: Some(42),
             content: "This is a test log message.".to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
         };
 
         let formatted = SystemLogRecordLineFormatter::format_record(&record);
@@ -134,6 +196,9 @@ mod tests {
             file: Some(r"C:\work\buckyos\src\main.rs".to_string()),
             line: Some(128),
             content: "windows path test".to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
         };
 
         let formatted = SystemLogRecordLineFormatter::format_record(&record);
@@ -154,6 +219,9 @@ mod tests {
             file: None,
             line: None,
             content: "content without file pos".to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
         };
 
         let formatted = SystemLogRecordLineFormatter::format_record(&record);
@@ -174,6 +242,9 @@ mod tests {
             file: Some("/var/log/archive:v1/app.rs".to_string()),
             line: Some(9),
             content: "colon in file path".to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
         };
 
         let formatted = SystemLogRecordLineFormatter::format_record(&record);
@@ -209,6 +280,47 @@ mod tests {
         assert!(ret.unwrap_err().contains("invalid file and line format"));
     }
 
+    #[test]
+    fn test_format_and_parse_record_with_structured_fields() {
+        let record = SystemLogRecord::easy_log(LogLevel::Info, "rpc done".to_string())
+            .with_trace(Some("trace-1".to_string()), Some("span 2".to_string()))
+            .with_field("method", "sys_config_get")
+            .with_field("peer", "node-a \"x\"");
+
+        let formatted = SystemLogRecordLineFormatter::format_record(&record);
+        assert!(formatted.contains(r#"@{"trace_id":"trace-1","span_id":"span 2","#));
+        let parsed = SystemLogRecordLineFormatter::parse_record(&formatted).unwrap();
+        assert_eq!(parsed.trace_id.as_deref(), Some("trace-1"));
+        assert_eq!(parsed.span_id.as_deref(), Some("span 2"));
+        assert_eq!(parsed.fields, record.fields);
+        assert_eq!(parsed.content, "rpc done");
+
+        let record = SystemLogRecord {
+            file: Some("main.rs".to_string()),
+            line: Some(7),
+            ..record
+        };
+        let formatted = SystemLogRecordLineFormatter::format_record(&record);
+        let parsed = SystemLogRecordLineFormatter::parse_record(&formatted).unwrap();
+        assert_eq!(parsed.line, Some(7));
+        assert_eq!(parsed.trace_id.as_deref(), Some("trace-1"));
+        assert_eq!(parsed.content, "rpc done");
+    }
+
+    #[test]
+    fn test_parse_record_keeps_content_that_only_looks_structured() {
+        let line =
+            "2024-01-01_00:00:00.000_+00:00 [info] [test] <a.rs:1> @{\"user\":\"bob\"} login";
+        let parsed = SystemLogRecordLineFormatter::parse_record(line).unwrap();
+        assert_eq!(parsed.content, "@{\"user\":\"bob\"} login");
+        assert!(parsed.fields.is_empty());
+        assert_eq!(parsed.trace_id, None);
+
+        let line = "2024-01-01_00:00:00.000_+00:00 [info] [test] @{broken";
+        let parsed = SystemLogRecordLineFormatter::parse_record(line).unwrap();
+        assert_eq!(parsed.content, "@{broken");
+    }
+
     #[test]
     fn test_parse_record_rejects_invalid_file_line_missing_line() {
         let line = "2024-01-01_00:00:00.000_+00:00 [info] [test] <C:\\a\\b.rs:> bad";
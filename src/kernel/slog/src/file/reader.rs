@@ -569,6 +569,9 @@ mod tests {
             file: Some("reader.rs".to_string()),
             line: Some(42),
             content: content.to_string(),
+            trace_id: None,
+            span_id: None,
+            fields: Default::default(),
         }
     }
 
@@ -0,0 +1,207 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Separates the trace id from the span id in a kRPC trace string, which
+/// carries both in the request's single `trace_id` slot.
+pub const RPC_TRACE_SPAN_SEPARATOR: char = '/';
+
+thread_local! {
+    static CURRENT_LOG_CONTEXT: RefCell<LogContext> = RefCell::new(LogContext::default());
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Trace and span of the operation being served. Records logged without
+/// their own `trace_id` / `span_id` key values inherit them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogContext {
+    pub trace_id: Option<String>,
+    pub span_id: Option<String>,
+}
+
+impl LogContext {
+    pub fn new(trace_id: impl Into<String>, span_id: Option<String>) -> Self {
+        Self {
+            trace_id: Some(trace_id.into()),
+            span_id,
+        }
+    }
+
+    pub fn current() -> Self {
+        CURRENT_LOG_CONTEXT.with(|current| current.borrow().clone())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trace_id.is_none() && self.span_id.is_none()
+    }
+
+    /// Parse `trace[/span]` as sent in a kRPC request.
+    pub fn from_rpc_trace(raw: &str) -> Self {
+        let raw = raw.trim();
+        let (trace_id, span_id) = match raw.split_once(RPC_TRACE_SPAN_SEPARATOR) {
+            Some((trace_id, span_id)) => (trace_id, Some(span_id)),
+            None => (raw, None),
+        };
+        Self {
+            trace_id: Some(trace_id.to_string()).filter(|id| !id.is_empty()),
+            span_id: span_id.map(str::to_string).filter(|id| !id.is_empty()),
+        }
+    }
+
+    /// Context for serving one kRPC call: the caller's trace and the span it
+    /// assigned to the call, or a new trace when the caller sent none.
+    pub fn for_rpc_call(raw: Option<&str>) -> Self {
+        let ctx = raw.map(Self::from_rpc_trace).unwrap_or_default();
+        match ctx.trace_id {
+            Some(_) => Self {
+                span_id: ctx.span_id.or_else(|| Some(new_log_id())),
+                ..ctx
+            },
+            None => Self::new(new_log_id(), Some(new_log_id())),
+        }
+    }
+
+    /// Trace string for an outgoing kRPC call: the same trace with a new span
+    /// for the call. `None` outside any trace.
+    pub fn child_rpc_trace(&self) -> Option<String> {
+        let trace_id = self.trace_id.as_ref()?;
+        Some(format!(
+            "{}{}{}",
+            trace_id,
+            RPC_TRACE_SPAN_SEPARATOR,
+            new_log_id()
+        ))
+    }
+
+    /// Make this the current context of the thread until the guard drops.
+    pub fn enter(self) -> LogContextGuard {
+        let previous = CURRENT_LOG_CONTEXT.with(|current| current.replace(self));
+        LogContextGuard {
+            previous: Some(previous),
+        }
+    }
+
+    /// Run `future` with this as the current context on every poll, so the
+    /// context follows the task across worker threads.
+    pub fn scope<F: Future>(self, future: F) -> LogContextFuture<F> {
+        LogContextFuture {
+            context: self,
+            inner: Box::pin(future),
+        }
+    }
+}
+
+/// Restores the previous context when dropped.
+pub struct LogContextGuard {
+    previous: Option<LogContext>,
+}
+
+impl Drop for LogContextGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            CURRENT_LOG_CONTEXT.with(|current| current.replace(previous));
+        }
+    }
+}
+
+pub struct LogContextFuture<F: Future> {
+    context: LogContext,
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for LogContextFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let _guard = this.context.clone().enter();
+        this.inner.as_mut().poll(cx)
+    }
+}
+
+/// Hex id unique within the process and unlikely to collide across nodes.
+pub fn new_log_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    let seq = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    format!(
+        "{:016x}{:08x}",
+        nanos ^ (std::process::id() as u64).rotate_left(32),
+        seq as u32
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rpc_trace_round_trip() {
+        let ctx = LogContext::from_rpc_trace("trace-1/span-2");
+        assert_eq!(ctx, LogContext::new("trace-1", Some("span-2".to_string())));
+        assert_eq!(
+            LogContext::from_rpc_trace("trace-1"),
+            LogContext::new("trace-1", None)
+        );
+        assert!(LogContext::from_rpc_trace("  ").is_empty());
+
+        let child = ctx.child_rpc_trace().unwrap();
+        let child = LogContext::from_rpc_trace(&child);
+        assert_eq!(child.trace_id.as_deref(), Some("trace-1"));
+        assert_ne!(child.span_id.as_deref(), Some("span-2"));
+        assert!(LogContext::default().child_rpc_trace().is_none());
+    }
+
+    #[test]
+    fn test_rpc_call_context_starts_trace_when_caller_sent_none() {
+        let served = LogContext::for_rpc_call(Some("trace-1/span-2"));
+        assert_eq!(
+            served,
+            LogContext::new("trace-1", Some("span-2".to_string()))
+        );
+
+        let adopted = LogContext::for_rpc_call(Some("trace-1"));
+        assert_eq!(adopted.trace_id.as_deref(), Some("trace-1"));
+        assert!(adopted.span_id.is_some());
+
+        let started = LogContext::for_rpc_call(None);
+        assert!(started.trace_id.is_some() && started.span_id.is_some());
+    }
+
+    #[test]
+    fn test_enter_restores_previous_context() {
+        let outer = LogContext::new("outer", None).enter();
+        {
+            let _inner = LogContext::new("inner", Some("s".to_string())).enter();
+            assert_eq!(LogContext::current().trace_id.as_deref(), Some("inner"));
+        }
+        assert_eq!(LogContext::current().trace_id.as_deref(), Some("outer"));
+        drop(outer);
+        assert!(LogContext::current().is_empty());
+    }
+
+    #[test]
+    fn test_scope_sets_context_only_while_polling() {
+        let ctx = LogContext::new("trace-scope", Some("span-scope".to_string()));
+        let seen = block_on(ctx.clone().scope(async { LogContext::current() }));
+        assert_eq!(seen, ctx);
+        assert!(LogContext::current().is_empty());
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = std::task::Waker::noop();
+        let mut cx = Context::from_waker(waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use super::constants::*;
 use super::log_config::*;
+use super::redact::LogRedactor;
 use super::target::*;
 use flexi_logger::{Cleanup, Criterion, DeferredNow, LogSpecification, Logger, Naming, Record};
 use log::{Log, Metadata};
@@ -190,6 +191,7 @@ pub struct FlexiLogger {
     max_level: LogLevel,
 
     targets: Vec<Box<dyn SystemLogTarget>>,
+    redactor: Arc<LogRedactor>,
 }
 
 impl FlexiLogger {
@@ -226,6 +228,7 @@ impl FlexiLogger {
             global_logger,
             module_loggers,
             targets,
+            redactor: Arc::new(LogRedactor::new(&config.redaction)),
         })
     }
 
@@ -233,6 +236,10 @@ impl FlexiLogger {
         self.max_level
     }
 
+    pub fn redactor(&self) -> Arc<LogRedactor> {
+        self.redactor.clone()
+    }
+
     fn get_logger(&self, target: &str) -> &FlexiModuleLogger {
         let mod_name = match target.find("::") {
             Some(pos) => &target[..pos],
@@ -259,15 +266,38 @@ impl Log for FlexiLogger {
 
         let logger = self.get_logger(target);
         if logger.check_level(record.metadata().level()) {
-            //println!("will output");
-            logger.logger.log(record);
-
-            // If there are other targets, output to the targets
-            if !self.targets.is_empty() {
-                let record = SystemLogRecord::new(record);
-                for target in &self.targets {
-                    target.log(&record);
+            if self.redactor.is_empty() {
+                //println!("will output");
+                logger.logger.log(record);
+
+                // If there are other targets, output to the targets
+                if !self.targets.is_empty() {
+                    let record = SystemLogRecord::new(record);
+                    for target in &self.targets {
+                        target.log(&record);
+                    }
                 }
+                return;
+            }
+
+            // Redact once, the same masked content goes to the log files and the targets
+            let mut system_record = SystemLogRecord::new(record);
+            if self.redactor.redact_record(&mut system_record) {
+                logger.logger.log(
+                    &Record::builder()
+                        .args(format_args!("{}", system_record.content))
+                        .metadata(record.metadata().clone())
+                        .module_path(record.module_path())
+                        .file(record.file())
+                        .line(record.line())
+                        .build(),
+                );
+            } else {
+                logger.logger.log(record);
+            }
+
+            for target in &self.targets {
+                target.log(&system_record);
             }
         }
     }
@@ -348,4 +378,57 @@ mod tests {
 
         std::fs::remove_dir_all(&dir).unwrap();
     }
+
+    struct CaptureTarget {
+        records: Arc<std::sync::Mutex<Vec<SystemLogRecord>>>,
+    }
+
+    impl SystemLogTarget for CaptureTarget {
+        fn log(&self, record: &SystemLogRecord) {
+            self.records.lock().unwrap().push(record.clone());
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[test]
+    fn test_flexi_logger_redacts_records_before_targets() {
+        let dir = new_temp_log_dir("redaction");
+        let mut config = LogConfig::new(dir.clone());
+        config.global.level = LogLevel::Info;
+        config.global.console = LogLevel::Off;
+
+        let records = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let target = Box::new(CaptureTarget {
+            records: records.clone(),
+        }) as Box<dyn SystemLogTarget>;
+        let logger = FlexiLogger::new(&config, vec![target]).unwrap();
+
+        let kvs: &[(&str, &str)] = &[("peer", "bob@example.com")];
+        logger.log(
+            &Record::builder()
+                .args(format_args!(
+                    "aicc provider key sk-0123456789abcdefghij for alice@example.com"
+                ))
+                .level(Level::Info)
+                .target("svc")
+                .key_values(&kvs)
+                .build(),
+        );
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].content,
+            "aicc provider key [REDACTED:secret_key] for [REDACTED:email]"
+        );
+        assert_eq!(records[0].fields["peer"], "[REDACTED:email]");
+        let stats = logger.redactor().stats();
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.rules["email"], 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,4 +1,5 @@
 use super::LogLevel;
+use super::RedactionDetector;
 use crate::util::TomlHelper;
 
 use std::path::{Path, PathBuf};
@@ -230,12 +231,110 @@ impl LogModuleConfig {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct LogRedactionRule {
+    pub name: String,
+
+    // Regex, capture groups can be used in the replacement as $1 or ${name}
+    pub pattern: String,
+
+    // If None, matches are replaced by DEFAULT_REDACTION_REPLACEMENT
+    pub replacement: Option<String>,
+}
+
+/// Masking of secrets before records reach disk, loaded from the `[redaction]` table:
+///
+/// ```toml
+/// [redaction]
+/// enable = true
+/// detectors = ["jwt", "bearer_token", "secret_key", "email", "url_password"]
+///
+/// [[redaction.rules]]
+/// name = "user_id"
+/// pattern = "(uid=)\\d+"
+/// replacement = "${1}***"
+/// ```
+#[derive(Clone, Debug)]
+pub struct LogRedactionConfig {
+    pub enable: bool,
+
+    // Built-in detectors, all of them by default
+    pub detectors: Vec<RedactionDetector>,
+
+    pub rules: Vec<LogRedactionRule>,
+}
+
+impl Default for LogRedactionConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            detectors: RedactionDetector::ALL.to_vec(),
+            rules: vec![],
+        }
+    }
+}
+
+impl LogRedactionConfig {
+    pub fn load(&mut self, node: &toml::value::Table) {
+        for (k, v) in node {
+            match k.as_str() {
+                "enable" => match TomlHelper::decode_from_boolean(v) {
+                    Ok(v) => self.enable = v,
+                    Err(e) => println!("decode log.toml redaction enable field error! {}", e),
+                },
+
+                "detectors" => {
+                    let Some(list) = v.as_array() else {
+                        println!("invalid redaction detectors, except array: {}", v);
+                        continue;
+                    };
+                    self.detectors = list
+                        .iter()
+                        .filter_map(|item| TomlHelper::decode_from_string(item).ok())
+                        .collect();
+                }
+
+                "rules" => {
+                    let Some(list) = v.as_array() else {
+                        println!("invalid redaction rules, except array: {}", v);
+                        continue;
+                    };
+                    for item in list {
+                        match Self::load_rule(item) {
+                            Ok(rule) => self.rules.push(rule),
+                            Err(e) => println!("decode log.toml redaction rule error! {}", e),
+                        }
+                    }
+                }
+
+                v => {
+                    println!("unknown redaction config field: {}", v);
+                }
+            }
+        }
+    }
+
+    fn load_rule(node: &toml::Value) -> Result<LogRedactionRule, String> {
+        let node = node
+            .as_table()
+            .ok_or_else(|| format!("invalid redaction rule, except table: {}", node))?;
+
+        Ok(LogRedactionRule {
+            name: TomlHelper::decode_string_field(node, "name")?,
+            pattern: TomlHelper::decode_string_field(node, "pattern")?,
+            replacement: TomlHelper::decode_option_string_field(node, "replacement")?,
+        })
+    }
+}
+
 pub struct LogConfig {
     pub log_dir: PathBuf,
     pub basename: String,
 
     pub global: LogModuleConfig,
     pub modules: HashMap<String, LogModuleConfig>,
+
+    pub redaction: LogRedactionConfig,
 }
 
 impl LogConfig {
@@ -253,6 +352,7 @@ impl LogConfig {
             global: LogModuleConfig::new_default("global"),
             modules: HashMap::new(),
             basename,
+            redaction: LogRedactionConfig::default(),
         }
     }
 
@@ -293,6 +393,10 @@ impl LogConfig {
                     self.global.load(v);
                 }
 
+                "redaction" => {
+                    self.redaction.load(v);
+                }
+
                 key => {
                     self.load_mod(key, v);
                 }
@@ -351,7 +455,7 @@ impl LogConfig {
 
 #[cfg(test)]
 mod tests {
-    use super::LogDebugInfoFlags;
+    use super::{LogConfig, LogDebugInfoFlags, RedactionDetector};
 
     #[test]
     fn test_log_debug_info_flags_default_is_safe() {
@@ -368,4 +472,43 @@ mod tests {
         assert!(!flags.is_args_present());
         assert!(!flags.is_env_present());
     }
+
+    #[test]
+    fn test_log_config_loads_redaction_table() {
+        let node: toml::Value = toml::from_str(
+            r#"
+[global]
+level = "info"
+
+[redaction]
+detectors = ["jwt", "email", "unknown"]
+
+[[redaction.rules]]
+name = "user_id"
+pattern = "(uid=)\\d+"
+replacement = "${1}***"
+
+[[redaction.rules]]
+name = "missing_pattern"
+"#,
+        )
+        .unwrap();
+
+        let dir = std::env::temp_dir().join("buckyos/slog_log_config_tests");
+        let mut config = LogConfig::new(dir);
+        assert!(config.redaction.enable);
+        config.load(&node).unwrap();
+
+        assert!(config.modules.is_empty());
+        assert_eq!(
+            config.redaction.detectors,
+            vec![RedactionDetector::Jwt, RedactionDetector::Email]
+        );
+        assert_eq!(config.redaction.rules.len(), 1);
+        assert_eq!(config.redaction.rules[0].pattern, r"(uid=)\d+");
+        assert_eq!(
+            config.redaction.rules[0].replacement.as_deref(),
+            Some("${1}***")
+        );
+    }
 }
@@ -1,10 +1,14 @@
 mod constants;
+mod context;
 mod flexi_log;
 mod log_config;
+mod redact;
 mod system_log;
 mod target;
 
 pub use constants::*;
+pub use context::*;
 pub use log_config::*;
+pub use redact::*;
 pub use system_log::*;
 pub use target::*;
@@ -1,6 +1,7 @@
 use super::constants::*;
 use super::flexi_log::*;
 use super::log_config::*;
+use super::redact::LogRedactor;
 use super::target::*;
 use crate::file::FileLogTarget;
 use log::Log;
@@ -126,6 +127,28 @@ impl SystemLoggerBuilder {
         self
     }
 
+    /// Turn the redaction stage off, or back on with the configured detectors.
+    pub fn redaction(mut self, enable: bool) -> Self {
+        self.config.redaction.enable = enable;
+        self
+    }
+
+    /// Mask matches of `pattern`, `$1` style groups may be used in `replacement`.
+    pub fn redaction_rule(mut self, name: &str, pattern: &str, replacement: Option<&str>) -> Self {
+        self.config.redaction.rules.push(LogRedactionRule {
+            name: name.to_owned(),
+            pattern: pattern.to_owned(),
+            replacement: replacement.map(|v| v.to_owned()),
+        });
+        self
+    }
+
+    /// Load `[global]`, module and `[redaction]` tables of a log.toml.
+    pub fn load_config(mut self, config_node: &toml::Value) -> Result<Self, String> {
+        self.config.load(config_node)?;
+        Ok(self)
+    }
+
     pub fn debug_info_flags(mut self, flags: u32) -> Self {
         self.config.global.set_debug_info_flags(flags);
         self
@@ -181,6 +204,7 @@ impl SystemLogger {
     pub fn start(self) -> Result<(), String> {
         let max_level = self.logger.get_max_level();
         let flags = self.config.global.get_debug_info_flags();
+        let redactor = self.logger.redactor();
 
         if let Err(e) = log::set_boxed_logger(self.into()) {
             let msg = format!("call set_boxed_logger failed! {}", e);
@@ -189,6 +213,7 @@ impl SystemLogger {
         }
 
         log::set_max_level(max_level.into());
+        LogRedactor::set_global(redactor);
         Self::display_debug_info(flags);
         Ok(())
     }
@@ -1,8 +1,15 @@
 use super::constants::*;
+use super::context::LogContext;
 use chrono::DateTime;
 use chrono::offset::{Local, Utc};
 use log::Record;
+use log::kv::{Error as KvError, Key, Value, VisitSource};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Structured keys lifted out of `fields` into their own record slots.
+pub const LOG_TRACE_ID_KEY: &str = "trace_id";
+pub const LOG_SPAN_ID_KEY: &str = "span_id";
 
 pub struct LogTimeHelper;
 
@@ -41,6 +48,36 @@ pub struct SystemLogRecord {
     pub file: Option<String>,
     pub line: Option<u32>,
     pub content: String,
+    /// Id shared by every record of one request, across service hops.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// Id of the operation inside the trace that emitted the record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Collects `log` key/values, e.g. `info!(trace_id = id, peer = addr; "...")`.
+#[derive(Default)]
+struct RecordFieldsVisitor {
+    trace_id: Option<String>,
+    span_id: Option<String>,
+    fields: BTreeMap<String, String>,
+}
+
+impl<'kvs> VisitSource<'kvs> for RecordFieldsVisitor {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), KvError> {
+        let value = value.to_string();
+        match key.as_str() {
+            LOG_TRACE_ID_KEY => self.trace_id = Some(value).filter(|v| !v.is_empty()),
+            LOG_SPAN_ID_KEY => self.span_id = Some(value).filter(|v| !v.is_empty()),
+            key => {
+                self.fields.insert(key.to_string(), value);
+            }
+        }
+        Ok(())
+    }
 }
 
 impl SystemLogRecord {
@@ -51,6 +88,15 @@ impl SystemLogRecord {
 
         let content = format!("{}", record.args());
 
+        let mut visitor = RecordFieldsVisitor::default();
+        let _ = record.key_values().visit(&mut visitor);
+        // Explicit key values win over the context of the operation being served.
+        if visitor.trace_id.is_none() || visitor.span_id.is_none() {
+            let context = LogContext::current();
+            visitor.trace_id = visitor.trace_id.or(context.trace_id);
+            visitor.span_id = visitor.span_id.or(context.span_id);
+        }
+
         Self {
             level,
             target,
@@ -58,6 +104,9 @@ impl SystemLogRecord {
             file: record.file().map(|v| v.to_owned()),
             line: record.line(),
             content,
+            trace_id: visitor.trace_id,
+            span_id: visitor.span_id,
+            fields: visitor.fields,
         }
     }
 
@@ -69,9 +118,27 @@ impl SystemLogRecord {
             file: None,
             line: None,
             content,
+            trace_id: None,
+            span_id: None,
+            fields: BTreeMap::new(),
         }
     }
 
+    pub fn with_trace(mut self, trace_id: Option<String>, span_id: Option<String>) -> Self {
+        self.trace_id = trace_id;
+        self.span_id = span_id;
+        self
+    }
+
+    pub fn with_field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn has_structured_fields(&self) -> bool {
+        self.trace_id.is_some() || self.span_id.is_some() || !self.fields.is_empty()
+    }
+
     pub fn content(&self) -> &str {
         self.content.as_str()
     }
@@ -145,3 +212,51 @@ impl SystemLogTarget for ConsoleCyfsLogTarget {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_collects_trace_ids_and_fields_from_key_values() {
+        let kvs: &[(&str, &str)] = &[
+            ("trace_id", "trace-1"),
+            ("span_id", "span-2"),
+            ("method", "sys_config_get"),
+        ];
+        let args = format_args!("rpc done");
+        let log_record = Record::builder()
+            .args(args)
+            .level(log::Level::Info)
+            .target("kv_test")
+            .key_values(&kvs)
+            .build();
+
+        let record = SystemLogRecord::new(&log_record);
+        assert_eq!(record.trace_id.as_deref(), Some("trace-1"));
+        assert_eq!(record.span_id.as_deref(), Some("span-2"));
+        assert_eq!(
+            record.fields.get("method").map(String::as_str),
+            Some("sys_config_get")
+        );
+        assert_eq!(record.fields.len(), 1);
+        assert_eq!(record.content, "rpc done");
+    }
+
+    #[test]
+    fn test_record_inherits_current_log_context() {
+        let _guard = LogContext::new("trace-ctx", Some("span-ctx".to_string())).enter();
+        let kvs: &[(&str, &str)] = &[("span_id", "span-explicit")];
+        let args = format_args!("in rpc");
+        let log_record = Record::builder()
+            .args(args)
+            .level(log::Level::Info)
+            .target("ctx_test")
+            .key_values(&kvs)
+            .build();
+
+        let record = SystemLogRecord::new(&log_record);
+        assert_eq!(record.trace_id.as_deref(), Some("trace-ctx"));
+        assert_eq!(record.span_id.as_deref(), Some("span-explicit"));
+    }
+}
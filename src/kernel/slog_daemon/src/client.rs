@@ -42,14 +42,25 @@ impl LogDaemonClient {
         upload_global_concurrency: usize,
         log_dir: &Path,
         excluded: Vec<String>,
+    ) -> Result<Self, String> {
+        let uploader = LogUploader::new(node, service_endpoint, upload_timeout_secs);
+        Self::new_with_uploader(uploader, upload_global_concurrency, log_dir, excluded)
+    }
+
+    /// Start with a prepared uploader, e.g. one configured with compression and a signer.
+    pub fn new_with_uploader(
+        uploader: LogUploader,
+        upload_global_concurrency: usize,
+        log_dir: &Path,
+        excluded: Vec<String>,
     ) -> Result<Self, String> {
         let upload_global_concurrency = upload_global_concurrency.max(1);
         let shutdown_token = CancellationToken::new();
         let reader_shutdown = shutdown_token.child_token();
         let uploader_shutdown = shutdown_token.child_token();
 
-        let uploader =
-            LogUploader::new(node.clone(), service_endpoint.clone(), upload_timeout_secs);
+        let node = uploader.node().to_string();
+        let service_endpoint = uploader.service_endpoint().to_string();
 
         let (tx, rx) = mpsc::channel::<LogRecordLoad>(100);
         let reader_manager = LogReaderManager::open(log_dir, excluded, tx, reader_shutdown)
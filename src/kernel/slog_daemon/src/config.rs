@@ -2,6 +2,7 @@ use crate::constants::{
     DEFAULT_NODE_ID, DEFAULT_SERVER_ENDPOINT, DEFAULT_UPLOAD_GLOBAL_CONCURRENCY,
     DEFAULT_UPLOAD_TIMEOUT_SECS,
 };
+use crate::upload::UploadCompression;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
@@ -23,6 +24,9 @@ pub struct PathConfig {
 pub struct UploadConfig {
     pub timeout_secs: u64,
     pub global_concurrency: usize,
+    pub compression: UploadCompression,
+    /// Device key used to sign uploads; uploads are unsigned when not set.
+    pub device_key_file: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +44,8 @@ pub struct DaemonEnvOverrides {
     pub log_dir: Option<PathBuf>,
     pub upload_timeout_secs: Option<u64>,
     pub upload_global_concurrency: Option<usize>,
+    pub upload_compression: Option<UploadCompression>,
+    pub device_key_file: Option<PathBuf>,
 }
 
 impl Default for DaemonConfig {
@@ -57,6 +63,8 @@ impl Default for DaemonConfig {
             upload: UploadConfig {
                 timeout_secs: DEFAULT_UPLOAD_TIMEOUT_SECS,
                 global_concurrency: DEFAULT_UPLOAD_GLOBAL_CONCURRENCY,
+                compression: UploadCompression::Auto,
+                device_key_file: None,
             },
         }
     }
@@ -88,6 +96,14 @@ impl DaemonConfig {
         if let Some(v) = overrides.upload_global_concurrency {
             self.upload.global_concurrency = v;
         }
+
+        if let Some(v) = overrides.upload_compression {
+            self.upload.compression = v;
+        }
+
+        if let Some(v) = overrides.device_key_file {
+            self.upload.device_key_file = Some(v);
+        }
     }
 }
 
@@ -96,10 +112,11 @@ mod tests {
     use super::{DaemonConfig, DaemonEnvOverrides};
     use crate::constants::{
         DEFAULT_NODE_ID, DEFAULT_SERVER_ENDPOINT, DEFAULT_UPLOAD_GLOBAL_CONCURRENCY,
-        DEFAULT_UPLOAD_TIMEOUT_SECS, SLOG_LOG_DIR_ENV_KEY, SLOG_NODE_ID_ENV_KEY,
-        SLOG_SERVER_ENDPOINT_ENV_KEY, SLOG_UPLOAD_GLOBAL_CONCURRENCY_ENV_KEY,
-        SLOG_UPLOAD_TIMEOUT_SECS_ENV_KEY,
+        DEFAULT_UPLOAD_TIMEOUT_SECS, SLOG_DEVICE_KEY_FILE_ENV_KEY, SLOG_LOG_DIR_ENV_KEY,
+        SLOG_NODE_ID_ENV_KEY, SLOG_SERVER_ENDPOINT_ENV_KEY, SLOG_UPLOAD_COMPRESSION_ENV_KEY,
+        SLOG_UPLOAD_GLOBAL_CONCURRENCY_ENV_KEY, SLOG_UPLOAD_TIMEOUT_SECS_ENV_KEY,
     };
+    use crate::upload::UploadCompression;
     use std::path::PathBuf;
 
     #[test]
@@ -113,6 +130,8 @@ mod tests {
             DEFAULT_UPLOAD_GLOBAL_CONCURRENCY
         );
         assert_eq!(cfg.path.log_dir, slog::get_buckyos_log_root_dir());
+        assert_eq!(cfg.upload.compression, UploadCompression::Auto);
+        assert!(cfg.upload.device_key_file.is_none());
     }
 
     #[test]
@@ -124,6 +143,8 @@ mod tests {
             log_dir: Some(PathBuf::from("/tmp/slog_log_root")),
             upload_timeout_secs: Some(33),
             upload_global_concurrency: Some(8),
+            upload_compression: Some(UploadCompression::Gzip),
+            device_key_file: Some(PathBuf::from("/tmp/slog_device_key.pem")),
         };
         cfg.apply_env_overrides(overrides);
 
@@ -132,6 +153,11 @@ mod tests {
         assert_eq!(cfg.path.log_dir, PathBuf::from("/tmp/slog_log_root"));
         assert_eq!(cfg.upload.timeout_secs, 33);
         assert_eq!(cfg.upload.global_concurrency, 8);
+        assert_eq!(cfg.upload.compression, UploadCompression::Gzip);
+        assert_eq!(
+            cfg.upload.device_key_file,
+            Some(PathBuf::from("/tmp/slog_device_key.pem"))
+        );
     }
 
     #[test]
@@ -146,6 +172,8 @@ mod tests {
             log_dir: None,
             upload_timeout_secs: Some(12),
             upload_global_concurrency: None,
+            upload_compression: None,
+            device_key_file: None,
         };
         cfg.apply_env_overrides(overrides);
 
@@ -169,5 +197,7 @@ mod tests {
             SLOG_UPLOAD_GLOBAL_CONCURRENCY_ENV_KEY,
             "SLOG_UPLOAD_GLOBAL_CONCURRENCY"
         );
+        assert_eq!(SLOG_UPLOAD_COMPRESSION_ENV_KEY, "SLOG_UPLOAD_COMPRESSION");
+        assert_eq!(SLOG_DEVICE_KEY_FILE_ENV_KEY, "SLOG_DEVICE_KEY_FILE");
     }
 }
@@ -21,6 +21,13 @@ pub const SLOG_UPLOAD_TIMEOUT_SECS_ENV_KEY: &str = "SLOG_UPLOAD_TIMEOUT_SECS";
 /// Environment key for global upload concurrency.
 pub const SLOG_UPLOAD_GLOBAL_CONCURRENCY_ENV_KEY: &str = "SLOG_UPLOAD_GLOBAL_CONCURRENCY";
 
+/// Environment key for upload body compression: `auto`, `zstd`, `gzip` or `none`.
+pub const SLOG_UPLOAD_COMPRESSION_ENV_KEY: &str = "SLOG_UPLOAD_COMPRESSION";
+
+/// Environment key for the device private key (Ed25519 PEM) used to sign uploads,
+/// set by node_daemon together with `SLOG_NODE_ID` when it starts the daemon.
+pub const SLOG_DEVICE_KEY_FILE_ENV_KEY: &str = "SLOG_DEVICE_KEY_FILE";
+
 /// Default node id when no external config is provided.
 pub const DEFAULT_NODE_ID: &str = "node-001";
 
@@ -53,3 +60,12 @@ pub const INITIAL_UPLOAD_FAILED_RETRY_INTERVAL_SECS: u64 = 2;
 
 /// Upper bound of exponential backoff after repeated failures.
 pub const MAX_UPLOAD_FAILED_RETRY_INTERVAL_SECS: u64 = 60 * 2;
+
+/// Payloads smaller than this are sent uncompressed even if the server accepts codings.
+pub const UPLOAD_COMPRESSION_MIN_BYTES: usize = 1024;
+
+/// `aud` claim of upload tokens, must match `slog_server`.
+pub const UPLOAD_TOKEN_AUDIENCE: &str = "slog_server";
+
+/// Lifetime of one upload token; a new token is signed for every attempt.
+pub const UPLOAD_TOKEN_TTL_SECS: u64 = 300;
@@ -15,6 +15,7 @@ extern crate log;
 use crate::client::LogDaemonClient;
 use crate::config::{DaemonConfig, DaemonEnvOverrides};
 use crate::constants::*;
+use crate::upload::{LogUploader, UploadCompression, UploadSigner};
 use std::path::PathBuf;
 
 fn read_env_nonempty(env_key: &str) -> Option<String> {
@@ -63,6 +64,22 @@ fn parse_env_positive_usize(env_key: &str, default_value: usize) -> Option<usize
     }
 }
 
+fn parse_env_upload_compression(env_key: &str) -> Option<UploadCompression> {
+    match std::env::var(env_key) {
+        Ok(v) if !v.trim().is_empty() => match UploadCompression::parse(&v) {
+            Some(v) => Some(v),
+            None => {
+                warn!(
+                    "invalid {} value '{}', fallback to default auto",
+                    env_key, v
+                );
+                None
+            }
+        },
+        _ => None,
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let mut cfg = DaemonConfig::default();
@@ -78,6 +95,8 @@ async fn main() {
             SLOG_UPLOAD_GLOBAL_CONCURRENCY_ENV_KEY,
             DEFAULT_UPLOAD_GLOBAL_CONCURRENCY,
         ),
+        upload_compression: parse_env_upload_compression(SLOG_UPLOAD_COMPRESSION_ENV_KEY),
+        device_key_file: read_env_path(SLOG_DEVICE_KEY_FILE_ENV_KEY),
     };
     cfg.apply_env_overrides(env_overrides);
 
@@ -85,6 +104,8 @@ async fn main() {
     let service_endpoint = cfg.server.endpoint;
     let upload_timeout_secs = cfg.upload.timeout_secs;
     let upload_global_concurrency = cfg.upload.global_concurrency;
+    let upload_compression = cfg.upload.compression;
+    let device_key_file = cfg.upload.device_key_file;
     let log_dir = cfg.path.log_dir;
 
     // Init slog daemon own logs, output to file and console
@@ -107,21 +128,36 @@ async fn main() {
     }
 
     info!(
-        "slog_daemon config: node_id={}, endpoint={}, log_dir={}, upload_timeout_secs={}, upload_global_concurrency={}",
+        "slog_daemon config: node_id={}, endpoint={}, log_dir={}, upload_timeout_secs={}, upload_global_concurrency={}, upload_compression={}",
         node_id,
         service_endpoint,
         log_dir.display(),
         upload_timeout_secs,
-        upload_global_concurrency
+        upload_global_concurrency,
+        upload_compression.as_str()
     );
 
+    let mut uploader = LogUploader::new(node_id.clone(), service_endpoint, upload_timeout_secs)
+        .with_compression(upload_compression);
+    match device_key_file {
+        Some(path) => match UploadSigner::load(node_id, &path) {
+            Ok(signer) => uploader = uploader.with_signer(signer),
+            Err(e) => {
+                eprintln!("Failed to load device key: {}", e);
+                return;
+            }
+        },
+        None => warn!(
+            "{} not set, log uploads will not be signed and a zone slog_server rejects them",
+            SLOG_DEVICE_KEY_FILE_ENV_KEY
+        ),
+    }
+
     // Specify excluded services
     // We should not upload logs from slog_daemon itself and slog_server
     let excluded = vec![SERVICE_NAME.to_string(), "slog_server".to_string()];
-    let client = match LogDaemonClient::new_with_upload_concurrency(
-        node_id,
-        service_endpoint,
-        upload_timeout_secs,
+    let client = match LogDaemonClient::new_with_uploader(
+        uploader,
         upload_global_concurrency,
         &log_dir,
         excluded,
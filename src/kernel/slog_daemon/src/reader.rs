@@ -371,6 +371,9 @@ mod tests {
                 file: Some(format!("{}.rs", name)),
                 line: Some(i as u32 + 1),
                 content: format!("{}-{}", name, i),
+                trace_id: None,
+                span_id: None,
+                fields: Default::default(),
             };
             content.push_str(&SystemLogRecordLineFormatter::format_record(&record));
         }
@@ -1,6 +1,7 @@
 mod test_pipeline_all_invalid_lines;
 mod test_pipeline_append_during_runtime;
 mod test_pipeline_backpressure_slow_server;
+mod test_pipeline_compressed_authenticated_upload;
 mod test_pipeline_concurrent_multi_service_integrity;
 mod test_pipeline_corrupt_line_sidecar;
 mod test_pipeline_crash_recovery;
@@ -18,5 +19,6 @@ mod test_pipeline_server_recovery;
 mod test_pipeline_server_request_interruption;
 mod test_pipeline_service_churn;
 mod test_pipeline_sqlite_locked_retry;
+mod test_pipeline_structured_fields;
 mod test_pipeline_transient_append_failure;
 mod test_pipeline_upload_timeout_recovery;
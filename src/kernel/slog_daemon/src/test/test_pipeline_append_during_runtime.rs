@@ -42,6 +42,9 @@ fn make_record(service: &str, time: u64, content: &str) -> SystemLogRecord {
         file: Some("pipeline_append_runtime_test.rs".to_string()),
         line: Some(1),
         content: content.to_string(),
+        trace_id: None,
+        span_id: None,
+        fields: Default::default(),
     }
 }
 
@@ -120,6 +123,7 @@ async fn query_uploaded_contents(
             start_time: None,
             end_time: None,
             limit: Some(10_000),
+            search: None,
         })
         .await?;
 
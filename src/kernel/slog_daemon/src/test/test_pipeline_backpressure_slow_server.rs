@@ -46,6 +46,9 @@ fn make_record(service: &str, time: u64, content: &str) -> SystemLogRecord {
         file: Some("pipeline_backpressure_test.rs".to_string()),
         line: Some(1),
         content: content.to_string(),
+        trace_id: None,
+        span_id: None,
+        fields: Default::default(),
     }
 }
 
@@ -155,6 +158,7 @@ async fn query_service_contents(
             start_time: None,
             end_time: None,
             limit: Some(20_000),
+            search: None,
         })
         .await?;
 
@@ -208,7 +212,7 @@ struct SlowAppendStorage {
 
 #[async_trait::async_trait]
 impl LogStorage for SlowAppendStorage {
-    async fn append_logs(&self, records: LogRecords) -> Result<(), String> {
+    async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String> {
         self.append_calls.fetch_add(1, Ordering::SeqCst);
         tokio::time::sleep(self.delay).await;
         self.inner.append_logs(records).await
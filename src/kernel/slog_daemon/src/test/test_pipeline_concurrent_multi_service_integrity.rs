@@ -44,6 +44,9 @@ fn make_record(service: &str, time: u64, content: &str) -> SystemLogRecord {
         file: Some("pipeline_concurrent_integrity.rs".to_string()),
         line: Some(1),
         content: content.to_string(),
+        trace_id: None,
+        span_id: None,
+        fields: Default::default(),
     }
 }
 
@@ -93,6 +96,7 @@ async fn query_service_contents(
             start_time: None,
             end_time: None,
             limit: Some(20_000),
+            search: None,
         })
         .await?;
 
@@ -175,7 +179,7 @@ struct JitterAppendStorage {
 
 #[async_trait::async_trait]
 impl LogStorage for JitterAppendStorage {
-    async fn append_logs(&self, records: LogRecords) -> Result<(), String> {
+    async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String> {
         self.append_calls.fetch_add(1, Ordering::SeqCst);
         let hash = records
             .service
@@ -40,6 +40,9 @@ fn make_record(service: &str, time: u64, content: &str) -> SystemLogRecord {
         file: Some("pipeline_corrupt_sidecar_test.rs".to_string()),
         line: Some(1),
         content: content.to_string(),
+        trace_id: None,
+        span_id: None,
+        fields: Default::default(),
     }
 }
 
@@ -107,6 +110,7 @@ async fn wait_for_uploaded_logs(
                 start_time: None,
                 end_time: None,
                 limit: Some(1000),
+                search: None,
             })
             .await?;
         let count: usize = result.iter().map(|records| records.logs.len()).sum();
@@ -43,6 +43,9 @@ fn make_record(service: &str, time: u64, content: &str) -> SystemLogRecord {
         file: Some("pipeline_graceful_shutdown_test.rs".to_string()),
         line: Some(1),
         content: content.to_string(),
+        trace_id: None,
+        span_id: None,
+        fields: Default::default(),
     }
 }
 
@@ -104,6 +107,7 @@ async fn query_uploaded_contents(
             start_time: None,
             end_time: None,
             limit: Some(10_000),
+            search: None,
         })
         .await?;
 
@@ -200,7 +204,7 @@ struct SlowAppendStorage {
 
 #[async_trait::async_trait]
 impl LogStorage for SlowAppendStorage {
-    async fn append_logs(&self, records: LogRecords) -> Result<(), String> {
+    async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String> {
         self.append_started.fetch_add(1, Ordering::SeqCst);
         tokio::time::sleep(self.delay).await;
         let ret = self.inner.append_logs(records).await;
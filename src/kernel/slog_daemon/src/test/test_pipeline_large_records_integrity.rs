@@ -45,6 +45,9 @@ fn make_large_record(service: &str, time: u64, idx: usize, size: usize) -> Syste
         file: Some("pipeline_large_records_test.rs".to_string()),
         line: Some(1),
         content,
+        trace_id: None,
+        span_id: None,
+        fields: Default::default(),
     }
 }
 
@@ -92,6 +95,7 @@ async fn query_uploaded_contents(
             start_time: None,
             end_time: None,
             limit: Some(20_000),
+            search: None,
         })
         .await?;
     let mut contents = Vec::new();
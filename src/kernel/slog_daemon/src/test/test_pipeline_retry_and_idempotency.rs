@@ -43,6 +43,9 @@ fn make_record(service: &str, time: u64, content: &str) -> SystemLogRecord {
         file: Some("pipeline_retry_test.rs".to_string()),
         line: Some(1),
         content: content.to_string(),
+        trace_id: None,
+        span_id: None,
+        fields: Default::default(),
     }
 }
 
@@ -97,6 +100,7 @@ async fn wait_for_uploaded_logs(
                 start_time: None,
                 end_time: None,
                 limit: Some(1000),
+                search: None,
             })
             .await?;
         let count: usize = result.iter().map(|records| records.logs.len()).sum();
@@ -176,16 +180,16 @@ struct FalseNegativeOnceStorage {
 
 #[async_trait::async_trait]
 impl LogStorage for FalseNegativeOnceStorage {
-    async fn append_logs(&self, records: LogRecords) -> Result<(), String> {
+    async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String> {
         self.append_calls.fetch_add(1, Ordering::SeqCst);
-        self.inner.append_logs(records).await?;
+        let inserted = self.inner.append_logs(records).await?;
 
         // Simulate "stored but returned failure once": uploader will retry the same batch.
         if !self.injected_failure.swap(true, Ordering::SeqCst) {
             return Err("injected false-negative append failure".to_string());
         }
 
-        Ok(())
+        Ok(inserted)
     }
 
     async fn query_logs(&self, request: LogQueryRequest) -> Result<Vec<LogRecords>, String> {
@@ -43,6 +43,9 @@ fn make_record(service: &str, time: u64, content: &str) -> SystemLogRecord {
         file: Some("pipeline_sqlite_locked_test.rs".to_string()),
         line: Some(1),
         content: content.to_string(),
+        trace_id: None,
+        span_id: None,
+        fields: Default::default(),
     }
 }
 
@@ -85,7 +88,7 @@ struct LockedThenOkStorage {
 
 #[async_trait::async_trait]
 impl LogStorage for LockedThenOkStorage {
-    async fn append_logs(&self, records: LogRecords) -> Result<(), String> {
+    async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String> {
         self.append_calls.fetch_add(1, Ordering::SeqCst);
 
         let remaining = self.remaining_locked_errors.load(Ordering::SeqCst);
@@ -114,6 +117,7 @@ async fn query_uploaded_contents(
             start_time: None,
             end_time: None,
             limit: Some(10_000),
+            search: None,
         })
         .await?;
     let mut contents = Vec::new();
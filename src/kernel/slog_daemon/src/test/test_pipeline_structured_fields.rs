@@ -0,0 +1,238 @@
+use crate::client::LogDaemonClient;
+use slog::{LogLevel, LogMeta, SystemLogRecord, SystemLogRecordLineFormatter};
+use slog_server::server::LogHttpServer;
+use slog_server::storage::{
+    LogQueryRequest, LogRecords, LogSearchFilter, LogStorage, LogStorageType,
+    create_log_storage_with_dir,
+};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
+
+fn new_temp_root(prefix: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let root = std::env::temp_dir().join(format!(
+        "buckyos/slog_pipeline_tests/{}_{}_{}",
+        prefix,
+        std::process::id(),
+        nanos
+    ));
+    std::fs::create_dir_all(&root).unwrap();
+    root
+}
+
+fn allocate_bind_addr() -> Result<String, String> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")
+        .map_err(|e| format!("failed to bind test listener on loopback: {}", e))?;
+    let addr = listener
+        .local_addr()
+        .map_err(|e| format!("failed to read local address: {}", e))?;
+    Ok(format!("127.0.0.1:{}", addr.port()))
+}
+
+fn make_record(
+    service: &str,
+    time: u64,
+    content: &str,
+    trace_id: Option<&str>,
+    method: &str,
+) -> SystemLogRecord {
+    SystemLogRecord {
+        level: LogLevel::Info,
+        target: service.to_string(),
+        time,
+        file: Some("pipeline_test.rs".to_string()),
+        line: Some(1),
+        content: content.to_string(),
+        trace_id: trace_id.map(str::to_string),
+        span_id: None,
+        fields: [("method".to_string(), method.to_string())].into(),
+    }
+}
+
+fn prepare_service_logs(
+    log_root: &Path,
+    service: &str,
+    records: &[SystemLogRecord],
+) -> Result<PathBuf, String> {
+    let service_dir = log_root.join(service);
+    std::fs::create_dir_all(&service_dir).map_err(|e| {
+        format!(
+            "failed to create service log dir {}: {}",
+            service_dir.display(),
+            e
+        )
+    })?;
+
+    let meta = LogMeta::open(&service_dir)?;
+    let file_name = format!("{}.1.log", service);
+    meta.append_new_file(&file_name)
+        .map_err(|e| format!("append_new_file failed: {}", e))?;
+
+    let mut content = String::new();
+    for record in records {
+        content.push_str(&SystemLogRecordLineFormatter::format_record(record));
+    }
+
+    let log_file = service_dir.join(&file_name);
+    std::fs::write(&log_file, &content)
+        .map_err(|e| format!("failed to write log file {}: {}", log_file.display(), e))?;
+    meta.update_current_write_index(content.len() as u64)
+        .map_err(|e| format!("update_current_write_index failed: {}", e))?;
+
+    Ok(service_dir)
+}
+
+async fn wait_for_uploaded_logs(
+    storage: &dyn LogStorage,
+    node: &str,
+    service: &str,
+    expected_count: usize,
+    timeout: Duration,
+) -> Result<Vec<LogRecords>, String> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let result = storage
+            .query_logs(LogQueryRequest {
+                node: Some(node.to_string()),
+                service: Some(service.to_string()),
+                level: None,
+                start_time: None,
+                end_time: None,
+                limit: Some(1000),
+                search: None,
+            })
+            .await?;
+        let count: usize = result.iter().map(|records| records.logs.len()).sum();
+        if count >= expected_count {
+            return Ok(result);
+        }
+
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "timeout waiting for uploaded logs, expected >= {}, got {}",
+                expected_count, count
+            ));
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_pipeline_structured_fields_are_queryable_after_upload() {
+    let root = new_temp_root("structured_fields");
+    let storage_dir = root.join("server_storage");
+    let bind_addr = match allocate_bind_addr() {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!(
+                "skip pipeline_structured_fields test due socket restriction: {}",
+                e
+            );
+            std::fs::remove_dir_all(&root).unwrap();
+            return;
+        }
+    };
+    let endpoint = format!("http://{}/logs", bind_addr);
+    let node = "node-e2e";
+    let service = "svc_structured";
+
+    let input_records = vec![
+        make_record(
+            service,
+            1722000000001,
+            "rpc begin",
+            Some("trace-a"),
+            "sys_config_get",
+        ),
+        make_record(
+            service,
+            1722000000002,
+            "rpc other",
+            Some("trace-b"),
+            "sys_config_set",
+        ),
+        make_record(
+            service,
+            1722000000003,
+            "rpc end",
+            Some("trace-a"),
+            "sys_config_get",
+        ),
+        make_record(service, 1722000000004, "no trace", None, "sys_config_get"),
+    ];
+    prepare_service_logs(&root, service, &input_records).unwrap();
+
+    let storage = create_log_storage_with_dir(LogStorageType::Sqlite, &storage_dir).unwrap();
+    let server = LogHttpServer::new(storage.clone());
+    let server_handle = tokio::spawn({
+        let bind_addr = bind_addr.clone();
+        async move {
+            let _ = server.run(&bind_addr).await;
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let daemon = LogDaemonClient::new(
+        node.to_string(),
+        endpoint,
+        3,
+        &root,
+        vec!["slog_daemon".to_string(), "slog_server".to_string()],
+    )
+    .unwrap();
+
+    wait_for_uploaded_logs(
+        storage.as_ref().as_ref(),
+        node,
+        service,
+        input_records.len(),
+        Duration::from_secs(8),
+    )
+    .await
+    .unwrap();
+
+    let result = storage
+        .query_logs(LogQueryRequest {
+            node: None,
+            service: None,
+            level: None,
+            start_time: None,
+            end_time: None,
+            limit: None,
+            search: Some(LogSearchFilter {
+                trace_id: Some("trace-a".to_string()),
+                fields: [("method".to_string(), "sys_config_get".to_string())].into(),
+                ..Default::default()
+            }),
+        })
+        .await
+        .unwrap();
+    let mut traced: Vec<SystemLogRecord> = result
+        .into_iter()
+        .flat_map(|records| records.logs)
+        .collect();
+    traced.sort_by_key(|log| log.time);
+    assert_eq!(
+        traced
+            .iter()
+            .map(|log| log.content.as_str())
+            .collect::<Vec<_>>(),
+        vec!["rpc begin", "rpc end"]
+    );
+    assert!(traced.iter().all(|log| {
+        log.trace_id.as_deref() == Some("trace-a")
+            && log.fields.get("method").map(String::as_str) == Some("sys_config_get")
+    }));
+
+    daemon.shutdown().await.unwrap();
+    server_handle.abort();
+    let _ = server_handle.await;
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
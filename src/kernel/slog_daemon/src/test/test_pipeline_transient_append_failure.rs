@@ -43,6 +43,9 @@ fn make_record(service: &str, time: u64, content: &str) -> SystemLogRecord {
         file: Some("pipeline_transient_append_failure_test.rs".to_string()),
         line: Some(1),
         content: content.to_string(),
+        trace_id: None,
+        span_id: None,
+        fields: Default::default(),
     }
 }
 
@@ -85,7 +88,7 @@ struct FailThenOkStorage {
 
 #[async_trait::async_trait]
 impl LogStorage for FailThenOkStorage {
-    async fn append_logs(&self, records: LogRecords) -> Result<(), String> {
+    async fn append_logs(&self, records: LogRecords) -> Result<LogRecords, String> {
         self.append_calls.fetch_add(1, Ordering::SeqCst);
         if self.remaining_failures.load(Ordering::SeqCst) > 0 {
             self.remaining_failures.fetch_sub(1, Ordering::SeqCst);
@@ -112,6 +115,7 @@ async fn query_uploaded_contents(
             start_time: None,
             end_time: None,
             limit: Some(10_000),
+            search: None,
         })
         .await?;
     let mut contents = Vec::new();
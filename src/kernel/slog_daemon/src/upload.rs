@@ -1,5 +1,17 @@
-use crate::constants::DEFAULT_UPLOAD_TIMEOUT_SECS;
+use crate::constants::{
+    DEFAULT_UPLOAD_TIMEOUT_SECS, UPLOAD_COMPRESSION_MIN_BYTES, UPLOAD_TOKEN_AUDIENCE,
+    UPLOAD_TOKEN_TTL_SECS,
+};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::header::{ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE};
+use sha2::{Digest, Sha256};
 use slog::SystemLogRecord;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::time::Duration;
 
 #[derive(serde::Deserialize)]
@@ -8,12 +20,182 @@ struct UploadResponse {
     message: String,
 }
 
+/// Upload token claims, mirrors `slog_server::auth::UploadTokenClaims`.
+#[derive(serde::Serialize)]
+struct UploadTokenClaims<'a> {
+    iss: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+    batch_id: &'a str,
+    body_sha256: String,
+}
+
+/// Which request body codings the uploader may use once the server
+/// advertises them in `Accept-Encoding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadCompression {
+    /// Prefer zstd, then gzip.
+    Auto,
+    Zstd,
+    Gzip,
+    None,
+}
+
+impl UploadCompression {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "zstd" => Some(Self::Zstd),
+            "gzip" => Some(Self::Gzip),
+            "none" | "identity" | "off" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Zstd => "zstd",
+            Self::Gzip => "gzip",
+            Self::None => "none",
+        }
+    }
+
+    fn preferred(&self) -> &'static [ContentCoding] {
+        match self {
+            Self::Auto => &[ContentCoding::Zstd, ContentCoding::Gzip],
+            Self::Zstd => &[ContentCoding::Zstd],
+            Self::Gzip => &[ContentCoding::Gzip],
+            Self::None => &[],
+        }
+    }
+
+    /// Best coding allowed by this setting and listed in the server's `Accept-Encoding`.
+    fn negotiate(&self, accept_encoding: &str) -> ContentCoding {
+        let offered: Vec<String> = accept_encoding
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let name = parts.next()?.trim().to_ascii_lowercase();
+                // `q=0` explicitly refuses a coding.
+                let refused = parts.any(|param| {
+                    param
+                        .trim()
+                        .strip_prefix("q=")
+                        .and_then(|q| q.trim().parse::<f32>().ok())
+                        .is_some_and(|q| q <= 0.0)
+                });
+                (!name.is_empty() && !refused).then_some(name)
+            })
+            .collect();
+
+        self.preferred()
+            .iter()
+            .copied()
+            .find(|coding| offered.iter().any(|name| name == coding.as_str()))
+            .unwrap_or(ContentCoding::Identity)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum ContentCoding {
+    Identity = 0,
+    Gzip = 1,
+    Zstd = 2,
+}
+
+impl ContentCoding {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Gzip,
+            2 => Self::Zstd,
+            _ => Self::Identity,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    fn encode(&self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(body.to_vec()),
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            Self::Zstd => zstd::stream::encode_all(body, 0),
+        }
+    }
+}
+
+/// Signs each upload batch with the device key, see `slog_server::auth`.
+pub struct UploadSigner {
+    device: String,
+    key: EncodingKey,
+}
+
+impl UploadSigner {
+    pub fn new(device: String, key: EncodingKey) -> Self {
+        Self { device, key }
+    }
+
+    /// Load an Ed25519 private key in PKCS#8 PEM form, e.g. the node's device key.
+    pub fn load(device: String, key_file: &Path) -> Result<Self, String> {
+        let pem = std::fs::read(key_file).map_err(|e| {
+            let msg = format!("failed to read device key {}: {}", key_file.display(), e);
+            error!("{}", msg);
+            msg
+        })?;
+        let key = EncodingKey::from_ed_pem(&pem).map_err(|e| {
+            let msg = format!("invalid device key {}: {}", key_file.display(), e);
+            error!("{}", msg);
+            msg
+        })?;
+        Ok(Self::new(device, key))
+    }
+
+    fn sign(&self, batch_id: &str, body: &[u8]) -> Result<String, String> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let claims = UploadTokenClaims {
+            iss: &self.device,
+            aud: UPLOAD_TOKEN_AUDIENCE,
+            iat: now,
+            exp: now + UPLOAD_TOKEN_TTL_SECS,
+            batch_id,
+            body_sha256: URL_SAFE_NO_PAD.encode(Sha256::digest(body)),
+        };
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(self.device.clone());
+        jsonwebtoken::encode(&header, &claims, &self.key).map_err(|e| {
+            let msg = format!("failed to sign upload batch {}: {}", batch_id, e);
+            error!("{}", msg);
+            msg
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct LogUploader {
     node: String,
     service_endpoint: String,
     client: reqwest::Client,
     timeout: Duration,
+    compression: UploadCompression,
+    // Coding negotiated from the last server response, shared by all clones.
+    negotiated: Arc<AtomicU8>,
+    signer: Option<Arc<UploadSigner>>,
 }
 
 impl LogUploader {
@@ -34,9 +216,30 @@ impl LogUploader {
             service_endpoint,
             client,
             timeout,
+            compression: UploadCompression::Auto,
+            negotiated: Arc::new(AtomicU8::new(ContentCoding::Identity as u8)),
+            signer: None,
         }
     }
 
+    pub fn with_compression(mut self, compression: UploadCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_signer(mut self, signer: UploadSigner) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    pub fn service_endpoint(&self) -> &str {
+        &self.service_endpoint
+    }
+
     pub async fn upload_logs(
         &self,
         service: &str,
@@ -52,20 +255,80 @@ impl LogUploader {
             "record_ids": record_ids,
             "logs": records,
         });
+        let json = serde_json::to_vec(&payload).map_err(|e| {
+            let msg = format!("failed to serialize upload payload: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+
+        let mut coding = if json.len() >= UPLOAD_COMPRESSION_MIN_BYTES {
+            ContentCoding::from_u8(self.negotiated.load(Ordering::Relaxed))
+        } else {
+            ContentCoding::Identity
+        };
+        loop {
+            let (status, body) = self.send_batch(batch_id, &json, coding).await?;
+
+            // A server that cannot decode the coding answers 415 with what it
+            // accepts; resend the same batch plain right away.
+            if status == reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE
+                && coding != ContentCoding::Identity
+            {
+                warn!(
+                    "server rejected {} upload body, falling back to identity",
+                    coding.as_str()
+                );
+                self.negotiated
+                    .store(ContentCoding::Identity as u8, Ordering::Relaxed);
+                coding = ContentCoding::Identity;
+                continue;
+            }
+
+            return Self::validate_upload_response(status, &body);
+        }
+    }
+
+    async fn send_batch(
+        &self,
+        batch_id: &str,
+        json: &[u8],
+        coding: ContentCoding,
+    ) -> Result<(reqwest::StatusCode, String), String> {
+        let body = coding.encode(json).map_err(|e| {
+            let msg = format!("failed to {} encode upload body: {}", coding.as_str(), e);
+            error!("{}", msg);
+            msg
+        })?;
 
         // Send the HTTP POST request
-        let response = self
+        let mut request = self
             .client
             .post(&self.service_endpoint)
             .timeout(self.timeout)
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| {
-                let msg = format!("failed to send logs to server: {}", e);
-                error!("{}", msg);
-                msg
-            })?;
+            .header(CONTENT_TYPE, "application/json");
+        if coding != ContentCoding::Identity {
+            request = request.header(CONTENT_ENCODING, coding.as_str());
+        }
+        if let Some(signer) = self.signer.as_ref() {
+            // Signed per attempt over the bytes on the wire, so retries stay valid.
+            let token = signer.sign(batch_id, &body)?;
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+
+        let response = request.body(body).send().await.map_err(|e| {
+            let msg = format!("failed to send logs to server: {}", e);
+            error!("{}", msg);
+            msg
+        })?;
+
+        if let Some(accept_encoding) = response
+            .headers()
+            .get(ACCEPT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+        {
+            let negotiated = self.compression.negotiate(accept_encoding);
+            self.negotiated.store(negotiated as u8, Ordering::Relaxed);
+        }
 
         let status = response.status();
         let body = response.text().await.map_err(|e| {
@@ -73,8 +336,7 @@ impl LogUploader {
             error!("{}", msg);
             msg
         })?;
-
-        Self::validate_upload_response(status, &body)
+        Ok((status, body))
     }
 
     fn effective_timeout_secs(timeout_secs: u64) -> u64 {
@@ -113,8 +375,9 @@ impl LogUploader {
 
 #[cfg(test)]
 mod tests {
-    use super::LogUploader;
+    use super::{ContentCoding, LogUploader, UploadCompression};
     use crate::constants::DEFAULT_UPLOAD_TIMEOUT_SECS;
+    use std::io::Read;
 
     #[test]
     #[ignore]
@@ -174,4 +437,65 @@ mod tests {
     fn test_effective_timeout_secs_keeps_non_zero_value() {
         assert_eq!(LogUploader::effective_timeout_secs(25), 25);
     }
+
+    #[test]
+    fn test_negotiate_picks_preferred_coding_offered_by_server() {
+        assert_eq!(
+            UploadCompression::Auto.negotiate("zstd, gzip"),
+            ContentCoding::Zstd
+        );
+        assert_eq!(
+            UploadCompression::Auto.negotiate("gzip;q=1.0, zstd;q=0"),
+            ContentCoding::Gzip
+        );
+        assert_eq!(
+            UploadCompression::Zstd.negotiate("gzip"),
+            ContentCoding::Identity
+        );
+        assert_eq!(
+            UploadCompression::None.negotiate("zstd, gzip"),
+            ContentCoding::Identity
+        );
+        assert_eq!(
+            UploadCompression::Auto.negotiate(""),
+            ContentCoding::Identity
+        );
+    }
+
+    #[test]
+    fn test_upload_compression_parse_round_trips() {
+        for compression in [
+            UploadCompression::Auto,
+            UploadCompression::Zstd,
+            UploadCompression::Gzip,
+            UploadCompression::None,
+        ] {
+            assert_eq!(
+                UploadCompression::parse(compression.as_str()),
+                Some(compression)
+            );
+        }
+        assert_eq!(
+            UploadCompression::parse(" GZIP "),
+            Some(UploadCompression::Gzip)
+        );
+        assert_eq!(UploadCompression::parse("brotli"), None);
+    }
+
+    #[test]
+    fn test_content_coding_encode_is_decodable() {
+        let body = br#"{"node":"node-001","logs":[]}"#.repeat(64);
+
+        let gzip = ContentCoding::Gzip.encode(&body).unwrap();
+        let mut decoded = Vec::new();
+        flate2::read::MultiGzDecoder::new(gzip.as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+
+        let zstd = ContentCoding::Zstd.encode(&body).unwrap();
+        assert_eq!(zstd::stream::decode_all(zstd.as_slice()).unwrap(), body);
+
+        assert_eq!(ContentCoding::Identity.encode(&body).unwrap(), body);
+    }
 }
@@ -185,6 +185,9 @@ pub fn make_record(service: &str, time: u64, content: &str) -> SystemLogRecord {
         file: Some("process_e2e.rs".to_string()),
         line: Some(1),
         content: content.to_string(),
+        trace_id: None,
+        span_id: None,
+        fields: Default::default(),
     }
 }
 
@@ -356,6 +359,8 @@ pub fn spawn_server_process(bind_addr: &str, storage_dir: &Path) -> Result<Child
     let child = Command::new(&bin_path)
         .env("SLOG_SERVER_BIND", bind_addr)
         .env("SLOG_STORAGE_DIR", storage_dir)
+        // Unsigned daemons and no zone runtime in these process tests.
+        .env("SLOG_UPLOAD_AUTH", "off")
         .env("BUCKYOS_ROOT", buckyos_root)
         .stdout(Stdio::null())
         .stderr(stderr_stdio)
@@ -539,6 +544,7 @@ pub async fn wait_for_uploaded_count(
                 start_time: None,
                 end_time: None,
                 limit: Some(1000),
+                search: None,
             })
             .await?;
         let count: usize = result.iter().map(|r| r.logs.len()).sum();
@@ -568,6 +574,7 @@ pub async fn query_uploaded_count(
             start_time: None,
             end_time: None,
             limit: Some(5000),
+            search: None,
         })
         .await?;
     Ok(result.iter().map(|r| r.logs.len()).sum())
@@ -586,6 +593,7 @@ pub async fn query_uploaded_contents(
             start_time: None,
             end_time: None,
             limit: Some(5000),
+            search: None,
         })
         .await?;
 
@@ -610,6 +618,7 @@ pub async fn query_uploaded_counts_by_service(
             start_time: None,
             end_time: None,
             limit: None,
+            search: None,
         })
         .await?;
 
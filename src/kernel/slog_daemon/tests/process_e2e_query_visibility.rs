@@ -59,6 +59,7 @@ async fn query_records(
             start_time,
             end_time,
             limit,
+            search: None,
         })
         .await?;
 
@@ -0,0 +1,560 @@
+// Schemas checked before a value is written to the store.
+//
+// A schema is registered for a key pattern: `*` matches exactly one path
+// segment (`services/*/spec`), and a pattern ending with `/` matches every key
+// under it (`system/network/`). Schemas use a subset of JSON Schema: `type`,
+// `enum`, `const`, `properties`, `required`, `additionalProperties`, `items`,
+// `minItems`/`maxItems`, `minLength`/`maxLength`, `minimum`/`maximum` and
+// `pattern`, plus the annotations `title`, `description`, `$comment`,
+// `$schema`, `default` and `examples`. Any other keyword is rejected when the
+// schema is registered, so a schema never silently checks less than it says.
+// Values whose schema has a root type of `"string"` are checked as plain text
+// instead of being parsed as JSON, which is how non-JSON documents such as the
+// rbac policy are covered.
+
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+const TYPE_NAMES: [&str; 7] = [
+    "null", "boolean", "object", "array", "number", "integer", "string",
+];
+
+const SUPPORTED_KEYWORDS: [&str; 20] = [
+    "type",
+    "enum",
+    "const",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "minItems",
+    "maxItems",
+    "minLength",
+    "maxLength",
+    "minimum",
+    "maximum",
+    "pattern",
+    "title",
+    "description",
+    "$comment",
+    "$schema",
+    "default",
+    "examples",
+];
+
+/// First place where a value does not match its schema.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SchemaError {
+    /// JSON pointer of the failing value, `""` for the whole document.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl SchemaError {
+    fn new(pointer: &str, message: impl Into<String>) -> Self {
+        Self {
+            pointer: pointer.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Schemas shipped for the core system documents. A schema registered for
+/// the same pattern replaces the built-in one; registering `{}` turns the
+/// check off.
+pub(crate) fn builtin_schemas() -> Vec<(&'static str, Value)> {
+    vec![
+        // KernelServiceSpec
+        (
+            "services/*/spec",
+            json!({
+                "type": "object",
+                "required": [
+                    "service_doc",
+                    "enable",
+                    "app_index",
+                    "expected_instance_count",
+                    "state",
+                    "spec_config"
+                ],
+                "properties": {
+                    "service_doc": {"type": "object"},
+                    "enable": {"type": "boolean"},
+                    "app_index": {"type": "integer", "minimum": 0, "maximum": 65535},
+                    "expected_instance_count": {"type": "integer", "minimum": 0},
+                    "state": {
+                        "type": "string",
+                        "enum": [
+                            "new", "running", "stopped", "stopping", "restarting",
+                            "updating", "deleted", "New", "Running", "Deployed",
+                            "deployed", "Stopped", "Disable", "Stopping",
+                            "Restarting", "Updating", "Deleted"
+                        ]
+                    },
+                    "spec_config": {"type": "object"}
+                }
+            }),
+        ),
+        // UserSettings
+        (
+            "users/*/settings",
+            json!({
+                "type": "object",
+                "required": ["user_id", "type", "password", "state", "res_pool_id"],
+                "properties": {
+                    "user_id": {"type": "string", "minLength": 1},
+                    "type": {
+                        "type": "string",
+                        "enum": ["admin", "user", "root", "limited", "guest"]
+                    },
+                    "password": {"type": "string"},
+                    "state": {
+                        "type": "string",
+                        "pattern": "^(active|pending|suspended|deleted|banned)(:|$)"
+                    },
+                    "res_pool_id": {"type": "string"},
+                    "is_local": {"type": "boolean"},
+                    "allow_password_change": {"type": ["boolean", "null"]}
+                }
+            }),
+        ),
+        // casbin policy text: blank lines, `#` comments and `p`/`g` rules
+        // with at least two fields after the rule type.
+        (
+            "system/rbac/policy",
+            json!({
+                "type": "string",
+                "pattern": "\\A(?:[ \\t]*(?:#[^\\n]*|[pg][0-9]*[ \\t]*(?:,[^,\\n]*[^,\\s][^,\\n]*){2,})?[ \\t]*\\r?(?:\\n|\\z))*\\z"
+            }),
+        ),
+    ]
+}
+
+/// Whether `pattern` applies to the normalized store key `key`.
+pub(crate) fn pattern_matches(pattern: &str, key: &str) -> bool {
+    if let Some(prefix) = pattern.strip_suffix('/') {
+        let prefix_segments: Vec<&str> = prefix.split('/').collect();
+        let key_segments: Vec<&str> = key.split('/').collect();
+        return key_segments.len() > prefix_segments.len()
+            && segments_match(&prefix_segments, &key_segments[..prefix_segments.len()]);
+    }
+    let pattern_segments: Vec<&str> = pattern.split('/').collect();
+    let key_segments: Vec<&str> = key.split('/').collect();
+    pattern_segments.len() == key_segments.len() && segments_match(&pattern_segments, &key_segments)
+}
+
+fn segments_match(pattern_segments: &[&str], key_segments: &[&str]) -> bool {
+    pattern_segments
+        .iter()
+        .zip(key_segments.iter())
+        .all(|(pattern, segment)| *pattern == "*" || pattern == segment)
+}
+
+/// Rejects schemas using unsupported keywords, or supported keywords with
+/// values of the wrong shape, so a broken schema fails at registration and
+/// not on every write.
+pub(crate) fn check_schema(schema: &Value) -> Result<(), String> {
+    check_schema_at(schema, "")
+}
+
+fn check_schema_at(schema: &Value, pointer: &str) -> Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        return Err(format!("schema at '{}' must be an object", pointer));
+    };
+    let fail = |keyword: &str, reason: &str| {
+        Err(format!(
+            "invalid '{}' in schema at '{}': {}",
+            keyword, pointer, reason
+        ))
+    };
+
+    if let Some(keyword) = schema
+        .keys()
+        .find(|keyword| !SUPPORTED_KEYWORDS.contains(&keyword.as_str()))
+    {
+        return Err(format!(
+            "unsupported keyword '{}' in schema at '{}'",
+            keyword, pointer
+        ));
+    }
+    if let Some(type_value) = schema.get("type") {
+        let names: Vec<&Value> = match type_value {
+            Value::Array(names) => names.iter().collect(),
+            other => vec![other],
+        };
+        if names.is_empty()
+            || names
+                .iter()
+                .any(|name| !name.as_str().is_some_and(|name| TYPE_NAMES.contains(&name)))
+        {
+            return fail("type", "expected a type name or a list of type names");
+        }
+    }
+    if schema.get("enum").is_some_and(|values| !values.is_array()) {
+        return fail("enum", "expected an array");
+    }
+    if let Some(required) = schema.get("required") {
+        if !required
+            .as_array()
+            .is_some_and(|names| names.iter().all(Value::is_string))
+        {
+            return fail("required", "expected an array of property names");
+        }
+    }
+    for keyword in ["minItems", "maxItems", "minLength", "maxLength"] {
+        if schema.get(keyword).is_some_and(|limit| !limit.is_u64()) {
+            return fail(keyword, "expected a non-negative integer");
+        }
+    }
+    for keyword in ["minimum", "maximum"] {
+        if schema.get(keyword).is_some_and(|limit| !limit.is_number()) {
+            return fail(keyword, "expected a number");
+        }
+    }
+    if let Some(pattern) = schema.get("pattern") {
+        let Some(pattern) = pattern.as_str() else {
+            return fail("pattern", "expected a string");
+        };
+        if let Err(err) = Regex::new(pattern) {
+            return fail("pattern", &err.to_string());
+        }
+    }
+    if let Some(properties) = schema.get("properties") {
+        let Some(properties) = properties.as_object() else {
+            return fail("properties", "expected an object");
+        };
+        for (name, sub_schema) in properties {
+            check_schema_at(
+                sub_schema,
+                &format!("{}/properties/{}", pointer, escape_pointer_token(name)),
+            )?;
+        }
+    }
+    match schema.get("additionalProperties") {
+        None | Some(Value::Bool(_)) => {}
+        Some(sub_schema) => {
+            check_schema_at(sub_schema, &format!("{}/additionalProperties", pointer))?
+        }
+    }
+    if let Some(items) = schema.get("items") {
+        check_schema_at(items, &format!("{}/items", pointer))?;
+    }
+    Ok(())
+}
+
+/// Checks a stored value, as written to the store, against `schema`.
+pub(crate) fn validate_value(schema: &Value, raw_value: &str) -> Result<(), SchemaError> {
+    if schema.get("type").and_then(Value::as_str) == Some("string") {
+        return validate_at(schema, &Value::String(raw_value.to_string()), "");
+    }
+    let value: Value = serde_json::from_str(raw_value)
+        .map_err(|err| SchemaError::new("", format!("value is not valid JSON: {}", err)))?;
+    validate_at(schema, &value, "")
+}
+
+fn validate_at(schema: &Value, value: &Value, pointer: &str) -> Result<(), SchemaError> {
+    let Some(schema) = schema.as_object() else {
+        return Ok(());
+    };
+
+    if let Some(type_value) = schema.get("type") {
+        let matched = match type_value {
+            Value::Array(names) => names
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|name| value_has_type(value, name)),
+            Value::String(name) => value_has_type(value, name),
+            _ => true,
+        };
+        if !matched {
+            return Err(SchemaError::new(
+                pointer,
+                format!("expected {}, found {}", type_value, value_type_name(value)),
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return Err(SchemaError::new(
+                pointer,
+                format!("expected constant {}", expected),
+            ));
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return Err(SchemaError::new(
+                pointer,
+                format!("{} is not one of {}", value, Value::Array(allowed.clone())),
+            ));
+        }
+    }
+
+    match value {
+        Value::Object(fields) => validate_object(schema, fields, pointer)?,
+        Value::Array(items) => {
+            check_limit(schema, "minItems", items.len(), pointer, |limit, len| {
+                len >= limit
+            })?;
+            check_limit(schema, "maxItems", items.len(), pointer, |limit, len| {
+                len <= limit
+            })?;
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{}/{}", pointer, index))?;
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count();
+            check_limit(schema, "minLength", length, pointer, |limit, len| {
+                len >= limit
+            })?;
+            check_limit(schema, "maxLength", length, pointer, |limit, len| {
+                len <= limit
+            })?;
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                let regex = Regex::new(pattern).map_err(|err| {
+                    SchemaError::new(pointer, format!("invalid schema pattern: {}", err))
+                })?;
+                if !regex.is_match(text) {
+                    return Err(SchemaError::new(
+                        pointer,
+                        format!("value does not match pattern {}", pattern),
+                    ));
+                }
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or(f64::NAN);
+            if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+                if number < minimum {
+                    return Err(SchemaError::new(
+                        pointer,
+                        format!("{} is less than minimum {}", number, minimum),
+                    ));
+                }
+            }
+            if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+                if number > maximum {
+                    return Err(SchemaError::new(
+                        pointer,
+                        format!("{} is greater than maximum {}", number, maximum),
+                    ));
+                }
+            }
+        }
+        Value::Null | Value::Bool(_) => {}
+    }
+    Ok(())
+}
+
+fn validate_object(
+    schema: &Map<String, Value>,
+    fields: &Map<String, Value>,
+    pointer: &str,
+) -> Result<(), SchemaError> {
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !fields.contains_key(name) {
+                return Err(SchemaError::new(
+                    &format!("{}/{}", pointer, escape_pointer_token(name)),
+                    "required property is missing",
+                ));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (name, field_value) in fields {
+        let field_pointer = format!("{}/{}", pointer, escape_pointer_token(name));
+        if let Some(field_schema) = properties.and_then(|properties| properties.get(name)) {
+            validate_at(field_schema, field_value, &field_pointer)?;
+            continue;
+        }
+        match schema.get("additionalProperties") {
+            Some(Value::Bool(false)) => {
+                return Err(SchemaError::new(
+                    &field_pointer,
+                    "additional property is not allowed",
+                ));
+            }
+            Some(extra_schema @ Value::Object(_)) => {
+                validate_at(extra_schema, field_value, &field_pointer)?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn check_limit(
+    schema: &Map<String, Value>,
+    keyword: &str,
+    actual: usize,
+    pointer: &str,
+    accept: impl Fn(u64, u64) -> bool,
+) -> Result<(), SchemaError> {
+    let Some(limit) = schema.get(keyword).and_then(Value::as_u64) else {
+        return Ok(());
+    };
+    if accept(limit, actual as u64) {
+        return Ok(());
+    }
+    Err(SchemaError::new(
+        pointer,
+        format!("length {} violates {} {}", actual, keyword, limit),
+    ))
+}
+
+fn value_has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => match value {
+            Value::Number(number) => {
+                number.is_i64()
+                    || number.is_u64()
+                    || number.as_f64().is_some_and(|n| n.fract() == 0.0)
+            }
+            _ => false,
+        },
+        "number" => value.is_number(),
+        other => value_type_name(value) == other,
+    }
+}
+
+fn value_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+    }
+}
+
+fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin(pattern: &str) -> Value {
+        builtin_schemas()
+            .into_iter()
+            .find(|(builtin_pattern, _)| *builtin_pattern == pattern)
+            .map(|(_, schema)| schema)
+            .unwrap()
+    }
+
+    #[test]
+    fn builtin_schemas_are_well_formed() {
+        for (pattern, schema) in builtin_schemas() {
+            assert!(check_schema(&schema).is_ok(), "{}", pattern);
+        }
+        assert!(check_schema(&json!({"type": "text"})).is_err());
+        assert!(
+            check_schema(&json!({"properties": {"a/b": {"pattern": "("}}}))
+                .unwrap_err()
+                .starts_with("invalid 'pattern' in schema at '/properties/a~1b'")
+        );
+    }
+
+    #[test]
+    fn unsupported_keywords_are_rejected() {
+        for keyword in ["oneOf", "anyOf", "allOf", "not", "$ref", "format"] {
+            let schema = json!({"properties": {"port": {keyword: "x"}}});
+            assert_eq!(
+                check_schema(&schema).unwrap_err(),
+                format!(
+                    "unsupported keyword '{}' in schema at '/properties/port'",
+                    keyword
+                )
+            );
+        }
+        assert!(check_schema(&json!({
+            "title": "port",
+            "description": "listen port",
+            "default": 80,
+            "type": "integer"
+        }))
+        .is_ok());
+    }
+
+    #[test]
+    fn key_patterns_match_segments_and_prefixes() {
+        assert!(pattern_matches(
+            "services/*/spec",
+            "services/scheduler/spec"
+        ));
+        assert!(!pattern_matches(
+            "services/*/spec",
+            "services/scheduler/spec/x"
+        ));
+        assert!(!pattern_matches(
+            "services/*/spec",
+            "users/alice/apps/a/spec"
+        ));
+        assert!(pattern_matches(
+            "system/network/",
+            "system/network/zone/dns"
+        ));
+        assert!(!pattern_matches("system/network/", "system/network"));
+    }
+
+    #[test]
+    fn reports_pointer_of_first_violation() {
+        let settings = builtin("users/*/settings");
+        let valid = json!({
+            "user_id": "alice",
+            "type": "admin",
+            "password": "hash",
+            "state": "suspended:too many logins",
+            "res_pool_id": "default",
+            "is_local": true
+        });
+        assert!(validate_value(&settings, &valid.to_string()).is_ok());
+
+        let mut missing = valid.clone();
+        missing.as_object_mut().unwrap().remove("res_pool_id");
+        assert_eq!(
+            validate_value(&settings, &missing.to_string())
+                .unwrap_err()
+                .pointer,
+            "/res_pool_id"
+        );
+
+        let mut wrong_type = valid.clone();
+        wrong_type["type"] = json!("owner");
+        assert_eq!(
+            validate_value(&settings, &wrong_type.to_string())
+                .unwrap_err()
+                .pointer,
+            "/type"
+        );
+
+        let nested = json!({
+            "type": "object",
+            "properties": {"nodes": {"type": "array", "items": {"type": "integer"}}}
+        });
+        let err = validate_value(&nested, r#"{"nodes":[1,"2"]}"#).unwrap_err();
+        assert_eq!(err.pointer, "/nodes/1");
+        assert_eq!(err.message, "expected \"integer\", found string");
+
+        let err = validate_value(&settings, "{\"user_id\":").unwrap_err();
+        assert_eq!(err.pointer, "");
+        assert!(err.message.starts_with("value is not valid JSON"));
+    }
+
+    #[test]
+    fn rbac_policy_is_checked_as_text() {
+        let policy = builtin("system/rbac/policy");
+        let text = "# roles\np, kernel, obj://*, all,allow\n\ng, alice, admin\n";
+        assert!(validate_value(&policy, text).is_ok());
+        assert!(validate_value(&policy, "g, alice, admin").is_ok());
+        assert!(validate_value(&policy, "g, alice\n").is_err());
+        assert!(validate_value(&policy, "{\"p\": 1}").is_err());
+    }
+}
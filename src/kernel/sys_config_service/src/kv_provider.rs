@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 use async_trait::async_trait;
+use buckyos_api::{SystemConfigHistoryEntry, SystemConfigWatchResult};
 use buckyos_kit::*;
 use serde_json::Value;
 use std::collections::HashMap;
 use thiserror::Error;
+use tokio::sync::watch;
 
 #[derive(Error, Debug)]
 pub enum KVStoreErrors {
@@ -27,16 +29,51 @@ pub type Result<T> = std::result::Result<T, KVStoreErrors>;
 pub trait KVStoreProvider: Send + Sync {
     async fn get(&self, key: String) -> Result<Option<String>>;
     async fn get_with_revision(&self, key: String) -> Result<Option<(String, u64)>>;
-    async fn set(&self, key: String, value: String) -> Result<()>;
-    async fn set_by_path(&self, key: String, json_path: String, value: &Value) -> Result<()>;
+    // `writer` is recorded in the key history, `None` for internal writes.
+    async fn set(&self, key: String, value: String, writer: Option<&str>) -> Result<()>;
+    async fn set_by_path(
+        &self,
+        key: String,
+        json_path: String,
+        value: &Value,
+        writer: Option<&str>,
+    ) -> Result<()>;
     async fn exec_tx(
         &self,
         tx: HashMap<String, KVAction>,
         main_key: Option<(String, u64)>,
+        writer: Option<&str>,
     ) -> Result<()>;
-    async fn create(&self, key: &str, value: &str) -> Result<()>;
-    async fn delete(&self, key: &str) -> Result<()>;
+    async fn create(&self, key: &str, value: &str, writer: Option<&str>) -> Result<()>;
+    async fn delete(&self, key: &str, writer: Option<&str>) -> Result<()>;
     async fn list_data(&self, key_perfix: &str) -> Result<HashMap<String, String>>;
     async fn list_keys(&self, key_perfix: &str) -> Result<Vec<String>>;
     async fn list_direct_children(&self, prefix: String) -> Result<Vec<String>>;
+
+    /// Up to `limit` change events under `key_prefix` from `start_revision`
+    /// (inclusive, `None` meaning after the current revision), oldest first.
+    async fn list_changes(
+        &self,
+        key_prefix: &str,
+        start_revision: Option<u64>,
+        limit: usize,
+    ) -> Result<SystemConfigWatchResult>;
+    /// Receives the store revision after every committed write.
+    fn subscribe_changes(&self) -> watch::Receiver<u64>;
+    /// Store-wide revision of the latest write.
+    async fn store_revision(&self) -> Result<u64>;
+
+    /// Up to `limit` recorded values of `key`, newest first.
+    async fn list_history(&self, key: &str, limit: usize) -> Result<Vec<SystemConfigHistoryEntry>>;
+    /// Drop all but the newest `keep` history entries of every key under
+    /// `key_prefix`, returns how many were removed.
+    async fn prune_history(&self, key_prefix: &str, keep: usize) -> Result<usize>;
+    /// How many history entries each write keeps for its key.
+    async fn get_history_limit(&self) -> Result<usize>;
+    async fn set_history_limit(&self, limit: usize) -> Result<()>;
+
+    /// Schemas registered for key patterns, as schema text by pattern.
+    async fn list_schemas(&self) -> Result<HashMap<String, String>>;
+    /// Register the schema for `pattern`, `None` removes it.
+    async fn set_schema(&self, pattern: &str, schema: Option<&str>) -> Result<()>;
 }
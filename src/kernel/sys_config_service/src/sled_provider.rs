@@ -1,5 +1,9 @@
 use crate::kv_provider::*;
 use async_trait::async_trait;
+use buckyos_api::{
+    SystemConfigChange, SystemConfigChangeEvent, SystemConfigEventKind, SystemConfigHistoryEntry,
+    SystemConfigWatchResult,
+};
 use buckyos_kit::*;
 use log::*;
 use serde_json::Value;
@@ -7,20 +11,59 @@ use sled::{
     transaction::{ConflictableTransactionError, TransactionError, TransactionalTree},
     Db,
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+use tokio::sync::watch;
 
 type TxResult<T> = std::result::Result<T, ConflictableTransactionError<KVStoreErrors>>;
 
 pub struct SledStore {
     db: Arc<Db>,
+    changes: watch::Sender<u64>,
+    // Registered schemas, read on every write; dropped whenever `set_schema`
+    // changes SCHEMA_PREFIX.
+    schemas: RwLock<Option<HashMap<String, String>>>,
 }
 
 impl SledStore {
     const INTERNAL_META_PREFIX: &'static str = "__meta/";
     const REVISION_PREFIX: &'static str = "__meta/revision/";
+    // Store-wide revision, bumped once per write together with a change log entry.
+    const STORE_REVISION_KEY: &'static str = "__meta/store_revision";
+    const CHANGE_LOG_PREFIX: &'static str = "__meta/changes/";
+    // Watchers further behind than this are told to re-read instead.
+    const MAX_CHANGE_LOG_ENTRIES: u64 = 4096;
+    // Per-key history: HISTORY_PREFIX + key + '#' + zero padded key revision.
+    const HISTORY_PREFIX: &'static str = "__meta/history/";
+    const HISTORY_LIMIT_KEY: &'static str = "__meta/history_limit";
+    const DEFAULT_HISTORY_LIMIT: u64 = 32;
+    const SCHEMA_PREFIX: &'static str = "__meta/schemas/";
 
     fn from_db(db: Db) -> Self {
-        SledStore { db: Arc::new(db) }
+        let revision = db
+            .get(Self::STORE_REVISION_KEY.as_bytes())
+            .ok()
+            .flatten()
+            .and_then(|raw| String::from_utf8(raw.to_vec()).ok())
+            .and_then(|raw| raw.parse::<u64>().ok())
+            .unwrap_or(0);
+        let (changes, _) = watch::channel(revision);
+        SledStore {
+            db: Arc::new(db),
+            changes,
+            schemas: RwLock::new(None),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn new_temporary() -> Self {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .expect("open temporary sled db");
+        Self::from_db(db)
     }
 
     pub fn new() -> std::result::Result<Self, Box<dyn std::error::Error>> {
@@ -38,8 +81,40 @@ impl SledStore {
         key.starts_with(Self::INTERNAL_META_PREFIX)
     }
 
+    fn change_key(revision: u64) -> String {
+        format!("{}{:020}", Self::CHANGE_LOG_PREFIX, revision)
+    }
+
+    fn history_key(key: &str, revision: u64) -> String {
+        format!("{}{}#{:020}", Self::HISTORY_PREFIX, key, revision)
+    }
+
+    /// Split a history entry key back into the config key and its revision.
+    fn parse_history_key(raw_key: &[u8]) -> Option<(String, u64)> {
+        let raw_key = std::str::from_utf8(raw_key).ok()?;
+        let (key, revision) = raw_key
+            .strip_prefix(Self::HISTORY_PREFIX)?
+            .rsplit_once('#')?;
+        if revision.len() != 20 {
+            return None;
+        }
+        Some((key.to_string(), revision.parse().ok()?))
+    }
+
+    fn key_in_prefix(key: &str, prefix: &str) -> bool {
+        let prefix = prefix.trim_end_matches('/');
+        prefix.is_empty()
+            || key == prefix
+            || key
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+
     fn current_revision(db: &TransactionalTree, key: &str) -> TxResult<u64> {
-        let revision_key = Self::revision_key(key);
+        Self::read_revision(db, &Self::revision_key(key))
+    }
+
+    fn read_revision(db: &TransactionalTree, revision_key: &str) -> TxResult<u64> {
         let raw_revision = db.get(revision_key.as_bytes())?;
         let Some(raw_revision) = raw_revision else {
             return Ok(0);
@@ -69,6 +144,141 @@ impl SledStore {
         db.insert(revision_key.as_bytes(), revision.to_string().as_bytes())?;
         Ok(())
     }
+
+    /// Record one write in the change log, returns its store revision.
+    fn append_change(
+        db: &TransactionalTree,
+        kind: SystemConfigEventKind,
+        changes: Vec<SystemConfigChange>,
+    ) -> TxResult<u64> {
+        let revision = Self::read_revision(db, Self::STORE_REVISION_KEY)?
+            .checked_add(1)
+            .ok_or_else(|| {
+                ConflictableTransactionError::Abort(KVStoreErrors::InternalError(
+                    "store revision overflow".to_string(),
+                ))
+            })?;
+        let event = SystemConfigChangeEvent {
+            revision,
+            kind,
+            changes,
+        };
+        let raw_event = serde_json::to_vec(&event).map_err(|err| {
+            ConflictableTransactionError::Abort(KVStoreErrors::InternalError(err.to_string()))
+        })?;
+        db.insert(Self::change_key(revision).as_bytes(), raw_event)?;
+        db.insert(
+            Self::STORE_REVISION_KEY.as_bytes(),
+            revision.to_string().as_bytes(),
+        )?;
+        if revision > Self::MAX_CHANGE_LOG_ENTRIES {
+            db.remove(Self::change_key(revision - Self::MAX_CHANGE_LOG_ENTRIES).as_bytes())?;
+        }
+        Ok(revision)
+    }
+
+    /// Record the value `key` was written with at `revision`; entries past the
+    /// history limit are dropped by `trim_history` once the write commits.
+    fn record_history(
+        db: &TransactionalTree,
+        key: &str,
+        revision: u64,
+        value: Option<&str>,
+        writer: Option<&str>,
+    ) -> TxResult<()> {
+        let entry = SystemConfigHistoryEntry {
+            revision,
+            value: value.map(|value| value.to_string()),
+            timestamp: buckyos_get_unix_timestamp(),
+            writer: writer.map(|writer| writer.to_string()),
+        };
+        let raw_entry = serde_json::to_vec(&entry).map_err(|err| {
+            ConflictableTransactionError::Abort(KVStoreErrors::InternalError(err.to_string()))
+        })?;
+        db.insert(Self::history_key(key, revision).as_bytes(), raw_entry)?;
+        Ok(())
+    }
+
+    fn read_history_limit(&self) -> Result<u64> {
+        let Some(raw_limit) = self
+            .db
+            .get(Self::HISTORY_LIMIT_KEY.as_bytes())
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?
+        else {
+            return Ok(Self::DEFAULT_HISTORY_LIMIT);
+        };
+        String::from_utf8(raw_limit.to_vec())
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?
+            .parse::<u64>()
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))
+    }
+
+    /// Drop every history entry of `key` older than the history limit. A range
+    /// delete, so entries kept under an earlier, larger limit go as well.
+    fn trim_history(&self, key: &str) -> Result<()> {
+        let limit = self.read_history_limit()?;
+        let Some(raw_revision) = self
+            .db
+            .get(Self::revision_key(key).as_bytes())
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?
+        else {
+            return Ok(());
+        };
+        let revision = String::from_utf8(raw_revision.to_vec())
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?
+            .parse::<u64>()
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+        if revision <= limit {
+            return Ok(());
+        }
+
+        // zero padded revisions keep `key#...` lookalike keys out of the range
+        let stale = Self::history_key(key, 0)..=Self::history_key(key, revision - limit);
+        let mut batch = sled::Batch::default();
+        for raw_key in self.db.range(stale).keys() {
+            let raw_key = raw_key.map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            batch.remove(raw_key);
+        }
+        self.db
+            .apply_batch(batch)
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))
+    }
+
+    fn read_store_revision(&self) -> Result<u64> {
+        let Some(raw_revision) = self
+            .db
+            .get(Self::STORE_REVISION_KEY.as_bytes())
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?
+        else {
+            return Ok(0);
+        };
+        String::from_utf8(raw_revision.to_vec())
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?
+            .parse::<u64>()
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))
+    }
+
+    fn oldest_change_revision(&self) -> Result<Option<u64>> {
+        let Some(item) = self.db.scan_prefix(Self::CHANGE_LOG_PREFIX).next() else {
+            return Ok(None);
+        };
+        let (key, _) = item.map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+        let revision = String::from_utf8(key[Self::CHANGE_LOG_PREFIX.len()..].to_vec())
+            .ok()
+            .and_then(|raw| raw.parse::<u64>().ok())
+            .ok_or_else(|| {
+                KVStoreErrors::InternalError("invalid change log entry key".to_string())
+            })?;
+        Ok(Some(revision))
+    }
+
+    fn flush_and_notify(&self, revision: u64) -> Result<()> {
+        self.db
+            .flush()
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+        self.changes.send_replace(revision);
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -115,19 +325,27 @@ impl KVStoreProvider for SledStore {
         }
     }
 
-    async fn set(&self, key: String, value: String) -> Result<()> {
+    async fn set(&self, key: String, value: String, writer: Option<&str>) -> Result<()> {
         let tx_result = self.db.transaction(|db| {
             let next_revision = Self::next_revision(db, &key)?;
             db.insert(key.as_bytes(), value.as_bytes())?;
             Self::write_revision(db, &key, next_revision)?;
-            Ok(())
+            Self::record_history(db, &key, next_revision, Some(&value), writer)?;
+            Self::append_change(
+                db,
+                SystemConfigEventKind::Put,
+                vec![SystemConfigChange {
+                    key: key.clone(),
+                    value: Some(value.clone()),
+                    version: next_revision,
+                }],
+            )
         });
 
         match tx_result {
-            Ok(_) => {
-                self.db
-                    .flush()
-                    .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            Ok(revision) => {
+                self.trim_history(&key)?;
+                self.flush_and_notify(revision)?;
             }
             Err(TransactionError::Abort(err)) => return Err(err),
             Err(TransactionError::Storage(err)) => {
@@ -138,7 +356,13 @@ impl KVStoreProvider for SledStore {
         Ok(())
     }
 
-    async fn set_by_path(&self, key: String, json_path: String, value: &Value) -> Result<()> {
+    async fn set_by_path(
+        &self,
+        key: String,
+        json_path: String,
+        value: &Value,
+        writer: Option<&str>,
+    ) -> Result<()> {
         let tx_result = self.db.transaction(|db| {
             // Get the current value
             let current_value = match db.get(key.as_bytes())? {
@@ -161,23 +385,30 @@ impl KVStoreProvider for SledStore {
             // Update the value using json_path
             set_json_by_path(&mut current_value, &json_path, Some(value));
 
-            // Convert back to bytes
-            let updated_value = serde_json::to_vec(&current_value).map_err(|err| {
+            // Convert back to a string
+            let updated_value = serde_json::to_string(&current_value).map_err(|err| {
                 ConflictableTransactionError::Abort(KVStoreErrors::InternalError(err.to_string()))
             })?;
 
             let next_revision = Self::next_revision(db, &key)?;
-            db.insert(key.as_bytes(), updated_value)?;
+            db.insert(key.as_bytes(), updated_value.as_bytes())?;
             Self::write_revision(db, &key, next_revision)?;
-            Ok(())
+            Self::record_history(db, &key, next_revision, Some(&updated_value), writer)?;
+            Self::append_change(
+                db,
+                SystemConfigEventKind::Put,
+                vec![SystemConfigChange {
+                    key: key.clone(),
+                    value: Some(updated_value),
+                    version: next_revision,
+                }],
+            )
         });
 
         match tx_result {
-            Ok(_) => {
-                self.db
-                    .flush()
-                    .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
-                Ok(())
+            Ok(revision) => {
+                self.trim_history(&key)?;
+                self.flush_and_notify(revision)
             }
             Err(sled::transaction::TransactionError::Abort(err)) => Err(err),
             Err(sled::transaction::TransactionError::Storage(err)) => {
@@ -186,7 +417,7 @@ impl KVStoreProvider for SledStore {
         }
     }
 
-    async fn create(&self, key: &str, value: &str) -> Result<()> {
+    async fn create(&self, key: &str, value: &str, writer: Option<&str>) -> Result<()> {
         let tx_result = self.db.transaction(|db| {
             if db.get(key.as_bytes())?.is_some() {
                 return Err(ConflictableTransactionError::Abort(
@@ -197,14 +428,22 @@ impl KVStoreProvider for SledStore {
             let next_revision = Self::next_revision(db, key)?;
             db.insert(key.as_bytes(), value.as_bytes())?;
             Self::write_revision(db, key, next_revision)?;
-            Ok(())
+            Self::record_history(db, key, next_revision, Some(value), writer)?;
+            Self::append_change(
+                db,
+                SystemConfigEventKind::Put,
+                vec![SystemConfigChange {
+                    key: key.to_string(),
+                    value: Some(value.to_string()),
+                    version: next_revision,
+                }],
+            )
         });
 
         match tx_result {
-            Ok(_) => {
-                self.db
-                    .flush()
-                    .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            Ok(revision) => {
+                self.trim_history(key)?;
+                self.flush_and_notify(revision)?;
                 debug!("Sled Create key:[{}] to value:[{}]", key, value);
                 Ok(())
             }
@@ -222,7 +461,7 @@ impl KVStoreProvider for SledStore {
         }
     }
 
-    async fn delete(&self, key: &str) -> Result<()> {
+    async fn delete(&self, key: &str, writer: Option<&str>) -> Result<()> {
         let tx_result = self.db.transaction(|db| {
             if db.get(key.as_bytes())?.is_none() {
                 return Err(ConflictableTransactionError::Abort(
@@ -233,14 +472,22 @@ impl KVStoreProvider for SledStore {
             let next_revision = Self::next_revision(db, key)?;
             db.remove(key.as_bytes())?;
             Self::write_revision(db, key, next_revision)?;
-            Ok(())
+            Self::record_history(db, key, next_revision, None, writer)?;
+            Self::append_change(
+                db,
+                SystemConfigEventKind::Delete,
+                vec![SystemConfigChange {
+                    key: key.to_string(),
+                    value: None,
+                    version: next_revision,
+                }],
+            )
         });
 
         match tx_result {
-            Ok(_) => {
-                self.db
-                    .flush()
-                    .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            Ok(revision) => {
+                self.trim_history(key)?;
+                self.flush_and_notify(revision)?;
             }
             Err(TransactionError::Abort(err)) => return Err(err),
             Err(TransactionError::Storage(err)) => {
@@ -320,6 +567,7 @@ impl KVStoreProvider for SledStore {
         &self,
         tx: HashMap<String, KVAction>,
         main_key: Option<(String, u64)>,
+        writer: Option<&str>,
     ) -> Result<()> {
         let tx_result = self.db.transaction(|db| {
             let mut batch = sled::Batch::default();
@@ -346,19 +594,18 @@ impl KVStoreProvider for SledStore {
                 revision_updates.insert(key.clone(), next_revision);
             }
 
+            let mut changes = Vec::with_capacity(tx.len());
             for (key, action) in tx.iter() {
-                match action {
+                let new_value = match action {
                     KVAction::Create(value) => {
                         if db.get(key.as_bytes())?.is_some() {
                             return Err(ConflictableTransactionError::Abort(
                                 KVStoreErrors::KeyExist(key.to_string()),
                             ));
                         }
-                        batch.insert(key.as_bytes(), value.as_bytes());
-                    }
-                    KVAction::Update(value) => {
-                        batch.insert(key.as_bytes(), value.as_bytes());
+                        Some(value.clone())
                     }
+                    KVAction::Update(value) => Some(value.clone()),
                     KVAction::Append(value) => {
                         let existing_value = match db.get(key.as_bytes())? {
                             Some(val) => val,
@@ -376,8 +623,7 @@ impl KVStoreProvider for SledStore {
                                 ))
                             })?;
 
-                        let updated_value = format!("{}{}", existing_value, value);
-                        batch.insert(key.as_bytes(), updated_value.as_bytes());
+                        Some(format!("{}{}", existing_value, value))
                     }
                     KVAction::SetByJsonPath(value) => {
                         let existing_value = match db.get(key.as_bytes())? {
@@ -408,23 +654,35 @@ impl KVStoreProvider for SledStore {
                             }
                         }
 
-                        let updated_value = serde_json::to_vec(&existing_value).map_err(|err| {
-                            ConflictableTransactionError::Abort(KVStoreErrors::InternalError(
-                                err.to_string(),
-                            ))
-                        })?;
-
-                        batch.insert(key.as_bytes(), updated_value);
-                    }
-                    KVAction::Remove => {
-                        batch.remove(key.as_bytes());
+                        let updated_value =
+                            serde_json::to_string(&existing_value).map_err(|err| {
+                                ConflictableTransactionError::Abort(KVStoreErrors::InternalError(
+                                    err.to_string(),
+                                ))
+                            })?;
+                        Some(updated_value)
                     }
-                }
+                    KVAction::Remove => None,
+                };
 
-                if !revision_updates.contains_key(key) {
-                    let next_revision = Self::next_revision(db, key)?;
-                    revision_updates.insert(key.clone(), next_revision);
+                match new_value.as_ref() {
+                    Some(value) => batch.insert(key.as_bytes(), value.as_bytes()),
+                    None => batch.remove(key.as_bytes()),
                 }
+
+                let version = match revision_updates.get(key) {
+                    Some(revision) => *revision,
+                    None => {
+                        let next_revision = Self::next_revision(db, key)?;
+                        revision_updates.insert(key.clone(), next_revision);
+                        next_revision
+                    }
+                };
+                changes.push(SystemConfigChange {
+                    key: key.clone(),
+                    value: new_value,
+                    version,
+                });
             }
 
             for (key, revision) in revision_updates.into_iter() {
@@ -433,15 +691,25 @@ impl KVStoreProvider for SledStore {
             }
 
             db.apply_batch(&batch)?;
-            Ok(())
+            for change in changes.iter() {
+                Self::record_history(
+                    db,
+                    &change.key,
+                    change.version,
+                    change.value.as_deref(),
+                    writer,
+                )?;
+            }
+            changes.sort_by(|a, b| a.key.cmp(&b.key));
+            Self::append_change(db, SystemConfigEventKind::Tx, changes)
         });
 
         match tx_result {
-            Ok(_) => {
-                self.db
-                    .flush()
-                    .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
-                Ok(())
+            Ok(revision) => {
+                for key in tx.keys() {
+                    self.trim_history(key)?;
+                }
+                self.flush_and_notify(revision)
             }
             Err(TransactionError::Abort(err)) => Err(err),
             Err(TransactionError::Storage(err)) => {
@@ -449,6 +717,185 @@ impl KVStoreProvider for SledStore {
             }
         }
     }
+
+    async fn list_changes(
+        &self,
+        key_prefix: &str,
+        start_revision: Option<u64>,
+        limit: usize,
+    ) -> Result<SystemConfigWatchResult> {
+        let head = self.read_store_revision()?;
+        let start = start_revision.unwrap_or(head + 1).max(1);
+        let mut result = SystemConfigWatchResult {
+            revision: head,
+            next_revision: head + 1,
+            ..Default::default()
+        };
+        if start == head + 1 {
+            return Ok(result);
+        }
+
+        // A start past the head means the store was replaced under the watcher.
+        let oldest = self.oldest_change_revision()?.unwrap_or(head + 1);
+        if start > head + 1 || start < oldest {
+            result.compacted = true;
+            return Ok(result);
+        }
+
+        let range = Self::change_key(start)..=Self::change_key(head);
+        for item in self.db.range(range) {
+            let (_, raw_event) =
+                item.map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            let mut event: SystemConfigChangeEvent = serde_json::from_slice(&raw_event)
+                .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            result.next_revision = event.revision + 1;
+            event
+                .changes
+                .retain(|change| Self::key_in_prefix(&change.key, key_prefix));
+            if event.changes.is_empty() {
+                continue;
+            }
+            result.events.push(event);
+            if result.events.len() >= limit {
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    fn subscribe_changes(&self) -> watch::Receiver<u64> {
+        self.changes.subscribe()
+    }
+
+    async fn store_revision(&self) -> Result<u64> {
+        self.read_store_revision()
+    }
+
+    async fn list_history(&self, key: &str, limit: usize) -> Result<Vec<SystemConfigHistoryEntry>> {
+        let history_prefix = format!("{}{}#", Self::HISTORY_PREFIX, key);
+        let mut entries = Vec::new();
+        // Newest first; keys of `key#...` children are skipped by the parser.
+        for item in self.db.scan_prefix(history_prefix.as_bytes()).rev() {
+            let (raw_key, raw_entry) =
+                item.map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            if Self::parse_history_key(&raw_key).map(|(entry_key, _)| entry_key)
+                != Some(key.to_string())
+            {
+                continue;
+            }
+            let entry: SystemConfigHistoryEntry = serde_json::from_slice(&raw_entry)
+                .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            entries.push(entry);
+            if entries.len() >= limit {
+                break;
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn prune_history(&self, key_prefix: &str, keep: usize) -> Result<usize> {
+        if keep == 0 {
+            return Err(KVStoreErrors::InternalError(
+                "history keep must be at least 1".to_string(),
+            ));
+        }
+        let scan_prefix = format!(
+            "{}{}",
+            Self::HISTORY_PREFIX,
+            key_prefix.trim_end_matches('/')
+        );
+        let mut entries_by_key: HashMap<String, Vec<sled::IVec>> = HashMap::new();
+        for raw_key in self.db.scan_prefix(scan_prefix.as_bytes()).keys() {
+            let raw_key = raw_key.map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            let Some((key, _)) = Self::parse_history_key(&raw_key) else {
+                continue;
+            };
+            if Self::key_in_prefix(&key, key_prefix) {
+                entries_by_key.entry(key).or_default().push(raw_key);
+            }
+        }
+
+        let mut batch = sled::Batch::default();
+        let mut removed = 0;
+        for raw_keys in entries_by_key.values() {
+            // entries are in revision order, the newest `keep` are at the end
+            let stale_count = raw_keys.len().saturating_sub(keep);
+            for raw_key in raw_keys.iter().take(stale_count) {
+                batch.remove(raw_key.clone());
+                removed += 1;
+            }
+        }
+        self.db
+            .apply_batch(batch)
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+        self.db
+            .flush()
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+        Ok(removed)
+    }
+
+    async fn get_history_limit(&self) -> Result<usize> {
+        Ok(self.read_history_limit()? as usize)
+    }
+
+    async fn set_history_limit(&self, limit: usize) -> Result<()> {
+        if limit == 0 {
+            return Err(KVStoreErrors::InternalError(
+                "history limit must be at least 1".to_string(),
+            ));
+        }
+        self.db
+            .insert(
+                Self::HISTORY_LIMIT_KEY.as_bytes(),
+                limit.to_string().as_bytes(),
+            )
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+        self.db
+            .flush()
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+        Ok(())
+    }
+
+    async fn list_schemas(&self) -> Result<HashMap<String, String>> {
+        if let Some(schemas) = self.schemas.read().unwrap().as_ref() {
+            return Ok(schemas.clone());
+        }
+        // Filled under the write lock so a concurrent `set_schema` cannot be
+        // overwritten by a scan taken before it.
+        let mut cached = self.schemas.write().unwrap();
+        if let Some(schemas) = cached.as_ref() {
+            return Ok(schemas.clone());
+        }
+        let mut schemas = HashMap::new();
+        for item in self.db.scan_prefix(Self::SCHEMA_PREFIX.as_bytes()) {
+            let (key, value) = item.map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            let key = String::from_utf8(key.to_vec())
+                .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            let value = String::from_utf8(value.to_vec())
+                .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+            schemas.insert(key[Self::SCHEMA_PREFIX.len()..].to_string(), value);
+        }
+        *cached = Some(schemas.clone());
+        Ok(schemas)
+    }
+
+    async fn set_schema(&self, pattern: &str, schema: Option<&str>) -> Result<()> {
+        let schema_key = format!("{}{}", Self::SCHEMA_PREFIX, pattern);
+        let mut cached = self.schemas.write().unwrap();
+        *cached = None;
+        match schema {
+            Some(schema) => self
+                .db
+                .insert(schema_key.as_bytes(), schema.as_bytes())
+                .map(|_| ()),
+            None => self.db.remove(schema_key.as_bytes()).map(|_| ()),
+        }
+        .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+        self.db
+            .flush()
+            .map_err(|err| KVStoreErrors::InternalError(err.to_string()))?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -457,11 +904,7 @@ mod tests {
     use serde_json::json;
 
     fn setup_store() -> SledStore {
-        let db = sled::Config::new()
-            .temporary(true)
-            .open()
-            .expect("open temporary sled db");
-        SledStore::from_db(db)
+        SledStore::new_temporary()
     }
 
     #[tokio::test]
@@ -469,7 +912,7 @@ mod tests {
         let store = setup_store();
 
         store
-            .create("users/alice/profile", r#"{"name":"alice"}"#)
+            .create("users/alice/profile", r#"{"name":"alice"}"#, None)
             .await
             .expect("create key");
         assert_eq!(
@@ -484,6 +927,7 @@ mod tests {
             .set(
                 "users/alice/profile".to_string(),
                 r#"{"name":"alice-2"}"#.to_string(),
+                None,
             )
             .await
             .expect("set key");
@@ -500,6 +944,7 @@ mod tests {
                 "users/alice/profile".to_string(),
                 "/name".to_string(),
                 &json!("alice-3"),
+                None,
             )
             .await
             .expect("set by path");
@@ -512,7 +957,7 @@ mod tests {
         );
 
         store
-            .delete("users/alice/profile")
+            .delete("users/alice/profile", None)
             .await
             .expect("delete key");
         assert_eq!(
@@ -536,7 +981,7 @@ mod tests {
         let store = setup_store();
 
         store
-            .create("users/alice/guard", "v1")
+            .create("users/alice/guard", "v1", None)
             .await
             .expect("create guard");
 
@@ -546,7 +991,7 @@ mod tests {
             KVAction::Create("payload-1".to_string()),
         );
         store
-            .exec_tx(first_tx, Some(("users/alice/guard".to_string(), 1)))
+            .exec_tx(first_tx, Some(("users/alice/guard".to_string(), 1)), None)
             .await
             .expect("first tx should pass");
 
@@ -571,7 +1016,7 @@ mod tests {
             KVAction::Update("payload-2".to_string()),
         );
         let err = store
-            .exec_tx(stale_tx, Some(("users/alice/guard".to_string(), 1)))
+            .exec_tx(stale_tx, Some(("users/alice/guard".to_string(), 1)), None)
             .await
             .expect_err("stale tx should fail");
 
@@ -602,7 +1047,7 @@ mod tests {
         let store = setup_store();
 
         store
-            .create("users/alice/profile", "v1")
+            .create("users/alice/profile", "v1", None)
             .await
             .expect("create profile");
 
@@ -620,4 +1065,231 @@ mod tests {
         assert!(user_data.contains_key("users/alice/profile"));
         assert!(!user_data.contains_key("__meta/revision/users/alice/profile"));
     }
+
+    #[tokio::test]
+    async fn schemas_are_stored_outside_the_key_space() {
+        let store = setup_store();
+
+        store
+            .set_schema("users/*/settings", Some(r#"{"type":"object"}"#))
+            .await
+            .expect("set schema");
+        let schemas = store.list_schemas().await.expect("list schemas");
+        assert_eq!(
+            schemas.get("users/*/settings").map(String::as_str),
+            Some(r#"{"type":"object"}"#)
+        );
+        assert!(store.list_data("").await.expect("list data").is_empty());
+        assert_eq!(store.store_revision().await.expect("store revision"), 0);
+
+        store
+            .set_schema("users/*/settings", Some(r#"{"type":"string"}"#))
+            .await
+            .expect("replace schema");
+        let schemas = store.list_schemas().await.expect("list schemas");
+        assert_eq!(
+            schemas.get("users/*/settings").map(String::as_str),
+            Some(r#"{"type":"string"}"#)
+        );
+
+        store
+            .set_schema("users/*/settings", None)
+            .await
+            .expect("remove schema");
+        assert!(store.list_schemas().await.expect("list schemas").is_empty());
+    }
+
+    #[tokio::test]
+    async fn list_changes_resumes_from_revision_and_filters_prefix() {
+        let store = setup_store();
+        let mut changes_rx = store.subscribe_changes();
+
+        store
+            .create("users/alice/profile", "v1", None)
+            .await
+            .expect("create");
+        store
+            .set(
+                "users/alice-2/profile".to_string(),
+                "other".to_string(),
+                None,
+            )
+            .await
+            .expect("set sibling");
+        let mut tx = HashMap::new();
+        tx.insert(
+            "users/alice/settings".to_string(),
+            KVAction::Create("s1".to_string()),
+        );
+        tx.insert("users/alice/profile".to_string(), KVAction::Remove);
+        store.exec_tx(tx, None, None).await.expect("exec tx");
+        assert!(changes_rx.has_changed().expect("sender alive"));
+        assert_eq!(*changes_rx.borrow_and_update(), 3);
+
+        let result = store
+            .list_changes("users/alice", Some(1), 100)
+            .await
+            .expect("list changes");
+        assert_eq!(result.revision, 3);
+        assert_eq!(result.next_revision, 4);
+        assert!(!result.compacted);
+        assert_eq!(result.events.len(), 2);
+        assert_eq!(result.events[0].kind, SystemConfigEventKind::Put);
+        assert_eq!(result.events[0].changes[0].value.as_deref(), Some("v1"));
+        assert_eq!(result.events[1].revision, 3);
+        assert_eq!(result.events[1].kind, SystemConfigEventKind::Tx);
+        let tx_changes = &result.events[1].changes;
+        assert_eq!(tx_changes[0].key, "users/alice/profile");
+        assert_eq!(tx_changes[0].value, None);
+        assert_eq!(tx_changes[0].version, 2);
+        assert_eq!(tx_changes[1].key, "users/alice/settings");
+
+        let limited = store
+            .list_changes("", Some(1), 1)
+            .await
+            .expect("list limited");
+        assert_eq!(limited.events.len(), 1);
+        assert_eq!(limited.next_revision, 2);
+
+        let caught_up = store
+            .list_changes("users", None, 100)
+            .await
+            .expect("list from head");
+        assert!(caught_up.events.is_empty());
+        assert_eq!(caught_up.next_revision, 4);
+    }
+
+    #[tokio::test]
+    async fn list_changes_reports_compaction() {
+        let store = setup_store();
+        for i in 0..SledStore::MAX_CHANGE_LOG_ENTRIES + 2 {
+            store
+                .set("counter".to_string(), i.to_string(), None)
+                .await
+                .expect("set counter");
+        }
+
+        let stale = store
+            .list_changes("counter", Some(1), 100)
+            .await
+            .expect("list stale");
+        assert!(stale.compacted);
+        assert!(stale.events.is_empty());
+        assert_eq!(stale.next_revision, SledStore::MAX_CHANGE_LOG_ENTRIES + 3);
+
+        let ahead = store
+            .list_changes("counter", Some(stale.next_revision + 10), 100)
+            .await
+            .expect("list ahead");
+        assert!(ahead.compacted);
+
+        let recent = store
+            .list_changes("counter", Some(3), 1)
+            .await
+            .expect("list recent");
+        assert!(!recent.compacted);
+        assert_eq!(recent.events[0].revision, 3);
+    }
+
+    #[tokio::test]
+    async fn history_records_writes_and_prunes() {
+        let store = setup_store();
+
+        store
+            .create("boot/config", "v1", Some("control-panel@root"))
+            .await
+            .expect("create");
+        store
+            .set("boot/config".to_string(), "v2".to_string(), None)
+            .await
+            .expect("set");
+        let mut tx = HashMap::new();
+        tx.insert("boot/config".to_string(), KVAction::Remove);
+        store
+            .exec_tx(tx, None, Some("buckycli@root"))
+            .await
+            .expect("exec tx");
+        store
+            .create("boot/config#x", "other", None)
+            .await
+            .expect("create lookalike key");
+
+        let history = store
+            .list_history("boot/config", 10)
+            .await
+            .expect("list history");
+        let revisions: Vec<u64> = history.iter().map(|entry| entry.revision).collect();
+        assert_eq!(revisions, vec![3, 2, 1]);
+        assert_eq!(history[0].value, None);
+        assert_eq!(history[0].writer.as_deref(), Some("buckycli@root"));
+        assert_eq!(history[1].value.as_deref(), Some("v2"));
+        assert_eq!(history[1].writer, None);
+        assert_eq!(history[2].writer.as_deref(), Some("control-panel@root"));
+
+        let removed = store.prune_history("boot", 1).await.expect("prune");
+        assert_eq!(removed, 2);
+        let history = store
+            .list_history("boot/config", 10)
+            .await
+            .expect("list pruned history");
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].revision, 3);
+        assert_eq!(
+            store
+                .list_history("boot/config#x", 10)
+                .await
+                .expect("list lookalike")
+                .len(),
+            1
+        );
+
+        store.set_history_limit(2).await.expect("set limit");
+        for value in ["a", "b", "c"] {
+            store
+                .set("boot/config".to_string(), value.to_string(), None)
+                .await
+                .expect("set");
+        }
+        let history = store
+            .list_history("boot/config", 10)
+            .await
+            .expect("list limited history");
+        let revisions: Vec<u64> = history.iter().map(|entry| entry.revision).collect();
+        assert_eq!(revisions, vec![6, 5]);
+    }
+
+    #[tokio::test]
+    async fn lowering_history_limit_trims_all_older_entries() {
+        let store = setup_store();
+        for value in ["a", "b", "c", "d", "e"] {
+            store
+                .set("boot/config".to_string(), value.to_string(), None)
+                .await
+                .expect("set");
+        }
+        store
+            .create("boot/config#x", "other", None)
+            .await
+            .expect("create lookalike key");
+
+        store.set_history_limit(2).await.expect("set limit");
+        store
+            .set("boot/config".to_string(), "f".to_string(), None)
+            .await
+            .expect("set");
+        let history = store
+            .list_history("boot/config", 10)
+            .await
+            .expect("list history");
+        let revisions: Vec<u64> = history.iter().map(|entry| entry.revision).collect();
+        assert_eq!(revisions, vec![6, 5]);
+        assert_eq!(
+            store
+                .list_history("boot/config#x", 10)
+                .await
+                .expect("list lookalike")
+                .len(),
+            1
+        );
+    }
 }
@@ -0,0 +1,352 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+
+use chrono::{Local, TimeZone};
+use clap::{value_parser, Arg, ArgAction, Command};
+use serde_json::Value;
+
+const DEFAULT_SLOG_SERVER_URL: &str = "http://127.0.0.1:22001";
+const TAIL_RECONNECT_DELAY_SECS: u64 = 3;
+
+pub fn build_log_command() -> Command {
+    Command::new("log")
+        .about("query and follow logs collected by slog_server")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("tail")
+                .about("follow newly uploaded log records from any set of nodes/services")
+                .arg(
+                    Arg::new("server")
+                        .long("server")
+                        .default_value(DEFAULT_SLOG_SERVER_URL)
+                        .help("slog_server base url"),
+                )
+                .arg(
+                    Arg::new("node")
+                        .long("node")
+                        .action(ArgAction::Append)
+                        .help("only records from this node, repeatable"),
+                )
+                .arg(
+                    Arg::new("service")
+                        .long("service")
+                        .action(ArgAction::Append)
+                        .help("only records from this service, repeatable"),
+                )
+                .arg(
+                    Arg::new("level")
+                        .long("level")
+                        .action(ArgAction::Append)
+                        .help(
+                            "only records at this level (error/warn/info/debug/trace), repeatable",
+                        ),
+                )
+                .arg(
+                    Arg::new("keyword")
+                        .long("keyword")
+                        .help("keyword the content must contain"),
+                )
+                .arg(
+                    Arg::new("regex")
+                        .long("regex")
+                        .help("regex the content must match"),
+                )
+                .arg(
+                    Arg::new("trace_id")
+                        .long("trace_id")
+                        .help("only records of this trace"),
+                )
+                .arg(
+                    Arg::new("since_seq")
+                        .long("since_seq")
+                        .value_parser(value_parser!(u64))
+                        .help("replay buffered records after this stream sequence first"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("print every record as one JSON line"),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("export stored log records as NDJSON or OpenTelemetry OTLP/JSON lines")
+                .arg(
+                    Arg::new("server")
+                        .long("server")
+                        .default_value(DEFAULT_SLOG_SERVER_URL)
+                        .help("slog_server base url"),
+                )
+                .arg(
+                    Arg::new("node")
+                        .long("node")
+                        .help("only records from this node"),
+                )
+                .arg(
+                    Arg::new("service")
+                        .long("service")
+                        .help("only records from this service"),
+                )
+                .arg(
+                    Arg::new("level")
+                        .long("level")
+                        .help("only records at this level (error/warn/info/debug/trace)"),
+                )
+                .arg(
+                    Arg::new("start_time")
+                        .long("start_time")
+                        .value_parser(value_parser!(u64))
+                        .help("oldest record time, unix millis"),
+                )
+                .arg(
+                    Arg::new("end_time")
+                        .long("end_time")
+                        .value_parser(value_parser!(u64))
+                        .help("newest record time, unix millis"),
+                )
+                .arg(
+                    Arg::new("keyword")
+                        .long("keyword")
+                        .help("keyword the content must contain"),
+                )
+                .arg(
+                    Arg::new("regex")
+                        .long("regex")
+                        .help("regex the content must match"),
+                )
+                .arg(
+                    Arg::new("trace_id")
+                        .long("trace_id")
+                        .help("only records of this trace"),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .value_parser(value_parser!(u64))
+                        .help("export at most this many records, newest first"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["ndjson", "otlp"])
+                        .default_value("ndjson")
+                        .help("ndjson: one record per line, otlp: OTLP/JSON log requests per line"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .help("write the export to this file instead of stdout"),
+                ),
+        )
+}
+
+pub async fn handle_log_command(matches: &clap::ArgMatches) -> Result<(), String> {
+    match matches.subcommand() {
+        Some(("tail", sub)) => handle_tail(sub).await,
+        Some(("export", sub)) => handle_export(sub).await,
+        _ => Err("unknown log subcommand".to_string()),
+    }
+}
+
+fn joined_values(matches: &clap::ArgMatches, id: &str) -> Option<String> {
+    let values: Vec<String> = matches.get_many::<String>(id)?.cloned().collect();
+    Some(values.join(","))
+}
+
+async fn handle_tail(matches: &clap::ArgMatches) -> Result<(), String> {
+    let server = matches.get_one::<String>("server").unwrap();
+    let url = format!("{}/tail", server.trim_end_matches('/'));
+    let want_json = matches.get_flag("json");
+
+    let mut query: Vec<(&str, String)> = Vec::new();
+    for (key, id) in [
+        ("nodes", "node"),
+        ("services", "service"),
+        ("levels", "level"),
+    ] {
+        if let Some(value) = joined_values(matches, id) {
+            query.push((key, value));
+        }
+    }
+    for key in ["keyword", "regex", "trace_id"] {
+        if let Some(value) = matches.get_one::<String>(key) {
+            query.push((key, value.clone()));
+        }
+    }
+
+    let client = reqwest::Client::new();
+    let mut last_seq = matches.get_one::<u64>("since_seq").copied();
+    loop {
+        let mut request = client.get(&url).query(&query);
+        // Resume after a dropped connection without repeating printed records.
+        if let Some(seq) = last_seq {
+            request = request.header("Last-Event-ID", seq.to_string());
+        }
+        let mut response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("connect to {} failed: {}, retrying", url, e);
+                tokio::time::sleep(Duration::from_secs(TAIL_RECONNECT_DELAY_SECS)).await;
+                continue;
+            }
+        };
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("slog_server tail failed ({}): {}", status, body));
+        }
+        if last_seq.is_none() {
+            last_seq = response
+                .headers()
+                .get("x-log-stream-seq")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok());
+        }
+
+        let mut pending: Vec<u8> = Vec::new();
+        loop {
+            let chunk = match response.chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => {
+                    eprintln!("log stream closed by server, reconnecting");
+                    break;
+                }
+                Err(e) => {
+                    eprintln!("log stream interrupted: {}, reconnecting", e);
+                    break;
+                }
+            };
+            pending.extend_from_slice(&chunk);
+            while let Some(end) = pending.windows(2).position(|window| window == b"\n\n") {
+                let event_bytes: Vec<u8> = pending.drain(..end + 2).collect();
+                if let Some(seq) =
+                    print_sse_event(&String::from_utf8_lossy(&event_bytes), want_json)
+                {
+                    last_seq = Some(seq);
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(TAIL_RECONNECT_DELAY_SECS)).await;
+    }
+}
+
+async fn handle_export(matches: &clap::ArgMatches) -> Result<(), String> {
+    let server = matches.get_one::<String>("server").unwrap();
+    let url = format!("{}/export", server.trim_end_matches('/'));
+
+    let mut query: Vec<(&str, String)> = Vec::new();
+    for key in [
+        "node", "service", "level", "keyword", "regex", "trace_id", "format",
+    ] {
+        if let Some(value) = matches.get_one::<String>(key) {
+            query.push((key, value.clone()));
+        }
+    }
+    for key in ["start_time", "end_time", "limit"] {
+        if let Some(value) = matches.get_one::<u64>(key) {
+            query.push((key, value.to_string()));
+        }
+    }
+
+    let mut response = reqwest::Client::new()
+        .get(&url)
+        .query(&query)
+        .send()
+        .await
+        .map_err(|e| format!("connect to {} failed: {}", url, e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("slog_server export failed ({}): {}", status, body));
+    }
+
+    let output = matches.get_one::<String>("output");
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| format!("create {} failed: {}", path, e))?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let mut lines = 0usize;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("export stream interrupted after {} lines: {}", lines, e))?
+    {
+        lines += chunk.iter().filter(|b| **b == b'\n').count();
+        writer
+            .write_all(&chunk)
+            .map_err(|e| format!("write export failed: {}", e))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("write export failed: {}", e))?;
+
+    if let Some(path) = output {
+        eprintln!("exported {} lines to {}", lines, path);
+    }
+    Ok(())
+}
+
+/// Print one SSE event and return its stream sequence for `log` events.
+fn print_sse_event(event: &str, want_json: bool) -> Option<u64> {
+    let mut event_type = "message";
+    let mut data = String::new();
+    for line in event.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            event_type = value.trim();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push_str(value.trim_start());
+        }
+    }
+    let payload: Value = serde_json::from_str(&data).ok()?;
+    match event_type {
+        "log" => {
+            if want_json {
+                println!("{}", payload);
+            } else {
+                println!("{}", format_record(&payload));
+            }
+            payload.get("seq").and_then(|value| value.as_u64())
+        }
+        "lagged" => {
+            let missed = payload
+                .get("missed")
+                .and_then(|value| value.as_u64())
+                .unwrap_or(0);
+            eprintln!(
+                "-- {} records were dropped before they could be delivered --",
+                missed
+            );
+            None
+        }
+        _ => None,
+    }
+}
+
+fn format_record(record: &Value) -> String {
+    let text = |value: Option<&Value>| value.and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let log = record.get("log").cloned().unwrap_or(Value::Null);
+    let time = log
+        .get("time")
+        .and_then(|value| value.as_i64())
+        .and_then(|value| Local.timestamp_millis_opt(value).single())
+        .map(|value| value.format("%m-%d %H:%M:%S%.3f").to_string())
+        .unwrap_or_default();
+    let mut line = format!(
+        "{} {}/{} [{}] {}",
+        time,
+        text(record.get("node")),
+        text(record.get("service")),
+        text(log.get("level")).to_uppercase(),
+        text(log.get("content")),
+    );
+    if let Some(trace_id) = log.get("trace_id").and_then(|value| value.as_str()) {
+        line.push_str(&format!(" trace_id={}", trace_id));
+    }
+    line
+}
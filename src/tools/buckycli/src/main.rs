@@ -1,6 +1,7 @@
 mod app;
 mod did;
 mod loader;
+mod log_cmd;
 mod ndn;
 mod node_cmd;
 #[allow(unused_mut, dead_code, unused_variables)]
@@ -10,7 +11,7 @@ mod sys_config;
 use buckyos_api::test_config;
 use buckyos_api::*;
 use buckyos_kit::get_version;
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 use loader::*;
 use log::info;
 use package_cmd::*;
@@ -53,6 +54,7 @@ async fn main() -> Result<(), String> {
         )
         .subcommand(Command::new("version").about("buckycli version"))
         .subcommand(node_cmd::build_node_command())
+        .subcommand(log_cmd::build_log_command())
         .subcommand(
             Command::new("pub_pkg")
                 .about("publish packed raw package to local repo")
@@ -216,6 +218,51 @@ async fn main() -> Result<(), String> {
                         .help("append system config,
     buckycli sys_config --append $key $value")
                 )
+                .arg(
+                    Arg::new("backup")
+                        .long("backup")
+                        .value_name("file")
+                        .help("export all system config into an archive signed by the zone owner key.
+    Keys under --seal prefixes are encrypted when $BUCKYOS_CONFIG_ARCHIVE_PASSPHRASE is set.
+    buckycli sys_config --backup $file [--seal $prefix]...")
+                )
+                .arg(
+                    Arg::new("seal")
+                        .long("seal")
+                        .value_name("prefix")
+                        .action(ArgAction::Append)
+                        .requires("backup")
+                        .help("key prefix to encrypt in the backup archive (default: system/verify-hub/, users/)")
+                )
+                .arg(
+                    Arg::new("restore")
+                        .long("restore")
+                        .value_name("file")
+                        .help("restore system config from a backup archive in one transaction,
+    buckycli sys_config --restore $file [--replace] [--dry_run] [--offline]")
+                )
+                .arg(
+                    Arg::new("replace")
+                        .long("replace")
+                        .action(ArgAction::SetTrue)
+                        .requires("restore")
+                        .help("also remove keys that are not in the archive")
+                )
+                .arg(
+                    Arg::new("dry_run")
+                        .long("dry_run")
+                        .action(ArgAction::SetTrue)
+                        .requires("restore")
+                        .help("only print which keys the restore would add, change or remove")
+                )
+                .arg(
+                    Arg::new("offline")
+                        .long("offline")
+                        .action(ArgAction::SetTrue)
+                        .requires("restore")
+                        .help("verify the archive against the owner key in node_identity.json and stage it,
+    system_config applies it on its next start (for a stopped service or a lost store)")
+                )
         )
         .subcommand(
             Command::new("did")
@@ -485,6 +532,24 @@ oods look like this 'ood1,ood2'.")
         }
     }
 
+    // `log` 子命令直接访问 slog_server 的 HTTP 接口，同样不需要 BuckyOS runtime。
+    if let Some(("log", log_matches)) = subcommand {
+        return log_cmd::handle_log_command(log_matches).await;
+    }
+
+    // 离线 restore 针对 system_config 已停止或数据丢失的节点，无法也无需登录。
+    if let Some(("sys_config", sys_config_matches)) = subcommand {
+        if sys_config_matches.get_flag("offline") {
+            if let Some(file_path) = sys_config_matches.get_one::<String>("restore") {
+                return sys_config::restore_config_offline(
+                    file_path,
+                    sys_config_matches.get_flag("replace"),
+                    sys_config_matches.get_flag("dry_run"),
+                );
+            }
+        }
+    }
+
     let mut runtime = init_buckyos_api_runtime("buckycli", None, BuckyOSRuntimeType::AppClient)
         .await
         .map_err(|e| {
@@ -740,6 +805,21 @@ oods look like this 'ood1,ood2'.")
                 sys_config::set_config(key, &content).await;
                 return Ok(());
             }
+            if let Some(file_path) = matches.get_one::<String>("backup") {
+                let seal_prefixes = matches
+                    .get_many::<String>("seal")
+                    .map(|prefixes| prefixes.cloned().collect())
+                    .unwrap_or_default();
+                return sys_config::backup_config(file_path, seal_prefixes).await;
+            }
+            if let Some(file_path) = matches.get_one::<String>("restore") {
+                return sys_config::restore_config(
+                    file_path,
+                    matches.get_flag("replace"),
+                    matches.get_flag("dry_run"),
+                )
+                .await;
+            }
         }
         Some(("connect", _matches)) => {
             sys_config::connect_into().await;
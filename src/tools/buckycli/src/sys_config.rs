@@ -1,4 +1,5 @@
 use buckyos_api::*;
+use buckyos_kit::get_buckyos_system_etc_dir;
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
 
@@ -49,6 +50,177 @@ pub async fn list_config(key: &str) {
     }
 }
 
+// Passphrase for sealing secret-bearing prefixes, kept out of argv and shell history.
+const ARCHIVE_PASSPHRASE_ENV: &str = "BUCKYOS_CONFIG_ARCHIVE_PASSPHRASE";
+
+fn archive_passphrase() -> Option<String> {
+    std::env::var(ARCHIVE_PASSPHRASE_ENV)
+        .ok()
+        .filter(|passphrase| !passphrase.is_empty())
+}
+
+pub async fn backup_config(file_path: &str, seal_prefixes: Vec<String>) -> Result<(), String> {
+    let api_runtime = get_buckyos_api_runtime().map_err(|err| err.to_string())?;
+    let owner_key = api_runtime.user_private_key.as_ref().ok_or_else(|| {
+        "backup must be signed with the zone owner key, user_private_key.pem not found".to_string()
+    })?;
+    let syc_cfg_client = api_runtime
+        .get_system_config_client()
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut archive = syc_cfg_client
+        .backup()
+        .await
+        .map_err(|err| format!("config backup error: {}", err))?;
+    let key_count = archive.entries.len();
+
+    match archive_passphrase() {
+        Some(passphrase) => {
+            let seal_prefixes = if seal_prefixes.is_empty() {
+                DEFAULT_SEALED_PREFIXES
+                    .iter()
+                    .map(|prefix| prefix.to_string())
+                    .collect()
+            } else {
+                seal_prefixes
+            };
+            archive
+                .seal(&seal_prefixes, &passphrase)
+                .map_err(|err| format!("seal archive error: {}", err))?;
+            println!("sealed prefixes: {}", seal_prefixes.join(", "));
+        }
+        None if !seal_prefixes.is_empty() => {
+            return Err(format!(
+                "--seal needs a passphrase in ${}",
+                ARCHIVE_PASSPHRASE_ENV
+            ));
+        }
+        None => println!(
+            "warning: archive is not sealed, set ${} to encrypt secret-bearing keys",
+            ARCHIVE_PASSPHRASE_ENV
+        ),
+    }
+
+    let jwt = archive
+        .to_jwt(owner_key, api_runtime.user_id.clone())
+        .map_err(|err| err.to_string())?;
+    std::fs::write(file_path, jwt)
+        .map_err(|err| format!("write archive {} error: {}", file_path, err))?;
+    println!(
+        "backup {} keys at revision {} to {}",
+        key_count, archive.revision, file_path
+    );
+    Ok(())
+}
+
+// Read an archive file and unseal it locally to check the passphrase; returns
+// the signed JWT, the plaintext archive and, for a sealed archive, the key the
+// service unseals it with.
+fn read_archive_file(
+    file_path: &str,
+) -> Result<(String, SystemConfigArchive, Option<String>), String> {
+    let jwt = std::fs::read_to_string(file_path)
+        .map_err(|err| format!("read archive {} error: {}", file_path, err))?;
+    let jwt = jwt.trim().to_string();
+
+    let mut archive =
+        SystemConfigArchive::from_jwt_unverified(&jwt).map_err(|err| err.to_string())?;
+    archive.verify().map_err(|err| err.to_string())?;
+    let mut seal_key = None;
+    if archive.sealed.is_some() {
+        let passphrase = archive_passphrase().ok_or_else(|| {
+            format!(
+                "archive is sealed, set ${} to its passphrase",
+                ARCHIVE_PASSPHRASE_ENV
+            )
+        })?;
+        seal_key = archive
+            .unseal_key(&passphrase)
+            .map_err(|err| format!("unseal archive error: {}", err))?;
+        if let Some(seal_key) = seal_key.as_deref() {
+            archive
+                .unseal_with_key(seal_key)
+                .map_err(|err| format!("unseal archive error: {}", err))?;
+        }
+    }
+    Ok((jwt, archive, seal_key))
+}
+
+pub async fn restore_config(file_path: &str, replace: bool, dry_run: bool) -> Result<(), String> {
+    // The service checks the owner signature and unseals with the seal key.
+    let (jwt, archive, seal_key) = read_archive_file(file_path)?;
+
+    let api_runtime = get_buckyos_api_runtime().map_err(|err| err.to_string())?;
+    let syc_cfg_client = api_runtime
+        .get_system_config_client()
+        .await
+        .map_err(|err| err.to_string())?;
+    let diff = syc_cfg_client
+        .restore(&jwt, seal_key.as_deref(), replace, dry_run)
+        .await
+        .map_err(|err| format!("config restore error: {}", err))?;
+
+    diff.added.iter().for_each(|key| println!("+ {}", key));
+    diff.changed.iter().for_each(|key| println!("~ {}", key));
+    diff.removed.iter().for_each(|key| println!("- {}", key));
+    diff.schemas
+        .iter()
+        .for_each(|pattern| println!("schema {}", pattern));
+    println!(
+        "{}: {} added, {} changed, {} removed, {} unchanged, {} schemas (archive revision {})",
+        if diff.applied { "restored" } else { "dry run" },
+        diff.added.len(),
+        diff.changed.len(),
+        diff.removed.len(),
+        diff.unchanged,
+        diff.schemas.len(),
+        archive.revision
+    );
+    Ok(())
+}
+
+// For a node whose system_config service is down or whose store is lost: the
+// archive is checked against the owner key in node_identity.json and staged,
+// still sealed, in the etc dir with its seal key; system_config applies it on
+// its next start and deletes the staged file.
+pub fn restore_config_offline(file_path: &str, replace: bool, dry_run: bool) -> Result<(), String> {
+    let (jwt, archive, seal_key) = read_archive_file(file_path)?;
+    let etc_dir = get_buckyos_system_etc_dir();
+    let owner_key = load_local_owner_public_key(&etc_dir)?;
+    SystemConfigArchive::from_jwt(&jwt, &owner_key)
+        .map_err(|err| format!("archive is not signed by the zone owner: {}", err))?;
+
+    archive.entries.keys().for_each(|key| println!("  {}", key));
+    archive
+        .schemas
+        .keys()
+        .for_each(|pattern| println!("  schema {}", pattern));
+    if dry_run {
+        println!(
+            "dry run: {} keys (archive revision {}) signed by the zone owner",
+            archive.entries.len(),
+            archive.revision
+        );
+        return Ok(());
+    }
+
+    let pending = SystemConfigPendingRestore {
+        archive: jwt,
+        seal_key,
+        replace,
+    };
+    let content = serde_json::to_vec_pretty(&pending).map_err(|err| err.to_string())?;
+    let pending_path = etc_dir.join(SYSTEM_CONFIG_PENDING_RESTORE_FILE);
+    atomic_write_private(&pending_path, &content)?;
+    println!(
+        "staged {} keys (archive revision {}) in {}, system_config applies them on its next start",
+        archive.entries.len(),
+        archive.revision,
+        pending_path.display()
+    );
+    Ok(())
+}
+
 pub async fn connect_into() {
     let api_runtime = get_buckyos_api_runtime().unwrap();
     let syc_cfg_client = api_runtime.get_system_config_client().await.unwrap();
@@ -163,12 +163,16 @@ async function cleanupQueue(
 
 async function openKeventStream(
   gatewayBaseUrl: string,
+  sessionToken: string,
   pattern: string,
 ): Promise<StreamReader> {
   const controller = new AbortController();
   const response = await fetch(`${gatewayBaseUrl}/kapi/kevent/stream`, {
     method: "POST",
-    headers: { "content-type": "application/json" },
+    headers: {
+      "content-type": "application/json",
+      authorization: `Bearer ${sessionToken}`,
+    },
     body: JSON.stringify({
       patterns: [pattern],
       keepalive_ms: 1000,
@@ -225,12 +229,16 @@ async function openKeventStream(
 
 async function publishKevent(
   gatewayBaseUrl: string,
+  sessionToken: string,
   eventid: string,
   data: JsonObject,
 ): Promise<void> {
   const response = await fetch(`${gatewayBaseUrl}/kapi/kevent/publish`, {
     method: "POST",
-    headers: { "content-type": "application/json" },
+    headers: {
+      "content-type": "application/json",
+      authorization: `Bearer ${sessionToken}`,
+    },
     body: JSON.stringify({ eventid, data }),
   });
   const body = await response.text();
@@ -258,7 +266,8 @@ async function probeKmsgGet(gatewayBaseUrl: string): Promise<void> {
 
 async function run(): Promise<void> {
   await prepareAppClientConfig();
-  const { buckyos, userId, ownerUserId, zoneHost } = await initTestRuntime();
+  const { buckyos, userId, ownerUserId, zoneHost, sessionToken } =
+    await initTestRuntime();
   const appId = getEnv("BUCKYOS_TEST_APP_ID", "buckycli")!;
   const gatewayBaseUrl = getEnv(
     "BUCKYOS_GATEWAY_BASE_URL",
@@ -337,7 +346,7 @@ async function run(): Promise<void> {
     );
 
     const eventid = `/kevent_kmsg/dv/${tag}`;
-    stream = await openKeventStream(gatewayBaseUrl, eventid);
+    stream = await openKeventStream(gatewayBaseUrl, sessionToken, eventid);
     const ack = await stream.readFrame(5000);
     assert.equal(ack.type, "ack", "kevent stream should ack");
 
@@ -351,7 +360,7 @@ async function run(): Promise<void> {
         headers: { test_case: "DV-05" },
       },
     });
-    await publishKevent(gatewayBaseUrl, eventid, {
+    await publishKevent(gatewayBaseUrl, sessionToken, eventid, {
       queue_urn: queueUrn,
       index: signalIndex,
       tag,
@@ -404,7 +413,7 @@ async function run(): Promise<void> {
     assert.equal(signalFetched[0].index, signalIndex);
     await kmsg.call("commit_ack", { sub_id: subId, index: signalIndex });
 
-    await publishKevent(gatewayBaseUrl, eventid, {
+    await publishKevent(gatewayBaseUrl, sessionToken, eventid, {
       queue_urn: queueUrn,
       index: signalIndex,
       tag,
@@ -155,12 +155,16 @@ async function cleanupQueue(rpc: RpcClient, queueUrn: string | null) {
 
 async function openKeventStream(
   gatewayBaseUrl: string,
+  sessionToken: string,
   pattern: string,
 ): Promise<StreamReader> {
   const controller = new AbortController();
   const response = await fetch(`${gatewayBaseUrl}/kapi/kevent/stream`, {
     method: "POST",
-    headers: { "content-type": "application/json" },
+    headers: {
+      "content-type": "application/json",
+      authorization: `Bearer ${sessionToken}`,
+    },
     body: JSON.stringify({ patterns: [pattern], keepalive_ms: 1000 }),
     signal: controller.signal,
   });
@@ -214,12 +218,16 @@ async function openKeventStream(
 
 async function publishKevent(
   gatewayBaseUrl: string,
+  sessionToken: string,
   eventid: string,
   data: JsonObject,
 ): Promise<void> {
   const response = await fetch(`${gatewayBaseUrl}/kapi/kevent/publish`, {
     method: "POST",
-    headers: { "content-type": "application/json" },
+    headers: {
+      "content-type": "application/json",
+      authorization: `Bearer ${sessionToken}`,
+    },
     body: JSON.stringify({ eventid, data }),
   });
   const body = await response.text();
@@ -301,7 +309,8 @@ async function observeOldStream(stream: StreamReader): Promise<string> {
 
 async function run(): Promise<void> {
   await prepareAppClientConfig();
-  const { buckyos, userId, ownerUserId, zoneHost } = await initTestRuntime();
+  const { buckyos, userId, ownerUserId, zoneHost, sessionToken } =
+    await initTestRuntime();
   const appId = getEnv("BUCKYOS_TEST_APP_ID", "buckycli")!;
   const gatewayBaseUrl = getEnv(
     "BUCKYOS_GATEWAY_BASE_URL",
@@ -354,7 +363,7 @@ async function run(): Promise<void> {
     await kmsg.call("commit_ack", { sub_id: subId, index: firstIndex });
 
     const eventid = `/kevent_kmsg/restart/${tag}`;
-    oldStream = await openKeventStream(gatewayBaseUrl, eventid);
+    oldStream = await openKeventStream(gatewayBaseUrl, sessionToken, eventid);
     const ack = await oldStream.readFrame(5000);
     assert.equal(ack.type, "ack", "kevent stream should ack before restart");
 
@@ -406,7 +415,7 @@ async function run(): Promise<void> {
       });
     }
 
-    newStream = await openKeventStream(gatewayBaseUrl, eventid);
+    newStream = await openKeventStream(gatewayBaseUrl, sessionToken, eventid);
     const newAck = await newStream.readFrame(5000);
     assert.equal(newAck.type, "ack", "kevent stream should reconnect");
 
@@ -441,7 +450,7 @@ async function run(): Promise<void> {
       index: fallbackIndex,
     });
 
-    await publishKevent(gatewayBaseUrl, eventid, {
+    await publishKevent(gatewayBaseUrl, sessionToken, eventid, {
       queue_urn: queueUrn,
       index: fallbackIndex,
       tag,
@@ -108,12 +108,16 @@ async function rpcCall<T>(
 
 async function openKeventStream(
   gatewayBaseUrl: string,
+  sessionToken: string,
   pattern: string,
 ): Promise<StreamReader> {
   const controller = new AbortController();
   const response = await fetch(`${gatewayBaseUrl}/kapi/kevent/stream`, {
     method: "POST",
-    headers: { "content-type": "application/json" },
+    headers: {
+      "content-type": "application/json",
+      authorization: `Bearer ${sessionToken}`,
+    },
     body: JSON.stringify({
       patterns: [pattern],
       keepalive_ms: 1000,
@@ -240,7 +244,7 @@ async function waitForTaskChangedEvent(
 
 async function run(): Promise<void> {
   await prepareAppClientConfig();
-  const { buckyos, zoneHost } = await initTestRuntime();
+  const { buckyos, zoneHost, sessionToken } = await initTestRuntime();
   const gatewayBaseUrl = getEnv(
     "BUCKYOS_GATEWAY_BASE_URL",
     `https://${zoneHost}`,
@@ -281,7 +285,7 @@ async function run(): Promise<void> {
     // 2. Subscribe to the per-task channel, then drive a status change and
     //    expect the task-changed event.
     const eventId = `/task_mgr/${watched.id}`;
-    stream = await openKeventStream(gatewayBaseUrl, eventId);
+    stream = await openKeventStream(gatewayBaseUrl, sessionToken, eventId);
     const ack = await stream.readFrame(5000);
     assert.equal(ack.type, "ack", "kevent stream should ack");
 
@@ -75,6 +75,8 @@ export interface TestRuntime {
   ownerUserId: string;
   /** zone 主机名 */
   zoneHost: string;
+  /** 登录得到的 session token，直接访问 HTTP 接口时作为 Bearer token */
+  sessionToken: string;
   /** 解析后的 session token claims */
   sessionTokenClaims: Record<string, unknown> | null;
 }
@@ -133,6 +135,13 @@ export async function initTestRuntime(): Promise<TestRuntime> {
   const ownerUserId =
     buckyos.getBuckyOSConfig()?.ownerUserId ?? userId;
 
-  cached = { buckyos, userId, ownerUserId, zoneHost, sessionTokenClaims };
+  cached = {
+    buckyos,
+    userId,
+    ownerUserId,
+    zoneHost,
+    sessionToken: accountInfo.session_token,
+    sessionTokenClaims,
+  };
   return cached;
 }